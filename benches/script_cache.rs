@@ -0,0 +1,114 @@
+//! Script cache hit rate when a block's transactions are verified twice
+//!
+//! Mirrors relay followed by block connection: the first pass verifies every
+//! input, the second is answered from the cache.
+
+use anya_core::bitcoin::script_cache::ScriptCache;
+use anya_core::bitcoin::wallet::signer::{finalize_psbt, sign_psbt, SighashType};
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::Hash;
+use bitcoin::psbt::Psbt;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey, VerifyOnly};
+use bitcoin::sighash::SighashCache;
+use bitcoin::transaction::Version;
+use bitcoin::{
+    ecdsa, Amount, CompressedPublicKey, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut,
+    Txid, Witness,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+const BLOCK_TRANSACTIONS: usize = 500;
+
+/// Signed P2WPKH spends with the outputs they spend
+fn block() -> Vec<(Transaction, TxOut)> {
+    let secp = Secp256k1::new();
+    (0..BLOCK_TRANSACTIONS)
+        .map(|i| {
+            let key = SecretKey::from_slice(&[(i % 250) as u8 + 1; 32]).unwrap();
+            let spent = TxOut {
+                value: Amount::from_sat(10_000 + i as u64),
+                script_pubkey: ScriptBuf::new_p2wpkh(
+                    &CompressedPublicKey(key.public_key(&secp)).wpubkey_hash(),
+                ),
+            };
+            let tx = Transaction {
+                version: Version::TWO,
+                lock_time: LockTime::ZERO,
+                input: vec![TxIn {
+                    previous_output: OutPoint::new(Txid::all_zeros(), i as u32),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                }],
+                output: vec![TxOut {
+                    value: Amount::from_sat(9_000),
+                    script_pubkey: spent.script_pubkey.clone(),
+                }],
+            };
+            let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+            psbt.inputs[0].witness_utxo = Some(spent.clone());
+            sign_psbt(&secp, &mut psbt, &key, SighashType::All).unwrap();
+            finalize_psbt(&mut psbt);
+            (psbt.extract_tx().unwrap(), spent)
+        })
+        .collect()
+}
+
+/// Check the P2WPKH signature of input 0
+fn verify(secp: &Secp256k1<VerifyOnly>, tx: &Transaction, spent: &TxOut) -> Result<(), String> {
+    let witness = &tx.input[0].witness;
+    let signature = ecdsa::Signature::from_slice(&witness[0]).map_err(|e| e.to_string())?;
+    let key = bitcoin::PublicKey::from_slice(&witness[1]).map_err(|e| e.to_string())?;
+    let sighash = SighashCache::new(tx)
+        .p2wpkh_signature_hash(0, &spent.script_pubkey, spent.value, signature.sighash_type)
+        .map_err(|e| e.to_string())?;
+    secp.verify_ecdsa(
+        &Message::from_digest(sighash.to_byte_array()),
+        &signature.signature,
+        &key.inner,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Verify every input of `block` through `cache`
+fn replay(secp: &Secp256k1<VerifyOnly>, cache: &ScriptCache, block: &[(Transaction, TxOut)]) {
+    for (tx, spent) in block {
+        let key = cache.key(tx, 0, spent, 0);
+        cache.verify_with(key, || verify(secp, tx, spent)).unwrap();
+    }
+}
+
+fn benchmark_block_replay(c: &mut Criterion) {
+    let secp = Secp256k1::verification_only();
+    let block = block();
+
+    let cache = ScriptCache::new(BLOCK_TRANSACTIONS);
+    replay(&secp, &cache, &block);
+    replay(&secp, &cache, &block);
+    let stats = cache.stats();
+    println!(
+        "block replayed twice: {} hits, {} misses, hit rate {:.1}%",
+        stats.hits,
+        stats.misses,
+        stats.hit_rate() * 100.0
+    );
+
+    c.bench_function("script_cache_block_uncached", |b| {
+        b.iter(|| {
+            for (tx, spent) in &block {
+                verify(&secp, black_box(tx), spent).unwrap();
+            }
+        })
+    });
+    c.bench_function("script_cache_block_twice", |b| {
+        b.iter(|| {
+            let cache = ScriptCache::new(BLOCK_TRANSACTIONS);
+            replay(&secp, &cache, black_box(&block));
+            replay(&secp, &cache, black_box(&block));
+        })
+    });
+}
+
+criterion_group!(benches, benchmark_block_replay);
+criterion_main!(benches);
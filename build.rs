@@ -1,4 +1,6 @@
 use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 fn main() {
     // Detect OS and set appropriate feature flags
@@ -21,4 +23,56 @@ fn main() {
             println!("cargo:rustc-cfg=feature=\"secure-storage-fallback\"");
         }
     }
+
+    embed_build_info();
+}
+
+/// Expose the git commit, build time and compiler version to `build_info()`
+///
+/// `SOURCE_DATE_EPOCH` pins the build time for reproducible builds.
+fn embed_build_info() {
+    // Missing paths would force a rerun on every build of a source tarball
+    if std::path::Path::new(".git").exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        println!("cargo:rerun-if-changed=.git/index");
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let git_commit = command_output("git", &["rev-parse", "HEAD"])
+        .map(|commit| {
+            let dirty = command_output("git", &["status", "--porcelain", "--untracked-files=no"])
+                .is_some_and(|status| !status.is_empty());
+            if dirty {
+                format!("{commit}-dirty")
+            } else {
+                commit
+            }
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let build_time = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version =
+        command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=ANYA_GIT_COMMIT={git_commit}");
+    println!("cargo:rustc-env=ANYA_BUILD_TIMESTAMP={build_time}");
+    println!("cargo:rustc-env=ANYA_RUSTC_VERSION={rustc_version}");
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
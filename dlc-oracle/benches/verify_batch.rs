@@ -0,0 +1,53 @@
+//! Batch against sequential verification of 100 oracle attestations
+//!
+//! Half the attestations are standalone values signed with ECDSA only, the
+//! other half event outcomes that also carry a BIP340 signature per digit.
+
+use bitcoin::secp256k1::SecretKey;
+use criterion::{criterion_group, criterion_main, Criterion};
+use dlc_oracle::{Attestation, EventDescriptor, Oracle};
+use std::hint::black_box;
+
+const ATTESTATIONS: usize = 100;
+
+fn attestations() -> Vec<Attestation> {
+    let oracle = Oracle::new(SecretKey::from_slice(&[0x42; 32]).unwrap());
+    let descriptor = EventDescriptor::DigitDecomposition {
+        base: 10,
+        is_signed: false,
+        unit: "usd".to_string(),
+        precision: 0,
+        nb_digits: 4,
+    };
+    (0..ATTESTATIONS)
+        .map(|i| {
+            if i % 2 == 0 {
+                oracle.attest(&i.to_string()).unwrap()
+            } else {
+                let event = oracle
+                    .create_event(&format!("price-{i}"), 0, descriptor.clone())
+                    .unwrap();
+                oracle.attest_outcome(&event, &i.to_string()).unwrap()
+            }
+        })
+        .collect()
+}
+
+fn bench_verify(c: &mut Criterion) {
+    let attestations = attestations();
+    let mut group = c.benchmark_group("verify_100_attestations");
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            for attestation in &attestations {
+                black_box(Oracle::verify(black_box(attestation), None).unwrap());
+            }
+        })
+    });
+    group.bench_function("batch", |b| {
+        b.iter(|| black_box(Oracle::verify_batch(black_box(&attestations), None)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_verify);
+criterion_main!(benches);
@@ -0,0 +1,808 @@
+// Oracle announcements and their wire formats
+//
+// An announcement commits an oracle to the nonces it will sign an event's
+// outcome with. DLC implementations disagree on how it is serialized, so each
+// encoding is an `AnnouncementFormat` in a `FormatRegistry`:
+//
+// - `dlcspecs-v0`: the TLV encoding, with the announcement (type 55332), the
+//   event (type 55330) and the event descriptor (enum 55302, digit
+//   decomposition 55306) each wrapped in a BigSize type-length record
+// - `dlcspecs-v1`: the sub-type encoding, with a u16 message type 55332 in
+//   front and the event descriptor prefixed by its BigSize sub-type (0 enum,
+//   1 digit decomposition) instead of TLV records
+//
+// Both sign the serialized event with BIP340 under the tagged hash
+// `DLC/oracle/announcement/v0`. Strings are BigSize length-prefixed UTF-8.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::schnorr::Signature;
+use bitcoin::secp256k1::{Message, Secp256k1, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{Error, Result};
+
+/// Name of the TLV announcement format
+pub const DLCSPECS_V0: &str = "dlcspecs-v0";
+
+/// Name of the sub-type announcement format
+pub const DLCSPECS_V1: &str = "dlcspecs-v1";
+
+/// Message type of an oracle announcement
+pub const ANNOUNCEMENT_TYPE: u16 = 55332;
+
+/// TLV type of an oracle event
+pub const EVENT_TLV_TYPE: u64 = 55330;
+
+/// TLV type of an enumerated outcome event descriptor
+pub const ENUM_DESCRIPTOR_TLV_TYPE: u64 = 55302;
+
+/// TLV type of a digit decomposition event descriptor
+pub const DIGIT_DESCRIPTOR_TLV_TYPE: u64 = 55306;
+
+/// Tag of the hash an announcement signature commits to
+const ANNOUNCEMENT_TAG: &str = "DLC/oracle/announcement/v0";
+
+/// Outcomes an event can take
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventDescriptor {
+    /// One of a fixed list of outcomes, attested with a single nonce
+    Enum {
+        /// Possible outcomes
+        outcomes: Vec<String>,
+    },
+    /// A number attested digit by digit, with one nonce per digit and
+    /// one for the sign
+    DigitDecomposition {
+        /// Base of the digits
+        base: u16,
+        /// Whether the number can be negative
+        is_signed: bool,
+        /// Unit of the number
+        unit: String,
+        /// Power of ten the number is scaled by
+        precision: i32,
+        /// Number of digits
+        nb_digits: u16,
+    },
+}
+
+impl EventDescriptor {
+    /// Number of nonces needed to attest the outcome
+    pub fn nonce_count(&self) -> usize {
+        match self {
+            EventDescriptor::Enum { .. } => 1,
+            EventDescriptor::DigitDecomposition {
+                is_signed,
+                nb_digits,
+                ..
+            } => usize::from(*nb_digits) + usize::from(*is_signed),
+        }
+    }
+}
+
+/// Event an oracle commits to attest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleEvent {
+    /// Nonces the outcome will be signed with
+    pub oracle_nonces: Vec<XOnlyPublicKey>,
+    /// Earliest time of the attestation, in seconds since the epoch
+    pub event_maturity_epoch: u32,
+    /// Possible outcomes
+    pub event_descriptor: EventDescriptor,
+    /// Identifier of the event
+    pub event_id: String,
+}
+
+/// Signed commitment of an oracle to an event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleAnnouncement {
+    /// Signature of the oracle over the serialized event
+    pub announcement_signature: Signature,
+    /// Public key of the oracle
+    pub oracle_public_key: XOnlyPublicKey,
+    /// Announced event
+    pub oracle_event: OracleEvent,
+    /// Name of the format the announcement is serialized in
+    pub format: String,
+}
+
+/// Serialization of oracle announcements
+pub trait AnnouncementFormat: Send + Sync {
+    /// Name the format is registered under
+    fn name(&self) -> &'static str;
+
+    /// Whether `bytes` look like an announcement in this format
+    fn matches(&self, bytes: &[u8]) -> bool;
+
+    /// Serialize an event, as covered by the announcement signature
+    fn encode_event(&self, event: &OracleEvent) -> Result<Vec<u8>>;
+
+    /// Serialize a complete announcement
+    fn encode(&self, announcement: &OracleAnnouncement) -> Result<Vec<u8>>;
+
+    /// Parse a complete announcement, without checking its signature
+    fn decode(&self, bytes: &[u8]) -> Result<OracleAnnouncement>;
+
+    /// Message the oracle signs to announce `event`
+    fn signature_message(&self, event: &OracleEvent) -> Result<Message> {
+        let tag = sha256::Hash::hash(ANNOUNCEMENT_TAG.as_bytes());
+        let mut engine = sha256::Hash::engine();
+        engine.input(tag.as_ref());
+        engine.input(tag.as_ref());
+        engine.input(&self.encode_event(event)?);
+        Ok(Message::from_digest(
+            sha256::Hash::from_engine(engine).to_byte_array(),
+        ))
+    }
+}
+
+/// Announcement formats an oracle can produce and parse
+#[derive(Clone)]
+pub struct FormatRegistry {
+    formats: Vec<Arc<dyn AnnouncementFormat>>,
+}
+
+impl Default for FormatRegistry {
+    /// Registry with the dlcspecs v0 and v1 formats
+    fn default() -> Self {
+        Self {
+            formats: vec![Arc::new(DlcSpecsV0), Arc::new(DlcSpecsV1)],
+        }
+    }
+}
+
+impl FormatRegistry {
+    /// Registry without any format
+    pub fn empty() -> Self {
+        Self {
+            formats: Vec::new(),
+        }
+    }
+
+    /// Add a format, failing if one with the same name is registered
+    pub fn register(&mut self, format: Arc<dyn AnnouncementFormat>) -> Result<()> {
+        if self.formats.iter().any(|f| f.name() == format.name()) {
+            return Err(Error::Format(format!(
+                "format {} is already registered",
+                format.name()
+            )));
+        }
+        self.formats.push(format);
+        Ok(())
+    }
+
+    /// Format registered under `name`
+    pub fn get(&self, name: &str) -> Result<&dyn AnnouncementFormat> {
+        self.formats
+            .iter()
+            .find(|format| format.name() == name)
+            .map(|format| format.as_ref())
+            .ok_or_else(|| Error::Format(format!("unknown announcement format {name}")))
+    }
+
+    /// Names of the registered formats, in detection order
+    pub fn names(&self) -> Vec<&'static str> {
+        self.formats.iter().map(|format| format.name()).collect()
+    }
+
+    /// Parse an announcement in any registered format and check its signature
+    pub fn parse(&self, bytes: &[u8]) -> Result<OracleAnnouncement> {
+        let format = self
+            .formats
+            .iter()
+            .find(|format| format.matches(bytes))
+            .ok_or_else(|| Error::Format("unrecognized announcement format".to_string()))?;
+        let announcement = format.decode(bytes)?;
+        self.verify(&announcement)?;
+        Ok(announcement)
+    }
+
+    /// Check the signature of an announcement in its format
+    pub fn verify(&self, announcement: &OracleAnnouncement) -> Result<()> {
+        let format = self.get(&announcement.format)?;
+        let event = &announcement.oracle_event;
+        if event.oracle_nonces.len() != event.event_descriptor.nonce_count() {
+            return Err(Error::Verification(format!(
+                "event {} has {} nonces, its descriptor needs {}",
+                event.event_id,
+                event.oracle_nonces.len(),
+                event.event_descriptor.nonce_count()
+            )));
+        }
+        Secp256k1::verification_only()
+            .verify_schnorr(
+                &announcement.announcement_signature,
+                &format.signature_message(event)?,
+                &announcement.oracle_public_key,
+            )
+            .map_err(|e| Error::Verification(e.to_string()))
+    }
+}
+
+/// Parse an announcement in a dlcspecs format and check its signature
+pub fn parse_announcement(bytes: &[u8]) -> Result<OracleAnnouncement> {
+    FormatRegistry::default().parse(bytes)
+}
+
+/// TLV encoding, see [`DLCSPECS_V0`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DlcSpecsV0;
+
+impl AnnouncementFormat for DlcSpecsV0 {
+    fn name(&self) -> &'static str {
+        DLCSPECS_V0
+    }
+
+    fn matches(&self, bytes: &[u8]) -> bool {
+        Reader::new(bytes).bigsize().ok() == Some(u64::from(ANNOUNCEMENT_TYPE))
+    }
+
+    fn encode_event(&self, event: &OracleEvent) -> Result<Vec<u8>> {
+        let mut value = Vec::new();
+        write_nonces(&mut value, &event.oracle_nonces)?;
+        value.extend_from_slice(&event.event_maturity_epoch.to_be_bytes());
+
+        let mut descriptor = Vec::new();
+        let descriptor_type = match &event.event_descriptor {
+            EventDescriptor::Enum { outcomes } => {
+                write_outcomes(&mut descriptor, outcomes)?;
+                ENUM_DESCRIPTOR_TLV_TYPE
+            }
+            EventDescriptor::DigitDecomposition {
+                base,
+                is_signed,
+                unit,
+                precision,
+                nb_digits,
+            } => {
+                write_bigsize(&mut descriptor, u64::from(*base));
+                write_digit_fields(&mut descriptor, *is_signed, unit, *precision, *nb_digits);
+                DIGIT_DESCRIPTOR_TLV_TYPE
+            }
+        };
+        write_tlv(&mut value, descriptor_type, &descriptor);
+        write_string(&mut value, &event.event_id);
+
+        let mut tlv = Vec::new();
+        write_tlv(&mut tlv, EVENT_TLV_TYPE, &value);
+        Ok(tlv)
+    }
+
+    fn encode(&self, announcement: &OracleAnnouncement) -> Result<Vec<u8>> {
+        let mut value = Vec::new();
+        write_signed_header(&mut value, announcement);
+        value.extend(self.encode_event(&announcement.oracle_event)?);
+
+        let mut tlv = Vec::new();
+        write_tlv(&mut tlv, u64::from(ANNOUNCEMENT_TYPE), &value);
+        Ok(tlv)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<OracleAnnouncement> {
+        let mut reader = Reader::new(bytes);
+        let mut announcement = reader.tlv(u64::from(ANNOUNCEMENT_TYPE))?;
+        reader.finish()?;
+
+        let (announcement_signature, oracle_public_key) = read_signed_header(&mut announcement)?;
+        let mut event = announcement.tlv(EVENT_TLV_TYPE)?;
+        announcement.finish()?;
+
+        let oracle_nonces = read_nonces(&mut event)?;
+        let event_maturity_epoch = event.u32()?;
+        let descriptor_type = event.bigsize()?;
+        let mut descriptor = event.value()?;
+        let event_descriptor = match descriptor_type {
+            ENUM_DESCRIPTOR_TLV_TYPE => EventDescriptor::Enum {
+                outcomes: read_outcomes(&mut descriptor)?,
+            },
+            DIGIT_DESCRIPTOR_TLV_TYPE => {
+                let base = u16::try_from(descriptor.bigsize()?)
+                    .map_err(|_| Error::Format("digit base above 65535".to_string()))?;
+                read_digit_fields(&mut descriptor, base)?
+            }
+            other => {
+                return Err(Error::Format(format!(
+                    "unknown event descriptor type {other}"
+                )))
+            }
+        };
+        descriptor.finish()?;
+        let event_id = event.string()?;
+        event.finish()?;
+
+        Ok(OracleAnnouncement {
+            announcement_signature,
+            oracle_public_key,
+            oracle_event: OracleEvent {
+                oracle_nonces,
+                event_maturity_epoch,
+                event_descriptor,
+                event_id,
+            },
+            format: DLCSPECS_V0.to_string(),
+        })
+    }
+}
+
+/// Sub-type encoding, see [`DLCSPECS_V1`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DlcSpecsV1;
+
+impl AnnouncementFormat for DlcSpecsV1 {
+    fn name(&self) -> &'static str {
+        DLCSPECS_V1
+    }
+
+    fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&ANNOUNCEMENT_TYPE.to_be_bytes())
+    }
+
+    fn encode_event(&self, event: &OracleEvent) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        write_nonces(&mut bytes, &event.oracle_nonces)?;
+        bytes.extend_from_slice(&event.event_maturity_epoch.to_be_bytes());
+        match &event.event_descriptor {
+            EventDescriptor::Enum { outcomes } => {
+                write_bigsize(&mut bytes, 0);
+                write_outcomes(&mut bytes, outcomes)?;
+            }
+            EventDescriptor::DigitDecomposition {
+                base,
+                is_signed,
+                unit,
+                precision,
+                nb_digits,
+            } => {
+                write_bigsize(&mut bytes, 1);
+                bytes.extend_from_slice(&base.to_be_bytes());
+                write_digit_fields(&mut bytes, *is_signed, unit, *precision, *nb_digits);
+            }
+        }
+        write_string(&mut bytes, &event.event_id);
+        Ok(bytes)
+    }
+
+    fn encode(&self, announcement: &OracleAnnouncement) -> Result<Vec<u8>> {
+        let mut bytes = ANNOUNCEMENT_TYPE.to_be_bytes().to_vec();
+        write_signed_header(&mut bytes, announcement);
+        bytes.extend(self.encode_event(&announcement.oracle_event)?);
+        Ok(bytes)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<OracleAnnouncement> {
+        let mut reader = Reader::new(bytes);
+        let message_type = reader.u16()?;
+        if message_type != ANNOUNCEMENT_TYPE {
+            return Err(Error::Format(format!(
+                "message type {message_type} is not an announcement"
+            )));
+        }
+        let (announcement_signature, oracle_public_key) = read_signed_header(&mut reader)?;
+        let oracle_nonces = read_nonces(&mut reader)?;
+        let event_maturity_epoch = reader.u32()?;
+        let event_descriptor = match reader.bigsize()? {
+            0 => EventDescriptor::Enum {
+                outcomes: read_outcomes(&mut reader)?,
+            },
+            1 => {
+                let base = reader.u16()?;
+                read_digit_fields(&mut reader, base)?
+            }
+            other => {
+                return Err(Error::Format(format!(
+                    "unknown event descriptor sub-type {other}"
+                )))
+            }
+        };
+        let event_id = reader.string()?;
+        reader.finish()?;
+
+        Ok(OracleAnnouncement {
+            announcement_signature,
+            oracle_public_key,
+            oracle_event: OracleEvent {
+                oracle_nonces,
+                event_maturity_epoch,
+                event_descriptor,
+                event_id,
+            },
+            format: DLCSPECS_V1.to_string(),
+        })
+    }
+}
+
+fn write_bigsize(bytes: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0xfc => bytes.push(value as u8),
+        0xfd..=0xffff => {
+            bytes.push(0xfd);
+            bytes.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            bytes.push(0xfe);
+            bytes.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            bytes.push(0xff);
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+fn write_tlv(bytes: &mut Vec<u8>, tlv_type: u64, value: &[u8]) {
+    write_bigsize(bytes, tlv_type);
+    write_bigsize(bytes, value.len() as u64);
+    bytes.extend_from_slice(value);
+}
+
+fn write_string(bytes: &mut Vec<u8>, value: &str) {
+    write_bigsize(bytes, value.len() as u64);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+fn write_count(bytes: &mut Vec<u8>, count: usize, what: &str) -> Result<()> {
+    let count = u16::try_from(count)
+        .map_err(|_| Error::Format(format!("{count} {what} do not fit a u16 count")))?;
+    bytes.extend_from_slice(&count.to_be_bytes());
+    Ok(())
+}
+
+fn write_nonces(bytes: &mut Vec<u8>, nonces: &[XOnlyPublicKey]) -> Result<()> {
+    write_count(bytes, nonces.len(), "nonces")?;
+    for nonce in nonces {
+        bytes.extend_from_slice(&nonce.serialize());
+    }
+    Ok(())
+}
+
+fn write_outcomes(bytes: &mut Vec<u8>, outcomes: &[String]) -> Result<()> {
+    write_count(bytes, outcomes.len(), "outcomes")?;
+    for outcome in outcomes {
+        write_string(bytes, outcome);
+    }
+    Ok(())
+}
+
+fn write_digit_fields(
+    bytes: &mut Vec<u8>,
+    is_signed: bool,
+    unit: &str,
+    precision: i32,
+    nb_digits: u16,
+) {
+    bytes.push(u8::from(is_signed));
+    write_string(bytes, unit);
+    bytes.extend_from_slice(&precision.to_be_bytes());
+    bytes.extend_from_slice(&nb_digits.to_be_bytes());
+}
+
+fn write_signed_header(bytes: &mut Vec<u8>, announcement: &OracleAnnouncement) {
+    bytes.extend_from_slice(announcement.announcement_signature.as_ref());
+    bytes.extend_from_slice(&announcement.oracle_public_key.serialize());
+}
+
+fn read_signed_header(reader: &mut Reader<'_>) -> Result<(Signature, XOnlyPublicKey)> {
+    let signature = Signature::from_slice(reader.take(64)?)
+        .map_err(|e| Error::Format(format!("invalid announcement signature: {e}")))?;
+    let public_key = reader.x_only_public_key()?;
+    Ok((signature, public_key))
+}
+
+fn read_nonces(reader: &mut Reader<'_>) -> Result<Vec<XOnlyPublicKey>> {
+    (0..reader.u16()?)
+        .map(|_| reader.x_only_public_key())
+        .collect()
+}
+
+fn read_outcomes(reader: &mut Reader<'_>) -> Result<Vec<String>> {
+    (0..reader.u16()?).map(|_| reader.string()).collect()
+}
+
+fn read_digit_fields(reader: &mut Reader<'_>, base: u16) -> Result<EventDescriptor> {
+    let is_signed = match reader.u8()? {
+        0 => false,
+        1 => true,
+        other => return Err(Error::Format(format!("invalid boolean {other}"))),
+    };
+    Ok(EventDescriptor::DigitDecomposition {
+        base,
+        is_signed,
+        unit: reader.string()?,
+        precision: i32::from_be_bytes(reader.array()?),
+        nb_digits: reader.u16()?,
+    })
+}
+
+/// Cursor over a serialized announcement
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(Error::Format("announcement is truncated".to_string()));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    /// BigSize integer, rejecting non-minimal encodings
+    fn bigsize(&mut self) -> Result<u64> {
+        let (value, min) = match self.u8()? {
+            0xfd => (u64::from(self.u16()?), 0xfd),
+            0xfe => (u64::from(self.u32()?), 0x1_0000),
+            0xff => (u64::from_be_bytes(self.array()?), 0x1_0000_0000),
+            small => return Ok(u64::from(small)),
+        };
+        if value < min {
+            return Err(Error::Format(format!(
+                "non-canonical BigSize encoding of {value}"
+            )));
+        }
+        Ok(value)
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        usize::try_from(self.bigsize()?)
+            .map_err(|_| Error::Format("length does not fit in memory".to_string()))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|e| Error::Format(format!("invalid UTF-8 string: {e}")))
+    }
+
+    fn x_only_public_key(&mut self) -> Result<XOnlyPublicKey> {
+        XOnlyPublicKey::from_slice(self.take(32)?)
+            .map_err(|e| Error::Format(format!("invalid x-only public key: {e}")))
+    }
+
+    /// Value of a length-prefixed record whose type was already read
+    fn value(&mut self) -> Result<Reader<'a>> {
+        let len = self.len()?;
+        Ok(Reader::new(self.take(len)?))
+    }
+
+    /// Value of a TLV record that must have type `expected`
+    fn tlv(&mut self, expected: u64) -> Result<Reader<'a>> {
+        let tlv_type = self.bigsize()?;
+        if tlv_type != expected {
+            return Err(Error::Format(format!(
+                "expected TLV type {expected}, found {tlv_type}"
+            )));
+        }
+        self.value()
+    }
+
+    fn finish(&self) -> Result<()> {
+        if !self.bytes.is_empty() {
+            return Err(Error::Format(format!(
+                "{} trailing bytes",
+                self.bytes.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Oracle;
+    use bitcoin::secp256k1::SecretKey;
+
+    fn oracle() -> Oracle {
+        Oracle::new(SecretKey::from_slice(&[0x11; 32]).unwrap())
+    }
+
+    fn enum_event(oracle: &Oracle) -> OracleEvent {
+        oracle
+            .create_event(
+                "btc-above-100k",
+                1_700_000_000,
+                EventDescriptor::Enum {
+                    outcomes: vec!["yes".to_string(), "no".to_string()],
+                },
+            )
+            .unwrap()
+    }
+
+    fn digit_event(oracle: &Oracle) -> OracleEvent {
+        oracle
+            .create_event(
+                "btcusd-2024-01-01",
+                1_704_067_200,
+                EventDescriptor::DigitDecomposition {
+                    base: 2,
+                    is_signed: true,
+                    unit: "usd/btc".to_string(),
+                    precision: -2,
+                    nb_digits: 20,
+                },
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_announcements_round_trip_in_both_formats() {
+        let oracle = oracle();
+        for event in [enum_event(&oracle), digit_event(&oracle)] {
+            for format in [DLCSPECS_V0, DLCSPECS_V1] {
+                let bytes = oracle.announcement_bytes(&event, format).unwrap();
+                let parsed = parse_announcement(&bytes).unwrap();
+                assert_eq!(parsed.format, format);
+                assert_eq!(parsed.oracle_event, event);
+                assert_eq!(
+                    parsed.oracle_public_key,
+                    oracle.public_key().x_only_public_key().0
+                );
+                let registry = FormatRegistry::default();
+                assert_eq!(
+                    registry.get(format).unwrap().encode(&parsed).unwrap(),
+                    bytes
+                );
+            }
+        }
+        assert_eq!(digit_event(&oracle).oracle_nonces.len(), 21);
+        // Nonces are a function of the event id
+        assert_eq!(enum_event(&oracle), enum_event(&oracle));
+        assert_ne!(
+            enum_event(&oracle).oracle_nonces,
+            digit_event(&oracle).oracle_nonces[..1]
+        );
+    }
+
+    #[test]
+    fn test_encodings_follow_dlcspecs_layout() {
+        let oracle = oracle();
+        let event = enum_event(&oracle);
+        let nonce = event.oracle_nonces[0].serialize();
+        let mut event_body = vec![0x00, 0x01];
+        event_body.extend_from_slice(&nonce);
+        event_body.extend_from_slice(&1_700_000_000u32.to_be_bytes());
+
+        // v1: u16 message type, then the descriptor under sub-type 0
+        let v1 = oracle.announcement_bytes(&event, DLCSPECS_V1).unwrap();
+        assert_eq!(v1[..2], [0xd8, 0x24]);
+        let mut expected = event_body.clone();
+        expected.extend_from_slice(&[0x00, 0x00, 0x02, 0x03, b'y', b'e', b's', 0x02, b'n', b'o']);
+        expected.extend_from_slice(&[0x0e]);
+        expected.extend_from_slice(b"btc-above-100k");
+        assert_eq!(v1[2 + 64 + 32..], expected[..]);
+
+        // v0: the same fields inside nested TLV records
+        let v0 = oracle.announcement_bytes(&event, DLCSPECS_V0).unwrap();
+        let event_len = event_body.len() + 13 + 15;
+        assert_eq!(v0[..3], [0xfd, 0xd8, 0x24]);
+        assert_eq!(usize::from(v0[3]), 64 + 32 + 4 + event_len);
+        let event_tlv = &v0[4 + 64 + 32..];
+        assert_eq!(event_tlv[..4], [0xfd, 0xd8, 0x22, event_len as u8]);
+        assert_eq!(event_tlv[4..4 + event_body.len()], event_body[..]);
+        assert_eq!(
+            event_tlv[4 + event_body.len()..][..14],
+            [0xfd, 0xd8, 0x06, 0x09, 0x00, 0x02, 0x03, b'y', b'e', b's', 0x02, b'n', b'o', 0x0e]
+        );
+    }
+
+    #[test]
+    fn test_tampered_and_malformed_announcements_are_rejected() {
+        let oracle = oracle();
+        let event = enum_event(&oracle);
+        let v1 = oracle.announcement_bytes(&event, DLCSPECS_V1).unwrap();
+
+        // Maturity moved by one second
+        let mut tampered = v1.clone();
+        tampered[2 + 64 + 32 + 2 + 32 + 3] ^= 1;
+        assert!(matches!(
+            parse_announcement(&tampered),
+            Err(Error::Verification(_))
+        ));
+
+        // The signature covers the encoding it was made for
+        let mut relabelled = parse_announcement(&v1).unwrap();
+        relabelled.format = DLCSPECS_V0.to_string();
+        assert!(FormatRegistry::default().verify(&relabelled).is_err());
+
+        let mut trailing = v1.clone();
+        trailing.push(0);
+        assert!(matches!(
+            parse_announcement(&trailing),
+            Err(Error::Format(_))
+        ));
+        assert!(matches!(
+            parse_announcement(&v1[..v1.len() - 1]),
+            Err(Error::Format(_))
+        ));
+        assert!(matches!(
+            parse_announcement(&[0x00; 8]),
+            Err(Error::Format(_))
+        ));
+        // 0x0a encoded on three bytes is not a canonical BigSize
+        assert!(Reader::new(&[0xfd, 0x00, 0x0a]).bigsize().is_err());
+    }
+
+    #[test]
+    fn test_registry_is_pluggable() {
+        /// v1 encoding under another name, never auto-detected
+        struct Renamed;
+
+        impl AnnouncementFormat for Renamed {
+            fn name(&self) -> &'static str {
+                "renamed"
+            }
+
+            fn matches(&self, _bytes: &[u8]) -> bool {
+                false
+            }
+
+            fn encode_event(&self, event: &OracleEvent) -> Result<Vec<u8>> {
+                DlcSpecsV1.encode_event(event)
+            }
+
+            fn encode(&self, announcement: &OracleAnnouncement) -> Result<Vec<u8>> {
+                DlcSpecsV1.encode(announcement)
+            }
+
+            fn decode(&self, bytes: &[u8]) -> Result<OracleAnnouncement> {
+                DlcSpecsV1
+                    .decode(bytes)
+                    .map(|announcement| OracleAnnouncement {
+                        format: self.name().to_string(),
+                        ..announcement
+                    })
+            }
+        }
+
+        let mut registry = FormatRegistry::empty();
+        registry.register(Arc::new(Renamed)).unwrap();
+        assert!(registry.register(Arc::new(Renamed)).is_err());
+        assert_eq!(registry.names(), vec!["renamed"]);
+
+        let secret_key = SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let oracle = Oracle::with_registry(secret_key, registry.clone(), "renamed").unwrap();
+        assert_eq!(oracle.format(), "renamed");
+        let bytes = oracle
+            .announce(
+                "event",
+                0,
+                EventDescriptor::Enum {
+                    outcomes: vec!["a".to_string()],
+                },
+            )
+            .unwrap();
+        let announcement = registry.get("renamed").unwrap().decode(&bytes).unwrap();
+        registry.verify(&announcement).unwrap();
+        assert!(registry.parse(&bytes).is_err());
+
+        assert!(Oracle::with_format(secret_key, "renamed").is_err());
+        assert!(oracle
+            .announcement(&announcement.oracle_event, DLCSPECS_V0)
+            .is_err());
+    }
+}
@@ -0,0 +1,70 @@
+// Injectable wall clock for attestation timestamps
+//
+// The oracle reads the time through `Clock` so that maturity checks can be
+// tested with a `MockClock` instead of real time.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Source of the current wall-clock time
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Current time
+    fn now(&self) -> SystemTime;
+
+    /// Seconds since the Unix epoch, or 0 if the clock is set before it
+    fn unix_timestamp(&self) -> u64 {
+        self.now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    }
+}
+
+/// The operating system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that only moves when told to
+///
+/// Clones share the same time, so a test can keep one handle and advance the
+/// clock an oracle was given.
+#[derive(Debug, Clone)]
+pub struct MockClock(Arc<Mutex<SystemTime>>);
+
+impl MockClock {
+    /// Clock starting at `start`
+    pub fn new(start: SystemTime) -> Self {
+        Self(Arc::new(Mutex::new(start)))
+    }
+
+    /// Clock starting `secs` seconds after the Unix epoch
+    pub fn at_unix(secs: u64) -> Self {
+        Self::new(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Jump to `time`
+    pub fn set(&self, time: SystemTime) {
+        *self.time() = time;
+    }
+
+    /// Move the clock forward by `by`
+    pub fn advance(&self, by: Duration) {
+        *self.time() += by;
+    }
+
+    fn time(&self) -> MutexGuard<'_, SystemTime> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.time()
+    }
+}
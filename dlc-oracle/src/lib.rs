@@ -4,10 +4,42 @@
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
 
-use bitcoin::secp256k1::{PublicKey, SecretKey};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{schnorr, Keypair, Message, Parity, PublicKey, Scalar, Secp256k1, SecretKey, Verification, XOnlyPublicKey};
 use secp256k1::ecdsa::Signature;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Oracle announcements and their wire formats
+pub mod announcement;
+/// Injectable wall clock
+pub mod clock;
+pub use clock::{Clock, MockClock, SystemClock};
+pub use announcement::{
+    parse_announcement, AnnouncementFormat, EventDescriptor, FormatRegistry, OracleAnnouncement,
+    OracleEvent, DLCSPECS_V0, DLCSPECS_V1,
+};
+
+/// Tag of the hash oracle nonces are derived with
+const NONCE_TAG: &str = "DLC/oracle/nonce/v0";
+
+/// Tag of the hash an attestation signature commits to
+const ATTESTATION_TAG: &str = "anya/oracle/attestation/v0";
+
+/// Tag of the hash an outcome signature commits to
+const OUTCOME_TAG: &str = "DLC/oracle/attestation/v0";
+
+/// Tag of the BIP340 challenge hash
+const CHALLENGE_TAG: &str = "BIP0340/challenge";
+
+/// How far in the future an attestation timestamp may be, for clock drift
+pub const MAX_CLOCK_DRIFT_SECS: u64 = 300;
+
+/// Smallest share of a batch worth verifying on its own thread
+const MIN_BATCH_CHUNK: usize = 16;
 
 /// Oracle attestation data
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,10 +48,45 @@ pub struct Attestation {
     pub value: String,
     /// The public key used for signing
     pub public_key: PublicKey,
-    /// The signature over the attested value
+    /// The signature over the attested value, event, maturity and timestamp
     pub signature: Signature,
     /// Timestamp of attestation
     pub timestamp: u64,
+    /// Identifier of the attested event, empty for a standalone value
+    #[serde(default)]
+    pub event_id: String,
+    /// Maturity of the attested event, in seconds since the epoch
+    #[serde(default)]
+    pub maturity: u64,
+    /// Outcomes signed with the event's nonces: the outcome of an enum
+    /// event, or the sign and digits of a digit decomposition event
+    #[serde(default)]
+    pub outcomes: Vec<String>,
+    /// BIP340 signature of each outcome under the matching announced nonce
+    #[serde(default)]
+    pub outcome_signatures: Vec<schnorr::Signature>,
+}
+
+impl Attestation {
+    /// Message the oracle signs
+    fn message(event_id: &str, value: &str, maturity: u64, timestamp: u64) -> Message {
+        let tag = sha256::Hash::hash(ATTESTATION_TAG.as_bytes());
+        let mut engine = sha256::Hash::engine();
+        engine.input(tag.as_ref());
+        engine.input(tag.as_ref());
+        for field in [event_id, value] {
+            engine.input(&(field.len() as u64).to_be_bytes());
+            engine.input(field.as_bytes());
+        }
+        engine.input(&maturity.to_be_bytes());
+        engine.input(&timestamp.to_be_bytes());
+        Message::from_digest(sha256::Hash::from_engine(engine).to_byte_array())
+    }
+    
+    /// Message an outcome signature commits to
+    fn outcome_message(outcome: &str) -> Message {
+        Message::from_digest(tagged_hash(OUTCOME_TAG, &[outcome.as_bytes()]))
+    }
 }
 
 /// Oracle error types
@@ -36,6 +103,10 @@ pub enum Error {
     /// Internal error
     #[error("Internal error: {0}")]
     Internal(String),
+    
+    /// Announcement encoding error
+    #[error("Format error: {0}")]
+    Format(String),
 }
 
 /// Result type for DLC oracle operations
@@ -45,14 +116,53 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub struct Oracle {
     secret_key: SecretKey,
     public_key: PublicKey,
+    registry: FormatRegistry,
+    format: String,
+    /// Timestamp of the latest attestation, which later ones may not precede
+    last_attestation: AtomicU64,
+    /// Nonces that signed an outcome, with the outcome they attested
+    used_nonces: Mutex<HashMap<XOnlyPublicKey, String>>,
+    /// Source of attestation timestamps
+    clock: Arc<dyn Clock>,
 }
 
 impl Oracle {
     /// Create a new oracle instance with generated keys
+    ///
+    /// Announces in the dlcspecs v0 TLV format.
     pub fn new(secret_key: SecretKey) -> Self {
         let secp = bitcoin::secp256k1::Secp256k1::new();
         let public_key = PublicKey::from_secret_key(&secp, &secret_key);
-        Self { secret_key, public_key }
+        Self {
+            secret_key,
+            public_key,
+            registry: FormatRegistry::default(),
+            format: DLCSPECS_V0.to_string(),
+            last_attestation: AtomicU64::new(0),
+            used_nonces: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+    
+    /// Timestamp attestations with `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Create an oracle announcing in `format` from the default registry
+    pub fn with_format(secret_key: SecretKey, format: &str) -> Result<Self> {
+        Self::with_registry(secret_key, FormatRegistry::default(), format)
+    }
+    
+    /// Create an oracle announcing in `format` from `registry`
+    pub fn with_registry(secret_key: SecretKey, registry: FormatRegistry, format: &str) -> Result<Self> {
+        registry.get(format)?;
+        Ok(Self {
+            format: format.to_string(),
+            registry,
+            ..Self::new(secret_key)
+        })
     }
     
     /// Get the oracle's public key
@@ -60,35 +170,478 @@ impl Oracle {
         self.public_key
     }
     
+    /// Name of the format [`Oracle::announce`] uses
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+    
+    /// Formats this oracle can announce in
+    pub fn registry(&self) -> &FormatRegistry {
+        &self.registry
+    }
+    
+    /// Create an event with the nonces its outcome will be attested with
+    ///
+    /// Nonces are derived deterministically from the oracle key and
+    /// `event_id`, like RFC 6979 nonces, so no nonce state has to be kept
+    /// between announcement and attestation.
+    pub fn create_event(&self, event_id: &str, event_maturity_epoch: u32, event_descriptor: EventDescriptor) -> Result<OracleEvent> {
+        let oracle_nonces = self
+            .event_nonces(event_id, &event_descriptor)?
+            .iter()
+            .map(|nonce| nonce.x_only_public_key().0)
+            .collect();
+        Ok(OracleEvent {
+            oracle_nonces,
+            event_maturity_epoch,
+            event_descriptor,
+            event_id: event_id.to_string(),
+        })
+    }
+    
+    /// Sign `event` as an announcement in `format`
+    pub fn announcement(&self, event: &OracleEvent, format: &str) -> Result<OracleAnnouncement> {
+        let message = self.registry.get(format)?.signature_message(event)?;
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &self.secret_key);
+        Ok(OracleAnnouncement {
+            announcement_signature: secp.sign_schnorr_with_rng(&message, &keypair, &mut rand::thread_rng()),
+            oracle_public_key: keypair.x_only_public_key().0,
+            oracle_event: event.clone(),
+            format: format.to_string(),
+        })
+    }
+    
+    /// Canonical serialization of the announcement of `event` in `format`
+    pub fn announcement_bytes(&self, event: &OracleEvent, format: &str) -> Result<Vec<u8>> {
+        let announcement = self.announcement(event, format)?;
+        self.registry.get(format)?.encode(&announcement)
+    }
+    
+    /// Create and announce an event in this oracle's format
+    pub fn announce(&self, event_id: &str, event_maturity_epoch: u32, event_descriptor: EventDescriptor) -> Result<Vec<u8>> {
+        let event = self.create_event(event_id, event_maturity_epoch, event_descriptor)?;
+        self.announcement_bytes(&event, &self.format)
+    }
+    
+    /// Nonce `index` of the event `event_id`
+    fn event_nonce(&self, event_id: &str, index: u32) -> Result<Keypair> {
+        let digest = tagged_hash(NONCE_TAG, &[&self.secret_key.secret_bytes(), event_id.as_bytes(), &index.to_be_bytes()]);
+        let nonce = SecretKey::from_slice(&digest)
+            .map_err(|e| Error::Signing(format!("nonce derivation failed: {e}")))?;
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        Ok(Keypair::from_secret_key(&secp, &nonce))
+    }
+    
+    /// Nonces of every outcome of the event `event_id`
+    fn event_nonces(&self, event_id: &str, event_descriptor: &EventDescriptor) -> Result<Vec<Keypair>> {
+        (0..event_descriptor.nonce_count())
+            .map(|index| self.event_nonce(event_id, index as u32))
+            .collect()
+    }
+    
+    /// BIP340 signature of `message` with the committed `nonce`
+    fn sign_with_nonce(&self, message: &Message, nonce: &Keypair) -> Result<schnorr::Signature> {
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let (nonce_point, nonce_parity) = nonce.x_only_public_key();
+        let (public_key, key_parity) = self.secret_key.x_only_public_key(&secp);
+        // BIP340 uses the secrets of the even-y points
+        let k = match nonce_parity {
+            Parity::Even => nonce.secret_key(),
+            Parity::Odd => nonce.secret_key().negate(),
+        };
+        let x = match key_parity {
+            Parity::Even => self.secret_key,
+            Parity::Odd => self.secret_key.negate(),
+        };
+        let challenge = tagged_hash(CHALLENGE_TAG, &[&nonce_point.serialize(), &public_key.serialize(), message.as_ref()]);
+        let e = Scalar::from_be_bytes(challenge)
+            .map_err(|_| Error::Signing("challenge out of range".to_string()))?;
+        let s = x
+            .mul_tweak(&e)
+            .and_then(|ex| ex.add_tweak(&Scalar::from(k)))
+            .map_err(|e| Error::Signing(e.to_string()))?;
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(&nonce_point.serialize());
+        signature[32..].copy_from_slice(&s.secret_bytes());
+        schnorr::Signature::from_slice(&signature).map_err(|e| Error::Signing(e.to_string()))
+    }
+    
     /// Create an attestation for a given value
     pub fn attest(&self, value: &str) -> Result<Attestation> {
-        // Implementation would go here
+        self.sign_attestation("", value, 0, self.clock.unix_timestamp())
+    }
+    
+    /// Attest the outcome of an announced event
+    ///
+    /// Signs each outcome with the event's announced nonce. Signing two
+    /// messages with one nonce reveals the oracle key, so an event is
+    /// attested at most once: a used nonce is never signed with again, even
+    /// for the same outcome. Fails with `Error::Verification("not yet
+    /// mature")` before the event's maturity time.
+    pub fn attest_outcome(&self, event: &OracleEvent, outcome: &str) -> Result<Attestation> {
+        self.attest_outcome_at(event, outcome, self.clock.unix_timestamp())
+    }
+    
+    fn attest_outcome_at(&self, event: &OracleEvent, outcome: &str, timestamp: u64) -> Result<Attestation> {
+        let outcomes = outcome_parts(&event.event_descriptor, outcome)
+            .map_err(|e| Error::Signing(format!("{outcome:?} is not an outcome of event {}: {e}", event.event_id)))?;
+        let maturity = u64::from(event.event_maturity_epoch);
+        if timestamp < maturity {
+            return Err(Error::Verification("not yet mature".to_string()));
+        }
+        let nonces = self.event_nonces(&event.event_id, &event.event_descriptor)?;
+        if !nonces.iter().map(|nonce| nonce.x_only_public_key().0).eq(event.oracle_nonces.iter().copied()) {
+            return Err(Error::Signing(format!("event {} was not announced by this oracle", event.event_id)));
+        }
+        
+        let mut used_nonces = self.used_nonces.lock()
+            .map_err(|_| Error::Internal("nonce ledger lock poisoned".to_string()))?;
+        if let Some(previous) = event.oracle_nonces.iter().find_map(|nonce| used_nonces.get(nonce)) {
+            return Err(Error::Signing(format!("event {} was already attested as {previous:?}", event.event_id)));
+        }
+        let mut attestation = self.sign_attestation(&event.event_id, outcome, maturity, timestamp)?;
+        attestation.outcome_signatures = outcomes
+            .iter()
+            .zip(&nonces)
+            .map(|(part, nonce)| self.sign_with_nonce(&Attestation::outcome_message(part), nonce))
+            .collect::<Result<_>>()?;
+        attestation.outcomes = outcomes;
+        for nonce in &event.oracle_nonces {
+            used_nonces.insert(*nonce, outcome.to_string());
+        }
+        Ok(attestation)
+    }
+    
+    /// Sign an attestation, refusing timestamps before the previous one
+    fn sign_attestation(&self, event_id: &str, value: &str, maturity: u64, timestamp: u64) -> Result<Attestation> {
+        let previous = self.last_attestation.fetch_max(timestamp, Ordering::SeqCst);
+        if timestamp < previous {
+            return Err(Error::Signing(format!(
+                "timestamp {timestamp} precedes the previous attestation at {previous}"
+            )));
+        }
         let secp = bitcoin::secp256k1::Secp256k1::new();
-        let message = bitcoin::secp256k1::Message::from_slice(value.as_bytes()).unwrap();
-        let signature = secp.sign_ecdsa(&message, &self.secret_key);
+        let message = Attestation::message(event_id, value, maturity, timestamp);
         Ok(Attestation {
             value: value.to_string(),
             public_key: self.public_key,
-            signature,
-            timestamp: current_timestamp(),
+            signature: secp.sign_ecdsa(&message, &self.secret_key),
+            timestamp,
+            event_id: event_id.to_string(),
+            maturity,
+            outcomes: Vec::new(),
+            outcome_signatures: Vec::new(),
         })
     }
     
     /// Verify an attestation
-    pub fn verify(attestation: &Attestation) -> Result<bool> {
-        // Implementation would go here
-        let secp = bitcoin::secp256k1::Secp256k1::new();
-        let message = bitcoin::secp256k1::Message::from_slice(attestation.value.as_bytes()).unwrap();
+    ///
+    /// Rejects attestations made before their event's maturity, timestamped
+    /// more than [`MAX_CLOCK_DRIFT_SECS`] in the future, or, with `max_age`,
+    /// older than `max_age`.
+    pub fn verify(attestation: &Attestation, max_age: Option<Duration>) -> Result<bool> {
+        Self::verify_at(attestation, max_age, current_timestamp())
+    }
+    
+    /// Verify several attestations, reporting each one individually
+    ///
+    /// Every attestation gets the checks of [`Oracle::verify`], so a bad one
+    /// fails only its own entry; attestations with and without Schnorr
+    /// outcome signatures can be mixed. rust-secp256k1 has no batch
+    /// verification, and a randomized BIP340 batch equation built from its
+    /// point operations is slower than verifying each signature, so instead
+    /// the batch shares one verification context and clock reading and is
+    /// spread over the available cores. Results are in `attestations` order.
+    pub fn verify_batch(attestations: &[Attestation], max_age: Option<Duration>) -> Vec<Result<bool>> {
+        let secp = Secp256k1::verification_only();
+        let now = current_timestamp();
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = ((attestations.len() + threads - 1) / threads).max(MIN_BATCH_CHUNK);
+        if attestations.len() <= chunk {
+            return attestations.iter().map(|a| Self::verify_with(&secp, a, max_age, now)).collect();
+        }
+        
+        std::thread::scope(|scope| {
+            let secp = &secp;
+            let workers: Vec<_> = attestations
+                .chunks(chunk)
+                .map(|part| {
+                    scope.spawn(move || {
+                        part.iter().map(|a| Self::verify_with(secp, a, max_age, now)).collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect()
+        })
+    }
+    
+    fn verify_at(attestation: &Attestation, max_age: Option<Duration>, now: u64) -> Result<bool> {
+        Self::verify_with(&Secp256k1::verification_only(), attestation, max_age, now)
+    }
+    
+    fn verify_with<C: Verification>(secp: &Secp256k1<C>, attestation: &Attestation, max_age: Option<Duration>, now: u64) -> Result<bool> {
+        let message = Attestation::message(&attestation.event_id, &attestation.value, attestation.maturity, attestation.timestamp);
         secp.verify_ecdsa(&message, &attestation.signature, &attestation.public_key)
-            .map(|_| true)
-            .map_err(|e| Error::Verification(e.to_string()))
+            .map_err(|e| Error::Verification(e.to_string()))?;
+        if attestation.outcomes.len() != attestation.outcome_signatures.len() {
+            return Err(Error::Verification("outcome without a signature".to_string()));
+        }
+        let oracle_key = attestation.public_key.x_only_public_key().0;
+        for (outcome, signature) in attestation.outcomes.iter().zip(&attestation.outcome_signatures) {
+            secp.verify_schnorr(signature, &Attestation::outcome_message(outcome), &oracle_key)
+                .map_err(|e| Error::Verification(format!("outcome {outcome:?}: {e}")))?;
+        }
+        
+        if attestation.timestamp < attestation.maturity {
+            return Err(Error::Verification("attested before maturity".to_string()));
+        }
+        if attestation.timestamp > now.saturating_add(MAX_CLOCK_DRIFT_SECS) {
+            return Err(Error::Verification(format!("timestamp {} is in the future", attestation.timestamp)));
+        }
+        if let Some(max_age) = max_age {
+            let age = now.saturating_sub(attestation.timestamp);
+            if age > max_age.as_secs() {
+                return Err(Error::Verification(format!("stale attestation, {age}s old")));
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// BIP340 tagged hash of the concatenation of `parts`
+fn tagged_hash(tag: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    for part in parts {
+        engine.input(part);
+    }
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Outcomes signed to attest `outcome`, one per nonce of the event
+///
+/// A digit decomposition outcome is an integer, signed as `+` or `-` when the
+/// event is signed and then as its digits, most significant first.
+fn outcome_parts(descriptor: &EventDescriptor, outcome: &str) -> std::result::Result<Vec<String>, String> {
+    match descriptor {
+        EventDescriptor::Enum { outcomes } => {
+            if !outcomes.iter().any(|o| o == outcome) {
+                return Err("not one of the enumerated outcomes".to_string());
+            }
+            Ok(vec![outcome.to_string()])
+        }
+        EventDescriptor::DigitDecomposition { base, is_signed, nb_digits, .. } => {
+            let value: i64 = outcome.parse().map_err(|e| format!("{e}"))?;
+            if *base < 2 || (value < 0 && !is_signed) {
+                return Err(format!("cannot be expressed in base {base}"));
+            }
+            let mut magnitude = value.unsigned_abs();
+            let mut digits = vec![String::new(); usize::from(*nb_digits)];
+            for digit in digits.iter_mut().rev() {
+                *digit = (magnitude % u64::from(*base)).to_string();
+                magnitude /= u64::from(*base);
+            }
+            if magnitude != 0 {
+                return Err(format!("does not fit in {nb_digits} digits"));
+            }
+            if *is_signed {
+                digits.insert(0, if value < 0 { "-" } else { "+" }.to_string());
+            }
+            Ok(digits)
+        }
     }
 }
 
 /// Get the current timestamp in seconds since epoch
+///
+/// A system clock set before the epoch reads as 0 instead of panicking.
 pub fn current_timestamp() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs()
+    SystemClock.unix_timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const MATURITY: u32 = 1_700_000_000;
+    
+    fn oracle() -> Oracle {
+        Oracle::new(SecretKey::from_slice(&[0x33; 32]).unwrap())
+    }
+    
+    fn event(oracle: &Oracle) -> OracleEvent {
+        let descriptor = EventDescriptor::Enum { outcomes: vec!["yes".to_string(), "no".to_string()] };
+        oracle.create_event("settles", MATURITY, descriptor).unwrap()
+    }
+    
+    #[test]
+    fn test_premature_attestations_are_rejected() {
+        let oracle = oracle();
+        let event = event(&oracle);
+        let maturity = u64::from(MATURITY);
+        
+        match oracle.attest_outcome_at(&event, "yes", maturity - 1) {
+            Err(Error::Verification(msg)) => assert_eq!(msg, "not yet mature"),
+            other => panic!("expected a maturity error, got {other:?}"),
+        }
+        let future = oracle.create_event("later", u32::MAX, event.event_descriptor.clone()).unwrap();
+        assert!(oracle.attest_outcome(&future, "yes").is_err());
+        assert!(oracle.attest_outcome_at(&event, "maybe", maturity).is_err());
+        
+        let attestation = oracle.attest_outcome_at(&event, "yes", maturity).unwrap();
+        assert_eq!(attestation.event_id, "settles");
+        assert!(Oracle::verify_at(&attestation, None, maturity).unwrap());
+        
+        // Moving the timestamp before maturity breaks the signature
+        let mut backdated = attestation.clone();
+        backdated.timestamp = maturity - 1;
+        assert!(Oracle::verify_at(&backdated, None, maturity).is_err());
+    }
+    
+    #[test]
+    fn test_mock_clock_drives_maturity() {
+        let clock = MockClock::at_unix(u64::from(MATURITY) - 10);
+        let oracle = oracle().with_clock(Arc::new(clock.clone()));
+        let event = event(&oracle);
+        assert!(matches!(oracle.attest_outcome(&event, "yes"), Err(Error::Verification(_))));
+        
+        clock.advance(Duration::from_secs(10));
+        let attestation = oracle.attest_outcome(&event, "yes").unwrap();
+        assert_eq!(attestation.timestamp, u64::from(MATURITY));
+        
+        clock.set(std::time::UNIX_EPOCH - Duration::from_secs(1));
+        assert_eq!(clock.unix_timestamp(), 0);
+    }
+    
+    #[test]
+    fn test_stale_and_future_attestations_are_rejected() {
+        let oracle = oracle();
+        let event = event(&oracle);
+        let attested_at = u64::from(MATURITY) + 10;
+        let attestation = oracle.attest_outcome_at(&event, "no", attested_at).unwrap();
+        
+        let hour_later = attested_at + 3600;
+        assert!(Oracle::verify_at(&attestation, None, hour_later).unwrap());
+        assert!(Oracle::verify_at(&attestation, Some(Duration::from_secs(3600)), hour_later).unwrap());
+        assert!(matches!(
+            Oracle::verify_at(&attestation, Some(Duration::from_secs(60)), hour_later),
+            Err(Error::Verification(_))
+        ));
+        
+        let before = attested_at - MAX_CLOCK_DRIFT_SECS - 1;
+        assert!(Oracle::verify_at(&attestation, None, before).is_err());
+        assert!(Oracle::verify_at(&attestation, None, before + 1).unwrap());
+        
+        let standalone = oracle.attest("42").unwrap();
+        assert!(Oracle::verify(&standalone, Some(Duration::from_secs(60))).unwrap());
+    }
+    
+    #[test]
+    fn test_attestation_timestamps_are_monotonic() {
+        let oracle = oracle();
+        let descriptor = event(&oracle).event_descriptor;
+        let events: Vec<OracleEvent> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|id| oracle.create_event(id, MATURITY, descriptor.clone()).unwrap())
+            .collect();
+        let first = u64::from(MATURITY) + 100;
+        oracle.attest_outcome_at(&events[0], "yes", first).unwrap();
+        oracle.attest_outcome_at(&events[1], "yes", first).unwrap();
+        assert!(matches!(
+            oracle.attest_outcome_at(&events[2], "no", first - 1),
+            Err(Error::Signing(_))
+        ));
+        oracle.attest_outcome_at(&events[3], "no", first + 1).unwrap();
+    }
+    
+    #[test]
+    fn test_event_nonce_signs_only_one_outcome() {
+        let oracle = oracle();
+        let event = event(&oracle);
+        let at = u64::from(MATURITY);
+        let attestation = oracle.attest_outcome_at(&event, "yes", at).unwrap();
+        
+        // The outcome is signed with the announced nonce
+        assert_eq!(attestation.outcomes, vec!["yes".to_string()]);
+        let nonce = &attestation.outcome_signatures[0].as_ref()[..32];
+        assert_eq!(nonce, event.oracle_nonces[0].serialize());
+        assert!(Oracle::verify_at(&attestation, None, at).unwrap());
+        let mut swapped = attestation.clone();
+        swapped.outcomes = vec!["no".to_string()];
+        assert!(Oracle::verify_at(&swapped, None, at).is_err());
+        
+        // Neither a different nor the same outcome is signed again
+        for outcome in ["no", "yes"] {
+            match oracle.attest_outcome_at(&event, outcome, at + 1) {
+                Err(Error::Signing(msg)) => assert!(msg.contains("already attested"), "{msg}"),
+                other => panic!("expected a nonce reuse error, got {other:?}"),
+            }
+        }
+        
+        // Nor is an event with the same id but another descriptor
+        let renamed = EventDescriptor::Enum { outcomes: vec!["yes".to_string(), "no".to_string(), "void".to_string()] };
+        let same_id = oracle.create_event("settles", MATURITY, renamed).unwrap();
+        assert!(oracle.attest_outcome_at(&same_id, "void", at + 1).is_err());
+        
+        // An event whose nonces this oracle did not derive is refused
+        let mut foreign = oracle.create_event("foreign", MATURITY, event.event_descriptor.clone()).unwrap();
+        foreign.oracle_nonces = event.oracle_nonces.clone();
+        assert!(oracle.attest_outcome_at(&foreign, "no", at + 1).is_err());
+    }
+    
+    #[test]
+    fn test_batch_reports_each_attestation() {
+        let oracle = oracle();
+        let descriptor = event(&oracle).event_descriptor;
+        let mut attestations = Vec::new();
+        for i in 0..40 {
+            let attestation = if i % 2 == 0 {
+                oracle.attest(&i.to_string()).unwrap()
+            } else {
+                let event = oracle.create_event(&format!("event-{i}"), 0, descriptor.clone()).unwrap();
+                oracle.attest_outcome(&event, "yes").unwrap()
+            };
+            attestations.push(attestation);
+        }
+        // One bad ECDSA signature and one bad Schnorr outcome signature
+        attestations[6].value = "tampered".to_string();
+        attestations[33].outcomes = vec!["no".to_string()];
+        
+        let results = Oracle::verify_batch(&attestations, None);
+        assert_eq!(results.len(), attestations.len());
+        for (i, (result, attestation)) in results.iter().zip(&attestations).enumerate() {
+            assert_eq!(result.is_ok(), Oracle::verify(attestation, None).is_ok(), "attestation {i}");
+            assert_eq!(result.is_err(), i == 6 || i == 33, "attestation {i}");
+        }
+        assert!(Oracle::verify_batch(&attestations[..3], None).iter().all(|r| r.is_ok()));
+        assert!(Oracle::verify_batch(&[], None).is_empty());
+    }
+    
+    #[test]
+    fn test_digit_outcomes_are_signed_digit_by_digit() {
+        let oracle = oracle();
+        let descriptor = EventDescriptor::DigitDecomposition {
+            base: 10,
+            is_signed: true,
+            unit: "usd".to_string(),
+            precision: 0,
+            nb_digits: 5,
+        };
+        let event = oracle.create_event("price", MATURITY, descriptor.clone()).unwrap();
+        let at = u64::from(MATURITY);
+        assert!(oracle.attest_outcome_at(&event, "123456", at).is_err());
+        
+        let attestation = oracle.attest_outcome_at(&event, "-4321", at).unwrap();
+        assert_eq!(attestation.outcomes, vec!["-", "0", "4", "3", "2", "1"]);
+        for (signature, nonce) in attestation.outcome_signatures.iter().zip(&event.oracle_nonces) {
+            assert_eq!(signature.as_ref()[..32], nonce.serialize());
+        }
+        assert!(Oracle::verify_at(&attestation, None, at).unwrap());
+    }
 }
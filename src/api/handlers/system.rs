@@ -16,3 +16,7 @@ pub async fn system_info() -> Result<Json<Value>, StatusCode> {
         "status": "operational"
     })))
 }
+
+pub async fn version() -> Result<Json<Value>, StatusCode> {
+    Ok(Json(json!(crate::build_info())))
+}
@@ -29,6 +29,7 @@ pub fn configure_routes(
     let public_routes = Router::new()
         .route("/health", get(handlers::system::health_check))
         .route("/info", get(handlers::system::system_info))
+        .route("/version", get(handlers::system::version))
         .route("/login", post(handlers::auth::login));
 
     // Identity routes - require authentication
@@ -757,13 +757,24 @@ impl EnhancedInstaller {
             anyhow::bail!("BIP compliance validation failed: {}", e);
         }
 
+        // BIP-341 support is whatever the bundled wallet test vectors show
+        let vectors = anya_core::testing::bip_vectors::run_bip341_vectors();
+        let bip341 = vectors.compliance_status();
+        let overall_status = match bip341 {
+            anya_core::compliance::ComplianceStatus::Full => "Passed",
+            _ => {
+                println!("⚠️  BIP-341 test vectors:\n{vectors}");
+                "Partial"
+            }
+        };
+
         // Create a proper compliance report
         let report = BipComplianceReport {
-            bip341: anya_core::compliance::ComplianceStatus::Full,
+            bip341,
             bip342: anya_core::compliance::ComplianceStatus::Full,
             bip174: anya_core::compliance::ComplianceStatus::Full,
             bip370: anya_core::compliance::ComplianceStatus::Full,
-            overall_status: "Passed".to_string(),
+            overall_status: overall_status.to_string(),
             timestamp: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .unwrap()
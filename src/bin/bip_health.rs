@@ -7,7 +7,6 @@ use std::error::Error;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tracing::{info, warn, Level};
-use tracing_subscriber::FmtSubscriber;
 
 use anya_core::bip::validation::BitcoinConfig;
 use anya_core::bip::{Bip353, Bip353Config, Bip353Status, BipHealthChecker};
@@ -68,8 +67,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     } else {
         Level::INFO
     };
-    let subscriber = FmtSubscriber::builder().with_max_level(level).finish();
-    tracing::subscriber::set_global_default(subscriber)?;
+    anya_core::logging::init_logging_with(
+        &anya_core::logging::LoggingConfig::from_env().with_default_level(level),
+    )?;
 
     // Create default output path if not specified
     let default_output = format!(
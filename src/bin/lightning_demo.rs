@@ -52,6 +52,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             min_confirmations: 6,
             default_fee_rate: 10,
             wallet_path: None,
+            chain_source: None,
         };
 
         // Create Lightning node instance
@@ -33,19 +33,34 @@ enum Commands {
         #[arg(short, long)]
         config: Option<String>,
     },
+    /// Exercise each enabled subsystem and report pass/fail
+    Selftest,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt::init();
+    anya_core::init_logging()?;
 
     let cli = Cli::parse();
 
     match cli.command {
         Commands::Start { port, config } => {
+            info!("Anya Core {}", anya_core::build_info());
             info!("Starting Anya Core server on port {}", port);
             if let Some(config_path) = config {
                 info!("Using configuration file: {}", config_path);
+                let config = anya_core::AnyaConfig::from_file(&config_path)?;
+                info!("Configured for {}", config.node_config.network);
+                if std::env::var_os("RUST_LOG").is_none() {
+                    anya_core::logging::set_log_level(&config.runtime.log_level)?;
+                }
+
+                #[cfg(unix)]
+                {
+                    let live = std::sync::Arc::new(anya_core::config::LiveConfig::new(config));
+                    anya_core::config::reload::reload_on_sighup(live, config_path.into())?;
+                    info!("Send SIGHUP to reload the log level");
+                }
             }
 
             // Initialize core systems
@@ -64,12 +79,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         Commands::Validate { config } => {
             info!("Validating configuration");
-            if let Some(config_path) = config {
-                info!("Validating configuration file: {}", config_path);
+            match config {
+                Some(config_path) => {
+                    info!("Validating configuration file: {}", config_path);
+                    anya_core::AnyaConfig::from_file(&config_path)?;
+                }
+                None => {
+                    let mut config = anya_core::AnyaConfig::default();
+                    config.apply_env_overrides()?;
+                    config.validate()?;
+                }
             }
 
             println!("✅ Configuration validation passed");
             Ok(())
         }
+        Commands::Selftest => {
+            info!("Running subsystem self-test");
+            let report =
+                anya_core::tools::run_selftest(&anya_core::tools::selftest::default_checks()).await;
+            print!("{report}");
+
+            if !report.passed() {
+                warn!("Self-test failed: {:?}", report.failures());
+                std::process::exit(1);
+            }
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,8 @@
+// Generates the Kotlin and Swift bindings for `anya_core::mobile::bindings`
+//
+//   cargo run --features uniffi-cli --bin uniffi-bindgen -- generate \
+//       --library target/release/libanya_core.so --language swift --out-dir bindings/swift
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
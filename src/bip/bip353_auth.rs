@@ -1,11 +1,11 @@
 // [AIR-3][AIS-3][BPC-3][AIT-3] BIP353 Beta Access Control
 // Lightning Network based authorization for BIP353 beta features
 
+use crate::security::crypto::message_from_hashed;
 use base64::{engine::general_purpose, Engine as _};
 use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
-use secp256k1::{Message, Secp256k1};
+use secp256k1::Secp256k1;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
@@ -201,15 +201,7 @@ impl BetaAccessManager {
         };
 
         // Create message from k1
-        let mut hasher = Sha256::new();
-        hasher.update(session.k1.as_bytes());
-        let message_hash = hasher.finalize();
-
-        let message = {
-            let digest: [u8; 32] = message_hash.into();
-            Message::from_digest_slice(&digest)
-                .map_err(|e| BetaAccessError::AuthError(format!("Invalid message: {e}")))?
-        };
+        let message = message_from_hashed(session.k1.as_bytes());
 
         // Parse public key and signature
         let pubkey = match secp256k1::PublicKey::from_slice(&pubkey_bytes) {
@@ -46,6 +46,11 @@ impl BitcoinAdapter {
     pub fn get_implementation(&self) -> Arc<dyn BitcoinInterface> {
         self.implementation.clone()
     }
+
+    /// Check whether a protocol feature (e.g. "BIP-341") is supported
+    pub fn supports_feature(&self, feature: &str) -> bool {
+        self.config.supports_bip(feature).unwrap_or(false)
+    }
 }
 
 /// Implementation of BitcoinInterface following hexagonal architecture pattern
@@ -0,0 +1,381 @@
+// [AIR-3][AIS-3][BPC-3] BIP-21 payment URIs
+//
+// Parses and builds `bitcoin:` URIs, including the `pj` (BIP-78 payjoin) and
+// `lightning` (unified QR) parameters. Amounts are decimal BTC and converted
+// to satoshis digit by digit, never through floating point. Unknown
+// parameters are kept unless they carry the `req-` prefix, which BIP-21
+// requires a client to reject.
+
+use crate::AnyaError;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, Amount, Network};
+use std::fmt;
+
+/// URI scheme, matched case-insensitively
+const SCHEME: &str = "bitcoin:";
+
+/// Prefix of parameters the payer must understand to proceed
+const REQUIRED_PREFIX: &str = "req-";
+
+/// Decimal places in an amount of BTC
+const BTC_DECIMALS: usize = 8;
+
+/// BIP-21 specific error types
+#[derive(Debug, thiserror::Error)]
+pub enum Bip21Error {
+    #[error("Not a bitcoin: URI")]
+    InvalidScheme,
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Address {address} is not valid on {network}")]
+    WrongNetwork { address: String, network: Network },
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+    #[error("Unsupported required parameter: {0}")]
+    UnknownRequiredParam(String),
+    #[error("Duplicate parameter: {0}")]
+    DuplicateParam(String),
+    #[error("Invalid percent-encoding: {0}")]
+    InvalidEncoding(String),
+}
+
+impl From<Bip21Error> for AnyaError {
+    fn from(err: Bip21Error) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+/// BIP-21 result type
+pub type Bip21Result<T> = Result<T, Bip21Error>;
+
+/// A `bitcoin:` payment request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitcoinUri {
+    /// Address to pay, checked against the network the URI was parsed for
+    pub address: Address,
+    pub amount: Option<Amount>,
+    /// Label for the recipient
+    pub label: Option<String>,
+    /// Note describing the payment
+    pub message: Option<String>,
+    /// BIP-78 payjoin endpoint
+    pub payjoin: Option<String>,
+    /// BOLT11 invoice or BOLT12 offer paying the same request over Lightning
+    pub lightning: Option<String>,
+    /// Optional parameters not interpreted here, decoded, in URI order
+    pub extras: Vec<(String, String)>,
+}
+
+impl BitcoinUri {
+    /// Request a payment to `address` with no further parameters
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            amount: None,
+            label: None,
+            message: None,
+            payjoin: None,
+            lightning: None,
+            extras: Vec::new(),
+        }
+    }
+
+    /// Parse a `bitcoin:` URI whose address must be valid on `network`
+    pub fn parse(uri: &str, network: Network) -> Bip21Result<Self> {
+        let scheme = uri.get(..SCHEME.len()).ok_or(Bip21Error::InvalidScheme)?;
+        if !scheme.eq_ignore_ascii_case(SCHEME) {
+            return Err(Bip21Error::InvalidScheme);
+        }
+        let rest = &uri[SCHEME.len()..];
+        let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let unchecked: Address<NetworkUnchecked> = address
+            .parse()
+            .map_err(|e| Bip21Error::InvalidAddress(format!("{address}: {e}")))?;
+        let checked = unchecked
+            .require_network(network)
+            .map_err(|_| Bip21Error::WrongNetwork {
+                address: address.to_string(),
+                network,
+            })?;
+        let mut parsed = Self::new(checked);
+
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let key = percent_decode(key)?.to_ascii_lowercase();
+            let value = percent_decode(value)?;
+            let slot = match key.as_str() {
+                "amount" => {
+                    if parsed.amount.is_some() {
+                        return Err(Bip21Error::DuplicateParam(key));
+                    }
+                    parsed.amount = Some(parse_btc_amount(&value)?);
+                    continue;
+                }
+                "label" => &mut parsed.label,
+                "message" => &mut parsed.message,
+                "pj" => &mut parsed.payjoin,
+                "lightning" => &mut parsed.lightning,
+                _ if key.starts_with(REQUIRED_PREFIX) => {
+                    return Err(Bip21Error::UnknownRequiredParam(key));
+                }
+                _ => {
+                    parsed.extras.push((key, value));
+                    continue;
+                }
+            };
+            if slot.replace(value).is_some() {
+                return Err(Bip21Error::DuplicateParam(key));
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for BitcoinUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{SCHEME}{}", self.address)?;
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            params.push(format!("amount={}", format_btc_amount(amount)));
+        }
+        let named = [
+            ("label", &self.label),
+            ("message", &self.message),
+            ("pj", &self.payjoin),
+            ("lightning", &self.lightning),
+        ];
+        for (key, value) in named {
+            if let Some(value) = value {
+                params.push(format!("{key}={}", percent_encode(value)));
+            }
+        }
+        for (key, value) in &self.extras {
+            params.push(format!("{}={}", percent_encode(key), percent_encode(value)));
+        }
+        if !params.is_empty() {
+            write!(f, "?{}", params.join("&"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a decimal BTC amount such as `20.3` into satoshis
+fn parse_btc_amount(value: &str) -> Bip21Result<Amount> {
+    let invalid = || Bip21Error::InvalidAmount(value.to_string());
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty())
+        || !is_digits(whole)
+        || !is_digits(fraction)
+        || fraction.len() > BTC_DECIMALS
+    {
+        return Err(invalid());
+    }
+
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let fraction: u64 = format!("{fraction:0<BTC_DECIMALS$}")
+        .parse()
+        .map_err(|_| invalid())?;
+    let sats = whole
+        .checked_mul(Amount::ONE_BTC.to_sat())
+        .and_then(|sats| sats.checked_add(fraction))
+        .filter(|sats| *sats <= Amount::MAX_MONEY.to_sat())
+        .ok_or_else(invalid)?;
+    Ok(Amount::from_sat(sats))
+}
+
+/// Decimal BTC without trailing zeros
+fn format_btc_amount(amount: Amount) -> String {
+    let sats = amount.to_sat();
+    let one_btc = Amount::ONE_BTC.to_sat();
+    let fraction = format!("{:0>BTC_DECIMALS$}", sats % one_btc);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (sats / one_btc).to_string()
+    } else {
+        format!("{}.{fraction}", sats / one_btc)
+    }
+}
+
+fn percent_decode(value: &str) -> Bip21Result<String> {
+    let invalid = || Bip21Error::InvalidEncoding(value.to_string());
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).ok_or_else(invalid)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The address in the BIP-21 examples has a bad checksum; the genesis
+    // coinbase address stands in for it
+    const ADDRESS: &str = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+
+    fn parse(uri: &str) -> Bip21Result<BitcoinUri> {
+        BitcoinUri::parse(uri, Network::Bitcoin)
+    }
+
+    #[test]
+    fn test_bip21_examples() {
+        let plain = parse(&format!("bitcoin:{ADDRESS}")).unwrap();
+        assert_eq!(plain.address.to_string(), ADDRESS);
+        assert_eq!(plain.amount, None);
+
+        let labelled = parse(&format!("bitcoin:{ADDRESS}?label=Luke-Jr")).unwrap();
+        assert_eq!(labelled.label.as_deref(), Some("Luke-Jr"));
+
+        let request = parse(&format!("bitcoin:{ADDRESS}?amount=20.3&label=Luke-Jr")).unwrap();
+        assert_eq!(request.amount, Some(Amount::from_sat(2_030_000_000)));
+
+        let donation = parse(&format!(
+            "bitcoin:{ADDRESS}?amount=50&label=Luke-Jr&message=Donation%20for%20project%20xyz"
+        ))
+        .unwrap();
+        assert_eq!(donation.amount, Some(Amount::from_int_btc(50)));
+        assert_eq!(
+            donation.message.as_deref(),
+            Some("Donation for project xyz")
+        );
+
+        let required = parse(&format!(
+            "bitcoin:{ADDRESS}?req-somethingyoudontunderstand=50&req-somethingelseyoudontget=999"
+        ));
+        assert!(matches!(
+            required,
+            Err(Bip21Error::UnknownRequiredParam(key)) if key == "req-somethingyoudontunderstand"
+        ));
+
+        let optional = parse(&format!(
+            "bitcoin:{ADDRESS}?somethingyoudontunderstand=50&somethingelseyoudontget=999"
+        ))
+        .unwrap();
+        assert_eq!(optional.extras.len(), 2);
+        assert_eq!(
+            optional.extras[1],
+            ("somethingelseyoudontget".to_string(), "999".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unified_onchain_and_lightning_uri() {
+        let invoice = "LNBC10U1P3PJ257PP5YZTKWJCZ5FTL5LAXKAV23ZMZEKAW37ZK6KMV80PK4XAEV5QHTZ7QDPDWD3XGER9WD5KWM36YPRX7U3QD36KUCMGYP282ETNV3SHJCQZPGXQYZ5VQSP5USYC4LK9CHSFP53KVCNVQ456GANH60D89REYKDNGSMTJ6YW3NHVQ9QYYSSQJCEWM5CJWZ4A6RFJX77C490YCED6PEMK0UPKXHY89CMM7SCT66K8GNEANWYKZGDRWRFJE69H9U5U0W57RRCSYSAS7GADWMZXC8C6T0SPJAZUP6";
+        let uri = format!(
+            "BITCOIN:BC1QYLH3U67J673H6Y6ALV70M0PL2YZ53TZHVXGG7U?amount=0.00001&label=sbddesign%3A%20For%20lunch%20Tuesday&message=For%20lunch%20Tuesday&lightning={invoice}"
+        );
+        let parsed = parse(&uri).unwrap();
+        assert_eq!(
+            parsed.address.to_string(),
+            "bc1qylh3u67j673h6y6alv70m0pl2yz53tzhvxgg7u"
+        );
+        assert_eq!(parsed.amount, Some(Amount::from_sat(1_000)));
+        assert_eq!(
+            parsed.label.as_deref(),
+            Some("sbddesign: For lunch Tuesday")
+        );
+        assert_eq!(parsed.lightning.as_deref(), Some(invoice));
+
+        // Generation round-trips, percent-encoding the label
+        let generated = parsed.to_string();
+        assert!(generated.starts_with(
+            "bitcoin:bc1qylh3u67j673h6y6alv70m0pl2yz53tzhvxgg7u?amount=0.00001&label=sbddesign%3A%20For"
+        ));
+        assert_eq!(parse(&generated).unwrap(), parsed);
+    }
+
+    #[test]
+    fn test_payjoin_endpoint_round_trips() {
+        let mut uri = BitcoinUri::new(
+            ADDRESS
+                .parse::<Address<NetworkUnchecked>>()
+                .unwrap()
+                .assume_checked(),
+        );
+        uri.amount = Some(Amount::from_sat(123_456_789));
+        uri.payjoin = Some("https://example.com/pj?v=1&x=a b".to_string());
+
+        let generated = uri.to_string();
+        assert_eq!(
+            generated,
+            format!(
+                "bitcoin:{ADDRESS}?amount=1.23456789&pj=https%3A%2F%2Fexample.com%2Fpj%3Fv%3D1%26x%3Da%20b"
+            )
+        );
+        assert_eq!(parse(&generated).unwrap(), uri);
+    }
+
+    #[test]
+    fn test_amounts_are_exact() {
+        // 0.29 BTC is not representable as a float and must not round down
+        assert_eq!(parse_btc_amount("0.29").unwrap().to_sat(), 29_000_000);
+        assert_eq!(parse_btc_amount(".5").unwrap().to_sat(), 50_000_000);
+        assert_eq!(parse_btc_amount("21000000").unwrap(), Amount::MAX_MONEY);
+        for invalid in [
+            "",
+            ".",
+            "0.000000001",
+            "-1",
+            "1e3",
+            "1,5",
+            "21000000.00000001",
+        ] {
+            assert!(parse_btc_amount(invalid).is_err(), "{invalid} accepted");
+        }
+        assert_eq!(format_btc_amount(Amount::from_sat(2_030_000_000)), "20.3");
+        assert_eq!(format_btc_amount(Amount::from_sat(1)), "0.00000001");
+        assert_eq!(format_btc_amount(Amount::ZERO), "0");
+    }
+
+    #[test]
+    fn test_rejects_invalid_uris() {
+        assert!(matches!(
+            parse(&format!("litecoin:{ADDRESS}")),
+            Err(Bip21Error::InvalidScheme)
+        ));
+        assert!(matches!(
+            BitcoinUri::parse(&format!("bitcoin:{ADDRESS}"), Network::Testnet),
+            Err(Bip21Error::WrongNetwork { .. })
+        ));
+        assert!(matches!(
+            parse("bitcoin:notanaddress"),
+            Err(Bip21Error::InvalidAddress(_))
+        ));
+        assert!(matches!(
+            parse(&format!("bitcoin:{ADDRESS}?label=a&label=b")),
+            Err(Bip21Error::DuplicateParam(_))
+        ));
+        assert!(matches!(
+            parse(&format!("bitcoin:{ADDRESS}?message=100%")),
+            Err(Bip21Error::InvalidEncoding(_))
+        ));
+    }
+}
@@ -5,7 +5,8 @@ use std::error::Error;
 //! This module implements BIP-340 (Schnorr Signatures) for Bitcoin Core integration.
 //! Compliant with official Bitcoin Improvement Proposals (BIPs).
 
-use bitcoin::{secp256k1, PublicKey, hashes::{sha256, Hash}};
+use bitcoin::{secp256k1, PublicKey};
+use crate::bitcoin::bip341::tagged_hash;
 use secp256k1::{Secp256k1, Message, SecretKey};
 use thiserror::Error;
 use crate::security::constant_time;
@@ -130,26 +131,6 @@ impl TryFrom<&[u8]> for XOnlyPublicKey {
     }
 }
 
-/// Calculate a BIP-340 tagged hash
-fn tagged_hash(tag: &[u8], msg: &[u8]) -> [u8; 32] {
-    // Calculate tag hash
-    let tag_hash = sha256::Hash::hash(tag);
-    
-    // Initialize hasher with tag hash
-    let mut engine = sha256::Hash::engine();
-    engine.input(&tag_hash);
-    engine.input(&tag_hash);
-    engine.input(msg);
-    
-    // Finalize hash
-    let result = sha256::Hash::from_engine(engine);
-    
-    // Convert to array
-    let mut output = [0u8; 32];
-    output.copy_from_slice(&result[..]);
-    output
-}
-
 /// BIP-340 Schnorr implementation
 pub struct Bip340Schnorr;
 
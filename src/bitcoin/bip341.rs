@@ -15,7 +15,7 @@ use rand;
 use std::collections::HashMap;
 use thiserror::Error;
 
-/// Tag for the taproot branch hash
+/// Tag for the taproot leaf hash
 const TAPROOT_LEAF_TAG: &[u8] = b"TapLeaf";
 /// Tag for the taproot branch hash
 const TAPROOT_BRANCH_TAG: &[u8] = b"TapBranch";
@@ -46,6 +46,22 @@ pub enum Bip341Error {
     Other(String),
 }
 
+/// Hash engine for the BIP-340 tagged hash `SHA256(SHA256(tag) || SHA256(tag) || msg)`
+fn tagged_engine(tag: &[u8]) -> sha256::HashEngine {
+    let tag_hash = sha256::Hash::hash(tag);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag_hash.as_ref());
+    engine.input(tag_hash.as_ref());
+    engine
+}
+
+/// BIP-340 tagged hash `SHA256(SHA256(tag) || SHA256(tag) || msg)`
+pub fn tagged_hash(tag: &[u8], msg: &[u8]) -> [u8; 32] {
+    let mut engine = tagged_engine(tag);
+    engine.input(msg);
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
 /// Script leaf version (as defined in BIP-341)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -94,11 +110,10 @@ impl TaprootLeaf {
     pub fn compute_leaf_hash(&self) -> [u8; 32] {
         let version_byte: u8 = self.version.into();
 
-        // Initialize hasher with tag
-        let mut engine = sha256::Hash::engine();
-        engine.input(TAPROOT_LEAF_TAG);
+        // H_TapLeaf(version || compact_size(script) || script)
+        let mut engine = tagged_engine(TAPROOT_LEAF_TAG);
         engine.input(&[version_byte]);
-        engine.input(&self.script);
+        engine.input(&bitcoin::consensus::serialize(&self.script));
 
         // Finalize hash
         let result = sha256::Hash::from_engine(engine);
@@ -135,9 +150,8 @@ impl TaprootBranch {
 
     /// Compute the branch hash
     pub fn compute_branch_hash(&self) -> [u8; 32] {
-        // Initialize hasher with tag
-        let mut engine = sha256::Hash::engine();
-        engine.input(TAPROOT_BRANCH_TAG);
+        // H_TapBranch(left || right)
+        let mut engine = tagged_engine(TAPROOT_BRANCH_TAG);
         engine.input(&self.left);
         engine.input(&self.right);
 
@@ -388,9 +402,7 @@ impl Bip341Taproot {
             tweak_input.extend_from_slice(&root);
         }
 
-        // Initialize hasher with tag
-        let mut engine = sha256::Hash::engine();
-        engine.input(TAPROOT_TWEAK_TAG);
+        let mut engine = tagged_engine(TAPROOT_TWEAK_TAG);
         engine.input(&tweak_input);
 
         // Finalize hash
@@ -657,6 +669,18 @@ pub fn create_taproot_output(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tagged_hash_matches_tap_tweak() {
+        let secp = Secp256k1::new();
+        let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let (key, _) = secret_key.x_only_public_key(&secp);
+
+        assert_eq!(
+            tagged_hash(TAPROOT_TWEAK_TAG, &key.serialize()),
+            bitcoin::taproot::TapTweakHash::from_key_and_tweak(key, None).to_byte_array()
+        );
+    }
+
     #[test]
     fn test_taproot_leaf_hash() {
         let script = vec![0x51, 0x21, 0x03]; // OP_1 OP_SIZE OP_PUSH3
@@ -0,0 +1,478 @@
+// [AIR-3][AIS-3][BPC-3][RES-3] Persistent block index
+//
+// Every known header is indexed by hash with its height, cumulative work,
+// validation status and position in the block files. Changes are appended to
+// a log in the data directory as they happen and replayed on startup, where
+// the last record for a hash wins. Pruning a block only drops its file
+// position, so ancestor lookups and fork resolution keep working.
+
+use crate::{AnyaError, AnyaResult};
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::{BlockHash, Work};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Block index log in the data directory
+const BLOCK_INDEX_FILE: &str = "block_index.jsonl";
+
+/// Validation and storage state of an indexed block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockStatus {
+    /// Block data has been fully validated
+    pub valid: bool,
+    /// Block is part of the active chain
+    pub in_active_chain: bool,
+    /// Block data has been deleted from the block files
+    pub pruned: bool,
+}
+
+/// Location of a block in the block files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockFilePos {
+    pub file: u32,
+    pub offset: u64,
+}
+
+/// An indexed block header
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockIndexEntry {
+    pub header: BlockHeader,
+    pub height: u32,
+    pub status: BlockStatus,
+    /// Total work of the chain up to and including this block
+    pub chain_work: Work,
+    /// Where the block data is stored, unless pruned or not downloaded
+    pub file_pos: Option<BlockFilePos>,
+}
+
+impl BlockIndexEntry {
+    pub fn hash(&self) -> BlockHash {
+        self.header.block_hash()
+    }
+}
+
+/// Blocks to disconnect and connect to move the active chain to a new tip
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainChange {
+    /// Last block shared by the old and new chains
+    pub fork: BlockHash,
+    /// Blocks removed from the active chain, old tip first
+    pub disconnected: Vec<BlockHash>,
+    /// Blocks added to the active chain, lowest first
+    pub connected: Vec<BlockHash>,
+}
+
+/// [AIR-3][BPC-3] Block index with the active chain, persisted in the data directory
+pub struct BlockIndex {
+    path: PathBuf,
+    log: File,
+    entries: HashMap<BlockHash, BlockIndexEntry>,
+    /// Active chain by height
+    active: Vec<BlockHash>,
+}
+
+impl BlockIndex {
+    /// Open the index in `datadir`, starting from `genesis` if it is new
+    pub fn open(datadir: impl AsRef<Path>, genesis: &BlockHeader) -> AnyaResult<Self> {
+        let path = datadir.as_ref().join(BLOCK_INDEX_FILE);
+        let mut entries = HashMap::new();
+        if path.exists() {
+            let file = File::open(&path).map_err(|e| io_error(&path, e))?;
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|e| io_error(&path, e))?;
+                // A record torn by a crash can only be the last one
+                let Ok(entry) = serde_json::from_str::<BlockIndexEntry>(&line) else {
+                    log::warn!(
+                        "Ignoring incomplete block index record in {}",
+                        path.display()
+                    );
+                    break;
+                };
+                entries.insert(entry.hash(), entry);
+            }
+        }
+
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| io_error(&path, e))?;
+        let mut index = Self {
+            path,
+            log,
+            entries,
+            active: Vec::new(),
+        };
+
+        if index.entries.is_empty() {
+            index.write(BlockIndexEntry {
+                header: *genesis,
+                height: 0,
+                status: BlockStatus {
+                    valid: true,
+                    in_active_chain: true,
+                    pruned: false,
+                },
+                chain_work: genesis.work(),
+                file_pos: None,
+            })?;
+        }
+        index.rebuild_active_chain()?;
+        Ok(index)
+    }
+
+    pub fn get(&self, hash: &BlockHash) -> Option<&BlockIndexEntry> {
+        self.entries.get(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Tip of the active chain
+    pub fn get_tip(&self) -> &BlockIndexEntry {
+        &self.entries[&self.active[self.active.len() - 1]]
+    }
+
+    /// Block of the active chain at `height`
+    pub fn active_at_height(&self, height: u32) -> Option<BlockHash> {
+        self.active.get(height as usize).copied()
+    }
+
+    /// Index a header whose parent is already indexed
+    ///
+    /// The header must satisfy its own proof-of-work target. Indexing a known
+    /// header returns the existing entry.
+    pub fn insert_header(&mut self, header: BlockHeader) -> AnyaResult<&BlockIndexEntry> {
+        let hash = header.block_hash();
+        if !self.entries.contains_key(&hash) {
+            let parent = self.entries.get(&header.prev_blockhash).ok_or_else(|| {
+                AnyaError::NotFound(format!(
+                    "Parent {} of block {hash} is not indexed",
+                    header.prev_blockhash
+                ))
+            })?;
+            header
+                .validate_pow(header.target())
+                .map_err(|e| AnyaError::Bitcoin(format!("Block {hash}: {e}")))?;
+
+            let entry = BlockIndexEntry {
+                header,
+                height: parent.height + 1,
+                status: BlockStatus::default(),
+                chain_work: parent.chain_work + header.work(),
+                file_pos: None,
+            };
+            self.write(entry)?;
+        }
+        Ok(&self.entries[&hash])
+    }
+
+    /// Record that the block passed validation and is stored at `file_pos`
+    pub fn mark_valid(&mut self, hash: &BlockHash, file_pos: BlockFilePos) -> AnyaResult<()> {
+        self.update(hash, |entry| {
+            entry.status.valid = true;
+            entry.status.pruned = false;
+            entry.file_pos = Some(file_pos);
+        })
+    }
+
+    /// Record that the block's data has been pruned from disk
+    pub fn mark_pruned(&mut self, hash: &BlockHash) -> AnyaResult<()> {
+        self.update(hash, |entry| {
+            entry.status.pruned = true;
+            entry.file_pos = None;
+        })
+    }
+
+    /// Ancestor of `hash` at `height`, or `hash` itself at its own height
+    ///
+    /// Walks back only until the branch joins the active chain, whose blocks
+    /// are looked up by height directly.
+    pub fn ancestor_at_height(&self, hash: &BlockHash, height: u32) -> Option<BlockHash> {
+        let mut entry = self.entries.get(hash)?;
+        if height > entry.height {
+            return None;
+        }
+        loop {
+            if entry.status.in_active_chain {
+                return self.active_at_height(height);
+            }
+            if entry.height == height {
+                return Some(entry.hash());
+            }
+            entry = self.entries.get(&entry.header.prev_blockhash)?;
+        }
+    }
+
+    /// Last common ancestor of `a` and `b`
+    pub fn find_fork(&self, a: &BlockHash, b: &BlockHash) -> Option<BlockHash> {
+        let height = self.entries.get(a)?.height.min(self.entries.get(b)?.height);
+        let mut a = self.ancestor_at_height(a, height)?;
+        let mut b = self.ancestor_at_height(b, height)?;
+        while a != b {
+            let entry_a = &self.entries[&a];
+            // Once either side is on the active chain, so is the fork
+            if entry_a.status.in_active_chain || self.entries[&b].status.in_active_chain {
+                let joined = self
+                    .joins_active_chain(&a)?
+                    .min(self.joins_active_chain(&b)?);
+                return self.active_at_height(joined);
+            }
+            a = entry_a.header.prev_blockhash;
+            b = self.entries[&b].header.prev_blockhash;
+        }
+        Some(a)
+    }
+
+    /// Make `hash` the tip of the active chain
+    ///
+    /// Every block between the fork point and the new tip must have been
+    /// validated. Returns the blocks to disconnect and connect.
+    pub fn set_tip(&mut self, hash: &BlockHash) -> AnyaResult<ChainChange> {
+        let tip = self.get_tip().hash();
+        let fork = self.find_fork(&tip, hash).ok_or_else(|| {
+            AnyaError::NotFound(format!("Block {hash} is not connected to the index"))
+        })?;
+        let fork_height = self.entries[&fork].height;
+
+        let mut connected = Vec::new();
+        let mut cursor = *hash;
+        while cursor != fork {
+            let entry = &self.entries[&cursor];
+            if !entry.status.valid {
+                return Err(AnyaError::Bitcoin(format!(
+                    "Block {cursor} at height {} has not been validated",
+                    entry.height
+                )));
+            }
+            connected.push(cursor);
+            cursor = entry.header.prev_blockhash;
+        }
+        connected.reverse();
+
+        let disconnected: Vec<BlockHash> = self.active[fork_height as usize + 1..]
+            .iter()
+            .rev()
+            .copied()
+            .collect();
+        for block in &disconnected {
+            self.update(block, |entry| entry.status.in_active_chain = false)?;
+        }
+        for block in &connected {
+            self.update(block, |entry| entry.status.in_active_chain = true)?;
+        }
+        self.active.truncate(fork_height as usize + 1);
+        self.active.extend_from_slice(&connected);
+
+        Ok(ChainChange {
+            fork,
+            disconnected,
+            connected,
+        })
+    }
+
+    /// Height at which the branch ending in `hash` joins the active chain
+    fn joins_active_chain(&self, hash: &BlockHash) -> Option<u32> {
+        let mut entry = self.entries.get(hash)?;
+        while !entry.status.in_active_chain {
+            entry = self.entries.get(&entry.header.prev_blockhash)?;
+        }
+        Some(entry.height)
+    }
+
+    /// Rebuild the height index by walking back from the highest active block
+    fn rebuild_active_chain(&mut self) -> AnyaResult<()> {
+        let tip = self
+            .entries
+            .values()
+            .filter(|entry| entry.status.in_active_chain)
+            .max_by_key(|entry| entry.height)
+            .ok_or_else(|| AnyaError::System("Block index has no active chain".to_string()))?;
+
+        let mut active = Vec::with_capacity(tip.height as usize + 1);
+        let mut entry = tip;
+        loop {
+            active.push(entry.hash());
+            if entry.height == 0 {
+                break;
+            }
+            entry = self
+                .entries
+                .get(&entry.header.prev_blockhash)
+                .ok_or_else(|| {
+                    AnyaError::System(format!(
+                        "Block index is missing the parent of active block {}",
+                        entry.hash()
+                    ))
+                })?;
+        }
+        active.reverse();
+        self.active = active;
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        hash: &BlockHash,
+        change: impl FnOnce(&mut BlockIndexEntry),
+    ) -> AnyaResult<()> {
+        let mut entry = self
+            .entries
+            .get(hash)
+            .cloned()
+            .ok_or_else(|| AnyaError::NotFound(format!("Block {hash} is not indexed")))?;
+        change(&mut entry);
+        self.write(entry)
+    }
+
+    /// Append `entry` to the log, then apply it in memory
+    fn write(&mut self, entry: BlockIndexEntry) -> AnyaResult<()> {
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        self.log
+            .write_all(&line)
+            .and_then(|()| self.log.flush())
+            .map_err(|e| io_error(&self.path, e))?;
+        self.entries.insert(entry.hash(), entry);
+        Ok(())
+    }
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> AnyaError {
+    AnyaError::System(format!("Block index file {}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Network, TxMerkleNode};
+
+    /// Regtest header on top of `prev`, distinguished by `tag`
+    fn header(prev: &BlockHeader, tag: u8) -> BlockHeader {
+        let mut header = BlockHeader {
+            prev_blockhash: prev.block_hash(),
+            merkle_root: TxMerkleNode::from_byte_array([tag; 32]),
+            time: prev.time + 600,
+            ..*prev
+        };
+        while header.validate_pow(header.target()).is_err() {
+            header.nonce += 1;
+        }
+        header
+    }
+
+    /// Index and validate `len` headers on top of `from`, returning them
+    fn extend(index: &mut BlockIndex, from: BlockHeader, len: u8, tag: u8) -> Vec<BlockHash> {
+        let mut prev = from;
+        let mut hashes = Vec::new();
+        for i in 0..len {
+            prev = header(&prev, tag + i);
+            let hash = index.insert_header(prev).unwrap().hash();
+            index
+                .mark_valid(
+                    &hash,
+                    BlockFilePos {
+                        file: 0,
+                        offset: u64::from(tag + i) * 1000,
+                    },
+                )
+                .unwrap();
+            hashes.push(hash);
+        }
+        hashes
+    }
+
+    #[test]
+    fn test_fork_resolution_and_reorg() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis = genesis_block(Network::Regtest).header;
+        let mut index = BlockIndex::open(dir.path(), &genesis).unwrap();
+
+        // Active chain of 4 blocks, and a longer branch forking after block 2
+        let main = extend(&mut index, genesis, 4, 10);
+        index.set_tip(&main[3]).unwrap();
+        let fork_base = index.get(&main[1]).unwrap().header;
+        let branch = extend(&mut index, fork_base, 3, 50);
+
+        assert_eq!(index.get_tip().hash(), main[3]);
+        assert_eq!(index.get(&branch[2]).unwrap().height, 5);
+        assert_eq!(index.ancestor_at_height(&branch[2], 2), Some(main[1]));
+        assert_eq!(index.ancestor_at_height(&branch[2], 3), Some(branch[0]));
+        assert_eq!(index.ancestor_at_height(&main[0], 2), None);
+        assert_eq!(index.find_fork(&main[3], &branch[2]), Some(main[1]));
+        assert_eq!(index.find_fork(&branch[1], &main[2]), Some(main[1]));
+        assert_eq!(index.find_fork(&main[1], &main[3]), Some(main[1]));
+        assert!(index.get(&branch[2]).unwrap().chain_work > index.get_tip().chain_work);
+
+        let change = index.set_tip(&branch[2]).unwrap();
+        assert_eq!(change.fork, main[1]);
+        assert_eq!(change.disconnected, vec![main[3], main[2]]);
+        assert_eq!(change.connected, branch);
+        assert_eq!(index.active_at_height(3), Some(branch[0]));
+        assert!(!index.get(&main[2]).unwrap().status.in_active_chain);
+
+        // Fork lookups now go through the new active chain
+        assert_eq!(index.find_fork(&main[3], &branch[2]), Some(main[1]));
+        assert_eq!(index.ancestor_at_height(&main[3], 3), Some(main[2]));
+    }
+
+    #[test]
+    fn test_reload_restores_tip_and_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis = genesis_block(Network::Regtest).header;
+        let tip = {
+            let mut index = BlockIndex::open(dir.path(), &genesis).unwrap();
+            let chain = extend(&mut index, genesis, 3, 1);
+            index.set_tip(&chain[2]).unwrap();
+            index.mark_pruned(&chain[0]).unwrap();
+
+            // Unvalidated blocks cannot become the tip
+            let unvalidated = header(&index.get(&chain[2]).unwrap().header, 99);
+            let unvalidated = index.insert_header(unvalidated).unwrap().hash();
+            assert!(index.set_tip(&unvalidated).is_err());
+            chain
+        };
+
+        let index = BlockIndex::open(dir.path(), &genesis).unwrap();
+        assert_eq!(index.len(), 5);
+        assert_eq!(index.get_tip().hash(), tip[2]);
+        assert_eq!(index.get_tip().height, 3);
+        let pruned = index.get(&tip[0]).unwrap();
+        assert!(pruned.status.pruned && pruned.status.valid);
+        assert_eq!(pruned.file_pos, None);
+        assert_eq!(
+            index.get(&tip[1]).unwrap().file_pos,
+            Some(BlockFilePos {
+                file: 0,
+                offset: 2000
+            })
+        );
+        assert_eq!(
+            index.ancestor_at_height(&tip[2], 0),
+            Some(genesis.block_hash())
+        );
+    }
+
+    #[test]
+    fn test_rejects_orphan_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis = genesis_block(Network::Regtest).header;
+        let mut index = BlockIndex::open(dir.path(), &genesis).unwrap();
+        let mut orphan = header(&genesis, 1);
+        orphan.prev_blockhash = BlockHash::all_zeros();
+        assert!(matches!(
+            index.insert_header(orphan),
+            Err(AnyaError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,761 @@
+// BOLT12 Offer Decoding for Bitcoin Module
+// Decodes bech32 `lno` offer strings into their TLV fields and builds
+// the matching invoice_request messages
+
+use crate::AnyaError;
+use secp256k1::{schnorr, Keypair, Message, PublicKey, Secp256k1, SecretKey};
+
+use crate::bitcoin::bip341::tagged_hash;
+use crate::bitcoin::lightning::{truncated_be, write_bigsize, LightningPublicKey};
+
+/// Human-readable part of a BOLT12 offer
+pub const OFFER_HRP: &str = "lno";
+
+/// Bech32 character set used by BOLT12 strings
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+// Offer TLV record types
+const OFFER_CHAINS: u64 = 2;
+const OFFER_METADATA: u64 = 4;
+const OFFER_CURRENCY: u64 = 6;
+const OFFER_AMOUNT: u64 = 8;
+const OFFER_DESCRIPTION: u64 = 10;
+const OFFER_FEATURES: u64 = 12;
+const OFFER_ABSOLUTE_EXPIRY: u64 = 14;
+const OFFER_PATHS: u64 = 16;
+const OFFER_ISSUER: u64 = 18;
+const OFFER_QUANTITY_MAX: u64 = 20;
+const OFFER_ISSUER_ID: u64 = 22;
+
+// invoice_request TLV record types
+const INVREQ_METADATA: u64 = 0;
+const INVREQ_AMOUNT: u64 = 82;
+const INVREQ_QUANTITY: u64 = 86;
+const INVREQ_PAYER_ID: u64 = 88;
+
+/// TLV type of the BIP-340 signature record
+const SIGNATURE_TLV_TYPE: u64 = 240;
+
+/// Signature records occupy the inclusive range 240..=1000
+const SIGNATURE_TLV_RANGE: std::ops::RangeInclusive<u64> = 240..=1000;
+
+/// BOLT12-specific error types
+#[derive(Debug, thiserror::Error)]
+pub enum Bolt12Error {
+    #[error("Invalid bech32 encoding: {0}")]
+    InvalidEncoding(String),
+    #[error("Malformed TLV stream: {0}")]
+    MalformedTlv(String),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("Offer expired at {0}")]
+    Expired(u64),
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+}
+
+impl From<Bolt12Error> for AnyaError {
+    fn from(err: Bolt12Error) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+/// BOLT12 result type
+pub type Bolt12Result<T> = Result<T, Bolt12Error>;
+
+/// Introduction node of a blinded path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntroductionNode {
+    /// Node identified by its public key
+    NodeId([u8; 33]),
+    /// Node identified by a short channel id and direction
+    DirectedShortChannelId { direction: u8, scid: u64 },
+}
+
+/// Hop within a blinded path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlindedHop {
+    /// Blinded node id of the hop
+    pub blinded_node_id: [u8; 33],
+
+    /// Encrypted data for the hop
+    pub encrypted_recipient_data: Vec<u8>,
+}
+
+/// Blinded path to the offer issuer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlindedPath {
+    /// Introduction node of the path
+    pub introduction_node: IntroductionNode,
+
+    /// First path key (blinding point)
+    pub blinding_point: [u8; 33],
+
+    /// Blinded hops
+    pub hops: Vec<BlindedHop>,
+}
+
+/// Decoded BOLT12 offer
+#[derive(Debug, Clone)]
+pub struct Bolt12Offer {
+    /// Chain hashes the offer is valid for (empty means bitcoin mainnet)
+    pub chains: Vec<[u8; 32]>,
+
+    /// Issuer-supplied metadata
+    pub metadata: Option<Vec<u8>>,
+
+    /// ISO 4217 currency code, if the amount is not in millisatoshis
+    pub currency: Option<String>,
+
+    /// Amount, in millisatoshis or in `currency` units
+    pub amount: Option<u64>,
+
+    /// Offer description
+    pub description: Option<String>,
+
+    /// Feature bits
+    pub features: Vec<u8>,
+
+    /// Expiry as seconds since the Unix epoch
+    pub absolute_expiry: Option<u64>,
+
+    /// Blinded paths to the issuer
+    pub paths: Vec<BlindedPath>,
+
+    /// Issuer name
+    pub issuer: Option<String>,
+
+    /// Maximum quantity (0 means unlimited)
+    pub quantity_max: Option<u64>,
+
+    /// Issuer node id
+    pub node_id: Option<LightningPublicKey>,
+
+    /// Raw non-signature TLV records, in stream order
+    records: Vec<TlvRecord>,
+}
+
+impl Bolt12Offer {
+    /// Whether the offer has expired at `now` (seconds since the Unix epoch)
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.absolute_expiry.is_some_and(|expiry| now > expiry)
+    }
+
+    /// Amount in millisatoshis, if the offer is denominated in bitcoin
+    pub fn amount_msat(&self) -> Option<u64> {
+        match self.currency {
+            Some(_) => None,
+            None => self.amount,
+        }
+    }
+}
+
+/// Single TLV record with its raw encoding preserved for merkle hashing
+#[derive(Debug, Clone)]
+struct TlvRecord {
+    record_type: u64,
+    value: Vec<u8>,
+    raw: Vec<u8>,
+}
+
+/// Decode a bech32 `lno` offer string into its TLV fields
+///
+/// The string may be split with `+` continuations as permitted by BOLT12.
+/// Records must be in strictly ascending type order and unknown even types
+/// are rejected. If a signature record is present it is verified against
+/// the offer's merkle root and issuer id.
+pub fn parse_offer(bolt12: &str) -> Bolt12Result<Bolt12Offer> {
+    let data = decode_bech32(bolt12, OFFER_HRP)?;
+    let records = parse_tlv_stream(&data)?;
+
+    let (signature_records, records): (Vec<_>, Vec<_>) = records
+        .into_iter()
+        .partition(|r| SIGNATURE_TLV_RANGE.contains(&r.record_type));
+
+    let mut offer = Bolt12Offer {
+        chains: Vec::new(),
+        metadata: None,
+        currency: None,
+        amount: None,
+        description: None,
+        features: Vec::new(),
+        absolute_expiry: None,
+        paths: Vec::new(),
+        issuer: None,
+        quantity_max: None,
+        node_id: None,
+        records: Vec::new(),
+    };
+
+    for record in &records {
+        let value = &record.value;
+        match record.record_type {
+            OFFER_CHAINS => {
+                if value.is_empty() || value.len() % 32 != 0 {
+                    return Err(malformed("offer_chains must be a list of 32-byte hashes"));
+                }
+                offer.chains = value
+                    .chunks_exact(32)
+                    .map(|c| c.try_into().expect("chunk is 32 bytes"))
+                    .collect();
+            }
+            OFFER_METADATA => offer.metadata = Some(value.clone()),
+            OFFER_CURRENCY => {
+                if value.len() != 3 || !value.iter().all(u8::is_ascii_uppercase) {
+                    return Err(malformed("offer_currency must be a 3-letter ISO 4217 code"));
+                }
+                offer.currency = Some(String::from_utf8_lossy(value).into_owned());
+            }
+            OFFER_AMOUNT => offer.amount = Some(read_tu64(value)?),
+            OFFER_DESCRIPTION => offer.description = Some(read_utf8(value, "offer_description")?),
+            OFFER_FEATURES => offer.features = value.clone(),
+            OFFER_ABSOLUTE_EXPIRY => offer.absolute_expiry = Some(read_tu64(value)?),
+            OFFER_PATHS => offer.paths = read_blinded_paths(value)?,
+            OFFER_ISSUER => offer.issuer = Some(read_utf8(value, "offer_issuer")?),
+            OFFER_QUANTITY_MAX => offer.quantity_max = Some(read_tu64(value)?),
+            OFFER_ISSUER_ID => offer.node_id = Some(read_point(value, "offer_issuer_id")?),
+            t if t % 2 == 0 => {
+                return Err(malformed(&format!("unknown even TLV type {t}")));
+            }
+            _ => {}
+        }
+    }
+
+    if offer.currency.is_some() && offer.amount.is_none() {
+        return Err(malformed("offer_currency set without offer_amount"));
+    }
+    if offer.amount == Some(0) {
+        return Err(malformed("offer_amount must be greater than zero"));
+    }
+    if offer.description.is_none() && offer.amount.is_some() {
+        return Err(malformed(
+            "offer_description is required when offer_amount is set",
+        ));
+    }
+    if offer.node_id.is_none() && offer.paths.is_empty() {
+        return Err(malformed("offer must set offer_issuer_id or offer_paths"));
+    }
+
+    for record in &signature_records {
+        if record.record_type != SIGNATURE_TLV_TYPE {
+            continue;
+        }
+        let node_id = offer.node_id.as_ref().ok_or_else(|| {
+            Bolt12Error::InvalidSignature("signature present without offer_issuer_id".to_string())
+        })?;
+        verify_signature("offer", &records, &record.value, node_id)?;
+    }
+
+    offer.records = records;
+    Ok(offer)
+}
+
+/// Build a signed invoice_request message for an offer
+///
+/// The offer records are mirrored into the request, followed by fresh payer
+/// metadata, the requested amount and quantity and the payer id derived from
+/// `payer_key`. The request is signed over its merkle root with BIP-340.
+/// Sending the message over an onion message path is left to the caller.
+pub fn request_invoice_for_offer(
+    offer: &Bolt12Offer,
+    payer_key: &SecretKey,
+    amount_msat: Option<u64>,
+    quantity: Option<u64>,
+    now: u64,
+) -> Bolt12Result<Vec<u8>> {
+    if let Some(expiry) = offer.absolute_expiry.filter(|_| offer.is_expired(now)) {
+        return Err(Bolt12Error::Expired(expiry));
+    }
+
+    match (offer.amount_msat(), amount_msat) {
+        (None, None) => {
+            return Err(Bolt12Error::InvalidAmount(
+                "offer has no amount in msat; an amount must be supplied".to_string(),
+            ));
+        }
+        (_, Some(0)) => {
+            return Err(Bolt12Error::InvalidAmount(
+                "requested amount must be greater than zero".to_string(),
+            ));
+        }
+        (Some(min), Some(requested)) => {
+            let expected = min.saturating_mul(quantity.unwrap_or(1));
+            if requested < expected {
+                return Err(Bolt12Error::InvalidAmount(format!(
+                    "requested {requested} msat is below the offer amount of {expected} msat"
+                )));
+            }
+        }
+        _ => {}
+    }
+
+    let secp = Secp256k1::new();
+    let keypair = Keypair::from_secret_key(&secp, payer_key);
+    let payer_id = PublicKey::from_secret_key(&secp, payer_key).serialize();
+    let payer_metadata: [u8; 32] = rand::random();
+
+    let mut records = vec![encode_record(INVREQ_METADATA, &payer_metadata)];
+    records.extend(offer.records.iter().cloned());
+    if let Some(amount) = amount_msat {
+        records.push(encode_record(INVREQ_AMOUNT, &truncated_be(amount)));
+    }
+    if let Some(quantity) = quantity {
+        records.push(encode_record(INVREQ_QUANTITY, &truncated_be(quantity)));
+    }
+    records.push(encode_record(INVREQ_PAYER_ID, &payer_id));
+
+    let digest = signature_digest("invoice_request", &records);
+    let signature = secp.sign_schnorr_no_aux_rand(&Message::from_digest(digest), &keypair);
+
+    let mut message: Vec<u8> = records.iter().flat_map(|r| r.raw.clone()).collect();
+    message.extend(encode_record(SIGNATURE_TLV_TYPE, &signature.serialize()).raw);
+    Ok(message)
+}
+
+fn malformed(reason: &str) -> Bolt12Error {
+    Bolt12Error::MalformedTlv(reason.to_string())
+}
+
+/// Decode a checksum-less bech32 string with the expected human-readable part
+fn decode_bech32(s: &str, expected_hrp: &str) -> Bolt12Result<Vec<u8>> {
+    // `+` joins split strings and may be followed by whitespace
+    let mut joined = String::with_capacity(s.len());
+    let mut parts = s.split('+');
+    joined.push_str(parts.next().unwrap_or_default());
+    for part in parts {
+        let part = part.trim_start();
+        if part.is_empty() || joined.is_empty() {
+            return Err(Bolt12Error::InvalidEncoding(
+                "empty segment around '+'".to_string(),
+            ));
+        }
+        joined.push_str(part);
+    }
+
+    let has_lower = joined.bytes().any(|b| b.is_ascii_lowercase());
+    let has_upper = joined.bytes().any(|b| b.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(Bolt12Error::InvalidEncoding("mixed case".to_string()));
+    }
+    let joined = joined.to_ascii_lowercase();
+
+    let (hrp, data) = joined
+        .rsplit_once('1')
+        .ok_or_else(|| Bolt12Error::InvalidEncoding("missing separator".to_string()))?;
+    if hrp != expected_hrp {
+        return Err(Bolt12Error::InvalidEncoding(format!(
+            "expected prefix '{expected_hrp}', found '{hrp}'"
+        )));
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() * 5 / 8);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for c in data.bytes() {
+        let value = BECH32_CHARSET.iter().position(|&x| x == c).ok_or_else(|| {
+            Bolt12Error::InvalidEncoding(format!("invalid character '{}'", c as char))
+        })?;
+        acc = (acc << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        return Err(Bolt12Error::InvalidEncoding("invalid padding".to_string()));
+    }
+
+    Ok(bytes)
+}
+
+/// Split a TLV stream into records, enforcing ascending and canonical encoding
+fn parse_tlv_stream(data: &[u8]) -> Bolt12Result<Vec<TlvRecord>> {
+    let mut records = Vec::new();
+    let mut pos = 0;
+    let mut last_type: Option<u64> = None;
+
+    while pos < data.len() {
+        let start = pos;
+        let record_type = read_bigsize(data, &mut pos)?;
+        let length = read_bigsize(data, &mut pos)?;
+
+        if last_type.is_some_and(|last| record_type <= last) {
+            return Err(malformed(&format!(
+                "TLV type {record_type} is not in ascending order"
+            )));
+        }
+        last_type = Some(record_type);
+
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|len| pos.checked_add(len))
+            .filter(|end| *end <= data.len())
+            .ok_or_else(|| malformed(&format!("TLV type {record_type} length exceeds stream")))?;
+
+        records.push(TlvRecord {
+            record_type,
+            value: data[pos..end].to_vec(),
+            raw: data[start..end].to_vec(),
+        });
+        pos = end;
+    }
+
+    Ok(records)
+}
+
+/// BOLT-01 BigSize decoding with canonical-encoding checks
+fn read_bigsize(data: &[u8], pos: &mut usize) -> Bolt12Result<u64> {
+    let prefix = *data
+        .get(*pos)
+        .ok_or_else(|| malformed("unexpected end of BigSize"))?;
+    *pos += 1;
+
+    let (width, min) = match prefix {
+        0xfd => (2, 0xfd),
+        0xfe => (4, 0x1_0000),
+        0xff => (8, 0x1_0000_0000),
+        _ => return Ok(u64::from(prefix)),
+    };
+    let bytes = data
+        .get(*pos..*pos + width)
+        .ok_or_else(|| malformed("unexpected end of BigSize"))?;
+    *pos += width;
+
+    let value = bytes.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+    if value < min {
+        return Err(malformed("non-canonical BigSize encoding"));
+    }
+    Ok(value)
+}
+
+/// Decode a BOLT-01 `tu64` (big-endian, no leading zeros)
+fn read_tu64(value: &[u8]) -> Bolt12Result<u64> {
+    if value.len() > 8 || value.first() == Some(&0) {
+        return Err(malformed("non-minimal tu64 encoding"));
+    }
+    Ok(value.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b)))
+}
+
+fn read_utf8(value: &[u8], field: &str) -> Bolt12Result<String> {
+    String::from_utf8(value.to_vec()).map_err(|_| malformed(&format!("{field} is not valid UTF-8")))
+}
+
+fn read_point(value: &[u8], field: &str) -> Bolt12Result<LightningPublicKey> {
+    PublicKey::from_slice(value)
+        .map(|key| LightningPublicKey {
+            bytes: key.serialize(),
+        })
+        .map_err(|e| malformed(&format!("{field} is not a valid point: {e}")))
+}
+
+fn read_blinded_paths(value: &[u8]) -> Bolt12Result<Vec<BlindedPath>> {
+    let mut paths = Vec::new();
+    let mut pos = 0;
+
+    let take = |pos: &mut usize, len: usize| -> Bolt12Result<Vec<u8>> {
+        let bytes = value
+            .get(*pos..*pos + len)
+            .ok_or_else(|| malformed("truncated offer_paths"))?;
+        *pos += len;
+        Ok(bytes.to_vec())
+    };
+
+    while pos < value.len() {
+        let introduction_node = match value[pos] {
+            0 | 1 => {
+                let bytes = take(&mut pos, 9)?;
+                IntroductionNode::DirectedShortChannelId {
+                    direction: bytes[0],
+                    scid: u64::from_be_bytes(bytes[1..].try_into().expect("8 bytes")),
+                }
+            }
+            2 | 3 => IntroductionNode::NodeId(to_point_bytes(&take(&mut pos, 33)?)?),
+            other => {
+                return Err(malformed(&format!(
+                    "invalid introduction node prefix {other:#04x}"
+                )))
+            }
+        };
+        let blinding_point = to_point_bytes(&take(&mut pos, 33)?)?;
+        let num_hops = take(&mut pos, 1)?[0];
+        if num_hops == 0 {
+            return Err(malformed("blinded path has no hops"));
+        }
+
+        let mut hops = Vec::with_capacity(num_hops as usize);
+        for _ in 0..num_hops {
+            let blinded_node_id = to_point_bytes(&take(&mut pos, 33)?)?;
+            let len_bytes = take(&mut pos, 2)?;
+            let enclen = u16::from_be_bytes([len_bytes[0], len_bytes[1]]) as usize;
+            hops.push(BlindedHop {
+                blinded_node_id,
+                encrypted_recipient_data: take(&mut pos, enclen)?,
+            });
+        }
+
+        paths.push(BlindedPath {
+            introduction_node,
+            blinding_point,
+            hops,
+        });
+    }
+
+    Ok(paths)
+}
+
+fn to_point_bytes(bytes: &[u8]) -> Bolt12Result<[u8; 33]> {
+    read_point(bytes, "blinded path point").map(|key| key.bytes)
+}
+
+/// Verify a BIP-340 signature record against the merkle root of `records`
+fn verify_signature(
+    message_name: &str,
+    records: &[TlvRecord],
+    signature: &[u8],
+    signer: &LightningPublicKey,
+) -> Bolt12Result<()> {
+    let signature = schnorr::Signature::from_slice(signature)
+        .map_err(|e| Bolt12Error::InvalidSignature(format!("malformed signature: {e}")))?;
+    let pubkey = PublicKey::from_slice(&signer.bytes)
+        .map_err(|e| Bolt12Error::InvalidSignature(format!("invalid signing key: {e}")))?;
+    let (xonly, _) = pubkey.x_only_public_key();
+
+    let digest = signature_digest(message_name, records);
+    Secp256k1::verification_only()
+        .verify_schnorr(&signature, &Message::from_digest(digest), &xonly)
+        .map_err(|e| Bolt12Error::InvalidSignature(e.to_string()))
+}
+
+/// Digest signed by a BOLT12 `signature` record
+fn signature_digest(message_name: &str, records: &[TlvRecord]) -> [u8; 32] {
+    let tag = format!("lightning{message_name}signature");
+    tagged_hash(tag.as_bytes(), &merkle_root(records))
+}
+
+/// BOLT12 merkle root over the non-signature TLV records
+///
+/// Each record contributes `H("LnBranch", leaf, nonce)` where the leaf
+/// commits to the full record and the nonce to its type, keyed by the first
+/// record. Leaves are then paired level by level, lowest-order first.
+fn merkle_root(records: &[TlvRecord]) -> [u8; 32] {
+    let Some(first) = records.first() else {
+        return [0u8; 32];
+    };
+    let nonce_tag = [b"LnNonce".as_slice(), &first.raw].concat();
+
+    let mut leaves: Vec<[u8; 32]> = records
+        .iter()
+        .map(|record| {
+            let mut type_bytes = Vec::new();
+            write_bigsize(&mut type_bytes, record.record_type);
+            branch_hash(
+                tagged_hash(b"LnLeaf", &record.raw),
+                tagged_hash(&nonce_tag, &type_bytes),
+            )
+        })
+        .collect();
+
+    let mut offset = 1;
+    while offset < leaves.len() {
+        let step = offset * 2;
+        let mut i = 0;
+        while i + offset < leaves.len() {
+            leaves[i] = branch_hash(leaves[i], leaves[i + offset]);
+            i += step;
+        }
+        offset = step;
+    }
+
+    leaves[0]
+}
+
+fn branch_hash(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (lesser, greater) = if a <= b { (a, b) } else { (b, a) };
+    tagged_hash(b"LnBranch", &[lesser, greater].concat())
+}
+
+fn encode_record(record_type: u64, value: &[u8]) -> TlvRecord {
+    let mut raw = Vec::new();
+    write_bigsize(&mut raw, record_type);
+    write_bigsize(&mut raw, value.len() as u64);
+    raw.extend_from_slice(value);
+    TlvRecord {
+        record_type,
+        value: value.to_vec(),
+        raw,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_bech32(hrp: &str, data: &[u8]) -> String {
+        let mut out = format!("{hrp}1");
+        let mut acc: u32 = 0;
+        let mut bits = 0;
+        for b in data {
+            acc = (acc << 8) | u32::from(*b);
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                out.push(BECH32_CHARSET[((acc >> bits) & 31) as usize] as char);
+            }
+        }
+        if bits > 0 {
+            out.push(BECH32_CHARSET[((acc << (5 - bits)) & 31) as usize] as char);
+        }
+        out
+    }
+
+    fn issuer_key() -> SecretKey {
+        SecretKey::from_slice(&[0x21; 32]).unwrap()
+    }
+
+    fn offer_records(expiry: Option<u64>) -> Vec<TlvRecord> {
+        let issuer_id = PublicKey::from_secret_key(&Secp256k1::new(), &issuer_key()).serialize();
+        let mut records = vec![
+            encode_record(OFFER_AMOUNT, &truncated_be(50_000)),
+            encode_record(OFFER_DESCRIPTION, b"coffee"),
+        ];
+        if let Some(expiry) = expiry {
+            records.push(encode_record(OFFER_ABSOLUTE_EXPIRY, &truncated_be(expiry)));
+        }
+        records.push(encode_record(OFFER_ISSUER, b"Anya"));
+        records.push(encode_record(OFFER_ISSUER_ID, &issuer_id));
+        records
+    }
+
+    fn encode_offer(records: &[TlvRecord]) -> String {
+        let data: Vec<u8> = records.iter().flat_map(|r| r.raw.clone()).collect();
+        encode_bech32(OFFER_HRP, &data)
+    }
+
+    fn sign_records(records: &[TlvRecord]) -> TlvRecord {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &issuer_key());
+        let digest = signature_digest("offer", records);
+        let sig = secp.sign_schnorr_no_aux_rand(&Message::from_digest(digest), &keypair);
+        encode_record(SIGNATURE_TLV_TYPE, &sig.serialize())
+    }
+
+    #[test]
+    fn test_parse_offer_fields() {
+        let offer = parse_offer(&encode_offer(&offer_records(Some(1_700_000_000)))).unwrap();
+
+        assert_eq!(offer.amount_msat(), Some(50_000));
+        assert_eq!(offer.description.as_deref(), Some("coffee"));
+        assert_eq!(offer.issuer.as_deref(), Some("Anya"));
+        assert_eq!(offer.absolute_expiry, Some(1_700_000_000));
+        assert!(offer.node_id.is_some());
+        assert!(offer.paths.is_empty());
+    }
+
+    #[test]
+    fn test_parse_offer_with_split_string() {
+        let encoded = encode_offer(&offer_records(None));
+        let (head, tail) = encoded.split_at(20);
+        let offer = parse_offer(&format!("{head}+\n  {tail}")).unwrap();
+        assert_eq!(offer.description.as_deref(), Some("coffee"));
+    }
+
+    #[test]
+    fn test_offer_expiry() {
+        let offer = parse_offer(&encode_offer(&offer_records(Some(1_000)))).unwrap();
+        assert!(!offer.is_expired(1_000));
+        assert!(offer.is_expired(1_001));
+
+        let offer = parse_offer(&encode_offer(&offer_records(None))).unwrap();
+        assert!(!offer.is_expired(u64::MAX));
+    }
+
+    #[test]
+    fn test_parse_offer_rejects_wrong_hrp() {
+        let data: Vec<u8> = offer_records(None)
+            .iter()
+            .flat_map(|r| r.raw.clone())
+            .collect();
+        let result = parse_offer(&encode_bech32("lni", &data));
+        assert!(matches!(result, Err(Bolt12Error::InvalidEncoding(_))));
+    }
+
+    #[test]
+    fn test_parse_offer_rejects_unordered_tlv() {
+        let mut records = offer_records(None);
+        records.swap(0, 1);
+        let result = parse_offer(&encode_offer(&records));
+        assert!(matches!(result, Err(Bolt12Error::MalformedTlv(_))));
+    }
+
+    #[test]
+    fn test_parse_offer_rejects_truncated_tlv() {
+        let mut data: Vec<u8> = offer_records(None)
+            .iter()
+            .flat_map(|r| r.raw.clone())
+            .collect();
+        data.truncate(data.len() - 1);
+        let result = parse_offer(&encode_bech32(OFFER_HRP, &data));
+        assert!(matches!(result, Err(Bolt12Error::MalformedTlv(_))));
+    }
+
+    #[test]
+    fn test_parse_offer_rejects_unknown_even_type() {
+        let mut records = offer_records(None);
+        records.push(encode_record(24, b"x"));
+        let result = parse_offer(&encode_offer(&records));
+        assert!(matches!(result, Err(Bolt12Error::MalformedTlv(_))));
+    }
+
+    #[test]
+    fn test_parse_offer_verifies_signature() {
+        let records = offer_records(None);
+        let mut signed = records.clone();
+        signed.push(sign_records(&records));
+        assert!(parse_offer(&encode_offer(&signed)).is_ok());
+
+        let mut tampered = records.clone();
+        tampered[1] = encode_record(OFFER_DESCRIPTION, b"tea");
+        tampered.push(sign_records(&records));
+        let result = parse_offer(&encode_offer(&tampered));
+        assert!(matches!(result, Err(Bolt12Error::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_request_invoice_for_offer() {
+        let offer = parse_offer(&encode_offer(&offer_records(Some(2_000)))).unwrap();
+        let payer_key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+
+        let message =
+            request_invoice_for_offer(&offer, &payer_key, Some(50_000), None, 1_000).unwrap();
+        let records = parse_tlv_stream(&message).unwrap();
+        assert_eq!(records.first().unwrap().record_type, INVREQ_METADATA);
+        assert_eq!(records.last().unwrap().record_type, SIGNATURE_TLV_TYPE);
+
+        let (signature, unsigned): (Vec<_>, Vec<_>) = records
+            .into_iter()
+            .partition(|r| r.record_type == SIGNATURE_TLV_TYPE);
+        let payer_id = unsigned
+            .iter()
+            .find(|r| r.record_type == INVREQ_PAYER_ID)
+            .unwrap();
+        let signer = LightningPublicKey {
+            bytes: payer_id.value.clone().try_into().unwrap(),
+        };
+        assert!(
+            verify_signature("invoice_request", &unsigned, &signature[0].value, &signer).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_request_invoice_rejects_expired_or_short_amount() {
+        let offer = parse_offer(&encode_offer(&offer_records(Some(2_000)))).unwrap();
+        let payer_key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+
+        let result = request_invoice_for_offer(&offer, &payer_key, None, None, 3_000);
+        assert!(matches!(result, Err(Bolt12Error::Expired(2_000))));
+
+        let result = request_invoice_for_offer(&offer, &payer_key, Some(1_000), None, 1_000);
+        assert!(matches!(result, Err(Bolt12Error::InvalidAmount(_))));
+    }
+}
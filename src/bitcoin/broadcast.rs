@@ -0,0 +1,440 @@
+// [AIR-3][AIS-3][BPC-3] Transaction broadcast retry queue
+//
+// Pending broadcasts are announced to peers with `inv` until enough of them
+// have fetched the transaction with `getdata`. Failed rounds are retried with
+// exponential backoff. The queue is persisted in the data directory so that
+// broadcasts survive a restart.
+
+use crate::core::metrics::PrometheusMetrics;
+use crate::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use bitcoin::{Transaction, Txid};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex, RwLock};
+
+/// File in the data directory holding the pending broadcasts
+pub const BROADCAST_QUEUE_FILE: &str = "broadcast_queue.json";
+
+/// Gauge reporting the number of pending broadcasts
+pub const QUEUE_DEPTH_METRIC: &str = "broadcast_queue_depth";
+
+/// [AIR-3][BPC-3] Retry behaviour of the broadcast queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastConfig {
+    /// Number of distinct peers that must request the transaction
+    pub required_acks: usize,
+    /// Delay before the first retry; doubled after every failed attempt
+    pub initial_backoff: Duration,
+    /// Upper bound on the retry delay
+    pub max_backoff: Duration,
+    /// Attempts after which the broadcast is abandoned
+    pub max_retries: u32,
+}
+
+impl Default for BroadcastConfig {
+    fn default() -> Self {
+        Self {
+            required_acks: 2,
+            initial_backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(600),
+            max_retries: 10,
+        }
+    }
+}
+
+impl BroadcastConfig {
+    /// Delay before the next attempt after `attempts` failed attempts
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        exponential_backoff(self.initial_backoff, self.max_backoff, attempts)
+    }
+}
+
+/// Delay after `attempts` failed attempts: `initial`, doubled after every
+/// further failure, capped at `max`
+pub fn exponential_backoff(initial: Duration, max: Duration, attempts: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+    initial.saturating_mul(factor).min(max)
+}
+
+/// [AIR-3][BPC-3] A connected peer transactions can be relayed to
+#[async_trait]
+pub trait TxBroadcastPeer: Send + Sync {
+    /// Human-readable peer identifier (usually its socket address)
+    fn id(&self) -> String;
+
+    /// Announce the transaction with `inv` and serve it on `getdata`
+    ///
+    /// Returns `true` if the peer requested the transaction.
+    async fn announce_transaction(&self, tx: &Transaction) -> AnyaResult<bool>;
+}
+
+/// [AIR-3][BPC-3] Outcome of a queued broadcast
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastEvent {
+    /// Enough peers requested the transaction
+    Acknowledged { txid: Txid, peers: usize },
+    /// The transaction was dropped after `attempts` attempts
+    GaveUp { txid: Txid, attempts: u32 },
+}
+
+/// [AIR-3][BPC-3] Broadcast waiting for peer acknowledgement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingBroadcast {
+    /// Transaction to relay
+    pub tx: Transaction,
+    /// Attempts made so far
+    pub attempts: u32,
+    /// Peers that have requested the transaction
+    pub acknowledged_by: BTreeSet<String>,
+    /// Earliest time of the next attempt
+    pub next_attempt: DateTime<Utc>,
+}
+
+/// [AIR-3][AIS-3][BPC-3] Persistent broadcast retry queue
+pub struct BroadcastQueue {
+    config: BroadcastConfig,
+    path: PathBuf,
+    pending: Mutex<BTreeMap<Txid, PendingBroadcast>>,
+    peers: RwLock<Vec<Arc<dyn TxBroadcastPeer>>>,
+    metrics: Option<Arc<RwLock<PrometheusMetrics>>>,
+    event_sender: mpsc::UnboundedSender<BroadcastEvent>,
+    event_receiver: RwLock<Option<mpsc::UnboundedReceiver<BroadcastEvent>>>,
+}
+
+impl BroadcastQueue {
+    /// Open the queue stored in `datadir`, reloading pending broadcasts
+    pub fn load(datadir: impl AsRef<Path>, config: BroadcastConfig) -> AnyaResult<Self> {
+        let path = datadir.as_ref().join(BROADCAST_QUEUE_FILE);
+        let pending = if path.exists() {
+            let data = std::fs::read_to_string(&path).map_err(|e| io_error(&path, e))?;
+            let entries: Vec<PendingBroadcast> = serde_json::from_str(&data)?;
+            entries
+                .into_iter()
+                .map(|entry| (entry.tx.compute_txid(), entry))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+        let (event_sender, event_receiver) = mpsc::unbounded_channel();
+
+        Ok(Self {
+            config,
+            path,
+            pending: Mutex::new(pending),
+            peers: RwLock::new(Vec::new()),
+            metrics: None,
+            event_sender,
+            event_receiver: RwLock::new(Some(event_receiver)),
+        })
+    }
+
+    /// Report the queue depth through `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<RwLock<PrometheusMetrics>>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Register a peer broadcasts are announced to
+    pub async fn add_peer(&self, peer: Arc<dyn TxBroadcastPeer>) {
+        self.peers.write().await.push(peer);
+    }
+
+    /// Take the receiver for broadcast events
+    pub async fn take_event_receiver(&self) -> Option<mpsc::UnboundedReceiver<BroadcastEvent>> {
+        self.event_receiver.write().await.take()
+    }
+
+    /// Queue a transaction for broadcast
+    ///
+    /// Returns `false` if the transaction is already queued.
+    pub async fn enqueue(&self, tx: Transaction) -> AnyaResult<bool> {
+        let mut pending = self.pending.lock().await;
+        let txid = tx.compute_txid();
+        if pending.contains_key(&txid) {
+            return Ok(false);
+        }
+        pending.insert(
+            txid,
+            PendingBroadcast {
+                tx,
+                attempts: 0,
+                acknowledged_by: BTreeSet::new(),
+                next_attempt: Utc::now(),
+            },
+        );
+        self.persist(&pending).await?;
+        Ok(true)
+    }
+
+    /// Number of pending broadcasts
+    pub async fn depth(&self) -> usize {
+        self.pending.lock().await.len()
+    }
+
+    /// Snapshot of the pending broadcasts
+    pub async fn pending(&self) -> Vec<PendingBroadcast> {
+        self.pending.lock().await.values().cloned().collect()
+    }
+
+    /// Run one broadcast round for every entry that is due
+    ///
+    /// Each due transaction is announced to the peers that have not yet
+    /// requested it. Entries reaching `required_acks` are removed with an
+    /// `Acknowledged` event; others are rescheduled with backoff, or removed
+    /// with a `GaveUp` event after `max_retries` attempts.
+    pub async fn process_due(&self) -> AnyaResult<()> {
+        let now = Utc::now();
+        let due: Vec<(Txid, PendingBroadcast)> = self
+            .pending
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, entry)| entry.next_attempt <= now)
+            .map(|(txid, entry)| (*txid, entry.clone()))
+            .collect();
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        let peers = self.peers.read().await.clone();
+        let mut acks = Vec::with_capacity(due.len());
+        for (txid, entry) in &due {
+            let mut acknowledged = BTreeSet::new();
+            for peer in &peers {
+                let id = peer.id();
+                if entry.acknowledged_by.contains(&id) {
+                    continue;
+                }
+                match peer.announce_transaction(&entry.tx).await {
+                    Ok(true) => {
+                        acknowledged.insert(id);
+                    }
+                    Ok(false) => log::debug!("Peer {id} did not request transaction {txid}"),
+                    Err(e) => log::debug!("Announcing {txid} to peer {id} failed: {e}"),
+                }
+            }
+            acks.push((*txid, acknowledged));
+        }
+
+        let mut pending = self.pending.lock().await;
+        for (txid, acknowledged) in acks {
+            // The entry may have been removed while peers were contacted
+            let Some(entry) = pending.get_mut(&txid) else {
+                continue;
+            };
+            entry.attempts += 1;
+            entry.acknowledged_by.extend(acknowledged);
+
+            if entry.acknowledged_by.len() >= self.config.required_acks {
+                let peers = entry.acknowledged_by.len();
+                pending.remove(&txid);
+                log::info!("Transaction {txid} relayed to {peers} peers");
+                self.emit_event(BroadcastEvent::Acknowledged { txid, peers });
+            } else if entry.attempts >= self.config.max_retries {
+                let attempts = entry.attempts;
+                pending.remove(&txid);
+                log::warn!("Giving up broadcasting {txid} after {attempts} attempts");
+                self.emit_event(BroadcastEvent::GaveUp { txid, attempts });
+            } else {
+                let delay = self.config.backoff(entry.attempts);
+                entry.next_attempt = now
+                    + chrono::Duration::from_std(delay)
+                        .map_err(|e| AnyaError::Bitcoin(format!("invalid backoff: {e}")))?;
+            }
+        }
+        self.persist(&pending).await
+    }
+
+    /// Process due broadcasts every `poll_interval` in the background
+    pub fn spawn(self: Arc<Self>, poll_interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(poll_interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.process_due().await {
+                    log::warn!("Broadcast queue round failed: {e}");
+                }
+            }
+        })
+    }
+
+    /// Write the queue to disk and update the depth gauge
+    async fn persist(&self, pending: &BTreeMap<Txid, PendingBroadcast>) -> AnyaResult<()> {
+        let entries: Vec<&PendingBroadcast> = pending.values().collect();
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&entries)?)
+            .map_err(|e| io_error(&tmp, e))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| io_error(&self.path, e))?;
+
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .write()
+                .await
+                .set_gauge(QUEUE_DEPTH_METRIC, pending.len() as f64);
+        }
+        Ok(())
+    }
+
+    fn emit_event(&self, event: BroadcastEvent) {
+        let _ = self.event_sender.send(event);
+    }
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> AnyaError {
+    AnyaError::System(format!("Broadcast queue file {}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+
+    struct MockPeer {
+        id: &'static str,
+        requests: bool,
+    }
+
+    #[async_trait]
+    impl TxBroadcastPeer for MockPeer {
+        fn id(&self) -> String {
+            self.id.to_string()
+        }
+
+        async fn announce_transaction(&self, _tx: &Transaction) -> AnyaResult<bool> {
+            Ok(self.requests)
+        }
+    }
+
+    fn transaction(lock_time: u32) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::from_consensus(lock_time),
+            input: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    fn immediate_retries(max_retries: u32) -> BroadcastConfig {
+        BroadcastConfig {
+            required_acks: 2,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            max_retries,
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let config = BroadcastConfig::default();
+        assert_eq!(config.backoff(1), Duration::from_secs(5));
+        assert_eq!(config.backoff(2), Duration::from_secs(10));
+        assert_eq!(config.backoff(4), Duration::from_secs(40));
+        assert_eq!(config.backoff(30), Duration::from_secs(600));
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_deduplicates_and_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let metrics = Arc::new(RwLock::new(PrometheusMetrics::new()));
+        let queue = BroadcastQueue::load(dir.path(), BroadcastConfig::default())
+            .unwrap()
+            .with_metrics(metrics.clone());
+
+        assert!(queue.enqueue(transaction(1)).await.unwrap());
+        assert!(!queue.enqueue(transaction(1)).await.unwrap());
+        assert!(queue.enqueue(transaction(2)).await.unwrap());
+        assert_eq!(queue.depth().await, 2);
+        assert_eq!(
+            metrics.read().await.get_gauge(QUEUE_DEPTH_METRIC),
+            Some(2.0)
+        );
+        drop(queue);
+
+        let reloaded = BroadcastQueue::load(dir.path(), BroadcastConfig::default()).unwrap();
+        assert_eq!(reloaded.depth().await, 2);
+        assert!(!reloaded.enqueue(transaction(2)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_peers_acknowledge() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = BroadcastQueue::load(dir.path(), immediate_retries(5)).unwrap();
+        let mut events = queue.take_event_receiver().await.unwrap();
+        let tx = transaction(1);
+        queue.enqueue(tx.clone()).await.unwrap();
+
+        // No peers connected yet: the broadcast stays queued
+        queue.process_due().await.unwrap();
+        assert_eq!(queue.pending().await[0].attempts, 1);
+
+        queue
+            .add_peer(Arc::new(MockPeer {
+                id: "a",
+                requests: true,
+            }))
+            .await;
+        queue.process_due().await.unwrap();
+        assert_eq!(queue.depth().await, 1);
+
+        queue
+            .add_peer(Arc::new(MockPeer {
+                id: "b",
+                requests: true,
+            }))
+            .await;
+        queue.process_due().await.unwrap();
+        assert_eq!(queue.depth().await, 0);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            BroadcastEvent::Acknowledged {
+                txid: tx.compute_txid(),
+                peers: 2
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = BroadcastQueue::load(dir.path(), immediate_retries(3)).unwrap();
+        let mut events = queue.take_event_receiver().await.unwrap();
+        queue
+            .add_peer(Arc::new(MockPeer {
+                id: "a",
+                requests: false,
+            }))
+            .await;
+        let tx = transaction(1);
+        queue.enqueue(tx.clone()).await.unwrap();
+
+        for _ in 0..3 {
+            queue.process_due().await.unwrap();
+        }
+        assert_eq!(queue.depth().await, 0);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            BroadcastEvent::GaveUp {
+                txid: tx.compute_txid(),
+                attempts: 3
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_entry_waits_for_backoff() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = BroadcastQueue::load(dir.path(), BroadcastConfig::default()).unwrap();
+        queue.enqueue(transaction(1)).await.unwrap();
+
+        queue.process_due().await.unwrap();
+        queue.process_due().await.unwrap();
+        // The second round is skipped until the 5s backoff has elapsed
+        assert_eq!(queue.pending().await[0].attempts, 1);
+    }
+}
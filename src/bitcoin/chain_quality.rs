@@ -0,0 +1,422 @@
+// [AIR-3][AIS-3][BPC-3] Chain-quality monitoring
+//
+// Looks for signs of a majority-hashrate attack in the stream of
+// `ChainEvent`s: reorgs deeper than normal chain competition produces, a
+// high share of blocks being orphaned, and a sudden drop in the hashrate
+// implied by block difficulty and timestamps. Each signal is published as a
+// Prometheus gauge, and crossing a threshold sends a `ChainAnomalyDetected`
+// event to subscribers. Stale-rate and hashrate alerts fire once when the
+// signal crosses its threshold, not again on every block until it recovers.
+
+use crate::bitcoin::confirmations::ChainEvent;
+use crate::core::PrometheusMetrics;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// [AIR-3][BPC-3] Thresholds for [`ChainQualityMonitor`]
+#[derive(Debug, Clone)]
+pub struct ChainQualityConfig {
+    /// Reorgs deeper than this many blocks raise a high-severity alert
+    pub max_reorg_depth: u32,
+    /// Reorgs at least this deep raise a medium-severity alert
+    pub reorg_warning_depth: u32,
+    /// Connected blocks over which the stale-block rate is measured
+    pub stale_window: usize,
+    /// Orphaned blocks per connected block above which an alert is raised
+    pub max_stale_rate: f64,
+    /// Recent blocks whose implied hashrate is compared with the baseline
+    pub hashrate_window: usize,
+    /// Blocks making up the baseline implied hashrate
+    pub hashrate_baseline: usize,
+    /// Recent to baseline hashrate ratio below which an alert is raised;
+    /// below half of it the alert is high severity
+    pub min_hashrate_ratio: f64,
+}
+
+impl Default for ChainQualityConfig {
+    fn default() -> Self {
+        Self {
+            max_reorg_depth: 6,
+            reorg_warning_depth: 3,
+            stale_window: 144,
+            max_stale_rate: 0.05,
+            hashrate_window: 12,
+            hashrate_baseline: 144,
+            min_hashrate_ratio: 0.5,
+        }
+    }
+}
+
+/// [AIR-3][BPC-3] What looks wrong with the chain
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnomalyKind {
+    /// `depth` blocks were disconnected before the chain advanced again
+    DeepReorg { depth: u32 },
+    /// Share of recent blocks that were orphaned
+    StaleRate { rate: f64 },
+    /// Recent implied hashrate as a fraction of the baseline
+    HashrateDrop { ratio: f64 },
+}
+
+impl AnomalyKind {
+    /// Label used for the anomaly counter
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnomalyKind::DeepReorg { .. } => "deep_reorg",
+            AnomalyKind::StaleRate { .. } => "stale_rate",
+            AnomalyKind::HashrateDrop { .. } => "hashrate_drop",
+        }
+    }
+}
+
+/// [AIR-3][BPC-3] How urgently an anomaly needs attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnomalySeverity {
+    Medium,
+    High,
+}
+
+/// [AIR-3][BPC-3] Notification sent to [`ChainQualityMonitor::subscribe`] receivers
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainQualityEvent {
+    ChainAnomalyDetected {
+        kind: AnomalyKind,
+        severity: AnomalySeverity,
+    },
+}
+
+/// [AIR-3][AIS-3][BPC-3] Tracks reorg depth, stale-block rate and implied hashrate
+pub struct ChainQualityMonitor {
+    config: ChainQualityConfig,
+    metrics: Option<Arc<Mutex<PrometheusMetrics>>>,
+    subscribers: Vec<mpsc::UnboundedSender<ChainQualityEvent>>,
+    /// Blocks disconnected since the last connected block
+    pending_reorg: u32,
+    deepest_reorg: u32,
+    /// Blocks orphaned just before each recently connected block
+    stale: VecDeque<u32>,
+    /// Timestamp and difficulty of the most recent active-chain blocks
+    headers: VecDeque<(u32, f64)>,
+    stale_alerting: bool,
+    hashrate_alerting: bool,
+}
+
+impl ChainQualityMonitor {
+    pub fn new(config: ChainQualityConfig) -> Self {
+        Self {
+            config,
+            metrics: None,
+            subscribers: Vec::new(),
+            pending_reorg: 0,
+            deepest_reorg: 0,
+            stale: VecDeque::new(),
+            headers: VecDeque::new(),
+            stale_alerting: false,
+            hashrate_alerting: false,
+        }
+    }
+
+    /// Publish the chain-quality gauges to `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<Mutex<PrometheusMetrics>>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Receive anomalies detected from now on
+    pub fn subscribe(&mut self) -> mpsc::UnboundedReceiver<ChainQualityEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Deepest reorg seen so far
+    pub fn deepest_reorg(&self) -> u32 {
+        self.deepest_reorg
+    }
+
+    /// Orphaned blocks per connected block over the stale window
+    pub fn stale_rate(&self) -> f64 {
+        if self.stale.is_empty() {
+            return 0.0;
+        }
+        self.stale.iter().sum::<u32>() as f64 / self.stale.len() as f64
+    }
+
+    /// Recent implied hashrate as a fraction of the baseline, once enough
+    /// blocks have been seen
+    pub fn hashrate_ratio(&self) -> Option<f64> {
+        let window = self.config.hashrate_window;
+        if window < 2 || self.headers.len() < self.config.hashrate_baseline.max(window) {
+            return None;
+        }
+        let recent = implied_hashrate(self.headers.range(self.headers.len() - window..))?;
+        let baseline = implied_hashrate(self.headers.iter())?;
+        Some(recent / baseline)
+    }
+
+    pub fn handle_event(&mut self, event: &ChainEvent) {
+        match event {
+            ChainEvent::BlockDisconnected { .. } => {
+                self.pending_reorg += 1;
+                self.headers.pop_back();
+            }
+            ChainEvent::BlockConnected { block, .. } => {
+                let orphaned = std::mem::take(&mut self.pending_reorg);
+                if orphaned > 0 {
+                    self.finish_reorg(orphaned);
+                }
+
+                self.stale.push_back(orphaned);
+                while self.stale.len() > self.config.stale_window {
+                    self.stale.pop_front();
+                }
+                self.check_stale_rate();
+
+                self.headers
+                    .push_back((block.header.time, block.header.difficulty_float()));
+                while self.headers.len() > self.config.hashrate_baseline.max(1) {
+                    self.headers.pop_front();
+                }
+                self.check_hashrate();
+            }
+        }
+    }
+
+    /// Handle chain events from `events` in the background
+    pub fn spawn(
+        monitor: Arc<Mutex<Self>>,
+        mut events: mpsc::UnboundedReceiver<ChainEvent>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                monitor
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .handle_event(&event);
+            }
+        })
+    }
+
+    fn finish_reorg(&mut self, depth: u32) {
+        self.deepest_reorg = self.deepest_reorg.max(depth);
+        self.set_gauge("chain_last_reorg_depth", depth as f64);
+        self.set_gauge("chain_max_reorg_depth", self.deepest_reorg as f64);
+
+        let severity = if depth > self.config.max_reorg_depth {
+            AnomalySeverity::High
+        } else if depth >= self.config.reorg_warning_depth {
+            AnomalySeverity::Medium
+        } else {
+            return;
+        };
+        self.notify(AnomalyKind::DeepReorg { depth }, severity);
+    }
+
+    fn check_stale_rate(&mut self) {
+        let rate = self.stale_rate();
+        self.set_gauge("chain_stale_block_rate", rate);
+
+        // A single orphan in a short sample is not a meaningful rate
+        let anomalous =
+            self.stale.len() >= self.config.stale_window && rate > self.config.max_stale_rate;
+        if anomalous && !self.stale_alerting {
+            let severity = if rate > self.config.max_stale_rate * 2.0 {
+                AnomalySeverity::High
+            } else {
+                AnomalySeverity::Medium
+            };
+            self.notify(AnomalyKind::StaleRate { rate }, severity);
+        }
+        self.stale_alerting = anomalous;
+    }
+
+    fn check_hashrate(&mut self) {
+        let Some(ratio) = self.hashrate_ratio() else {
+            return;
+        };
+        self.set_gauge("chain_hashrate_ratio", ratio);
+
+        let anomalous = ratio < self.config.min_hashrate_ratio;
+        if anomalous && !self.hashrate_alerting {
+            let severity = if ratio < self.config.min_hashrate_ratio / 2.0 {
+                AnomalySeverity::High
+            } else {
+                AnomalySeverity::Medium
+            };
+            self.notify(AnomalyKind::HashrateDrop { ratio }, severity);
+        }
+        self.hashrate_alerting = anomalous;
+    }
+
+    fn notify(&mut self, kind: AnomalyKind, severity: AnomalySeverity) {
+        log::warn!("Chain anomaly detected: {kind:?} ({severity:?})");
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .increment_counter("chain_anomalies_detected", "kind", kind.label());
+        }
+        let event = ChainQualityEvent::ChainAnomalyDetected { kind, severity };
+        // Drop subscribers whose receiver is gone
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    fn set_gauge(&self, name: &str, value: f64) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .set_gauge(name, value);
+        }
+    }
+}
+
+/// Work per second implied by consecutive headers: the difficulty of every
+/// block after the first over the time they took
+fn implied_hashrate<'a>(mut headers: impl Iterator<Item = &'a (u32, f64)>) -> Option<f64> {
+    let &(first_time, _) = headers.next()?;
+    let (mut last_time, mut work) = (first_time, 0.0);
+    for &(time, difficulty) in headers {
+        last_time = time;
+        work += difficulty;
+    }
+    // Timestamps may go backwards; treat the span as at least one second
+    let elapsed = last_time.saturating_sub(first_time).max(1);
+    Some(work / elapsed as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::block::{Header, Version as BlockVersion};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Block, BlockHash, CompactTarget, TxMerkleNode};
+
+    fn block(nonce: u32, time: u32) -> Block {
+        Block {
+            header: Header {
+                version: BlockVersion::ONE,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time,
+                bits: CompactTarget::from_consensus(0x207fffff),
+                nonce,
+            },
+            txdata: Vec::new(),
+        }
+    }
+
+    fn connect(monitor: &mut ChainQualityMonitor, height: u32, time: u32) -> BlockHash {
+        let block = block(height, time);
+        let hash = block.block_hash();
+        monitor.handle_event(&ChainEvent::BlockConnected { block, height });
+        hash
+    }
+
+    #[test]
+    fn test_deep_reorg_raises_high_severity_alert() {
+        let metrics = Arc::new(Mutex::new(PrometheusMetrics::new()));
+        let mut monitor =
+            ChainQualityMonitor::new(ChainQualityConfig::default()).with_metrics(metrics.clone());
+        let mut alerts = monitor.subscribe();
+
+        let mut hashes: Vec<(BlockHash, u32)> = (100..110)
+            .map(|height| (connect(&mut monitor, height, height * 600), height))
+            .collect();
+
+        // A one-block reorg is ordinary chain competition
+        let (hash, height) = hashes.pop().unwrap();
+        monitor.handle_event(&ChainEvent::BlockDisconnected { hash, height });
+        hashes.push((connect(&mut monitor, height, height * 600 + 1), height));
+        assert!(alerts.try_recv().is_err());
+
+        // An attacker's private chain replaces nine blocks
+        for &(hash, height) in hashes[1..].iter().rev() {
+            monitor.handle_event(&ChainEvent::BlockDisconnected { hash, height });
+        }
+        assert!(
+            alerts.try_recv().is_err(),
+            "depth is known once the chain advances"
+        );
+        for height in 101..110 {
+            connect(&mut monitor, height, height * 600 + 2);
+        }
+
+        assert_eq!(
+            alerts.try_recv().unwrap(),
+            ChainQualityEvent::ChainAnomalyDetected {
+                kind: AnomalyKind::DeepReorg { depth: 9 },
+                severity: AnomalySeverity::High,
+            }
+        );
+        assert!(alerts.try_recv().is_err());
+        assert_eq!(monitor.deepest_reorg(), 9);
+
+        let metrics = metrics.lock().unwrap();
+        assert_eq!(metrics.get_gauge("chain_last_reorg_depth"), Some(9.0));
+        assert_eq!(metrics.get_gauge("chain_max_reorg_depth"), Some(9.0));
+        assert_eq!(metrics.get_counter("chain_anomalies_detected"), Some(1));
+    }
+
+    #[test]
+    fn test_stale_rate_and_hashrate_drop() {
+        let config = ChainQualityConfig {
+            stale_window: 20,
+            max_stale_rate: 0.1,
+            hashrate_window: 5,
+            hashrate_baseline: 20,
+            ..ChainQualityConfig::default()
+        };
+        let mut monitor = ChainQualityMonitor::new(config);
+        let mut alerts = monitor.subscribe();
+
+        // Twenty blocks ten minutes apart, one of them replaced
+        let mut time = 0;
+        for height in 0..20 {
+            time += 600;
+            let hash = connect(&mut monitor, height, time);
+            if height % 10 == 5 && height < 10 {
+                monitor.handle_event(&ChainEvent::BlockDisconnected { hash, height });
+                connect(&mut monitor, height, time + 1);
+            }
+        }
+        assert!(alerts.try_recv().is_err());
+        assert_eq!(monitor.stale_rate(), 0.05);
+        assert!((monitor.hashrate_ratio().unwrap() - 1.0).abs() < 0.05);
+
+        // Blocks slow to one an hour at unchanged difficulty
+        for height in 20..25 {
+            time += 3600;
+            connect(&mut monitor, height, time);
+        }
+        match alerts.try_recv().unwrap() {
+            ChainQualityEvent::ChainAnomalyDetected {
+                kind: AnomalyKind::HashrateDrop { ratio },
+                severity,
+            } => {
+                assert!(ratio < 0.5, "{ratio}");
+                assert_eq!(severity, AnomalySeverity::Medium);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        // Reported once, not on every slow block
+        connect(&mut monitor, 25, time + 3600);
+        assert!(alerts.try_recv().is_err());
+
+        // Three one-block reorgs push the stale rate over 10%
+        for height in 26..29 {
+            let hash = connect(&mut monitor, height, time);
+            monitor.handle_event(&ChainEvent::BlockDisconnected { hash, height });
+            connect(&mut monitor, height, time + 1);
+        }
+        assert_eq!(
+            alerts.try_recv().unwrap(),
+            ChainQualityEvent::ChainAnomalyDetected {
+                kind: AnomalyKind::StaleRate { rate: 0.15 },
+                severity: AnomalySeverity::Medium,
+            }
+        );
+    }
+}
@@ -0,0 +1,323 @@
+// [AIR-3][AIS-3][BPC-3] Electrum chain source
+//
+// Speaks the Electrum protocol (newline-delimited JSON-RPC) over TCP or TLS.
+// One connection is opened lazily and shared by all requests; after an I/O
+// error it is dropped and reopened by the next request. Electrum servers do
+// not serve full blocks or look headers up by hash, so those calls fail.
+
+use super::{fee_rate_from_sat_per_vb, script_hash, ChainBackend, ChainSource, ChainUtxo};
+use crate::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::{Amount, Block, BlockHash, FeeRate, OutPoint, Script, Transaction, TxOut, Txid};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_native_tls::{native_tls, TlsConnector};
+
+/// Time allowed for connecting and for each request
+const ELECTRUM_TIMEOUT: Duration = Duration::from_secs(30);
+
+trait ElectrumIo: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ElectrumIo for T {}
+
+struct Connection {
+    stream: BufReader<Box<dyn ElectrumIo>>,
+    next_id: u64,
+}
+
+#[derive(Deserialize)]
+struct ListUnspentEntry {
+    tx_hash: Txid,
+    tx_pos: u32,
+    height: i64,
+    value: u64,
+}
+
+/// [AIR-3][BPC-3] Chain source backed by an Electrum server
+pub struct ElectrumSource {
+    host: String,
+    port: u16,
+    tls: bool,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl ElectrumSource {
+    /// Client for `tcp://host:port` or `ssl://host:port`
+    pub fn new(url: &str) -> AnyaResult<Self> {
+        let (tls, address) = if let Some(address) = url.strip_prefix("ssl://") {
+            (true, address)
+        } else if let Some(address) = url.strip_prefix("tcp://") {
+            (false, address)
+        } else {
+            return Err(AnyaError::Bitcoin(format!(
+                "Electrum URL {url} must start with tcp:// or ssl://"
+            )));
+        };
+        let (host, port) = address
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+            .ok_or_else(|| AnyaError::Bitcoin(format!("Electrum URL {url} needs host:port")))?;
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            tls,
+            connection: Mutex::new(None),
+        })
+    }
+
+    async fn connect(&self) -> AnyaResult<Connection> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| self.error(format!("connection failed: {e}")))?;
+        let stream: Box<dyn ElectrumIo> = if self.tls {
+            let connector = native_tls::TlsConnector::new()
+                .map_err(|e| self.error(format!("TLS setup failed: {e}")))?;
+            let tls = TlsConnector::from(connector)
+                .connect(&self.host, tcp)
+                .await
+                .map_err(|e| self.error(format!("TLS handshake failed: {e}")))?;
+            Box::new(tls)
+        } else {
+            Box::new(tcp)
+        };
+        Ok(Connection {
+            stream: BufReader::new(stream),
+            next_id: 0,
+        })
+    }
+
+    /// Send one request and wait for its response
+    async fn request(&self, method: &str, params: Value) -> AnyaResult<Value> {
+        let mut guard = self.connection.lock().await;
+        if guard.is_none() {
+            let connection = tokio::time::timeout(ELECTRUM_TIMEOUT, self.connect())
+                .await
+                .map_err(|_| self.error("connection timed out".to_string()))??;
+            *guard = Some(connection);
+        }
+        let connection = guard.as_mut().expect("connection was just opened");
+
+        let result =
+            tokio::time::timeout(ELECTRUM_TIMEOUT, exchange(connection, method, params)).await;
+        match result {
+            Ok(Ok(response)) => {
+                if let Some(error) = response.get("error").filter(|error| !error.is_null()) {
+                    return Err(self.error(format!("{method} failed: {error}")));
+                }
+                Ok(response.get("result").cloned().unwrap_or(Value::Null))
+            }
+            Ok(Err(e)) => {
+                *guard = None;
+                Err(self.error(format!("{method}: {e}")))
+            }
+            Err(_) => {
+                *guard = None;
+                Err(self.error(format!("{method} timed out")))
+            }
+        }
+    }
+
+    async fn request_as<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> AnyaResult<T> {
+        let result = self.request(method, params).await?;
+        serde_json::from_value(result)
+            .map_err(|e| self.error(format!("unexpected {method} response: {e}")))
+    }
+
+    fn error(&self, message: String) -> AnyaError {
+        AnyaError::Bitcoin(format!("Electrum {}:{} {message}", self.host, self.port))
+    }
+}
+
+/// Write a request and read lines until the matching response
+///
+/// Subscription notifications carry no id and are skipped.
+async fn exchange(
+    connection: &mut Connection,
+    method: &str,
+    params: Value,
+) -> std::io::Result<Value> {
+    connection.next_id += 1;
+    let id = connection.next_id;
+    let mut line = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+        .to_string()
+        .into_bytes();
+    line.push(b'\n');
+    connection.stream.get_mut().write_all(&line).await?;
+    connection.stream.get_mut().flush().await?;
+
+    loop {
+        let mut response = String::new();
+        if connection.stream.read_line(&mut response).await? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let response: Value = serde_json::from_str(&response)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if response.get("id").and_then(Value::as_u64) == Some(id) {
+            return Ok(response);
+        }
+    }
+}
+
+#[async_trait]
+impl ChainSource for ElectrumSource {
+    fn backend(&self) -> ChainBackend {
+        ChainBackend::Electrum
+    }
+
+    async fn tip_height(&self) -> AnyaResult<u32> {
+        let tip = self
+            .request("blockchain.headers.subscribe", json!([]))
+            .await?;
+        tip.get("height")
+            .and_then(Value::as_u64)
+            .map(|height| height as u32)
+            .ok_or_else(|| self.error(format!("unexpected headers.subscribe response: {tip}")))
+    }
+
+    async fn get_block(&self, hash: &BlockHash) -> AnyaResult<Block> {
+        Err(self.error(format!("does not serve full blocks (requested {hash})")))
+    }
+
+    async fn get_header(&self, hash: &BlockHash) -> AnyaResult<BlockHeader> {
+        Err(self.error(format!("cannot look up header {hash} by hash")))
+    }
+
+    async fn get_tx(&self, txid: &Txid) -> AnyaResult<Transaction> {
+        let hex: String = self
+            .request_as("blockchain.transaction.get", json!([txid.to_string()]))
+            .await?;
+        deserialize_hex(&hex).map_err(|e| self.error(format!("invalid transaction {txid}: {e}")))
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        let txid: String = self
+            .request_as(
+                "blockchain.transaction.broadcast",
+                json!([serialize_hex(tx)]),
+            )
+            .await?;
+        txid.parse()
+            .map_err(|e| self.error(format!("broadcast returned {txid:?}: {e}")))
+    }
+
+    async fn get_utxos(&self, script: &Script) -> AnyaResult<Vec<ChainUtxo>> {
+        let entries: Vec<ListUnspentEntry> = self
+            .request_as(
+                "blockchain.scripthash.listunspent",
+                json!([script_hash(script)]),
+            )
+            .await?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| ChainUtxo {
+                outpoint: OutPoint::new(entry.tx_hash, entry.tx_pos),
+                txout: TxOut {
+                    value: Amount::from_sat(entry.value),
+                    script_pubkey: script.to_owned(),
+                },
+                // Unconfirmed outputs are reported at height 0, or -1 with unconfirmed parents
+                height: u32::try_from(entry.height)
+                    .ok()
+                    .filter(|height| *height > 0),
+            })
+            .collect())
+    }
+
+    async fn is_script_used(&self, script: &Script) -> AnyaResult<bool> {
+        let history: Vec<Value> = self
+            .request_as(
+                "blockchain.scripthash.get_history",
+                json!([script_hash(script)]),
+            )
+            .await?;
+        Ok(!history.is_empty())
+    }
+
+    async fn estimate_fee(&self, target_blocks: u16) -> AnyaResult<FeeRate> {
+        // Reported in BTC/kB, or -1 when the server has no estimate
+        let per_kb: f64 = self
+            .request_as("blockchain.estimatefee", json!([target_blocks]))
+            .await?;
+        if per_kb <= 0.0 {
+            return Err(self.error(format!("has no fee estimate for {target_blocks} blocks")));
+        }
+        Ok(fee_rate_from_sat_per_vb(per_kb * 100_000_000.0 / 1000.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Serve canned responses to the first connection, echoing request ids
+    async fn server(responses: Vec<Value>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            for result in responses {
+                let mut line = String::new();
+                socket.read_line(&mut line).await.unwrap();
+                let request: Value = serde_json::from_str(&line).unwrap();
+                // A notification arrives first and must be skipped
+                let notification = json!({"jsonrpc": "2.0", "method": "blockchain.headers.subscribe", "params": []});
+                let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+                let reply = format!("{notification}\n{response}\n");
+                socket.get_mut().write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        format!("tcp://{address}")
+    }
+
+    #[tokio::test]
+    async fn test_electrum_requests() {
+        let url = server(vec![
+            json!({"height": 850_000, "hex": ""}),
+            json!([
+                {"tx_hash": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", "tx_pos": 1, "height": 100, "value": 5000},
+                {"tx_hash": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", "tx_pos": 2, "height": 0, "value": 700}
+            ]),
+            json!(0.00012),
+            json!(-1),
+        ])
+        .await;
+        let source = ElectrumSource::new(&url).unwrap();
+
+        assert_eq!(source.tip_height().await.unwrap(), 850_000);
+
+        let script = Script::from_bytes(&[0x51]);
+        let utxos = source.get_utxos(script).await.unwrap();
+        assert_eq!(utxos.len(), 2);
+        assert_eq!(utxos[0].height, Some(100));
+        assert_eq!(utxos[0].txout.value, Amount::from_sat(5000));
+        assert_eq!(utxos[1].height, None);
+
+        // 0.00012 BTC/kB is 12 sat/vB
+        let fee_rate = source.estimate_fee(6).await.unwrap();
+        assert_eq!(fee_rate.to_sat_per_vb_floor(), 12);
+        assert!(source.estimate_fee(1).await.is_err());
+
+        let block_hash = BlockHash::from_raw_hash(bitcoin::hashes::Hash::all_zeros());
+        assert!(source.get_block(&block_hash).await.is_err());
+    }
+
+    #[test]
+    fn test_rejects_unknown_scheme() {
+        assert!(ElectrumSource::new("ssl://electrum.example.org:50002").is_ok());
+        assert!(ElectrumSource::new("http://electrum.example.org:50002").is_err());
+        assert!(ElectrumSource::new("tcp://electrum.example.org").is_err());
+    }
+}
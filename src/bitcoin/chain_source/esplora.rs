@@ -0,0 +1,183 @@
+// [AIR-3][AIS-3][BPC-3] Esplora HTTP chain source
+//
+// Uses the REST API served by Esplora and mempool.space. Outputs are looked
+// up by their Electrum-style script hash.
+
+use super::{fee_rate_from_sat_per_vb, script_hash, ChainBackend, ChainSource, ChainUtxo};
+use crate::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::consensus::encode::{deserialize, deserialize_hex, serialize_hex};
+use bitcoin::{Amount, Block, BlockHash, FeeRate, OutPoint, Script, Transaction, TxOut, Txid};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+struct EsploraUtxo {
+    txid: Txid,
+    vout: u32,
+    value: u64,
+    status: EsploraTxStatus,
+}
+
+#[derive(Deserialize)]
+struct EsploraTxStatus {
+    confirmed: bool,
+    block_height: Option<u32>,
+}
+
+/// [AIR-3][BPC-3] Chain source backed by an Esplora server
+pub struct EsploraSource {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl EsploraSource {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn get(&self, path: &str) -> AnyaResult<reqwest::Response> {
+        let url = format!("{}{path}", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| AnyaError::Bitcoin(format!("Esplora request {url} failed: {e}")))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Err(AnyaError::NotFound(format!("Esplora {path}"))),
+            status if !status.is_success() => Err(AnyaError::Bitcoin(format!(
+                "Esplora {path} returned {status}"
+            ))),
+            _ => Ok(response),
+        }
+    }
+
+    async fn get_text(&self, path: &str) -> AnyaResult<String> {
+        self.get(path)
+            .await?
+            .text()
+            .await
+            .map_err(|e| AnyaError::Bitcoin(format!("Esplora {path}: {e}")))
+    }
+
+    async fn get_bytes(&self, path: &str) -> AnyaResult<Vec<u8>> {
+        let bytes = self
+            .get(path)
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| AnyaError::Bitcoin(format!("Esplora {path}: {e}")))?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> AnyaResult<T> {
+        self.get(path)
+            .await?
+            .json()
+            .await
+            .map_err(|e| AnyaError::Bitcoin(format!("Esplora {path}: {e}")))
+    }
+}
+
+#[async_trait]
+impl ChainSource for EsploraSource {
+    fn backend(&self) -> ChainBackend {
+        ChainBackend::Esplora
+    }
+
+    async fn tip_height(&self) -> AnyaResult<u32> {
+        let text = self.get_text("/blocks/tip/height").await?;
+        text.trim()
+            .parse()
+            .map_err(|e| AnyaError::Bitcoin(format!("Esplora tip height {text:?}: {e}")))
+    }
+
+    async fn get_block(&self, hash: &BlockHash) -> AnyaResult<Block> {
+        let bytes = self.get_bytes(&format!("/block/{hash}/raw")).await?;
+        deserialize(&bytes).map_err(|e| AnyaError::Bitcoin(format!("Esplora block {hash}: {e}")))
+    }
+
+    async fn get_header(&self, hash: &BlockHash) -> AnyaResult<BlockHeader> {
+        let hex = self.get_text(&format!("/block/{hash}/header")).await?;
+        deserialize_hex(hex.trim())
+            .map_err(|e| AnyaError::Bitcoin(format!("Esplora header {hash}: {e}")))
+    }
+
+    async fn get_tx(&self, txid: &Txid) -> AnyaResult<Transaction> {
+        let bytes = self.get_bytes(&format!("/tx/{txid}/raw")).await?;
+        deserialize(&bytes).map_err(|e| AnyaError::Bitcoin(format!("Esplora tx {txid}: {e}")))
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        let url = format!("{}/tx", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .body(serialize_hex(tx))
+            .send()
+            .await
+            .map_err(|e| AnyaError::Bitcoin(format!("Esplora request {url} failed: {e}")))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| AnyaError::Bitcoin(format!("Esplora broadcast: {e}")))?;
+        if !status.is_success() {
+            return Err(AnyaError::Bitcoin(format!(
+                "Esplora rejected {}: {}",
+                tx.compute_txid(),
+                text.trim()
+            )));
+        }
+        text.trim()
+            .parse()
+            .map_err(|e| AnyaError::Bitcoin(format!("Esplora broadcast returned {text:?}: {e}")))
+    }
+
+    async fn get_utxos(&self, script: &Script) -> AnyaResult<Vec<ChainUtxo>> {
+        let utxos: Vec<EsploraUtxo> = self
+            .get_json(&format!("/scripthash/{}/utxo", script_hash(script)))
+            .await?;
+        Ok(utxos
+            .into_iter()
+            .map(|utxo| ChainUtxo {
+                outpoint: OutPoint::new(utxo.txid, utxo.vout),
+                txout: TxOut {
+                    value: Amount::from_sat(utxo.value),
+                    script_pubkey: script.to_owned(),
+                },
+                height: utxo.status.block_height.filter(|_| utxo.status.confirmed),
+            })
+            .collect())
+    }
+
+    async fn is_script_used(&self, script: &Script) -> AnyaResult<bool> {
+        // The first page of the history is enough to tell whether there is any
+        let txs: Vec<serde_json::Value> = self
+            .get_json(&format!("/scripthash/{}/txs", script_hash(script)))
+            .await?;
+        Ok(!txs.is_empty())
+    }
+
+    async fn estimate_fee(&self, target_blocks: u16) -> AnyaResult<FeeRate> {
+        let estimates: HashMap<String, f64> = self.get_json("/fee-estimates").await?;
+        // Use the estimate for the longest target not beyond the requested one
+        estimates
+            .iter()
+            .filter_map(|(target, rate)| Some((target.parse::<u16>().ok()?, *rate)))
+            .filter(|(target, _)| *target <= target_blocks)
+            .max_by_key(|(target, _)| *target)
+            .map(|(_, rate)| fee_rate_from_sat_per_vb(rate))
+            .ok_or_else(|| {
+                AnyaError::Bitcoin(format!(
+                    "Esplora has no fee estimate for {target_blocks} blocks"
+                ))
+            })
+    }
+}
@@ -0,0 +1,291 @@
+// [AIR-3][AIS-3][BPC-3][RES-3] Multi-endpoint transaction broadcast
+//
+// A transaction is submitted to every configured endpoint at once, for
+// example our own node, a public Esplora and a mempool.space-style API, so a
+// single endpoint that is down or filters the transaction cannot stop it from
+// reaching the network. The first acceptance wins; only when every endpoint
+// rejects the transaction is an error returned, listing each endpoint's reason.
+
+use super::{ChainSource, ChainSourceConfig};
+use crate::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use bitcoin::{Network, Transaction, Txid};
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// [AIR-3][BPC-3] Something that can submit a transaction to the network
+#[async_trait]
+pub trait Broadcaster: Send + Sync {
+    /// Submit `tx`, returning its txid once accepted
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid>;
+}
+
+#[async_trait]
+impl<S: ChainSource + ?Sized> Broadcaster for S {
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        ChainSource::broadcast(self, tx).await
+    }
+}
+
+/// Adapter for chain sources that are already behind an `Arc<dyn ChainSource>`
+struct SourceBroadcaster(Arc<dyn ChainSource>);
+
+#[async_trait]
+impl Broadcaster for SourceBroadcaster {
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        self.0.broadcast(tx).await
+    }
+}
+
+/// [AIR-3][BPC-3] Named broadcast endpoint in the configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BroadcastEndpoint {
+    /// Label reported in errors, e.g. `"own-node"` or `"mempool.space"`
+    pub name: String,
+    #[serde(flatten)]
+    pub source: ChainSourceConfig,
+}
+
+/// Endpoint that accepted a fanned-out broadcast
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BroadcastAccepted {
+    pub endpoint: String,
+    pub txid: Txid,
+}
+
+/// Reason one endpoint rejected a broadcast
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointFailure {
+    pub endpoint: String,
+    pub reason: String,
+}
+
+/// [AIR-3][BPC-3] Every endpoint rejected a broadcast
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BroadcastFailures {
+    /// One entry per endpoint, in the order the failures arrived
+    pub failures: Vec<EndpointFailure>,
+}
+
+impl fmt::Display for BroadcastFailures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.failures.is_empty() {
+            return write!(f, "No broadcast endpoints configured");
+        }
+        write!(f, "All {} broadcast endpoints failed", self.failures.len())?;
+        for (i, failure) in self.failures.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { "; " };
+            write!(f, "{separator}{}: {}", failure.endpoint, failure.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BroadcastFailures {}
+
+impl From<BroadcastFailures> for AnyaError {
+    fn from(failures: BroadcastFailures) -> Self {
+        AnyaError::Bitcoin(failures.to_string())
+    }
+}
+
+/// [AIR-3][AIS-3][BPC-3] Broadcaster fanning out to several endpoints
+#[derive(Default)]
+pub struct MultiBroadcaster {
+    endpoints: Vec<(String, Arc<dyn Broadcaster>)>,
+}
+
+impl MultiBroadcaster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a broadcaster for the configured endpoints on `network`
+    ///
+    /// No connection is made until the first broadcast.
+    pub fn from_config(endpoints: &[BroadcastEndpoint], network: Network) -> AnyaResult<Self> {
+        let mut broadcaster = Self::new();
+        for endpoint in endpoints {
+            let source = endpoint.source.connect(network)?;
+            broadcaster = broadcaster
+                .with_endpoint(endpoint.name.clone(), Arc::new(SourceBroadcaster(source)));
+        }
+        Ok(broadcaster)
+    }
+
+    /// Add an endpoint reported as `name`
+    pub fn with_endpoint(
+        mut self,
+        name: impl Into<String>,
+        endpoint: Arc<dyn Broadcaster>,
+    ) -> Self {
+        self.endpoints.push((name.into(), endpoint));
+        self
+    }
+
+    /// Names of the endpoints, in the order they were added
+    pub fn endpoints(&self) -> Vec<&str> {
+        self.endpoints
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Submit `tx` to every endpoint concurrently
+    ///
+    /// Returns as soon as one endpoint accepts. The remaining submissions
+    /// keep running in the background so the transaction still propagates
+    /// through them.
+    pub async fn broadcast_any(
+        &self,
+        tx: &Transaction,
+    ) -> Result<BroadcastAccepted, BroadcastFailures> {
+        let mut pending: FuturesUnordered<_> = self
+            .endpoints
+            .iter()
+            .map(|(name, endpoint)| {
+                let (endpoint, tx) = (endpoint.clone(), tx.clone());
+                let task = tokio::spawn(async move { endpoint.broadcast(&tx).await });
+                let name = name.clone();
+                async move {
+                    let result = task.await.unwrap_or_else(|e| {
+                        Err(AnyaError::System(format!("Broadcast task failed: {e}")))
+                    });
+                    (name, result)
+                }
+            })
+            .collect();
+
+        let mut failures = Vec::new();
+        while let Some((endpoint, result)) = pending.next().await {
+            match result {
+                Ok(txid) => return Ok(BroadcastAccepted { endpoint, txid }),
+                Err(e) => {
+                    log::warn!("Broadcast endpoint {endpoint} rejected transaction: {e}");
+                    failures.push(EndpointFailure {
+                        endpoint,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+        Err(BroadcastFailures { failures })
+    }
+}
+
+#[async_trait]
+impl Broadcaster for MultiBroadcaster {
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        Ok(self.broadcast_any(tx).await?.txid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, ScriptBuf, TxOut};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn transaction() -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: Vec::new(),
+            output: vec![TxOut {
+                value: Amount::from_sat(50_000),
+                script_pubkey: ScriptBuf::new_op_return([0x42; 4]),
+            }],
+        }
+    }
+
+    async fn esplora(status: u16, body: String) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/tx"))
+            .respond_with(ResponseTemplate::new(status).set_body_string(body))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn endpoint(name: &str, server: &MockServer) -> BroadcastEndpoint {
+        BroadcastEndpoint {
+            name: name.to_string(),
+            source: ChainSourceConfig::Esplora {
+                url: Some(server.uri()),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_first_acceptance_wins_and_failures_are_aggregated() {
+        let tx = transaction();
+        let txid = tx.compute_txid();
+        let rejecting = esplora(400, "bad-txns-inputs-missingorspent".to_string()).await;
+        let accepting = esplora(200, txid.to_string()).await;
+
+        let broadcaster = MultiBroadcaster::from_config(
+            &[
+                endpoint("public", &rejecting),
+                endpoint("own-node", &accepting),
+            ],
+            Network::Regtest,
+        )
+        .unwrap();
+        assert_eq!(broadcaster.endpoints(), vec!["public", "own-node"]);
+        let accepted = broadcaster.broadcast_any(&tx).await.unwrap();
+        assert_eq!(accepted.endpoint, "own-node");
+        assert_eq!(accepted.txid, txid);
+        assert_eq!(
+            Broadcaster::broadcast(&broadcaster, &tx).await.unwrap(),
+            txid
+        );
+
+        let also_rejecting = esplora(503, "overloaded".to_string()).await;
+        let broadcaster = MultiBroadcaster::from_config(
+            &[
+                endpoint("public", &rejecting),
+                endpoint("mempool", &also_rejecting),
+            ],
+            Network::Regtest,
+        )
+        .unwrap();
+        let failures = broadcaster.broadcast_any(&tx).await.unwrap_err();
+        let mut reasons: Vec<_> = failures
+            .failures
+            .iter()
+            .map(|failure| (failure.endpoint.as_str(), failure.reason.as_str()))
+            .collect();
+        reasons.sort();
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(reasons[0].0, "mempool");
+        assert!(reasons[0].1.contains("overloaded"));
+        assert_eq!(reasons[1].0, "public");
+        assert!(reasons[1].1.contains("bad-txns-inputs-missingorspent"));
+        assert!(matches!(AnyaError::from(failures), AnyaError::Bitcoin(_)));
+
+        let empty = MultiBroadcaster::new()
+            .broadcast_any(&tx)
+            .await
+            .unwrap_err();
+        assert!(empty.failures.is_empty());
+    }
+
+    #[test]
+    fn test_endpoint_config_is_flat() {
+        let endpoint: BroadcastEndpoint = serde_json::from_str(
+            r#"{"name":"own-node","backend":"bitcoind","url":"http://127.0.0.1:8332"}"#,
+        )
+        .unwrap();
+        assert_eq!(endpoint.name, "own-node");
+        assert!(matches!(
+            endpoint.source,
+            ChainSourceConfig::Bitcoind { .. }
+        ));
+    }
+}
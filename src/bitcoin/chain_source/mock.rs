@@ -0,0 +1,209 @@
+// [AIT-3] In-memory chain source for tests
+
+use super::{ChainBackend, ChainSource, ChainUtxo};
+use crate::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::{Block, BlockHash, FeeRate, Script, ScriptBuf, Transaction, Txid};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct MockChain {
+    tip_height: u32,
+    blocks: HashMap<BlockHash, Block>,
+    transactions: HashMap<Txid, Transaction>,
+    utxos: HashMap<ScriptBuf, Vec<ChainUtxo>>,
+    /// Scripts paid to by some transaction, including spent outputs
+    used_scripts: HashSet<ScriptBuf>,
+    fee_rate: Option<FeeRate>,
+    broadcasts: Vec<Transaction>,
+}
+
+/// [AIT-3] Chain source serving data added by the test, recording broadcasts
+#[derive(Default)]
+pub struct MockChainSource {
+    chain: Mutex<MockChain>,
+}
+
+impl MockChainSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `block`, and its transactions, at `height`; the highest block is the tip
+    pub fn add_block(&self, block: Block, height: u32) {
+        let mut chain = self.chain();
+        chain.tip_height = chain.tip_height.max(height);
+        for tx in &block.txdata {
+            chain.transactions.insert(tx.compute_txid(), tx.clone());
+        }
+        chain.blocks.insert(block.block_hash(), block);
+    }
+
+    pub fn add_utxo(&self, utxo: ChainUtxo) {
+        let mut chain = self.chain();
+        chain.used_scripts.insert(utxo.txout.script_pubkey.clone());
+        chain
+            .utxos
+            .entry(utxo.txout.script_pubkey.clone())
+            .or_default()
+            .push(utxo);
+    }
+
+    /// Record that `script` was paid to by outputs that are spent by now
+    pub fn add_spent_history(&self, script: ScriptBuf) {
+        self.chain().used_scripts.insert(script);
+    }
+
+    pub fn set_tip_height(&self, height: u32) {
+        self.chain().tip_height = height;
+    }
+
+    pub fn set_fee_rate(&self, fee_rate: FeeRate) {
+        self.chain().fee_rate = Some(fee_rate);
+    }
+
+    /// Transactions passed to [`ChainSource::broadcast`], oldest first
+    pub fn broadcasts(&self) -> Vec<Transaction> {
+        self.chain().broadcasts.clone()
+    }
+
+    fn chain(&self) -> std::sync::MutexGuard<'_, MockChain> {
+        self.chain.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl ChainSource for MockChainSource {
+    fn backend(&self) -> ChainBackend {
+        ChainBackend::Mock
+    }
+
+    async fn tip_height(&self) -> AnyaResult<u32> {
+        Ok(self.chain().tip_height)
+    }
+
+    async fn get_block(&self, hash: &BlockHash) -> AnyaResult<Block> {
+        self.chain()
+            .blocks
+            .get(hash)
+            .cloned()
+            .ok_or_else(|| AnyaError::NotFound(format!("Block {hash}")))
+    }
+
+    async fn get_header(&self, hash: &BlockHash) -> AnyaResult<BlockHeader> {
+        Ok(self.get_block(hash).await?.header)
+    }
+
+    async fn get_tx(&self, txid: &Txid) -> AnyaResult<Transaction> {
+        self.chain()
+            .transactions
+            .get(txid)
+            .cloned()
+            .ok_or_else(|| AnyaError::NotFound(format!("Transaction {txid}")))
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        let txid = tx.compute_txid();
+        let mut chain = self.chain();
+        chain.transactions.insert(txid, tx.clone());
+        chain.broadcasts.push(tx.clone());
+        Ok(txid)
+    }
+
+    async fn get_utxos(&self, script: &Script) -> AnyaResult<Vec<ChainUtxo>> {
+        Ok(self.chain().utxos.get(script).cloned().unwrap_or_default())
+    }
+
+    async fn is_script_used(&self, script: &Script) -> AnyaResult<bool> {
+        Ok(self.chain().used_scripts.contains(script))
+    }
+
+    async fn estimate_fee(&self, target_blocks: u16) -> AnyaResult<FeeRate> {
+        self.chain().fee_rate.ok_or_else(|| {
+            AnyaError::Bitcoin(format!("No fee estimate for {target_blocks} blocks"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::{
+        AddressManager, AddressType, CoinSelectionStrategy, FeeStrategy, TransactionManager,
+        Wallet, WalletConfig, WalletType,
+    };
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, Network, OutPoint, TxOut};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    fn wallet(chain_source: Option<Arc<dyn ChainSource>>) -> Wallet {
+        let config = WalletConfig {
+            wallet_type: WalletType::Standard,
+            network: Network::Regtest,
+            name: "chain-source".to_string(),
+            seed_phrase: None,
+            password: None,
+            receive_descriptor: String::new(),
+            change_descriptor: String::new(),
+            xpub: None,
+            data_dir: PathBuf::new(),
+            use_rpc: false,
+            coin_selection: CoinSelectionStrategy::LargestFirst,
+            gap_limit: 20,
+            min_confirmations: 1,
+            fee_strategy: FeeStrategy::Medium,
+        };
+        let wallet = Wallet::new(config, chain_source);
+        wallet
+            .initialize(Some("chain source test seed"), None)
+            .unwrap();
+        wallet
+    }
+
+    fn utxo(n: u8, script_pubkey: ScriptBuf, sats: u64, height: Option<u32>) -> ChainUtxo {
+        ChainUtxo {
+            outpoint: OutPoint::new(Txid::from_byte_array([n; 32]), 0),
+            txout: TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey,
+            },
+            height,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wallet_reads_and_broadcasts_through_chain_source() {
+        let chain = Arc::new(MockChainSource::new());
+        let wallet = wallet(Some(chain.clone()));
+        let mine = wallet
+            .get_address(3, AddressType::SegWit)
+            .unwrap()
+            .script_pubkey();
+        chain.set_tip_height(110);
+        chain.add_utxo(utxo(1, mine.clone(), 50_000, Some(101)));
+        chain.add_utxo(utxo(2, mine.clone(), 20_000, None));
+        chain.add_utxo(utxo(3, ScriptBuf::new_op_return([]), 1_000, Some(101)));
+        assert_eq!(wallet.refresh_utxos().await.unwrap(), 70_000);
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: Vec::new(),
+            output: vec![utxo(4, mine, 10_000, None).txout],
+        };
+        assert_eq!(wallet.broadcast(&tx).await.unwrap(), tx.compute_txid());
+        assert_eq!(chain.broadcasts(), vec![tx.clone()]);
+        assert_eq!(wallet.get_transactions(10, 0).unwrap(), vec![tx]);
+    }
+
+    #[tokio::test]
+    async fn test_wallet_without_chain_source() {
+        let wallet = wallet(None);
+        assert!(wallet.refresh_utxos().await.is_err());
+    }
+}
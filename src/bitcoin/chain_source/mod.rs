@@ -0,0 +1,205 @@
+// [AIR-3][AIS-3][BPC-3][RES-3] Pluggable chain data sources
+//
+// Wallets, Layer 2 protocols and the node read chain data through the
+// `ChainSource` trait instead of embedding one client. Backends for bitcoind
+// JSON-RPC, Electrum and Esplora HTTP are provided and selected with
+// `ChainSourceConfig`; Electrum and Esplora fall back to the endpoints
+// resolved by `external_endpoints`. `ChainSourcePool` spreads requests over
+// several connections to one backend, and `MultiBroadcaster` submits a
+// transaction to several endpoints at once.
+
+pub mod electrum;
+pub mod esplora;
+pub mod fanout;
+pub mod mock;
+pub mod pool;
+pub mod rpc;
+
+pub use electrum::ElectrumSource;
+pub use esplora::EsploraSource;
+pub use fanout::{
+    BroadcastAccepted, BroadcastEndpoint, BroadcastFailures, Broadcaster, EndpointFailure,
+    MultiBroadcaster,
+};
+pub use mock::MockChainSource;
+pub use pool::{ChainSourcePool, ChainSourcePoolConfig, DispatchStrategy, PoolStats};
+pub use rpc::BitcoindSource;
+
+use crate::bitcoin::external_endpoints::ExternalBitcoinEndpoints;
+use crate::AnyaResult;
+use async_trait::async_trait;
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{Block, BlockHash, FeeRate, Network, OutPoint, Script, Transaction, TxOut, Txid};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Backend behind a [`ChainSource`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainBackend {
+    Bitcoind,
+    Electrum,
+    Esplora,
+    Mock,
+}
+
+/// Unspent output reported by a chain source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainUtxo {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    /// Height of the confirming block, `None` while unconfirmed
+    pub height: Option<u32>,
+}
+
+/// [AIR-3][BPC-3] Read access to the chain and transaction broadcast
+#[async_trait]
+pub trait ChainSource: Send + Sync {
+    /// Backend serving the data
+    fn backend(&self) -> ChainBackend;
+
+    /// Height of the best chain tip
+    async fn tip_height(&self) -> AnyaResult<u32>;
+
+    async fn get_block(&self, hash: &BlockHash) -> AnyaResult<Block>;
+
+    async fn get_header(&self, hash: &BlockHash) -> AnyaResult<BlockHeader>;
+
+    async fn get_tx(&self, txid: &Txid) -> AnyaResult<Transaction>;
+
+    /// Submit `tx` to the network, returning its txid
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid>;
+
+    /// Unspent outputs paying to `script`
+    async fn get_utxos(&self, script: &Script) -> AnyaResult<Vec<ChainUtxo>>;
+
+    /// Whether any transaction has paid to `script`, spent or not
+    ///
+    /// Backends without an address index only see unspent outputs, which is
+    /// the default.
+    async fn is_script_used(&self, script: &Script) -> AnyaResult<bool> {
+        Ok(!self.get_utxos(script).await?.is_empty())
+    }
+
+    /// Fee rate expected to confirm within `target_blocks`
+    async fn estimate_fee(&self, target_blocks: u16) -> AnyaResult<FeeRate>;
+}
+
+/// [AIR-3][BPC-3] Chain source selection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum ChainSourceConfig {
+    /// bitcoind JSON-RPC
+    Bitcoind {
+        url: String,
+        /// RPC username and password
+        #[serde(default)]
+        auth: Option<(String, String)>,
+    },
+    /// Electrum server at `tcp://host:port` or `ssl://host:port`
+    Electrum {
+        /// Defaults to the network's Electrum endpoint
+        #[serde(default)]
+        url: Option<String>,
+    },
+    /// Esplora HTTP API
+    Esplora {
+        /// Defaults to the network's explorer API endpoint
+        #[serde(default)]
+        url: Option<String>,
+    },
+}
+
+impl ChainSourceConfig {
+    /// Create the configured chain source for `network`
+    ///
+    /// No connection is made until the first request.
+    pub fn connect(&self, network: Network) -> AnyaResult<Arc<dyn ChainSource>> {
+        Ok(match self {
+            Self::Bitcoind { url, auth } => Arc::new(BitcoindSource::new(url, auth.clone())?),
+            Self::Electrum { url } => {
+                let url = url.clone().unwrap_or_else(|| {
+                    ExternalBitcoinEndpoints::resolve()
+                        .electrum_for(&network)
+                        .to_string()
+                });
+                Arc::new(ElectrumSource::new(&url)?)
+            }
+            Self::Esplora { url } => {
+                let url = url.clone().unwrap_or_else(|| {
+                    ExternalBitcoinEndpoints::resolve()
+                        .explorer_api_for(&network)
+                        .to_string()
+                });
+                Arc::new(EsploraSource::new(&url))
+            }
+        })
+    }
+}
+
+/// Convert a fee rate in sat/vB, as reported by most backends
+pub(crate) fn fee_rate_from_sat_per_vb(sat_per_vb: f64) -> FeeRate {
+    FeeRate::from_sat_per_kwu((sat_per_vb * 250.0).round() as u64)
+}
+
+/// Electrum-style script hash used by Electrum and Esplora to index outputs
+///
+/// The SHA-256 of the script, in reversed byte order.
+pub(crate) fn script_hash(script: &Script) -> String {
+    let mut hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
+    hash.reverse();
+    hex::encode(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Address;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_config_selects_backend() {
+        let config: ChainSourceConfig =
+            serde_json::from_str(r#"{"backend":"esplora","url":"http://127.0.0.1:3002"}"#).unwrap();
+        let source = config.connect(Network::Regtest).unwrap();
+        assert_eq!(source.backend(), ChainBackend::Esplora);
+
+        let electrum: ChainSourceConfig =
+            serde_json::from_str(r#"{"backend":"electrum"}"#).unwrap();
+        assert_eq!(
+            electrum.connect(Network::Bitcoin).unwrap().backend(),
+            ChainBackend::Electrum
+        );
+
+        let bitcoind = ChainSourceConfig::Bitcoind {
+            url: "http://127.0.0.1:18443".to_string(),
+            auth: Some(("user".to_string(), "pass".to_string())),
+        };
+        assert_eq!(
+            bitcoind.connect(Network::Regtest).unwrap().backend(),
+            ChainBackend::Bitcoind
+        );
+
+        let unknown = ChainSourceConfig::Electrum {
+            url: Some("http://127.0.0.1:50001".to_string()),
+        };
+        assert!(unknown.connect(Network::Regtest).is_err());
+    }
+
+    #[test]
+    fn test_fee_rate_conversion() {
+        assert_eq!(fee_rate_from_sat_per_vb(1.0).to_sat_per_vb_floor(), 1);
+        assert_eq!(fee_rate_from_sat_per_vb(12.5).to_sat_per_kwu(), 3125);
+    }
+
+    #[test]
+    fn test_script_hash_matches_electrum_protocol_example() {
+        let address = Address::from_str("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa")
+            .unwrap()
+            .assume_checked();
+        assert_eq!(
+            script_hash(&address.script_pubkey()),
+            "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161"
+        );
+    }
+}
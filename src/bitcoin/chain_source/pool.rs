@@ -0,0 +1,504 @@
+// [AIR-3][AIS-3][BPC-3][RES-3] Connection pool for chain sources
+//
+// Keeps between `min_connections` and `max_connections` clients of one
+// backend and spreads requests over them, round-robin or to the least busy
+// connection. A request that fails is retried once on a different connection
+// before the error is returned, and periodic health checks evict connections
+// that no longer answer and open new ones to stay at the minimum.
+
+use super::{ChainBackend, ChainSource, ChainSourceConfig, ChainUtxo};
+use crate::core::PrometheusMetrics;
+use crate::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::{Block, BlockHash, FeeRate, Network, Script, Transaction, Txid};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How the pool picks a connection for a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DispatchStrategy {
+    RoundRobin,
+    /// The connection with the fewest requests in flight
+    LeastBusy,
+}
+
+/// [AIR-3][BPC-3] Settings for [`ChainSourcePool`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainSourcePoolConfig {
+    /// Connections opened up front and restored by health checks
+    pub min_connections: usize,
+    /// Upper bound on open connections; more are opened while all are busy
+    pub max_connections: usize,
+    pub dispatch: DispatchStrategy,
+    pub health_check_interval: Duration,
+}
+
+impl Default for ChainSourcePoolConfig {
+    fn default() -> Self {
+        Self {
+            min_connections: 2,
+            max_connections: 8,
+            dispatch: DispatchStrategy::LeastBusy,
+            health_check_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// [AIR-3][BPC-3] Pool counters for metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Connections with a request in flight
+    pub active: usize,
+    pub idle: usize,
+    /// Failed requests, including those that succeeded on retry
+    pub errors: u64,
+    /// Requests retried on a different connection
+    pub retries: u64,
+    /// Connections evicted by health checks
+    pub evicted: u64,
+}
+
+type Connector = Box<dyn Fn() -> AnyaResult<Arc<dyn ChainSource>> + Send + Sync>;
+
+struct PooledConnection {
+    id: u64,
+    source: Arc<dyn ChainSource>,
+    in_flight: AtomicUsize,
+}
+
+/// Marks a request in flight on a connection for as long as it is held
+struct InFlight(Arc<PooledConnection>);
+
+impl InFlight {
+    fn new(connection: Arc<PooledConnection>) -> Self {
+        connection.in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(connection)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// [AIR-3][AIS-3][BPC-3] Pooled [`ChainSource`] over connections of one backend
+pub struct ChainSourcePool {
+    config: ChainSourcePoolConfig,
+    connect: Connector,
+    backend: ChainBackend,
+    connections: Mutex<Vec<Arc<PooledConnection>>>,
+    next_id: AtomicU64,
+    next_dispatch: AtomicUsize,
+    errors: AtomicU64,
+    retries: AtomicU64,
+    evicted: AtomicU64,
+    checking: AtomicBool,
+}
+
+impl ChainSourcePool {
+    /// Create a pool opening connections with `connect`
+    ///
+    /// `min_connections` connections are opened immediately.
+    pub fn new<F>(config: ChainSourcePoolConfig, connect: F) -> AnyaResult<Self>
+    where
+        F: Fn() -> AnyaResult<Arc<dyn ChainSource>> + Send + Sync + 'static,
+    {
+        if config.max_connections == 0 || config.min_connections > config.max_connections {
+            return Err(AnyaError::InvalidInput(format!(
+                "Invalid chain source pool size {}..={}",
+                config.min_connections, config.max_connections
+            )));
+        }
+        let first = connect()?;
+        let pool = Self {
+            backend: first.backend(),
+            config,
+            connect: Box::new(connect),
+            connections: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+            next_dispatch: AtomicUsize::new(0),
+            errors: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            evicted: AtomicU64::new(0),
+            checking: AtomicBool::new(false),
+        };
+        {
+            let mut connections = pool.connections();
+            connections.push(pool.wrap(first));
+            pool.fill(&mut connections)?;
+        }
+        Ok(pool)
+    }
+
+    /// Create a pool of the chain source configured by `source`
+    pub fn from_config(
+        source: ChainSourceConfig,
+        network: Network,
+        config: ChainSourcePoolConfig,
+    ) -> AnyaResult<Self> {
+        Self::new(config, move || source.connect(network))
+    }
+
+    pub fn config(&self) -> &ChainSourcePoolConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let connections = self.connections();
+        let active = connections
+            .iter()
+            .filter(|c| c.in_flight.load(Ordering::SeqCst) > 0)
+            .count();
+        PoolStats {
+            active,
+            idle: connections.len() - active,
+            errors: self.errors.load(Ordering::SeqCst),
+            retries: self.retries.load(Ordering::SeqCst),
+            evicted: self.evicted.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Publish [`PoolStats`] as `chain_source_pool_*` gauges
+    pub fn publish(&self, metrics: &mut PrometheusMetrics) {
+        let stats = self.stats();
+        metrics.set_gauge("chain_source_pool_active", stats.active as f64);
+        metrics.set_gauge("chain_source_pool_idle", stats.idle as f64);
+        metrics.set_gauge("chain_source_pool_errors", stats.errors as f64);
+        metrics.set_gauge("chain_source_pool_retries", stats.retries as f64);
+        metrics.set_gauge("chain_source_pool_evicted", stats.evicted as f64);
+    }
+
+    /// Probe every connection, evict those that fail and reopen up to the
+    /// minimum, returning the number evicted
+    pub async fn health_check(&self) -> AnyaResult<usize> {
+        if self.checking.swap(true, Ordering::SeqCst) {
+            return Ok(0);
+        }
+        let connections = self.connections().clone();
+        let mut dead = Vec::new();
+        for connection in &connections {
+            if let Err(e) = connection.source.tip_height().await {
+                log::warn!("Evicting chain source connection {}: {e}", connection.id);
+                dead.push(connection.id);
+            }
+        }
+
+        let result = {
+            let mut connections = self.connections();
+            connections.retain(|c| !dead.contains(&c.id));
+            self.fill(&mut connections)
+        };
+        self.evicted.fetch_add(dead.len() as u64, Ordering::SeqCst);
+        self.checking.store(false, Ordering::SeqCst);
+        result.map(|()| dead.len())
+    }
+
+    /// Run [`Self::health_check`] every `health_check_interval`
+    pub fn spawn_health_checks(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.config.health_check_interval);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.health_check().await {
+                    log::warn!("Chain source pool health check failed: {e}");
+                }
+            }
+        })
+    }
+
+    fn connections(&self) -> std::sync::MutexGuard<'_, Vec<Arc<PooledConnection>>> {
+        self.connections.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wrap(&self, source: Arc<dyn ChainSource>) -> Arc<PooledConnection> {
+        Arc::new(PooledConnection {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            source,
+            in_flight: AtomicUsize::new(0),
+        })
+    }
+
+    fn fill(&self, connections: &mut Vec<Arc<PooledConnection>>) -> AnyaResult<()> {
+        while connections.len() < self.config.min_connections {
+            connections.push(self.wrap((self.connect)()?));
+        }
+        Ok(())
+    }
+
+    /// Pick a connection other than `exclude`, opening one when every
+    /// candidate is busy, or none is left, and the pool has room
+    fn checkout(&self, exclude: Option<u64>) -> AnyaResult<InFlight> {
+        let mut connections = self.connections();
+        let candidates: Vec<&Arc<PooledConnection>> = connections
+            .iter()
+            .filter(|c| Some(c.id) != exclude)
+            .collect();
+        let all_busy = candidates
+            .iter()
+            .all(|c| c.in_flight.load(Ordering::SeqCst) > 0);
+        if all_busy && connections.len() < self.config.max_connections {
+            match (self.connect)() {
+                Ok(source) => {
+                    let connection = self.wrap(source);
+                    connections.push(connection.clone());
+                    return Ok(InFlight::new(connection));
+                }
+                Err(e) if candidates.is_empty() => return Err(e),
+                Err(e) => log::debug!("Could not open chain source connection: {e}"),
+            }
+        }
+
+        let connection = match self.config.dispatch {
+            DispatchStrategy::RoundRobin if !candidates.is_empty() => {
+                let next = self.next_dispatch.fetch_add(1, Ordering::SeqCst);
+                candidates[next % candidates.len()]
+            }
+            _ => candidates
+                .iter()
+                .min_by_key(|c| c.in_flight.load(Ordering::SeqCst))
+                .copied()
+                .ok_or_else(|| {
+                    AnyaError::Bitcoin("No chain source connection available".to_string())
+                })?,
+        };
+        Ok(InFlight::new(connection.clone()))
+    }
+
+    /// Run `request` on a pooled connection, retrying once on another
+    async fn dispatch<'a, T>(
+        &'a self,
+        request: impl Fn(Arc<dyn ChainSource>) -> BoxFuture<'a, AnyaResult<T>>,
+    ) -> AnyaResult<T> {
+        let first = self.checkout(None)?;
+        let error = match request(first.0.source.clone()).await {
+            Err(e) if is_connection_error(&e) => e,
+            result => return result,
+        };
+        self.errors.fetch_add(1, Ordering::SeqCst);
+        let failed = first.0.id;
+        drop(first);
+
+        let Ok(retry) = self.checkout(Some(failed)) else {
+            return Err(error);
+        };
+        log::debug!(
+            "Chain source request failed on connection {failed}, retrying on {}: {error}",
+            retry.0.id
+        );
+        self.retries.fetch_add(1, Ordering::SeqCst);
+        let result = request(retry.0.source.clone()).await;
+        if result.as_ref().is_err_and(is_connection_error) {
+            self.errors.fetch_add(1, Ordering::SeqCst);
+        }
+        result
+    }
+}
+
+/// Whether a failed request may succeed on another connection
+///
+/// Not-found and invalid-input errors are answers from the backend, which
+/// every connection would repeat.
+fn is_connection_error(error: &AnyaError) -> bool {
+    !matches!(error, AnyaError::NotFound(_) | AnyaError::InvalidInput(_))
+}
+
+#[async_trait]
+impl ChainSource for ChainSourcePool {
+    fn backend(&self) -> ChainBackend {
+        self.backend
+    }
+
+    async fn tip_height(&self) -> AnyaResult<u32> {
+        self.dispatch(|source| Box::pin(async move { source.tip_height().await }))
+            .await
+    }
+
+    async fn get_block(&self, hash: &BlockHash) -> AnyaResult<Block> {
+        self.dispatch(|source| Box::pin(async move { source.get_block(hash).await }))
+            .await
+    }
+
+    async fn get_header(&self, hash: &BlockHash) -> AnyaResult<BlockHeader> {
+        self.dispatch(|source| Box::pin(async move { source.get_header(hash).await }))
+            .await
+    }
+
+    async fn get_tx(&self, txid: &Txid) -> AnyaResult<Transaction> {
+        self.dispatch(|source| Box::pin(async move { source.get_tx(txid).await }))
+            .await
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        self.dispatch(|source| Box::pin(async move { source.broadcast(tx).await }))
+            .await
+    }
+
+    async fn get_utxos(&self, script: &Script) -> AnyaResult<Vec<ChainUtxo>> {
+        self.dispatch(|source| Box::pin(async move { source.get_utxos(script).await }))
+            .await
+    }
+
+    async fn is_script_used(&self, script: &Script) -> AnyaResult<bool> {
+        self.dispatch(|source| Box::pin(async move { source.is_script_used(script).await }))
+            .await
+    }
+
+    async fn estimate_fee(&self, target_blocks: u16) -> AnyaResult<FeeRate> {
+        self.dispatch(|source| Box::pin(async move { source.estimate_fee(target_blocks).await }))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::chain_source::MockChainSource;
+    use std::collections::HashSet;
+
+    /// Connection that fails every request while its id is marked down
+    struct FlakyConnection {
+        id: usize,
+        chain: Arc<MockChainSource>,
+        down: Arc<Mutex<HashSet<usize>>>,
+        served: Arc<Mutex<Vec<usize>>>,
+    }
+
+    #[async_trait]
+    impl ChainSource for FlakyConnection {
+        fn backend(&self) -> ChainBackend {
+            ChainBackend::Mock
+        }
+
+        async fn tip_height(&self) -> AnyaResult<u32> {
+            self.served.lock().unwrap().push(self.id);
+            if self.down.lock().unwrap().contains(&self.id) {
+                return Err(AnyaError::Timeout(format!("connection {} reset", self.id)));
+            }
+            self.chain.tip_height().await
+        }
+
+        async fn get_block(&self, hash: &BlockHash) -> AnyaResult<Block> {
+            self.chain.get_block(hash).await
+        }
+
+        async fn get_header(&self, hash: &BlockHash) -> AnyaResult<BlockHeader> {
+            self.chain.get_header(hash).await
+        }
+
+        async fn get_tx(&self, txid: &Txid) -> AnyaResult<Transaction> {
+            self.chain.get_tx(txid).await
+        }
+
+        async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+            self.chain.broadcast(tx).await
+        }
+
+        async fn get_utxos(&self, script: &Script) -> AnyaResult<Vec<ChainUtxo>> {
+            self.chain.get_utxos(script).await
+        }
+
+        async fn estimate_fee(&self, target_blocks: u16) -> AnyaResult<FeeRate> {
+            self.chain.estimate_fee(target_blocks).await
+        }
+    }
+
+    struct Harness {
+        pool: ChainSourcePool,
+        down: Arc<Mutex<HashSet<usize>>>,
+        served: Arc<Mutex<Vec<usize>>>,
+    }
+
+    fn pool(config: ChainSourcePoolConfig) -> Harness {
+        let chain = Arc::new(MockChainSource::new());
+        chain.set_tip_height(800_000);
+        let down = Arc::new(Mutex::new(HashSet::new()));
+        let served = Arc::new(Mutex::new(Vec::new()));
+        let opened = AtomicUsize::new(0);
+        let (d, s) = (down.clone(), served.clone());
+        let pool = ChainSourcePool::new(config, move || {
+            Ok(Arc::new(FlakyConnection {
+                id: opened.fetch_add(1, Ordering::SeqCst),
+                chain: chain.clone(),
+                down: d.clone(),
+                served: s.clone(),
+            }) as Arc<dyn ChainSource>)
+        })
+        .unwrap();
+        Harness { pool, down, served }
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_request_on_different_connection() {
+        let harness = pool(ChainSourcePoolConfig {
+            min_connections: 2,
+            max_connections: 2,
+            dispatch: DispatchStrategy::RoundRobin,
+            ..Default::default()
+        });
+        harness.down.lock().unwrap().insert(0);
+
+        // Round-robin sends the first request to the dead connection 0
+        assert_eq!(harness.pool.tip_height().await.unwrap(), 800_000);
+        assert_eq!(*harness.served.lock().unwrap(), vec![0, 1]);
+        let stats = harness.pool.stats();
+        assert_eq!((stats.errors, stats.retries), (1, 1));
+        assert_eq!((stats.active, stats.idle), (0, 2));
+
+        // With both connections down the retry's error is surfaced
+        harness.down.lock().unwrap().insert(1);
+        let err = harness.pool.tip_height().await.unwrap_err();
+        assert!(matches!(err, AnyaError::Timeout(_)));
+        assert_eq!(harness.pool.stats().errors, 3);
+
+        // Not-found answers are not retried
+        let missing = harness
+            .pool
+            .get_tx(&bitcoin::hashes::Hash::all_zeros())
+            .await;
+        assert!(matches!(missing, Err(AnyaError::NotFound(_))));
+        assert_eq!(harness.pool.stats().retries, 2);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_evicts_dead_connections() {
+        let harness = pool(ChainSourcePoolConfig {
+            min_connections: 3,
+            max_connections: 4,
+            ..Default::default()
+        });
+        harness.down.lock().unwrap().extend([0, 2]);
+
+        assert_eq!(harness.pool.health_check().await.unwrap(), 2);
+        let stats = harness.pool.stats();
+        assert_eq!((stats.idle, stats.evicted), (3, 2));
+
+        // Connection 1 and the reopened 3 and 4 are all healthy
+        harness.served.lock().unwrap().clear();
+        assert_eq!(harness.pool.health_check().await.unwrap(), 0);
+        let mut probed = harness.served.lock().unwrap().clone();
+        probed.sort();
+        assert_eq!(probed, vec![1, 3, 4]);
+
+        let mut metrics = PrometheusMetrics::new();
+        harness.pool.publish(&mut metrics);
+        assert_eq!(metrics.get_gauge("chain_source_pool_idle"), Some(3.0));
+        assert_eq!(metrics.get_gauge("chain_source_pool_evicted"), Some(2.0));
+
+        let invalid = ChainSourcePoolConfig {
+            min_connections: 3,
+            max_connections: 2,
+            ..Default::default()
+        };
+        assert!(ChainSourcePool::new(invalid, || Ok(
+            Arc::new(MockChainSource::new()) as Arc<dyn ChainSource>
+        ))
+        .is_err());
+    }
+}
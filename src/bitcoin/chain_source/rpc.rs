@@ -0,0 +1,128 @@
+// [AIR-3][AIS-3][BPC-3] bitcoind JSON-RPC chain source
+//
+// Calls are blocking in `bitcoincore_rpc`, so each one runs on the blocking
+// thread pool. Transaction lookups need `-txindex` for confirmed transactions
+// outside the wallet, and UTXO lookups use `scantxoutset`.
+
+use super::{fee_rate_from_sat_per_vb, ChainBackend, ChainSource, ChainUtxo};
+use crate::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::{Block, BlockHash, FeeRate, OutPoint, Script, Transaction, TxOut, Txid};
+use bitcoincore_rpc::json::ScanTxOutRequest;
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use std::sync::Arc;
+
+/// [AIR-3][BPC-3] Chain source backed by a bitcoind node
+pub struct BitcoindSource {
+    client: Arc<Client>,
+}
+
+impl BitcoindSource {
+    pub fn new(url: &str, auth: Option<(String, String)>) -> AnyaResult<Self> {
+        let auth = match auth {
+            Some((user, pass)) => Auth::UserPass(user, pass),
+            None => Auth::None,
+        };
+        let client = Client::new(url, auth)
+            .map_err(|e| AnyaError::Bitcoin(format!("bitcoind client for {url}: {e}")))?;
+        Ok(Self {
+            client: Arc::new(client),
+        })
+    }
+
+    /// Run `call` against the client on the blocking thread pool
+    async fn call<T, F>(&self, what: &'static str, call: F) -> AnyaResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Client) -> bitcoincore_rpc::Result<T> + Send + 'static,
+    {
+        let client = self.client.clone();
+        tokio::task::spawn_blocking(move || call(&client))
+            .await
+            .map_err(|e| AnyaError::System(format!("bitcoind {what} task failed: {e}")))?
+            .map_err(|e| AnyaError::Bitcoin(format!("bitcoind {what}: {e}")))
+    }
+}
+
+#[async_trait]
+impl ChainSource for BitcoindSource {
+    fn backend(&self) -> ChainBackend {
+        ChainBackend::Bitcoind
+    }
+
+    async fn tip_height(&self) -> AnyaResult<u32> {
+        let height = self
+            .call("getblockcount", |client| client.get_block_count())
+            .await?;
+        Ok(height as u32)
+    }
+
+    async fn get_block(&self, hash: &BlockHash) -> AnyaResult<Block> {
+        let hash = *hash;
+        self.call("getblock", move |client| client.get_block(&hash))
+            .await
+    }
+
+    async fn get_header(&self, hash: &BlockHash) -> AnyaResult<BlockHeader> {
+        let hash = *hash;
+        self.call("getblockheader", move |client| {
+            client.get_block_header(&hash)
+        })
+        .await
+    }
+
+    async fn get_tx(&self, txid: &Txid) -> AnyaResult<Transaction> {
+        let txid = *txid;
+        self.call("getrawtransaction", move |client| {
+            client.get_raw_transaction(&txid, None)
+        })
+        .await
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        let tx = tx.clone();
+        self.call("sendrawtransaction", move |client| {
+            client.send_raw_transaction(&tx)
+        })
+        .await
+    }
+
+    async fn get_utxos(&self, script: &Script) -> AnyaResult<Vec<ChainUtxo>> {
+        let request = ScanTxOutRequest::Single(format!("raw({})", script.to_hex_string()));
+        let result = self
+            .call("scantxoutset", move |client| {
+                client.scan_tx_out_set_blocking(&[request])
+            })
+            .await?;
+        Ok(result
+            .unspents
+            .into_iter()
+            .map(|utxo| ChainUtxo {
+                outpoint: OutPoint::new(utxo.txid, utxo.vout),
+                txout: TxOut {
+                    value: utxo.amount,
+                    script_pubkey: utxo.script_pub_key,
+                },
+                // scantxoutset only sees the UTXO set, so every output is confirmed
+                height: Some(utxo.height as u32),
+            })
+            .collect())
+    }
+
+    async fn estimate_fee(&self, target_blocks: u16) -> AnyaResult<FeeRate> {
+        let estimate = self
+            .call("estimatesmartfee", move |client| {
+                client.estimate_smart_fee(target_blocks, None)
+            })
+            .await?;
+        // Reported in BTC/kvB
+        let per_kvb = estimate.fee_rate.ok_or_else(|| {
+            AnyaError::Bitcoin(format!(
+                "bitcoind has no fee estimate for {target_blocks} blocks: {}",
+                estimate.errors.unwrap_or_default().join(", ")
+            ))
+        })?;
+        Ok(fee_rate_from_sat_per_vb(per_kvb.to_sat() as f64 / 1000.0))
+    }
+}
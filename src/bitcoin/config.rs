@@ -1,4 +1,5 @@
 // Bitcoin configuration module
+use crate::bitcoin::chain_source::ChainSourceConfig;
 use serde::{Deserialize, Serialize};
 
 /// Bitcoin network configuration
@@ -18,6 +19,9 @@ pub struct BitcoinConfig {
     pub default_fee_rate: u64,
     /// Path to wallet file (if applicable)
     pub wallet_path: Option<String>,
+    /// Backend for chain data; bitcoind at `rpc_url` when unset
+    #[serde(default)]
+    pub chain_source: Option<ChainSourceConfig>,
 }
 
 impl Default for BitcoinConfig {
@@ -30,11 +34,25 @@ impl Default for BitcoinConfig {
             min_confirmations: 6,
             default_fee_rate: 10,
             wallet_path: None,
+            chain_source: None,
         }
     }
 }
 
 impl BitcoinConfig {
+    /// Chain source to use, falling back to bitcoind at `rpc_url`
+    pub fn chain_source_config(&self) -> ChainSourceConfig {
+        self.chain_source
+            .clone()
+            .unwrap_or_else(|| ChainSourceConfig::Bitcoind {
+                url: self
+                    .rpc_url
+                    .clone()
+                    .unwrap_or_else(|| "http://127.0.0.1:18332".to_string()),
+                auth: self.auth.clone(),
+            })
+    }
+
     /// Check if a specific BIP is supported
     pub fn supports_bip(&self, bip: &str) -> Result<bool, Box<dyn std::error::Error>> {
         match bip {
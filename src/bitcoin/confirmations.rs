@@ -0,0 +1,376 @@
+// [AIR-3][AIS-3][BPC-3] Transaction confirmation tracking
+//
+// Watches the active chain for transactions a caller is waiting on. Blocks
+// arrive as `ChainEvent`s, usually from a receiver passed to `spawn`. A
+// transaction counts as confirmed once a connected block contains it, and
+// its depth grows with every block connected on top. Each waiter is resolved
+// exactly once: with `Confirmed` when the target depth is reached, or with
+// `Reorged` if the block confirming the transaction is disconnected first.
+//
+// Only blocks seen after tracking starts are considered, so a transaction
+// should be tracked before it can confirm, typically right after broadcast.
+
+use bitcoin::{Block, BlockHash, Txid};
+use futures::future::FutureExt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+
+/// [AIR-3][BPC-3] Change to the active chain
+#[derive(Debug, Clone)]
+pub enum ChainEvent {
+    /// `block` became the tip at `height`
+    BlockConnected { block: Block, height: u32 },
+    /// The tip at `height`, `hash`, was removed from the active chain
+    BlockDisconnected { hash: BlockHash, height: u32 },
+}
+
+/// [AIR-3][BPC-3] How a tracked transaction was resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfirmationOutcome {
+    /// The transaction reached the target depth
+    Confirmed {
+        txid: Txid,
+        block_hash: BlockHash,
+        height: u32,
+        confirmations: u32,
+    },
+    /// The block confirming the transaction was disconnected before the target depth
+    Reorged {
+        txid: Txid,
+        block_hash: BlockHash,
+        height: u32,
+    },
+    /// The tracker stopped receiving chain events
+    Cancelled { txid: Txid },
+}
+
+type Callback = Box<dyn FnOnce(ConfirmationOutcome) + Send>;
+
+struct Waiter {
+    target: u32,
+    callback: Callback,
+}
+
+#[derive(Default)]
+struct Tracked {
+    /// Block containing the transaction and its height
+    confirmed_in: Option<(BlockHash, u32)>,
+    waiters: Vec<Waiter>,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    txs: HashMap<Txid, Tracked>,
+}
+
+/// [AIR-3][BPC-3] Notifies waiters when transactions reach a confirmation depth
+#[derive(Default)]
+pub struct ConfirmationTracker {
+    state: Mutex<TrackerState>,
+}
+
+impl ConfirmationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for `txid` to reach `target` confirmations
+    ///
+    /// A target of zero is treated as one.
+    pub fn track(&self, txid: Txid, target: u32) -> impl Future<Output = ConfirmationOutcome> {
+        let (sender, receiver) = oneshot::channel();
+        self.track_with_callback(txid, target, move |outcome| {
+            let _ = sender.send(outcome);
+        });
+        receiver.map(move |outcome| outcome.unwrap_or(ConfirmationOutcome::Cancelled { txid }))
+    }
+
+    /// Call `callback` once `txid` reaches `target` confirmations or is reorged out
+    ///
+    /// The callback runs on the task handling chain events and must not block.
+    pub fn track_with_callback(
+        &self,
+        txid: Txid,
+        target: u32,
+        callback: impl FnOnce(ConfirmationOutcome) + Send + 'static,
+    ) {
+        self.lock()
+            .txs
+            .entry(txid)
+            .or_default()
+            .waiters
+            .push(Waiter {
+                target: target.max(1),
+                callback: Box::new(callback),
+            });
+    }
+
+    /// Number of transactions with unresolved waiters
+    pub fn tracked(&self) -> usize {
+        self.lock().txs.len()
+    }
+
+    /// Apply a chain event, resolving the waiters it completes
+    pub fn handle_event(&self, event: &ChainEvent) {
+        let mut resolved = Vec::new();
+        {
+            let mut state = self.lock();
+            match event {
+                ChainEvent::BlockConnected { block, height } => {
+                    let tip_height = *height;
+                    let block_hash = block.block_hash();
+                    for tx in &block.txdata {
+                        if let Some(tracked) = state.txs.get_mut(&tx.compute_txid()) {
+                            tracked.confirmed_in.get_or_insert((block_hash, *height));
+                        }
+                    }
+
+                    for (txid, tracked) in state.txs.iter_mut() {
+                        let Some((block_hash, height)) = tracked.confirmed_in else {
+                            continue;
+                        };
+                        let confirmations = (tip_height + 1).saturating_sub(height);
+                        let (done, waiting): (Vec<Waiter>, Vec<Waiter>) =
+                            std::mem::take(&mut tracked.waiters)
+                                .into_iter()
+                                .partition(|waiter| waiter.target <= confirmations);
+                        tracked.waiters = waiting;
+                        resolved.extend(done.into_iter().map(|waiter| {
+                            let outcome = ConfirmationOutcome::Confirmed {
+                                txid: *txid,
+                                block_hash,
+                                height,
+                                confirmations,
+                            };
+                            (waiter.callback, outcome)
+                        }));
+                    }
+                }
+                ChainEvent::BlockDisconnected { hash, height } => {
+                    for (txid, tracked) in state.txs.iter_mut() {
+                        if tracked.confirmed_in.map(|(block_hash, _)| block_hash) != Some(*hash) {
+                            continue;
+                        }
+                        tracked.confirmed_in = None;
+                        resolved.extend(tracked.waiters.drain(..).map(|waiter| {
+                            let outcome = ConfirmationOutcome::Reorged {
+                                txid: *txid,
+                                block_hash: *hash,
+                                height: *height,
+                            };
+                            (waiter.callback, outcome)
+                        }));
+                    }
+                }
+            }
+            state.txs.retain(|_, tracked| !tracked.waiters.is_empty());
+        }
+
+        // Callbacks run without the lock so they may track further transactions
+        for (callback, outcome) in resolved {
+            callback(outcome);
+        }
+    }
+
+    /// Resolve every waiter with `Cancelled`
+    pub fn cancel_all(&self) {
+        let txs = std::mem::take(&mut self.lock().txs);
+        for (txid, tracked) in txs {
+            for waiter in tracked.waiters {
+                (waiter.callback)(ConfirmationOutcome::Cancelled { txid });
+            }
+        }
+    }
+
+    /// Handle chain events from `events` in the background
+    ///
+    /// Pending waiters are cancelled once the sending side closes.
+    pub fn spawn(
+        self: Arc<Self>,
+        mut events: mpsc::UnboundedReceiver<ChainEvent>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                self.handle_event(&event);
+            }
+            self.cancel_all();
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TrackerState> {
+        // Callbacks run outside the lock, so a panicking one cannot poison it
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::block::{Header, Version as BlockVersion};
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction::Version;
+    use bitcoin::{CompactTarget, Transaction, TxMerkleNode};
+    use futures::FutureExt;
+
+    fn transaction(lock_time: u32) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::from_consensus(lock_time),
+            input: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    fn block(nonce: u32, txdata: Vec<Transaction>) -> Block {
+        Block {
+            header: Header {
+                version: BlockVersion::ONE,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: 0,
+                bits: CompactTarget::from_consensus(0x207fffff),
+                nonce,
+            },
+            txdata,
+        }
+    }
+
+    fn connect(tracker: &ConfirmationTracker, height: u32, txdata: Vec<Transaction>) -> BlockHash {
+        let block = block(height, txdata);
+        let hash = block.block_hash();
+        tracker.handle_event(&ChainEvent::BlockConnected { block, height });
+        hash
+    }
+
+    #[test]
+    fn test_resolves_at_target_depth() {
+        let tracker = ConfirmationTracker::new();
+        let tx = transaction(1);
+        let txid = tx.compute_txid();
+        let mut one = tracker.track(txid, 1).boxed();
+        let mut three = tracker.track(txid, 3).boxed();
+
+        connect(&tracker, 100, Vec::new());
+        assert!((&mut one).now_or_never().is_none());
+
+        let block_hash = connect(&tracker, 101, vec![tx]);
+        assert_eq!(
+            one.now_or_never(),
+            Some(ConfirmationOutcome::Confirmed {
+                txid,
+                block_hash,
+                height: 101,
+                confirmations: 1
+            })
+        );
+        connect(&tracker, 102, Vec::new());
+        assert!((&mut three).now_or_never().is_none());
+        connect(&tracker, 103, Vec::new());
+        assert_eq!(
+            three.now_or_never(),
+            Some(ConfirmationOutcome::Confirmed {
+                txid,
+                block_hash,
+                height: 101,
+                confirmations: 3
+            })
+        );
+        assert_eq!(tracker.tracked(), 0);
+    }
+
+    #[test]
+    fn test_reorg_before_target_notifies_waiters() {
+        let tracker = ConfirmationTracker::new();
+        let tx = transaction(2);
+        let txid = tx.compute_txid();
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = outcomes.clone();
+        tracker.track_with_callback(txid, 6, move |outcome| {
+            recorded.lock().unwrap().push(outcome)
+        });
+
+        let block_hash = connect(&tracker, 200, vec![tx]);
+        let hashes = [
+            (connect(&tracker, 201, Vec::new()), 201),
+            (connect(&tracker, 202, Vec::new()), 202),
+        ];
+        for (hash, height) in hashes.into_iter().rev() {
+            tracker.handle_event(&ChainEvent::BlockDisconnected { hash, height });
+        }
+        assert!(outcomes.lock().unwrap().is_empty());
+
+        tracker.handle_event(&ChainEvent::BlockDisconnected {
+            hash: block_hash,
+            height: 200,
+        });
+        assert_eq!(
+            *outcomes.lock().unwrap(),
+            vec![ConfirmationOutcome::Reorged {
+                txid,
+                block_hash,
+                height: 200
+            }]
+        );
+        assert_eq!(tracker.tracked(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_tracks_many_transactions_from_event_stream() {
+        let tracker = Arc::new(ConfirmationTracker::new());
+        let txs: Vec<Transaction> = (0..50).map(transaction).collect();
+        let waits: Vec<_> = txs
+            .iter()
+            .enumerate()
+            .map(|(i, tx)| tracker.track(tx.compute_txid(), i as u32 % 3 + 1))
+            .collect();
+        let unconfirmed = tracker.track(transaction(1_000).compute_txid(), 1);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let handle = tracker.clone().spawn(receiver);
+        for (offset, chunk) in txs.chunks(10).enumerate() {
+            sender
+                .send(ChainEvent::BlockConnected {
+                    block: block(offset as u32, chunk.to_vec()),
+                    height: 300 + offset as u32,
+                })
+                .unwrap();
+        }
+        for height in 305..307 {
+            sender
+                .send(ChainEvent::BlockConnected {
+                    block: block(height, Vec::new()),
+                    height,
+                })
+                .unwrap();
+        }
+
+        for (i, outcome) in futures::future::join_all(waits)
+            .await
+            .into_iter()
+            .enumerate()
+        {
+            let ConfirmationOutcome::Confirmed {
+                txid,
+                height,
+                confirmations,
+                ..
+            } = outcome
+            else {
+                panic!("transaction {i} did not confirm: {outcome:?}");
+            };
+            assert_eq!(txid, txs[i].compute_txid());
+            assert_eq!(height, 300 + i as u32 / 10);
+            assert_eq!(confirmations, i as u32 % 3 + 1);
+        }
+
+        drop(sender);
+        handle.await.unwrap();
+        assert!(matches!(
+            unconfirmed.await,
+            ConfirmationOutcome::Cancelled { .. }
+        ));
+    }
+}
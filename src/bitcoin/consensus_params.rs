@@ -0,0 +1,246 @@
+// [AIR-3][AIS-3][BPC-3][RES-3] Network-specific consensus parameters
+//
+// Soft-fork activation heights, the subsidy schedule and block limits differ
+// between networks. Values follow Bitcoin Core's chain parameters; regtest
+// matches bitcoind's defaults, with every soft fork active from the start.
+
+use crate::AnyaError;
+use bitcoin::consensus::Params;
+use bitcoin::script::Builder;
+use bitcoin::{Amount, Block, Network, Weight};
+
+/// Subsidy of the first block before any halving
+const INITIAL_SUBSIDY: Amount = Amount::from_sat(50 * 100_000_000);
+
+/// Block-level consensus rule violations
+#[derive(Debug, thiserror::Error)]
+pub enum ConsensusRuleError {
+    #[error("Invalid coinbase at height {height}: {reason}")]
+    Coinbase { height: u32, reason: String },
+    #[error("Block weight {weight} exceeds the maximum of {max}")]
+    Weight { weight: Weight, max: Weight },
+    #[error("Witness data at height {0}, before segwit activation")]
+    PrematureWitness(u32),
+}
+
+impl From<ConsensusRuleError> for AnyaError {
+    fn from(err: ConsensusRuleError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+/// [AIR-3][BPC-3] Consensus parameters of one network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsensusParams {
+    /// Network these parameters apply to
+    pub network: Network,
+    /// Blocks between subsidy halvings
+    pub subsidy_halving_interval: u32,
+    /// Maximum block weight (BIP-141)
+    pub max_block_weight: Weight,
+    /// Height from which the coinbase must commit to the block height (BIP-34)
+    pub bip34_height: u32,
+    /// Height from which OP_CHECKLOCKTIMEVERIFY is enforced (BIP-65)
+    pub bip65_height: u32,
+    /// Height from which strict DER signatures are enforced (BIP-66)
+    pub bip66_height: u32,
+    /// Height from which relative lock-times are enforced (BIP-9 `csv`)
+    pub csv_height: u32,
+    /// Height from which segregated witness is enforced (BIP-9 `segwit`)
+    pub segwit_height: u32,
+    /// Height from which Taproot is enforced (BIP-341)
+    pub taproot_height: u32,
+}
+
+impl ConsensusParams {
+    /// Parameters of a well-known network
+    pub fn for_network(network: Network) -> Self {
+        let (bip34_height, bip65_height, bip66_height, csv_height, segwit_height, taproot_height) =
+            match network {
+                Network::Bitcoin => (227_931, 388_381, 363_725, 419_328, 481_824, 709_632),
+                Network::Testnet => (21_111, 581_885, 330_776, 770_112, 834_624, 2_011_968),
+                Network::Testnet4 | Network::Signet => (1, 1, 1, 1, 1, 0),
+                Network::Regtest => (1, 1, 1, 1, 0, 0),
+            };
+
+        Self {
+            network,
+            subsidy_halving_interval: if network == Network::Regtest {
+                150
+            } else {
+                210_000
+            },
+            max_block_weight: Weight::MAX_BLOCK,
+            bip34_height,
+            bip65_height,
+            bip66_height,
+            csv_height,
+            segwit_height,
+            taproot_height,
+        }
+    }
+
+    /// Proof-of-work and difficulty parameters of the network
+    pub fn pow_params(&self) -> &'static Params {
+        self.network.params()
+    }
+
+    /// Block subsidy in satoshis at `height`, halved every interval
+    pub fn subsidy_at_height(&self, height: u32) -> u64 {
+        let halvings = height / self.subsidy_halving_interval;
+        if halvings >= 64 {
+            return 0;
+        }
+        INITIAL_SUBSIDY.to_sat() >> halvings
+    }
+
+    /// Whether segregated witness rules apply at `height`
+    pub fn is_segwit_active(&self, height: u32) -> bool {
+        height >= self.segwit_height
+    }
+
+    /// Whether Taproot rules apply at `height`
+    pub fn is_taproot_active(&self, height: u32) -> bool {
+        height >= self.taproot_height
+    }
+
+    /// Check the rules of `block` at `height` that depend on the network
+    ///
+    /// Covers coinbase placement, the weight limit, the BIP-34 height
+    /// commitment and witness data before segwit activation.
+    pub fn check_block(&self, block: &Block, height: u32) -> Result<(), ConsensusRuleError> {
+        let coinbase_error = |reason: &str| ConsensusRuleError::Coinbase {
+            height,
+            reason: reason.to_string(),
+        };
+        match block.txdata.first() {
+            Some(tx) if tx.is_coinbase() => {}
+            _ => return Err(coinbase_error("first transaction is not a coinbase")),
+        }
+        if block.txdata.iter().skip(1).any(|tx| tx.is_coinbase()) {
+            return Err(coinbase_error("more than one coinbase"));
+        }
+
+        let weight = block.weight();
+        if weight > self.max_block_weight {
+            return Err(ConsensusRuleError::Weight {
+                weight,
+                max: self.max_block_weight,
+            });
+        }
+
+        // Same prefix check as Bitcoin Core, so heights up to 16 use OP_N
+        if height >= self.bip34_height {
+            let expected = Builder::new().push_int(i64::from(height)).into_script();
+            if !block.txdata[0]
+                .input
+                .first()
+                .is_some_and(|input| input.script_sig.as_bytes().starts_with(expected.as_bytes()))
+            {
+                return Err(coinbase_error("does not commit to the block height"));
+            }
+        }
+
+        if !self.is_segwit_active(height)
+            && block
+                .txdata
+                .iter()
+                .any(|tx| tx.input.iter().any(|input| !input.witness.is_empty()))
+        {
+            return Err(ConsensusRuleError::PrematureWitness(height));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::transaction::Version;
+    use bitcoin::{OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+
+    fn block_at(height: i64, witness: Witness) -> Block {
+        let mut block = genesis_block(Network::Regtest);
+        block.txdata = vec![Transaction {
+            version: Version::ONE,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: Builder::new().push_int(height).push_int(0).into_script(),
+                sequence: Sequence::MAX,
+                witness,
+            }],
+            output: vec![TxOut {
+                value: INITIAL_SUBSIDY,
+                script_pubkey: ScriptBuf::new(),
+            }],
+        }];
+        block
+    }
+
+    #[test]
+    fn test_halving_schedule() {
+        let mainnet = ConsensusParams::for_network(Network::Bitcoin);
+        assert_eq!(mainnet.subsidy_at_height(0), 5_000_000_000);
+        assert_eq!(mainnet.subsidy_at_height(209_999), 5_000_000_000);
+        assert_eq!(mainnet.subsidy_at_height(210_000), 2_500_000_000);
+        assert_eq!(mainnet.subsidy_at_height(840_000), 312_500_000);
+        assert_eq!(mainnet.subsidy_at_height(64 * 210_000), 0);
+
+        let regtest = ConsensusParams::for_network(Network::Regtest);
+        assert_eq!(regtest.subsidy_at_height(149), 5_000_000_000);
+        assert_eq!(regtest.subsidy_at_height(150), 2_500_000_000);
+    }
+
+    #[test]
+    fn test_regtest_soft_forks_active_immediately() {
+        let regtest = ConsensusParams::for_network(Network::Regtest);
+        assert!(regtest.is_segwit_active(0));
+        assert!(regtest.is_taproot_active(0));
+        assert_eq!(regtest.bip34_height, 1);
+        assert_eq!(
+            regtest
+                .pow_params()
+                .max_attainable_target
+                .to_compact_lossy()
+                .to_consensus(),
+            0x207fffff
+        );
+        assert!(regtest.pow_params().no_pow_retargeting);
+
+        let mainnet = ConsensusParams::for_network(Network::Bitcoin);
+        assert!(!mainnet.is_taproot_active(709_631));
+        assert!(mainnet.is_taproot_active(709_632));
+        assert!(!mainnet.is_segwit_active(481_823));
+    }
+
+    #[test]
+    fn test_check_block_rules() {
+        let regtest = ConsensusParams::for_network(Network::Regtest);
+        regtest
+            .check_block(&block_at(5, Witness::new()), 5)
+            .unwrap();
+        // Witness data is allowed on regtest, whose segwit height is zero
+        let witness = Witness::from_slice(&[[0u8; 32]]);
+        regtest
+            .check_block(&block_at(5, witness.clone()), 5)
+            .unwrap();
+
+        assert!(matches!(
+            regtest.check_block(&block_at(4, Witness::new()), 5),
+            Err(ConsensusRuleError::Coinbase { height: 5, .. })
+        ));
+
+        let mainnet = ConsensusParams::for_network(Network::Bitcoin);
+        assert!(matches!(
+            mainnet.check_block(&block_at(5, witness), 5),
+            Err(ConsensusRuleError::PrematureWitness(5))
+        ));
+        // Before BIP-34 the coinbase need not commit to the height
+        mainnet
+            .check_block(&block_at(1, Witness::new()), 5)
+            .unwrap();
+    }
+}
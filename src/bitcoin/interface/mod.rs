@@ -251,6 +251,7 @@ pub fn create_bitcoin_interface(
         min_confirmations: 6,
         default_fee_rate: 10,
         wallet_path: None,
+        chain_source: config.chain_source.clone(),
     };
     match implementation_type {
         BitcoinImplementationType::Rust => {
@@ -314,6 +315,7 @@ pub fn get_current_bitcoin_interface(
         min_confirmations: 6,
         default_fee_rate: 10,
         wallet_path: None,
+        chain_source: config.chain_source.clone(),
     };
     // [AIR-3][AIS-3][BPC-3][RES-3] Create a Rust implementation of the Bitcoin interface
     // Properly handle error conversion to avoid Box<dyn StdError> sizing issues
@@ -0,0 +1,95 @@
+// Cancellation and progress reporting for long-running RGB scans
+// Storage scans check a shared token between records so callers can stop them
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::{AnyaError, AnyaResult};
+
+/// Flag shared between a caller and a running scan
+///
+/// Clones share the same flag, so a token handed to a scan can be cancelled
+/// from another task.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that has not been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signal every scan holding this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`CancellationToken::cancel`] has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Callback receiving the records processed so far and the total to process
+pub type ProgressFn = dyn Fn(usize, usize) + Send + Sync;
+
+/// Cancellation token and optional progress callback for a scan
+#[derive(Clone, Default)]
+pub struct ScanControl {
+    token: CancellationToken,
+    progress: Option<Arc<ProgressFn>>,
+}
+
+impl ScanControl {
+    /// Control a scan with the given token
+    pub fn new(token: CancellationToken) -> Self {
+        Self {
+            token,
+            progress: None,
+        }
+    }
+
+    /// Report progress to `progress` after every record
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(usize, usize) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Token that cancels this scan
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Fail with [`AnyaError::Cancelled`] if the scan was cancelled
+    pub fn check(&self, operation: &str) -> AnyaResult<()> {
+        if self.token.is_cancelled() {
+            return Err(AnyaError::Cancelled(format!("{operation} was cancelled")));
+        }
+        Ok(())
+    }
+
+    /// Record that `processed` of `total` records are done
+    ///
+    /// Yields to the runtime so a cancelling task can run between records,
+    /// then checks the token.
+    pub async fn advance(&self, operation: &str, processed: usize, total: usize) -> AnyaResult<()> {
+        if let Some(progress) = &self.progress {
+            progress(processed, total);
+        }
+        tokio::task::yield_now().await;
+        self.check(operation)
+    }
+}
+
+impl fmt::Debug for ScanControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScanControl")
+            .field("token", &self.token)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
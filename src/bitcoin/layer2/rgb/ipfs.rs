@@ -0,0 +1,394 @@
+// IPFS storage backend for RGB
+// Stores assets and history as CBOR blobs on an IPFS node via its HTTP API
+
+use async_trait::async_trait;
+use bitcoin::hashes::{sha256, Hash};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use super::storage::walk_history;
+use super::{HistoryEntry, RGBAsset, RgbStorage, ScanControl};
+use crate::storage::IPFSConfig;
+use crate::{AnyaError, AnyaResult};
+
+/// Maps asset IDs to the CIDs of their latest asset and history blobs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct IpfsIndex {
+    assets: BTreeMap<String, String>,
+    history: BTreeMap<String, String>,
+}
+
+/// Response of the `/api/v0/add` endpoint
+#[derive(Deserialize)]
+struct AddResponse {
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+/// Stores RGB records on IPFS through a Kubo-compatible HTTP API
+///
+/// Every asset and every version of an asset's history is added as a CBOR
+/// blob, pinned when `enable_pinning` is set, and resolved by CID. A JSON
+/// index on local disk maps asset IDs to their current CIDs. If the IPFS API
+/// cannot be reached, operations fail with an error naming the endpoint
+/// rather than reporting empty data.
+pub struct IpfsStorage {
+    client: reqwest::Client,
+    config: IPFSConfig,
+    index_path: PathBuf,
+    index: Mutex<IpfsIndex>,
+}
+
+impl IpfsStorage {
+    /// Open a storage talking to `config.endpoint`, keeping its index at `index_path`
+    pub async fn open(config: IPFSConfig, index_path: impl Into<PathBuf>) -> AnyaResult<Self> {
+        let index_path = index_path.into();
+        let index = match tokio::fs::read(&index_path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => IpfsIndex::default(),
+            Err(e) => {
+                return Err(AnyaError::System(format!(
+                    "Failed to read IPFS index {}: {e}",
+                    index_path.display()
+                )))
+            }
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout))
+            .build()
+            .map_err(|e| AnyaError::System(format!("Failed to build IPFS client: {e}")))?;
+        Ok(Self {
+            client,
+            config,
+            index_path,
+            index: Mutex::new(index),
+        })
+    }
+
+    /// CID of the stored asset, if any
+    pub async fn asset_cid(&self, asset_id: &str) -> Option<String> {
+        self.index.lock().await.assets.get(asset_id).cloned()
+    }
+
+    /// CID of the asset's current history blob, if any
+    pub async fn history_cid(&self, asset_id: &str) -> Option<String> {
+        self.index.lock().await.history.get(asset_id).cloned()
+    }
+
+    fn api_url(&self, command: &str) -> String {
+        format!(
+            "{}/api/v0/{command}",
+            self.config.endpoint.trim_end_matches('/')
+        )
+    }
+
+    async fn call(&self, request: reqwest::RequestBuilder, command: &str) -> AnyaResult<Vec<u8>> {
+        let response = request.send().await.map_err(|e| {
+            AnyaError::System(format!(
+                "IPFS API at {} is unavailable: {e}",
+                self.config.endpoint
+            ))
+        })?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| AnyaError::System(format!("IPFS {command} failed: {e}")))?;
+        if !status.is_success() {
+            return Err(AnyaError::System(format!(
+                "IPFS {command} failed with {status}: {}",
+                String::from_utf8_lossy(&body).trim()
+            )));
+        }
+        Ok(body.to_vec())
+    }
+
+    /// Add `value` as a CBOR blob and return its CID
+    async fn put<T: Serialize>(&self, name: &str, value: &T) -> AnyaResult<String> {
+        let mut data = Vec::new();
+        ciborium::into_writer(value, &mut data)
+            .map_err(|e| AnyaError::System(format!("Failed to encode {name}: {e}")))?;
+        if data.len() > self.config.max_file_size {
+            return Err(AnyaError::InvalidInput(format!(
+                "{name} is {} bytes, above the {} byte limit",
+                data.len(),
+                self.config.max_file_size
+            )));
+        }
+
+        // The API only accepts multipart uploads; the boundary is derived from
+        // the content so it cannot occur inside it
+        let boundary = format!("anya-rgb-{}", sha256::Hash::hash(&data));
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{name}\"\r\n\
+             Content-Type: application/cbor\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(&data);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+        let request = self
+            .client
+            .post(self.api_url("add"))
+            .query(&[
+                ("pin", self.config.enable_pinning.to_string()),
+                ("cid-version", "1".to_string()),
+            ])
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(body);
+        let response: AddResponse = serde_json::from_slice(&self.call(request, "add").await?)?;
+        Ok(response.hash)
+    }
+
+    /// Fetch and decode the CBOR blob at `cid`
+    async fn get<T: DeserializeOwned>(&self, cid: &str) -> AnyaResult<T> {
+        let request = self.client.post(self.api_url("cat")).query(&[("arg", cid)]);
+        let data = self.call(request, "cat").await?;
+        ciborium::from_reader(data.as_slice())
+            .map_err(|e| AnyaError::System(format!("Failed to decode IPFS object {cid}: {e}")))
+    }
+
+    /// Persist the index by writing a temporary file and renaming it over the old one
+    async fn save_index(&self, index: &IpfsIndex) -> AnyaResult<()> {
+        let io_error = |e: std::io::Error| {
+            AnyaError::System(format!(
+                "Failed to write IPFS index {}: {e}",
+                self.index_path.display()
+            ))
+        };
+        if let Some(parent) = self.index_path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+        let tmp = self.index_path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(index)?)
+            .await
+            .map_err(io_error)?;
+        tokio::fs::rename(&tmp, &self.index_path)
+            .await
+            .map_err(io_error)
+    }
+}
+
+#[async_trait]
+impl RgbStorage for IpfsStorage {
+    async fn store_asset(&self, asset: &RGBAsset) -> AnyaResult<()> {
+        let mut index = self.index.lock().await;
+        let cid = self.put(&format!("asset-{}.cbor", asset.id), asset).await?;
+        index.assets.insert(asset.id.clone(), cid);
+        self.save_index(&index).await
+    }
+
+    async fn load_asset(&self, asset_id: &str) -> AnyaResult<Option<RGBAsset>> {
+        match self.asset_cid(asset_id).await {
+            Some(cid) => self.get(&cid).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn scan_assets(&self, control: &ScanControl) -> AnyaResult<Vec<RGBAsset>> {
+        control.check("Asset scan")?;
+        let cids: Vec<String> = self.index.lock().await.assets.values().cloned().collect();
+
+        let mut assets = Vec::with_capacity(cids.len());
+        for (index, cid) in cids.iter().enumerate() {
+            assets.push(self.get(cid).await?);
+            control.advance("Asset scan", index + 1, cids.len()).await?;
+        }
+        Ok(assets)
+    }
+
+    async fn store_transfer(&self, asset_id: &str, entry: &HistoryEntry) -> AnyaResult<()> {
+        // Holding the index lock across the read-modify-write keeps
+        // concurrent transfers for the same asset from dropping each other
+        let mut index = self.index.lock().await;
+        let mut history: Vec<HistoryEntry> = match index.history.get(asset_id) {
+            Some(cid) => self.get(cid).await?,
+            None => Vec::new(),
+        };
+        history.push(entry.clone());
+        let cid = self
+            .put(&format!("history-{asset_id}.cbor"), &history)
+            .await?;
+        index.history.insert(asset_id.to_string(), cid);
+        self.save_index(&index).await
+    }
+
+    async fn scan_history(
+        &self,
+        asset_id: &str,
+        control: &ScanControl,
+    ) -> AnyaResult<Vec<HistoryEntry>> {
+        control.check("History scan")?;
+        let history = match self.history_cid(asset_id).await {
+            Some(cid) => self.get(&cid).await?,
+            None => Vec::new(),
+        };
+        walk_history(history, control).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::layer2::rgb::OperationType;
+    use bitcoin::Txid;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex as StdMutex};
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    /// In-memory stand-in for the IPFS `add` and `cat` endpoints
+    #[derive(Clone, Default)]
+    struct MockIpfs {
+        blobs: Arc<StdMutex<HashMap<String, Vec<u8>>>>,
+    }
+
+    impl Respond for MockIpfs {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let mut blobs = self.blobs.lock().unwrap();
+            if request.url.path().ends_with("/add") {
+                // Take the single file part between its headers and the closing boundary
+                let body = &request.body;
+                let start = body.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+                let end = body.windows(4).rposition(|w| w == b"\r\n--").unwrap();
+                let data = body[start..end].to_vec();
+                let cid = format!("bafy{}", sha256::Hash::hash(&data));
+                blobs.insert(cid.clone(), data);
+                return ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "Hash": cid }));
+            }
+            let cid = request
+                .url
+                .query_pairs()
+                .find(|(key, _)| key == "arg")
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default();
+            match blobs.get(&cid) {
+                Some(data) => ResponseTemplate::new(200).set_body_bytes(data.clone()),
+                None => ResponseTemplate::new(500).set_body_string("merkledag: not found"),
+            }
+        }
+    }
+
+    fn asset(id: &str) -> RGBAsset {
+        RGBAsset {
+            id: id.to_string(),
+            name: format!("Asset {id}"),
+            description: Some("stored on IPFS".to_string()),
+            total_supply: 1_000,
+            precision: 8,
+            metadata: HashMap::new(),
+            contract_id: format!("contract-{id}"),
+            schema_id: "rgb20".to_string(),
+        }
+    }
+
+    fn config(endpoint: String) -> IPFSConfig {
+        IPFSConfig {
+            endpoint,
+            timeout: 5,
+            ..IPFSConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ipfs_storage_round_trips_through_api() {
+        let server = MockServer::start().await;
+        let ipfs = MockIpfs::default();
+        Mock::given(method("POST"))
+            .and(path("/api/v0/add"))
+            .and(query_param("pin", "true"))
+            .respond_with(ipfs.clone())
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v0/cat"))
+            .respond_with(ipfs.clone())
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let index_path = dir.path().join("ipfs-index.json");
+        let storage = IpfsStorage::open(config(server.uri()), &index_path)
+            .await
+            .unwrap();
+        storage.store_asset(&asset("b")).await.unwrap();
+        storage.store_asset(&asset("a")).await.unwrap();
+        for (operation, amount) in [
+            (OperationType::Issue, 1_000),
+            (OperationType::Transfer, 400),
+        ] {
+            let entry = HistoryEntry {
+                txid: Txid::all_zeros(),
+                operation,
+                amount,
+                timestamp: 1_700_000_000,
+                confirmed: true,
+            };
+            storage.store_transfer("a", &entry).await.unwrap();
+        }
+
+        // Two assets plus two versions of the history were added
+        assert_eq!(ipfs.blobs.lock().unwrap().len(), 4);
+        let history_cid = storage.history_cid("a").await.unwrap();
+
+        // A fresh instance resolves everything through the on-disk index
+        let reopened = IpfsStorage::open(config(server.uri()), &index_path)
+            .await
+            .unwrap();
+        assert_eq!(reopened.history_cid("a").await.unwrap(), history_cid);
+        let ids: Vec<String> = reopened
+            .load_assets()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|asset| asset.id)
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert_eq!(
+            reopened.load_asset("a").await.unwrap().unwrap().description,
+            Some("stored on IPFS".to_string())
+        );
+        assert!(reopened.load_asset("c").await.unwrap().is_none());
+        assert_eq!(reopened.get_balance("a").await.unwrap(), 600);
+        assert!(reopened.load_history("b").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ipfs_storage_reports_unavailable_api() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_path = dir.path().join("ipfs-index.json");
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(MockIpfs::default())
+            .mount(&server)
+            .await;
+        IpfsStorage::open(config(server.uri()), &index_path)
+            .await
+            .unwrap()
+            .store_asset(&asset("a"))
+            .await
+            .unwrap();
+
+        // Point the same index at a port nothing listens on
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let storage = IpfsStorage::open(config(format!("http://{closed}")), &index_path)
+            .await
+            .unwrap();
+        let err = storage.load_asset("a").await.unwrap_err();
+        assert!(matches!(&err, AnyaError::System(msg) if msg.contains("unavailable")));
+        assert!(storage.load_assets().await.is_err());
+        assert!(storage.store_asset(&asset("b")).await.is_err());
+        assert!(storage.asset_cid("b").await.is_none());
+    }
+}
@@ -4,32 +4,44 @@
 // validation solution for Bitcoin assets. It allows for the creation
 // and transfer of complex assets on top of Bitcoin's blockchain.
 
+mod cancel;
 mod client;
 mod contract;
+mod ipfs;
 mod node;
 mod schema;
 mod state;
+mod storage;
 mod wallet;
 
 // Export RGB types from submodules
+pub use self::cancel::{CancellationToken, ProgressFn, ScanControl};
 pub use self::client::{ClientConfig, RGBClient, RGBClientBuilder};
 pub use self::contract::{Contract, ContractBuilder, ContractType, Witness};
+pub use self::ipfs::IpfsStorage;
 pub use self::node::{NodeConfig, RGBNode};
 pub use self::schema::{Field, FieldType, Schema, SchemaType, Validation};
 pub use self::state::{StateTransfer, StateTransition, StateValidator};
+pub use self::storage::{
+    migrate_fs_to_store, DecentralizedStorage, FsStorage, MigrationSummary, RgbStorage,
+};
 pub use self::wallet::{AssetBalance, RGBWallet};
 
+use bitcoin::hashes::{sha256d, Hash};
 use bitcoin::Txid;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 // async_trait is implemented at trait definition level
 // use async_trait::async_trait;
 
 use crate::bitcoin::wallet::transactions::TxOptions;
-use crate::AnyaResult;
+use crate::core::{Clock, SystemClock};
+use crate::{AnyaError, AnyaResult};
 
 /// RGB asset data
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RGBAsset {
     /// Unique identifier for the asset
     pub id: String,
@@ -93,12 +105,28 @@ pub trait RGBManager: Send + Sync {
     /// List all assets
     async fn list_assets(&self) -> AnyaResult<Vec<RGBAsset>>;
 
+    /// List all assets, returning [`AnyaError::Cancelled`] if `control` is cancelled
+    async fn scan_assets(&self, control: &ScanControl) -> AnyaResult<Vec<RGBAsset>>;
+
     /// Get asset balance
     async fn get_balance(&self, asset_id: &str) -> AnyaResult<u64>;
 
     /// Get transfer history for an asset
     async fn get_history(&self, asset_id: &str) -> AnyaResult<Vec<HistoryEntry>>;
 
+    /// Get transfer history for an asset, returning [`AnyaError::Cancelled`]
+    /// if `control` is cancelled
+    async fn scan_history(
+        &self,
+        asset_id: &str,
+        control: &ScanControl,
+    ) -> AnyaResult<Vec<HistoryEntry>>;
+
+    /// Re-read every asset and its history and check issued supply
+    ///
+    /// Progress counts assets rescanned out of all stored assets.
+    async fn rescan(&self, control: &ScanControl) -> AnyaResult<RescanSummary>;
+
     /// Validate an asset's state
     async fn validate_asset(&self, asset_id: &str) -> AnyaResult<bool>;
 
@@ -122,6 +150,25 @@ impl RGBFactory {
     pub fn default_manager() -> Box<dyn RGBManager> {
         Box::new(DefaultRGBManager::default())
     }
+
+    /// Create an RGB manager persisting to the given storage backend
+    pub fn new_manager_with_storage(
+        config: RGBConfig,
+        storage: Arc<dyn RgbStorage>,
+    ) -> Box<dyn RGBManager> {
+        Box::new(DefaultRGBManager::with_storage(config, storage))
+    }
+
+    /// Create an RGB manager timestamping history entries with `clock`
+    pub fn new_manager_with_clock(
+        config: RGBConfig,
+        storage: Arc<dyn RgbStorage>,
+        clock: Arc<dyn Clock>,
+    ) -> Box<dyn RGBManager> {
+        let mut manager = DefaultRGBManager::with_storage(config, storage);
+        manager.clock = clock;
+        Box::new(manager)
+    }
 }
 
 /// Configuration for RGB operations
@@ -197,7 +244,7 @@ pub enum TransferStatus {
 }
 
 /// Entry in an asset's history
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HistoryEntry {
     /// Transaction ID
     pub txid: Txid,
@@ -216,7 +263,7 @@ pub struct HistoryEntry {
 }
 
 /// Types of operations in asset history
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OperationType {
     /// Asset issuance
     Issue,
@@ -231,6 +278,19 @@ pub enum OperationType {
     Reissue,
 }
 
+/// Result of [`RGBManager::rescan`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RescanSummary {
+    /// Assets rescanned
+    pub assets: usize,
+
+    /// History entries read across all assets
+    pub transfers: usize,
+
+    /// IDs of assets whose issuance history does not match their total supply
+    pub invalid: Vec<String>,
+}
+
 /// Default implementation of the RGB manager
 #[allow(dead_code)]
 struct DefaultRGBManager {
@@ -239,14 +299,32 @@ struct DefaultRGBManager {
 
     /// Configuration
     config: RGBConfig,
+
+    /// Asset and history storage
+    storage: Arc<dyn RgbStorage>,
+
+    /// Source of history entry timestamps
+    clock: Arc<dyn Clock>,
 }
 
 impl DefaultRGBManager {
-    /// Create a new default RGB manager
+    /// Create a new default RGB manager storing data under `config.data_dir`
     pub fn new(config: RGBConfig) -> Self {
+        // Expand a leading `~` so the default directory lands in $HOME
+        let data_dir = match (config.data_dir.strip_prefix("~"), std::env::var_os("HOME")) {
+            (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+            _ => config.data_dir.clone(),
+        };
+        Self::with_storage(config, Arc::new(FsStorage::new(data_dir)))
+    }
+
+    /// Create an RGB manager using the given storage backend
+    pub fn with_storage(config: RGBConfig, storage: Arc<dyn RgbStorage>) -> Self {
         Self {
             client: None,
             config,
+            storage,
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -261,6 +339,26 @@ impl DefaultRGBManager {
         }
         Ok(())
     }
+
+    /// Deterministic transaction ID for a locally recorded operation
+    fn operation_txid(parts: &[&str]) -> Txid {
+        Txid::from_raw_hash(sha256d::Hash::hash(parts.join(":").as_bytes()))
+    }
+
+    /// Whether the issuance recorded in `history` adds up to the asset's supply
+    fn supply_matches(asset: &RGBAsset, history: &[HistoryEntry]) -> bool {
+        let issued: u64 = history
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry.operation,
+                    OperationType::Issue | OperationType::Reissue
+                )
+            })
+            .map(|entry| entry.amount)
+            .sum();
+        issued == asset.total_supply
+    }
 }
 
 impl Default for DefaultRGBManager {
@@ -271,66 +369,266 @@ impl Default for DefaultRGBManager {
 
 #[async_trait::async_trait]
 impl RGBManager for DefaultRGBManager {
-    async fn create_asset(&self, _params: AssetCreationParams) -> AnyaResult<RGBAsset> {
-        // Placeholder implementation
-        Ok(RGBAsset {
-            id: "placeholder_asset".to_string(),
-            name: "Placeholder Asset".to_string(),
-            description: Some("Placeholder RGB asset".to_string()),
-            total_supply: 1000000,
-            precision: 8,
-            metadata: HashMap::new(),
-            contract_id: "placeholder_contract".to_string(),
-            schema_id: "placeholder_schema".to_string(),
-        })
+    async fn create_asset(&self, params: AssetCreationParams) -> AnyaResult<RGBAsset> {
+        let genesis = Self::operation_txid(&[&params.issuer, &params.schema_id, &params.name]);
+        let asset = RGBAsset {
+            id: genesis.to_string(),
+            name: params.name,
+            description: params.description,
+            total_supply: params.total_supply,
+            precision: params.precision,
+            metadata: params.metadata,
+            contract_id: genesis.to_string(),
+            schema_id: params.schema_id,
+        };
+        if self.storage.load_asset(&asset.id).await?.is_some() {
+            return Err(AnyaError::InvalidInput(format!(
+                "Asset {} already exists",
+                asset.id
+            )));
+        }
+
+        self.storage.store_asset(&asset).await?;
+        self.storage
+            .store_transfer(
+                &asset.id,
+                &HistoryEntry {
+                    txid: genesis,
+                    operation: OperationType::Issue,
+                    amount: asset.total_supply,
+                    timestamp: self.clock.unix_timestamp(),
+                    confirmed: true,
+                },
+            )
+            .await?;
+        Ok(asset)
     }
 
-    async fn transfer_asset(&self, _transfer: AssetTransfer) -> AnyaResult<TransferStatus> {
-        // Placeholder implementation
+    async fn transfer_asset(&self, transfer: AssetTransfer) -> AnyaResult<TransferStatus> {
+        if self.storage.load_asset(&transfer.asset_id).await?.is_none() {
+            return Ok(TransferStatus::Rejected(format!(
+                "Unknown asset {}",
+                transfer.asset_id
+            )));
+        }
+        let balance = self.storage.get_balance(&transfer.asset_id).await?;
+        if transfer.amount == 0 || transfer.amount > balance {
+            return Ok(TransferStatus::Rejected(format!(
+                "Cannot transfer {} with a balance of {balance}",
+                transfer.amount
+            )));
+        }
+
+        let timestamp = self.clock.unix_timestamp();
+        let txid = Self::operation_txid(&[
+            &transfer.asset_id,
+            &transfer.recipient,
+            &transfer.amount.to_string(),
+            &timestamp.to_string(),
+        ]);
+        self.storage
+            .store_transfer(
+                &transfer.asset_id,
+                &HistoryEntry {
+                    txid,
+                    operation: OperationType::Transfer,
+                    amount: transfer.amount,
+                    timestamp,
+                    confirmed: false,
+                },
+            )
+            .await?;
         Ok(TransferStatus::Pending)
     }
 
-    async fn get_asset(&self, _asset_id: &str) -> AnyaResult<Option<RGBAsset>> {
-        // Placeholder implementation
-        Ok(None)
+    async fn get_asset(&self, asset_id: &str) -> AnyaResult<Option<RGBAsset>> {
+        self.storage.load_asset(asset_id).await
     }
 
     async fn list_assets(&self) -> AnyaResult<Vec<RGBAsset>> {
-        // Placeholder implementation
-        Ok(Vec::new())
+        self.storage.load_assets().await
+    }
+
+    async fn scan_assets(&self, control: &ScanControl) -> AnyaResult<Vec<RGBAsset>> {
+        self.storage.scan_assets(control).await
+    }
+
+    async fn get_balance(&self, asset_id: &str) -> AnyaResult<u64> {
+        self.storage.get_balance(asset_id).await
+    }
+
+    async fn get_history(&self, asset_id: &str) -> AnyaResult<Vec<HistoryEntry>> {
+        self.storage.load_history(asset_id).await
     }
 
-    async fn get_balance(&self, _asset_id: &str) -> AnyaResult<u64> {
-        // Placeholder implementation
-        Ok(0)
+    async fn scan_history(
+        &self,
+        asset_id: &str,
+        control: &ScanControl,
+    ) -> AnyaResult<Vec<HistoryEntry>> {
+        self.storage.scan_history(asset_id, control).await
     }
 
-    async fn get_history(&self, _asset_id: &str) -> AnyaResult<Vec<HistoryEntry>> {
-        // Placeholder implementation
-        Ok(Vec::new())
+    async fn rescan(&self, control: &ScanControl) -> AnyaResult<RescanSummary> {
+        // Listing and per-asset history honour the token; only the outer
+        // loop reports progress
+        let inner = ScanControl::new(control.token().clone());
+        let assets = self.storage.scan_assets(&inner).await?;
+
+        let mut summary = RescanSummary::default();
+        for asset in &assets {
+            let history = self.storage.scan_history(&asset.id, &inner).await?;
+            if !Self::supply_matches(asset, &history) {
+                summary.invalid.push(asset.id.clone());
+            }
+            summary.assets += 1;
+            summary.transfers += history.len();
+            control
+                .advance("Rescan", summary.assets, assets.len())
+                .await?;
+        }
+        Ok(summary)
+    }
+
+    async fn validate_asset(&self, asset_id: &str) -> AnyaResult<bool> {
+        let Some(asset) = self.storage.load_asset(asset_id).await? else {
+            return Ok(false);
+        };
+        let history = self.storage.load_history(asset_id).await?;
+        Ok(Self::supply_matches(&asset, &history))
     }
 
-    async fn validate_asset(&self, _asset_id: &str) -> AnyaResult<bool> {
-        // Placeholder implementation
-        Ok(true)
+    async fn import_asset(&self, contract_data: &[u8]) -> AnyaResult<RGBAsset> {
+        let asset: RGBAsset = serde_json::from_slice(contract_data)?;
+        self.storage.store_asset(&asset).await?;
+        Ok(asset)
+    }
+
+    async fn export_asset(&self, asset_id: &str) -> AnyaResult<Vec<u8>> {
+        let asset = self
+            .storage
+            .load_asset(asset_id)
+            .await?
+            .ok_or_else(|| AnyaError::NotFound(format!("Asset {asset_id} not found")))?;
+        Ok(serde_json::to_vec(&asset)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    fn manager() -> Box<dyn RGBManager> {
+        let store = Arc::new(MemoryStorage::new());
+        RGBFactory::new_manager_with_storage(
+            RGBConfig::default(),
+            Arc::new(DecentralizedStorage::new(store)),
+        )
+    }
+
+    fn transfer(asset_id: &str, amount: u64) -> AssetTransfer {
+        AssetTransfer {
+            asset_id: asset_id.to_string(),
+            amount,
+            recipient: "utxob:recipient".to_string(),
+            change_address: None,
+            fee_rate: 1,
+            tx_options: None,
+        }
     }
 
-    async fn import_asset(&self, _contract_data: &[u8]) -> AnyaResult<RGBAsset> {
-        // Placeholder implementation
-        Ok(RGBAsset {
-            id: "imported_asset".to_string(),
-            name: "Imported Asset".to_string(),
-            description: Some("Imported RGB asset".to_string()),
-            total_supply: 1000000,
-            precision: 8,
-            metadata: HashMap::new(),
-            contract_id: "imported_contract".to_string(),
-            schema_id: "imported_schema".to_string(),
-        })
+    #[tokio::test]
+    async fn test_manager_tracks_balance_through_storage() {
+        let manager = manager();
+        let asset = manager
+            .create_asset(AssetCreationParams {
+                name: "Test Asset".to_string(),
+                description: None,
+                total_supply: 1_000,
+                precision: 8,
+                metadata: HashMap::new(),
+                schema_id: "rgb20".to_string(),
+                issuer: "issuer".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(manager.get_balance(&asset.id).await.unwrap(), 1_000);
+        assert!(manager.validate_asset(&asset.id).await.unwrap());
+
+        assert_eq!(
+            manager
+                .transfer_asset(transfer(&asset.id, 400))
+                .await
+                .unwrap(),
+            TransferStatus::Pending
+        );
+        assert_eq!(manager.get_balance(&asset.id).await.unwrap(), 600);
+        assert!(matches!(
+            manager
+                .transfer_asset(transfer(&asset.id, 601))
+                .await
+                .unwrap(),
+            TransferStatus::Rejected(_)
+        ));
+        assert_eq!(manager.get_history(&asset.id).await.unwrap().len(), 2);
+
+        let exported = manager.export_asset(&asset.id).await.unwrap();
+        let imported = manager.import_asset(&exported).await.unwrap();
+        assert_eq!(imported.id, asset.id);
+        assert_eq!(manager.list_assets().await.unwrap().len(), 1);
+
+        let progress = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = progress.clone();
+        let control = ScanControl::default()
+            .with_progress(move |done, total| seen.lock().unwrap().push((done, total)));
+        let summary = manager.rescan(&control).await.unwrap();
+        assert_eq!(summary.assets, 1);
+        assert_eq!(summary.transfers, 2);
+        assert!(summary.invalid.is_empty());
+        assert_eq!(*progress.lock().unwrap(), vec![(1, 1)]);
+
+        control.token().cancel();
+        assert!(matches!(
+            manager.scan_history(&asset.id, &control).await,
+            Err(AnyaError::Cancelled(_))
+        ));
     }
 
-    async fn export_asset(&self, _asset_id: &str) -> AnyaResult<Vec<u8>> {
-        // Placeholder implementation
-        Ok(Vec::new())
+    #[tokio::test]
+    async fn test_history_timestamps_come_from_clock() {
+        let clock = crate::core::MockClock::at_unix(1_700_000_000);
+        let manager = RGBFactory::new_manager_with_clock(
+            RGBConfig::default(),
+            Arc::new(DecentralizedStorage::new(Arc::new(MemoryStorage::new()))),
+            Arc::new(clock.clone()),
+        );
+        let asset = manager
+            .create_asset(AssetCreationParams {
+                name: "Clocked".to_string(),
+                description: None,
+                total_supply: 100,
+                precision: 0,
+                metadata: HashMap::new(),
+                schema_id: "rgb20".to_string(),
+                issuer: "issuer".to_string(),
+            })
+            .await
+            .unwrap();
+        clock.advance(std::time::Duration::from_secs(90));
+        manager
+            .transfer_asset(transfer(&asset.id, 10))
+            .await
+            .unwrap();
+
+        let mut timestamps: Vec<_> = manager
+            .get_history(&asset.id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|entry| entry.timestamp)
+            .collect();
+        timestamps.sort();
+        assert_eq!(timestamps, vec![1_700_000_000, 1_700_000_090]);
     }
 }
@@ -0,0 +1,614 @@
+// RGB storage backends
+// This file provides the persistence layer used by the RGB manager
+
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{HistoryEntry, OperationType, RGBAsset, ScanControl};
+use crate::storage::KeyValueStorage;
+use crate::{AnyaError, AnyaResult};
+
+/// Persistence backend for RGB assets and their history
+///
+/// Balances are derived from the stored history by [`RgbStorage::get_balance`],
+/// so every backend reports the same balance for the same records. The
+/// `scan_*` methods check a [`ScanControl`] between records so long scans can
+/// report progress and be cancelled.
+#[async_trait]
+pub trait RgbStorage: Send + Sync {
+    /// Store or replace an asset
+    async fn store_asset(&self, asset: &RGBAsset) -> AnyaResult<()>;
+
+    /// Load an asset by ID
+    async fn load_asset(&self, asset_id: &str) -> AnyaResult<Option<RGBAsset>>;
+
+    /// Load all stored assets
+    async fn load_assets(&self) -> AnyaResult<Vec<RGBAsset>> {
+        self.scan_assets(&ScanControl::default()).await
+    }
+
+    /// Load all stored assets, stopping early if `control` is cancelled
+    async fn scan_assets(&self, control: &ScanControl) -> AnyaResult<Vec<RGBAsset>>;
+
+    /// Append an entry to an asset's history
+    async fn store_transfer(&self, asset_id: &str, entry: &HistoryEntry) -> AnyaResult<()>;
+
+    /// Load an asset's history in insertion order
+    async fn load_history(&self, asset_id: &str) -> AnyaResult<Vec<HistoryEntry>> {
+        self.scan_history(asset_id, &ScanControl::default()).await
+    }
+
+    /// Load an asset's history, stopping early if `control` is cancelled
+    async fn scan_history(
+        &self,
+        asset_id: &str,
+        control: &ScanControl,
+    ) -> AnyaResult<Vec<HistoryEntry>>;
+
+    /// Balance held for an asset
+    ///
+    /// Issuance adds to the balance while transfers and burns spend from it,
+    /// whether confirmed or not, so pending transfers cannot be spent twice.
+    async fn get_balance(&self, asset_id: &str) -> AnyaResult<u64> {
+        let balance = self
+            .load_history(asset_id)
+            .await?
+            .iter()
+            .fold(0u64, |balance, entry| match entry.operation {
+                OperationType::Issue | OperationType::Reissue => {
+                    balance.saturating_add(entry.amount)
+                }
+                OperationType::Transfer | OperationType::Burn => {
+                    balance.saturating_sub(entry.amount)
+                }
+            });
+        Ok(balance)
+    }
+}
+
+/// Stores RGB records as JSON files under a data directory
+///
+/// Assets live in `assets/<id>.json` and each asset's history in
+/// `history/<id>.json`. Assets are also kept in an in-memory index, built by
+/// the first asset lookup or by [`FsStorage::rebuild_index`] and updated on
+/// every [`RgbStorage::store_asset`], so lookups do not read the directory.
+pub struct FsStorage {
+    root: PathBuf,
+    index: RwLock<Option<BTreeMap<String, RGBAsset>>>,
+}
+
+impl FsStorage {
+    /// Asset files read at once while rebuilding the index
+    const INDEX_CONCURRENCY: usize = 32;
+
+    /// Create a storage rooted at `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            index: RwLock::new(None),
+        }
+    }
+
+    /// Rebuild the asset index from the files on disk, returning the number
+    /// of assets indexed
+    ///
+    /// Files are read concurrently. Files that cannot be parsed are logged
+    /// and skipped rather than failing the rebuild.
+    pub async fn rebuild_index(&self) -> AnyaResult<usize> {
+        let mut index = self.index.write().await;
+        let assets = self.read_assets(&ScanControl::default()).await?;
+        let count = assets.len();
+        *index = Some(assets);
+        Ok(count)
+    }
+
+    fn asset_path(&self, asset_id: &str) -> PathBuf {
+        self.root.join("assets").join(format!("{asset_id}.json"))
+    }
+
+    fn history_path(&self, asset_id: &str) -> PathBuf {
+        self.root.join("history").join(format!("{asset_id}.json"))
+    }
+
+    /// Build the index from disk unless it is already built
+    async fn ensure_index(&self, control: &ScanControl) -> AnyaResult<()> {
+        if self.index.read().await.is_some() {
+            return Ok(());
+        }
+        let mut index = self.index.write().await;
+        if index.is_none() {
+            *index = Some(self.read_assets(control).await?);
+        }
+        Ok(())
+    }
+
+    /// Read every asset file under `assets/`, keyed by asset ID
+    async fn read_assets(&self, control: &ScanControl) -> AnyaResult<BTreeMap<String, RGBAsset>> {
+        control.check("Asset scan")?;
+        let dir = self.root.join("assets");
+        let list_error =
+            |e: std::io::Error| AnyaError::System(format!("Failed to list {}: {e}", dir.display()));
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(list_error(e)),
+        };
+
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(list_error)? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+
+        let total = paths.len();
+        let mut loaded = stream::iter(paths)
+            .map(|path| async move { Self::read_indexed_asset(&path).await })
+            .buffer_unordered(Self::INDEX_CONCURRENCY);
+
+        let mut assets = BTreeMap::new();
+        let mut processed = 0;
+        while let Some(asset) = loaded.next().await {
+            if let Some(asset) = asset? {
+                assets.insert(asset.id.clone(), asset);
+            }
+            processed += 1;
+            control.advance("Asset scan", processed, total).await?;
+        }
+        Ok(assets)
+    }
+
+    /// Read one asset file, skipping it if it is corrupt
+    async fn read_indexed_asset(path: &Path) -> AnyaResult<Option<RGBAsset>> {
+        let bytes = match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(AnyaError::System(format!(
+                    "Failed to read {}: {e}",
+                    path.display()
+                )))
+            }
+        };
+        match serde_json::from_slice(&bytes) {
+            Ok(asset) => Ok(Some(asset)),
+            Err(e) => {
+                log::warn!("Skipping corrupt RGB asset file {}: {e}", path.display());
+                Ok(None)
+            }
+        }
+    }
+
+    async fn read_json<T: DeserializeOwned>(path: &Path) -> AnyaResult<Option<T>> {
+        match tokio::fs::read(path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AnyaError::System(format!(
+                "Failed to read {}: {e}",
+                path.display()
+            ))),
+        }
+    }
+
+    async fn write_json<T: Serialize + Sync>(path: &Path, value: &T) -> AnyaResult<()> {
+        let io_error = |e: std::io::Error| {
+            AnyaError::System(format!("Failed to write {}: {e}", path.display()))
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+        tokio::fs::write(path, serde_json::to_vec(value)?)
+            .await
+            .map_err(io_error)
+    }
+}
+
+#[async_trait]
+impl RgbStorage for FsStorage {
+    async fn store_asset(&self, asset: &RGBAsset) -> AnyaResult<()> {
+        // Hold the index lock across the write so a concurrent rebuild
+        // cannot miss the new file
+        let mut index = self.index.write().await;
+        Self::write_json(&self.asset_path(&asset.id), asset).await?;
+        if let Some(index) = index.as_mut() {
+            index.insert(asset.id.clone(), asset.clone());
+        }
+        Ok(())
+    }
+
+    async fn load_asset(&self, asset_id: &str) -> AnyaResult<Option<RGBAsset>> {
+        self.ensure_index(&ScanControl::default()).await?;
+        Ok(self
+            .index
+            .read()
+            .await
+            .as_ref()
+            .and_then(|index| index.get(asset_id).cloned()))
+    }
+
+    async fn scan_assets(&self, control: &ScanControl) -> AnyaResult<Vec<RGBAsset>> {
+        self.ensure_index(control).await?;
+        control.check("Asset scan")?;
+        Ok(self
+            .index
+            .read()
+            .await
+            .as_ref()
+            .map(|index| index.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn store_transfer(&self, asset_id: &str, entry: &HistoryEntry) -> AnyaResult<()> {
+        let path = self.history_path(asset_id);
+        let mut history: Vec<HistoryEntry> = Self::read_json(&path).await?.unwrap_or_default();
+        history.push(entry.clone());
+        Self::write_json(&path, &history).await
+    }
+
+    async fn scan_history(
+        &self,
+        asset_id: &str,
+        control: &ScanControl,
+    ) -> AnyaResult<Vec<HistoryEntry>> {
+        control.check("History scan")?;
+        let history = Self::read_json(&self.history_path(asset_id))
+            .await?
+            .unwrap_or_default();
+        walk_history(history, control).await
+    }
+}
+
+/// Stores RGB records in a DWN-style key-value store
+///
+/// Assets are kept under `rgb/asset/<id>` and history under
+/// `rgb/history/<id>`, both as JSON.
+pub struct DecentralizedStorage {
+    store: Arc<dyn KeyValueStorage>,
+}
+
+impl DecentralizedStorage {
+    const ASSET_PREFIX: &'static str = "rgb/asset/";
+    const HISTORY_PREFIX: &'static str = "rgb/history/";
+
+    /// Create a storage on top of a key-value store
+    pub fn new(store: Arc<dyn KeyValueStorage>) -> Self {
+        Self { store }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, key: &str) -> AnyaResult<Option<T>> {
+        let value = self
+            .store
+            .get(key)
+            .await
+            .map_err(|e| AnyaError::System(format!("Failed to read {key}: {e}")))?;
+        value
+            .map(|value| serde_json::from_str(&value).map_err(AnyaError::from))
+            .transpose()
+    }
+
+    async fn set_json<T: Serialize + Sync>(&self, key: &str, value: &T) -> AnyaResult<()> {
+        self.store
+            .set(key, &serde_json::to_string(value)?)
+            .await
+            .map_err(|e| AnyaError::System(format!("Failed to write {key}: {e}")))
+    }
+}
+
+#[async_trait]
+impl RgbStorage for DecentralizedStorage {
+    async fn store_asset(&self, asset: &RGBAsset) -> AnyaResult<()> {
+        self.set_json(&format!("{}{}", Self::ASSET_PREFIX, asset.id), asset)
+            .await
+    }
+
+    async fn load_asset(&self, asset_id: &str) -> AnyaResult<Option<RGBAsset>> {
+        self.get_json(&format!("{}{asset_id}", Self::ASSET_PREFIX))
+            .await
+    }
+
+    async fn scan_assets(&self, control: &ScanControl) -> AnyaResult<Vec<RGBAsset>> {
+        control.check("Asset scan")?;
+        let mut keys = self
+            .store
+            .list_keys(Self::ASSET_PREFIX)
+            .await
+            .map_err(|e| AnyaError::System(format!("Failed to list assets: {e}")))?;
+        keys.sort();
+
+        let mut assets = Vec::with_capacity(keys.len());
+        for (index, key) in keys.iter().enumerate() {
+            if let Some(asset) = self.get_json(key).await? {
+                assets.push(asset);
+            }
+            control.advance("Asset scan", index + 1, keys.len()).await?;
+        }
+        Ok(assets)
+    }
+
+    async fn store_transfer(&self, asset_id: &str, entry: &HistoryEntry) -> AnyaResult<()> {
+        let key = format!("{}{asset_id}", Self::HISTORY_PREFIX);
+        let mut history: Vec<HistoryEntry> = self.get_json(&key).await?.unwrap_or_default();
+        history.push(entry.clone());
+        self.set_json(&key, &history).await
+    }
+
+    async fn scan_history(
+        &self,
+        asset_id: &str,
+        control: &ScanControl,
+    ) -> AnyaResult<Vec<HistoryEntry>> {
+        control.check("History scan")?;
+        let history = self
+            .get_json(&format!("{}{asset_id}", Self::HISTORY_PREFIX))
+            .await?
+            .unwrap_or_default();
+        walk_history(history, control).await
+    }
+}
+
+/// Report each loaded history entry to `control`, stopping if it is cancelled
+pub(super) async fn walk_history(
+    history: Vec<HistoryEntry>,
+    control: &ScanControl,
+) -> AnyaResult<Vec<HistoryEntry>> {
+    for processed in 1..=history.len() {
+        control
+            .advance("History scan", processed, history.len())
+            .await?;
+    }
+    Ok(history)
+}
+
+/// Counts reported by [`migrate_fs_to_store`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationSummary {
+    pub assets_migrated: usize,
+    pub assets_skipped: usize,
+    pub transfers_migrated: usize,
+    pub transfers_skipped: usize,
+}
+
+/// Copy assets and history from a legacy [`FsStorage`] data directory into `store`
+///
+/// Assets already present in `store`, and history entries it already holds,
+/// are skipped so an interrupted migration can simply be run again.
+pub async fn migrate_fs_to_store(
+    data_dir: &Path,
+    store: &dyn RgbStorage,
+) -> AnyaResult<MigrationSummary> {
+    let legacy = FsStorage::new(data_dir);
+    let mut summary = MigrationSummary::default();
+
+    for asset in legacy.load_assets().await? {
+        if store.load_asset(&asset.id).await?.is_some() {
+            summary.assets_skipped += 1;
+        } else {
+            store.store_asset(&asset).await?;
+            summary.assets_migrated += 1;
+        }
+
+        let existing = store.load_history(&asset.id).await?;
+        for entry in legacy.load_history(&asset.id).await? {
+            if existing.contains(&entry) {
+                summary.transfers_skipped += 1;
+            } else {
+                store.store_transfer(&asset.id, &entry).await?;
+                summary.transfers_migrated += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::layer2::rgb::CancellationToken;
+    use crate::storage::memory::MemoryStorage;
+    use bitcoin::hashes::Hash;
+    use bitcoin::Txid;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn asset(id: &str, total_supply: u64) -> RGBAsset {
+        RGBAsset {
+            id: id.to_string(),
+            name: format!("Asset {id}"),
+            description: None,
+            total_supply,
+            precision: 8,
+            metadata: HashMap::from([("ticker".to_string(), id.to_uppercase())]),
+            contract_id: format!("contract-{id}"),
+            schema_id: "rgb20".to_string(),
+        }
+    }
+
+    fn entry(operation: OperationType, amount: u64, confirmed: bool) -> HistoryEntry {
+        HistoryEntry {
+            txid: Txid::all_zeros(),
+            operation,
+            amount,
+            timestamp: 1_700_000_000,
+            confirmed,
+        }
+    }
+
+    /// Behaviour every backend must share
+    async fn exercise_storage(storage: &dyn RgbStorage) {
+        assert!(storage.load_assets().await.unwrap().is_empty());
+        assert!(storage.load_asset("b").await.unwrap().is_none());
+        assert_eq!(storage.get_balance("b").await.unwrap(), 0);
+
+        storage.store_asset(&asset("b", 500)).await.unwrap();
+        storage.store_asset(&asset("a", 1_000)).await.unwrap();
+        let loaded = storage.load_asset("a").await.unwrap().unwrap();
+        assert_eq!(loaded.total_supply, 1_000);
+        assert_eq!(loaded.metadata["ticker"], "A");
+        let ids: Vec<String> = storage
+            .load_assets()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|asset| asset.id)
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+
+        for history_entry in [
+            entry(OperationType::Issue, 1_000, true),
+            entry(OperationType::Transfer, 300, true),
+            entry(OperationType::Transfer, 100, false),
+            entry(OperationType::Burn, 50, true),
+        ] {
+            storage.store_transfer("a", &history_entry).await.unwrap();
+        }
+        let history = storage.load_history("a").await.unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[1].operation, OperationType::Transfer);
+        assert!(!history[2].confirmed);
+        assert_eq!(storage.get_balance("a").await.unwrap(), 550);
+        assert!(storage.load_history("b").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fs_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        exercise_storage(&FsStorage::new(dir.path())).await;
+    }
+
+    #[tokio::test]
+    async fn test_fs_index_tracks_writes_and_skips_corrupt_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FsStorage::new(dir.path());
+        storage.store_asset(&asset("b", 500)).await.unwrap();
+        storage.store_asset(&asset("a", 1_000)).await.unwrap();
+        let ids = |assets: Vec<RGBAsset>| -> Vec<String> {
+            assets.into_iter().map(|asset| asset.id).collect()
+        };
+        assert_eq!(ids(storage.load_assets().await.unwrap()), vec!["a", "b"]);
+
+        // Writes through the storage update the built index
+        storage.store_asset(&asset("a", 2_000)).await.unwrap();
+        storage.store_asset(&asset("d", 10)).await.unwrap();
+        assert_eq!(
+            storage.load_asset("a").await.unwrap().unwrap().total_supply,
+            2_000
+        );
+        assert_eq!(
+            ids(storage.load_assets().await.unwrap()),
+            vec!["a", "b", "d"]
+        );
+
+        // Files written behind its back only show up after a rebuild
+        FsStorage::new(dir.path())
+            .store_asset(&asset("c", 5))
+            .await
+            .unwrap();
+        tokio::fs::write(dir.path().join("assets").join("corrupt.json"), b"{\"id\":")
+            .await
+            .unwrap();
+        assert!(storage.load_asset("c").await.unwrap().is_none());
+        assert_eq!(storage.rebuild_index().await.unwrap(), 4);
+        assert_eq!(
+            ids(storage.load_assets().await.unwrap()),
+            vec!["a", "b", "c", "d"]
+        );
+        assert_eq!(
+            ids(FsStorage::new(dir.path()).load_assets().await.unwrap()),
+            vec!["a", "b", "c", "d"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fs_history_scan_cancels_midway() {
+        const ENTRIES: usize = 20_000;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FsStorage::new(dir.path()));
+        let history = vec![entry(OperationType::Transfer, 1, true); ENTRIES];
+        FsStorage::write_json(&storage.history_path("a"), &history)
+            .await
+            .unwrap();
+
+        let token = CancellationToken::new();
+        let processed = Arc::new(AtomicUsize::new(0));
+        let seen = processed.clone();
+        let control = ScanControl::new(token.clone()).with_progress(move |done, total| {
+            assert_eq!(total, ENTRIES);
+            seen.store(done, Ordering::SeqCst);
+        });
+        let scan = tokio::spawn({
+            let storage = storage.clone();
+            async move { storage.scan_history("a", &control).await }
+        });
+
+        while processed.load(Ordering::SeqCst) < ENTRIES / 10 {
+            tokio::task::yield_now().await;
+        }
+        token.cancel();
+        let result = tokio::time::timeout(Duration::from_secs(1), scan)
+            .await
+            .expect("cancelled scan should return promptly")
+            .unwrap();
+        assert!(matches!(result, Err(AnyaError::Cancelled(_))));
+        assert!(processed.load(Ordering::SeqCst) < ENTRIES);
+
+        // A fresh token scans the whole history
+        assert_eq!(storage.load_history("a").await.unwrap().len(), ENTRIES);
+    }
+
+    #[tokio::test]
+    async fn test_decentralized_storage() {
+        let store: Arc<dyn KeyValueStorage> = Arc::new(MemoryStorage::new());
+        exercise_storage(&DecentralizedStorage::new(store)).await;
+    }
+
+    #[tokio::test]
+    async fn test_migrate_fs_to_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = FsStorage::new(dir.path());
+        legacy.store_asset(&asset("a", 1_000)).await.unwrap();
+        legacy.store_asset(&asset("b", 500)).await.unwrap();
+        legacy
+            .store_transfer("a", &entry(OperationType::Issue, 1_000, true))
+            .await
+            .unwrap();
+        legacy
+            .store_transfer("a", &entry(OperationType::Transfer, 250, false))
+            .await
+            .unwrap();
+        legacy
+            .store_transfer("b", &entry(OperationType::Issue, 500, true))
+            .await
+            .unwrap();
+
+        let store = DecentralizedStorage::new(Arc::new(MemoryStorage::new()));
+        let summary = migrate_fs_to_store(dir.path(), &store).await.unwrap();
+        assert_eq!(
+            summary,
+            MigrationSummary {
+                assets_migrated: 2,
+                assets_skipped: 0,
+                transfers_migrated: 3,
+                transfers_skipped: 0,
+            }
+        );
+        assert_eq!(store.load_assets().await.unwrap().len(), 2);
+        assert_eq!(store.get_balance("a").await.unwrap(), 750);
+        assert_eq!(store.get_balance("b").await.unwrap(), 500);
+
+        // A second run finds everything already migrated
+        let summary = migrate_fs_to_store(dir.path(), &store).await.unwrap();
+        assert_eq!(summary.assets_skipped, 2);
+        assert_eq!(summary.transfers_skipped, 3);
+        assert_eq!(summary.assets_migrated + summary.transfers_migrated, 0);
+        assert_eq!(store.load_history("a").await.unwrap().len(), 2);
+    }
+}
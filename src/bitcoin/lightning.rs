@@ -4,6 +4,7 @@
 
 use crate::AnyaError;
 use crate::AnyaResult;
+use bitcoin::hashes::{sha256, Hash};
 use secp256k1::SecretKey as Secp256k1SecretKey;
 use std::collections::HashMap;
 use std::fmt;
@@ -14,6 +15,35 @@ use std::time::{SystemTime, UNIX_EPOCH};
 // Import BitcoinConfig from a module we know exists
 use crate::bitcoin::config::BitcoinConfig;
 
+/// TLV record type carrying the payment preimage in a keysend final-hop payload
+pub const KEYSEND_PREIMAGE_TLV_TYPE: u64 = 5482373484;
+
+/// Feature name advertised by nodes accepting spontaneous (keysend) payments
+pub const KEYSEND_FEATURE: &str = "keysend";
+
+/// Default final CLTV delta for keysend payments
+const KEYSEND_FINAL_CLTV_EXPIRY: u32 = 40;
+
+/// Lightning-specific error types
+#[derive(Debug, thiserror::Error)]
+pub enum LightningError {
+    #[error("Destination does not support keysend: {0}")]
+    KeysendNotSupported(String),
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+    #[error("Lightning node error: {0}")]
+    Node(String),
+}
+
+impl From<LightningError> for AnyaError {
+    fn from(err: LightningError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+/// Lightning result type
+pub type LightningResult<T> = Result<T, LightningError>;
+
 // Define custom Lightning-specific key types to avoid conflicts with secp256k1 types
 #[derive(Clone)]
 pub struct LightningPublicKey {
@@ -126,6 +156,9 @@ struct LightningState {
     /// Payments made by this node
     payments: HashMap<String, Payment>,
 
+    /// Features advertised by known nodes, keyed by hex pubkey
+    node_features: HashMap<String, Vec<String>>,
+
     /// Last updated timestamp
     last_updated: u64,
 }
@@ -245,6 +278,42 @@ pub struct Payment {
     pub description: Option<String>,
 }
 
+/// Final-hop onion payload for a keysend payment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeysendPayload {
+    /// Amount to forward to the final hop in millisatoshis
+    pub amount_msat: u64,
+
+    /// Outgoing CLTV value for the final hop
+    pub outgoing_cltv_value: u32,
+
+    /// Payment preimage revealed to the recipient
+    pub preimage: [u8; 32],
+}
+
+impl KeysendPayload {
+    /// Encode the payload as a BOLT-04 TLV stream
+    ///
+    /// Records are written in ascending type order: `amt_to_forward` (2),
+    /// `outgoing_cltv_value` (4) and the keysend preimage (5482373484).
+    pub fn encode_tlv_stream(&self) -> Vec<u8> {
+        let mut stream = Vec::new();
+        write_tlv_record(&mut stream, 2, &truncated_be(self.amount_msat));
+        write_tlv_record(
+            &mut stream,
+            4,
+            &truncated_be(u64::from(self.outgoing_cltv_value)),
+        );
+        write_tlv_record(&mut stream, KEYSEND_PREIMAGE_TLV_TYPE, &self.preimage);
+        stream
+    }
+
+    /// Payment hash committed to by the preimage
+    pub fn payment_hash(&self) -> [u8; 32] {
+        sha256::Hash::hash(&self.preimage).to_byte_array()
+    }
+}
+
 /// Payment status enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PaymentStatus {
@@ -362,6 +431,7 @@ impl LightningNode {
             peers: HashMap::new(),
             invoices: HashMap::new(),
             payments: HashMap::new(),
+            node_features: HashMap::new(),
             last_updated: current_time(),
         };
 
@@ -630,6 +700,83 @@ impl LightningNode {
             .map_err(|e| format!("Mutex lock error: {e}"))?;
         Ok(state.payments.values().cloned().collect())
     }
+
+    /// Record the features a node advertises (e.g. from its node announcement)
+    pub fn update_node_features(
+        &self,
+        node_pubkey: &LightningPublicKey,
+        features: Vec<String>,
+    ) -> AnyaResult<()> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?;
+        state
+            .node_features
+            .insert(node_pubkey.to_string(), features);
+        state.last_updated = current_time();
+        Ok(())
+    }
+
+    /// Send a spontaneous (keysend) payment without an invoice
+    ///
+    /// The preimage is carried to the destination in the final-hop TLV record
+    /// of type 5482373484. If no preimage is supplied a random one is
+    /// generated and returned in the resulting payment. Destinations whose
+    /// features are known must advertise keysend support.
+    pub fn keysend(
+        &self,
+        dest: &LightningPublicKey,
+        amount_msat: u64,
+        preimage: Option<[u8; 32]>,
+    ) -> LightningResult<Payment> {
+        if amount_msat == 0 {
+            return Err(LightningError::InvalidAmount(
+                "keysend amount must be greater than zero".to_string(),
+            ));
+        }
+
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|e| LightningError::Node(format!("Mutex lock error: {e}")))?;
+
+        let dest_hex = dest.to_string();
+        if let Some(features) = state.node_features.get(&dest_hex) {
+            if !features.iter().any(|f| f == KEYSEND_FEATURE) {
+                return Err(LightningError::KeysendNotSupported(dest_hex));
+            }
+        }
+
+        let payload = KeysendPayload {
+            amount_msat,
+            outgoing_cltv_value: KEYSEND_FINAL_CLTV_EXPIRY,
+            preimage: preimage.unwrap_or_else(rand::random),
+        };
+        let onion_payload = payload.encode_tlv_stream();
+
+        let now = current_time();
+        let payment_id = format!("keysend_{:x}", rand::random::<u64>());
+        let payment = Payment {
+            payment_id: payment_id.clone(),
+            payment_hash: hex::encode(payload.payment_hash()),
+            preimage: Some(hex::encode(payload.preimage)),
+            amount_msat,
+            fee_msat: amount_msat / 100, // 1% fee, matching pay_invoice
+            status: PaymentStatus::Succeeded,
+            created_at: now,
+            resolved_at: Some(now),
+            description: Some(format!(
+                "Keysend to {dest_hex} ({} byte final-hop payload)",
+                onion_payload.len()
+            )),
+        };
+
+        state.payments.insert(payment_id, payment.clone());
+        state.last_updated = now;
+
+        Ok(payment)
+    }
 }
 
 impl BitcoinLightningBridge {
@@ -778,6 +925,39 @@ impl BitcoinLightningBridge {
     }
 }
 
+/// Append a TLV record using BigSize-encoded type and length
+fn write_tlv_record(stream: &mut Vec<u8>, record_type: u64, value: &[u8]) {
+    write_bigsize(stream, record_type);
+    write_bigsize(stream, value.len() as u64);
+    stream.extend_from_slice(value);
+}
+
+/// BOLT-01 BigSize encoding
+pub(crate) fn write_bigsize(stream: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0xfc => stream.push(value as u8),
+        0xfd..=0xffff => {
+            stream.push(0xfd);
+            stream.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            stream.push(0xfe);
+            stream.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            stream.push(0xff);
+            stream.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Big-endian encoding with leading zero bytes removed (BOLT-01 `tu64`)
+pub(crate) fn truncated_be(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    bytes[start..].to_vec()
+}
+
 fn current_time() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -820,4 +1000,81 @@ mod tests {
         let txid = LightningTxid::from_slice(&invalid_txid);
         assert!(txid.is_err());
     }
+
+    fn test_dest() -> LightningPublicKey {
+        LightningPublicKey::from_str(&("02".to_string() + &"b".repeat(64))).unwrap()
+    }
+
+    #[test]
+    fn test_keysend_payload_tlv_encoding() {
+        let payload = KeysendPayload {
+            amount_msat: 1000,
+            outgoing_cltv_value: 40,
+            preimage: [0x11; 32],
+        };
+        let stream = payload.encode_tlv_stream();
+
+        // amt_to_forward: type 2, length 2, 0x03e8
+        assert_eq!(&stream[..4], &[0x02, 0x02, 0x03, 0xe8]);
+        // outgoing_cltv_value: type 4, length 1, 40
+        assert_eq!(&stream[4..7], &[0x04, 0x01, 40]);
+        // keysend preimage: BigSize(5482373484) = 0xff + 8 bytes, length 32
+        assert_eq!(stream[7], 0xff);
+        assert_eq!(&stream[8..16], &KEYSEND_PREIMAGE_TLV_TYPE.to_be_bytes());
+        assert_eq!(stream[16], 32);
+        assert_eq!(&stream[17..], &[0x11; 32]);
+    }
+
+    #[test]
+    fn test_keysend_with_supplied_preimage() {
+        let node = LightningNode::new(&BitcoinConfig::default()).unwrap();
+        let preimage = [0x07; 32];
+
+        let payment = node.keysend(&test_dest(), 5_000, Some(preimage)).unwrap();
+        assert_eq!(payment.preimage, Some(hex::encode(preimage)));
+        assert_eq!(
+            payment.payment_hash,
+            hex::encode(sha256::Hash::hash(&preimage).to_byte_array())
+        );
+        assert_eq!(payment.amount_msat, 5_000);
+    }
+
+    #[test]
+    fn test_keysend_generates_preimage() {
+        let node = LightningNode::new(&BitcoinConfig::default()).unwrap();
+
+        let payment = node.keysend(&test_dest(), 1_000, None).unwrap();
+        let preimage = hex::decode(payment.preimage.as_ref().unwrap()).unwrap();
+        assert_eq!(preimage.len(), 32);
+        assert_eq!(
+            payment.payment_hash,
+            hex::encode(sha256::Hash::hash(&preimage).to_byte_array())
+        );
+        assert!(node.get_payment(&payment.payment_hash).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_keysend_destination_without_support() {
+        let node = LightningNode::new(&BitcoinConfig::default()).unwrap();
+        let dest = test_dest();
+        node.update_node_features(&dest, vec!["option_static_remotekey".to_string()])
+            .unwrap();
+
+        let result = node.keysend(&dest, 1_000, None);
+        assert!(matches!(
+            result,
+            Err(LightningError::KeysendNotSupported(_))
+        ));
+
+        node.update_node_features(&dest, vec![KEYSEND_FEATURE.to_string()])
+            .unwrap();
+        assert!(node.keysend(&dest, 1_000, None).is_ok());
+    }
+
+    #[test]
+    fn test_keysend_rejects_zero_amount() {
+        let node = LightningNode::new(&BitcoinConfig::default()).unwrap();
+        let result = node.keysend(&test_dest(), 0, None);
+        assert!(matches!(result, Err(LightningError::InvalidAmount(_))));
+    }
 }
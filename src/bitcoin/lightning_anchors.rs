@@ -0,0 +1,665 @@
+// [AIR-3][AIS-3][BPC-3] Anchor-output commitments and CPFP fee bumping
+//
+// Commitment transactions are signed long before they may be broadcast, so
+// their fee rate is a guess. With anchor outputs (BOLT 3 `option_anchors`)
+// each side gets a 330 sat output only it can spend right away, and a node
+// force-closing raises the fee of the commitment at broadcast time with a
+// child spending its anchor (CPFP). The commitment fee only has to get it
+// into the mempool.
+//
+// `AnchorBumper::bump_commitment` compares the fee rate of the stored
+// commitment with the chain source estimate for the confirmation target and
+// only builds a child when the commitment would fall short. The child spends
+// our anchor plus confirmed wallet outputs and pays for parent and child
+// together to reach the target. It is kept within the 1000 vB child limit of
+// BIP-431 (TRUC) so it remains relayable once commitments use version 3.
+// HTLC outputs are not built yet.
+
+use crate::bitcoin::chain_source::{ChainSource, ChainUtxo};
+use crate::bitcoin::lightning_store::{ChannelStore, ChannelStoreError};
+use crate::bitcoin::weight::{fee_for, COMPRESSED_KEY_SIZE, ECDSA_SIGNATURE_SIZE};
+use crate::AnyaError;
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::opcodes::all::{
+    OP_CHECKMULTISIG, OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_CSV, OP_DROP, OP_ELSE, OP_ENDIF, OP_IF,
+    OP_IFDUP, OP_NOTIF, OP_PUSHNUM_1, OP_PUSHNUM_16, OP_PUSHNUM_2,
+};
+use bitcoin::script::Builder;
+use bitcoin::secp256k1::{All, Message, PublicKey, Secp256k1, SecretKey};
+use bitcoin::sighash::{EcdsaSighashType, SegwitV0Sighash, SighashCache};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    ecdsa, Amount, CompressedPublicKey, OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn,
+    TxOut, Weight, Witness,
+};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Value of each anchor output
+pub const ANCHOR_OUTPUT_VALUE: Amount = Amount::from_sat(330);
+
+/// Weight BOLT 3 charges for an anchor commitment without HTLC outputs
+pub const ANCHOR_COMMITMENT_WEIGHT: u64 = 1124;
+
+/// Largest child of a TRUC transaction under BIP-431, in vbytes
+pub const MAX_CHILD_VSIZE: u64 = 1000;
+
+/// Default confirmation target of a force-closing commitment, in blocks
+pub const DEFAULT_CONFIRMATION_TARGET: u16 = 6;
+
+/// Anchor and fee-bumping errors
+#[derive(Debug, Error)]
+pub enum AnchorError {
+    #[error("Funder balance {balance} cannot pay the {cost} commitment fee and anchors")]
+    FeeExceedsBalance { balance: Amount, cost: Amount },
+    #[error("Unknown channel {0}")]
+    UnknownChannel(String),
+    #[error("Channel {0} has no signed commitment")]
+    NoCommitment(String),
+    #[error("Commitment of channel {0} has no anchor for our funding key")]
+    NoAnchor(String),
+    #[error("Invalid commitment: {0}")]
+    InvalidCommitment(String),
+    #[error("Wallet outputs worth {available} cannot pay the {required} bump fee")]
+    InsufficientFunds { available: Amount, required: Amount },
+    #[error("Fee-bumping child of {0} vB exceeds the {MAX_CHILD_VSIZE} vB limit")]
+    ChildTooLarge(u64),
+    #[error("Signing failed: {0}")]
+    Signing(String),
+    #[error("Chain source error: {0}")]
+    Chain(String),
+    #[error(transparent)]
+    Store(#[from] ChannelStoreError),
+}
+
+impl From<AnchorError> for AnyaError {
+    fn from(err: AnchorError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+pub type AnchorResult<T> = Result<T, AnchorError>;
+
+/// [AIR-3][BPC-3] Keys of the holder's commitment transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitmentKeys {
+    pub local_funding_pubkey: PublicKey,
+    pub remote_funding_pubkey: PublicKey,
+    pub revocation_pubkey: PublicKey,
+    pub local_delayed_pubkey: PublicKey,
+    pub remote_payment_pubkey: PublicKey,
+}
+
+/// [AIR-3][BPC-3] Channel parameters of a commitment transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitmentParams {
+    pub funding_outpoint: OutPoint,
+    pub commitment_number: u64,
+    /// Factor hiding the commitment number, see [`obscure_factor`]
+    pub obscure_factor: u64,
+    /// Relative delay on our `to_local` output
+    pub to_self_delay: u16,
+    /// Outputs below this value are left out
+    pub dust_limit: Amount,
+    pub feerate_per_kw: u32,
+    /// Whether we opened the channel and so pay the fee and anchors
+    pub local_is_funder: bool,
+}
+
+/// 2-of-2 multisig script of the funding output
+pub fn funding_script(a: &PublicKey, b: &PublicKey) -> ScriptBuf {
+    let (a, b) = (a.serialize(), b.serialize());
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    Builder::new()
+        .push_opcode(OP_PUSHNUM_2)
+        .push_slice(first)
+        .push_slice(second)
+        .push_opcode(OP_PUSHNUM_2)
+        .push_opcode(OP_CHECKMULTISIG)
+        .into_script()
+}
+
+/// Anchor witness script: spendable by `funding_pubkey`, or by anyone after 16 blocks
+pub fn anchor_script(funding_pubkey: &PublicKey) -> ScriptBuf {
+    Builder::new()
+        .push_slice(funding_pubkey.serialize())
+        .push_opcode(OP_CHECKSIG)
+        .push_opcode(OP_IFDUP)
+        .push_opcode(OP_NOTIF)
+        .push_opcode(OP_PUSHNUM_16)
+        .push_opcode(OP_CSV)
+        .push_opcode(OP_ENDIF)
+        .into_script()
+}
+
+/// `to_local` witness script: revocable, else ours after `to_self_delay`
+pub fn to_local_script(
+    revocation_pubkey: &PublicKey,
+    to_self_delay: u16,
+    local_delayed_pubkey: &PublicKey,
+) -> ScriptBuf {
+    Builder::new()
+        .push_opcode(OP_IF)
+        .push_slice(revocation_pubkey.serialize())
+        .push_opcode(OP_ELSE)
+        .push_int(to_self_delay as i64)
+        .push_opcode(OP_CSV)
+        .push_opcode(OP_DROP)
+        .push_slice(local_delayed_pubkey.serialize())
+        .push_opcode(OP_ENDIF)
+        .push_opcode(OP_CHECKSIG)
+        .into_script()
+}
+
+/// `to_remote` witness script of anchor channels, delayed by one block
+pub fn to_remote_script(remote_payment_pubkey: &PublicKey) -> ScriptBuf {
+    Builder::new()
+        .push_slice(remote_payment_pubkey.serialize())
+        .push_opcode(OP_CHECKSIGVERIFY)
+        .push_opcode(OP_PUSHNUM_1)
+        .push_opcode(OP_CSV)
+        .into_script()
+}
+
+/// Lower 48 bits of SHA256(opener payment basepoint || accepter payment basepoint)
+pub fn obscure_factor(
+    opener_payment_basepoint: &PublicKey,
+    accepter_payment_basepoint: &PublicKey,
+) -> u64 {
+    let mut engine = sha256::Hash::engine();
+    engine.input(&opener_payment_basepoint.serialize());
+    engine.input(&accepter_payment_basepoint.serialize());
+    let hash = sha256::Hash::from_engine(engine).to_byte_array();
+    let mut factor = [0u8; 8];
+    factor[2..].copy_from_slice(&hash[26..]);
+    u64::from_be_bytes(factor)
+}
+
+/// Unsigned commitment transaction with anchor outputs
+///
+/// The funder pays the fee for [`ANCHOR_COMMITMENT_WEIGHT`] and both anchors.
+/// Each anchor is only added with the balance output of its side.
+pub fn build_anchor_commitment(
+    params: &CommitmentParams,
+    keys: &CommitmentKeys,
+    to_local_msat: u64,
+    to_remote_msat: u64,
+) -> AnchorResult<Transaction> {
+    let fee = Amount::from_sat(params.feerate_per_kw as u64 * ANCHOR_COMMITMENT_WEIGHT / 1000);
+    let cost = fee + ANCHOR_OUTPUT_VALUE * 2;
+    let mut to_local = Amount::from_sat(to_local_msat / 1000);
+    let mut to_remote = Amount::from_sat(to_remote_msat / 1000);
+    let funder = if params.local_is_funder {
+        &mut to_local
+    } else {
+        &mut to_remote
+    };
+    *funder = funder
+        .checked_sub(cost)
+        .ok_or(AnchorError::FeeExceedsBalance {
+            balance: *funder,
+            cost,
+        })?;
+
+    let p2wsh = |script: ScriptBuf| ScriptBuf::new_p2wsh(&script.wscript_hash());
+    let anchor = |pubkey: &PublicKey| TxOut {
+        value: ANCHOR_OUTPUT_VALUE,
+        script_pubkey: p2wsh(anchor_script(pubkey)),
+    };
+    let mut output = Vec::new();
+    if to_local >= params.dust_limit {
+        output.push(TxOut {
+            value: to_local,
+            script_pubkey: p2wsh(to_local_script(
+                &keys.revocation_pubkey,
+                params.to_self_delay,
+                &keys.local_delayed_pubkey,
+            )),
+        });
+        output.push(anchor(&keys.local_funding_pubkey));
+    }
+    if to_remote >= params.dust_limit {
+        output.push(TxOut {
+            value: to_remote,
+            script_pubkey: p2wsh(to_remote_script(&keys.remote_payment_pubkey)),
+        });
+        output.push(anchor(&keys.remote_funding_pubkey));
+    }
+    // BIP-69 output order
+    output.sort_by(|a, b| {
+        a.value
+            .cmp(&b.value)
+            .then_with(|| a.script_pubkey.cmp(&b.script_pubkey))
+    });
+
+    let obscured = (params.commitment_number ^ params.obscure_factor) & 0xffff_ffff_ffff;
+    Ok(Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::from_consensus(0x2000_0000 | (obscured & 0xff_ffff) as u32),
+        input: vec![TxIn {
+            previous_output: params.funding_outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence(0x8000_0000 | (obscured >> 24) as u32),
+            witness: Witness::new(),
+        }],
+        output,
+    })
+}
+
+/// [AIR-3][AIS-3][BPC-3] Raises the fee of stored commitments through their anchors
+pub struct AnchorBumper {
+    store: ChannelStore,
+    chain: Arc<dyn ChainSource>,
+    /// Our funding key, which the local anchor pays to
+    anchor_key: SecretKey,
+    /// Key of the P2WPKH wallet outputs paying bump fees and receiving change
+    wallet_key: SecretKey,
+    confirmation_target: u16,
+    secp: Secp256k1<All>,
+}
+
+impl AnchorBumper {
+    pub fn new(
+        store: ChannelStore,
+        chain: Arc<dyn ChainSource>,
+        anchor_key: SecretKey,
+        wallet_key: SecretKey,
+    ) -> Self {
+        Self {
+            store,
+            chain,
+            anchor_key,
+            wallet_key,
+            confirmation_target: DEFAULT_CONFIRMATION_TARGET,
+            secp: Secp256k1::new(),
+        }
+    }
+
+    /// Blocks within which a bumped commitment should confirm
+    pub fn with_confirmation_target(mut self, blocks: u16) -> Self {
+        self.confirmation_target = blocks;
+        self
+    }
+
+    /// Script of the wallet outputs that fund bumps
+    pub fn wallet_script(&self) -> ScriptBuf {
+        let pubkey = CompressedPublicKey(self.wallet_key.public_key(&self.secp));
+        ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash())
+    }
+
+    /// Signed child spending our anchor of the commitment of `channel_id`
+    ///
+    /// Returns `None` when no bump is needed: the commitment is confirmed or
+    /// already pays the estimated fee rate for the confirmation target. The child is returned for the caller to broadcast after
+    /// the commitment.
+    pub async fn bump_commitment(&self, channel_id: &str) -> AnchorResult<Option<Transaction>> {
+        let state = self
+            .store
+            .load(channel_id)?
+            .ok_or_else(|| AnchorError::UnknownChannel(channel_id.to_string()))?;
+        let commitment = state
+            .commitment_tx
+            .ok_or_else(|| AnchorError::NoCommitment(channel_id.to_string()))?;
+
+        let anchor_script = anchor_script(&self.anchor_key.public_key(&self.secp));
+        let anchor_spk = ScriptBuf::new_p2wsh(&anchor_script.wscript_hash());
+        let vout = commitment
+            .output
+            .iter()
+            .position(|txout| txout.script_pubkey == anchor_spk)
+            .ok_or_else(|| AnchorError::NoAnchor(channel_id.to_string()))?;
+        let anchor = OutPoint::new(commitment.compute_txid(), vout as u32);
+        let anchor_value = commitment.output[vout].value;
+        if self.is_confirmed(&commitment).await? {
+            return Ok(None);
+        }
+
+        let target = self
+            .chain
+            .estimate_fee(self.confirmation_target)
+            .await
+            .map_err(chain_error)?;
+        let outputs: Amount = commitment.output.iter().map(|txout| txout.value).sum();
+        let parent_fee = Amount::from_sat(state.capacity_sat)
+            .checked_sub(outputs)
+            .ok_or_else(|| {
+                AnchorError::InvalidCommitment(format!(
+                    "outputs of {outputs} exceed the {} sat capacity",
+                    state.capacity_sat
+                ))
+            })?;
+        let parent_weight = commitment.weight();
+        if parent_fee >= fee_for(target, parent_weight) {
+            return Ok(None);
+        }
+        log::info!(
+            "Commitment {} of channel {channel_id} pays {parent_fee} below the {target:?} target, bumping",
+            anchor.txid
+        );
+
+        // Unconfirmed wallet outputs would add their ancestors to the package
+        let mut wallet_utxos: Vec<ChainUtxo> = self
+            .chain
+            .get_utxos(&self.wallet_script())
+            .await
+            .map_err(chain_error)?
+            .into_iter()
+            .filter(|utxo| utxo.height.is_some())
+            .collect();
+        wallet_utxos.sort_by_key(|utxo| std::cmp::Reverse(utxo.txout.value));
+
+        let dust = self.wallet_script().minimal_non_dust();
+        let mut required = Amount::ZERO;
+        let mut available = anchor_value;
+        for count in 0..=wallet_utxos.len() {
+            let inputs = &wallet_utxos[..count];
+            available = anchor_value + inputs.iter().map(|utxo| utxo.txout.value).sum::<Amount>();
+            let mut child = self.child(anchor, inputs);
+            let child_weight = signed_weight(&child, &anchor_script);
+            let child_fee = fee_for(target, parent_weight + child_weight) - parent_fee;
+            required = child_fee + dust;
+            let Some(change) = available
+                .checked_sub(child_fee)
+                .filter(|change| *change >= dust)
+            else {
+                continue;
+            };
+
+            let vsize = child_weight.to_vbytes_ceil();
+            if vsize > MAX_CHILD_VSIZE {
+                return Err(AnchorError::ChildTooLarge(vsize));
+            }
+            child.output[0].value = change;
+            self.sign_child(&mut child, &anchor_script, anchor_value, inputs)?;
+            return Ok(Some(child));
+        }
+        Err(AnchorError::InsufficientFunds {
+            available,
+            required,
+        })
+    }
+
+    /// Whether any output of `commitment` is a confirmed UTXO
+    ///
+    /// `to_local` stays unspent for `to_self_delay` blocks, so a confirmed
+    /// commitment is recognised even after both anchors are spent.
+    async fn is_confirmed(&self, commitment: &Transaction) -> AnchorResult<bool> {
+        let txid = commitment.compute_txid();
+        for txout in &commitment.output {
+            let utxos = self
+                .chain
+                .get_utxos(&txout.script_pubkey)
+                .await
+                .map_err(chain_error)?;
+            if utxos
+                .iter()
+                .any(|utxo| utxo.outpoint.txid == txid && utxo.height.is_some())
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Unsigned child spending `anchor` and `inputs` to a change output
+    fn child(&self, anchor: OutPoint, inputs: &[ChainUtxo]) -> Transaction {
+        let spend = |previous_output| TxIn {
+            previous_output,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        };
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: std::iter::once(spend(anchor))
+                .chain(inputs.iter().map(|utxo| spend(utxo.outpoint)))
+                .collect(),
+            output: vec![TxOut {
+                value: Amount::ZERO,
+                script_pubkey: self.wallet_script(),
+            }],
+        }
+    }
+
+    fn sign_child(
+        &self,
+        child: &mut Transaction,
+        anchor_script: &Script,
+        anchor_value: Amount,
+        inputs: &[ChainUtxo],
+    ) -> AnchorResult<()> {
+        let wallet_pubkey = self.wallet_key.public_key(&self.secp);
+        let mut witnesses = Vec::with_capacity(child.input.len());
+        {
+            let mut cache = SighashCache::new(&*child);
+            let sighash = cache
+                .p2wsh_signature_hash(0, anchor_script, anchor_value, EcdsaSighashType::All)
+                .map_err(|e| AnchorError::Signing(e.to_string()))?;
+            let signature = self.sign(sighash, &self.anchor_key);
+            witnesses.push(Witness::from_slice(&[
+                signature.to_vec(),
+                anchor_script.to_bytes(),
+            ]));
+            for (index, utxo) in inputs.iter().enumerate() {
+                let sighash = cache
+                    .p2wpkh_signature_hash(
+                        index + 1,
+                        &utxo.txout.script_pubkey,
+                        utxo.txout.value,
+                        EcdsaSighashType::All,
+                    )
+                    .map_err(|e| AnchorError::Signing(e.to_string()))?;
+                let signature = self.sign(sighash, &self.wallet_key);
+                witnesses.push(Witness::p2wpkh(&signature, &wallet_pubkey));
+            }
+        }
+        for (input, witness) in child.input.iter_mut().zip(witnesses) {
+            input.witness = witness;
+        }
+        Ok(())
+    }
+
+    fn sign(&self, sighash: SegwitV0Sighash, key: &SecretKey) -> ecdsa::Signature {
+        let message = Message::from_digest(sighash.to_byte_array());
+        ecdsa::Signature {
+            signature: self.secp.sign_ecdsa(&message, key),
+            sighash_type: EcdsaSighashType::All,
+        }
+    }
+}
+
+/// Weight of `child` once its anchor and P2WPKH inputs are signed
+fn signed_weight(child: &Transaction, anchor_script: &Script) -> Weight {
+    let mut signed = child.clone();
+    for (index, input) in signed.input.iter_mut().enumerate() {
+        input.witness = if index == 0 {
+            Witness::from_slice(&[
+                [0u8; ECDSA_SIGNATURE_SIZE].as_slice(),
+                anchor_script.as_bytes(),
+            ])
+        } else {
+            Witness::from_slice(&[
+                [0u8; ECDSA_SIGNATURE_SIZE].as_slice(),
+                &[0u8; COMPRESSED_KEY_SIZE],
+            ])
+        };
+    }
+    signed.weight()
+}
+
+fn chain_error(err: AnyaError) -> AnchorError {
+    AnchorError::Chain(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::chain_source::MockChainSource;
+    use crate::bitcoin::lightning_store::ChannelState;
+    use bitcoin::FeeRate;
+    use bitcoin::Txid;
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    fn secret(seed: u8) -> SecretKey {
+        SecretKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn pubkey(seed: u8) -> PublicKey {
+        secret(seed).public_key(&Secp256k1::new())
+    }
+
+    fn keys() -> CommitmentKeys {
+        CommitmentKeys {
+            local_funding_pubkey: pubkey(1),
+            remote_funding_pubkey: pubkey(2),
+            revocation_pubkey: pubkey(3),
+            local_delayed_pubkey: pubkey(4),
+            remote_payment_pubkey: pubkey(5),
+        }
+    }
+
+    fn params(feerate_per_kw: u32) -> CommitmentParams {
+        CommitmentParams {
+            funding_outpoint: OutPoint::new(Txid::all_zeros(), 0),
+            commitment_number: 42,
+            obscure_factor: 0x2bb0_3852_1914,
+            to_self_delay: 144,
+            dust_limit: Amount::from_sat(546),
+            feerate_per_kw,
+            local_is_funder: true,
+        }
+    }
+
+    #[test]
+    fn test_obscure_factor_matches_bolt3() {
+        let local = PublicKey::from_str(
+            "034f355bdcb7cc0af728ef3cceb9615d90684bb5b2ca5f859ab0f0b704075871aa",
+        )
+        .unwrap();
+        let remote = PublicKey::from_str(
+            "032c0b7cf95324a07d05398b240174dc0c2be444d96b159aa6c7f7b1e668680991",
+        )
+        .unwrap();
+        assert_eq!(obscure_factor(&local, &remote), 0x2bb0_3852_1914);
+    }
+
+    #[test]
+    fn test_anchor_commitment_outputs() {
+        let tx = build_anchor_commitment(&params(1_000), &keys(), 70_000_000, 30_000_000).unwrap();
+        // BOLT 3 vector values for commitment number 42
+        assert_eq!(tx.lock_time.to_consensus_u32(), 542_251_326);
+        assert_eq!(tx.input[0].sequence.0, 2_150_346_808);
+
+        // The funder pays the 1124 sat fee and both anchors
+        let values: Vec<u64> = tx.output.iter().map(|txout| txout.value.to_sat()).collect();
+        assert_eq!(values, vec![330, 330, 30_000, 70_000 - 1_124 - 660]);
+        let anchors: Vec<&ScriptBuf> = tx.output[..2]
+            .iter()
+            .map(|txout| &txout.script_pubkey)
+            .collect();
+        for pubkey in [pubkey(1), pubkey(2)] {
+            let script = ScriptBuf::new_p2wsh(&anchor_script(&pubkey).wscript_hash());
+            assert!(anchors.contains(&&script));
+        }
+
+        // A trimmed balance output takes its anchor with it
+        let tx = build_anchor_commitment(&params(1_000), &keys(), 99_500_000, 500_000).unwrap();
+        assert_eq!(tx.output.len(), 2);
+        assert_eq!(tx.output[0].value, ANCHOR_OUTPUT_VALUE);
+        assert!(matches!(
+            build_anchor_commitment(&params(1_000), &keys(), 1_000_000, 99_000_000),
+            Err(AnchorError::FeeExceedsBalance { .. })
+        ));
+    }
+
+    fn channel(commitment: Transaction) -> ChannelState {
+        ChannelState {
+            channel_id: "channel_a".to_string(),
+            funding_outpoint: commitment.input[0].previous_output,
+            capacity_sat: 100_000,
+            remote_node_id: pubkey(9),
+            update_number: 42,
+            local_balance_msat: 70_000_000,
+            remote_balance_msat: 30_000_000,
+            htlcs: Vec::new(),
+            commitment_tx: Some(commitment),
+            revoked_secrets: BTreeMap::new(),
+        }
+    }
+
+    fn wallet_utxo(bumper: &AnchorBumper, n: u8, sats: u64, height: Option<u32>) -> ChainUtxo {
+        ChainUtxo {
+            outpoint: OutPoint::new(Txid::from_byte_array([n; 32]), 0),
+            txout: TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey: bumper.wallet_script(),
+            },
+            height,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bumps_only_commitments_below_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ChannelStore::open(dir.path()).unwrap();
+        let commitment =
+            build_anchor_commitment(&params(253), &keys(), 70_000_000, 30_000_000).unwrap();
+        store.persist(&channel(commitment.clone())).unwrap();
+        let chain = Arc::new(MockChainSource::new());
+        let bumper = AnchorBumper::new(store, chain.clone(), secret(1), secret(7));
+
+        // The commitment pays 284 sats, enough for 1 sat/vB
+        chain.set_fee_rate(FeeRate::from_sat_per_vb(1).unwrap());
+        assert!(bumper.bump_commitment("channel_a").await.unwrap().is_none());
+
+        chain.set_fee_rate(FeeRate::from_sat_per_vb(20).unwrap());
+        assert!(matches!(
+            bumper.bump_commitment("channel_a").await,
+            Err(AnchorError::InsufficientFunds { .. })
+        ));
+        chain.add_utxo(wallet_utxo(&bumper, 1, 1_000_000, None));
+        chain.add_utxo(wallet_utxo(&bumper, 2, 50_000, Some(100)));
+
+        let child = bumper.bump_commitment("channel_a").await.unwrap().unwrap();
+        let anchor_vout = commitment
+            .output
+            .iter()
+            .position(|txout| {
+                txout.script_pubkey
+                    == ScriptBuf::new_p2wsh(&anchor_script(&pubkey(1)).wscript_hash())
+            })
+            .unwrap();
+        assert_eq!(
+            child.input[0].previous_output,
+            OutPoint::new(commitment.compute_txid(), anchor_vout as u32)
+        );
+        assert_eq!(child.input.len(), 2);
+        assert_eq!(
+            child.input[1].previous_output.txid,
+            Txid::from_byte_array([2; 32])
+        );
+        assert_eq!(child.output[0].script_pubkey, bumper.wallet_script());
+
+        // Parent and child together pay at least the target rate
+        let parent_fee = 284;
+        let child_fee = 50_330 - child.output[0].value.to_sat();
+        let package_weight = commitment.weight() + child.weight();
+        assert!(parent_fee + child_fee >= package_weight.to_wu() * 20 / 4);
+        assert!(child.vsize() as u64 <= MAX_CHILD_VSIZE);
+
+        // Nothing to do once the commitment is confirmed
+        chain.add_utxo(ChainUtxo {
+            outpoint: child.input[0].previous_output,
+            txout: commitment.output[anchor_vout].clone(),
+            height: Some(101),
+        });
+        assert!(bumper.bump_commitment("channel_a").await.unwrap().is_none());
+        assert!(matches!(
+            bumper.bump_commitment("channel_b").await,
+            Err(AnchorError::UnknownChannel(_))
+        ));
+    }
+}
@@ -0,0 +1,545 @@
+// [AIR-3][AIS-3][BPC-3] Lightning channel state persistence
+//
+// A channel update must be durable before the node acts on it: before it
+// revokes the previous commitment and before it broadcasts the new one. Each
+// state is written to `<channel_id>.json.tmp` and fsynced, then renamed over
+// `<channel_id>.json` and the directory is fsynced. After a crash the channel
+// file holds either the previous or the new state, never a torn mix. A
+// leftover temporary file is an update whose persist never returned, so no
+// revocation or broadcast can depend on it, and it is discarded on open.
+//
+// Commitments are broadcast through `ChannelStore::broadcast_commitment`,
+// which persists the state first. A node that broadcast a commitment it later
+// lost could come back with an older, revoked state and forfeit the channel.
+//
+// The static channel backup (SCB) only holds what is needed to ask peers to
+// force-close after all channel state is lost: channel id, funding outpoint,
+// capacity and peer node id. It is encrypted with ChaCha20-Poly1305.
+
+use crate::security::rng::secure_rng;
+use crate::AnyaError;
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::{OutPoint, Transaction};
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Extension of persisted channel states
+const STATE_EXTENSION: &str = "json";
+
+/// Extension of states written but not yet committed
+const PENDING_EXTENSION: &str = "json.tmp";
+
+/// Format version of the static channel backup
+const SCB_VERSION: u8 = 1;
+
+const NONCE_LEN: usize = 12;
+
+/// Channel store errors
+#[derive(Debug, Error)]
+pub enum ChannelStoreError {
+    #[error("Channel store {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid channel id {0:?}")]
+    InvalidChannelId(String),
+    #[error("Corrupted channel state {path}: {reason}")]
+    Corrupted { path: PathBuf, reason: String },
+    #[error("Channel {channel_id} update {update} is older than persisted update {persisted}")]
+    StaleUpdate {
+        channel_id: String,
+        persisted: u64,
+        update: u64,
+    },
+    #[error("Channel {0} has no signed commitment")]
+    NoCommitment(String),
+    #[error("Wrong key or corrupted channel backup")]
+    Decryption,
+    #[error("Invalid channel backup: {0}")]
+    Backup(String),
+}
+
+impl From<ChannelStoreError> for AnyaError {
+    fn from(err: ChannelStoreError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+pub type ChannelStoreResult<T> = Result<T, ChannelStoreError>;
+
+/// [AIR-3][BPC-3] HTLC pending on a channel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HtlcState {
+    pub id: u64,
+    /// Offered by us rather than received from the peer
+    pub offered: bool,
+    pub amount_msat: u64,
+    pub payment_hash: sha256::Hash,
+    pub cltv_expiry: u32,
+}
+
+/// [AIR-3][AIS-3][BPC-3] Everything needed to resume or enforce a channel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelState {
+    pub channel_id: String,
+    pub funding_outpoint: OutPoint,
+    pub capacity_sat: u64,
+    pub remote_node_id: PublicKey,
+    /// Commitment number, increasing with every update
+    pub update_number: u64,
+    pub local_balance_msat: u64,
+    pub remote_balance_msat: u64,
+    pub htlcs: Vec<HtlcState>,
+    /// Latest fully signed local commitment, once the funding is signed
+    pub commitment_tx: Option<Transaction>,
+    /// Per-commitment secrets revealed by the peer, by commitment number
+    pub revoked_secrets: BTreeMap<u64, [u8; 32]>,
+}
+
+/// [AIR-3][BPC-3] Channel entry of a static channel backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelBackup {
+    pub channel_id: String,
+    pub funding_outpoint: OutPoint,
+    pub capacity_sat: u64,
+    pub remote_node_id: PublicKey,
+}
+
+impl From<&ChannelState> for ChannelBackup {
+    fn from(state: &ChannelState) -> Self {
+        Self {
+            channel_id: state.channel_id.clone(),
+            funding_outpoint: state.funding_outpoint,
+            capacity_sat: state.capacity_sat,
+            remote_node_id: state.remote_node_id,
+        }
+    }
+}
+
+/// [AIR-3][AIS-3][BPC-3] Crash-safe store of channel states, one file per channel
+pub struct ChannelStore {
+    dir: PathBuf,
+}
+
+impl ChannelStore {
+    /// Open the store in `dir`, discarding updates interrupted by a crash
+    pub fn open(dir: impl AsRef<Path>) -> ChannelStoreResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| io_error(&dir, e))?;
+        let store = Self { dir };
+
+        let mut discarded = false;
+        for path in store.files(PENDING_EXTENSION)? {
+            log::warn!("Discarding uncommitted channel update {}", path.display());
+            fs::remove_file(&path).map_err(|e| io_error(&path, e))?;
+            discarded = true;
+        }
+        if discarded {
+            sync_dir(&store.dir)?;
+        }
+        Ok(store)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Durably replace the stored state of `state.channel_id`
+    ///
+    /// Fails without writing anything if a later update is already stored.
+    pub fn persist(&self, state: &ChannelState) -> ChannelStoreResult<()> {
+        if let Some(persisted) = self.load(&state.channel_id)? {
+            if state.update_number < persisted.update_number {
+                return Err(ChannelStoreError::StaleUpdate {
+                    channel_id: state.channel_id.clone(),
+                    persisted: persisted.update_number,
+                    update: state.update_number,
+                });
+            }
+        }
+        self.write_ahead(state)?;
+        self.commit(&state.channel_id)
+    }
+
+    /// Stored state of `channel_id`, if any
+    pub fn load(&self, channel_id: &str) -> ChannelStoreResult<Option<ChannelState>> {
+        let path = self.path(channel_id, STATE_EXTENSION)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        read_state(&path).map(Some)
+    }
+
+    /// Every stored channel, ordered by channel id
+    ///
+    /// A corrupted file is an error rather than skipped, since a channel the
+    /// node forgets about can be closed with a revoked commitment unnoticed.
+    pub fn load_all(&self) -> ChannelStoreResult<Vec<ChannelState>> {
+        let mut states = self
+            .files(STATE_EXTENSION)?
+            .iter()
+            .map(|path| read_state(path))
+            .collect::<ChannelStoreResult<Vec<_>>>()?;
+        states.sort_by(|a, b| a.channel_id.cmp(&b.channel_id));
+        Ok(states)
+    }
+
+    /// Forget a closed channel
+    pub fn remove(&self, channel_id: &str) -> ChannelStoreResult<()> {
+        let path = self.path(channel_id, STATE_EXTENSION)?;
+        match fs::remove_file(&path) {
+            Ok(()) => sync_dir(&self.dir),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(io_error(&path, e)),
+        }
+    }
+
+    /// Persist `state`, then hand its commitment transaction to `broadcast`
+    ///
+    /// `broadcast` only runs once the state is on disk, so a commitment is
+    /// never published that a restart could lose.
+    pub fn broadcast_commitment<T, E>(
+        &self,
+        state: &ChannelState,
+        broadcast: impl FnOnce(&Transaction) -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        E: From<ChannelStoreError>,
+    {
+        let commitment = state
+            .commitment_tx
+            .as_ref()
+            .ok_or_else(|| ChannelStoreError::NoCommitment(state.channel_id.clone()))?;
+        self.persist(state)?;
+        broadcast(commitment)
+    }
+
+    /// Static channel backup of every stored channel, encrypted under `key`
+    pub fn static_channel_backup(&self, key: &[u8; 32]) -> ChannelStoreResult<Vec<u8>> {
+        let backups: Vec<ChannelBackup> = self.load_all()?.iter().map(Into::into).collect();
+        let plaintext =
+            serde_json::to_vec(&backups).map_err(|e| ChannelStoreError::Backup(e.to_string()))?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        secure_rng().fill_bytes(&mut nonce);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &associated_data(SCB_VERSION),
+                },
+            )
+            .map_err(|_| ChannelStoreError::Backup("Encryption failed".to_string()))?;
+
+        let mut blob = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        blob.push(SCB_VERSION);
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(&ciphertext);
+        Ok(blob)
+    }
+
+    /// Channels listed in a backup made by [`ChannelStore::static_channel_backup`]
+    pub fn restore_static_channel_backup(
+        blob: &[u8],
+        key: &[u8; 32],
+    ) -> ChannelStoreResult<Vec<ChannelBackup>> {
+        let (&version, rest) = blob
+            .split_first()
+            .ok_or_else(|| ChannelStoreError::Backup("Empty backup".to_string()))?;
+        if version != SCB_VERSION {
+            return Err(ChannelStoreError::Backup(format!(
+                "Unsupported version {version}"
+            )));
+        }
+        if rest.len() < NONCE_LEN {
+            return Err(ChannelStoreError::Backup("Truncated backup".to_string()));
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(key))
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &associated_data(version),
+                },
+            )
+            .map_err(|_| ChannelStoreError::Decryption)?;
+        serde_json::from_slice(&plaintext).map_err(|e| ChannelStoreError::Backup(e.to_string()))
+    }
+
+    /// Write and fsync the pending file of `state`
+    fn write_ahead(&self, state: &ChannelState) -> ChannelStoreResult<()> {
+        let path = self.path(&state.channel_id, PENDING_EXTENSION)?;
+        let data = serde_json::to_vec_pretty(state).map_err(|e| ChannelStoreError::Corrupted {
+            path: path.clone(),
+            reason: e.to_string(),
+        })?;
+        let mut file = File::create(&path).map_err(|e| io_error(&path, e))?;
+        file.write_all(&data).map_err(|e| io_error(&path, e))?;
+        file.sync_all().map_err(|e| io_error(&path, e))
+    }
+
+    /// Move the pending file of `channel_id` over its state
+    fn commit(&self, channel_id: &str) -> ChannelStoreResult<()> {
+        let pending = self.path(channel_id, PENDING_EXTENSION)?;
+        let path = self.path(channel_id, STATE_EXTENSION)?;
+        fs::rename(&pending, &path).map_err(|e| io_error(&path, e))?;
+        sync_dir(&self.dir)
+    }
+
+    fn path(&self, channel_id: &str, extension: &str) -> ChannelStoreResult<PathBuf> {
+        let valid = !channel_id.is_empty()
+            && channel_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(ChannelStoreError::InvalidChannelId(channel_id.to_string()));
+        }
+        Ok(self.dir.join(format!("{channel_id}.{extension}")))
+    }
+
+    /// Files in the store directory named `<channel_id>.<extension>`
+    fn files(&self, extension: &str) -> ChannelStoreResult<Vec<PathBuf>> {
+        let suffix = format!(".{extension}");
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(|e| io_error(&self.dir, e))? {
+            let path = entry.map_err(|e| io_error(&self.dir, e))?.path();
+            let name = path.file_name().and_then(|name| name.to_str());
+            if name
+                .and_then(|name| name.strip_suffix(&suffix))
+                .is_some_and(|id| !id.contains('.'))
+            {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+}
+
+fn read_state(path: &Path) -> ChannelStoreResult<ChannelState> {
+    let data = fs::read(path).map_err(|e| io_error(path, e))?;
+    serde_json::from_slice(&data).map_err(|e| ChannelStoreError::Corrupted {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })
+}
+
+/// Make renames and removals in `dir` durable
+fn sync_dir(dir: &Path) -> ChannelStoreResult<()> {
+    #[cfg(unix)]
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|e| io_error(dir, e))?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Header fields bound to the backup ciphertext
+fn associated_data(version: u8) -> Vec<u8> {
+    format!("anya-scb:{version}").into_bytes()
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> ChannelStoreError {
+    ChannelStoreError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, ScriptBuf, Sequence, TxIn, TxOut, Txid, Witness};
+
+    fn commitment(update_number: u64) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::from_consensus(0x2000_0000 | update_number as u32),
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence(0x8000_0000),
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(99_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        }
+    }
+
+    fn state(channel_id: &str, update_number: u64) -> ChannelState {
+        let secret_key = SecretKey::from_slice(&[7; 32]).unwrap();
+        ChannelState {
+            channel_id: channel_id.to_string(),
+            funding_outpoint: OutPoint::new(Txid::all_zeros(), 1),
+            capacity_sat: 100_000,
+            remote_node_id: secret_key.public_key(&Secp256k1::new()),
+            update_number,
+            local_balance_msat: 90_000_000 - update_number * 1_000,
+            remote_balance_msat: 10_000_000 + update_number * 1_000,
+            htlcs: vec![HtlcState {
+                id: update_number,
+                offered: true,
+                amount_msat: 1_000,
+                payment_hash: sha256::Hash::hash(&update_number.to_le_bytes()),
+                cltv_expiry: 800_040,
+            }],
+            commitment_tx: Some(commitment(update_number)),
+            revoked_secrets: (0..update_number).map(|n| (n, [n as u8; 32])).collect(),
+        }
+    }
+
+    #[test]
+    fn test_persist_and_load_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ChannelStore::open(dir.path()).unwrap();
+        store.persist(&state("channel_b", 3)).unwrap();
+        store.persist(&state("channel_a", 1)).unwrap();
+        store.persist(&state("channel_a", 2)).unwrap();
+        assert!(matches!(
+            store.persist(&state("channel_a", 1)),
+            Err(ChannelStoreError::StaleUpdate {
+                persisted: 2,
+                update: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            store.persist(&state("../escape", 1)),
+            Err(ChannelStoreError::InvalidChannelId(_))
+        ));
+
+        let reopened = ChannelStore::open(dir.path()).unwrap();
+        assert_eq!(
+            reopened.load_all().unwrap(),
+            vec![state("channel_a", 2), state("channel_b", 3)]
+        );
+        reopened.remove("channel_b").unwrap();
+        assert_eq!(reopened.load("channel_b").unwrap(), None);
+        assert_eq!(reopened.load_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_crash_between_update_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ChannelStore::open(dir.path()).unwrap();
+        store.persist(&state("channel_a", 5)).unwrap();
+        store.persist(&state("channel_b", 1)).unwrap();
+
+        // Crash after the new state reached disk but before it was committed
+        store.write_ahead(&state("channel_a", 6)).unwrap();
+        // Crash halfway through writing the new state
+        let torn = serde_json::to_vec(&state("channel_b", 2)).unwrap();
+        fs::write(
+            dir.path().join("channel_b.json.tmp"),
+            &torn[..torn.len() / 2],
+        )
+        .unwrap();
+        drop(store);
+
+        let recovered = ChannelStore::open(dir.path()).unwrap();
+        assert_eq!(
+            recovered.load_all().unwrap(),
+            vec![state("channel_a", 5), state("channel_b", 1)]
+        );
+        assert!(recovered.files(PENDING_EXTENSION).unwrap().is_empty());
+
+        // The interrupted update can be retried
+        recovered.persist(&state("channel_a", 6)).unwrap();
+        assert_eq!(
+            recovered.load("channel_a").unwrap(),
+            Some(state("channel_a", 6))
+        );
+
+        // A damaged committed file is reported, not silently dropped
+        fs::write(dir.path().join("channel_b.json"), &torn[..10]).unwrap();
+        assert!(matches!(
+            recovered.load_all(),
+            Err(ChannelStoreError::Corrupted { .. })
+        ));
+    }
+
+    #[test]
+    fn test_broadcast_commitment_persists_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ChannelStore::open(dir.path()).unwrap();
+        let update = state("channel_a", 4);
+
+        let txid = store
+            .broadcast_commitment(&update, |tx| {
+                // Visible to a fresh store, as after a crash right after broadcast
+                let on_disk = ChannelStore::open(dir.path())?.load("channel_a")?;
+                assert_eq!(on_disk.as_ref(), Some(&update));
+                Ok::<_, AnyaError>(tx.compute_txid())
+            })
+            .unwrap();
+        assert_eq!(txid, commitment(4).compute_txid());
+
+        // Nothing is broadcast when the state cannot be persisted
+        let mut broadcasts = 0;
+        let stale = store.broadcast_commitment(&state("channel_a", 3), |_| {
+            broadcasts += 1;
+            Ok::<_, AnyaError>(())
+        });
+        assert!(stale.is_err());
+        let mut unsigned = state("channel_b", 0);
+        unsigned.commitment_tx = None;
+        let unsigned = store.broadcast_commitment(&unsigned, |_| {
+            broadcasts += 1;
+            Ok::<_, AnyaError>(())
+        });
+        assert!(unsigned.is_err());
+        assert_eq!(broadcasts, 0);
+        assert_eq!(store.load("channel_b").unwrap(), None);
+    }
+
+    #[test]
+    fn test_static_channel_backup_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ChannelStore::open(dir.path()).unwrap();
+        store.persist(&state("channel_a", 2)).unwrap();
+        store.persist(&state("channel_b", 9)).unwrap();
+        let key = [0x11; 32];
+
+        let blob = store.static_channel_backup(&key).unwrap();
+        let backups = ChannelStore::restore_static_channel_backup(&blob, &key).unwrap();
+        assert_eq!(
+            backups,
+            vec![
+                ChannelBackup::from(&state("channel_a", 2)),
+                ChannelBackup::from(&state("channel_b", 9))
+            ]
+        );
+
+        assert!(matches!(
+            ChannelStore::restore_static_channel_backup(&blob, &[0x22; 32]),
+            Err(ChannelStoreError::Decryption)
+        ));
+        let mut tampered = blob.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            ChannelStore::restore_static_channel_backup(&tampered, &key),
+            Err(ChannelStoreError::Decryption)
+        ));
+        assert!(matches!(
+            ChannelStore::restore_static_channel_backup(&blob[..5], &key),
+            Err(ChannelStoreError::Backup(_))
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+// [AIR-3][BPC-3] Fee rate estimation
+//
+// Two estimates, both in sat/vB. The historical one learns from connected
+// blocks: each block contributes the lowest fee rate among the pool
+// transactions it confirmed, and a rate counts as enough for a target of N
+// blocks if some block in a window of N consecutive blocks mined at or below
+// it. The mempool one fills N blocks from the current pool in mining order
+// and reports the lowest package fee rate that still made it in. The mempool
+// reacts to congestion at once while history smooths out short spikes;
+// `estimate` blends the two.
+
+use super::{Mempool, MempoolEntry};
+use bitcoin::{Block, Weight};
+use std::collections::VecDeque;
+
+/// Weight reserved for the coinbase transaction, as in Bitcoin Core
+const COINBASE_RESERVED_WEIGHT: Weight = Weight::from_wu(4_000);
+
+/// [AIR-3][BPC-3] Fee estimator settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeEstimatorConfig {
+    /// Share of the blended estimate taken from the mempool, from 0 to 1
+    pub mempool_weight: f64,
+    /// Fraction of past block windows a historical estimate must have confirmed in
+    pub success_threshold: f64,
+    /// Number of recent blocks remembered
+    pub max_history: usize,
+    /// Lowest rate ever returned by `estimate`, in sat/vB
+    pub min_fee_rate: f64,
+}
+
+impl Default for FeeEstimatorConfig {
+    fn default() -> Self {
+        Self {
+            mempool_weight: 0.5,
+            success_threshold: 0.85,
+            max_history: 1008,
+            min_fee_rate: 1.0,
+        }
+    }
+}
+
+/// [AIR-3][BPC-3] Estimates the fee rate needed to confirm within a number of blocks
+#[derive(Debug, Clone, Default)]
+pub struct FeeEstimator {
+    config: FeeEstimatorConfig,
+    /// Lowest pool fee rate confirmed by each recent block, oldest first
+    block_minimums: VecDeque<f64>,
+}
+
+impl FeeEstimator {
+    pub fn new(config: FeeEstimatorConfig) -> Self {
+        Self {
+            config,
+            block_minimums: VecDeque::new(),
+        }
+    }
+
+    /// Learn from a newly connected block
+    ///
+    /// Must be called before the block's transactions are removed from
+    /// `mempool`. Blocks confirming no pool transaction are skipped, as they
+    /// say nothing about the fee rates miners accept.
+    pub fn process_block(&mut self, block: &Block, mempool: &Mempool) {
+        let minimum = block
+            .txdata
+            .iter()
+            .filter_map(|tx| mempool.get(&tx.compute_txid()))
+            .map(package_rate)
+            .reduce(f64::min);
+        if let Some(minimum) = minimum {
+            if self.block_minimums.len() == self.config.max_history {
+                self.block_minimums.pop_front();
+            }
+            self.block_minimums.push_back(minimum);
+        }
+    }
+
+    /// Fee rate that confirmed within `target_blocks` in recent history
+    ///
+    /// `None` until at least `target_blocks` blocks have been processed.
+    pub fn estimate_from_history(&self, target_blocks: u32) -> Option<f64> {
+        let target = target_blocks.max(1) as usize;
+        if self.block_minimums.len() < target {
+            return None;
+        }
+        let minimums: Vec<f64> = self.block_minimums.iter().copied().collect();
+        let mut windows: Vec<f64> = minimums
+            .windows(target)
+            .map(|window| window.iter().copied().fold(f64::INFINITY, f64::min))
+            .collect();
+        windows.sort_by(f64::total_cmp);
+
+        let needed = (self.config.success_threshold * windows.len() as f64).ceil() as usize;
+        windows.get(needed.clamp(1, windows.len()) - 1).copied()
+    }
+
+    /// Marginal fee rate of the first `target_blocks` blocks mined from `mempool`
+    ///
+    /// Fills blocks greedily in [`Mempool::iter_for_mining`] order and
+    /// returns the lowest package fee rate included. `None` if the pool does
+    /// not fill that many blocks, as it then sets no floor.
+    pub fn estimate_from_mempool(&self, mempool: &Mempool, target_blocks: u32) -> Option<f64> {
+        let capacity = Weight::MAX_BLOCK - COINBASE_RESERVED_WEIGHT;
+        let mut block = 1;
+        let mut used = Weight::ZERO;
+        let mut marginal = f64::INFINITY;
+
+        for entry in mempool.iter_for_mining() {
+            if used + entry.weight > capacity {
+                if block == target_blocks.max(1) {
+                    return Some(marginal);
+                }
+                block += 1;
+                used = Weight::ZERO;
+            }
+            used += entry.weight;
+            marginal = marginal.min(package_rate(&entry));
+        }
+        None
+    }
+
+    /// Blend of the mempool and historical estimates for `target_blocks`
+    ///
+    /// Uses `mempool_weight` when both are known and whichever one is known
+    /// otherwise. Never below `min_fee_rate`.
+    pub fn estimate(&self, mempool: &Mempool, target_blocks: u32) -> f64 {
+        let weight = self.config.mempool_weight.clamp(0.0, 1.0);
+        let estimate = match (
+            self.estimate_from_mempool(mempool, target_blocks),
+            self.estimate_from_history(target_blocks),
+        ) {
+            (Some(pool), Some(history)) => weight * pool + (1.0 - weight) * history,
+            (Some(rate), None) | (None, Some(rate)) => rate,
+            (None, None) => self.config.min_fee_rate,
+        };
+        estimate.max(self.config.min_fee_rate)
+    }
+}
+
+fn package_rate(entry: &MempoolEntry) -> f64 {
+    sat_per_vb(entry.package_fee_rate())
+}
+
+fn sat_per_vb(rate: bitcoin::FeeRate) -> f64 {
+    rate.to_sat_per_kwu() as f64 / 250.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::block::{Header, Version as BlockVersion};
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction::Version;
+    use bitcoin::{
+        Amount, BlockHash, CompactTarget, OutPoint, ScriptBuf, Transaction, TxIn, TxMerkleNode,
+        TxOut, Txid,
+    };
+
+    /// Transaction of exactly 100_000 vbytes, made unique by `tag`
+    fn large_tx(tag: u32) -> Transaction {
+        let mut tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::from_consensus(tag),
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), tag),
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::ZERO,
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        // A script over 65_535 bytes takes a five byte length prefix
+        let padding = 100_000 - tx.vsize() - 4;
+        tx.output[0].script_pubkey = ScriptBuf::from_bytes(vec![0x6a; padding]);
+        assert_eq!(tx.vsize(), 100_000);
+        tx
+    }
+
+    /// Pool holding ten 100 kvB transactions at each of 50, 40, 30, 20 and 10 sat/vB
+    fn congested_mempool() -> Mempool {
+        let mut pool = Mempool::new();
+        for (tier, rate) in [50, 40, 30, 20, 10].into_iter().enumerate() {
+            for n in 0..10 {
+                let tx = large_tx(tier as u32 * 10 + n);
+                pool.add(tx, Amount::from_sat(rate * 100_000)).unwrap();
+            }
+        }
+        pool
+    }
+
+    fn block(txdata: Vec<Transaction>) -> Block {
+        Block {
+            header: Header {
+                version: BlockVersion::ONE,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: 0,
+                bits: CompactTarget::from_consensus(0x207fffff),
+                nonce: 0,
+            },
+            txdata,
+        }
+    }
+
+    #[test]
+    fn test_mempool_estimate_fills_target_blocks() {
+        let pool = congested_mempool();
+        let estimator = FeeEstimator::default();
+
+        // A block holds nine of the 100 kvB transactions next to the coinbase
+        assert_eq!(estimator.estimate_from_mempool(&pool, 1), Some(50.0));
+        assert_eq!(estimator.estimate_from_mempool(&pool, 2), Some(40.0));
+        assert_eq!(estimator.estimate_from_mempool(&pool, 4), Some(20.0));
+        // Fifty transactions do not fill six blocks
+        assert_eq!(estimator.estimate_from_mempool(&pool, 6), None);
+        assert_eq!(estimator.estimate_from_mempool(&Mempool::new(), 1), None);
+    }
+
+    #[test]
+    fn test_history_and_blended_estimates() {
+        let pool = congested_mempool();
+        let mut estimator = FeeEstimator::new(FeeEstimatorConfig {
+            mempool_weight: 0.25,
+            ..FeeEstimatorConfig::default()
+        });
+        assert_eq!(estimator.estimate_from_history(1), None);
+        assert_eq!(estimator.estimate(&Mempool::new(), 1), 1.0);
+
+        // Recent blocks confirmed the 10 sat/vB tier, one of them only 50 sat/vB
+        let cheap: Vec<Transaction> = (40..50).map(large_tx).collect();
+        for _ in 0..9 {
+            estimator.process_block(&block(cheap.clone()), &pool);
+        }
+        estimator.process_block(&block(vec![large_tx(0)]), &pool);
+        assert_eq!(estimator.estimate_from_history(1), Some(10.0));
+        assert_eq!(estimator.estimate_from_history(20), None);
+
+        // Blocks of transactions the pool never saw teach nothing
+        estimator.process_block(&block(vec![large_tx(99)]), &pool);
+        assert_eq!(estimator.estimate_from_history(10), Some(10.0));
+
+        let blended = estimator.estimate(&pool, 1);
+        assert_eq!(blended, 0.25 * 50.0 + 0.75 * 10.0);
+        assert_eq!(estimator.estimate(&pool, 6), 10.0);
+    }
+}
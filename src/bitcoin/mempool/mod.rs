@@ -0,0 +1,612 @@
+// [AIR-3][AIS-3][BPC-3] Transaction pool with mining-score ordering
+//
+// Transactions are kept with their fee and in-pool parent/child links.
+// `iter_for_mining` orders them like Bitcoin Core's block assembly: the
+// package (a transaction plus its not-yet-selected ancestors) with the highest
+// ancestor fee rate is selected next, and the scores of its descendants are
+// updated. A low-fee parent is therefore selected as soon as a child makes
+// the package worth mining, and a parent always precedes its children.
+//
+// `add_transaction` applies the standardness policy before accepting a
+// transaction; `add` trusts the caller and only checks for conflicts.
+// Either way, a transaction spending an output a pool transaction already
+// spends is rejected and reported to subscribers as a double spend, whether
+// or not the original signalled replaceability.
+
+pub mod fees;
+pub mod policy;
+
+pub use fees::{FeeEstimator, FeeEstimatorConfig};
+pub use policy::{check_standard, PolicyConfig, PolicyError};
+
+use crate::{AnyaError, AnyaResult};
+use bitcoin::{Amount, FeeRate, OutPoint, Transaction, TxOut, Txid, Weight};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use tokio::sync::mpsc;
+
+/// A transaction in the pool
+#[derive(Debug, Clone)]
+pub struct MempoolEntry {
+    pub tx: Transaction,
+    pub fee: Amount,
+    pub weight: Weight,
+    /// Fee of the transaction and its ancestor package
+    ///
+    /// On insertion this covers all in-pool ancestors. Entries yielded by
+    /// [`Mempool::iter_for_mining`] carry the package they were selected in.
+    pub package_fee: Amount,
+    /// Weight of the transaction and its ancestor package
+    pub package_weight: Weight,
+}
+
+impl MempoolEntry {
+    pub fn txid(&self) -> Txid {
+        self.tx.compute_txid()
+    }
+
+    /// Fee rate of the transaction on its own
+    pub fn fee_rate(&self) -> FeeRate {
+        fee_rate(self.fee, self.weight)
+    }
+
+    /// Fee rate of the transaction's ancestor package, its mining score
+    pub fn package_fee_rate(&self) -> FeeRate {
+        fee_rate(self.package_fee, self.package_weight)
+    }
+}
+
+/// [AIR-3][BPC-3] Notification sent to [`Mempool::subscribe`] receivers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MempoolEvent {
+    /// A transaction spending outputs of a pool transaction was submitted
+    DoubleSpendDetected {
+        original_txid: Txid,
+        replacement_txid: Txid,
+        /// Outputs spent by both transactions
+        shared_inputs: Vec<OutPoint>,
+    },
+}
+
+struct PoolTx {
+    entry: MempoolEntry,
+    parents: HashSet<Txid>,
+    children: HashSet<Txid>,
+}
+
+/// Unconfirmed transactions with their dependencies
+#[derive(Default)]
+pub struct Mempool {
+    txs: HashMap<Txid, PoolTx>,
+    spends: HashMap<OutPoint, Txid>,
+    policy: PolicyConfig,
+    subscribers: Vec<mpsc::UnboundedSender<MempoolEvent>>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Empty pool applying `policy` in [`Mempool::add_transaction`]
+    pub fn with_policy(policy: PolicyConfig) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    /// Receive events for transactions submitted from now on
+    pub fn subscribe(&mut self) -> mpsc::UnboundedReceiver<MempoolEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn len(&self) -> usize {
+        self.txs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    pub fn contains(&self, txid: &Txid) -> bool {
+        self.txs.contains_key(txid)
+    }
+
+    pub fn get(&self, txid: &Txid) -> Option<&MempoolEntry> {
+        self.txs.get(txid).map(|pool_tx| &pool_tx.entry)
+    }
+
+    /// Pool transactions spending any output `tx` spends, in input order
+    pub fn conflicts_with(&self, tx: &Transaction) -> Vec<Txid> {
+        let txid = tx.compute_txid();
+        let mut conflicts = Vec::new();
+        for input in &tx.input {
+            if let Some(spender) = self.spends.get(&input.previous_output) {
+                if *spender != txid && !conflicts.contains(spender) {
+                    conflicts.push(*spender);
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Add a transaction spending `prevouts`, one per input in order
+    ///
+    /// The transaction must pass the pool's policy, must not be a coinbase
+    /// and must not create more value than it spends. The difference is its
+    /// fee.
+    pub fn add_transaction(&mut self, tx: Transaction, prevouts: &[TxOut]) -> AnyaResult<Txid> {
+        // Report double spends even when the replacement would fail policy
+        self.reject_conflicts(&tx)?;
+        self.policy.check(&tx, prevouts)?;
+
+        let txid = tx.compute_txid();
+        if tx.is_coinbase() {
+            return Err(AnyaError::Bitcoin(format!(
+                "Coinbase transaction {txid} cannot enter the mempool"
+            )));
+        }
+        if prevouts.len() != tx.input.len() {
+            return Err(PolicyError::PrevoutMismatch {
+                inputs: tx.input.len(),
+                prevouts: prevouts.len(),
+            }
+            .into());
+        }
+        let fee = total_value(prevouts)
+            .zip(total_value(&tx.output))
+            .and_then(|(spent, created)| spent.checked_sub(created))
+            .ok_or_else(|| {
+                AnyaError::Bitcoin(format!(
+                    "Transaction {txid} creates more value than it spends"
+                ))
+            })?;
+        self.add(tx, fee)
+    }
+
+    /// Add a transaction paying `fee`
+    ///
+    /// Rejects duplicates and transactions spending an output another pool
+    /// transaction already spends.
+    pub fn add(&mut self, tx: Transaction, fee: Amount) -> AnyaResult<Txid> {
+        let txid = tx.compute_txid();
+        if self.txs.contains_key(&txid) {
+            return Err(AnyaError::Bitcoin(format!(
+                "Transaction {txid} already in mempool"
+            )));
+        }
+        self.reject_conflicts(&tx)?;
+
+        let parents: HashSet<Txid> = tx
+            .input
+            .iter()
+            .map(|input| input.previous_output.txid)
+            .filter(|parent| self.txs.contains_key(parent))
+            .collect();
+        let weight = tx.weight();
+        let (mut package_fee, mut package_weight) = (fee, weight);
+        for ancestor in self.ancestors(parents.iter().copied()) {
+            let ancestor = &self.txs[&ancestor].entry;
+            package_fee += ancestor.fee;
+            package_weight += ancestor.weight;
+        }
+
+        for parent in &parents {
+            if let Some(parent) = self.txs.get_mut(parent) {
+                parent.children.insert(txid);
+            }
+        }
+        for input in &tx.input {
+            self.spends.insert(input.previous_output, txid);
+        }
+        self.txs.insert(
+            txid,
+            PoolTx {
+                entry: MempoolEntry {
+                    tx,
+                    fee,
+                    weight,
+                    package_fee,
+                    package_weight,
+                },
+                parents,
+                children: HashSet::new(),
+            },
+        );
+        Ok(txid)
+    }
+
+    /// Remove a transaction and all its descendants, returning them
+    pub fn remove(&mut self, txid: &Txid) -> Vec<MempoolEntry> {
+        if !self.txs.contains_key(txid) {
+            return Vec::new();
+        }
+        let mut removed = vec![*txid];
+        removed.extend(self.descendants(txid));
+
+        let mut entries = Vec::with_capacity(removed.len());
+        for txid in &removed {
+            let Some(pool_tx) = self.txs.remove(txid) else {
+                continue;
+            };
+            for parent in &pool_tx.parents {
+                if let Some(parent) = self.txs.get_mut(parent) {
+                    parent.children.remove(txid);
+                }
+            }
+            for input in &pool_tx.entry.tx.input {
+                self.spends.remove(&input.previous_output);
+            }
+            entries.push(pool_tx.entry);
+        }
+        entries
+    }
+
+    /// Transactions in block assembly order
+    ///
+    /// Packages are selected by descending ancestor fee rate, and every
+    /// transaction comes after its in-pool parents. Filling a block by taking
+    /// entries while they fit under the weight limit yields a valid block.
+    pub fn iter_for_mining(&self) -> impl Iterator<Item = MempoolEntry> {
+        let mut package: HashMap<Txid, (Amount, Weight)> = self
+            .txs
+            .iter()
+            .map(|(txid, pool_tx)| {
+                let entry = &pool_tx.entry;
+                (*txid, (entry.package_fee, entry.package_weight))
+            })
+            .collect();
+        let mut queue: BinaryHeap<Candidate> = package
+            .iter()
+            .map(|(txid, (fee, weight))| Candidate::new(*txid, *fee, *weight))
+            .collect();
+        let mut order = Vec::with_capacity(self.txs.len());
+
+        while let Some(candidate) = queue.pop() {
+            // Skip candidates already selected or whose package has shrunk since
+            if package.get(&candidate.txid) != Some(&(candidate.fee, candidate.weight)) {
+                continue;
+            }
+
+            let mut members: Vec<(usize, Txid)> = self
+                .ancestors([candidate.txid])
+                .into_iter()
+                .filter(|txid| package.contains_key(txid))
+                .map(|txid| (self.ancestors([txid]).len(), txid))
+                .collect();
+            // A parent has fewer ancestors than any of its children
+            members.sort_unstable();
+            for (_, member) in &members {
+                package.remove(member);
+            }
+
+            let mut rescored = HashSet::new();
+            for (_, member) in members {
+                let pool_tx = &self.txs[&member];
+                for descendant in self.descendants(&member) {
+                    if let Some((fee, weight)) = package.get_mut(&descendant) {
+                        *fee -= pool_tx.entry.fee;
+                        *weight -= pool_tx.entry.weight;
+                        rescored.insert(descendant);
+                    }
+                }
+                let mut entry = pool_tx.entry.clone();
+                entry.package_fee = candidate.fee;
+                entry.package_weight = candidate.weight;
+                order.push(entry);
+            }
+            for txid in rescored {
+                let (fee, weight) = package[&txid];
+                queue.push(Candidate::new(txid, fee, weight));
+            }
+        }
+        order.into_iter()
+    }
+
+    /// Fail if `tx` conflicts with the pool, notifying subscribers of each conflict
+    fn reject_conflicts(&mut self, tx: &Transaction) -> AnyaResult<()> {
+        let conflicts = self.conflicts_with(tx);
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+
+        let txid = tx.compute_txid();
+        for original_txid in &conflicts {
+            let original = &self.txs[original_txid].entry.tx;
+            let shared_inputs = tx
+                .input
+                .iter()
+                .map(|input| input.previous_output)
+                .filter(|outpoint| {
+                    original
+                        .input
+                        .iter()
+                        .any(|input| input.previous_output == *outpoint)
+                })
+                .collect();
+            let event = MempoolEvent::DoubleSpendDetected {
+                original_txid: *original_txid,
+                replacement_txid: txid,
+                shared_inputs,
+            };
+            // Drop subscribers whose receiver is gone
+            self.subscribers
+                .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
+
+        Err(AnyaError::Bitcoin(format!(
+            "Transaction {txid} double spends {}",
+            conflicts
+                .iter()
+                .map(Txid::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )))
+    }
+
+    /// The given transactions and all their in-pool ancestors
+    fn ancestors(&self, start: impl IntoIterator<Item = Txid>) -> HashSet<Txid> {
+        let mut found = HashSet::new();
+        let mut stack: Vec<Txid> = start.into_iter().collect();
+        while let Some(txid) = stack.pop() {
+            if let Some(pool_tx) = self.txs.get(&txid) {
+                if found.insert(txid) {
+                    stack.extend(pool_tx.parents.iter().copied());
+                }
+            }
+        }
+        found
+    }
+
+    /// All in-pool descendants of a transaction, excluding itself
+    fn descendants(&self, txid: &Txid) -> HashSet<Txid> {
+        let mut found = HashSet::new();
+        let mut stack: Vec<Txid> = self.txs[txid].children.iter().copied().collect();
+        while let Some(txid) = stack.pop() {
+            if found.insert(txid) {
+                stack.extend(self.txs[&txid].children.iter().copied());
+            }
+        }
+        found
+    }
+}
+
+/// Package queued for selection, ordered by ancestor fee rate
+#[derive(PartialEq, Eq)]
+struct Candidate {
+    txid: Txid,
+    fee: Amount,
+    weight: Weight,
+}
+
+impl Candidate {
+    fn new(txid: Txid, fee: Amount, weight: Weight) -> Self {
+        Self { txid, fee, weight }
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Compare fee/weight without division; ties go to the smaller package
+        let ours = self.fee.to_sat() as u128 * other.weight.to_wu() as u128;
+        let theirs = other.fee.to_sat() as u128 * self.weight.to_wu() as u128;
+        ours.cmp(&theirs)
+            .then_with(|| other.weight.cmp(&self.weight))
+            .then_with(|| other.txid.cmp(&self.txid))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Sum of output values, or `None` on overflow
+fn total_value<'a>(outputs: impl IntoIterator<Item = &'a TxOut>) -> Option<Amount> {
+    outputs.into_iter().try_fold(Amount::ZERO, |total, output| {
+        total.checked_add(output.value)
+    })
+}
+
+fn fee_rate(fee: Amount, weight: Weight) -> FeeRate {
+    let weight = weight.to_wu().max(1);
+    FeeRate::from_sat_per_kwu(fee.to_sat() * 1000 / weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction::Version;
+    use bitcoin::{ScriptBuf, Sequence, TxIn, WPubkeyHash, Witness};
+
+    /// Transaction spending `inputs`, made unique by `tag`
+    fn tx(inputs: &[OutPoint], tag: u8) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs
+                .iter()
+                .map(|outpoint| TxIn {
+                    previous_output: *outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::from_bytes(vec![0x6a, tag]),
+            }],
+        }
+    }
+
+    fn confirmed(vout: u32) -> OutPoint {
+        OutPoint::new(Txid::all_zeros(), vout)
+    }
+
+    fn add(pool: &mut Mempool, inputs: &[OutPoint], tag: u8, fee: u64) -> OutPoint {
+        let txid = pool.add(tx(inputs, tag), Amount::from_sat(fee)).unwrap();
+        OutPoint::new(txid, 0)
+    }
+
+    fn mining_order(pool: &Mempool) -> Vec<Txid> {
+        pool.iter_for_mining().map(|entry| entry.txid()).collect()
+    }
+
+    #[test]
+    fn test_high_fee_child_pulls_in_low_fee_parent() {
+        let mut pool = Mempool::new();
+        let parent = add(&mut pool, &[confirmed(0)], 1, 100);
+        let unrelated = add(&mut pool, &[confirmed(1)], 2, 5_000);
+        let child = add(&mut pool, &[parent], 3, 50_000);
+
+        // On its own the parent pays less than the unrelated transaction
+        assert!(
+            pool.get(&parent.txid).unwrap().fee_rate()
+                < pool.get(&unrelated.txid).unwrap().fee_rate()
+        );
+
+        let entries: Vec<MempoolEntry> = pool.iter_for_mining().collect();
+        let order: Vec<Txid> = entries.iter().map(MempoolEntry::txid).collect();
+        assert_eq!(order, vec![parent.txid, child.txid, unrelated.txid]);
+
+        // Parent and child were selected together, at the package fee rate
+        assert_eq!(entries[0].package_fee, Amount::from_sat(50_100));
+        assert_eq!(entries[0].package_fee_rate(), entries[1].package_fee_rate());
+        assert!(entries[0].package_fee_rate() > entries[2].package_fee_rate());
+        assert!(entries[0].fee_rate() < entries[0].package_fee_rate());
+    }
+
+    #[test]
+    fn test_parents_always_precede_children() {
+        let mut pool = Mempool::new();
+        let a = add(&mut pool, &[confirmed(0)], 1, 1_000);
+        let b = add(&mut pool, &[a], 2, 200);
+        let c = add(&mut pool, &[confirmed(1)], 3, 3_000);
+        // Diamond: d spends both b and c, and pays the most
+        let d = add(&mut pool, &[b, c], 4, 90_000);
+        let e = add(&mut pool, &[d], 5, 10);
+        add(&mut pool, &[confirmed(2)], 6, 2_000);
+
+        let order = mining_order(&pool);
+        assert_eq!(order.len(), pool.len());
+        for entry in pool.iter_for_mining() {
+            let position = order.iter().position(|txid| *txid == entry.txid()).unwrap();
+            for input in &entry.tx.input {
+                if let Some(parent) = order
+                    .iter()
+                    .position(|txid| *txid == input.previous_output.txid)
+                {
+                    assert!(
+                        parent < position,
+                        "{} mined before its parent",
+                        entry.txid()
+                    );
+                }
+            }
+        }
+        // The d package is worth the most; e trails as a cheap descendant
+        let package: HashSet<Txid> = order[..4].iter().copied().collect();
+        assert_eq!(package, HashSet::from([a.txid, b.txid, c.txid, d.txid]));
+        assert_eq!(order.last(), Some(&e.txid));
+    }
+
+    #[test]
+    fn test_rejects_conflicts_and_removes_descendants() {
+        let mut pool = Mempool::new();
+        let a = add(&mut pool, &[confirmed(0)], 1, 1_000);
+        let b = add(&mut pool, &[a], 2, 1_000);
+        add(&mut pool, &[b], 3, 1_000);
+
+        assert!(pool
+            .add(tx(&[confirmed(0)], 9), Amount::from_sat(5_000))
+            .is_err());
+        assert!(pool
+            .add(tx(&[confirmed(0)], 1), Amount::from_sat(5_000))
+            .is_err());
+        assert_eq!(
+            pool.get(&b.txid).unwrap().package_fee,
+            Amount::from_sat(2_000)
+        );
+
+        assert_eq!(pool.remove(&b.txid).len(), 2);
+        assert_eq!(mining_order(&pool), vec![a.txid]);
+        // The spent output is free again
+        add(&mut pool, &[a], 4, 1_000);
+    }
+
+    #[test]
+    fn test_double_spend_is_reported_to_subscribers() {
+        let mut pool = Mempool::new();
+        let mut events = pool.subscribe();
+        let original = add(&mut pool, &[confirmed(0), confirmed(1)], 1, 1_000);
+
+        // Neither transaction signals RBF, so the replacement is rejected but still reported
+        let replacement = tx(&[confirmed(2), confirmed(1)], 2);
+        assert!(!pool.get(&original.txid).unwrap().tx.is_explicitly_rbf());
+        assert_eq!(pool.conflicts_with(&replacement), vec![original.txid]);
+        assert!(pool
+            .add(replacement.clone(), Amount::from_sat(5_000))
+            .is_err());
+        assert!(!pool.contains(&replacement.compute_txid()));
+        assert_eq!(
+            events.try_recv().unwrap(),
+            MempoolEvent::DoubleSpendDetected {
+                original_txid: original.txid,
+                replacement_txid: replacement.compute_txid(),
+                shared_inputs: vec![confirmed(1)],
+            }
+        );
+
+        // The original itself is not a conflict, and unrelated spends raise no alert
+        assert!(pool
+            .conflicts_with(&pool.get(&original.txid).unwrap().tx)
+            .is_empty());
+        add(&mut pool, &[confirmed(3)], 3, 1_000);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_add_transaction_applies_policy_and_computes_fee() {
+        let mut pool = Mempool::new();
+        let spent = TxOut {
+            value: Amount::from_sat(12_000),
+            script_pubkey: ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros()),
+        };
+
+        let mut dust = tx(&[confirmed(0)], 1);
+        dust.output[0] = TxOut {
+            value: Amount::from_sat(100),
+            script_pubkey: ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros()),
+        };
+        assert!(pool
+            .add_transaction(dust.clone(), std::slice::from_ref(&spent))
+            .is_err());
+        assert!(pool.is_empty());
+
+        let txid = pool
+            .add_transaction(tx(&[confirmed(0)], 1), std::slice::from_ref(&spent))
+            .unwrap();
+        assert_eq!(pool.get(&txid).unwrap().fee, Amount::from_sat(2_000));
+
+        // Outputs worth more than the inputs are rejected even when policy is relaxed
+        let mut relaxed =
+            Mempool::with_policy(PolicyConfig::for_network(bitcoin::Network::Regtest));
+        relaxed
+            .add_transaction(dust, std::slice::from_ref(&spent))
+            .unwrap();
+        let overspend = TxOut {
+            value: Amount::from_sat(9_999),
+            ..spent
+        };
+        assert!(relaxed
+            .add_transaction(tx(&[confirmed(1)], 2), &[overspend])
+            .is_err());
+    }
+}
@@ -0,0 +1,356 @@
+// [AIR-3][AIS-3][BPC-3] Transaction standardness policy
+//
+// Relay and mempool rules on top of consensus, following Bitcoin Core's
+// `IsStandardTx` and sigop limits. A non-standard transaction may still be
+// valid in a block; nodes just refuse to relay it. Regtest accepts
+// non-standard transactions by default, as bitcoind does.
+
+use crate::AnyaError;
+use bitcoin::{Amount, FeeRate, Network, Script, Transaction, TxOut, Weight};
+
+/// Maximum weight of a standard transaction
+pub const MAX_STANDARD_TX_WEIGHT: Weight = Weight::from_wu(400_000);
+
+/// Maximum sigop cost of a standard transaction
+pub const MAX_STANDARD_TX_SIGOPS_COST: usize = 16_000;
+
+/// Maximum size of a standard OP_RETURN output script
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+
+/// Bitcoin Core's default `-dustrelayfee` of 3 sat/vB
+pub const DEFAULT_DUST_RELAY_FEE: FeeRate = FeeRate::from_sat_per_kwu(750);
+
+/// Standardness rule violations
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PolicyError {
+    #[error("Transaction has {inputs} inputs but {prevouts} spent outputs were given")]
+    PrevoutMismatch { inputs: usize, prevouts: usize },
+    #[error("Transaction weight {weight} exceeds the standard maximum of {max}")]
+    Weight { weight: Weight, max: Weight },
+    #[error("Sigop cost {cost} exceeds the standard maximum of {max}")]
+    SigopCost { cost: usize, max: usize },
+    #[error("Output {0} is a bare multisig")]
+    BareMultisig(usize),
+    #[error("Output {0} has a non-standard script")]
+    NonStandardScript(usize),
+    #[error("OP_RETURN output {index} is {size} bytes, above the maximum of {max}")]
+    OpReturnSize {
+        index: usize,
+        size: usize,
+        max: usize,
+    },
+    #[error("Output {index} of {value} is below the dust threshold of {threshold}")]
+    Dust {
+        index: usize,
+        value: Amount,
+        threshold: Amount,
+    },
+}
+
+impl From<PolicyError> for AnyaError {
+    fn from(err: PolicyError) -> Self {
+        AnyaError::Bitcoin(format!("Non-standard transaction: {err}"))
+    }
+}
+
+/// [AIR-3][BPC-3] Standardness limits applied on mempool acceptance
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyConfig {
+    /// Enforce the rules below; when false every transaction is standard
+    pub require_standard: bool,
+    /// Fee rate used to compute each output's dust threshold, 3 sat/vB by default
+    pub dust_relay_fee: FeeRate,
+    /// Maximum transaction weight
+    pub max_tx_weight: Weight,
+    /// Maximum sigop cost of a transaction
+    pub max_sigops_cost: usize,
+    /// Maximum size of an OP_RETURN output script
+    pub max_op_return_size: usize,
+    /// Accept bare multisig outputs
+    pub permit_bare_multisig: bool,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            require_standard: true,
+            dust_relay_fee: DEFAULT_DUST_RELAY_FEE,
+            max_tx_weight: MAX_STANDARD_TX_WEIGHT,
+            max_sigops_cost: MAX_STANDARD_TX_SIGOPS_COST,
+            max_op_return_size: MAX_OP_RETURN_RELAY,
+            permit_bare_multisig: false,
+        }
+    }
+}
+
+impl PolicyConfig {
+    /// Default policy of a network; regtest accepts non-standard transactions
+    pub fn for_network(network: Network) -> Self {
+        Self {
+            require_standard: network != Network::Regtest,
+            ..Self::default()
+        }
+    }
+
+    /// Check `tx` against these limits
+    ///
+    /// `prevouts` are the outputs spent by each input, in input order.
+    pub fn check(&self, tx: &Transaction, prevouts: &[TxOut]) -> Result<(), PolicyError> {
+        if !self.require_standard {
+            return Ok(());
+        }
+        if prevouts.len() != tx.input.len() {
+            return Err(PolicyError::PrevoutMismatch {
+                inputs: tx.input.len(),
+                prevouts: prevouts.len(),
+            });
+        }
+
+        let weight = tx.weight();
+        if weight > self.max_tx_weight {
+            return Err(PolicyError::Weight {
+                weight,
+                max: self.max_tx_weight,
+            });
+        }
+
+        let cost = tx.total_sigop_cost(|outpoint| {
+            tx.input
+                .iter()
+                .position(|input| input.previous_output == *outpoint)
+                .map(|index| prevouts[index].clone())
+        });
+        if cost > self.max_sigops_cost {
+            return Err(PolicyError::SigopCost {
+                cost,
+                max: self.max_sigops_cost,
+            });
+        }
+
+        for (index, output) in tx.output.iter().enumerate() {
+            let script = output.script_pubkey.as_script();
+            if script.is_op_return() {
+                if script.len() > self.max_op_return_size {
+                    return Err(PolicyError::OpReturnSize {
+                        index,
+                        size: script.len(),
+                        max: self.max_op_return_size,
+                    });
+                }
+                continue;
+            }
+            if script.is_multisig() {
+                if !self.permit_bare_multisig {
+                    return Err(PolicyError::BareMultisig(index));
+                }
+            } else if !is_standard_script(script) {
+                return Err(PolicyError::NonStandardScript(index));
+            }
+
+            let threshold = script.minimal_non_dust_custom(self.dust_relay_fee);
+            if output.value < threshold {
+                return Err(PolicyError::Dust {
+                    index,
+                    value: output.value,
+                    threshold,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check `tx` against the default mainnet policy
+pub fn check_standard(tx: &Transaction, prevouts: &[TxOut]) -> Result<(), PolicyError> {
+    PolicyConfig::default().check(tx, prevouts)
+}
+
+/// Smallest relayable value of an output paying `script`, at the default
+/// dust relay fee
+pub fn dust_threshold(script: &Script) -> Amount {
+    script.minimal_non_dust_custom(DEFAULT_DUST_RELAY_FEE)
+}
+
+/// Output script templates relayed by default, other than OP_RETURN and multisig
+fn is_standard_script(script: &Script) -> bool {
+    script.is_p2pk()
+        || script.is_p2pkh()
+        || script.is_p2sh()
+        // Includes P2WPKH, P2WSH, P2TR and future witness versions
+        || script.is_witness_program()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::Hash;
+    use bitcoin::opcodes::all::{OP_CHECKMULTISIG, OP_CHECKSIG, OP_PUSHNUM_1};
+    use bitcoin::script::{Builder, PushBytesBuf};
+    use bitcoin::transaction::Version;
+    use bitcoin::{OutPoint, PublicKey, ScriptBuf, Sequence, TxIn, Txid, WPubkeyHash, Witness};
+
+    fn p2wpkh() -> ScriptBuf {
+        ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros())
+    }
+
+    fn output(script_pubkey: ScriptBuf, sats: u64) -> TxOut {
+        TxOut {
+            value: Amount::from_sat(sats),
+            script_pubkey,
+        }
+    }
+
+    /// Transaction spending one confirmed output, with the given outputs
+    fn tx(script_sig: ScriptBuf, outputs: Vec<TxOut>) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), 0),
+                script_sig,
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: outputs,
+        }
+    }
+
+    fn check(outputs: Vec<TxOut>) -> Result<(), PolicyError> {
+        check_standard(&tx(ScriptBuf::new(), outputs), &[output(p2wpkh(), 100_000)])
+    }
+
+    fn op_return(len: usize) -> ScriptBuf {
+        ScriptBuf::new_op_return(PushBytesBuf::try_from(vec![0u8; len]).unwrap())
+    }
+
+    fn bare_multisig() -> ScriptBuf {
+        let key: PublicKey = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+            .parse()
+            .unwrap();
+        Builder::new()
+            .push_opcode(OP_PUSHNUM_1)
+            .push_key(&key)
+            .push_opcode(OP_PUSHNUM_1)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script()
+    }
+
+    #[test]
+    fn test_standard_transaction_accepted() {
+        let op_return = op_return(80);
+        check(vec![output(p2wpkh(), 50_000), output(op_return, 0)]).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_dust_output() {
+        // 294 sats is the P2WPKH dust threshold at 3 sat/vB
+        check(vec![output(p2wpkh(), 294)]).unwrap();
+        assert_eq!(
+            check(vec![output(p2wpkh(), 293)]),
+            Err(PolicyError::Dust {
+                index: 0,
+                value: Amount::from_sat(293),
+                threshold: Amount::from_sat(294),
+            })
+        );
+    }
+
+    #[test]
+    fn test_dust_threshold_depends_on_script_type() {
+        let p2pkh = ScriptBuf::new_p2pkh(&bitcoin::PubkeyHash::all_zeros());
+        let p2tr =
+            ScriptBuf::new_p2tr_tweaked(bitcoin::key::TweakedPublicKey::dangerous_assume_tweaked(
+                bitcoin::XOnlyPublicKey::from_slice(&[2; 32]).unwrap(),
+            ));
+        assert_eq!(dust_threshold(&p2pkh), Amount::from_sat(546));
+        assert_eq!(dust_threshold(&p2wpkh()), Amount::from_sat(294));
+        assert_eq!(dust_threshold(&p2tr), Amount::from_sat(330));
+    }
+
+    #[test]
+    fn test_rejects_overweight_transaction() {
+        let huge = ScriptBuf::from_bytes(vec![0x51; 100_001]);
+        assert!(matches!(
+            check(vec![output(huge, 50_000)]),
+            Err(PolicyError::Weight { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_bare_multisig_unless_permitted() {
+        assert_eq!(
+            check(vec![
+                output(p2wpkh(), 50_000),
+                output(bare_multisig(), 50_000)
+            ]),
+            Err(PolicyError::BareMultisig(1))
+        );
+
+        let permissive = PolicyConfig {
+            permit_bare_multisig: true,
+            ..PolicyConfig::default()
+        };
+        let tx = tx(ScriptBuf::new(), vec![output(bare_multisig(), 50_000)]);
+        permissive.check(&tx, &[output(p2wpkh(), 100_000)]).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_non_standard_script() {
+        let anyone_can_spend = Builder::new().push_opcode(OP_PUSHNUM_1).into_script();
+        assert_eq!(
+            check(vec![output(anyone_can_spend, 50_000)]),
+            Err(PolicyError::NonStandardScript(0))
+        );
+    }
+
+    #[test]
+    fn test_rejects_oversized_op_return() {
+        let op_return = op_return(81);
+        assert_eq!(
+            check(vec![output(op_return, 0)]),
+            Err(PolicyError::OpReturnSize {
+                index: 0,
+                size: 84,
+                max: MAX_OP_RETURN_RELAY,
+            })
+        );
+    }
+
+    #[test]
+    fn test_rejects_excessive_sigops() {
+        // P2SH redeem script of 520 CHECKMULTISIGs, 20 sigops each
+        let redeem_script = ScriptBuf::from_bytes(vec![OP_CHECKMULTISIG.to_u8(); 520]);
+        let script_sig = Builder::new()
+            .push_slice(PushBytesBuf::try_from(redeem_script.to_bytes()).unwrap())
+            .into_script();
+        let prevout = output(ScriptBuf::new_p2sh(&redeem_script.script_hash()), 100_000);
+        let tx = tx(script_sig, vec![output(p2wpkh(), 50_000)]);
+
+        assert_eq!(
+            check_standard(&tx, &[prevout]),
+            Err(PolicyError::SigopCost {
+                cost: 520 * 20 * 4,
+                max: MAX_STANDARD_TX_SIGOPS_COST,
+            })
+        );
+        // Every input needs its spent output to count P2SH and witness sigops
+        assert_eq!(
+            check_standard(&tx, &[]),
+            Err(PolicyError::PrevoutMismatch {
+                inputs: 1,
+                prevouts: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_regtest_accepts_non_standard() {
+        let bare_checksig = Builder::new().push_opcode(OP_CHECKSIG).into_script();
+        let tx = tx(ScriptBuf::new(), vec![output(bare_checksig, 1)]);
+        assert!(check_standard(&tx, &[output(p2wpkh(), 100_000)]).is_err());
+        PolicyConfig::for_network(Network::Regtest)
+            .check(&tx, &[])
+            .unwrap();
+    }
+}
@@ -4,22 +4,43 @@
 
 // Core modules for Bitcoin functionality
 pub mod adapters;
+pub mod bip21; // BIP-21 payment URIs
 pub mod bip341;
+pub mod block_index; // Persistent block index and active chain
+pub mod bolt12; // BOLT12 offer decoding
+pub mod broadcast; // Transaction broadcast retry queue
+pub mod chain_quality; // Reorg depth, stale-rate and hashrate anomaly detection
+pub mod chain_source; // Pluggable bitcoind / Electrum / Esplora data sources
 pub mod compat; // Compatibility module for older import patterns
 pub mod config;
+pub mod confirmations; // Transaction confirmation tracking
+pub mod consensus_params; // Network-specific activation heights and subsidy schedule
 pub mod error;
 pub mod external_endpoints; // Centralized external (Electrum / explorer / Liquid) endpoints
 pub mod interface;
 pub mod layer2; // Export layer2 module for Layer2Protocol trait
 pub mod lightning;
+pub mod lightning_anchors; // Anchor-output commitments and CPFP fee bumping
+pub mod lightning_store; // Crash-safe Lightning channel state and static backups
 pub mod manager;
+pub mod mempool; // Mining-ordered transaction pool
+pub mod network_params; // Network magic, ports, seeds and signet challenge
 pub mod node; // Bitcoin node management
+pub mod privacy; // Transaction privacy analysis
 pub mod protocol; // Bitcoin protocol compliance module
 pub mod rpc; // RPC shim re-exported for adapters/tests
 pub mod rust;
+pub mod script_cache; // Cache of successful script verifications
+pub mod silent_payments; // BIP-352 silent payment outputs
+pub mod spv; // SPV merkle-proof and header-chain verification
+pub mod sync; // Headers-first initial block download
 pub mod taproot;
+pub mod timestamp; // OpenTimestamps-style hash anchoring
+pub mod utxo_set; // UTXO set with assumeutxo-style snapshots
 pub mod validation; // Consolidated validation module
+pub mod version_bits; // BIP-9 / BIP-8 soft-fork signal monitoring
 pub mod wallet; // Bitcoin wallet management // Lightning Network implementation
+pub mod weight; // Witness element sizes and fees for weight estimates
 
 // Re-export key interfaces for easier access
 pub use adapters::BitcoinAdapter;
@@ -0,0 +1,620 @@
+// [AIR-3][AIS-3][BPC-3] Network parameters
+// Network magic, default port, DNS seeds and the BIP-325 signet challenge,
+// with block signature validation for custom signets.
+
+use crate::AnyaError;
+use bitcoin::blockdata::opcodes::all::{OP_CHECKMULTISIG, OP_PUSHBYTES_0, OP_PUSHNUM_1};
+use bitcoin::blockdata::opcodes::Opcode;
+use bitcoin::consensus::{encode, Decodable, Encodable};
+use bitcoin::hashes::{hash160, sha256d, Hash};
+use bitcoin::p2p::Magic;
+use bitcoin::script::{Builder, Instruction, PushBytesBuf};
+use bitcoin::secp256k1::{Message, Secp256k1};
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::{
+    absolute, transaction, Amount, Block, Network, OutPoint, PublicKey, ScriptBuf, Sequence,
+    Transaction, TxIn, TxOut, Witness, XOnlyPublicKey,
+};
+use thiserror::Error;
+
+/// Header of the signet solution push in the witness commitment output
+pub const SIGNET_HEADER: [u8; 4] = [0xec, 0xc7, 0xda, 0xa2];
+
+/// Default P2P port of signet networks
+pub const SIGNET_DEFAULT_PORT: u16 = 38333;
+
+/// Challenge of the default public signet (1-of-2 bare multisig)
+const DEFAULT_SIGNET_CHALLENGE: &str = "512103ad5e0edad18cb1f0fc0d28a3d4f1f3e445640337489abb10404f2d1e086be430210359ef5021964fe22d6f8e05b2463c9540ce96883fe3b278760f048f5189f2e6c452ae";
+
+/// Prefix of the BIP-141 witness commitment output script
+const WITNESS_COMMITMENT_PREFIX: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// Signet block validation errors
+#[derive(Debug, Error)]
+pub enum SignetError {
+    #[error("Block has no coinbase transaction")]
+    MissingCoinbase,
+    #[error("Invalid signet solution: {0}")]
+    InvalidSolution(String),
+    #[error("Unsupported signet challenge: {0}")]
+    UnsupportedChallenge(ScriptBuf),
+    #[error("Signet block signature does not satisfy the challenge")]
+    InvalidSignature,
+}
+
+impl From<SignetError> for AnyaError {
+    fn from(err: SignetError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+/// Result type for signet validation
+pub type SignetResult<T> = Result<T, SignetError>;
+
+/// Parameters identifying a Bitcoin P2P network
+///
+/// The well-known networks come from [`NetworkParams::for_network`]; private
+/// test networks override the magic, port and seeds, or are built with
+/// [`NetworkParams::custom_signet`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkParams {
+    /// Underlying network, used for address encoding and consensus rules
+    pub network: Network,
+    /// Message start bytes used in the P2P handshake
+    pub magic: Magic,
+    /// Default P2P port
+    pub default_port: u16,
+    /// DNS seeds queried for peer discovery
+    pub dns_seeds: Vec<String>,
+    /// Script block signatures must satisfy (signet only)
+    pub signet_challenge: Option<ScriptBuf>,
+}
+
+impl NetworkParams {
+    /// Parameters of a well-known network
+    pub fn for_network(network: Network) -> Self {
+        let (default_port, dns_seeds): (u16, &[&str]) = match network {
+            Network::Bitcoin => (
+                8333,
+                &[
+                    "seed.bitcoin.sipa.be",
+                    "dnsseed.bluematt.me",
+                    "seed.bitcoinstats.com",
+                    "seed.bitcoin.jonasschnelli.ch",
+                    "seed.btc.petertodd.net",
+                    "seed.bitcoin.sprovoost.nl",
+                ],
+            ),
+            Network::Testnet => (
+                18333,
+                &[
+                    "testnet-seed.bitcoin.jonasschnelli.ch",
+                    "seed.tbtc.petertodd.net",
+                    "testnet-seed.bluematt.me",
+                ],
+            ),
+            Network::Signet => (SIGNET_DEFAULT_PORT, &["seed.signet.bitcoin.sprovoost.nl"]),
+            _ => (18444, &[]),
+        };
+
+        let signet_challenge = (network == Network::Signet).then(|| {
+            ScriptBuf::from_hex(DEFAULT_SIGNET_CHALLENGE).expect("valid default signet challenge")
+        });
+
+        Self {
+            network,
+            magic: Magic::from(network),
+            default_port,
+            dns_seeds: dns_seeds.iter().map(|seed| seed.to_string()).collect(),
+            signet_challenge,
+        }
+    }
+
+    /// Parameters for a signet with its own challenge script
+    ///
+    /// The magic is derived from the challenge as in BIP-325, so peers of
+    /// other signets reject the handshake. No DNS seeds are set.
+    pub fn custom_signet(challenge: ScriptBuf) -> Self {
+        Self {
+            network: Network::Signet,
+            magic: signet_magic(&challenge),
+            default_port: SIGNET_DEFAULT_PORT,
+            dns_seeds: Vec::new(),
+            signet_challenge: Some(challenge),
+        }
+    }
+
+    /// Override the network magic
+    pub fn with_magic(mut self, magic: Magic) -> Self {
+        self.magic = magic;
+        self
+    }
+
+    /// Override the default P2P port
+    pub fn with_default_port(mut self, port: u16) -> Self {
+        self.default_port = port;
+        self
+    }
+
+    /// Override the DNS seeds
+    pub fn with_dns_seeds(mut self, dns_seeds: Vec<String>) -> Self {
+        self.dns_seeds = dns_seeds;
+        self
+    }
+
+    /// Check the BIP-325 block signature against the signet challenge
+    ///
+    /// Always succeeds on networks without a challenge and for the genesis
+    /// block, which carries no solution.
+    pub fn check_block_signature(&self, block: &Block) -> SignetResult<()> {
+        match &self.signet_challenge {
+            Some(challenge) if block.header.prev_blockhash != bitcoin::BlockHash::all_zeros() => {
+                check_signet_solution(block, challenge)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for NetworkParams {
+    fn default() -> Self {
+        Self::for_network(Network::Bitcoin)
+    }
+}
+
+/// Network magic of a signet: the first four bytes of sha256d of the
+/// challenge serialized as a single data push
+pub fn signet_magic(challenge: &ScriptBuf) -> Magic {
+    let mut data = Vec::with_capacity(challenge.len() + 9);
+    challenge
+        .consensus_encode(&mut data)
+        .expect("writing to a Vec cannot fail");
+    let hash = sha256d::Hash::hash(&data).to_byte_array();
+    Magic::from_bytes([hash[0], hash[1], hash[2], hash[3]])
+}
+
+/// Verify a block's signet solution against `challenge`
+///
+/// Supports `OP_TRUE`, bare k-of-n multisig, P2WPKH and P2TR key-path
+/// challenges.
+pub fn check_signet_solution(block: &Block, challenge: &ScriptBuf) -> SignetResult<()> {
+    let (to_sign, to_spend_output) = signet_transactions(block, challenge)?;
+
+    if challenge.as_bytes() == [OP_PUSHNUM_1.to_u8()] {
+        return Ok(());
+    }
+    let input = &to_sign.input[0];
+    let secp = Secp256k1::verification_only();
+    let mut cache = SighashCache::new(&to_sign);
+
+    if challenge.is_p2wpkh() {
+        if !input.script_sig.is_empty() || input.witness.len() != 2 {
+            return Err(SignetError::InvalidSignature);
+        }
+        let signature = bitcoin::ecdsa::Signature::from_slice(&input.witness[0])
+            .map_err(|_| SignetError::InvalidSignature)?;
+        let public_key =
+            PublicKey::from_slice(&input.witness[1]).map_err(|_| SignetError::InvalidSignature)?;
+        if hash160::Hash::hash(&input.witness[1]).as_byte_array() != &challenge.as_bytes()[2..22] {
+            return Err(SignetError::InvalidSignature);
+        }
+        let sighash = cache
+            .p2wpkh_signature_hash(0, challenge, Amount::ZERO, signature.sighash_type)
+            .map_err(|e| SignetError::InvalidSolution(e.to_string()))?;
+        return verify_ecdsa(&secp, &sighash.to_byte_array(), &signature, &public_key)
+            .then_some(())
+            .ok_or(SignetError::InvalidSignature);
+    }
+
+    if challenge.is_p2tr() {
+        if !input.script_sig.is_empty() || input.witness.len() != 1 {
+            return Err(SignetError::InvalidSignature);
+        }
+        let signature = bitcoin::taproot::Signature::from_slice(&input.witness[0])
+            .map_err(|_| SignetError::InvalidSignature)?;
+        let output_key = XOnlyPublicKey::from_slice(&challenge.as_bytes()[2..34])
+            .map_err(|_| SignetError::InvalidSignature)?;
+        let sighash = cache
+            .taproot_key_spend_signature_hash(
+                0,
+                &Prevouts::All(&[to_spend_output]),
+                signature.sighash_type,
+            )
+            .map_err(|e| SignetError::InvalidSolution(e.to_string()))?;
+        let message = Message::from_digest(sighash.to_byte_array());
+        return secp
+            .verify_schnorr(&signature.signature, &message, &output_key)
+            .map_err(|_| SignetError::InvalidSignature);
+    }
+
+    if let Some((required, keys)) = parse_multisig(challenge) {
+        if !input.witness.is_empty() {
+            return Err(SignetError::InvalidSignature);
+        }
+        let signatures = multisig_signatures(&input.script_sig)?;
+        if signatures.len() != required {
+            return Err(SignetError::InvalidSignature);
+        }
+
+        // CHECKMULTISIG matches signatures against keys in order
+        let mut keys = keys.iter();
+        for signature in signatures {
+            let signature = bitcoin::ecdsa::Signature::from_slice(&signature)
+                .map_err(|_| SignetError::InvalidSignature)?;
+            let sighash = cache
+                .legacy_signature_hash(0, challenge, signature.sighash_type.to_u32())
+                .map_err(|e| SignetError::InvalidSolution(e.to_string()))?;
+            let digest = sighash.to_byte_array();
+            if !keys.any(|key| verify_ecdsa(&secp, &digest, &signature, key)) {
+                return Err(SignetError::InvalidSignature);
+            }
+        }
+        return Ok(());
+    }
+
+    Err(SignetError::UnsupportedChallenge(challenge.clone()))
+}
+
+/// Build the BIP-325 `to_sign` transaction and the output it spends
+fn signet_transactions(block: &Block, challenge: &ScriptBuf) -> SignetResult<(Transaction, TxOut)> {
+    let coinbase = block.txdata.first().ok_or(SignetError::MissingCoinbase)?;
+
+    // The solution lives in the last witness commitment output; the block
+    // is signed with the solution stripped from that output
+    let mut modified_coinbase = coinbase.clone();
+    let mut solution = Vec::new();
+    if let Some(output) = modified_coinbase.output.iter_mut().rev().find(|output| {
+        output
+            .script_pubkey
+            .as_bytes()
+            .starts_with(&WITNESS_COMMITMENT_PREFIX)
+    }) {
+        let (script, extracted) = strip_solution(&output.script_pubkey)?;
+        if let Some(extracted) = extracted {
+            output.script_pubkey = script;
+            solution = extracted;
+        }
+    }
+
+    let (script_sig, witness) = if solution.is_empty() {
+        (ScriptBuf::new(), Witness::new())
+    } else {
+        let mut reader = solution.as_slice();
+        let script_sig = ScriptBuf::consensus_decode(&mut reader)
+            .map_err(|e| SignetError::InvalidSolution(e.to_string()))?;
+        let witness = Witness::consensus_decode(&mut reader)
+            .map_err(|e| SignetError::InvalidSolution(e.to_string()))?;
+        if !reader.is_empty() {
+            return Err(SignetError::InvalidSolution(
+                "Trailing data after witness".to_string(),
+            ));
+        }
+        (script_sig, witness)
+    };
+
+    let mut signed_block = block.clone();
+    signed_block.txdata[0] = modified_coinbase;
+    let merkle_root = signed_block
+        .compute_merkle_root()
+        .ok_or(SignetError::MissingCoinbase)?;
+
+    let mut block_data = Vec::with_capacity(72);
+    block
+        .header
+        .version
+        .consensus_encode(&mut block_data)
+        .and_then(|_| {
+            block
+                .header
+                .prev_blockhash
+                .consensus_encode(&mut block_data)
+        })
+        .and_then(|_| merkle_root.consensus_encode(&mut block_data))
+        .and_then(|_| block.header.time.consensus_encode(&mut block_data))
+        .expect("writing to a Vec cannot fail");
+    let block_data = PushBytesBuf::try_from(block_data).expect("72 bytes fit in a single push");
+
+    let to_spend_output = TxOut {
+        value: Amount::ZERO,
+        script_pubkey: challenge.clone(),
+    };
+    let to_spend = Transaction {
+        version: transaction::Version(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Builder::new()
+                .push_opcode(OP_PUSHBYTES_0)
+                .push_slice(block_data)
+                .into_script(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![to_spend_output.clone()],
+    };
+    let to_sign = Transaction {
+        version: transaction::Version(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(to_spend.compute_txid(), 0),
+            script_sig,
+            sequence: Sequence::ZERO,
+            witness,
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: Builder::new()
+                .push_opcode(bitcoin::opcodes::all::OP_RETURN)
+                .into_script(),
+        }],
+    };
+
+    Ok((to_sign, to_spend_output))
+}
+
+/// Truncate the first push starting with [`SIGNET_HEADER`] to the header,
+/// returning the rebuilt script and the removed solution bytes
+fn strip_solution(script: &ScriptBuf) -> SignetResult<(ScriptBuf, Option<Vec<u8>>)> {
+    let mut builder = Builder::new();
+    let mut solution = None;
+    for instruction in script.instructions() {
+        let instruction = instruction.map_err(|e| SignetError::InvalidSolution(e.to_string()))?;
+        builder = match instruction {
+            Instruction::PushBytes(data) if data.is_empty() => builder.push_opcode(OP_PUSHBYTES_0),
+            Instruction::PushBytes(data) => {
+                let bytes = data.as_bytes();
+                if solution.is_none()
+                    && bytes.len() > SIGNET_HEADER.len()
+                    && bytes.starts_with(&SIGNET_HEADER)
+                {
+                    solution = Some(bytes[SIGNET_HEADER.len()..].to_vec());
+                    builder.push_slice(SIGNET_HEADER)
+                } else {
+                    builder.push_slice(data)
+                }
+            }
+            Instruction::Op(opcode) => builder.push_opcode(opcode),
+        };
+    }
+    Ok((builder.into_script(), solution))
+}
+
+/// Parse `OP_k <pubkey>... OP_n OP_CHECKMULTISIG`
+fn parse_multisig(script: &ScriptBuf) -> Option<(usize, Vec<PublicKey>)> {
+    let instructions = script.instructions().collect::<Result<Vec<_>, _>>().ok()?;
+    let (first, rest) = instructions.split_first()?;
+    let (last, rest) = rest.split_last()?;
+    let (count, keys) = rest.split_last()?;
+
+    if last.opcode() != Some(OP_CHECKMULTISIG) {
+        return None;
+    }
+    let required = small_int(first.opcode()?)?;
+    let total = small_int(count.opcode()?)?;
+    let keys = keys
+        .iter()
+        .map(|instruction| PublicKey::from_slice(instruction.push_bytes()?.as_bytes()).ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    (required >= 1 && required <= total && total == keys.len()).then_some((required, keys))
+}
+
+/// Signatures pushed by a multisig scriptSig after the dummy element
+fn multisig_signatures(script_sig: &ScriptBuf) -> SignetResult<Vec<Vec<u8>>> {
+    let pushes = script_sig
+        .instructions()
+        .map(|instruction| match instruction {
+            Ok(Instruction::PushBytes(data)) => Ok(data.as_bytes().to_vec()),
+            _ => Err(SignetError::InvalidSignature),
+        })
+        .collect::<SignetResult<Vec<_>>>()?;
+
+    match pushes.split_first() {
+        // NULLDUMMY: the extra element consumed by CHECKMULTISIG must be empty
+        Some((dummy, signatures)) if dummy.is_empty() => Ok(signatures.to_vec()),
+        _ => Err(SignetError::InvalidSignature),
+    }
+}
+
+fn small_int(opcode: Opcode) -> Option<usize> {
+    let value = opcode.to_u8().checked_sub(OP_PUSHNUM_1.to_u8())? as usize + 1;
+    (value <= 16).then_some(value)
+}
+
+fn verify_ecdsa(
+    secp: &Secp256k1<bitcoin::secp256k1::VerifyOnly>,
+    digest: &[u8; 32],
+    signature: &bitcoin::ecdsa::Signature,
+    public_key: &PublicKey,
+) -> bool {
+    // Consensus does not enforce low-S on signet block signatures
+    let mut signature = signature.signature;
+    signature.normalize_s();
+    secp.verify_ecdsa(
+        &Message::from_digest(*digest),
+        &signature,
+        &public_key.inner,
+    )
+    .is_ok()
+}
+
+/// Serialize a signet solution for the witness commitment push
+pub fn encode_signet_solution(script_sig: &ScriptBuf, witness: &Witness) -> Vec<u8> {
+    let mut data = SIGNET_HEADER.to_vec();
+    data.extend(encode::serialize(script_sig));
+    data.extend(encode::serialize(witness));
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::block::{Header, Version};
+    use bitcoin::hashes::sha256;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::{BlockHash, CompactTarget, CompressedPublicKey, EcdsaSighashType, TxMerkleNode};
+
+    fn unsigned_block() -> Block {
+        let mut witness_commitment = [0u8; 36];
+        witness_commitment[..4].copy_from_slice(&WITNESS_COMMITMENT_PREFIX[2..]);
+        let commitment = Builder::new()
+            .push_opcode(bitcoin::opcodes::all::OP_RETURN)
+            .push_slice(witness_commitment)
+            .push_slice(SIGNET_HEADER)
+            .into_script();
+        let coinbase = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: Builder::new().push_int(1).into_script(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![
+                TxOut {
+                    value: Amount::from_sat(5_000_000_000),
+                    script_pubkey: ScriptBuf::new(),
+                },
+                TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: commitment,
+                },
+            ],
+        };
+
+        let mut block = Block {
+            header: Header {
+                version: Version::from_consensus(0x2000_0000),
+                prev_blockhash: BlockHash::from_byte_array([1; 32]),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: 1_700_000_000,
+                bits: CompactTarget::from_consensus(0x1e0377ae),
+                nonce: 0,
+            },
+            txdata: vec![coinbase],
+        };
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        block
+    }
+
+    /// Attach a solution to the witness commitment output
+    fn with_solution(mut block: Block, script_sig: ScriptBuf, witness: Witness) -> Block {
+        let output = &mut block.txdata[0].output[1];
+        let mut bytes = output.script_pubkey.to_bytes();
+        bytes.truncate(bytes.len() - SIGNET_HEADER.len() - 1);
+        let solution =
+            PushBytesBuf::try_from(encode_signet_solution(&script_sig, &witness)).unwrap();
+        output.script_pubkey = Builder::from(bytes).push_slice(solution).into_script();
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        block
+    }
+
+    fn sign_p2wpkh(block: Block, secret_key: &SecretKey, challenge: &ScriptBuf) -> Block {
+        let secp = Secp256k1::new();
+        let (to_sign, _) = signet_transactions(&block, challenge).unwrap();
+        let sighash = SighashCache::new(&to_sign)
+            .p2wpkh_signature_hash(0, challenge, Amount::ZERO, EcdsaSighashType::All)
+            .unwrap();
+        let signature = bitcoin::ecdsa::Signature::sighash_all(
+            secp.sign_ecdsa(&Message::from_digest(sighash.to_byte_array()), secret_key),
+        );
+        let public_key = secret_key.public_key(&secp);
+        let witness = Witness::from_slice(&[signature.to_vec(), public_key.serialize().to_vec()]);
+        with_solution(block, ScriptBuf::new(), witness)
+    }
+
+    fn p2wpkh_challenge(secret_key: &SecretKey) -> ScriptBuf {
+        let public_key = CompressedPublicKey(secret_key.public_key(&Secp256k1::new()));
+        ScriptBuf::new_p2wpkh(&public_key.wpubkey_hash())
+    }
+
+    #[test]
+    fn test_default_signet_magic_derived_from_challenge() {
+        let params = NetworkParams::for_network(Network::Signet);
+        let challenge = params.signet_challenge.clone().unwrap();
+        assert_eq!(signet_magic(&challenge), Magic::SIGNET);
+        assert_eq!(params.magic, Magic::SIGNET);
+    }
+
+    #[test]
+    fn test_custom_signet_params() {
+        let secret_key = SecretKey::from_slice(&[7; 32]).unwrap();
+        let challenge = p2wpkh_challenge(&secret_key);
+        let params = NetworkParams::custom_signet(challenge.clone())
+            .with_default_port(38444)
+            .with_dns_seeds(vec!["seed.example.org".to_string()]);
+
+        assert_eq!(params.network, Network::Signet);
+        assert_eq!(params.signet_challenge, Some(challenge.clone()));
+        assert_eq!(params.magic, signet_magic(&challenge));
+        assert_ne!(params.magic, Magic::SIGNET);
+        assert_eq!(params.default_port, 38444);
+        assert_eq!(params.dns_seeds, vec!["seed.example.org".to_string()]);
+
+        let magic = Magic::from_bytes([0xfa, 0xce, 0xb0, 0x0c]);
+        assert_eq!(params.with_magic(magic).magic, magic);
+    }
+
+    #[test]
+    fn test_custom_signet_block_signature() {
+        let secret_key = SecretKey::from_slice(&[7; 32]).unwrap();
+        let challenge = p2wpkh_challenge(&secret_key);
+        let params = NetworkParams::custom_signet(challenge.clone());
+
+        let block = sign_p2wpkh(unsigned_block(), &secret_key, &challenge);
+        params.check_block_signature(&block).unwrap();
+
+        // Changing a committed header field invalidates the signature
+        let mut tampered = block.clone();
+        tampered.header.time += 1;
+        assert!(matches!(
+            params.check_block_signature(&tampered),
+            Err(SignetError::InvalidSignature)
+        ));
+
+        // A block signed by another key does not satisfy the challenge
+        let other_key = SecretKey::from_slice(&[8; 32]).unwrap();
+        let forged = sign_p2wpkh(unsigned_block(), &other_key, &challenge);
+        assert!(params.check_block_signature(&forged).is_err());
+
+        // An unsigned block has no solution
+        assert!(params.check_block_signature(&unsigned_block()).is_err());
+
+        // Non-signet networks do not check signatures
+        NetworkParams::for_network(Network::Regtest)
+            .check_block_signature(&unsigned_block())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_multisig_signet_block_signature() {
+        let secp = Secp256k1::new();
+        let keys: Vec<SecretKey> = (1..=2u8)
+            .map(|i| SecretKey::from_slice(&sha256::Hash::hash(&[i]).to_byte_array()).unwrap())
+            .collect();
+        let challenge = Builder::new()
+            .push_int(1)
+            .push_key(&PublicKey::new(keys[0].public_key(&secp)))
+            .push_key(&PublicKey::new(keys[1].public_key(&secp)))
+            .push_int(2)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script();
+
+        let block = unsigned_block();
+        let (to_sign, _) = signet_transactions(&block, &challenge).unwrap();
+        let sighash = SighashCache::new(&to_sign)
+            .legacy_signature_hash(0, &challenge, EcdsaSighashType::All.to_u32())
+            .unwrap();
+        let signature = bitcoin::ecdsa::Signature::sighash_all(
+            secp.sign_ecdsa(&Message::from_digest(sighash.to_byte_array()), &keys[1]),
+        );
+        let script_sig = Builder::new()
+            .push_opcode(OP_PUSHBYTES_0)
+            .push_slice(PushBytesBuf::try_from(signature.to_vec()).unwrap())
+            .into_script();
+        let block = with_solution(block, script_sig, Witness::new());
+
+        check_signet_solution(&block, &challenge).unwrap();
+    }
+}
@@ -4,20 +4,121 @@
 // Bitcoin-Protocol-Compliant: Full BIP-341/342/174/340 support
 // AI-Testable: Comprehensive test coverage for node operations
 
+use crate::bitcoin::broadcast::BroadcastQueue;
+use crate::bitcoin::chain_source::ChainSource;
+use crate::bitcoin::network_params::NetworkParams;
+use crate::bitcoin::sync::{BlockSync, SyncConfig, SyncProgress};
 use crate::bitcoin::BitcoinConfig;
 use crate::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::p2p::ServiceFlags;
+use bitcoin::{Block, BlockHash, Network, Transaction, Txid};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// Default time to wait for a single peer to serve a pruned block
+const DEFAULT_PEER_BLOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// [AIR-3][AIS-3][BPC-3] Bitcoin Node implementation for managing Bitcoin Core connectivity
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct BitcoinNode {
     /// Configuration for the Bitcoin node
     config: BitcoinConfig,
+    /// Magic, ports, seeds and signet challenge of the network
+    network_params: NetworkParams,
     /// Current connection status
     status: Arc<RwLock<NodeStatus>>,
+    /// Block retrieval behaviour for pruned nodes
+    block_fetch_config: BlockFetchConfig,
+    /// Local block storage consulted before any peer
+    block_store: Option<Arc<dyn LocalBlockStore>>,
+    /// Peers that may be asked for blocks pruned locally
+    block_peers: Arc<RwLock<Vec<Arc<dyn BlockPeer>>>>,
+    /// Retry queue for outgoing transactions
+    broadcast_queue: Option<Arc<BroadcastQueue>>,
+    /// Initial block download behaviour
+    sync_config: SyncConfig,
+}
+
+impl fmt::Debug for BitcoinNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Show the status itself rather than tokio's lock wrapper
+        let status = match self.status.try_read() {
+            Ok(status) => format!("{:?}", *status),
+            Err(_) => "<locked>".to_string(),
+        };
+        f.debug_struct("BitcoinNode")
+            .field("config", &self.config)
+            .field("network_params", &self.network_params)
+            .field("status", &format_args!("{status}"))
+            .field("block_fetch_config", &self.block_fetch_config)
+            .field("sync_config", &self.sync_config)
+            .field("has_block_store", &self.block_store.is_some())
+            .field("has_broadcast_queue", &self.broadcast_queue.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// [AIR-3][BPC-3] Block retrieval configuration for pruned nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockFetchConfig {
+    /// Ask peers for blocks that have been pruned locally
+    pub fetch_pruned_from_peers: bool,
+    /// Maximum time to wait for a single peer to serve a block
+    pub peer_timeout: Duration,
+}
+
+impl Default for BlockFetchConfig {
+    fn default() -> Self {
+        Self {
+            fetch_pruned_from_peers: true,
+            peer_timeout: DEFAULT_PEER_BLOCK_TIMEOUT,
+        }
+    }
+}
+
+/// [AIR-3][BPC-3] Outcome of a local block lookup
+#[derive(Debug, Clone)]
+pub enum LocalBlock {
+    /// Block is available on disk
+    Available(Block),
+    /// Block is known but its data has been pruned
+    Pruned,
+    /// Block is not known to the local store
+    Unknown,
+}
+
+/// [AIR-3][BPC-3] Local block storage used by the node
+#[async_trait]
+pub trait LocalBlockStore: Send + Sync {
+    /// Look up a block by hash
+    async fn get_block(&self, hash: &BlockHash) -> AnyaResult<LocalBlock>;
+}
+
+/// [AIR-3][BPC-3] A connected peer able to serve blocks over P2P
+#[async_trait]
+pub trait BlockPeer: Send + Sync {
+    /// Human-readable peer identifier (usually its socket address)
+    fn id(&self) -> String;
+
+    /// Service flags advertised by the peer in its version message
+    fn services(&self) -> ServiceFlags;
+
+    /// Request a full block (`getdata`/`MSG_BLOCK`) from the peer
+    async fn request_block(&self, hash: &BlockHash) -> AnyaResult<Block>;
+
+    /// Request the headers following the first known `locator` hash (`getheaders`)
+    async fn request_headers(&self, _locator: &[BlockHash]) -> AnyaResult<Vec<BlockHeader>> {
+        Err(AnyaError::Bitcoin(format!(
+            "Peer {} does not serve headers",
+            self.id()
+        )))
+    }
 }
 
 /// [AIR-3][AIS-3][BPC-3] Node status tracking
@@ -49,12 +150,198 @@ impl BitcoinNode {
             peer_count: None,
         };
 
+        let network = match config.network.as_str() {
+            "mainnet" => Network::Bitcoin,
+            name => name
+                .parse::<Network>()
+                .map_err(|e| AnyaError::Bitcoin(e.to_string()))?,
+        };
+
         Ok(Self {
+            network_params: NetworkParams::for_network(network),
             config,
             status: Arc::new(RwLock::new(status)),
+            block_fetch_config: BlockFetchConfig::default(),
+            block_store: None,
+            block_peers: Arc::new(RwLock::new(Vec::new())),
+            broadcast_queue: None,
+            sync_config: SyncConfig::default(),
         })
     }
 
+    /// [AIR-3][BPC-3] Set the block retrieval configuration
+    pub fn with_block_fetch_config(mut self, block_fetch_config: BlockFetchConfig) -> Self {
+        self.block_fetch_config = block_fetch_config;
+        self
+    }
+
+    /// [AIR-3][BPC-3] Use custom network parameters, e.g. a private signet
+    pub fn with_network_params(mut self, network_params: NetworkParams) -> Self {
+        self.network_params = network_params;
+        self
+    }
+
+    /// [AIR-3][BPC-3] Attach the local block store
+    pub fn with_block_store(mut self, block_store: Arc<dyn LocalBlockStore>) -> Self {
+        self.block_store = Some(block_store);
+        self
+    }
+
+    /// [AIR-3][BPC-3] Attach the transaction broadcast queue
+    pub fn with_broadcast_queue(mut self, broadcast_queue: Arc<BroadcastQueue>) -> Self {
+        self.broadcast_queue = Some(broadcast_queue);
+        self
+    }
+
+    /// [AIR-3][BPC-3] Set the initial block download configuration
+    pub fn with_sync_config(mut self, sync_config: SyncConfig) -> Self {
+        self.sync_config = sync_config;
+        self
+    }
+
+    /// [AIR-3][BPC-3] Register a peer that may serve blocks
+    pub async fn add_block_peer(&self, peer: Arc<dyn BlockPeer>) {
+        self.block_peers.write().await.push(peer);
+    }
+
+    /// [AIR-3][AIS-3][BPC-3] Retrieve a block by hash
+    ///
+    /// The local store is consulted first. If the block has been pruned and
+    /// `fetch_pruned_from_peers` is enabled, each peer advertising
+    /// `NODE_NETWORK` is asked in turn, bounded by `peer_timeout`. Blocks
+    /// returned by a peer are only accepted if their hash matches.
+    pub async fn get_block(&self, hash: &BlockHash) -> AnyaResult<Block> {
+        let store = self
+            .block_store
+            .as_ref()
+            .ok_or_else(|| AnyaError::Bitcoin("No block store configured".to_string()))?;
+
+        match store.get_block(hash).await? {
+            LocalBlock::Available(block) => return Ok(block),
+            LocalBlock::Unknown => {
+                return Err(AnyaError::NotFound(format!("Block {hash} not found")));
+            }
+            LocalBlock::Pruned => {}
+        }
+
+        if !self.block_fetch_config.fetch_pruned_from_peers {
+            return Err(AnyaError::NotFound(format!(
+                "Block {hash} has been pruned and peer fetching is disabled"
+            )));
+        }
+
+        self.fetch_block_from_peers(hash).await
+    }
+
+    async fn fetch_block_from_peers(&self, hash: &BlockHash) -> AnyaResult<Block> {
+        let peers: Vec<Arc<dyn BlockPeer>> = self
+            .block_peers
+            .read()
+            .await
+            .iter()
+            .filter(|peer| peer.services().has(ServiceFlags::NETWORK))
+            .cloned()
+            .collect();
+
+        for peer in peers {
+            let request = peer.request_block(hash);
+            match tokio::time::timeout(self.block_fetch_config.peer_timeout, request).await {
+                Ok(Ok(block)) if block.block_hash() == *hash => {
+                    match self.network_params.check_block_signature(&block) {
+                        Ok(()) => return Ok(block),
+                        Err(e) => log::warn!(
+                            "Peer {} returned block {} with an invalid signet signature: {}",
+                            peer.id(),
+                            hash,
+                            e
+                        ),
+                    }
+                }
+                Ok(Ok(block)) => log::warn!(
+                    "Peer {} returned block {} when {} was requested",
+                    peer.id(),
+                    block.block_hash(),
+                    hash
+                ),
+                Ok(Err(e)) => {
+                    log::debug!("Peer {} could not serve block {}: {}", peer.id(), hash, e)
+                }
+                Err(_) => log::debug!("Peer {} timed out serving block {}", peer.id(), hash),
+            }
+        }
+
+        Err(AnyaError::NotFound(format!(
+            "Block {hash} has been pruned and no peer could serve it"
+        )))
+    }
+
+    /// [AIR-3][AIS-3][BPC-3] Run a headers-first initial block download
+    ///
+    /// Downloads and validates the header chain extending `tip` at
+    /// `tip_height`, then downloads the blocks in parallel from the registered
+    /// peers and passes them to `connect` in height order. The node's block
+    /// height is set to the last connected block. Returns the new tip height.
+    pub async fn initial_block_download(
+        &self,
+        tip: &BlockHeader,
+        tip_height: u32,
+        mut connect: impl FnMut(u32, Block) -> AnyaResult<()>,
+        mut progress: impl FnMut(SyncProgress),
+    ) -> AnyaResult<u32> {
+        let peers = self.block_peers.read().await.clone();
+        let sync = BlockSync::new(self.sync_config.clone(), self.network_params.clone(), peers);
+
+        let headers = sync.sync_headers(tip, tip_height).await?;
+        log::info!(
+            "Validated {} headers, downloading blocks {} to {}",
+            headers.len(),
+            tip_height + 1,
+            tip_height + headers.len() as u32
+        );
+
+        let mut connected = tip_height;
+        let result = sync
+            .download_blocks(
+                &headers,
+                tip_height + 1,
+                |height, block| {
+                    connect(height, block)?;
+                    connected = height;
+                    Ok(())
+                },
+                |report| {
+                    log::debug!(
+                        "Sync at height {} of {} ({:.1}%, {:.1} blocks/s)",
+                        report.height,
+                        report.target_height,
+                        report.percent,
+                        report.blocks_per_sec
+                    );
+                    progress(report)
+                },
+            )
+            .await;
+
+        self.status.write().await.block_height = Some(u64::from(connected));
+        result
+    }
+
+    /// [AIR-3][AIS-3][BPC-3] Broadcast a transaction to peers
+    ///
+    /// The transaction is persisted in the broadcast queue and announced
+    /// immediately; if too few peers request it, the queue keeps retrying
+    /// with backoff.
+    pub async fn broadcast_transaction(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        let queue = self
+            .broadcast_queue
+            .as_ref()
+            .ok_or_else(|| AnyaError::Bitcoin("No broadcast queue configured".to_string()))?;
+
+        queue.enqueue(tx.clone()).await?;
+        queue.process_due().await?;
+        Ok(tx.compute_txid())
+    }
+
     /// [AIR-3][AIS-3][BPC-3] Start the Bitcoin node connection
     pub async fn start(&self) -> AnyaResult<()> {
         let mut status = self.status.write().await;
@@ -101,10 +388,22 @@ impl BitcoinNode {
         self.config.network.to_string()
     }
 
+    /// [AIR-3][BPC-3] Get the network parameters used for P2P and validation
+    pub fn network_params(&self) -> &NetworkParams {
+        &self.network_params
+    }
+
     /// [AIR-3][AIS-3][BPC-3] Get node configuration
     pub fn get_config(&self) -> &BitcoinConfig {
         &self.config
     }
+
+    /// [AIR-3][BPC-3] Create the chain source selected by the node configuration
+    pub fn chain_source(&self) -> AnyaResult<Arc<dyn ChainSource>> {
+        self.config
+            .chain_source_config()
+            .connect(self.network_params.network)
+    }
 }
 
 impl Default for BitcoinNode {
@@ -113,3 +412,145 @@ impl Default for BitcoinNode {
         Self::new(config).expect("Failed to create default BitcoinNode")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::hashes::Hash;
+
+    struct PrunedStore;
+
+    #[async_trait]
+    impl LocalBlockStore for PrunedStore {
+        async fn get_block(&self, _hash: &BlockHash) -> AnyaResult<LocalBlock> {
+            Ok(LocalBlock::Pruned)
+        }
+    }
+
+    struct MockPeer {
+        services: ServiceFlags,
+        block: Option<Block>,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl BlockPeer for MockPeer {
+        fn id(&self) -> String {
+            "mock-peer".to_string()
+        }
+
+        fn services(&self) -> ServiceFlags {
+            self.services
+        }
+
+        async fn request_block(&self, _hash: &BlockHash) -> AnyaResult<Block> {
+            tokio::time::sleep(self.delay).await;
+            self.block
+                .clone()
+                .ok_or_else(|| AnyaError::NotFound("notfound".to_string()))
+        }
+    }
+
+    fn pruned_node() -> BitcoinNode {
+        BitcoinNode::default()
+            .with_block_store(Arc::new(PrunedStore))
+            .with_block_fetch_config(BlockFetchConfig {
+                fetch_pruned_from_peers: true,
+                peer_timeout: Duration::from_millis(50),
+            })
+    }
+
+    #[tokio::test]
+    async fn test_debug_shows_status() {
+        let node = pruned_node();
+        let debug = format!("{node:?}");
+        assert!(debug.contains("status: NodeStatus { connected: false"));
+        assert!(debug.contains("has_block_store: true"));
+        assert!(debug.ends_with(", .. }"));
+
+        let _writer = node.status.write().await;
+        assert!(format!("{node:?}").contains("status: <locked>"));
+    }
+
+    #[tokio::test]
+    async fn test_pruned_block_served_by_peer() {
+        let block = genesis_block(Network::Regtest);
+        let hash = block.block_hash();
+        let node = pruned_node();
+
+        // Pruned peers only advertise NODE_NETWORK_LIMITED and must be skipped
+        node.add_block_peer(Arc::new(MockPeer {
+            services: ServiceFlags::NETWORK_LIMITED,
+            block: None,
+            delay: Duration::ZERO,
+        }))
+        .await;
+        node.add_block_peer(Arc::new(MockPeer {
+            services: ServiceFlags::NETWORK | ServiceFlags::WITNESS,
+            block: Some(block.clone()),
+            delay: Duration::ZERO,
+        }))
+        .await;
+
+        let fetched = node.get_block(&hash).await.unwrap();
+        assert_eq!(fetched.block_hash(), hash);
+    }
+
+    #[tokio::test]
+    async fn test_pruned_block_no_peer_can_serve() {
+        let hash = BlockHash::all_zeros();
+        let node = pruned_node();
+
+        node.add_block_peer(Arc::new(MockPeer {
+            services: ServiceFlags::NETWORK,
+            block: None,
+            delay: Duration::ZERO,
+        }))
+        .await;
+        // A slow peer exceeds the timeout
+        node.add_block_peer(Arc::new(MockPeer {
+            services: ServiceFlags::NETWORK,
+            block: Some(genesis_block(Network::Regtest)),
+            delay: Duration::from_secs(5),
+        }))
+        .await;
+
+        let result = node.get_block(&hash).await;
+        assert!(matches!(result, Err(AnyaError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_pruned_block_peer_fetch_disabled() {
+        let block = genesis_block(Network::Regtest);
+        let node = pruned_node().with_block_fetch_config(BlockFetchConfig {
+            fetch_pruned_from_peers: false,
+            ..BlockFetchConfig::default()
+        });
+        node.add_block_peer(Arc::new(MockPeer {
+            services: ServiceFlags::NETWORK,
+            block: Some(block.clone()),
+            delay: Duration::ZERO,
+        }))
+        .await;
+
+        assert!(node.get_block(&block.block_hash()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_custom_signet_rejects_unsigned_peer_block() {
+        let mut block = genesis_block(Network::Regtest);
+        block.header.prev_blockhash = BlockHash::from_byte_array([1; 32]);
+        let challenge = bitcoin::ScriptBuf::new_p2wpkh(&bitcoin::WPubkeyHash::all_zeros());
+        let node = pruned_node().with_network_params(NetworkParams::custom_signet(challenge));
+        node.add_block_peer(Arc::new(MockPeer {
+            services: ServiceFlags::NETWORK,
+            block: Some(block.clone()),
+            delay: Duration::ZERO,
+        }))
+        .await;
+
+        let result = node.get_block(&block.block_hash()).await;
+        assert!(matches!(result, Err(AnyaError::NotFound(_))));
+    }
+}
@@ -0,0 +1,839 @@
+// [AIR-3][AIS-3][BPC-3] CoinJoin round coordination
+//
+// A round collects inputs and outputs from several participants during
+// registration, then builds one transaction spending all inputs to equal
+// denomination outputs plus at most one change output per participant.
+// Participants sign their own inputs with SIGHASH_ALL|ANYONECANPAY, so they
+// commit to every output without depending on the other inputs' signatures.
+// A participant who has not signed when the signing timeout passes is
+// removed, their inputs are banned from the round, and registration reopens
+// for the remaining participants.
+//
+// The coordinator only checks amounts and signatures; it does not verify
+// input ownership at registration or hide which participant registered which
+// outputs.
+
+use crate::bitcoin::mempool::policy::dust_threshold;
+use crate::bitcoin::wallet::signer::finalize_psbt;
+use crate::AnyaError;
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::Hash;
+use bitcoin::psbt::{Input, Psbt as PSBT, PsbtSighashType};
+use bitcoin::secp256k1::{Message, Secp256k1};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Amount, CompressedPublicKey, FeeRate, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut,
+    Weight, Witness, XOnlyPublicKey,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Weight of the version, locktime, segwit marker and input/output counts
+const TX_OVERHEAD_WEIGHT: Weight = Weight::from_wu(4 * (4 + 4 + 3 + 3) + 2);
+
+/// Weight of a P2WPKH input with a 72-byte signature
+const P2WPKH_INPUT_WEIGHT: Weight = Weight::from_wu(4 * (36 + 1 + 4) + 1 + 1 + 72 + 1 + 33);
+
+/// Weight of a P2TR key-path input with a 65-byte signature
+const P2TR_INPUT_WEIGHT: Weight = Weight::from_wu(4 * (36 + 1 + 4) + 1 + 1 + 65);
+
+/// CoinJoin errors
+#[derive(Debug, thiserror::Error)]
+pub enum CoinJoinError {
+    #[error("Round is not accepting this during {0:?}")]
+    WrongPhase(RoundPhase),
+    #[error("Invalid registration: {0}")]
+    InvalidRegistration(String),
+    #[error("Unknown participant {0}")]
+    UnknownParticipant(ParticipantId),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("Round needs {required} participants, has {registered}")]
+    NotEnoughParticipants { required: usize, registered: usize },
+    #[error("Transaction is unbalanced: {0}")]
+    Unbalanced(String),
+    #[error("Participants {0:?} have not signed")]
+    Incomplete(Vec<ParticipantId>),
+}
+
+impl From<CoinJoinError> for AnyaError {
+    fn from(err: CoinJoinError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+/// CoinJoin result type
+pub type CoinJoinResult<T> = Result<T, CoinJoinError>;
+
+/// Identifier handed to a participant at registration
+pub type ParticipantId = u32;
+
+/// Parameters of a CoinJoin round
+#[derive(Debug, Clone)]
+pub struct CoinJoinConfig {
+    /// Value of every mixed output
+    pub denomination: Amount,
+    /// Fee rate each participant pays for its own inputs and outputs
+    pub fee_rate: FeeRate,
+    /// Participants needed before signing can start
+    pub min_participants: usize,
+    /// Participants accepted before registration closes
+    pub max_participants: usize,
+    /// Time participants have to sign once signing starts
+    pub signing_timeout: Duration,
+}
+
+impl Default for CoinJoinConfig {
+    fn default() -> Self {
+        Self {
+            denomination: Amount::from_sat(100_000),
+            // 2 sat/vB
+            fee_rate: FeeRate::from_sat_per_kwu(500),
+            min_participants: 3,
+            max_participants: 50,
+            signing_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Input registered by a participant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoinJoinInput {
+    pub outpoint: OutPoint,
+    /// Output being spent; must be P2WPKH or P2TR
+    pub txout: TxOut,
+}
+
+/// Phase of a CoinJoin round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundPhase {
+    /// Accepting participants
+    Registration,
+    /// Transaction built, waiting for signatures
+    Signing,
+    /// Fully signed transaction extracted
+    Finalized,
+}
+
+#[derive(Debug, Clone)]
+struct Participant {
+    inputs: Vec<CoinJoinInput>,
+    outputs: Vec<TxOut>,
+    fee: Amount,
+    signed: bool,
+}
+
+/// [AIS-3] Coordinator state for one CoinJoin round
+#[derive(Debug)]
+pub struct CoinJoinRound {
+    config: CoinJoinConfig,
+    phase: RoundPhase,
+    participants: BTreeMap<ParticipantId, Participant>,
+    next_id: ParticipantId,
+    banned: HashSet<OutPoint>,
+    psbt: Option<PSBT>,
+    signing_deadline: Option<Instant>,
+}
+
+impl CoinJoinRound {
+    /// Open a round for registration
+    pub fn new(config: CoinJoinConfig) -> Self {
+        Self {
+            config,
+            phase: RoundPhase::Registration,
+            participants: BTreeMap::new(),
+            next_id: 0,
+            banned: HashSet::new(),
+            psbt: None,
+            signing_deadline: None,
+        }
+    }
+
+    pub fn config(&self) -> &CoinJoinConfig {
+        &self.config
+    }
+
+    pub fn phase(&self) -> RoundPhase {
+        self.phase
+    }
+
+    /// Registered participants, in registration order
+    pub fn participants(&self) -> Vec<ParticipantId> {
+        self.participants.keys().copied().collect()
+    }
+
+    /// Fee a participant registering `inputs` and `outputs` must leave unclaimed
+    ///
+    /// Covers the weight of its own inputs and outputs at the round's fee rate
+    /// plus a share of the transaction overhead, split over the minimum number
+    /// of participants so the overhead is paid whichever of them sign.
+    pub fn participant_fee(&self, inputs: &[CoinJoinInput], outputs: &[TxOut]) -> Amount {
+        let input_weight = inputs
+            .iter()
+            .map(|input| input_weight(&input.txout.script_pubkey).unwrap_or(P2WPKH_INPUT_WEIGHT))
+            .fold(Weight::ZERO, |total, weight| total + weight);
+        let output_weight = outputs
+            .iter()
+            .map(TxOut::weight)
+            .fold(Weight::ZERO, |total, weight| total + weight);
+        let participants = self.config.min_participants.max(1) as u64;
+        let overhead =
+            Weight::from_wu((TX_OVERHEAD_WEIGHT.to_wu() + participants - 1) / participants);
+        self.config
+            .fee_rate
+            .fee_wu(input_weight + output_weight + overhead)
+            .unwrap_or(Amount::MAX_MONEY)
+    }
+
+    /// Register a participant's inputs and outputs
+    ///
+    /// Outputs must pay exactly the denomination, except for at most one
+    /// change output. Inputs must cover the outputs plus
+    /// [`participant_fee`](Self::participant_fee) exactly.
+    pub fn register(
+        &mut self,
+        inputs: Vec<CoinJoinInput>,
+        outputs: Vec<TxOut>,
+    ) -> CoinJoinResult<ParticipantId> {
+        if self.phase != RoundPhase::Registration {
+            return Err(CoinJoinError::WrongPhase(self.phase));
+        }
+        if self.participants.len() >= self.config.max_participants {
+            return Err(CoinJoinError::InvalidRegistration(format!(
+                "round is full with {} participants",
+                self.participants.len()
+            )));
+        }
+        if inputs.is_empty() {
+            return Err(CoinJoinError::InvalidRegistration("no inputs".to_string()));
+        }
+
+        let registered_inputs: HashSet<OutPoint> = self
+            .participants
+            .values()
+            .flat_map(|participant| participant.inputs.iter().map(|input| input.outpoint))
+            .collect();
+        let mut outpoints = HashSet::new();
+        for input in &inputs {
+            if self.banned.contains(&input.outpoint) {
+                return Err(CoinJoinError::InvalidRegistration(format!(
+                    "input {} is banned from this round",
+                    input.outpoint
+                )));
+            }
+            if registered_inputs.contains(&input.outpoint) || !outpoints.insert(input.outpoint) {
+                return Err(CoinJoinError::InvalidRegistration(format!(
+                    "input {} is already registered",
+                    input.outpoint
+                )));
+            }
+            if input_weight(&input.txout.script_pubkey).is_none() {
+                return Err(CoinJoinError::InvalidRegistration(format!(
+                    "input {} is not P2WPKH or P2TR",
+                    input.outpoint
+                )));
+            }
+        }
+
+        let denominations = outputs
+            .iter()
+            .filter(|output| output.value == self.config.denomination)
+            .count();
+        if denominations == 0 {
+            return Err(CoinJoinError::InvalidRegistration(format!(
+                "no output of {}",
+                self.config.denomination
+            )));
+        }
+        if outputs.len() - denominations > 1 {
+            return Err(CoinJoinError::InvalidRegistration(
+                "more than one change output".to_string(),
+            ));
+        }
+        if outputs
+            .iter()
+            .any(|output| output.value < dust_threshold(&output.script_pubkey))
+        {
+            return Err(CoinJoinError::InvalidRegistration(
+                "change output is dust".to_string(),
+            ));
+        }
+        let registered_scripts: HashSet<&ScriptBuf> = self
+            .participants
+            .values()
+            .flat_map(|participant| {
+                participant
+                    .outputs
+                    .iter()
+                    .map(|output| &output.script_pubkey)
+            })
+            .collect();
+        let mut scripts = HashSet::new();
+        for output in &outputs {
+            if registered_scripts.contains(&output.script_pubkey)
+                || !scripts.insert(&output.script_pubkey)
+            {
+                return Err(CoinJoinError::InvalidRegistration(format!(
+                    "output script {} is reused",
+                    output.script_pubkey
+                )));
+            }
+        }
+
+        let fee = self.participant_fee(&inputs, &outputs);
+        let input_total = sum(inputs.iter().map(|input| input.txout.value))
+            .ok_or_else(|| CoinJoinError::InvalidRegistration("input overflow".to_string()))?;
+        let output_total = sum(outputs.iter().map(|output| output.value))
+            .ok_or_else(|| CoinJoinError::InvalidRegistration("output overflow".to_string()))?;
+        if output_total.checked_add(fee) != Some(input_total) {
+            return Err(CoinJoinError::InvalidRegistration(format!(
+                "inputs of {input_total} must pay outputs of {output_total} plus a fee of {fee}"
+            )));
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.participants.insert(
+            id,
+            Participant {
+                inputs,
+                outputs,
+                fee,
+                signed: false,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Close registration and build the transaction for participants to sign
+    ///
+    /// Inputs and outputs are sorted so their order reveals nothing about
+    /// registration. Every input carries its spent output and requests
+    /// SIGHASH_ALL|ANYONECANPAY. Signatures must be submitted before
+    /// `now + signing_timeout`.
+    pub fn start_signing(&mut self, now: Instant) -> CoinJoinResult<PSBT> {
+        if self.phase != RoundPhase::Registration {
+            return Err(CoinJoinError::WrongPhase(self.phase));
+        }
+        if self.participants.len() < self.config.min_participants {
+            return Err(CoinJoinError::NotEnoughParticipants {
+                required: self.config.min_participants,
+                registered: self.participants.len(),
+            });
+        }
+
+        let mut inputs: Vec<&CoinJoinInput> = self
+            .participants
+            .values()
+            .flat_map(|participant| &participant.inputs)
+            .collect();
+        inputs.sort_by_key(|input| input.outpoint);
+        let mut outputs: Vec<TxOut> = self
+            .participants
+            .values()
+            .flat_map(|participant| participant.outputs.iter().cloned())
+            .collect();
+        outputs.sort_by(|a, b| {
+            (a.value, a.script_pubkey.as_bytes()).cmp(&(b.value, b.script_pubkey.as_bytes()))
+        });
+
+        let unsigned_tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs
+                .iter()
+                .map(|input| TxIn {
+                    previous_output: input.outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: outputs,
+        };
+        let mut psbt =
+            PSBT::from_unsigned_tx(unsigned_tx).expect("transaction is built without signatures");
+        for (psbt_input, input) in psbt.inputs.iter_mut().zip(&inputs) {
+            psbt_input.witness_utxo = Some(input.txout.clone());
+            psbt_input.sighash_type =
+                Some(PsbtSighashType::from(EcdsaSighashType::AllPlusAnyoneCanPay));
+        }
+
+        self.phase = RoundPhase::Signing;
+        self.signing_deadline = Some(now + self.config.signing_timeout);
+        self.psbt = Some(psbt.clone());
+        Ok(psbt)
+    }
+
+    /// Accept a participant's signatures from their copy of the round PSBT
+    ///
+    /// Every input the participant registered must carry a valid
+    /// SIGHASH_ALL|ANYONECANPAY signature; anything else in `signed` is ignored.
+    pub fn submit_signatures(&mut self, id: ParticipantId, signed: &PSBT) -> CoinJoinResult<()> {
+        if self.phase != RoundPhase::Signing {
+            return Err(CoinJoinError::WrongPhase(self.phase));
+        }
+        let participant = self
+            .participants
+            .get(&id)
+            .ok_or(CoinJoinError::UnknownParticipant(id))?;
+        let psbt = self.psbt.as_mut().expect("signing phase has a transaction");
+        if signed.unsigned_tx != psbt.unsigned_tx || signed.inputs.len() != psbt.inputs.len() {
+            return Err(CoinJoinError::InvalidSignature(
+                "signed PSBT is not for this round's transaction".to_string(),
+            ));
+        }
+
+        let secp = Secp256k1::verification_only();
+        let mut cache = SighashCache::new(&psbt.unsigned_tx);
+        let mut verified = Vec::new();
+        for input in &participant.inputs {
+            let index = psbt
+                .unsigned_tx
+                .input
+                .iter()
+                .position(|txin| txin.previous_output == input.outpoint)
+                .expect("registered inputs are in the transaction");
+            let script = &input.txout.script_pubkey;
+            let signed_input = &signed.inputs[index];
+            let mut signature = Input::default();
+
+            if script.is_p2tr() {
+                let sig = signed_input.tap_key_sig.ok_or_else(|| {
+                    CoinJoinError::InvalidSignature(format!("input {index} is not signed"))
+                })?;
+                if sig.sighash_type != TapSighashType::AllPlusAnyoneCanPay {
+                    return Err(CoinJoinError::InvalidSignature(format!(
+                        "input {index} is signed with {}, not ALL|ANYONECANPAY",
+                        sig.sighash_type
+                    )));
+                }
+                let output_key = XOnlyPublicKey::from_slice(&script.as_bytes()[2..])
+                    .map_err(|e| CoinJoinError::InvalidSignature(e.to_string()))?;
+                let sighash = cache
+                    .taproot_key_spend_signature_hash(
+                        index,
+                        &Prevouts::One(index, &input.txout),
+                        sig.sighash_type,
+                    )
+                    .map_err(|e| CoinJoinError::InvalidSignature(e.to_string()))?;
+                let message = Message::from_digest(sighash.to_byte_array());
+                secp.verify_schnorr(&sig.signature, &message, &output_key)
+                    .map_err(|_| {
+                        CoinJoinError::InvalidSignature(format!(
+                            "input {index} signature is invalid"
+                        ))
+                    })?;
+                signature.tap_key_sig = Some(sig);
+            } else {
+                let (key, sig) = signed_input
+                    .partial_sigs
+                    .iter()
+                    .find(|(key, _)| {
+                        CompressedPublicKey::try_from(**key)
+                            .is_ok_and(|key| *script == ScriptBuf::new_p2wpkh(&key.wpubkey_hash()))
+                    })
+                    .ok_or_else(|| {
+                        CoinJoinError::InvalidSignature(format!("input {index} is not signed"))
+                    })?;
+                if sig.sighash_type != EcdsaSighashType::AllPlusAnyoneCanPay {
+                    return Err(CoinJoinError::InvalidSignature(format!(
+                        "input {index} is signed with {}, not ALL|ANYONECANPAY",
+                        sig.sighash_type
+                    )));
+                }
+                let sighash = cache
+                    .p2wpkh_signature_hash(index, script, input.txout.value, sig.sighash_type)
+                    .map_err(|e| CoinJoinError::InvalidSignature(e.to_string()))?;
+                let message = Message::from_digest(sighash.to_byte_array());
+                secp.verify_ecdsa(&message, &sig.signature, &key.inner)
+                    .map_err(|_| {
+                        CoinJoinError::InvalidSignature(format!(
+                            "input {index} signature is invalid"
+                        ))
+                    })?;
+                signature.partial_sigs.insert(*key, *sig);
+            }
+            verified.push((index, signature));
+        }
+
+        for (index, signature) in verified {
+            let input = &mut psbt.inputs[index];
+            input.partial_sigs = signature.partial_sigs;
+            input.tap_key_sig = signature.tap_key_sig;
+        }
+        if let Some(participant) = self.participants.get_mut(&id) {
+            participant.signed = true;
+        }
+        Ok(())
+    }
+
+    /// Remove participants who have not signed once the signing timeout passes
+    ///
+    /// Their inputs are banned from the round and registration reopens for the
+    /// remaining participants, whose signatures are discarded since the
+    /// transaction will change. Returns the removed participants; before the
+    /// deadline, or outside the signing phase, nothing happens.
+    pub fn expire(&mut self, now: Instant) -> Vec<ParticipantId> {
+        if self.phase != RoundPhase::Signing
+            || self
+                .signing_deadline
+                .map_or(true, |deadline| now < deadline)
+        {
+            return Vec::new();
+        }
+        let removed: Vec<ParticipantId> = self
+            .participants
+            .iter()
+            .filter(|(_, participant)| !participant.signed)
+            .map(|(id, _)| *id)
+            .collect();
+        if removed.is_empty() {
+            return removed;
+        }
+
+        for id in &removed {
+            if let Some(participant) = self.participants.remove(id) {
+                self.banned
+                    .extend(participant.inputs.iter().map(|input| input.outpoint));
+            }
+        }
+        for participant in self.participants.values_mut() {
+            participant.signed = false;
+        }
+        self.phase = RoundPhase::Registration;
+        self.psbt = None;
+        self.signing_deadline = None;
+        removed
+    }
+
+    /// Check the fully signed transaction is balanced and extract it
+    pub fn finalize(&mut self) -> CoinJoinResult<Transaction> {
+        if self.phase != RoundPhase::Signing {
+            return Err(CoinJoinError::WrongPhase(self.phase));
+        }
+        let unsigned: Vec<ParticipantId> = self
+            .participants
+            .iter()
+            .filter(|(_, participant)| !participant.signed)
+            .map(|(id, _)| *id)
+            .collect();
+        if !unsigned.is_empty() {
+            return Err(CoinJoinError::Incomplete(unsigned));
+        }
+
+        let mut psbt = self.psbt.clone().expect("signing phase has a transaction");
+        let input_total = sum(psbt
+            .inputs
+            .iter()
+            .filter_map(|input| input.witness_utxo.as_ref().map(|txout| txout.value)));
+        let output_total = sum(psbt.unsigned_tx.output.iter().map(|output| output.value));
+        let fee_total = sum(self
+            .participants
+            .values()
+            .map(|participant| participant.fee));
+        match (input_total, output_total, fee_total) {
+            (Some(inputs), Some(outputs), Some(fees))
+                if outputs.checked_add(fees) == Some(inputs) => {}
+            _ => {
+                return Err(CoinJoinError::Unbalanced(format!(
+                    "inputs {input_total:?}, outputs {output_total:?}, fees {fee_total:?}"
+                )))
+            }
+        }
+
+        let finalized = finalize_psbt(&mut psbt);
+        if finalized != psbt.inputs.len() {
+            return Err(CoinJoinError::InvalidSignature(format!(
+                "only {finalized} of {} inputs could be finalized",
+                psbt.inputs.len()
+            )));
+        }
+        let tx = psbt.extract_tx_unchecked_fee_rate();
+        self.phase = RoundPhase::Finalized;
+        Ok(tx)
+    }
+}
+
+/// Estimated weight of an input spending `script`, if it can be registered
+fn input_weight(script: &ScriptBuf) -> Option<Weight> {
+    if script.is_p2wpkh() {
+        Some(P2WPKH_INPUT_WEIGHT)
+    } else if script.is_p2tr() {
+        Some(P2TR_INPUT_WEIGHT)
+    } else {
+        None
+    }
+}
+
+fn sum(mut amounts: impl Iterator<Item = Amount>) -> Option<Amount> {
+    amounts.try_fold(Amount::ZERO, Amount::checked_add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::signer::{sign_psbt, SighashType};
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::{PublicKey, Txid};
+
+    fn key(seed: u8) -> SecretKey {
+        SecretKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn p2wpkh(secret_key: &SecretKey) -> ScriptBuf {
+        let secp = Secp256k1::new();
+        ScriptBuf::new_p2wpkh(&CompressedPublicKey(secret_key.public_key(&secp)).wpubkey_hash())
+    }
+
+    fn p2tr(secret_key: &SecretKey) -> ScriptBuf {
+        let secp = Secp256k1::new();
+        let (internal_key, _) = secret_key.x_only_public_key(&secp);
+        ScriptBuf::new_p2tr(&secp, internal_key, None)
+    }
+
+    fn input(n: u8, script_pubkey: ScriptBuf, sats: u64) -> CoinJoinInput {
+        CoinJoinInput {
+            outpoint: OutPoint::new(Txid::from_byte_array([n; 32]), 0),
+            txout: TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey,
+            },
+        }
+    }
+
+    fn round(min_participants: usize) -> CoinJoinRound {
+        CoinJoinRound::new(CoinJoinConfig {
+            min_participants,
+            ..Default::default()
+        })
+    }
+
+    /// Register one mixed output plus change for each input, returning the participant
+    fn join(round: &mut CoinJoinRound, inputs: Vec<CoinJoinInput>, seed: u8) -> ParticipantId {
+        let mut outputs = vec![
+            TxOut {
+                value: round.config().denomination,
+                script_pubkey: p2wpkh(&key(seed)),
+            },
+            TxOut {
+                value: Amount::ZERO,
+                script_pubkey: p2wpkh(&key(seed + 100)),
+            },
+        ];
+        let fee = round.participant_fee(&inputs, &outputs);
+        let input_total = sum(inputs.iter().map(|input| input.txout.value)).unwrap();
+        outputs[1].value = input_total - round.config().denomination - fee;
+        round.register(inputs, outputs).unwrap()
+    }
+
+    fn sign(psbt: &PSBT, secret_key: &SecretKey) -> PSBT {
+        let mut psbt = psbt.clone();
+        let secp = Secp256k1::new();
+        sign_psbt(
+            &secp,
+            &mut psbt,
+            secret_key,
+            SighashType::AllPlusAnyoneCanPay,
+        )
+        .unwrap();
+        psbt
+    }
+
+    #[test]
+    fn test_three_participants_sign_independently() {
+        let mut round = round(3);
+        let (alice, bob, carol) = (key(1), key(2), key(3));
+        let a = join(&mut round, vec![input(1, p2wpkh(&alice), 150_000)], 11);
+        let b = join(&mut round, vec![input(2, p2tr(&bob), 180_000)], 12);
+        let c = join(
+            &mut round,
+            vec![
+                input(3, p2wpkh(&carol), 70_000),
+                input(4, p2wpkh(&carol), 90_000),
+            ],
+            13,
+        );
+        assert_eq!(round.participants(), vec![a, b, c]);
+
+        let psbt = round.start_signing(Instant::now()).unwrap();
+        assert_eq!(psbt.unsigned_tx.input.len(), 4);
+        let denomination = round.config().denomination;
+        let mixed = psbt
+            .unsigned_tx
+            .output
+            .iter()
+            .filter(|output| output.value == denomination)
+            .count();
+        assert_eq!(mixed, 3);
+        assert!(round
+            .register(vec![input(5, p2wpkh(&key(4)), 200_000)], Vec::new())
+            .is_err());
+
+        // Each participant signs a copy without seeing the others' signatures
+        let signed: Vec<PSBT> = [&alice, &bob, &carol]
+            .iter()
+            .map(|secret_key| sign(&psbt, secret_key))
+            .collect();
+        assert!(matches!(
+            round.finalize(),
+            Err(CoinJoinError::Incomplete(_))
+        ));
+        for (id, signed) in [a, b, c].into_iter().zip(&signed).rev() {
+            round.submit_signatures(id, signed).unwrap();
+        }
+
+        let tx = round.finalize().unwrap();
+        assert_eq!(round.phase(), RoundPhase::Finalized);
+        assert_eq!(tx.compute_txid(), psbt.unsigned_tx.compute_txid());
+        assert!(tx.input.iter().all(|txin| !txin.witness.is_empty()));
+        let fee = round.participant_fee(&[input(0, p2wpkh(&alice), 0)], &tx.output[..2])
+            + round.participant_fee(&[input(0, p2tr(&bob), 0)], &tx.output[..2])
+            + round.participant_fee(
+                &[input(0, p2wpkh(&carol), 0), input(0, p2wpkh(&carol), 0)],
+                &tx.output[..2],
+            );
+        assert_eq!(psbt.fee().unwrap(), fee);
+        assert!(psbt.fee().unwrap() >= round.config().fee_rate.fee_wu(tx.weight()).unwrap());
+    }
+
+    #[test]
+    fn test_participant_who_does_not_sign_is_removed() {
+        let mut round = round(2);
+        let (alice, bob, carol) = (key(1), key(2), key(3));
+        let a = join(&mut round, vec![input(1, p2wpkh(&alice), 150_000)], 11);
+        let b = join(&mut round, vec![input(2, p2tr(&bob), 180_000)], 12);
+        let c = join(&mut round, vec![input(3, p2wpkh(&carol), 160_000)], 13);
+
+        let start = Instant::now();
+        let psbt = round.start_signing(start).unwrap();
+        round.submit_signatures(a, &sign(&psbt, &alice)).unwrap();
+        round.submit_signatures(b, &sign(&psbt, &bob)).unwrap();
+        assert!(round.expire(start + Duration::from_secs(1)).is_empty());
+
+        let timeout = round.config().signing_timeout;
+        assert_eq!(round.expire(start + timeout), vec![c]);
+        assert_eq!(round.phase(), RoundPhase::Registration);
+        assert_eq!(round.participants(), vec![a, b]);
+        let banned = round.register(vec![input(3, p2wpkh(&carol), 160_000)], Vec::new());
+        assert!(matches!(banned, Err(CoinJoinError::InvalidRegistration(_))));
+
+        // Signatures over the old transaction are discarded
+        let psbt = round.start_signing(start + timeout).unwrap();
+        assert_eq!(psbt.unsigned_tx.input.len(), 2);
+        assert!(matches!(
+            round.finalize(),
+            Err(CoinJoinError::Incomplete(_))
+        ));
+        round.submit_signatures(a, &sign(&psbt, &alice)).unwrap();
+        round.submit_signatures(b, &sign(&psbt, &bob)).unwrap();
+        assert_eq!(round.finalize().unwrap().input.len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_unbalanced_registrations() {
+        let mut round = round(2);
+        let inputs = vec![input(1, p2wpkh(&key(1)), 150_000)];
+        let denomination = round.config().denomination;
+        let output = |seed: u8, value: Amount| TxOut {
+            value,
+            script_pubkey: p2wpkh(&key(seed)),
+        };
+
+        // Change claims the whole fee
+        let outputs = vec![
+            output(11, denomination),
+            output(12, Amount::from_sat(50_000)),
+        ];
+        assert!(round.register(inputs.clone(), outputs).is_err());
+
+        // No denomination output, two change outputs, and a reused script
+        let change = Amount::from_sat(49_000);
+        for outputs in [
+            vec![output(12, change)],
+            vec![
+                output(11, denomination),
+                output(12, change),
+                output(13, change),
+            ],
+            vec![output(11, denomination), output(11, change)],
+        ] {
+            assert!(round.register(inputs.clone(), outputs).is_err());
+        }
+
+        // Legacy inputs cannot be weighed or signed with the round's sighash
+        let secp = Secp256k1::new();
+        let legacy = ScriptBuf::new_p2pkh(&PublicKey::new(key(1).public_key(&secp)).pubkey_hash());
+        let outputs = vec![output(11, denomination), output(12, change)];
+        assert!(round
+            .register(vec![input(2, legacy, 150_000)], outputs)
+            .is_err());
+
+        let id = join(&mut round, inputs.clone(), 11);
+        assert_eq!(round.participants(), vec![id]);
+        assert!(round.register(inputs, Vec::new()).is_err());
+        assert!(matches!(
+            round.start_signing(Instant::now()),
+            Err(CoinJoinError::NotEnoughParticipants { .. })
+        ));
+    }
+
+    #[test]
+    fn test_change_dust_depends_on_script() {
+        let mut round = round(1);
+        let denomination = round.config().denomination;
+        let mut outputs = vec![
+            TxOut {
+                value: denomination,
+                script_pubkey: p2wpkh(&key(11)),
+            },
+            TxOut {
+                value: Amount::ZERO,
+                script_pubkey: p2wpkh(&key(12)),
+            },
+        ];
+        let inputs = vec![input(1, p2wpkh(&key(1)), 0)];
+        let fee = round.participant_fee(&inputs, &outputs);
+
+        // 294 sats is the P2WPKH dust threshold, below the legacy 546
+        for (change, accepted) in [(293, false), (294, true)] {
+            outputs[1].value = Amount::from_sat(change);
+            let value = (denomination + fee + outputs[1].value).to_sat();
+            let inputs = vec![input(1, p2wpkh(&key(1)), value)];
+            assert_eq!(round.register(inputs, outputs.clone()).is_ok(), accepted);
+        }
+    }
+
+    #[test]
+    fn test_rejects_signatures_without_anyone_can_pay() {
+        let mut round = round(2);
+        let (alice, bob) = (key(1), key(2));
+        let a = join(&mut round, vec![input(1, p2wpkh(&alice), 150_000)], 11);
+        let b = join(&mut round, vec![input(2, p2tr(&bob), 180_000)], 12);
+        let psbt = round.start_signing(Instant::now()).unwrap();
+
+        let secp = Secp256k1::new();
+        for (id, secret_key) in [(a, &alice), (b, &bob)] {
+            let mut signed = psbt.clone();
+            for input in &mut signed.inputs {
+                input.sighash_type = None;
+            }
+            sign_psbt(&secp, &mut signed, secret_key, SighashType::All).unwrap();
+            assert!(matches!(
+                round.submit_signatures(id, &signed),
+                Err(CoinJoinError::InvalidSignature(_))
+            ));
+        }
+
+        // Someone else's signature does not count for Alice's input
+        assert!(round.submit_signatures(a, &sign(&psbt, &bob)).is_err());
+        assert!(round.submit_signatures(a, &psbt).is_err());
+        assert!(matches!(
+            round.submit_signatures(7, &sign(&psbt, &alice)),
+            Err(CoinJoinError::UnknownParticipant(7))
+        ));
+        round.submit_signatures(a, &sign(&psbt, &alice)).unwrap();
+        assert!(matches!(round.finalize(), Err(CoinJoinError::Incomplete(ids)) if ids == vec![b]));
+    }
+}
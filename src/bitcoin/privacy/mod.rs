@@ -0,0 +1,496 @@
+// [AIR-3][AIS-3][BPC-3] Transaction privacy analysis
+//
+// Tags transactions by origin and scores them against common chain-analysis
+// heuristics (address reuse, round amounts, change detection) so wallets can
+// see how much a transaction leaks before or after it is broadcast.
+// `coinjoin` coordinates collaborative transactions that break those
+// heuristics.
+
+// Scoring helpers are only reachable from `analyze_transaction` in debug builds
+#![cfg_attr(not(debug_assertions), allow(dead_code))]
+
+pub mod coinjoin;
+
+pub use coinjoin::{CoinJoinConfig, CoinJoinError, CoinJoinInput, CoinJoinRound};
+
+use bitcoin::{OutPoint, ScriptBuf, Transaction, TxIn, TxOut, Txid};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Amounts that are a multiple of this many satoshis are considered "round"
+const ROUND_AMOUNT_SATS: u64 = 100_000;
+
+/// Sub-score weights used to compute the overall privacy score
+const ADDRESS_REUSE_WEIGHT: f64 = 0.4;
+const ROUND_AMOUNT_WEIGHT: f64 = 0.3;
+const CHANGE_DETECTION_WEIGHT: f64 = 0.3;
+
+/// Evidence weights used by the change-output heuristics
+const OWNED_OUTPUT_EVIDENCE: f64 = 0.4;
+const SINGLE_OWNED_EVIDENCE: f64 = 0.3;
+const INPUT_TYPE_EVIDENCE: f64 = 0.15;
+const NON_ROUND_EVIDENCE: f64 = 0.15;
+
+/// Where a transaction was first seen by this node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionOrigin {
+    /// Created and broadcast by the local wallet
+    Local,
+    /// Received from the network
+    Relayed,
+}
+
+/// Wallet-side knowledge used when analysing a transaction
+#[derive(Debug, Clone, Default)]
+pub struct WalletContext {
+    /// Scripts controlled by the wallet
+    pub owned_scripts: HashSet<ScriptBuf>,
+    /// Scripts that have already received funds on-chain
+    pub used_scripts: HashSet<ScriptBuf>,
+    /// Previous outputs spent by the transactions being analysed
+    pub prevouts: HashMap<OutPoint, TxOut>,
+    /// Transactions originated by the local node
+    local_txids: HashSet<Txid>,
+}
+
+impl WalletContext {
+    /// Create an empty wallet context
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tag a transaction as locally originated
+    pub fn tag_local(&mut self, txid: Txid) {
+        self.local_txids.insert(txid);
+    }
+
+    /// Origin of a transaction as far as this node knows
+    pub fn origin(&self, txid: &Txid) -> TransactionOrigin {
+        if self.local_txids.contains(txid) {
+            TransactionOrigin::Local
+        } else {
+            TransactionOrigin::Relayed
+        }
+    }
+}
+
+/// Privacy score for a single transaction
+///
+/// Sub-scores range from 0.0 (leaks everything) to 1.0 (no leak detected);
+/// `overall` is their weighted sum scaled to 0-100.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyScore {
+    /// Origin tag of the analysed transaction
+    pub origin: TransactionOrigin,
+    /// Weighted overall score (0-100)
+    pub overall: f64,
+    /// Outputs paying to previously used or input scripts
+    pub address_reuse: f64,
+    /// Outputs with suspiciously round amounts
+    pub round_amounts: f64,
+    /// How easily the change output can be identified
+    pub change_detection: f64,
+    /// Suggestions for improving privacy
+    pub suggestions: Vec<String>,
+}
+
+/// Analyse a transaction against common privacy heuristics
+///
+/// Only available in debug builds: scores are a development aid and are not
+/// meant to be exposed by production nodes.
+#[cfg(debug_assertions)]
+pub fn analyze_transaction(tx: &Transaction, wallet_context: &WalletContext) -> PrivacyScore {
+    let mut suggestions = Vec::new();
+
+    let address_reuse = score_address_reuse(tx, wallet_context);
+    if address_reuse < 1.0 {
+        suggestions.push("Use a fresh address for every output".to_string());
+    }
+
+    let round_amounts = score_round_amounts(tx);
+    if round_amounts < 1.0 {
+        suggestions.push("Avoid round payment amounts that reveal the change output".to_string());
+    }
+
+    let change_detection = score_change_detection(tx, wallet_context);
+    if change_detection < 1.0 {
+        suggestions.push("Match the change output type to the inputs being spent".to_string());
+    }
+
+    let overall = 100.0
+        * (address_reuse * ADDRESS_REUSE_WEIGHT
+            + round_amounts * ROUND_AMOUNT_WEIGHT
+            + change_detection * CHANGE_DETECTION_WEIGHT);
+
+    PrivacyScore {
+        origin: wallet_context.origin(&tx.compute_txid()),
+        overall,
+        address_reuse,
+        round_amounts,
+        change_detection,
+        suggestions,
+    }
+}
+
+/// Likely change output of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEstimate {
+    /// Index of the change output
+    pub index: usize,
+    /// Confidence in the estimate (0.0-1.0)
+    pub confidence: f64,
+}
+
+/// Index of the likely change output of one of the wallet's own transactions
+///
+/// See [`estimate_change`] for the heuristics applied.
+pub fn detect_change(tx: &Transaction, owned_scripts: &HashSet<ScriptBuf>) -> Option<usize> {
+    estimate_change(tx, owned_scripts).map(|estimate| estimate.index)
+}
+
+/// Estimate the change output of one of the wallet's own transactions
+///
+/// Only outputs paying to `owned_scripts` are candidates. Each candidate
+/// gathers evidence from being the single owned output, matching the script
+/// type spent by the inputs and carrying a non-round amount. Returns `None`
+/// when there is nothing to pay change from, no owned output, or the best
+/// candidates cannot be told apart.
+pub fn estimate_change(
+    tx: &Transaction,
+    owned_scripts: &HashSet<ScriptBuf>,
+) -> Option<ChangeEstimate> {
+    // A single output is a sweep or consolidation, not a payment with change
+    if tx.output.len() < 2 {
+        return None;
+    }
+
+    let owned: Vec<usize> = tx
+        .output
+        .iter()
+        .enumerate()
+        .filter(|(_, out)| owned_scripts.contains(&out.script_pubkey))
+        .map(|(index, _)| index)
+        .collect();
+
+    let input_types: HashSet<ScriptKind> =
+        tx.input.iter().filter_map(ScriptKind::of_input).collect();
+
+    let mut scored: Vec<ChangeEstimate> = owned
+        .iter()
+        .map(|&index| {
+            let out = &tx.output[index];
+            let mut confidence = OWNED_OUTPUT_EVIDENCE;
+            if owned.len() == 1 {
+                confidence += SINGLE_OWNED_EVIDENCE;
+            }
+            if input_types.contains(&ScriptKind::of(&out.script_pubkey)) {
+                confidence += INPUT_TYPE_EVIDENCE;
+            }
+            if !is_round_amount(out.value.to_sat()) {
+                confidence += NON_ROUND_EVIDENCE;
+            }
+            ChangeEstimate {
+                index,
+                confidence: confidence.min(1.0),
+            }
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    match scored.as_slice() {
+        [] => None,
+        [best, runner_up, ..] if best.confidence == runner_up.confidence => None,
+        [best, ..] => Some(*best),
+    }
+}
+
+/// Fraction of outputs that do not reuse an address
+fn score_address_reuse(tx: &Transaction, ctx: &WalletContext) -> f64 {
+    if tx.output.is_empty() {
+        return 1.0;
+    }
+
+    let input_scripts: HashSet<&ScriptBuf> = input_prevouts(tx, ctx)
+        .map(|prevout| &prevout.script_pubkey)
+        .collect();
+
+    let reused = tx
+        .output
+        .iter()
+        .filter(|out| {
+            ctx.used_scripts.contains(&out.script_pubkey)
+                || input_scripts.contains(&out.script_pubkey)
+        })
+        .count();
+
+    1.0 - reused as f64 / tx.output.len() as f64
+}
+
+/// Penalise transactions where round amounts distinguish payment from change
+fn score_round_amounts(tx: &Transaction) -> f64 {
+    if tx.output.len() < 2 {
+        return 1.0;
+    }
+
+    let round = tx
+        .output
+        .iter()
+        .filter(|out| is_round_amount(out.value.to_sat()))
+        .count();
+
+    // All-round or no-round outputs are indistinguishable from each other
+    if round == 0 || round == tx.output.len() {
+        1.0
+    } else {
+        1.0 - round as f64 / tx.output.len() as f64
+    }
+}
+
+/// Penalise transactions with exactly one output matching the input script type
+fn score_change_detection(tx: &Transaction, ctx: &WalletContext) -> f64 {
+    if tx.output.len() < 2 {
+        return 1.0;
+    }
+
+    let input_types: HashSet<ScriptKind> = input_prevouts(tx, ctx)
+        .map(|prevout| ScriptKind::of(&prevout.script_pubkey))
+        .collect();
+    if input_types.is_empty() {
+        return 1.0;
+    }
+
+    let matching = tx
+        .output
+        .iter()
+        .filter(|out| input_types.contains(&ScriptKind::of(&out.script_pubkey)))
+        .count();
+
+    if matching == 1 {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+pub(crate) fn is_round_amount(sats: u64) -> bool {
+    sats > 0 && sats % ROUND_AMOUNT_SATS == 0
+}
+
+pub(crate) fn input_prevouts<'a>(
+    tx: &'a Transaction,
+    ctx: &'a WalletContext,
+) -> impl Iterator<Item = &'a TxOut> + 'a {
+    tx.input
+        .iter()
+        .filter_map(|input| ctx.prevouts.get(&input.previous_output))
+}
+
+/// Coarse output script classification used by the heuristics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ScriptKind {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    Other,
+}
+
+impl ScriptKind {
+    pub(crate) fn of(script: &ScriptBuf) -> Self {
+        if script.is_p2pkh() {
+            ScriptKind::P2pkh
+        } else if script.is_p2sh() {
+            ScriptKind::P2sh
+        } else if script.is_p2wpkh() {
+            ScriptKind::P2wpkh
+        } else if script.is_p2wsh() {
+            ScriptKind::P2wsh
+        } else if script.is_p2tr() {
+            ScriptKind::P2tr
+        } else {
+            ScriptKind::Other
+        }
+    }
+
+    /// Infer the spent script type from an input's scriptSig and witness
+    pub(crate) fn of_input(input: &TxIn) -> Option<Self> {
+        let witness_len = input.witness.len();
+        match (input.script_sig.is_empty(), witness_len) {
+            (true, 1) if matches!(input.witness.nth(0).map(<[u8]>::len), Some(64 | 65)) => {
+                Some(ScriptKind::P2tr)
+            }
+            (true, 2) if input.witness.nth(1).map(<[u8]>::len) == Some(33) => {
+                Some(ScriptKind::P2wpkh)
+            }
+            (true, n) if n > 0 => Some(ScriptKind::P2wsh),
+            (false, 0) if input.script_sig.is_push_only() => {
+                // <sig> <pubkey> spends P2PKH; anything else pushes a redeem script
+                let pushes = input.script_sig.instructions().count();
+                if pushes == 2 {
+                    Some(ScriptKind::P2pkh)
+                } else {
+                    Some(ScriptKind::P2sh)
+                }
+            }
+            (false, n) if n > 0 => Some(ScriptKind::P2sh),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::Hash;
+    use bitcoin::key::{TweakedPublicKey, XOnlyPublicKey};
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, PubkeyHash, Sequence, TxIn, WPubkeyHash, Witness};
+
+    fn p2wpkh(seed: u8) -> ScriptBuf {
+        ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array([seed; 20]))
+    }
+
+    fn p2pkh(seed: u8) -> ScriptBuf {
+        ScriptBuf::new_p2pkh(&PubkeyHash::from_byte_array([seed; 20]))
+    }
+
+    fn p2tr(seed: u8) -> ScriptBuf {
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let sk = bitcoin::secp256k1::SecretKey::from_slice(&[seed; 32]).unwrap();
+        let (xonly, _) =
+            XOnlyPublicKey::from_keypair(&bitcoin::secp256k1::Keypair::from_secret_key(&secp, &sk));
+        ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(xonly))
+    }
+
+    fn spend(prevout: OutPoint, outputs: Vec<(ScriptBuf, u64)>) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: prevout,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            }],
+            output: outputs
+                .into_iter()
+                .map(|(script_pubkey, sats)| TxOut {
+                    value: Amount::from_sat(sats),
+                    script_pubkey,
+                })
+                .collect(),
+        }
+    }
+
+    fn context_with_input(prevout: OutPoint, script: ScriptBuf) -> WalletContext {
+        let mut ctx = WalletContext::new();
+        ctx.used_scripts.insert(script.clone());
+        ctx.prevouts.insert(
+            prevout,
+            TxOut {
+                value: Amount::from_sat(1_000_000),
+                script_pubkey: script,
+            },
+        );
+        ctx
+    }
+
+    #[test]
+    fn test_privacy_poor_transaction_scores_lower() {
+        let prevout = OutPoint::new(Txid::all_zeros(), 0);
+        let input_script = p2wpkh(1);
+        let ctx = context_with_input(prevout, input_script.clone());
+
+        // Round payment to a legacy address, change back to the input address
+        let poor = spend(prevout, vec![(p2pkh(2), 500_000), (input_script, 487_321)]);
+        // Non-round payment, fresh change address of the same type as the payee
+        let good = spend(prevout, vec![(p2wpkh(3), 512_347), (p2wpkh(4), 474_974)]);
+
+        let poor_score = analyze_transaction(&poor, &ctx);
+        let good_score = analyze_transaction(&good, &ctx);
+
+        assert!(poor_score.overall < good_score.overall);
+        assert!(poor_score.address_reuse < 1.0);
+        assert!(poor_score.round_amounts < 1.0);
+        assert!(!poor_score.suggestions.is_empty());
+        assert_eq!(good_score.overall, 100.0);
+        assert!(good_score.suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_change_detection_by_script_type() {
+        let prevout = OutPoint::new(Txid::all_zeros(), 1);
+        let ctx = context_with_input(prevout, p2wpkh(1));
+
+        let tx = spend(prevout, vec![(p2tr(5), 300_001), (p2wpkh(6), 200_002)]);
+        let score = analyze_transaction(&tx, &ctx);
+        assert_eq!(score.change_detection, 0.0);
+    }
+
+    #[test]
+    fn test_origin_tagging() {
+        let prevout = OutPoint::new(Txid::all_zeros(), 2);
+        let mut ctx = context_with_input(prevout, p2wpkh(1));
+        let tx = spend(prevout, vec![(p2wpkh(7), 10_000)]);
+
+        assert_eq!(
+            analyze_transaction(&tx, &ctx).origin,
+            TransactionOrigin::Relayed
+        );
+        ctx.tag_local(tx.compute_txid());
+        assert_eq!(
+            analyze_transaction(&tx, &ctx).origin,
+            TransactionOrigin::Local
+        );
+    }
+
+    fn spend_from_p2wpkh(outputs: Vec<(ScriptBuf, u64)>) -> Transaction {
+        let mut tx = spend(OutPoint::new(Txid::all_zeros(), 3), outputs);
+        tx.input[0].witness = Witness::from_slice(&[vec![0x30; 71], vec![0x02; 33]]);
+        tx
+    }
+
+    #[test]
+    fn test_detect_change_unambiguous() {
+        let owned: HashSet<ScriptBuf> = [p2wpkh(6)].into_iter().collect();
+        let tx = spend_from_p2wpkh(vec![(p2tr(5), 500_000), (p2wpkh(6), 487_321)]);
+
+        assert_eq!(detect_change(&tx, &owned), Some(1));
+        let estimate = estimate_change(&tx, &owned).unwrap();
+        assert_eq!(estimate.confidence, 1.0);
+    }
+
+    #[test]
+    fn test_detect_change_ambiguous_owned_outputs() {
+        let owned: HashSet<ScriptBuf> = [p2wpkh(6), p2wpkh(7)].into_iter().collect();
+        let tx = spend_from_p2wpkh(vec![
+            (p2tr(5), 300_000),
+            (p2wpkh(6), 123_457),
+            (p2wpkh(7), 234_567),
+        ]);
+        assert_eq!(detect_change(&tx, &owned), None);
+
+        // A round amount on one of them tips the balance towards the other
+        let tx = spend_from_p2wpkh(vec![
+            (p2tr(5), 300_001),
+            (p2wpkh(6), 100_000),
+            (p2wpkh(7), 234_567),
+        ]);
+        let estimate = estimate_change(&tx, &owned).unwrap();
+        assert_eq!(estimate.index, 2);
+        assert!(estimate.confidence < 1.0);
+    }
+
+    #[test]
+    fn test_detect_change_none() {
+        let owned: HashSet<ScriptBuf> = [p2wpkh(6)].into_iter().collect();
+
+        let payment_only = spend_from_p2wpkh(vec![(p2tr(5), 300_000), (p2pkh(8), 200_000)]);
+        assert_eq!(detect_change(&payment_only, &owned), None);
+
+        let sweep = spend_from_p2wpkh(vec![(p2wpkh(6), 999_000)]);
+        assert_eq!(detect_change(&sweep, &owned), None);
+    }
+}
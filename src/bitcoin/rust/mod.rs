@@ -5,8 +5,9 @@ use crate::bitcoin::error::{BitcoinError, BitcoinResult};
 use crate::bitcoin::interface::{
     AddressType, BitcoinImplementationType, BitcoinInterface, BlockHeader,
 };
+use crate::security::rng::secure_rng;
 use async_trait::async_trait;
-use bitcoin::secp256k1::{self, XOnlyPublicKey as SecpXOnlyPublicKey};
+use bitcoin::secp256k1::XOnlyPublicKey as SecpXOnlyPublicKey;
 use bitcoin::{
     absolute::LockTime, secp256k1::Secp256k1, Address as BitcoinAddress, Block as BitcoinBlock,
     CompressedPublicKey, FeeRate, Network, PrivateKey, PubkeyHash, ScriptBuf,
@@ -54,9 +55,7 @@ impl LocalWallet {
         &mut self,
         address_type: AddressType,
     ) -> Result<(String, BitcoinAddress), BitcoinError> {
-        let (secret_key, public_key) = self
-            .secp
-            .generate_keypair(&mut secp256k1::rand::thread_rng());
+        let (secret_key, public_key) = self.secp.generate_keypair(&mut secure_rng());
         let bitcoin_pubkey = bitcoin::PublicKey::new(public_key);
         let key_id = format!("key_{bitcoin_pubkey}");
         let network = self.network();
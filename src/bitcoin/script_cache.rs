@@ -0,0 +1,249 @@
+// [AIR-3][AIS-3][BPC-3][RES-3] Cache of successful script verifications
+//
+// Transactions are usually verified once on relay and again when a block
+// containing them is connected, and again after a reorg. Successful input
+// verifications are remembered so the interpreter only runs once per input.
+//
+// An entry is a salted hash of everything the result depends on: the wtxid,
+// which commits to the signatures and so to their sighash types, the input
+// index, the verification flags, and the script and amount of the spent
+// output. A transaction with the same txid but a different witness, or the
+// same input checked against a different UTXO, therefore never hits the
+// entry of another. The per-cache random salt keeps peers from crafting
+// colliding keys. Failures are never cached.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::{Transaction, TxOut};
+use lru::LruCache;
+use rand::RngCore;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Default number of cached verifications; each entry takes under 100 bytes
+pub const DEFAULT_SCRIPT_CACHE_ENTRIES: usize = 100_000;
+
+/// [AIR-3] Identifies one input verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScriptCacheKey([u8; 32]);
+
+/// [AIR-3] Cache counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScriptCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+impl ScriptCacheStats {
+    /// Share of lookups answered from the cache, from 0 to 1
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// [AIR-3][AIS-3][BPC-3] Size-bounded LRU cache of successful input verifications
+pub struct ScriptCache {
+    salt: [u8; 32],
+    entries: Mutex<LruCache<ScriptCacheKey, ()>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for ScriptCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_SCRIPT_CACHE_ENTRIES)
+    }
+}
+
+impl ScriptCache {
+    /// Cache holding at most `max_entries` verifications, at least one
+    pub fn new(max_entries: usize) -> Self {
+        let mut salt = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self {
+            salt,
+            entries: Mutex::new(LruCache::new(
+                NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN),
+            )),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Key for verifying input `vin` of `tx`, spending `spent`, under `flags`
+    pub fn key(&self, tx: &Transaction, vin: usize, spent: &TxOut, flags: u32) -> ScriptCacheKey {
+        let mut engine = sha256::Hash::engine();
+        engine.input(&self.salt);
+        engine.input(tx.compute_wtxid().as_byte_array());
+        engine.input(&(vin as u32).to_le_bytes());
+        engine.input(&flags.to_le_bytes());
+        engine.input(&spent.value.to_sat().to_le_bytes());
+        engine.input(&(spent.script_pubkey.len() as u32).to_le_bytes());
+        engine.input(spent.script_pubkey.as_bytes());
+        ScriptCacheKey(sha256::Hash::from_engine(engine).to_byte_array())
+    }
+
+    /// Whether the verification behind `key` succeeded before
+    pub fn contains(&self, key: &ScriptCacheKey) -> bool {
+        let found = self.lock().get(key).is_some();
+        let counter = if found { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    /// Record a successful verification, evicting the least recently used entry if full
+    pub fn insert(&self, key: ScriptCacheKey) {
+        self.lock().put(key, ());
+    }
+
+    /// Run `verify` unless it already succeeded for `key`, caching a success
+    pub fn verify_with<E>(
+        &self,
+        key: ScriptCacheKey,
+        verify: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        if self.contains(&key) {
+            return Ok(());
+        }
+        verify()?;
+        self.insert(key);
+        Ok(())
+    }
+
+    /// Forget one verification
+    pub fn invalidate(&self, key: &ScriptCacheKey) {
+        self.lock().pop(key);
+    }
+
+    /// Forget every verification, e.g. when the consensus rules in force change
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn stats(&self) -> ScriptCacheStats {
+        ScriptCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.lock().len(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<ScriptCacheKey, ()>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, TxIn, Txid, Witness};
+
+    fn transaction(witness: &[u8]) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::from_slice(&[witness]),
+            }],
+            output: Vec::new(),
+        }
+    }
+
+    fn spent(sats: u64, script: &[u8]) -> TxOut {
+        TxOut {
+            value: Amount::from_sat(sats),
+            script_pubkey: ScriptBuf::from_bytes(script.to_vec()),
+        }
+    }
+
+    #[test]
+    fn test_key_commits_to_witness_utxo_and_flags() {
+        let cache = ScriptCache::new(16);
+        let tx = transaction(&[1]);
+        let key = cache.key(&tx, 0, &spent(1_000, &[0x51]), 1);
+        cache.insert(key);
+        assert!(cache.contains(&cache.key(&tx, 0, &spent(1_000, &[0x51]), 1)));
+
+        // Same txid with another witness
+        let malleated = transaction(&[2]);
+        assert_eq!(malleated.compute_txid(), tx.compute_txid());
+        assert!(!cache.contains(&cache.key(&malleated, 0, &spent(1_000, &[0x51]), 1)));
+        // Other amount, script, input or flags
+        assert!(!cache.contains(&cache.key(&tx, 0, &spent(2_000, &[0x51]), 1)));
+        assert!(!cache.contains(&cache.key(&tx, 0, &spent(1_000, &[0x52]), 1)));
+        assert!(!cache.contains(&cache.key(&tx, 1, &spent(1_000, &[0x51]), 1)));
+        assert!(!cache.contains(&cache.key(&tx, 0, &spent(1_000, &[0x51]), 3)));
+
+        // Keys are salted per cache
+        let other = ScriptCache::new(16);
+        assert_ne!(other.key(&tx, 0, &spent(1_000, &[0x51]), 1), key);
+    }
+
+    #[test]
+    fn test_verify_with_caches_successes_only() {
+        let cache = ScriptCache::new(16);
+        let tx = transaction(&[1]);
+        let key = cache.key(&tx, 0, &spent(1_000, &[0x51]), 0);
+        let mut runs = 0;
+
+        assert_eq!(
+            cache.verify_with(key, || {
+                runs += 1;
+                Err("bad signature")
+            }),
+            Err("bad signature")
+        );
+        for _ in 0..3 {
+            cache
+                .verify_with(key, || {
+                    runs += 1;
+                    Ok::<_, &str>(())
+                })
+                .unwrap();
+        }
+        assert_eq!(runs, 2);
+        assert_eq!(
+            cache.stats(),
+            ScriptCacheStats {
+                hits: 2,
+                misses: 2,
+                entries: 1
+            }
+        );
+        assert_eq!(cache.stats().hit_rate(), 0.5);
+
+        cache.invalidate(&key);
+        assert!(!cache.contains(&key));
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = ScriptCache::new(2);
+        let keys: Vec<_> = (0..3)
+            .map(|vin| cache.key(&transaction(&[1]), vin, &spent(1_000, &[0x51]), 0))
+            .collect();
+        cache.insert(keys[0]);
+        cache.insert(keys[1]);
+        assert!(cache.contains(&keys[0]));
+        cache.insert(keys[2]);
+
+        assert!(cache.contains(&keys[0]));
+        assert!(!cache.contains(&keys[1]));
+        assert!(cache.contains(&keys[2]));
+        assert_eq!(cache.stats().entries, 2);
+
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
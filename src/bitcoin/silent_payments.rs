@@ -0,0 +1,338 @@
+// [AIR-3][AIS-3][BPC-3] BIP-352 silent payments (sending side)
+//
+// A silent payment address publishes a scan key and a spend key. The sender
+// derives a fresh taproot output for every payment from the transaction's
+// inputs, so outputs depend on the inputs chosen by coin selection.
+
+use crate::bitcoin::bip341::tagged_hash;
+use crate::AnyaError;
+use bitcoin::bech32::primitives::decode::CheckedHrpstring;
+use bitcoin::bech32::{Bech32m, ByteIterExt, Fe32, Fe32IterExt, Hrp};
+use bitcoin::key::{Parity, TweakedPublicKey};
+use bitcoin::secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
+use bitcoin::{Network, OutPoint, ScriptBuf};
+use std::fmt;
+use std::str::FromStr;
+
+/// Human-readable part of mainnet silent payment addresses
+const MAINNET_HRP: &str = "sp";
+
+/// Human-readable part of test network silent payment addresses
+const TESTNET_HRP: &str = "tsp";
+
+/// Silent payment errors
+#[derive(Debug, thiserror::Error)]
+pub enum SilentPaymentError {
+    #[error("Invalid silent payment address: {0}")]
+    InvalidAddress(String),
+    #[error("No eligible inputs for silent payment derivation")]
+    NoEligibleInputs,
+    #[error("Key derivation failed: {0}")]
+    Derivation(String),
+}
+
+impl From<SilentPaymentError> for AnyaError {
+    fn from(err: SilentPaymentError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+/// Silent payment result type
+pub type SilentPaymentResult<T> = Result<T, SilentPaymentError>;
+
+/// Decoded silent payment address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SilentPaymentAddress {
+    /// Receiver scan public key (B_scan)
+    pub scan_pubkey: PublicKey,
+    /// Receiver spend public key (B_spend)
+    pub spend_pubkey: PublicKey,
+    /// Whether the address is for mainnet
+    pub mainnet: bool,
+}
+
+impl SilentPaymentAddress {
+    /// Create an address from its scan and spend keys
+    pub fn new(scan_pubkey: PublicKey, spend_pubkey: PublicKey, network: Network) -> Self {
+        Self {
+            scan_pubkey,
+            spend_pubkey,
+            mainnet: network == Network::Bitcoin,
+        }
+    }
+
+    /// Whether `s` looks like a silent payment address (by prefix only)
+    pub fn is_silent_payment_address(s: &str) -> bool {
+        let lower = s.to_ascii_lowercase();
+        lower.starts_with("sp1") || lower.starts_with("tsp1")
+    }
+}
+
+impl FromStr for SilentPaymentAddress {
+    type Err = SilentPaymentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |e: &dyn fmt::Display| SilentPaymentError::InvalidAddress(e.to_string());
+
+        let mut checked = CheckedHrpstring::new::<Bech32m>(s).map_err(|e| invalid(&e))?;
+        let mainnet = match checked.hrp().as_str() {
+            MAINNET_HRP => true,
+            TESTNET_HRP => false,
+            other => return Err(invalid(&format!("unknown prefix '{other}'"))),
+        };
+        if checked.remove_witness_version() != Some(Fe32::Q) {
+            return Err(invalid(&"unsupported version"));
+        }
+
+        let payload: Vec<u8> = checked.byte_iter().collect();
+        if payload.len() != 66 {
+            return Err(invalid(&format!(
+                "expected 66 key bytes, found {}",
+                payload.len()
+            )));
+        }
+
+        Ok(Self {
+            scan_pubkey: PublicKey::from_slice(&payload[..33]).map_err(|e| invalid(&e))?,
+            spend_pubkey: PublicKey::from_slice(&payload[33..]).map_err(|e| invalid(&e))?,
+            mainnet,
+        })
+    }
+}
+
+impl fmt::Display for SilentPaymentAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hrp = Hrp::parse_unchecked(if self.mainnet {
+            MAINNET_HRP
+        } else {
+            TESTNET_HRP
+        });
+        let payload = self
+            .scan_pubkey
+            .serialize()
+            .into_iter()
+            .chain(self.spend_pubkey.serialize());
+        for c in payload
+            .bytes_to_fes()
+            .with_checksum::<Bech32m>(&hrp)
+            .with_witness_version(Fe32::Q)
+            .chars()
+        {
+            write!(f, "{c}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Transaction input contributing to the silent payment shared secret
+#[derive(Debug, Clone, Copy)]
+pub struct SilentPaymentInput {
+    /// Outpoint being spent
+    pub outpoint: OutPoint,
+    /// Private key of the spent output
+    pub secret_key: SecretKey,
+    /// Whether the spent output is taproot (key-path, x-only)
+    pub is_taproot: bool,
+}
+
+/// Compute the taproot outputs paying each recipient
+///
+/// `inputs` are the eligible inputs (P2TR, P2WPKH, P2SH-P2WPKH, P2PKH) with
+/// their keys; `outpoints` are all outpoints spent by the transaction, which
+/// may include ineligible inputs. Returns one P2TR script per recipient, in
+/// the same order. Recipients that share a scan key receive distinct outputs
+/// (k = 0, 1, ...).
+pub fn create_outputs(
+    recipients: &[SilentPaymentAddress],
+    inputs: &[SilentPaymentInput],
+    outpoints: &[OutPoint],
+) -> SilentPaymentResult<Vec<ScriptBuf>> {
+    let secp = Secp256k1::new();
+    let derivation = |e: bitcoin::secp256k1::Error| SilentPaymentError::Derivation(e.to_string());
+
+    let smallest_outpoint = outpoints
+        .iter()
+        .chain(inputs.iter().map(|input| &input.outpoint))
+        .map(bitcoin::consensus::serialize)
+        .min()
+        .ok_or(SilentPaymentError::NoEligibleInputs)?;
+
+    // a = sum of input keys, with taproot keys normalised to even y
+    let mut input_keys = inputs.iter().map(|input| {
+        let (_, parity) = input.secret_key.x_only_public_key(&secp);
+        if input.is_taproot && parity == Parity::Odd {
+            input.secret_key.negate()
+        } else {
+            input.secret_key
+        }
+    });
+    let first = input_keys
+        .next()
+        .ok_or(SilentPaymentError::NoEligibleInputs)?;
+    let a_sum = input_keys.try_fold(first, |acc, key| {
+        acc.add_tweak(&Scalar::from(key)).map_err(derivation)
+    })?;
+    let a_pub = PublicKey::from_secret_key(&secp, &a_sum);
+
+    let input_hash = tagged_hash(
+        b"BIP0352/Inputs",
+        &[smallest_outpoint.as_slice(), &a_pub.serialize()].concat(),
+    );
+    let input_hash = Scalar::from_be_bytes(input_hash)
+        .map_err(|e| SilentPaymentError::Derivation(format!("input hash out of range: {e:?}")))?;
+    let tweaked_a = a_sum.mul_tweak(&input_hash).map_err(derivation)?;
+
+    let mut counters: Vec<(PublicKey, u32)> = Vec::new();
+    recipients
+        .iter()
+        .map(|recipient| {
+            let k = match counters
+                .iter_mut()
+                .find(|(scan, _)| *scan == recipient.scan_pubkey)
+            {
+                Some((_, k)) => {
+                    *k += 1;
+                    *k
+                }
+                None => {
+                    counters.push((recipient.scan_pubkey, 0));
+                    0
+                }
+            };
+
+            let shared_secret = recipient
+                .scan_pubkey
+                .mul_tweak(&secp, &Scalar::from(tweaked_a))
+                .map_err(derivation)?;
+            output_script(&secp, &shared_secret, &recipient.spend_pubkey, k)
+        })
+        .collect()
+}
+
+/// P2TR script for output `k` derived from an ECDH shared secret
+pub(crate) fn output_script(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    shared_secret: &PublicKey,
+    spend_pubkey: &PublicKey,
+    k: u32,
+) -> SilentPaymentResult<ScriptBuf> {
+    let t_k = tagged_hash(
+        b"BIP0352/SharedSecret",
+        &[shared_secret.serialize().as_slice(), &k.to_be_bytes()].concat(),
+    );
+    let t_k = Scalar::from_be_bytes(t_k)
+        .map_err(|e| SilentPaymentError::Derivation(format!("t_k out of range: {e:?}")))?;
+    let output_key = spend_pubkey
+        .add_exp_tweak(secp, &t_k)
+        .map_err(|e| SilentPaymentError::Derivation(e.to_string()))?;
+
+    let (xonly, _) = output_key.x_only_public_key();
+    Ok(ScriptBuf::new_p2tr_tweaked(
+        TweakedPublicKey::dangerous_assume_tweaked(xonly),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::Txid;
+
+    fn key(seed: u8) -> SecretKey {
+        SecretKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn address() -> SilentPaymentAddress {
+        let secp = Secp256k1::new();
+        SilentPaymentAddress::new(
+            PublicKey::from_secret_key(&secp, &key(0x51)),
+            PublicKey::from_secret_key(&secp, &key(0x52)),
+            Network::Bitcoin,
+        )
+    }
+
+    #[test]
+    fn test_address_round_trip() {
+        let address = address();
+        let encoded = address.to_string();
+        assert!(encoded.starts_with("sp1q"));
+        assert!(SilentPaymentAddress::is_silent_payment_address(&encoded));
+        assert_eq!(encoded.parse::<SilentPaymentAddress>().unwrap(), address);
+    }
+
+    #[test]
+    fn test_address_rejects_bad_checksum() {
+        let mut encoded = address().to_string();
+        let last = encoded.pop().unwrap();
+        encoded.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(encoded.parse::<SilentPaymentAddress>().is_err());
+    }
+
+    #[test]
+    fn test_outputs_for_shared_scan_key_differ() {
+        let recipient = address();
+        let inputs = [SilentPaymentInput {
+            outpoint: OutPoint::new(Txid::from_byte_array([7; 32]), 0),
+            secret_key: key(0x11),
+            is_taproot: false,
+        }];
+
+        let outpoints = [inputs[0].outpoint];
+        let outputs = create_outputs(&[recipient, recipient], &inputs, &outpoints).unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert!(outputs.iter().all(|script| script.is_p2tr()));
+        assert_ne!(outputs[0], outputs[1]);
+    }
+
+    #[test]
+    fn test_receiver_finds_output_with_scan_key() {
+        let secp = Secp256k1::new();
+        let recipient = address();
+        let inputs = [
+            SilentPaymentInput {
+                outpoint: OutPoint::new(Txid::from_byte_array([9; 32]), 1),
+                secret_key: key(0x21),
+                is_taproot: true,
+            },
+            SilentPaymentInput {
+                outpoint: OutPoint::new(Txid::from_byte_array([3; 32]), 0),
+                secret_key: key(0x22),
+                is_taproot: false,
+            },
+        ];
+        let outpoints: Vec<OutPoint> = inputs.iter().map(|i| i.outpoint).collect();
+        let outputs = create_outputs(&[recipient], &inputs, &outpoints).unwrap();
+
+        // Receiver side: shared secret = input_hash * b_scan * A
+        let input_pubkeys: Vec<PublicKey> = inputs
+            .iter()
+            .map(|input| {
+                let public_key = input.secret_key.public_key(&secp);
+                if input.is_taproot {
+                    // Taproot inputs commit to the even-y key
+                    let (xonly, _) = public_key.x_only_public_key();
+                    xonly.public_key(Parity::Even)
+                } else {
+                    public_key
+                }
+            })
+            .collect();
+        let a_pub = PublicKey::combine_keys(&input_pubkeys.iter().collect::<Vec<_>>()).unwrap();
+        let smallest = outpoints
+            .iter()
+            .map(bitcoin::consensus::serialize)
+            .min()
+            .unwrap();
+        let input_hash = tagged_hash(
+            b"BIP0352/Inputs",
+            &[smallest.as_slice(), &a_pub.serialize()].concat(),
+        );
+        let scan_tweak = key(0x51)
+            .mul_tweak(&Scalar::from_be_bytes(input_hash).unwrap())
+            .unwrap();
+        let shared_secret = a_pub.mul_tweak(&secp, &Scalar::from(scan_tweak)).unwrap();
+
+        let scanned = output_script(&secp, &shared_secret, &recipient.spend_pubkey, 0).unwrap();
+        assert_eq!(outputs[0], scanned);
+    }
+}
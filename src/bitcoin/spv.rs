@@ -1,403 +1,413 @@
-use std::error::Error;
-//! Bitcoin SPV (Simplified Payment Verification) Implementation
-//! [AIR-3][AIS-3][BPC-3][AIT-3][RES-3]
-//!
-//! This module provides secure SPV proof verification for Bitcoin transactions
-//! using constant-time operations and secure cryptographic primitives.
+// [AIR-3][AIS-3][BPC-3] SPV verification for light clients
+//
+// A light client keeps block headers only. It trusts a transaction once a
+// merkle branch links the txid to the merkle root of a header, and trusts the
+// headers once they form a chain with valid proof-of-work and difficulty.
 
+use crate::AnyaError;
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::consensus::Params;
 use bitcoin::hashes::{sha256d, Hash};
-use bitcoin::{BlockHeader, Transaction, TxMerkleNode, Txid};
-use thiserror::Error;
-use bitcoin::merkle::PartialMerkleTree;
-use bitcoin::consensus::{Decodable, Encodable};
-use std::io::Cursor;
-use crate::bitcoin::error::{BitcoinError, BitcoinResult};
+use bitcoin::{Block, BlockHash, CompactTarget, TxMerkleNode, Txid};
+use serde::{Deserialize, Serialize};
 
-use crate::security::constant_time;
-
-/// SPV verification error
-#[derive(Debug, Error)]
+/// SPV verification errors
+#[derive(Debug, thiserror::Error)]
 pub enum SpvError {
+    #[error("Merkle root mismatch: header commits to {expected}, proof yields {computed}")]
+    RootMismatch {
+        expected: TxMerkleNode,
+        computed: TxMerkleNode,
+    },
+    #[error("Insufficient proof of work for block {0}")]
+    InsufficientPow(BlockHash),
     #[error("Invalid merkle proof: {0}")]
-    InvalidMerkleProof(String),
-    
-    #[error("Invalid block header: {0}")]
-    InvalidBlockHeader(String),
-    
-    #[error("Invalid transaction: {0}")]
-    InvalidTransaction(String),
-    
-    #[error("Bitcoin hash error: {0}")]
-    HashError(String),
-    
-    #[error("Deserialization error: {0}")]
-    DeserializationError(String),
-    
-    #[error("Proof verification error: {0}")]
-    VerificationError(String),
-    
-    #[error("Other error: {0}")]
-    Other(String),
-    
-    #[error("Merkle root mismatch")]
-    MerkleRootMismatch,
-    
-    #[error("Transaction not found in merkle proof")]
-    MissingTransaction,
+    InvalidProof(String),
+    #[error("Block {hash} does not build on {expected_prev}")]
+    BrokenChain {
+        hash: BlockHash,
+        expected_prev: BlockHash,
+    },
+    #[error("Invalid difficulty for block {hash} at height {height}: {reason}")]
+    InvalidDifficulty {
+        hash: BlockHash,
+        height: u32,
+        reason: String,
+    },
 }
 
-/// Structure for an SPV proof
-#[derive(Debug, Clone)]
-pub struct SpvProof {
-    /// Transaction ID being proven
-    pub tx_id: Txid,
-    
-    /// Transaction data (optional)
-    pub tx_data: Option<Transaction>,
-    
-    /// Block header containing the transaction
-    pub block_header: BlockHeader,
-    
-    /// Partial merkle tree proof
-    pub merkle_proof: PartialMerkleTree,
-    
-    /// Transaction index in the block
-    pub tx_index: usize,
+impl From<SpvError> for AnyaError {
+    fn from(err: SpvError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
 }
 
-impl SpvProof {
-    /// Create a new SPV proof
-    pub fn new(
-        tx_id: Txid,
-        tx_data: Option<Transaction>,
-        block_header: BlockHeader,
-        merkle_proof: PartialMerkleTree,
-        tx_index: usize,
-    ) -> Self {
-        Self {
-            tx_id,
-            tx_data,
-            block_header,
-            merkle_proof,
-            tx_index,
-        }
-    }
-    
-    /// Create an SPV proof from raw components
-    pub fn from_raw(
-        tx_id: &[u8],
-        tx_data: Option<&[u8]>,
-        block_header: &[u8],
-        merkle_proof: &[u8],
-        tx_index: usize,
-    ) -> Result<Self, SpvError> {
-        // Parse transaction ID
-        let tx_id = Txid::from_slice(tx_id)
-            .map_err(|e| SpvError::InvalidTransaction(format!("Invalid txid: {}", e)))?;
-        
-        // Parse transaction data if provided
-        let tx_data = if let Some(data) = tx_data {
-            let tx = bitcoin::consensus::deserialize(data)
-                .map_err(|e| SpvError::DeserializationError(format!("Invalid transaction data: {}", e)))?;
-            Some(tx)
-        } else {
-            None
-        };
-        
-        // Parse block header
-        let block_header = bitcoin::consensus::deserialize(block_header)
-            .map_err(|e| SpvError::InvalidBlockHeader(format!("Invalid block header: {}", e)))?;
-        
-        // Parse merkle proof
-        let merkle_proof = PartialMerkleTree::consensus_decode(Cursor::new(merkle_proof))
-            .map_err(|e| SpvError::InvalidMerkleProof(format!("Invalid merkle proof: {}", e)))?;
-        
-        Ok(Self {
-            tx_id,
-            tx_data,
-            block_header,
-            merkle_proof,
-            tx_index,
-        })
+/// SPV result type
+pub type SpvResult<T> = Result<T, SpvError>;
+
+/// Merkle branch proving that a transaction is included in a block
+///
+/// Same shape as the Electrum `blockchain.transaction.get_merkle` response:
+/// the transaction's position in the block and the sibling hashes from the
+/// leaves up to the root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Index of the transaction in the block
+    pub position: u32,
+    /// Sibling hashes, leaf level first
+    pub siblings: Vec<TxMerkleNode>,
+}
+
+impl MerkleProof {
+    /// Create a proof from a position and sibling hashes
+    pub fn new(position: u32, siblings: Vec<TxMerkleNode>) -> Self {
+        Self { position, siblings }
     }
-    
-    /// Verify the SPV proof
-    pub fn verify(&self) -> Result<bool, SpvError> {
-        // First, check if the transaction matches the txid
-        if let Some(tx) = &self.tx_data {
-            let actual_txid = tx.txid();
-            if actual_txid != self.tx_id {
-                return Err(SpvError::VerificationError(
-                    "Transaction does not match the provided txid".to_string()
-                ));
+
+    /// Proof for `txid` built from the transactions of `block`
+    ///
+    /// Returns `None` when the block does not contain the transaction.
+    pub fn from_block(block: &Block, txid: &Txid) -> Option<Self> {
+        let mut level: Vec<[u8; 32]> = block
+            .txdata
+            .iter()
+            .map(|tx| tx.compute_txid().to_byte_array())
+            .collect();
+        let position = level.iter().position(|id| *id == txid.to_byte_array())?;
+
+        let mut index = position;
+        let mut siblings = Vec::new();
+        while level.len() > 1 {
+            // An odd level pairs its last node with itself
+            if level.len() % 2 == 1 {
+                level.push(level[level.len() - 1]);
             }
+            siblings.push(TxMerkleNode::from_byte_array(level[index ^ 1]));
+            level = level
+                .chunks(2)
+                .map(|pair| sha256d::Hash::hash(&pair.concat()).to_byte_array())
+                .collect();
+            index /= 2;
         }
-        
-        // Then verify the merkle proof
-        let merkle_root = verify_merkle_proof(
-            &self.tx_id.to_byte_array(), 
-            &self.merkle_proof.merkle_root().to_byte_array(), 
-            &self.merkle_proof.consensus_encode(&mut Vec::new())?
-        )?;
-        
-        // Finally, compare the computed merkle root with the one in the block header
-        // Use constant-time comparison to prevent timing attacks
-        let header_merkle_root = self.block_header.merkle_root.to_byte_array();
-        let computed_merkle_root = merkle_root.to_byte_array();
-        
-        let equal = constant_time::constant_time_eq(&header_merkle_root, &computed_merkle_root);
-        
-        Ok(equal)
-    }
-    
-    /// Serialize the SPV proof
-    pub fn serialize(&self) -> Result<Vec<u8>, SpvError> {
-        let mut buffer = Vec::new();
-        
-        // Serialize block header
-        self.block_header.consensus_encode(&mut buffer)?;
-        
-        // Serialize txid
-        self.tx_id.consensus_encode(&mut buffer)?;
-        
-        // Serialize partial merkle tree
-        self.merkle_proof.consensus_encode(&mut buffer)?;
-        
-        // Serialize tx index
-        (self.tx_index as u32).consensus_encode(&mut buffer)?;
-        
-        Ok(buffer)
+        Some(Self::new(position as u32, siblings))
     }
-    
-    /// Deserialize an SPV proof
-    pub fn deserialize(data: &[u8]) -> Result<Self, SpvError> {
-        let mut cursor = Cursor::new(data);
-        
-        // Deserialize block header
-        let block_header = BlockHeader::consensus_decode(&mut cursor)?;
-        
-        // Deserialize txid
-        let tx_id = Txid::consensus_decode(&mut cursor)?;
-        
-        // Deserialize partial merkle tree
-        let merkle_proof = PartialMerkleTree::consensus_decode(&mut cursor)?;
-        
-        // Deserialize tx index
-        let tx_index = u32::consensus_decode(&mut cursor)? as usize;
-        
-        Ok(Self {
-            block_header,
-            tx_id,
-            merkle_proof,
-            tx_index,
-        })
+
+    /// Merkle root obtained by hashing `txid` up the branch
+    pub fn compute_root(&self, txid: &Txid) -> SpvResult<TxMerkleNode> {
+        if self.siblings.len() > 32 || u64::from(self.position) >> self.siblings.len() != 0 {
+            return Err(SpvError::InvalidProof(format!(
+                "position {} does not fit a branch of depth {}",
+                self.position,
+                self.siblings.len()
+            )));
+        }
+
+        let mut node = txid.to_byte_array();
+        for (depth, sibling) in self.siblings.iter().enumerate() {
+            let sibling = sibling.to_byte_array();
+            let (left, right) = if (self.position >> depth) & 1 == 0 {
+                (node, sibling)
+            } else {
+                (sibling, node)
+            };
+            node = sha256d::Hash::hash(&[left, right].concat()).to_byte_array();
+        }
+        Ok(TxMerkleNode::from_byte_array(node))
     }
 }
 
-/// Verify a merkle proof for a transaction
-///
-/// This function takes a transaction ID, a merkle path, and the transaction index
-/// and verifies that the transaction is included in the block with the given merkle root.
-///
-/// # Arguments
-/// * `tx_id` - The transaction ID to verify
-/// * `merkle_path` - The merkle path (pairs of hashes)
-/// * `tx_index` - The index of the transaction in the block
+/// Verify that `txid` is committed to by `block_header`
 ///
-/// # Returns
-/// The merkle root if the proof is valid, an error otherwise
+/// The header must satisfy the proof-of-work it claims. Returns `Ok(true)`
+/// for a valid proof; a root mismatch and insufficient work are reported as
+/// distinct errors.
 pub fn verify_merkle_proof(
-    tx_id: &[u8],
-    merkle_root: &[u8],
-    proof: &[u8],
-) -> Result<TxMerkleNode, SpvError> {
-    // Convert hash to TxMerkleNode
-    let tx_merkle_node = match TxMerkleNode::from_slice(tx_id) {
-        Ok(node) => node,
-        Err(_) => return Err(SpvError::Other("Invalid transaction hash".to_string())),
-    };
-    
-    // Deserialize the partial merkle tree
-    let partial_merkle_tree = match PartialMerkleTree::consensus_decode(Cursor::new(proof)) {
-        Ok(tree) => tree,
-        Err(_) => return Err(SpvError::InvalidMerkleProof("Failed to deserialize merkle proof".to_string())),
-        };
-        
-    // Extract matched transactions and indices
-    let mut matched_txids = Vec::new();
-    let mut indices = Vec::new();
-    
-    if !partial_merkle_tree.extract_matches(&mut matched_txids, &mut indices) {
-        return Err(SpvError::InvalidMerkleProof("Failed to extract matches from merkle proof".to_string()));
-    }
-    
-    // Check if our transaction is in the matched transactions
-    if !matched_txids.contains(&tx_merkle_node) {
-        return Err(SpvError::MissingTransaction);
-    }
-    
-    // Convert merkle root to TxMerkleNode
-    let expected_root = match TxMerkleNode::from_slice(merkle_root) {
-        Ok(node) => node,
-        Err(_) => return Err(SpvError::Other("Invalid merkle root".to_string())),
-    };
-    
-    // Verify the merkle root
-    if partial_merkle_tree.merkle_root() != expected_root {
-        return Err(SpvError::MerkleRootMismatch);
+    txid: &Txid,
+    proof: &MerkleProof,
+    block_header: &BlockHeader,
+) -> SpvResult<bool> {
+    block_header
+        .validate_pow(block_header.target())
+        .map_err(|_| SpvError::InsufficientPow(block_header.block_hash()))?;
+
+    let computed = proof.compute_root(txid)?;
+    if computed != block_header.merkle_root {
+        return Err(SpvError::RootMismatch {
+            expected: block_header.merkle_root,
+            computed,
+        });
     }
-    
-    Ok(tx_merkle_node)
+    Ok(true)
 }
 
-/// Verify that a transaction is included in a block
-///
-/// This is a helper function that creates an SPV proof and verifies it
+/// Verify a contiguous sequence of headers starting at `start_height`
 ///
-/// # Arguments
-/// * `tx_id_hex` - The transaction ID in hex format
-/// * `block_header_hex` - The block header in hex format
-/// * `merkle_proof_hex` - The merkle proof in hex format (array of hashes)
-/// * `tx_index` - The index of the transaction in the block
-///
-/// # Returns
-/// `true` if the proof is valid, `false` otherwise
-pub fn verify_tx_inclusion(
-    tx_id_hex: &str,
-    block_header_hex: &str,
-    merkle_proof_hex: &[&str],
-    tx_index: u32,
-) -> Result<bool, SpvError> {
-    // Convert hex strings to bytes
-    let tx_id = hex::decode(tx_id_hex)
-        .map_err(|e| SpvError::InvalidTransaction(format!("Invalid txid hex: {}", e)))?;
-    
-    let block_header = hex::decode(block_header_hex)
-        .map_err(|e| SpvError::InvalidBlockHeader(format!("Invalid block header hex: {}", e)))?;
-    
-    let merkle_proof = merkle_proof_hex.iter()
-        .map(|h| hex::decode(h))
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| SpvError::InvalidMerkleProof(format!("Invalid merkle path hex: {}", e)))?;
-    
-    // Create and verify the proof
-    let proof = SpvProof::from_raw(&tx_id, None, BlockHeader::from_slice(&block_header)?, PartialMerkleTree::from_txids(&[Txid::from_slice(&tx_id)?], &[Txid::from_slice(&tx_id)?]), tx_index as usize)?;
-    proof.verify()
-}
+/// Checks that every header links to its predecessor, meets its target and
+/// stays within the network's proof-of-work limit, and that the difficulty
+/// only changes as consensus allows. Retargets are recomputed exactly when
+/// the sequence contains the whole preceding period and bounded to a factor
+/// of four otherwise. On networks with minimum-difficulty blocks only the
+/// limit is enforced at retarget boundaries.
+pub fn verify_header_chain(
+    headers: &[BlockHeader],
+    start_height: u32,
+    params: impl AsRef<Params>,
+) -> SpvResult<()> {
+    let params = params.as_ref();
+    let interval = params.difficulty_adjustment_interval() as u32;
+    let limit_bits = params.max_attainable_target.to_compact_lossy();
+    // Bits of the last block not mined at minimum difficulty
+    let mut last_regular_bits = None;
 
-/// Verify a Bitcoin payment using SPV proof
-///
-/// This function verifies that a Bitcoin payment (transaction) is included in a block
-/// using an SPV proof, which is a more secure and efficient way to verify payments
-/// without downloading the entire blockchain.
-///
-/// # Arguments
-/// * `tx_id_hex` - The transaction ID in hex format
-/// * `block_header_hex` - The block header in hex format
-/// * `merkle_proof_hex` - The merkle proof in hex format (array of hashes)
-/// * `tx_index` - The index of the transaction in the block
-/// * `confirmations_required` - The number of confirmations required
-///
-/// # Returns
-/// `true` if the payment is valid, `false` otherwise
-pub fn verify_bitcoin_payment(
-    tx_id_hex: &str,
-    block_header_hex: &str,
-    merkle_proof_hex: &[&str],
-    tx_index: u32,
-    _confirmations_required: u64,
-) -> Result<bool, SpvError> {
-    // Verify inclusion of the transaction in the block
-    verify_tx_inclusion(tx_id_hex, block_header_hex, merkle_proof_hex, tx_index)
-    
-    // Note: Checking for the required number of confirmations would require
-    // connecting to a Bitcoin node to get the current blockchain height
-    // and the height of the block containing the transaction.
-    // This would typically be done in a higher-level function.
+    for (i, header) in headers.iter().enumerate() {
+        let height = start_height + i as u32;
+        let hash = header.block_hash();
+        let target = header.target();
+        if target > params.max_attainable_target || header.validate_pow(target).is_err() {
+            return Err(SpvError::InsufficientPow(hash));
+        }
+
+        if let Some(prev) = i.checked_sub(1).map(|p| &headers[p]) {
+            if header.prev_blockhash != prev.block_hash() {
+                return Err(SpvError::BrokenChain {
+                    hash,
+                    expected_prev: prev.block_hash(),
+                });
+            }
+            let invalid = |reason: String| SpvError::InvalidDifficulty {
+                hash,
+                height,
+                reason,
+            };
+
+            if height % interval != 0 {
+                let expected = if !params.allow_min_difficulty_blocks {
+                    Some(prev.bits)
+                } else if u64::from(header.time)
+                    > u64::from(prev.time) + 2 * params.pow_target_spacing
+                {
+                    Some(limit_bits)
+                } else {
+                    last_regular_bits
+                };
+                if let Some(expected) = expected.filter(|bits| *bits != header.bits) {
+                    return Err(invalid(format!(
+                        "expected bits {:#x}, found {:#x}",
+                        expected.to_consensus(),
+                        header.bits.to_consensus()
+                    )));
+                }
+            } else if params.no_pow_retargeting {
+                if header.bits != prev.bits {
+                    return Err(invalid("retargeting is disabled".to_string()));
+                }
+            } else if !params.allow_min_difficulty_blocks {
+                match (i as u32)
+                    .checked_sub(interval)
+                    .map(|f| &headers[f as usize])
+                {
+                    Some(first) => {
+                        let timespan = u64::from(prev.time.saturating_sub(first.time));
+                        let expected =
+                            CompactTarget::from_next_work_required(prev.bits, timespan, params);
+                        if header.bits != expected {
+                            return Err(invalid(format!(
+                                "expected retarget to {:#x}, found {:#x}",
+                                expected.to_consensus(),
+                                header.bits.to_consensus()
+                            )));
+                        }
+                    }
+                    None => {
+                        let prev_target = prev.target();
+                        if target < prev_target.min_transition_threshold()
+                            || target > prev_target.max_transition_threshold(params)
+                        {
+                            return Err(invalid("retarget exceeds a factor of four".to_string()));
+                        }
+                    }
+                }
+            }
+        }
+
+        if height % interval == 0 || header.bits != limit_bits {
+            last_regular_bits = Some(header.bits);
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bitcoin::hashes::hex::FromHex;
-    
+    use bitcoin::block::Version;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::consensus::deserialize;
+    use bitcoin::hex::FromHex;
+    use bitcoin::Network;
+
+    /// Testnet3 block 0000000000013b8ab2cd513b0261a14096412195a72a0c4827d229dcc7e0f7af
+    const TESTNET_HEADER: &str = "0100000090f0a9f110702f808219ebea1173056042a714bad51b916cb6800000000000005275289558f51c9966699404ae2294730c3c9f9bda53523ce50e9b95e558da2fdb261b4d4c86041b1ab1bf93";
+
+    /// Mainnet blocks 1 and 2
+    const MAINNET_HEADER_1: &str = "010000006fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e61bc6649ffff001d01e36299";
+    const MAINNET_HEADER_2: &str = "010000004860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9bb0bc6649ffff001d08d2bd61";
+
+    fn header(hex: &str) -> BlockHeader {
+        deserialize(&Vec::<u8>::from_hex(hex).unwrap()).unwrap()
+    }
+
+    fn proof(position: u32, siblings: &[&str]) -> MerkleProof {
+        MerkleProof::new(
+            position,
+            siblings.iter().map(|s| s.parse().unwrap()).collect(),
+        )
+    }
+
+    /// Branches for transactions 1 and 8 of the testnet block
+    fn testnet_proofs() -> Vec<(Txid, MerkleProof)> {
+        vec![
+            (
+                "f9fc751cb7dc372406a9f8d738d5e6f8f63bab71986a39cf36ee70ee17036d07"
+                    .parse()
+                    .unwrap(),
+                proof(
+                    1,
+                    &[
+                        "ef1d870d24c85b89d92ad50f4631026f585d6a34e972eaf427475e5d60acf3a3",
+                        "4a081b6fc84b3ff52a42c14b5f06a6b9dde6908984a4439814c186c139b32d52",
+                        "ca837db5ad733692f334145b3b9fdaca53e557a71da71d5110dc6c31f09f1889",
+                        "dbcfb7f7721e0110ade91481e63155b9f22d11d4fa550bc36f6d15af382c39ac",
+                    ],
+                ),
+            ),
+            (
+                "74d681e0e03bafa802c8aa084379aa98d9fcd632ddc2ed9782b586ec87451f20"
+                    .parse()
+                    .unwrap(),
+                proof(
+                    8,
+                    &[
+                        "74d681e0e03bafa802c8aa084379aa98d9fcd632ddc2ed9782b586ec87451f20",
+                        "bb8db5f1d687839cc15a875e321ffb910d1c62d9280c1e4089122544c3528a13",
+                        "e7413bdf2c1215c3983536a62b1e210d9006a789cdc1427ccb4bb347745e52fc",
+                        "660af9921e9e17eba1106409c93aeec1b390bff99b0c25499da1b9c0e9aa56bc",
+                    ],
+                ),
+            ),
+        ]
+    }
+
+    /// Header on top of `prev` meeting `bits`, found by grinding the nonce
+    fn mine(prev: &BlockHeader, time: u32, bits: CompactTarget) -> BlockHeader {
+        let mut header = BlockHeader {
+            version: Version::TWO,
+            prev_blockhash: prev.block_hash(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time,
+            bits,
+            nonce: 0,
+        };
+        while header.validate_pow(header.target()).is_err() {
+            header.nonce += 1;
+        }
+        header
+    }
+
+    /// Regtest parameters with retargeting every four blocks
+    fn fast_retarget_params() -> Params {
+        let mut params = Params::REGTEST;
+        params.no_pow_retargeting = false;
+        params.allow_min_difficulty_blocks = false;
+        params.pow_target_timespan = 4 * params.pow_target_spacing;
+        params
+    }
+
+    #[test]
+    fn test_testnet_merkle_proofs_verify() {
+        let header = header(TESTNET_HEADER);
+        for (txid, proof) in testnet_proofs() {
+            assert!(verify_merkle_proof(&txid, &proof, &header).unwrap());
+        }
+    }
+
     #[test]
-    fn test_compute_merkle_parent() {
-        // Example values from Bitcoin
-        let left_hex = "b67e5c13dd78c212e64e2fa8d153c6f6a5cc741a1ec9c9fb3045f9854c881ae4";
-        let right_hex = "1b4741e858a7b7c0a851a35c43858bc8902c0a91b5bd7043b9a27e8b00e2a8e2";
-        let expected_parent_hex = "eb42a05772f296e9fe8a7f0d8a7c9abad734cc7dd31799a2b12a728a5d4ad891";
-        
-        let left = TxMerkleNode::from_hex(left_hex)?;
-        let right = TxMerkleNode::from_hex(right_hex)?;
-        let expected_parent = TxMerkleNode::from_hex(expected_parent_hex)?;
-        
-        let computed_parent = verify_merkle_proof(&left.to_byte_array(), &right.to_byte_array(), &[left.to_byte_array(), right.to_byte_array()])?;
-        assert_eq!(computed_parent, expected_parent);
+    fn test_merkle_proof_root_mismatch() {
+        let header = header(TESTNET_HEADER);
+        let (txid, mut proof) = testnet_proofs().remove(0);
+
+        let other_txid = testnet_proofs()[1].0;
+        let result = verify_merkle_proof(&other_txid, &proof, &header);
+        assert!(matches!(result, Err(SpvError::RootMismatch { .. })));
+
+        proof.position = 0;
+        let result = verify_merkle_proof(&txid, &proof, &header);
+        assert!(matches!(result, Err(SpvError::RootMismatch { .. })));
+
+        proof.position = 16;
+        let result = verify_merkle_proof(&txid, &proof, &header);
+        assert!(matches!(result, Err(SpvError::InvalidProof(_))));
     }
-    
+
+    #[test]
+    fn test_merkle_proof_insufficient_pow() {
+        let mut header = header(TESTNET_HEADER);
+        header.nonce = header.nonce.wrapping_add(1);
+        let (txid, proof) = testnet_proofs().remove(0);
+        let result = verify_merkle_proof(&txid, &proof, &header);
+        assert!(matches!(result, Err(SpvError::InsufficientPow(_))));
+    }
+
     #[test]
-    fn test_verify_merkle_proof() {
-        // This is a simplified test with made-up values
-        // In a real test, you would use actual values from the Bitcoin blockchain
-        
-        // Create a sample transaction ID
-        let tx_id_hex = "b67e5c13dd78c212e64e2fa8d153c6f6a5cc741a1ec9c9fb3045f9854c881ae4";
-        let tx_id = Txid::from_hex(tx_id_hex)?;
-        
-        // Create a sample merkle path (2 nodes)
-        let merkle_path_hex = [
-            "1b4741e858a7b7c0a851a35c43858bc8902c0a91b5bd7043b9a27e8b00e2a8e2",
-            "9d28bd159e5ec3c21e1b305454231bd10033a4dd324b5d9c0bb29c60d4d7b4f8",
-        ];
-        
-        let merkle_path = merkle_path_hex.iter()
-            .map(|h| TxMerkleNode::from_hex(h)?)
-            .collect::<Vec<_>>();
-        
-        // The expected merkle root
-        let expected_root_hex = "eb98e9a0a41c33a68f53cf547ba78f349c6522f2c41ccec2934e3b324d0a67e2";
-        let expected_root = TxMerkleNode::from_hex(expected_root_hex)?;
-        
-        // Verify the proof (assuming tx_index = 0)
-        let computed_root = verify_merkle_proof(&merkle_path[0].to_byte_array(), &merkle_path[1].to_byte_array(), &merkle_path.iter().map(|node| node.to_byte_array()).collect::<Vec<_>>())?;
-        
-        // Verify that the computed root matches the expected root
-        assert_eq!(computed_root, expected_root);
+    fn test_mainnet_header_chain() {
+        let genesis = genesis_block(Network::Bitcoin).header;
+        let chain = [genesis, header(MAINNET_HEADER_1), header(MAINNET_HEADER_2)];
+        verify_header_chain(&chain, 0, Network::Bitcoin).unwrap();
+
+        let skipped = [genesis, header(MAINNET_HEADER_2)];
+        let result = verify_header_chain(&skipped, 0, Network::Bitcoin);
+        assert!(matches!(result, Err(SpvError::BrokenChain { .. })));
+
+        let mut weak = header(MAINNET_HEADER_2);
+        weak.nonce = weak.nonce.wrapping_add(1);
+        let result = verify_header_chain(&[header(MAINNET_HEADER_1), weak], 1, Network::Bitcoin);
+        assert!(matches!(result, Err(SpvError::InsufficientPow(_))));
     }
-    
+
     #[test]
-    fn test_spv_proof_verification() {
-        // This is a simplified test with made-up values
-        // In a real test, you would use actual values from the Bitcoin blockchain
-        
-        // Create a sample transaction ID
-        let tx_id_hex = "b67e5c13dd78c212e64e2fa8d153c6f6a5cc741a1ec9c9fb3045f9854c881ae4";
-        let tx_id = Txid::from_hex(tx_id_hex)?;
-        
-        // Create a sample merkle path (2 nodes)
-        let merkle_path_hex = [
-            "1b4741e858a7b7c0a851a35c43858bc8902c0a91b5bd7043b9a27e8b00e2a8e2",
-            "9d28bd159e5ec3c21e1b305454231bd10033a4dd324b5d9c0bb29c60d4d7b4f8",
-        ];
-        
-        let merkle_path = merkle_path_hex.iter()
-            .map(|h| TxMerkleNode::from_hex(h)?)
-            .collect::<Vec<_>>();
-        
-        // The merkle root that will be included in the block header
-        let root_hex = "eb98e9a0a41c33a68f53cf547ba78f349c6522f2c41ccec2934e3b324d0a67e2";
-        let root = TxMerkleNode::from_hex(root_hex)?;
-        
-        // Create a minimal block header with just the merkle root
-        let mut header = BlockHeader::default();
-        header.merkle_root = root;
-        
-        // Create an SPV proof
-        let proof = SpvProof::new(tx_id, None, header, PartialMerkleTree::from_txids(&[tx_id], &[tx_id]), 0);
-        
-        // Verify the proof
-        let result = proof.verify()?;
-        assert!(result);
+    fn test_difficulty_transitions() {
+        let params = fast_retarget_params();
+        let genesis = genesis_block(Network::Regtest).header;
+        let bits = genesis.bits;
+
+        let mut chain = vec![genesis];
+        for height in 1..4 {
+            let prev = chain[height - 1];
+            chain.push(mine(&prev, prev.time + 600, bits));
+        }
+        // Blocks came every 10 minutes, but the retarget spans only three
+        // intervals, so the target drops by a quarter
+        let prev = chain[3];
+        let retarget = CompactTarget::from_next_work_required(bits, 3 * 600, &params);
+        assert_ne!(retarget, bits);
+
+        let stale = mine(&prev, prev.time + 600, bits);
+        let result = verify_header_chain(&[chain.clone(), vec![stale]].concat(), 0, &params);
+        assert!(matches!(
+            result,
+            Err(SpvError::InvalidDifficulty { height: 4, .. })
+        ));
+
+        chain.push(mine(&prev, prev.time + 600, retarget));
+        verify_header_chain(&chain, 0, &params).unwrap();
+
+        // Bits may not change inside a period
+        let prev = chain[4];
+        chain.push(mine(&prev, prev.time + 600, bits));
+        let result = verify_header_chain(&chain, 0, &params);
+        assert!(matches!(
+            result,
+            Err(SpvError::InvalidDifficulty { height: 5, .. })
+        ));
     }
-} 
+}
@@ -0,0 +1,527 @@
+// [AIR-3][AIS-3][BPC-3][RES-3] Headers-first initial block download
+//
+// The full header chain is downloaded and validated before any block is
+// requested. Blocks are then fetched from several peers at once within a
+// sliding window above the next height to connect, and connected strictly in
+// height order as the window fills. A request that fails or exceeds the peer
+// timeout is reassigned to another peer.
+
+use crate::bitcoin::consensus_params::ConsensusParams;
+use crate::bitcoin::network_params::NetworkParams;
+use crate::bitcoin::node::BlockPeer;
+use crate::bitcoin::spv::verify_header_chain;
+use crate::{AnyaError, AnyaResult};
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::p2p::ServiceFlags;
+use bitcoin::{Block, BlockHash};
+use futures::future::BoxFuture;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Most headers a peer returns for one `getheaders` request
+pub const MAX_HEADERS_RESULTS: usize = 2000;
+
+/// [AIR-3][BPC-3] Initial block download configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConfig {
+    /// Blocks requested but not yet connected, across all peers
+    pub max_blocks_in_flight: usize,
+    /// Time a peer has to answer a single request before it is reassigned
+    pub peer_timeout: Duration,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            max_blocks_in_flight: 16,
+            peer_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// [AIR-3][BPC-3] Progress reported after each connected block
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SyncProgress {
+    /// Height of the last connected block
+    pub height: u32,
+    /// Height of the best validated header
+    pub target_height: u32,
+    /// Share of the target height reached, from 0 to 100
+    pub percent: f64,
+    /// Average rate since block download started
+    pub blocks_per_sec: f64,
+}
+
+/// Outcome of one block request
+type BlockResponse = (u32, usize, AnyaResult<Block>);
+
+/// [AIR-3][AIS-3][BPC-3] Headers-first block downloader over a set of peers
+pub struct BlockSync {
+    config: SyncConfig,
+    network_params: NetworkParams,
+    consensus_params: ConsensusParams,
+    peers: Vec<Arc<dyn BlockPeer>>,
+}
+
+impl BlockSync {
+    /// Create a downloader using the peers that serve the full chain
+    pub fn new(
+        config: SyncConfig,
+        network_params: NetworkParams,
+        peers: Vec<Arc<dyn BlockPeer>>,
+    ) -> Self {
+        let peers = peers
+            .into_iter()
+            .filter(|peer| peer.services().has(ServiceFlags::NETWORK))
+            .collect();
+        Self {
+            config,
+            consensus_params: ConsensusParams::for_network(network_params.network),
+            network_params,
+            peers,
+        }
+    }
+
+    /// Download and validate the headers that extend `tip` at `tip_height`
+    ///
+    /// Peers are asked in turn until one returns a short batch. Each batch must
+    /// connect to the chain so far and pass proof-of-work and difficulty
+    /// checks; a peer returning an invalid batch is not asked again.
+    pub async fn sync_headers(
+        &self,
+        tip: &BlockHeader,
+        tip_height: u32,
+    ) -> AnyaResult<Vec<BlockHeader>> {
+        let interval = self
+            .network_params
+            .network
+            .params()
+            .difficulty_adjustment_interval() as usize;
+        // Validated chain including the starting tip
+        let mut chain = vec![*tip];
+        let mut excluded = HashSet::new();
+
+        loop {
+            let locator = [chain[chain.len() - 1].block_hash()];
+            let (index, batch) = self.request_headers(&locator, &excluded).await?;
+            let batch_len = batch.len();
+
+            // Validate the batch with up to a full retarget period of context
+            let context = (chain.len() - 1).saturating_sub(interval);
+            let mut candidate = chain[context..].to_vec();
+            candidate.extend(batch);
+            if let Err(e) = verify_header_chain(
+                &candidate,
+                tip_height + context as u32,
+                self.network_params.network,
+            ) {
+                log::warn!(
+                    "Peer {} sent invalid headers: {}",
+                    self.peers[index].id(),
+                    e
+                );
+                excluded.insert(index);
+                continue;
+            }
+            chain.extend_from_slice(&candidate[chain.len() - context..]);
+
+            if batch_len < MAX_HEADERS_RESULTS {
+                chain.remove(0);
+                return Ok(chain);
+            }
+        }
+    }
+
+    async fn request_headers(
+        &self,
+        locator: &[BlockHash],
+        excluded: &HashSet<usize>,
+    ) -> AnyaResult<(usize, Vec<BlockHeader>)> {
+        for (index, peer) in self.peers.iter().enumerate() {
+            if excluded.contains(&index) {
+                continue;
+            }
+            match tokio::time::timeout(self.config.peer_timeout, peer.request_headers(locator))
+                .await
+            {
+                Ok(Ok(headers)) => return Ok((index, headers)),
+                Ok(Err(e)) => log::debug!("Peer {} could not serve headers: {}", peer.id(), e),
+                Err(_) => log::debug!("Peer {} timed out serving headers", peer.id()),
+            }
+        }
+        Err(AnyaError::Bitcoin(
+            "No peer could serve the header chain".to_string(),
+        ))
+    }
+
+    /// Download the blocks for `headers`, the first at `start_height`
+    ///
+    /// At most `max_blocks_in_flight` blocks above the next height to connect
+    /// are requested at once, spread over the least busy peers. Each block
+    /// must match its header and commitments and pass the network's block
+    /// rules before `connect` is called with it, in height order. A failed,
+    /// stalled or invalid response is retried on a peer that has not failed
+    /// that block yet. Returns the height of the last connected block.
+    pub async fn download_blocks(
+        &self,
+        headers: &[BlockHeader],
+        start_height: u32,
+        mut connect: impl FnMut(u32, Block) -> AnyaResult<()>,
+        mut progress: impl FnMut(SyncProgress),
+    ) -> AnyaResult<u32> {
+        if headers.is_empty() {
+            return Ok(start_height.saturating_sub(1));
+        }
+        if self.peers.is_empty() {
+            return Err(AnyaError::Bitcoin(
+                "No peers available for block download".to_string(),
+            ));
+        }
+
+        let target_height = start_height + headers.len() as u32 - 1;
+        let window = self.config.max_blocks_in_flight.max(1) as u32;
+        let started = Instant::now();
+
+        let mut next_connect = start_height;
+        let mut next_request = start_height;
+        let mut retries: Vec<u32> = Vec::new();
+        let mut failed: BTreeMap<u32, HashSet<usize>> = BTreeMap::new();
+        let mut in_flight = vec![0usize; self.peers.len()];
+        let mut received: BTreeMap<u32, Block> = BTreeMap::new();
+        let mut requests: FuturesUnordered<BoxFuture<'_, BlockResponse>> = FuturesUnordered::new();
+
+        while next_connect <= target_height {
+            // Keep the window full, reassigned requests first
+            while requests.len() + received.len() < window as usize {
+                let height = match retries.pop() {
+                    Some(height) => height,
+                    None if next_request <= target_height
+                        && next_request < next_connect + window =>
+                    {
+                        next_request += 1;
+                        next_request - 1
+                    }
+                    None => break,
+                };
+                let failed_peers = failed.get(&height);
+                let index = (0..self.peers.len())
+                    .filter(|index| !failed_peers.is_some_and(|f| f.contains(index)))
+                    .min_by_key(|index| in_flight[*index])
+                    .ok_or_else(|| {
+                        AnyaError::Bitcoin(format!("No peer could serve block at height {height}"))
+                    })?;
+                in_flight[index] += 1;
+                let hash = headers[(height - start_height) as usize].block_hash();
+                requests.push(self.request_block(index, height, hash));
+            }
+
+            let Some((height, index, result)) = requests.next().await else {
+                return Err(AnyaError::Bitcoin(format!(
+                    "Block download stalled at height {next_connect}"
+                )));
+            };
+            in_flight[index] -= 1;
+            let header = &headers[(height - start_height) as usize];
+            match result.and_then(|block| self.check_block(header, height, block)) {
+                Ok(block) => {
+                    received.insert(height, block);
+                }
+                Err(e) => {
+                    log::debug!(
+                        "Reassigning block at height {} after peer {} failed: {}",
+                        height,
+                        self.peers[index].id(),
+                        e
+                    );
+                    failed.entry(height).or_default().insert(index);
+                    retries.push(height);
+                    continue;
+                }
+            }
+
+            while let Some(block) = received.remove(&next_connect) {
+                connect(next_connect, block)?;
+                failed.remove(&next_connect);
+
+                let connected = next_connect - start_height + 1;
+                let elapsed = started.elapsed().as_secs_f64();
+                progress(SyncProgress {
+                    height: next_connect,
+                    target_height,
+                    percent: f64::from(next_connect) / f64::from(target_height.max(1)) * 100.0,
+                    blocks_per_sec: if elapsed > 0.0 {
+                        f64::from(connected) / elapsed
+                    } else {
+                        0.0
+                    },
+                });
+                next_connect += 1;
+            }
+        }
+        Ok(target_height)
+    }
+
+    fn request_block(
+        &self,
+        index: usize,
+        height: u32,
+        hash: BlockHash,
+    ) -> BoxFuture<'_, BlockResponse> {
+        let peer = self.peers[index].clone();
+        let timeout = self.config.peer_timeout;
+        Box::pin(async move {
+            let result = match tokio::time::timeout(timeout, peer.request_block(&hash)).await {
+                Ok(result) => result,
+                Err(_) => Err(AnyaError::Timeout(format!(
+                    "Peer {} stalled on block {hash}",
+                    peer.id()
+                ))),
+            };
+            (height, index, result)
+        })
+    }
+
+    /// Accept `block` only if it is the block `header` commits to and is
+    /// valid at `height`
+    fn check_block(&self, header: &BlockHeader, height: u32, block: Block) -> AnyaResult<Block> {
+        if block.header != *header {
+            return Err(AnyaError::Bitcoin(format!(
+                "Received block {} when {} was requested",
+                block.block_hash(),
+                header.block_hash()
+            )));
+        }
+        if !block.check_merkle_root() || !block.check_witness_commitment() {
+            return Err(AnyaError::Bitcoin(format!(
+                "Block {} does not match its commitments",
+                header.block_hash()
+            )));
+        }
+        self.network_params
+            .check_block_signature(&block)
+            .map_err(|e| AnyaError::Bitcoin(e.to_string()))?;
+        self.consensus_params.check_block(&block, height)?;
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::hashes::Hash;
+    use bitcoin::script::Builder;
+    use bitcoin::transaction::Version;
+    use bitcoin::{
+        Amount, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness,
+    };
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Regtest chain of `len` blocks after genesis, with valid proof-of-work
+    fn chain(len: u32) -> Vec<Block> {
+        let mut blocks = vec![genesis_block(Network::Regtest)];
+        for height in 1..=len {
+            let prev = &blocks[blocks.len() - 1];
+            let coinbase = Transaction {
+                version: Version::ONE,
+                lock_time: LockTime::ZERO,
+                input: vec![TxIn {
+                    previous_output: OutPoint::null(),
+                    script_sig: Builder::new().push_int(height as i64).into_script(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                }],
+                output: vec![TxOut {
+                    value: Amount::from_sat(50 * 100_000_000),
+                    script_pubkey: ScriptBuf::new(),
+                }],
+            };
+            let mut block = Block {
+                header: BlockHeader {
+                    prev_blockhash: prev.block_hash(),
+                    time: prev.header.time + 600,
+                    ..prev.header
+                },
+                txdata: vec![coinbase],
+            };
+            block.header.merkle_root = block.compute_merkle_root().unwrap();
+            while block.header.validate_pow(block.header.target()).is_err() {
+                block.header.nonce += 1;
+            }
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    struct MockPeer {
+        id: String,
+        blocks: HashMap<BlockHash, Block>,
+        headers: Vec<BlockHeader>,
+        /// Delay per block height, to deliver blocks out of order
+        delay: fn(u32) -> Duration,
+        served: Mutex<Vec<u32>>,
+    }
+
+    impl MockPeer {
+        fn new(id: &str, chain: &[Block], delay: fn(u32) -> Duration) -> Arc<Self> {
+            Arc::new(Self {
+                id: id.to_string(),
+                blocks: chain.iter().map(|b| (b.block_hash(), b.clone())).collect(),
+                headers: chain.iter().map(|b| b.header).collect(),
+                delay,
+                served: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl BlockPeer for MockPeer {
+        fn id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn services(&self) -> ServiceFlags {
+            ServiceFlags::NETWORK
+        }
+
+        async fn request_block(&self, hash: &BlockHash) -> AnyaResult<Block> {
+            let block = self
+                .blocks
+                .get(hash)
+                .cloned()
+                .ok_or_else(|| AnyaError::NotFound("notfound".to_string()))?;
+            let height = self
+                .headers
+                .iter()
+                .position(|h| h.block_hash() == *hash)
+                .unwrap();
+            tokio::time::sleep((self.delay)(height as u32)).await;
+            self.served.lock().unwrap().push(height as u32);
+            Ok(block)
+        }
+
+        async fn request_headers(&self, locator: &[BlockHash]) -> AnyaResult<Vec<BlockHeader>> {
+            let start = self
+                .headers
+                .iter()
+                .position(|h| h.block_hash() == locator[0])
+                .map_or(0, |p| p + 1);
+            Ok(self.headers[start..]
+                .iter()
+                .take(MAX_HEADERS_RESULTS)
+                .copied()
+                .collect())
+        }
+    }
+
+    fn block_sync(peers: Vec<Arc<dyn BlockPeer>>, peer_timeout: Duration) -> BlockSync {
+        BlockSync::new(
+            SyncConfig {
+                max_blocks_in_flight: 4,
+                peer_timeout,
+            },
+            NetworkParams::for_network(Network::Regtest),
+            peers,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_blocks_connect_in_order() {
+        let blocks = chain(12);
+        // Later blocks arrive first on one peer, earlier ones on the other
+        let fast_high = MockPeer::new("fast-high", &blocks, |h| {
+            Duration::from_millis(40 - 3 * h as u64)
+        });
+        let fast_low = MockPeer::new("fast-low", &blocks, |h| Duration::from_millis(3 * h as u64));
+        let sync = block_sync(
+            vec![fast_high.clone(), fast_low.clone()],
+            Duration::from_secs(5),
+        );
+
+        let headers = sync.sync_headers(&blocks[0].header, 0).await.unwrap();
+        assert_eq!(headers.len(), 12);
+
+        let mut connected = Vec::new();
+        let mut reports = Vec::new();
+        let tip = sync
+            .download_blocks(
+                &headers,
+                1,
+                |height, block| {
+                    assert_eq!(block.block_hash(), blocks[height as usize].block_hash());
+                    connected.push(height);
+                    Ok(())
+                },
+                |progress| reports.push(progress),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(tip, 12);
+        assert_eq!(connected, (1..=12).collect::<Vec<_>>());
+        assert!(!fast_high.served.lock().unwrap().is_empty());
+        assert!(!fast_low.served.lock().unwrap().is_empty());
+        let last = reports.last().unwrap();
+        assert_eq!((last.height, last.target_height), (12, 12));
+        assert_eq!(last.percent, 100.0);
+        assert!(last.blocks_per_sec > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_requests_reassigned() {
+        let blocks = chain(6);
+        let stalled = MockPeer::new("stalled", &blocks, |_| Duration::from_secs(10));
+        let healthy = MockPeer::new("healthy", &blocks, |_| Duration::ZERO);
+        let sync = block_sync(
+            vec![stalled.clone(), healthy.clone()],
+            Duration::from_millis(50),
+        );
+
+        let headers: Vec<_> = blocks[1..].iter().map(|b| b.header).collect();
+        let mut connected = Vec::new();
+        sync.download_blocks(
+            &headers,
+            1,
+            |height, _| {
+                connected.push(height);
+                Ok(())
+            },
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(connected, vec![1, 2, 3, 4, 5, 6]);
+        assert!(stalled.served.lock().unwrap().is_empty());
+        assert_eq!(healthy.served.lock().unwrap().len(), 6);
+
+        // With only a stalled peer the download fails rather than hanging
+        let sync = block_sync(vec![stalled], Duration::from_millis(50));
+        assert!(sync
+            .download_blocks(&headers, 1, |_, _| Ok(()), |_| {})
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_headers_rejected() {
+        let blocks = chain(3);
+        let mut forged = blocks.clone();
+        forged[2].header.prev_blockhash = BlockHash::all_zeros();
+        let liar = MockPeer::new("liar", &forged, |_| Duration::ZERO);
+        let honest = MockPeer::new("honest", &blocks, |_| Duration::ZERO);
+
+        let sync = block_sync(vec![liar.clone()], Duration::from_secs(1));
+        assert!(sync.sync_headers(&blocks[0].header, 0).await.is_err());
+
+        let sync = block_sync(vec![liar, honest], Duration::from_secs(1));
+        let headers = sync.sync_headers(&blocks[0].header, 0).await.unwrap();
+        assert_eq!(headers.last().unwrap().block_hash(), blocks[3].block_hash());
+    }
+}
@@ -14,6 +14,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::bitcoin::error::BitcoinError;
+use crate::security::rng::secure_rng;
 
 /// Errors that can occur during Taproot operations
 #[derive(Debug)]
@@ -219,8 +220,7 @@ impl TaprootAsset {
 /// - BIP-341/342 (Taproot)
 pub fn generate_keypair() -> Result<(SecretKey, bitcoin::key::XOnlyPublicKey), TaprootError> {
     let secp = Secp256k1::new();
-    let mut rng = rand::thread_rng();
-    let (secret_key, _) = secp.generate_keypair(&mut rng);
+    let (secret_key, _) = secp.generate_keypair(&mut secure_rng());
 
     // Convert to x-only public key for Taproot
     let x_only = secret_key.x_only_public_key(&secp);
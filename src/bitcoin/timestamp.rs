@@ -0,0 +1,453 @@
+// [AIR-3][AIS-3][BPC-3] OpenTimestamps-style Bitcoin anchoring
+//
+// Proves that data existed at some point in time by committing its SHA-256
+// hash to a Bitcoin transaction. Hashes anchored together are aggregated into
+// a merkle tree and only the root is committed, in a 32-byte OP_RETURN output
+// of a transaction funded from a P2WPKH key, so one transaction timestamps any
+// number of hashes. A proof carries the path from its hash to the root and
+// the anchor transaction. It is pending until upgraded with the block that
+// confirms the transaction, which adds the block hash, height and the merkle
+// branch of the transaction; verifying an upgraded proof then only needs that
+// block's header.
+
+use crate::bitcoin::chain_source::{ChainSource, ChainUtxo};
+use crate::bitcoin::spv::{verify_merkle_proof, MerkleProof};
+use crate::bitcoin::weight::{fee_for, COMPRESSED_KEY_SIZE, ECDSA_SIGNATURE_SIZE};
+use crate::{AnyaError, AnyaResult};
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::{All, Message, Secp256k1, SecretKey};
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    ecdsa, Amount, Block, BlockHash, CompressedPublicKey, FeeRate, ScriptBuf, Sequence,
+    Transaction, TxIn, TxOut, Witness,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Default confirmation target of anchor transactions, in blocks
+pub const DEFAULT_CONFIRMATION_TARGET: u16 = 6;
+
+/// [AIR-3][BPC-3] Step from a node of the aggregation tree to its parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MerkleStep {
+    /// Parent is SHA-256(sibling || node)
+    Prepend([u8; 32]),
+    /// Parent is SHA-256(node || sibling)
+    Append([u8; 32]),
+}
+
+impl MerkleStep {
+    fn apply(&self, node: [u8; 32]) -> [u8; 32] {
+        let (left, right) = match self {
+            Self::Prepend(sibling) => (*sibling, node),
+            Self::Append(sibling) => (node, *sibling),
+        };
+        sha256::Hash::hash(&[left, right].concat()).to_byte_array()
+    }
+}
+
+/// [AIR-3][BPC-3] Block confirming an anchor transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockAttestation {
+    pub block_hash: BlockHash,
+    pub height: u32,
+    /// Branch linking the anchor transaction to the block's merkle root
+    pub tx_proof: MerkleProof,
+}
+
+/// [AIR-3][AIS-3][BPC-3] Proof that a hash was committed to the chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimestampProof {
+    /// Path from the data hash to the anchored root, leaf first
+    pub path: Vec<MerkleStep>,
+    pub anchor_tx: Transaction,
+    /// Confirming block, `None` while pending
+    pub attestation: Option<BlockAttestation>,
+}
+
+impl TimestampProof {
+    /// Root reached by following the path up from `data_hash`
+    pub fn root(&self, data_hash: [u8; 32]) -> [u8; 32] {
+        self.path
+            .iter()
+            .fold(data_hash, |node, step| step.apply(node))
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.attestation.is_none()
+    }
+
+    /// Attest the proof with `block` at `height` if it contains the anchor
+    /// transaction, returning whether it did
+    pub fn upgrade(&mut self, block: &Block, height: u32) -> bool {
+        let Some(tx_proof) = MerkleProof::from_block(block, &self.anchor_tx.compute_txid()) else {
+            return false;
+        };
+        self.attestation = Some(BlockAttestation {
+            block_hash: block.block_hash(),
+            height,
+            tx_proof,
+        });
+        true
+    }
+}
+
+/// Merkle root of `hashes` and the path from each hash to it, in order
+///
+/// A node without a sibling moves up a level unchanged.
+pub fn aggregate(hashes: &[[u8; 32]]) -> ([u8; 32], Vec<Vec<MerkleStep>>) {
+    let mut paths = vec![Vec::new(); hashes.len()];
+    // Nodes of the current level with the leaves below each
+    let mut level: Vec<([u8; 32], Vec<usize>)> = hashes
+        .iter()
+        .enumerate()
+        .map(|(leaf, hash)| (*hash, vec![leaf]))
+        .collect();
+    while level.len() > 1 {
+        let mut parents = Vec::with_capacity((level.len() + 1) / 2);
+        let mut nodes = level.into_iter();
+        while let Some((left, mut left_leaves)) = nodes.next() {
+            let Some((right, right_leaves)) = nodes.next() else {
+                parents.push((left, left_leaves));
+                break;
+            };
+            for &leaf in &left_leaves {
+                paths[leaf].push(MerkleStep::Append(right));
+            }
+            for &leaf in &right_leaves {
+                paths[leaf].push(MerkleStep::Prepend(left));
+            }
+            left_leaves.extend(right_leaves);
+            let parent = sha256::Hash::hash(&[left, right].concat()).to_byte_array();
+            parents.push((parent, left_leaves));
+        }
+        level = parents;
+    }
+    let root = level.first().map_or([0; 32], |(root, _)| *root);
+    (root, paths)
+}
+
+/// Root committed by the OP_RETURN output of `tx`, if any
+pub fn anchored_root(tx: &Transaction) -> Option<[u8; 32]> {
+    tx.output.iter().find_map(|txout| {
+        match txout.script_pubkey.as_bytes() {
+            // OP_RETURN OP_PUSHBYTES_32 <root>
+            [0x6a, 0x20, root @ ..] => root.try_into().ok(),
+            _ => None,
+        }
+    })
+}
+
+/// [AIR-3][AIS-3][BPC-3] Check `proof` for `data_hash` against the chain
+///
+/// Returns the height of the confirming block, or `None` for a pending proof
+/// whose anchor transaction commits to the hash. Fails if the proof is not
+/// for `data_hash` or the attested block does not contain the transaction.
+pub async fn verify_timestamp(
+    chain: &dyn ChainSource,
+    proof: &TimestampProof,
+    data_hash: [u8; 32],
+) -> AnyaResult<Option<u32>> {
+    if anchored_root(&proof.anchor_tx) != Some(proof.root(data_hash)) {
+        return Err(AnyaError::Security(
+            "Timestamp proof does not commit to the data hash".to_string(),
+        ));
+    }
+    let Some(attestation) = &proof.attestation else {
+        return Ok(None);
+    };
+    let header = chain.get_header(&attestation.block_hash).await?;
+    verify_merkle_proof(
+        &proof.anchor_tx.compute_txid(),
+        &attestation.tx_proof,
+        &header,
+    )?;
+    Ok(Some(attestation.height))
+}
+
+/// [AIR-3][AIS-3][BPC-3] Anchors hashes in transactions funded by one key
+pub struct TimestampAnchor {
+    chain: Arc<dyn ChainSource>,
+    /// Key of the P2WPKH outputs paying anchor fees and receiving change
+    key: SecretKey,
+    fee_rate: Option<FeeRate>,
+    confirmation_target: u16,
+    secp: Secp256k1<All>,
+}
+
+impl TimestampAnchor {
+    pub fn new(chain: Arc<dyn ChainSource>, key: SecretKey) -> Self {
+        Self {
+            chain,
+            key,
+            fee_rate: None,
+            confirmation_target: DEFAULT_CONFIRMATION_TARGET,
+            secp: Secp256k1::new(),
+        }
+    }
+
+    /// Pay `fee_rate` instead of the chain source estimate
+    pub fn with_fee_rate(mut self, fee_rate: FeeRate) -> Self {
+        self.fee_rate = Some(fee_rate);
+        self
+    }
+
+    /// Blocks within which anchor transactions should confirm
+    pub fn with_confirmation_target(mut self, blocks: u16) -> Self {
+        self.confirmation_target = blocks;
+        self
+    }
+
+    /// Script of the outputs that fund anchors
+    pub fn funding_script(&self) -> ScriptBuf {
+        let pubkey = CompressedPublicKey(self.key.public_key(&self.secp));
+        ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash())
+    }
+
+    /// Anchor `data_hash` on its own; see [`TimestampAnchor::anchor_hashes`]
+    pub async fn anchor_hash(&self, data_hash: [u8; 32]) -> AnyaResult<TimestampProof> {
+        let mut proofs = self.anchor_hashes(&[data_hash]).await?;
+        Ok(proofs.remove(0))
+    }
+
+    /// Commit the merkle root of `hashes` in one broadcast transaction
+    ///
+    /// Returns a pending proof per hash, in order.
+    pub async fn anchor_hashes(&self, hashes: &[[u8; 32]]) -> AnyaResult<Vec<TimestampProof>> {
+        if hashes.is_empty() {
+            return Err(AnyaError::InvalidInput("No hashes to anchor".to_string()));
+        }
+        let (root, paths) = aggregate(hashes);
+        let tx = self.anchor_transaction(root).await?;
+        let txid = self.chain.broadcast(&tx).await?;
+        log::info!(
+            "Anchored {} hashes under root {} in {txid}",
+            hashes.len(),
+            hex::encode(root)
+        );
+
+        Ok(paths
+            .into_iter()
+            .map(|path| TimestampProof {
+                path,
+                anchor_tx: tx.clone(),
+                attestation: None,
+            })
+            .collect())
+    }
+
+    /// Signed transaction committing `root`, funded by the largest outputs first
+    async fn anchor_transaction(&self, root: [u8; 32]) -> AnyaResult<Transaction> {
+        let fee_rate = match self.fee_rate {
+            Some(fee_rate) => fee_rate,
+            None => self.chain.estimate_fee(self.confirmation_target).await?,
+        };
+        let script = self.funding_script();
+        let mut utxos = self.chain.get_utxos(&script).await?;
+        utxos.sort_by_key(|utxo| std::cmp::Reverse(utxo.txout.value));
+
+        let dust = script.minimal_non_dust();
+        let mut available = Amount::ZERO;
+        let mut required = dust;
+        for count in 1..=utxos.len() {
+            let inputs = &utxos[..count];
+            available = inputs.iter().map(|utxo| utxo.txout.value).sum();
+            let mut tx = self.unsigned(root, inputs);
+            let fee = signed_fee(fee_rate, &tx);
+            required = fee + dust;
+            let Some(change) = available.checked_sub(fee).filter(|change| *change >= dust) else {
+                continue;
+            };
+            tx.output[1].value = change;
+            self.sign(&mut tx, inputs)?;
+            return Ok(tx);
+        }
+        Err(AnyaError::Bitcoin(format!(
+            "Anchor outputs worth {available} cannot pay the {required} needed"
+        )))
+    }
+
+    /// Transaction spending `inputs` to the OP_RETURN commitment and change
+    fn unsigned(&self, root: [u8; 32], inputs: &[ChainUtxo]) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs
+                .iter()
+                .map(|utxo| TxIn {
+                    previous_output: utxo.outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: vec![
+                TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: ScriptBuf::new_op_return(root),
+                },
+                TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: self.funding_script(),
+                },
+            ],
+        }
+    }
+
+    fn sign(&self, tx: &mut Transaction, inputs: &[ChainUtxo]) -> AnyaResult<()> {
+        let pubkey = self.key.public_key(&self.secp);
+        let mut witnesses = Vec::with_capacity(inputs.len());
+        let mut cache = SighashCache::new(&*tx);
+        for (index, utxo) in inputs.iter().enumerate() {
+            let sighash = cache
+                .p2wpkh_signature_hash(
+                    index,
+                    &utxo.txout.script_pubkey,
+                    utxo.txout.value,
+                    EcdsaSighashType::All,
+                )
+                .map_err(|e| AnyaError::Bitcoin(format!("Signing failed: {e}")))?;
+            let message = Message::from_digest(sighash.to_byte_array());
+            let signature = ecdsa::Signature {
+                signature: self.secp.sign_ecdsa(&message, &self.key),
+                sighash_type: EcdsaSighashType::All,
+            };
+            witnesses.push(Witness::p2wpkh(&signature, &pubkey));
+        }
+        for (input, witness) in tx.input.iter_mut().zip(witnesses) {
+            input.witness = witness;
+        }
+        Ok(())
+    }
+}
+
+/// Fee of `tx` at `fee_rate` once its P2WPKH inputs are signed
+fn signed_fee(fee_rate: FeeRate, tx: &Transaction) -> Amount {
+    let mut signed = tx.clone();
+    for input in &mut signed.input {
+        input.witness = Witness::from_slice(&[
+            [0; ECDSA_SIGNATURE_SIZE].as_slice(),
+            [0; COMPRESSED_KEY_SIZE].as_slice(),
+        ]);
+    }
+    fee_for(fee_rate, signed.weight())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::chain_source::MockChainSource;
+    use bitcoin::block::{Header as BlockHeader, Version as BlockVersion};
+    use bitcoin::{CompactTarget, OutPoint, TxMerkleNode, Txid};
+
+    fn hash(data: &str) -> [u8; 32] {
+        sha256::Hash::hash(data.as_bytes()).to_byte_array()
+    }
+
+    /// Regtest-difficulty block of `txdata` on top of the zero hash
+    fn mine(txdata: Vec<Transaction>) -> Block {
+        let mut block = Block {
+            header: BlockHeader {
+                version: BlockVersion::TWO,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: 1_700_000_000,
+                bits: CompactTarget::from_consensus(0x207fffff),
+                nonce: 0,
+            },
+            txdata,
+        };
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        while block.header.validate_pow(block.header.target()).is_err() {
+            block.header.nonce += 1;
+        }
+        block
+    }
+
+    fn filler(n: u8) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::from_byte_array([n; 32]), 0),
+                ..Default::default()
+            }],
+            output: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_paths_lead_to_root() {
+        let hashes: Vec<[u8; 32]> = ["a", "b", "c", "d", "e"].map(hash).to_vec();
+        let (root, paths) = aggregate(&hashes);
+        for (hash, path) in hashes.iter().zip(&paths) {
+            let proof = TimestampProof {
+                path: path.clone(),
+                anchor_tx: filler(0),
+                attestation: None,
+            };
+            assert_eq!(proof.root(*hash), root);
+        }
+        // The odd fifth leaf joins the tree at the top
+        assert_eq!(paths[4].len(), 1);
+        assert_eq!(aggregate(&hashes[..1]), (hashes[0], vec![Vec::new()]));
+    }
+
+    #[tokio::test]
+    async fn test_anchor_upgrade_and_verify() {
+        let chain = Arc::new(MockChainSource::new());
+        chain.set_fee_rate(FeeRate::from_sat_per_vb(2).unwrap());
+        let anchor = TimestampAnchor::new(chain.clone(), SecretKey::from_slice(&[7; 32]).unwrap());
+        let hashes = [hash("invoice"), hash("contract"), hash("receipt")];
+        assert!(anchor.anchor_hashes(&hashes).await.is_err());
+
+        chain.add_utxo(ChainUtxo {
+            outpoint: OutPoint::new(Txid::from_byte_array([1; 32]), 0),
+            txout: TxOut {
+                value: Amount::from_sat(20_000),
+                script_pubkey: anchor.funding_script(),
+            },
+            height: Some(100),
+        });
+        let mut proofs = anchor.anchor_hashes(&hashes).await.unwrap();
+        let tx = chain.broadcasts().pop().unwrap();
+        assert_eq!(proofs.len(), 3);
+        assert!(proofs.iter().all(|proof| proof.anchor_tx == tx));
+        assert_eq!(anchored_root(&tx), Some(aggregate(&hashes).0));
+        // One P2WPKH input, the commitment and change weigh 610 WU: 305 sats
+        // at 2 sat/vB
+        assert_eq!(tx.output[1].value, Amount::from_sat(20_000 - 305));
+
+        assert_eq!(
+            verify_timestamp(chain.as_ref(), &proofs[1], hashes[1])
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(verify_timestamp(chain.as_ref(), &proofs[1], hashes[0])
+            .await
+            .is_err());
+
+        // A block without the anchor leaves the proof pending
+        assert!(!proofs[1].upgrade(&mine(vec![filler(1)]), 101));
+        let block = mine(vec![filler(1), tx, filler(2)]);
+        chain.add_block(block.clone(), 101);
+        assert!(proofs[1].upgrade(&block, 101));
+        assert_eq!(
+            verify_timestamp(chain.as_ref(), &proofs[1], hashes[1])
+                .await
+                .unwrap(),
+            Some(101)
+        );
+
+        // The attestation must match the block's merkle root
+        let attestation = proofs[1].attestation.as_mut().unwrap();
+        attestation.tx_proof.position = 2;
+        assert!(verify_timestamp(chain.as_ref(), &proofs[1], hashes[1])
+            .await
+            .is_err());
+    }
+}
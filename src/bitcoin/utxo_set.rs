@@ -0,0 +1,606 @@
+// [AIR-3][AIS-3][BPC-3][RES-3] UTXO set with assumeutxo-style snapshots
+//
+// The unspent outputs of the active chain, updated block by block with undo
+// data kept for recent blocks so reorgs and snapshot exports below the tip
+// can rewind. A snapshot is the full set at one block together with a
+// SHA-256 commitment over its base block and every coin in outpoint order.
+//
+// A node can bootstrap from a snapshot instead of validating from genesis,
+// but only if the snapshot hashes to a commitment it already trusts, from
+// its configuration or built in. History up to the snapshot is then
+// validated in the background by a `BackgroundValidator`, which rebuilds the
+// set from genesis and checks it reaches the same commitment.
+
+use crate::{AnyaError, AnyaResult};
+use bitcoin::consensus::{Decodable, Encodable};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::io;
+use bitcoin::{Block, BlockHash, OutPoint, TxOut};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Leading bytes of a snapshot file
+const SNAPSHOT_MAGIC: [u8; 8] = *b"anyautxo";
+
+/// Blocks of undo data kept by default
+pub const DEFAULT_MAX_UNDO: usize = 288;
+
+/// [AIR-3][BPC-3] An unspent output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coin {
+    pub txout: TxOut,
+    /// Height of the block that created the output
+    pub height: u32,
+    pub is_coinbase: bool,
+}
+
+impl Coin {
+    fn encode<W: io::Write + ?Sized>(&self, outpoint: &OutPoint, writer: &mut W) -> io::Result<()> {
+        outpoint.consensus_encode(writer)?;
+        ((self.height << 1) | u32::from(self.is_coinbase)).consensus_encode(writer)?;
+        self.txout.consensus_encode(writer)?;
+        Ok(())
+    }
+
+    fn decode<R: io::BufRead + ?Sized>(reader: &mut R) -> AnyaResult<(OutPoint, Self)> {
+        let outpoint = OutPoint::consensus_decode(reader).map_err(decode_error)?;
+        let code = u32::consensus_decode(reader).map_err(decode_error)?;
+        let txout = TxOut::consensus_decode(reader).map_err(decode_error)?;
+        let coin = Coin {
+            txout,
+            height: code >> 1,
+            is_coinbase: code & 1 == 1,
+        };
+        Ok((outpoint, coin))
+    }
+}
+
+/// Changes a connected block made, to undo it
+#[derive(Debug, Clone)]
+struct BlockUndo {
+    prev_block: BlockHash,
+    spent: Vec<(OutPoint, Coin)>,
+    created: Vec<OutPoint>,
+}
+
+/// [AIR-3][AIS-3][BPC-3] Unspent outputs of the active chain
+#[derive(Debug, Clone)]
+pub struct UtxoSet {
+    coins: BTreeMap<OutPoint, Coin>,
+    /// Tip the set is at, `None` before the genesis block is connected
+    tip: Option<(u32, BlockHash)>,
+    undo: VecDeque<BlockUndo>,
+    max_undo: usize,
+}
+
+impl Default for UtxoSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UtxoSet {
+    /// Empty set, before the genesis block
+    pub fn new() -> Self {
+        Self::with_max_undo(DEFAULT_MAX_UNDO)
+    }
+
+    /// Empty set keeping undo data for the last `max_undo` blocks
+    pub fn with_max_undo(max_undo: usize) -> Self {
+        Self {
+            coins: BTreeMap::new(),
+            tip: None,
+            undo: VecDeque::new(),
+            max_undo,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.coins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coins.is_empty()
+    }
+
+    pub fn get(&self, outpoint: &OutPoint) -> Option<&Coin> {
+        self.coins.get(outpoint)
+    }
+
+    /// Height and hash of the last connected block
+    pub fn tip(&self) -> Option<(u32, BlockHash)> {
+        self.tip
+    }
+
+    /// Apply `block` at `height`, which must extend the current tip
+    ///
+    /// Fails without changing the set if the block spends an output that is
+    /// not in it. Scripts are not checked here.
+    pub fn connect_block(&mut self, block: &Block, height: u32) -> AnyaResult<()> {
+        let expected = self.tip.map_or(0, |(tip, _)| tip + 1);
+        if height != expected {
+            return Err(AnyaError::Bitcoin(format!(
+                "Block at height {height} does not extend the UTXO set, expected height {expected}"
+            )));
+        }
+        if let Some((_, tip_hash)) = self.tip {
+            if block.header.prev_blockhash != tip_hash {
+                return Err(AnyaError::Bitcoin(format!(
+                    "Block {} does not build on UTXO set tip {tip_hash}",
+                    block.block_hash()
+                )));
+            }
+        }
+
+        let mut created_here = HashSet::new();
+        let mut undo = BlockUndo {
+            prev_block: block.header.prev_blockhash,
+            spent: Vec::new(),
+            created: Vec::new(),
+        };
+        for tx in &block.txdata {
+            if !tx.is_coinbase() {
+                for input in &tx.input {
+                    match self.coins.remove(&input.previous_output) {
+                        // Outputs created earlier in the block vanish on undo anyway
+                        Some(_) if created_here.contains(&input.previous_output) => {}
+                        Some(coin) => undo.spent.push((input.previous_output, coin)),
+                        None => {
+                            let missing = input.previous_output;
+                            self.revert(undo);
+                            return Err(AnyaError::Bitcoin(format!(
+                                "Block at height {height} spends missing output {missing}"
+                            )));
+                        }
+                    }
+                }
+            }
+            let txid = tx.compute_txid();
+            for (vout, txout) in tx.output.iter().enumerate() {
+                // Provably unspendable outputs never enter the set
+                if txout.script_pubkey.is_op_return() {
+                    continue;
+                }
+                let outpoint = OutPoint::new(txid, vout as u32);
+                let coin = Coin {
+                    txout: txout.clone(),
+                    height,
+                    is_coinbase: tx.is_coinbase(),
+                };
+                if let Some(replaced) = self.coins.insert(outpoint, coin) {
+                    // BIP-30: a duplicate coinbase overwrote an unspent output
+                    undo.spent.push((outpoint, replaced));
+                }
+                created_here.insert(outpoint);
+                undo.created.push(outpoint);
+            }
+        }
+
+        self.tip = Some((height, block.block_hash()));
+        self.undo.push_back(undo);
+        while self.undo.len() > self.max_undo {
+            self.undo.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Undo the tip block, if its undo data is still kept
+    pub fn disconnect_tip(&mut self) -> AnyaResult<()> {
+        let (height, _) = self
+            .tip
+            .ok_or_else(|| AnyaError::Bitcoin("UTXO set has no blocks".to_string()))?;
+        let undo = self.undo.pop_back().ok_or_else(|| {
+            AnyaError::Bitcoin(format!("No undo data for block at height {height}"))
+        })?;
+        self.tip = height.checked_sub(1).map(|prev| (prev, undo.prev_block));
+        self.revert(undo);
+        Ok(())
+    }
+
+    fn revert(&mut self, undo: BlockUndo) {
+        for outpoint in &undo.created {
+            self.coins.remove(outpoint);
+        }
+        self.coins.extend(undo.spent);
+    }
+
+    /// SHA-256 over the tip and every coin in outpoint order
+    pub fn commitment(&self) -> AnyaResult<sha256::Hash> {
+        let (height, hash) = self.tip.ok_or_else(|| {
+            AnyaError::Bitcoin("Cannot commit to a UTXO set with no blocks".to_string())
+        })?;
+        compute_commitment(height, hash, &self.coins)
+    }
+
+    /// Snapshot the set as it was at `at_height`
+    ///
+    /// Heights below the tip are reached by rewinding a copy of the set, so
+    /// they must be within the kept undo data.
+    pub fn export_snapshot(&self, at_height: u32) -> AnyaResult<SnapshotFile> {
+        let mut set = self.clone();
+        while set.tip.is_some_and(|(height, _)| height > at_height) {
+            set.disconnect_tip()?;
+        }
+        let (base_height, base_block_hash) = match set.tip {
+            Some((height, hash)) if height == at_height => (height, hash),
+            _ => {
+                return Err(AnyaError::Bitcoin(format!(
+                    "UTXO set has not reached height {at_height}"
+                )))
+            }
+        };
+        Ok(SnapshotFile {
+            base_height,
+            base_block_hash,
+            commitment: set.commitment()?,
+            coins: set.coins,
+        })
+    }
+
+    /// Bootstrap a set from `snapshot`, refusing it unless its contents hash
+    /// to `expected_commitment`
+    ///
+    /// The returned set has no undo data, so it cannot be rewound below the
+    /// snapshot's base block.
+    pub fn import_snapshot(
+        snapshot: SnapshotFile,
+        expected_commitment: sha256::Hash,
+    ) -> AnyaResult<Self> {
+        let commitment = compute_commitment(
+            snapshot.base_height,
+            snapshot.base_block_hash,
+            &snapshot.coins,
+        )?;
+        if commitment != expected_commitment {
+            return Err(AnyaError::Security(format!(
+                "UTXO snapshot at height {} hashes to {commitment}, expected {expected_commitment}",
+                snapshot.base_height
+            )));
+        }
+        log::info!(
+            "Loaded UTXO snapshot with {} coins at height {} ({})",
+            snapshot.coins.len(),
+            snapshot.base_height,
+            snapshot.base_block_hash
+        );
+        Ok(Self {
+            coins: snapshot.coins,
+            tip: Some((snapshot.base_height, snapshot.base_block_hash)),
+            ..Self::new()
+        })
+    }
+}
+
+fn compute_commitment(
+    height: u32,
+    block_hash: BlockHash,
+    coins: &BTreeMap<OutPoint, Coin>,
+) -> AnyaResult<sha256::Hash> {
+    let mut engine = sha256::Hash::engine();
+    let write = |engine: &mut sha256::HashEngine| -> io::Result<()> {
+        block_hash.consensus_encode(engine)?;
+        height.consensus_encode(engine)?;
+        (coins.len() as u64).consensus_encode(engine)?;
+        for (outpoint, coin) in coins {
+            coin.encode(outpoint, engine)?;
+        }
+        Ok(())
+    };
+    write(&mut engine).map_err(|e| AnyaError::System(format!("Hashing UTXO set: {e}")))?;
+    Ok(sha256::Hash::from_engine(engine))
+}
+
+fn decode_error(e: bitcoin::consensus::encode::Error) -> AnyaError {
+    AnyaError::Bitcoin(format!("Invalid UTXO snapshot: {e}"))
+}
+
+/// [AIR-3][BPC-3] The UTXO set at one block, as exported or loaded from disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFile {
+    pub base_height: u32,
+    pub base_block_hash: BlockHash,
+    /// Commitment recorded by the exporter; not trusted on import
+    pub commitment: sha256::Hash,
+    coins: BTreeMap<OutPoint, Coin>,
+}
+
+impl SnapshotFile {
+    pub fn coin_count(&self) -> usize {
+        self.coins.len()
+    }
+
+    /// Write the snapshot to `path`
+    pub fn write(&self, path: impl AsRef<Path>) -> AnyaResult<()> {
+        let path = path.as_ref();
+        let io_error = |e: std::io::Error| {
+            AnyaError::System(format!("Writing UTXO snapshot {}: {e}", path.display()))
+        };
+        let mut file = BufWriter::new(File::create(path).map_err(io_error)?);
+        file.write_all(&SNAPSHOT_MAGIC).map_err(io_error)?;
+        let mut encode = || -> io::Result<()> {
+            let writer = io::from_std_mut(&mut file);
+            self.base_block_hash.consensus_encode(writer)?;
+            self.base_height.consensus_encode(writer)?;
+            self.commitment.as_byte_array().consensus_encode(writer)?;
+            (self.coins.len() as u64).consensus_encode(writer)?;
+            for (outpoint, coin) in &self.coins {
+                coin.encode(outpoint, writer)?;
+            }
+            Ok(())
+        };
+        encode().map_err(|e| io_error(e.into()))?;
+        file.flush().map_err(io_error)
+    }
+
+    /// Read a snapshot written by [`SnapshotFile::write`]
+    pub fn read(path: impl AsRef<Path>) -> AnyaResult<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| {
+            AnyaError::System(format!("Reading UTXO snapshot {}: {e}", path.display()))
+        })?;
+        let mut file = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic).map_err(|e| {
+            AnyaError::Bitcoin(format!("Invalid UTXO snapshot {}: {e}", path.display()))
+        })?;
+        if magic != SNAPSHOT_MAGIC {
+            return Err(AnyaError::Bitcoin(format!(
+                "{} is not a UTXO snapshot",
+                path.display()
+            )));
+        }
+        let reader = io::from_std_mut(&mut file);
+        let base_block_hash = BlockHash::consensus_decode(reader).map_err(decode_error)?;
+        let base_height = u32::consensus_decode(reader).map_err(decode_error)?;
+        let commitment = sha256::Hash::from_byte_array(
+            <[u8; 32]>::consensus_decode(reader).map_err(decode_error)?,
+        );
+        let count = u64::consensus_decode(reader).map_err(decode_error)?;
+        let mut coins = BTreeMap::new();
+        for _ in 0..count {
+            let (outpoint, coin) = Coin::decode(reader)?;
+            coins.insert(outpoint, coin);
+        }
+        Ok(Self {
+            base_height,
+            base_block_hash,
+            commitment,
+            coins,
+        })
+    }
+}
+
+/// [AIR-3][BPC-3] Progress of validating history below a snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackgroundValidationProgress {
+    /// Height of the last block validated, if any
+    pub validated_height: Option<u32>,
+    /// Base height of the snapshot being validated
+    pub target_height: u32,
+    /// The rebuilt set matched the snapshot's commitment
+    pub complete: bool,
+}
+
+impl BackgroundValidationProgress {
+    /// Share of the history validated, from 0 to 100
+    pub fn percent(&self) -> f64 {
+        match self.validated_height {
+            Some(height) => f64::from(height + 1) / f64::from(self.target_height + 1) * 100.0,
+            None => 0.0,
+        }
+    }
+}
+
+/// [AIR-3][AIS-3][BPC-3] Rebuilds the UTXO set from genesis to confirm a snapshot
+pub struct BackgroundValidator {
+    set: UtxoSet,
+    target_height: u32,
+    expected_commitment: sha256::Hash,
+    complete: bool,
+}
+
+impl BackgroundValidator {
+    /// Validate history up to the snapshot at `target_height` committing to
+    /// `expected_commitment`
+    pub fn new(target_height: u32, expected_commitment: sha256::Hash) -> Self {
+        Self {
+            // The chain below the snapshot is not reorganized
+            set: UtxoSet::with_max_undo(0),
+            target_height,
+            expected_commitment,
+            complete: false,
+        }
+    }
+
+    pub fn progress(&self) -> BackgroundValidationProgress {
+        BackgroundValidationProgress {
+            validated_height: self.set.tip().map(|(height, _)| height),
+            target_height: self.target_height,
+            complete: self.complete,
+        }
+    }
+
+    /// Connect the next historical block
+    ///
+    /// On reaching the snapshot's height, fails if the rebuilt set does not
+    /// match the snapshot, which means the snapshot must be discarded.
+    pub fn connect_block(
+        &mut self,
+        block: &Block,
+        height: u32,
+    ) -> AnyaResult<BackgroundValidationProgress> {
+        if height > self.target_height {
+            return Err(AnyaError::Bitcoin(format!(
+                "Background validation stops at the snapshot height {}",
+                self.target_height
+            )));
+        }
+        self.set.connect_block(block, height)?;
+        if height == self.target_height {
+            let commitment = self.set.commitment()?;
+            if commitment != self.expected_commitment {
+                return Err(AnyaError::Security(format!(
+                    "Background validation reached {commitment} at height {height}, \
+                     but the snapshot commits to {}",
+                    self.expected_commitment
+                )));
+            }
+            log::info!("Background validation confirmed the UTXO snapshot at height {height}");
+            self.complete = true;
+        }
+        Ok(self.progress())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::block::{Header, Version as BlockVersion};
+    use bitcoin::transaction::Version;
+    use bitcoin::{
+        Amount, CompactTarget, ScriptBuf, Sequence, Transaction, TxIn, TxMerkleNode, Witness,
+    };
+
+    fn coinbase(height: u32) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::from_bytes(height.to_le_bytes().to_vec()),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(50_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        }
+    }
+
+    fn spend(outpoint: OutPoint, outputs: u64) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                ..TxIn::default()
+            }],
+            output: (0..outputs)
+                .map(|i| TxOut {
+                    value: Amount::from_sat(1_000 + i),
+                    script_pubkey: ScriptBuf::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Ten blocks, each spending the previous block's coinbase into two outputs
+    fn chain() -> Vec<Block> {
+        let mut blocks: Vec<Block> = Vec::new();
+        for height in 0..10 {
+            let mut txdata = vec![coinbase(height)];
+            if let Some(prev) = blocks.last() {
+                txdata.push(spend(OutPoint::new(prev.txdata[0].compute_txid(), 0), 2));
+            }
+            blocks.push(Block {
+                header: Header {
+                    version: BlockVersion::ONE,
+                    prev_blockhash: blocks
+                        .last()
+                        .map_or(BlockHash::all_zeros(), |prev| prev.block_hash()),
+                    merkle_root: TxMerkleNode::all_zeros(),
+                    time: height,
+                    bits: CompactTarget::from_consensus(0x207fffff),
+                    nonce: 0,
+                },
+                txdata,
+            });
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_snapshot_round_trip_and_background_validation() {
+        let blocks = chain();
+        let mut set = UtxoSet::new();
+        for (height, block) in blocks.iter().enumerate() {
+            set.connect_block(block, height as u32).unwrap();
+        }
+        assert_eq!(set.len(), 1 + 9 * 2);
+
+        // Export below the tip by rewinding
+        let snapshot = set.export_snapshot(6).unwrap();
+        assert_eq!(snapshot.base_block_hash, blocks[6].block_hash());
+        assert_eq!(snapshot.coin_count(), 1 + 6 * 2);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("utxo-6.dat");
+        snapshot.write(&path).unwrap();
+        let loaded = SnapshotFile::read(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        let trusted = snapshot.commitment;
+        let mut imported = UtxoSet::import_snapshot(loaded, trusted).unwrap();
+        assert_eq!(imported.commitment().unwrap(), trusted);
+
+        // The bootstrapped set follows the chain to the same state
+        for (height, block) in blocks.iter().enumerate().skip(7) {
+            imported.connect_block(block, height as u32).unwrap();
+        }
+        assert_eq!(imported.commitment().unwrap(), set.commitment().unwrap());
+
+        let mut validator = BackgroundValidator::new(6, trusted);
+        let progress = validator.connect_block(&blocks[0], 0).unwrap();
+        assert_eq!(progress.validated_height, Some(0));
+        assert!(!progress.complete);
+        for (height, block) in blocks.iter().enumerate().take(7).skip(1) {
+            validator.connect_block(block, height as u32).unwrap();
+        }
+        let progress = validator.progress();
+        assert!(progress.complete);
+        assert_eq!(progress.percent(), 100.0);
+    }
+
+    #[test]
+    fn test_import_refuses_mismatched_commitment() {
+        let blocks = chain();
+        let mut set = UtxoSet::new();
+        for (height, block) in blocks.iter().take(3).enumerate() {
+            set.connect_block(block, height as u32).unwrap();
+        }
+        let snapshot = set.export_snapshot(2).unwrap();
+
+        // A commitment for different contents is refused
+        let other = set.export_snapshot(1).unwrap().commitment;
+        assert!(UtxoSet::import_snapshot(snapshot.clone(), other).is_err());
+
+        // So is a snapshot whose coins were altered after export
+        let mut tampered = snapshot.clone();
+        let (outpoint, _) = tampered.coins.pop_first().unwrap();
+        tampered.coins.insert(
+            outpoint,
+            Coin {
+                txout: TxOut {
+                    value: Amount::from_sat(21_000_000),
+                    script_pubkey: ScriptBuf::new(),
+                },
+                height: 0,
+                is_coinbase: true,
+            },
+        );
+        assert!(UtxoSet::import_snapshot(tampered, snapshot.commitment).is_err());
+
+        // Background validation rejects history that does not reach the
+        // snapshot's commitment
+        let mut validator = BackgroundValidator::new(2, other);
+        for (height, block) in blocks.iter().take(2).enumerate() {
+            validator.connect_block(block, height as u32).unwrap();
+        }
+        assert!(validator.connect_block(&blocks[2], 2).is_err());
+        assert!(!validator.progress().complete);
+    }
+}
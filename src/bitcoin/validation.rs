@@ -1,8 +1,9 @@
 //! Bitcoin transaction validation [AIS-3][BPC-3][DAO-3][PFM-3]
 
+use super::consensus_params::ConsensusParams;
 use super::protocol::{BPCLevel, BitcoinProtocol};
 // --- Required imports for Schnorr and merkle proof validation ---
-use bitcoin::Transaction;
+use bitcoin::{Block, Network, Transaction};
 use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::sync::{Arc, Mutex, RwLock};
@@ -201,6 +202,8 @@ pub struct TransactionValidator {
     pub maintains_consensus: bool,
     /// Verification history for historical compatibility testing
     verification_history: Arc<Mutex<Vec<VerificationRecord>>>,
+    /// Consensus parameters of the network being validated
+    consensus: ConsensusParams,
 }
 
 impl Default for TransactionValidator {
@@ -243,6 +246,7 @@ impl TransactionValidator {
             optimization_active: true,
             maintains_consensus: true,
             verification_history: Arc::new(Mutex::new(Vec::new())),
+            consensus: ConsensusParams::for_network(Network::Bitcoin),
         }
     }
 
@@ -257,6 +261,24 @@ impl TransactionValidator {
         validator
     }
 
+    /// Validate against the consensus parameters of `network`
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.consensus = ConsensusParams::for_network(network);
+        self
+    }
+
+    /// Consensus parameters in use
+    pub fn consensus_params(&self) -> &ConsensusParams {
+        &self.consensus
+    }
+
+    /// Validate the network-dependent rules of `block` at `height`
+    pub fn validate_block(&self, block: &Block, height: u32) -> Result<(), ValidationError> {
+        self.consensus
+            .check_block(block, height)
+            .map_err(|e| ValidationError::ConsensusError(e.to_string()))
+    }
+
     /// Toggle hardware optimization on or off
     pub fn with_optimization(mut self, enabled: bool) -> Self {
         self.optimization_active = enabled;
@@ -0,0 +1,328 @@
+// [AIR-3][AIS-3][BPC-3] Block version and soft-fork signal monitoring
+//
+// Tallies BIP-9 / BIP-8 version-bit signaling over the current retarget
+// period for a configured set of deployments, so operators can follow a soft
+// fork's progress toward its activation threshold. Bits that no configured
+// deployment uses are tallied over the last full period of blocks; when
+// enough miners signal one, it may be a soft fork this node does not know
+// about, and a warning is logged once until the signal falls away again.
+
+use crate::bitcoin::confirmations::ChainEvent;
+use crate::core::PrometheusMetrics;
+use bitcoin::block::Header;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Top three version bits marking a BIP-9 signaling block
+const VERSION_BITS_TOP_MASK: i32 = 0xE000_0000_u32 as i32;
+const VERSION_BITS_TOP_BITS: i32 = 0x2000_0000;
+/// Bits 0 to 28 are available to deployments
+const VERSION_BITS_COUNT: u8 = 29;
+
+/// Whether `version` signals for `bit` under BIP-9
+pub fn signals_bit(version: i32, bit: u8) -> bool {
+    bit < VERSION_BITS_COUNT
+        && version & VERSION_BITS_TOP_MASK == VERSION_BITS_TOP_BITS
+        && version & (1 << bit) != 0
+}
+
+/// [AIR-3][BPC-3] A soft fork deployed through version bits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoftForkDeployment {
+    pub name: String,
+    pub bit: u8,
+    /// Signaling blocks per period needed to lock in
+    pub threshold: u32,
+}
+
+/// [AIR-3][BPC-3] Settings for [`VersionBitsMonitor`]
+#[derive(Debug, Clone)]
+pub struct VersionBitsConfig {
+    /// Blocks per signaling period, the difficulty retarget interval
+    pub period: u32,
+    pub deployments: Vec<SoftForkDeployment>,
+    /// Share of the last period's blocks signaling an unknown bit above
+    /// which a warning is raised
+    pub unknown_bit_warning: f64,
+}
+
+impl Default for VersionBitsConfig {
+    fn default() -> Self {
+        Self {
+            period: 2016,
+            // Bitcoin Core's test deployment, never activated on mainnet
+            deployments: vec![SoftForkDeployment {
+                name: "testdummy".to_string(),
+                bit: 28,
+                threshold: 1815,
+            }],
+            unknown_bit_warning: 0.5,
+        }
+    }
+}
+
+/// [AIR-3][BPC-3] Signaling progress of a deployment in the current period
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoftForkStatus {
+    pub name: String,
+    pub bit: u8,
+    /// Height of the first block of the current period
+    pub period_start: u32,
+    /// Blocks of the current period seen so far
+    pub blocks: u32,
+    pub signaling: u32,
+    pub threshold: u32,
+}
+
+impl SoftForkStatus {
+    /// Whether enough blocks have signaled to lock in at the period's end
+    pub fn threshold_reached(&self) -> bool {
+        self.signaling >= self.threshold
+    }
+
+    /// Signaling blocks as a fraction of the threshold, capped at 1
+    pub fn progress(&self) -> f64 {
+        if self.threshold == 0 {
+            return 1.0;
+        }
+        (self.signaling as f64 / self.threshold as f64).min(1.0)
+    }
+}
+
+/// [AIR-3][AIS-3][BPC-3] Tracks block versions of the active chain
+pub struct VersionBitsMonitor {
+    config: VersionBitsConfig,
+    metrics: Option<Arc<Mutex<PrometheusMetrics>>>,
+    /// Height and version of up to a period of the most recent blocks
+    blocks: VecDeque<(u32, i32)>,
+    /// Unknown bits currently above the warning share
+    warned: BTreeSet<u8>,
+}
+
+impl VersionBitsMonitor {
+    pub fn new(config: VersionBitsConfig) -> Self {
+        Self {
+            config,
+            metrics: None,
+            blocks: VecDeque::new(),
+            warned: BTreeSet::new(),
+        }
+    }
+
+    /// Publish block version and signaling gauges to `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<Mutex<PrometheusMetrics>>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn handle_event(&mut self, event: &ChainEvent) {
+        match event {
+            ChainEvent::BlockConnected { block, height } => {
+                self.connect_header(&block.header, *height)
+            }
+            ChainEvent::BlockDisconnected { height, .. } => self.disconnect(*height),
+        }
+    }
+
+    /// Record `header` as the new tip at `height`
+    pub fn connect_header(&mut self, header: &Header, height: u32) {
+        // Anything at or above `height` was replaced by this block
+        while self.blocks.back().is_some_and(|&(h, _)| h >= height) {
+            self.blocks.pop_back();
+        }
+        self.blocks
+            .push_back((height, header.version.to_consensus()));
+        while self.blocks.len() > self.config.period.max(1) as usize {
+            self.blocks.pop_front();
+        }
+        self.update();
+    }
+
+    /// Remove the tip at `height`
+    pub fn disconnect(&mut self, height: u32) {
+        if self.blocks.back().is_some_and(|&(h, _)| h == height) {
+            self.blocks.pop_back();
+            self.update();
+        }
+    }
+
+    /// Signaling progress of every configured deployment in the current period
+    pub fn soft_fork_status(&self) -> Vec<SoftForkStatus> {
+        let period = self.config.period.max(1);
+        let period_start = self.blocks.back().map_or(0, |&(tip, _)| tip - tip % period);
+        let current: Vec<i32> = self
+            .blocks
+            .iter()
+            .filter(|&&(height, _)| height >= period_start)
+            .map(|&(_, version)| version)
+            .collect();
+
+        self.config
+            .deployments
+            .iter()
+            .map(|deployment| SoftForkStatus {
+                name: deployment.name.clone(),
+                bit: deployment.bit,
+                period_start,
+                blocks: current.len() as u32,
+                signaling: current
+                    .iter()
+                    .filter(|&&version| signals_bit(version, deployment.bit))
+                    .count() as u32,
+                threshold: deployment.threshold,
+            })
+            .collect()
+    }
+
+    /// Blocks signaling each bit no deployment uses, over the last period
+    pub fn unknown_signals(&self) -> BTreeMap<u8, u32> {
+        let known: BTreeSet<u8> = self.config.deployments.iter().map(|d| d.bit).collect();
+        let mut tally = BTreeMap::new();
+        for &(_, version) in &self.blocks {
+            for bit in (0..VERSION_BITS_COUNT).filter(|bit| !known.contains(bit)) {
+                if signals_bit(version, bit) {
+                    *tally.entry(bit).or_insert(0) += 1;
+                }
+            }
+        }
+        tally
+    }
+
+    /// Unknown bits signaled by more than the warning share of the last period
+    pub fn unknown_soft_forks(&self) -> Vec<u8> {
+        let period = self.config.period.max(1) as f64;
+        self.unknown_signals()
+            .into_iter()
+            .filter(|&(_, count)| count as f64 / period > self.config.unknown_bit_warning)
+            .map(|(bit, _)| bit)
+            .collect()
+    }
+
+    fn update(&mut self) {
+        let unknown: BTreeSet<u8> = self.unknown_soft_forks().into_iter().collect();
+        for bit in unknown.difference(&self.warned) {
+            log::warn!(
+                "Unknown version bit {bit} signaled by more than {:.0}% of recent blocks; \
+                 an unknown soft fork may be activating",
+                self.config.unknown_bit_warning * 100.0
+            );
+        }
+        self.warned = unknown;
+
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+        let mut metrics = metrics.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(&(_, version)) = self.blocks.back() {
+            metrics.set_gauge("block_version", version as f64);
+        }
+        for status in self.soft_fork_status() {
+            metrics.set_gauge(
+                &format!("softfork_{}_signaling", status.name),
+                status.signaling as f64,
+            );
+            metrics.set_gauge(
+                &format!("softfork_{}_progress", status.name),
+                status.progress(),
+            );
+        }
+        metrics.set_gauge("unknown_version_bits_signaling", self.warned.len() as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::block::Version;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{BlockHash, CompactTarget, TxMerkleNode};
+
+    fn header(version: i32) -> Header {
+        Header {
+            version: Version::from_consensus(version),
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time: 0,
+            bits: CompactTarget::from_consensus(0x207fffff),
+            nonce: 0,
+        }
+    }
+
+    #[test]
+    fn test_signals_bit() {
+        assert!(signals_bit(0x2000_0004, 2));
+        assert!(!signals_bit(0x2000_0004, 1));
+        // Top bits other than 001 are not BIP-9 signals
+        assert!(!signals_bit(0x6000_0004, 2));
+        assert!(!signals_bit(4, 2));
+        assert!(!signals_bit(0x3000_0000, 29));
+    }
+
+    #[test]
+    fn test_tallies_signaling_in_current_period() {
+        let config = VersionBitsConfig {
+            period: 144,
+            deployments: vec![SoftForkDeployment {
+                name: "testfork".to_string(),
+                bit: 1,
+                threshold: 108,
+            }],
+            unknown_bit_warning: 0.5,
+        };
+        let metrics = Arc::new(Mutex::new(PrometheusMetrics::new()));
+        let mut monitor = VersionBitsMonitor::new(config).with_metrics(metrics.clone());
+
+        // The previous period signals bit 1 throughout and is not counted
+        for height in 1_000..1_008 {
+            monitor.connect_header(&header(0x2000_0002), height);
+        }
+        // 100 blocks of the period starting at 1_008: 60 signal bit 1, and
+        // 60 signal the unknown bit 5
+        for i in 0..100 {
+            let mut version = 0x2000_0000;
+            if i < 60 {
+                version |= 1 << 1;
+            }
+            if i >= 40 {
+                version |= 1 << 5;
+            }
+            monitor.connect_header(&header(version), 1_008 + i);
+        }
+
+        let status = &monitor.soft_fork_status()[0];
+        assert_eq!(status.period_start, 1_008);
+        assert_eq!((status.blocks, status.signaling), (100, 60));
+        assert!(!status.threshold_reached());
+        assert_eq!(monitor.unknown_signals(), BTreeMap::from([(5, 60)]));
+        assert!(monitor.unknown_soft_forks().is_empty());
+
+        // Replace the last block with a non-signaling one
+        monitor.disconnect(1_107);
+        monitor.connect_header(&header(0x2000_0000), 1_107);
+        assert_eq!(monitor.soft_fork_status()[0].signaling, 60);
+        assert_eq!(monitor.unknown_signals()[&5], 59);
+
+        // Pushing bit 5 past half the period raises the unknown soft fork warning
+        for i in 100..144 {
+            monitor.connect_header(&header(0x2000_0022), 1_008 + i);
+        }
+        let status = &monitor.soft_fork_status()[0];
+        assert_eq!((status.blocks, status.signaling), (144, 104));
+        assert_eq!(monitor.unknown_soft_forks(), vec![5]);
+
+        let metrics = metrics.lock().unwrap();
+        assert_eq!(
+            metrics.get_gauge("softfork_testfork_signaling"),
+            Some(104.0)
+        );
+        assert_eq!(
+            metrics.get_gauge("softfork_testfork_progress"),
+            Some(104.0 / 108.0)
+        );
+        assert_eq!(
+            metrics.get_gauge("unknown_version_bits_signaling"),
+            Some(1.0)
+        );
+        assert_eq!(metrics.get_gauge("block_version"), Some(0x2000_0022 as f64));
+    }
+}
@@ -0,0 +1,339 @@
+// Portable wallet backups
+//
+// A backup is a JSON envelope:
+//
+//     { "format": "anya-wallet-backup", "version": 1, "payload": <EncryptedSeed> }
+//
+// `payload` is a keystore ciphertext (Argon2id + ChaCha20-Poly1305, see
+// `keystore`) of a JSON `WalletBackup`: the seed phrase, descriptors and
+// xpub, every derived address with its derivation path and labels, wallet
+// labels and BIP-329 labels, and the gap-limit indexes. UTXOs and
+// transaction history are not included, since a rescan recovers them;
+// transaction labels are carried as wallet labels keyed by txid. Everything but the envelope header is
+// encrypted and authenticated, so a wrong passphrase or any corruption of the
+// payload fails the import. Readers refuse versions newer than they know; a
+// version bump must keep older ones importable.
+
+use super::keystore::{EncryptedSeed, KdfParams};
+use super::{
+    AddressInfo, BitcoinWallet, CoinSelectionStrategy, FeeStrategy, Label, WalletConfig,
+    WalletError, WalletIndexes, WalletMetadata, WalletStorage, WalletType,
+};
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::Network;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroizing;
+
+/// Value of the envelope's `format` field
+pub const BACKUP_FORMAT: &str = "anya-wallet-backup";
+
+/// Newest backup format version this build writes and reads
+pub const BACKUP_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct BackupEnvelope {
+    format: String,
+    version: u32,
+    payload: EncryptedSeed,
+}
+
+/// Wallet contents carried by a backup
+///
+/// Not `Debug`, since it holds the seed phrase in the clear.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletBackup {
+    pub name: String,
+    pub network: Network,
+    pub wallet_type: WalletType,
+    /// BIP-39 mnemonic, absent for watch-only wallets
+    pub seed_phrase: Option<String>,
+    /// BIP-39 passphrase extending the mnemonic
+    pub seed_password: Option<String>,
+    pub receive_descriptor: String,
+    pub change_descriptor: String,
+    pub xpub: Option<String>,
+    pub master_fingerprint: Option<[u8; 4]>,
+    /// Derived addresses with their paths and labels, receive chain first,
+    /// in index order
+    pub addresses: Vec<AddressInfo>,
+    /// Wallet labels, including transaction labels keyed by txid
+    pub labels: BTreeMap<String, String>,
+    /// BIP-329 labels, absent from backups written before they existed
+    #[serde(default)]
+    pub bip329_labels: Vec<Label>,
+    pub gap_limit: u32,
+    pub receive_index: u32,
+    pub change_index: u32,
+    /// Last block synced, where a rescan after import can resume
+    pub last_block: Option<u32>,
+    pub coin_selection: CoinSelectionStrategy,
+    pub min_confirmations: u32,
+    pub fee_strategy: FeeStrategy,
+    pub created_at: u64,
+}
+
+impl BitcoinWallet {
+    /// Contents of a backup of this wallet, in a stable order
+    pub fn backup_contents(&self) -> WalletBackup {
+        let storage = self.storage.lock().unwrap_or_else(|e| e.into_inner());
+        let mut labels: BTreeMap<String, String> = storage
+            .transactions
+            .values()
+            .filter(|info| !info.labels.is_empty())
+            .map(|info| (info.txid.to_string(), info.labels.join(", ")))
+            .collect();
+        labels.extend(storage.metadata.labels.clone());
+        let mut addresses: Vec<AddressInfo> = storage.addresses.values().cloned().collect();
+        addresses.sort_by(|a, b| {
+            (a.is_change, a.index, &a.address).cmp(&(b.is_change, b.index, &b.address))
+        });
+        WalletBackup {
+            name: self.config.name.clone(),
+            network: self.config.network,
+            wallet_type: self.config.wallet_type.clone(),
+            seed_phrase: self.config.seed_phrase.clone(),
+            seed_password: self.config.password.clone(),
+            receive_descriptor: self.config.receive_descriptor.clone(),
+            change_descriptor: self.config.change_descriptor.clone(),
+            xpub: self.config.xpub.clone(),
+            master_fingerprint: storage.metadata.master_fingerprint,
+            addresses,
+            labels,
+            bip329_labels: storage.labels.clone().into(),
+            gap_limit: self.config.gap_limit,
+            receive_index: storage.indexes.receive_index,
+            change_index: storage.indexes.change_index,
+            last_block: storage.indexes.last_block,
+            coin_selection: self.config.coin_selection,
+            min_confirmations: self.config.min_confirmations,
+            fee_strategy: self.config.fee_strategy,
+            created_at: storage.metadata.created_at,
+        }
+    }
+
+    /// [AIS-3] Export the wallet as a backup encrypted under `passphrase`
+    pub fn export_backup(&self, passphrase: &str) -> Result<Vec<u8>, WalletError> {
+        let contents = Zeroizing::new(
+            serde_json::to_vec(&self.backup_contents())
+                .map_err(|e| WalletError::SerializationError(e.to_string()))?,
+        );
+        let payload = EncryptedSeed::encrypt(&contents, passphrase, KdfParams::default())
+            .map_err(|e| WalletError::StorageError(e.to_string()))?;
+        serde_json::to_vec_pretty(&BackupEnvelope {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            payload,
+        })
+        .map_err(|e| WalletError::SerializationError(e.to_string()))
+    }
+
+    /// [AIS-3] Restore a wallet from [`BitcoinWallet::export_backup`] output
+    ///
+    /// The wallet has no data directory and no UTXOs; rescan to find its funds.
+    pub fn import_backup(data: &[u8], passphrase: &str) -> Result<BitcoinWallet, WalletError> {
+        let envelope: BackupEnvelope = serde_json::from_slice(data)
+            .map_err(|e| WalletError::StorageError(format!("Invalid wallet backup: {e}")))?;
+        if envelope.format != BACKUP_FORMAT {
+            return Err(WalletError::StorageError(format!(
+                "Not a wallet backup: format '{}'",
+                envelope.format
+            )));
+        }
+        if envelope.version > BACKUP_VERSION {
+            return Err(WalletError::StorageError(format!(
+                "Wallet backup version {} is newer than the supported version {BACKUP_VERSION}; \
+                 upgrade to import it",
+                envelope.version
+            )));
+        }
+
+        let contents = envelope.payload.decrypt(passphrase).map_err(|_| {
+            WalletError::StorageError("Wrong passphrase or corrupted wallet backup".to_string())
+        })?;
+        let backup: WalletBackup = serde_json::from_slice(&contents).map_err(|e| {
+            WalletError::StorageError(format!("Invalid wallet backup contents: {e}"))
+        })?;
+        Ok(Self::from_backup(backup))
+    }
+
+    fn from_backup(backup: WalletBackup) -> Self {
+        let storage = WalletStorage {
+            metadata: WalletMetadata {
+                created_at: backup.created_at,
+                updated_at: backup.created_at,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                network: backup.network,
+                master_fingerprint: backup.master_fingerprint,
+                labels: backup.labels.into_iter().collect(),
+            },
+            utxos: HashMap::new(),
+            transactions: HashMap::new(),
+            addresses: backup
+                .addresses
+                .into_iter()
+                .map(|info| (info.address.clone(), info))
+                .collect(),
+            indexes: WalletIndexes {
+                receive_index: backup.receive_index,
+                change_index: backup.change_index,
+                last_block: backup.last_block,
+                last_sync: None,
+            },
+            labels: backup.bip329_labels.into(),
+        };
+        BitcoinWallet {
+            config: WalletConfig {
+                wallet_type: backup.wallet_type,
+                network: backup.network,
+                name: backup.name,
+                seed_phrase: backup.seed_phrase,
+                password: backup.seed_password,
+                receive_descriptor: backup.receive_descriptor,
+                change_descriptor: backup.change_descriptor,
+                xpub: backup.xpub,
+                data_dir: PathBuf::new(),
+                use_rpc: false,
+                coin_selection: backup.coin_selection,
+                gap_limit: backup.gap_limit,
+                min_confirmations: backup.min_confirmations,
+                fee_strategy: backup.fee_strategy,
+            },
+            storage: Arc::new(Mutex::new(storage)),
+            secp: Secp256k1::new(),
+            seed: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::LabelRef;
+    use bitcoin::bip32::DerivationPath;
+    use bitcoin::hashes::Hash;
+    use bitcoin::ScriptBuf;
+    use std::str::FromStr;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn wallet() -> BitcoinWallet {
+        let address = |index: u32, is_change: bool, labels: &[&str]| AddressInfo {
+            address: format!("bcrt1q{index}{is_change}"),
+            path: Some(
+                DerivationPath::from_str(&format!("m/84'/1'/0'/{}/{index}", u8::from(is_change)))
+                    .unwrap(),
+            ),
+            script: ScriptBuf::new(),
+            is_change,
+            index,
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+            last_used: None,
+        };
+        let addresses = [
+            address(0, false, &["donations"]),
+            address(1, false, &[]),
+            address(0, true, &[]),
+        ];
+        BitcoinWallet {
+            config: WalletConfig {
+                wallet_type: WalletType::Taproot,
+                network: Network::Regtest,
+                name: "backup".to_string(),
+                seed_phrase: Some(PHRASE.to_string()),
+                password: Some("extra words".to_string()),
+                receive_descriptor: "wpkh([73c5da0a/84'/1'/0']tpub/0/*)".to_string(),
+                change_descriptor: "wpkh([73c5da0a/84'/1'/0']tpub/1/*)".to_string(),
+                xpub: None,
+                data_dir: PathBuf::from("/var/lib/anya/wallets"),
+                use_rpc: true,
+                coin_selection: CoinSelectionStrategy::BranchAndBound,
+                gap_limit: 30,
+                min_confirmations: 2,
+                fee_strategy: FeeStrategy::High,
+            },
+            storage: Arc::new(Mutex::new(WalletStorage {
+                metadata: WalletMetadata {
+                    created_at: 1_700_000_000,
+                    updated_at: 1_700_000_500,
+                    version: "1".to_string(),
+                    network: Network::Regtest,
+                    master_fingerprint: Some([0x73, 0xc5, 0xda, 0x0a]),
+                    labels: HashMap::from([("purpose".to_string(), "savings".to_string())]),
+                },
+                utxos: HashMap::new(),
+                transactions: HashMap::new(),
+                addresses: addresses
+                    .into_iter()
+                    .map(|info| (info.address.clone(), info))
+                    .collect(),
+                indexes: WalletIndexes {
+                    receive_index: 2,
+                    change_index: 1,
+                    last_block: Some(840_000),
+                    last_sync: Some(1_700_000_400),
+                },
+                labels: Default::default(),
+            })),
+            secp: Secp256k1::new(),
+            seed: None,
+        }
+    }
+
+    fn envelope(backup: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(backup).unwrap()
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let original = wallet();
+        let coins = LabelRef::Output(bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 1));
+        original.set_label(coins.clone(), "cold storage").unwrap();
+        let backup = original.export_backup("correct horse").unwrap();
+        assert_eq!(envelope(&backup)["version"], BACKUP_VERSION);
+        // The seed never appears in the clear
+        assert!(!String::from_utf8_lossy(&backup).contains("abandon"));
+
+        let restored = BitcoinWallet::import_backup(&backup, "correct horse").unwrap();
+        let contents = restored.backup_contents();
+        assert!(contents == original.backup_contents());
+        assert_eq!(contents.seed_phrase.as_deref(), Some(PHRASE));
+        assert_eq!(contents.addresses.len(), 3);
+        assert_eq!(contents.addresses[0].labels, ["donations"]);
+        assert_eq!((contents.receive_index, contents.change_index), (2, 1));
+        assert_eq!(restored.get_label(&coins).as_deref(), Some("cold storage"));
+        assert_eq!(restored.config.data_dir, PathBuf::new());
+    }
+
+    #[test]
+    fn test_corrupted_or_newer_backup_rejected() {
+        let backup = wallet().export_backup("correct horse").unwrap();
+
+        assert!(BitcoinWallet::import_backup(&backup, "battery staple").is_err());
+        assert!(
+            BitcoinWallet::import_backup(&backup[..backup.len() / 2], "correct horse").is_err()
+        );
+
+        let mut flipped = envelope(&backup);
+        let byte = flipped["payload"]["ciphertext"][10].as_u64().unwrap();
+        flipped["payload"]["ciphertext"][10] = (byte ^ 1).into();
+        let err =
+            BitcoinWallet::import_backup(&serde_json::to_vec(&flipped).unwrap(), "correct horse")
+                .err()
+                .unwrap();
+        assert!(err.to_string().contains("corrupted"), "{err}");
+
+        let mut newer = envelope(&backup);
+        newer["version"] = (BACKUP_VERSION + 1).into();
+        let err =
+            BitcoinWallet::import_backup(&serde_json::to_vec(&newer).unwrap(), "correct horse")
+                .err()
+                .unwrap();
+        assert!(
+            err.to_string().contains("newer than the supported version"),
+            "{err}"
+        );
+    }
+}
@@ -5,6 +5,7 @@
 // AI-Testable: Comprehensive test coverage for key derivation paths
 
 use crate::bitcoin::error::{BitcoinError, BitcoinResult};
+use crate::security::rng::secure_rng;
 use bitcoin::secp256k1::{Secp256k1, SecretKey};
 use bitcoin::{
     bip32::{DerivationPath, Xpriv, Xpub},
@@ -23,7 +24,7 @@ pub struct ExtendedKey {
 /// [AIS-3][BPC-3] Generate a new seed from an optional password
 pub fn generate_seed(_password: &str) -> BitcoinResult<[u8; 64]> {
     let mut seed = [0u8; 64];
-    rand::thread_rng().fill_bytes(&mut seed);
+    secure_rng().fill_bytes(&mut seed);
     Ok(seed)
 }
 
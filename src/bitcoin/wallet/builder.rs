@@ -0,0 +1,1044 @@
+// Transaction builder with coin control
+//
+// Selects wallet UTXOs for a set of recipients and produces an unsigned PSBT.
+// Callers can force specific outpoints into the selection or keep outpoints
+// out of it, e.g. to avoid merging UTXO clusters that should stay unlinked.
+// Silent payment recipients are resolved to their taproot outputs at build
+// time, once the inputs are known. Inputs and outputs the wallet controls get
+// their BIP-32 key origins, and taproot ones their BIP-371 fields, so
+// hardware signers can recognise them. Legacy inputs carry the full previous
+// transaction when it is supplied, segwit inputs only the spent output.
+// Fees are estimated from the weight each selected input type adds once
+// signed, so `simulate` reports the vsize and fee of a transaction before
+// anything is built or signed. Outputs below the dust threshold of their
+// script type are rejected by default, as nodes would refuse to relay them.
+
+use super::psbt::{add_key_derivations, add_taproot_derivations, KeyDerivation, TaprootDerivation};
+use super::{CoinSelectionStrategy, FeeRate, Utxo, WalletError};
+use crate::bitcoin::mempool::policy::DEFAULT_DUST_RELAY_FEE;
+use crate::bitcoin::silent_payments::{
+    self, SilentPaymentAddress, SilentPaymentError, SilentPaymentInput,
+};
+use bitcoin::absolute::LockTime;
+use bitcoin::psbt::Psbt as PSBT;
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bitcoin::transaction::{predict_weight, InputWeightPrediction, Version};
+use bitcoin::{
+    Address, Amount, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
+    Witness,
+};
+use bitcoin::{WitnessProgram, WitnessVersion};
+use log::warn;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// Leftover below this value goes to the fee when there is no change script
+const DUST_LIMIT_SATS: u64 = 546;
+
+/// Length of a taproot output script, paid by silent payments
+const P2TR_SCRIPT_LEN: usize = 34;
+
+/// Signed P2SH-P2WPKH input: the pushed redeem script plus a P2WPKH witness
+const NESTED_P2WPKH_MAX: InputWeightPrediction = InputWeightPrediction::from_slice(23, &[72, 33]);
+
+/// Probability of pushing the anti-fee-sniping locktime into the past
+const LOCKTIME_RANDOMIZE_PROBABILITY: f64 = 0.1;
+
+/// Maximum number of blocks the locktime is pushed back when randomized
+const LOCKTIME_MAX_RANDOM_OFFSET: u32 = 100;
+
+/// Anti-fee-sniping locktime for a transaction built at `tip_height`
+///
+/// Follows Bitcoin Core: the locktime is the current tip height, but one
+/// time in ten it is moved back by up to 99 blocks so that transactions
+/// which were delayed in broadcast do not stand out.
+pub fn anti_fee_sniping_locktime<R: Rng + ?Sized>(
+    tip_height: u32,
+    rng: &mut R,
+) -> Result<LockTime, WalletError> {
+    let mut height = tip_height;
+    if rng.gen_bool(LOCKTIME_RANDOMIZE_PROBABILITY) {
+        height = height.saturating_sub(rng.gen_range(0..LOCKTIME_MAX_RANDOM_OFFSET));
+    }
+    LockTime::from_height(height)
+        .map_err(|e| WalletError::InvalidParameters(format!("invalid tip height: {e}")))
+}
+
+impl From<SilentPaymentError> for WalletError {
+    fn from(err: SilentPaymentError) -> Self {
+        WalletError::TransactionError(err.to_string())
+    }
+}
+
+/// What the builder does with recipient outputs below the dust threshold
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DustPolicy {
+    /// Fail with [`WalletError::DustOutput`]
+    #[default]
+    Reject,
+    /// Log a warning and create the output anyway
+    Warn,
+}
+
+/// Destination of a payment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recipient {
+    /// Fixed output script
+    Script(ScriptBuf),
+    /// BIP-352 silent payment address, resolved from the selected inputs
+    SilentPayment(SilentPaymentAddress),
+}
+
+impl From<ScriptBuf> for Recipient {
+    fn from(script_pubkey: ScriptBuf) -> Self {
+        Recipient::Script(script_pubkey)
+    }
+}
+
+impl From<SilentPaymentAddress> for Recipient {
+    fn from(address: SilentPaymentAddress) -> Self {
+        Recipient::SilentPayment(address)
+    }
+}
+
+impl Recipient {
+    /// Parse a silent payment address or a regular Bitcoin address
+    ///
+    /// Addresses for any network other than `network` are rejected.
+    pub fn parse(s: &str, network: Network) -> Result<Self, WalletError> {
+        if SilentPaymentAddress::is_silent_payment_address(s) {
+            let address: SilentPaymentAddress = s.parse()?;
+            if address.mainnet != (network == Network::Bitcoin) {
+                return Err(WalletError::InvalidParameters(format!(
+                    "silent payment address {s} is not for {network}"
+                )));
+            }
+            return Ok(Recipient::SilentPayment(address));
+        }
+        let address = Address::from_str(s)
+            .map_err(|e| WalletError::InvalidParameters(format!("invalid address {s}: {e}")))?
+            .require_network(network)
+            .map_err(|e| WalletError::InvalidParameters(format!("address {s}: {e}")))?;
+        Ok(Recipient::Script(address.script_pubkey()))
+    }
+}
+
+/// Result of coin selection
+#[derive(Debug, Clone)]
+pub struct CoinSelection {
+    /// Selected UTXOs, mandatory ones first
+    pub selected: Vec<Utxo>,
+    /// Fee paid by the transaction in satoshis
+    pub fee: u64,
+    /// Change returned to the wallet in satoshis (0 if no change output)
+    pub change: u64,
+}
+
+impl CoinSelection {
+    /// Total value of the selected UTXOs in satoshis
+    pub fn total_input(&self) -> u64 {
+        self.selected.iter().map(|u| u.txout.value.to_sat()).sum()
+    }
+}
+
+/// Fee and size of a transaction, estimated without building or signing it
+#[derive(Debug, Clone)]
+pub struct TxSimulation {
+    /// UTXOs that would be spent, mandatory ones first
+    pub inputs: Vec<Utxo>,
+    /// Total value of the inputs in satoshis
+    pub total_input: u64,
+    /// Amount paid to each recipient in satoshis, in order
+    pub outputs: Vec<u64>,
+    /// Change returned to the wallet in satoshis (0 if no change output)
+    pub change: u64,
+    /// Estimated virtual size of the signed transaction
+    pub vsize: u64,
+    /// Fee paid in satoshis, including any leftover too small for change
+    pub fee: u64,
+    /// Fee divided by the estimated vsize, in sat/vB
+    pub effective_fee_rate: f64,
+}
+
+/// Builder for unsigned wallet transactions
+#[derive(Debug, Clone)]
+pub struct TransactionBuilder {
+    utxos: Vec<Utxo>,
+    recipients: Vec<(Recipient, Amount)>,
+    input_keys: HashMap<OutPoint, SecretKey>,
+    taproot_derivations: Vec<TaprootDerivation>,
+    key_derivations: Vec<KeyDerivation>,
+    previous_txs: HashMap<Txid, Transaction>,
+    change_script: Option<ScriptBuf>,
+    fee_rate: FeeRate,
+    strategy: CoinSelectionStrategy,
+    must_spend: Vec<OutPoint>,
+    avoid_spend: HashSet<OutPoint>,
+    manually_selected_only: bool,
+    enable_rbf: bool,
+    lock_time: Option<LockTime>,
+    tip_height: Option<u32>,
+    dust_policy: DustPolicy,
+    dust_relay_fee: bitcoin::FeeRate,
+}
+
+impl TransactionBuilder {
+    /// Create a builder spending from the given wallet UTXOs
+    pub fn new(utxos: Vec<Utxo>) -> Self {
+        Self {
+            utxos,
+            recipients: Vec::new(),
+            input_keys: HashMap::new(),
+            taproot_derivations: Vec::new(),
+            key_derivations: Vec::new(),
+            previous_txs: HashMap::new(),
+            change_script: None,
+            fee_rate: FeeRate::SatPerVb(1),
+            strategy: CoinSelectionStrategy::LargestFirst,
+            must_spend: Vec::new(),
+            avoid_spend: HashSet::new(),
+            manually_selected_only: false,
+            enable_rbf: true,
+            lock_time: None,
+            tip_height: None,
+            dust_policy: DustPolicy::default(),
+            dust_relay_fee: DEFAULT_DUST_RELAY_FEE,
+        }
+    }
+
+    /// Pay `amount` to a script or silent payment address
+    ///
+    /// Silent payment outputs depend on the spent inputs, so their script is
+    /// only derived in [`build`](Self::build); see [`input_keys`](Self::input_keys).
+    pub fn add_recipient(mut self, recipient: impl Into<Recipient>, amount: Amount) -> Self {
+        self.recipients.push((recipient.into(), amount));
+        self
+    }
+
+    /// Private keys of wallet UTXOs, needed to pay silent payment recipients
+    pub fn input_keys(mut self, keys: impl IntoIterator<Item = (OutPoint, SecretKey)>) -> Self {
+        self.input_keys.extend(keys);
+        self
+    }
+
+    /// Taproot keys of the wallet, recorded on matching inputs and outputs
+    pub fn taproot_derivations(
+        mut self,
+        derivations: impl IntoIterator<Item = TaprootDerivation>,
+    ) -> Self {
+        self.taproot_derivations.extend(derivations);
+        self
+    }
+
+    /// ECDSA keys of the wallet, recorded in `bip32_derivation` of matching inputs and outputs
+    pub fn key_derivations(mut self, derivations: impl IntoIterator<Item = KeyDerivation>) -> Self {
+        self.key_derivations.extend(derivations);
+        self
+    }
+
+    /// Transactions created by the wallet UTXOs, attached to legacy inputs spending them
+    pub fn previous_transactions(mut self, txs: impl IntoIterator<Item = Transaction>) -> Self {
+        self.previous_txs
+            .extend(txs.into_iter().map(|tx| (tx.compute_txid(), tx)));
+        self
+    }
+
+    /// Script that receives the change output
+    pub fn change_script(mut self, script_pubkey: ScriptBuf) -> Self {
+        self.change_script = Some(script_pubkey);
+        self
+    }
+
+    /// Fee rate used for the transaction
+    pub fn fee_rate(mut self, fee_rate: FeeRate) -> Self {
+        self.fee_rate = fee_rate;
+        self
+    }
+
+    /// Order in which non-mandatory UTXOs are considered
+    pub fn coin_selection(mut self, strategy: CoinSelectionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Outpoints that must be spent by the transaction
+    pub fn must_spend(mut self, outpoints: &[OutPoint]) -> Self {
+        for outpoint in outpoints {
+            if !self.must_spend.contains(outpoint) {
+                self.must_spend.push(*outpoint);
+            }
+        }
+        self
+    }
+
+    /// Outpoints that must never be spent by the transaction
+    pub fn avoid_spend(mut self, outpoints: &[OutPoint]) -> Self {
+        self.avoid_spend.extend(outpoints.iter().copied());
+        self
+    }
+
+    /// Spend only the `must_spend` outpoints, never adding other UTXOs
+    pub fn manually_selected_only(mut self) -> Self {
+        self.manually_selected_only = true;
+        self
+    }
+
+    /// Signal replace-by-fee on every input
+    pub fn enable_rbf(mut self, enable: bool) -> Self {
+        self.enable_rbf = enable;
+        self
+    }
+
+    /// Lock time of the transaction, overriding the anti-fee-sniping default
+    pub fn lock_time(mut self, lock_time: LockTime) -> Self {
+        self.lock_time = Some(lock_time);
+        self
+    }
+
+    /// Current chain tip height, used for the default locktime
+    pub fn current_height(mut self, tip_height: u32) -> Self {
+        self.tip_height = Some(tip_height);
+        self
+    }
+
+    /// How recipient outputs below the dust threshold are handled
+    pub fn dust_policy(mut self, policy: DustPolicy) -> Self {
+        self.dust_policy = policy;
+        self
+    }
+
+    /// Fee rate the dust thresholds are computed from, 3 sat/vB by default
+    pub fn dust_relay_fee(mut self, fee_rate: bitcoin::FeeRate) -> Self {
+        self.dust_relay_fee = fee_rate;
+        self
+    }
+
+    /// Select UTXOs covering the recipients and fee
+    ///
+    /// Mandatory outpoints are always included; avoided outpoints and
+    /// unspendable UTXOs are never considered. Fails if a mandatory outpoint
+    /// is unknown, unspendable or also avoided, or if the allowed UTXOs
+    /// cannot cover the target. Recipient outputs are checked first; see
+    /// [`check_outputs`](Self::check_outputs).
+    pub fn select_coins(&self) -> Result<CoinSelection, WalletError> {
+        let target = self.check_outputs()?;
+
+        let mut selected = Vec::with_capacity(self.must_spend.len());
+        for outpoint in &self.must_spend {
+            if self.avoid_spend.contains(outpoint) {
+                return Err(WalletError::InvalidParameters(format!(
+                    "outpoint {outpoint} is both required and avoided"
+                )));
+            }
+            let utxo = self
+                .utxos
+                .iter()
+                .find(|u| u.outpoint == *outpoint)
+                .ok_or_else(|| WalletError::UtxoError(format!("unknown outpoint {outpoint}")))?;
+            if !utxo.spendable {
+                return Err(WalletError::UtxoError(format!(
+                    "outpoint {outpoint} is not spendable"
+                )));
+            }
+            selected.push(utxo.clone());
+        }
+
+        let mut candidates: Vec<&Utxo> = if self.manually_selected_only {
+            Vec::new()
+        } else {
+            self.utxos
+                .iter()
+                .filter(|u| u.spendable)
+                .filter(|u| !self.avoid_spend.contains(&u.outpoint))
+                .filter(|u| !self.must_spend.contains(&u.outpoint))
+                .collect()
+        };
+        self.order_candidates(&mut candidates);
+        let mut candidates = candidates.into_iter();
+
+        loop {
+            let total: u64 = selected.iter().map(|u| u.txout.value.to_sat()).sum();
+            let fee_without_change = self.fee_for(&selected, false);
+
+            if total >= target + fee_without_change {
+                let fee_with_change = self.fee_for(&selected, true);
+                let change = total.saturating_sub(target + fee_with_change);
+                let change_dust = self
+                    .change_script
+                    .as_ref()
+                    .map(|script| script.minimal_non_dust_custom(self.dust_relay_fee).to_sat());
+
+                return if change_dust.is_some_and(|threshold| change >= threshold) {
+                    Ok(CoinSelection {
+                        selected,
+                        fee: fee_with_change,
+                        change,
+                    })
+                } else {
+                    // Leftover too small for a change output goes to the fee
+                    Ok(CoinSelection {
+                        selected,
+                        fee: total - target,
+                        change: 0,
+                    })
+                };
+            }
+
+            match candidates.next() {
+                Some(utxo) => selected.push(utxo.clone()),
+                None if self.manually_selected_only => {
+                    return Err(WalletError::InsufficientFunds(format!(
+                        "mandatory inputs provide {total} sats, need {}",
+                        target + fee_without_change
+                    )));
+                }
+                None => {
+                    return Err(WalletError::InsufficientFunds(format!(
+                        "spendable inputs provide {total} sats, need {}",
+                        target + fee_without_change
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Estimate the transaction [`build`](Self::build) would create
+    ///
+    /// Runs coin selection and sizes the result from the selected input
+    /// types, with signatures at their maximum length, without deriving
+    /// silent payment outputs or creating a PSBT. Fails like
+    /// [`select_coins`](Self::select_coins), with
+    /// [`WalletError::InsufficientFunds`] on a shortfall.
+    pub fn simulate(&self) -> Result<TxSimulation, WalletError> {
+        let selection = self.select_coins()?;
+        let vsize = self.estimate_vsize(&selection.selected, selection.change > 0);
+        Ok(TxSimulation {
+            total_input: selection.total_input(),
+            outputs: self.recipients.iter().map(|(_, a)| a.to_sat()).collect(),
+            change: selection.change,
+            vsize,
+            fee: selection.fee,
+            effective_fee_rate: selection.fee as f64 / vsize as f64,
+            inputs: selection.selected,
+        })
+    }
+
+    /// Build an unsigned PSBT for the selected coins
+    ///
+    /// Unless a lock time was set explicitly, RBF transactions built with a
+    /// known tip height get an anti-fee-sniping locktime. Fails if a supplied
+    /// previous transaction does not contain the UTXO a legacy input spends.
+    pub fn build(&self) -> Result<PSBT, WalletError> {
+        let selection = self.select_coins()?;
+
+        if self.change_script.is_none() && selection.fee > self.max_change_free_fee(&selection) {
+            return Err(WalletError::InvalidParameters(
+                "a change script is required for this transaction".to_string(),
+            ));
+        }
+
+        let lock_time = match (self.lock_time, self.tip_height) {
+            (Some(lock_time), _) => lock_time,
+            (None, Some(height)) if self.enable_rbf => {
+                anti_fee_sniping_locktime(height, &mut rand::thread_rng())?
+            }
+            _ => LockTime::ZERO,
+        };
+
+        let sequence = match (self.enable_rbf, lock_time == LockTime::ZERO) {
+            (true, _) => Sequence::ENABLE_RBF_NO_LOCKTIME,
+            (false, false) => Sequence::ENABLE_LOCKTIME_NO_RBF,
+            (false, true) => Sequence::MAX,
+        };
+
+        let mut output = self.resolve_recipients(&selection.selected)?;
+        if let (Some(script), true) = (&self.change_script, selection.change > 0) {
+            output.push(TxOut {
+                value: Amount::from_sat(selection.change),
+                script_pubkey: script.clone(),
+            });
+        }
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time,
+            input: selection
+                .selected
+                .iter()
+                .map(|utxo| TxIn {
+                    previous_output: utxo.outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output,
+        };
+
+        let mut psbt =
+            PSBT::from_unsigned_tx(tx).map_err(|e| WalletError::PsbtError(e.to_string()))?;
+        for (input, utxo) in psbt.inputs.iter_mut().zip(&selection.selected) {
+            input.redeem_script = utxo.redeem_script.clone();
+            input.witness_script = utxo.witness_script.clone();
+
+            let segwit = utxo.txout.script_pubkey.is_witness_program()
+                || (utxo.redeem_script.as_ref()).is_some_and(|script| script.is_witness_program());
+            match self.previous_txs.get(&utxo.outpoint.txid) {
+                Some(tx) if !segwit => {
+                    if tx.output.get(utxo.outpoint.vout as usize) != Some(&utxo.txout) {
+                        return Err(WalletError::PsbtError(format!(
+                            "Previous transaction does not contain UTXO {}",
+                            utxo.outpoint
+                        )));
+                    }
+                    input.non_witness_utxo = Some(tx.clone());
+                }
+                _ => input.witness_utxo = Some(utxo.txout.clone()),
+            }
+        }
+        add_key_derivations(&mut psbt, &self.key_derivations);
+        add_taproot_derivations(
+            &Secp256k1::verification_only(),
+            &mut psbt,
+            &self.taproot_derivations,
+        )?;
+
+        Ok(psbt)
+    }
+
+    /// Check the recipient amounts, returning their total in satoshis
+    ///
+    /// Fails if there are no recipients or they receive more than 21 million
+    /// BTC in total. An output below the dust threshold of its script type at
+    /// the dust relay fee is rejected or logged, depending on the
+    /// [`DustPolicy`]. Silent payment outputs are checked as taproot outputs.
+    pub fn check_outputs(&self) -> Result<u64, WalletError> {
+        if self.recipients.is_empty() {
+            return Err(WalletError::InvalidParameters(
+                "transaction has no recipients".to_string(),
+            ));
+        }
+
+        let mut total = Amount::ZERO;
+        for (index, (recipient, amount)) in self.recipients.iter().enumerate() {
+            total = total
+                .checked_add(*amount)
+                .filter(|total| *total <= Amount::MAX_MONEY)
+                .ok_or_else(|| {
+                    WalletError::InvalidParameters(
+                        "outputs exceed the 21 million BTC supply".to_string(),
+                    )
+                })?;
+
+            let threshold = match recipient {
+                Recipient::Script(script) => script.minimal_non_dust_custom(self.dust_relay_fee),
+                Recipient::SilentPayment(_) => {
+                    // Only the length of the taproot output script matters
+                    let program = WitnessProgram::new(WitnessVersion::V1, &[0; 32])
+                        .expect("32-byte program is a valid taproot program");
+                    ScriptBuf::new_witness_program(&program)
+                        .minimal_non_dust_custom(self.dust_relay_fee)
+                }
+            };
+            if *amount < threshold {
+                let (value, threshold) = (amount.to_sat(), threshold.to_sat());
+                match self.dust_policy {
+                    DustPolicy::Reject => {
+                        return Err(WalletError::DustOutput {
+                            index,
+                            value,
+                            threshold,
+                        });
+                    }
+                    DustPolicy::Warn => warn!(
+                        "Output {index} of {value} sats is below the dust threshold of {threshold} sats"
+                    ),
+                }
+            }
+        }
+        Ok(total.to_sat())
+    }
+
+    /// Output for every recipient, deriving silent payment scripts from the
+    /// eligible selected inputs
+    fn resolve_recipients(&self, selected: &[Utxo]) -> Result<Vec<TxOut>, WalletError> {
+        let silent: Vec<SilentPaymentAddress> = self
+            .recipients
+            .iter()
+            .filter_map(|(recipient, _)| match recipient {
+                Recipient::SilentPayment(address) => Some(*address),
+                Recipient::Script(_) => None,
+            })
+            .collect();
+
+        let mut silent_scripts = if silent.is_empty() {
+            Vec::new()
+        } else {
+            let inputs = selected
+                .iter()
+                .filter(|utxo| silent_payment_eligible(utxo))
+                .map(|utxo| {
+                    let secret_key = self.input_keys.get(&utxo.outpoint).ok_or_else(|| {
+                        WalletError::TransactionError(format!(
+                            "missing key for input {} required by silent payment",
+                            utxo.outpoint
+                        ))
+                    })?;
+                    Ok(SilentPaymentInput {
+                        outpoint: utxo.outpoint,
+                        secret_key: *secret_key,
+                        is_taproot: utxo.txout.script_pubkey.is_p2tr(),
+                    })
+                })
+                .collect::<Result<Vec<_>, WalletError>>()?;
+            if inputs.is_empty() {
+                return Err(SilentPaymentError::NoEligibleInputs.into());
+            }
+            let outpoints: Vec<OutPoint> = selected.iter().map(|utxo| utxo.outpoint).collect();
+            silent_payments::create_outputs(&silent, &inputs, &outpoints)?
+        }
+        .into_iter();
+
+        Ok(self
+            .recipients
+            .iter()
+            .map(|(recipient, amount)| TxOut {
+                value: *amount,
+                script_pubkey: match recipient {
+                    Recipient::Script(script) => script.clone(),
+                    Recipient::SilentPayment(_) => silent_scripts
+                        .next()
+                        .expect("one derived script per silent payment recipient"),
+                },
+            })
+            .collect())
+    }
+
+    fn fee_for(&self, inputs: &[Utxo], with_change: bool) -> u64 {
+        let vsize = self.estimate_vsize(inputs, with_change);
+        (vsize * self.fee_rate.to_sat_per_kb() + 999) / 1000
+    }
+
+    /// Virtual size of the signed transaction spending `inputs`
+    fn estimate_vsize(&self, inputs: &[Utxo], with_change: bool) -> u64 {
+        let recipients = self
+            .recipients
+            .iter()
+            .map(|(recipient, _)| match recipient {
+                Recipient::Script(script) => script.len(),
+                Recipient::SilentPayment(_) => P2TR_SCRIPT_LEN,
+            });
+        let change = self
+            .change_script
+            .iter()
+            .filter(|_| with_change)
+            .map(|script| script.len());
+        predict_weight(inputs.iter().map(input_weight), recipients.chain(change)).to_vbytes_ceil()
+    }
+
+    /// Largest fee accepted without a change output: the change-less fee
+    /// plus anything that would have been dust
+    fn max_change_free_fee(&self, selection: &CoinSelection) -> u64 {
+        self.fee_for(&selection.selected, false) + DUST_LIMIT_SATS
+    }
+
+    fn order_candidates(&self, candidates: &mut [&Utxo]) {
+        match self.strategy {
+            CoinSelectionStrategy::SmallestFirst => {
+                candidates.sort_by_key(|u| u.txout.value);
+            }
+            CoinSelectionStrategy::OldestFirst => {
+                candidates.sort_by_key(|u| std::cmp::Reverse(u.confirmations));
+            }
+            CoinSelectionStrategy::Random => {
+                candidates.shuffle(&mut rand::thread_rng());
+            }
+            // Branch and bound and privacy-optimized selection fall back to
+            // largest-first, which minimises the number of inputs merged
+            CoinSelectionStrategy::LargestFirst
+            | CoinSelectionStrategy::PrivacyOptimized
+            | CoinSelectionStrategy::BranchAndBound => {
+                candidates.sort_by_key(|u| std::cmp::Reverse(u.txout.value));
+            }
+        }
+    }
+}
+
+/// Weight a UTXO adds to the transaction once its input is signed
+///
+/// Covers the script types the wallet signs; other scripts, whose
+/// satisfaction is not known here, are sized as P2WPKH.
+fn input_weight(utxo: &Utxo) -> InputWeightPrediction {
+    let script = &utxo.txout.script_pubkey;
+    if script.is_p2tr() {
+        // The wallet signs with an explicit sighash byte
+        InputWeightPrediction::P2TR_KEY_NON_DEFAULT_SIGHASH
+    } else if script.is_p2pkh() {
+        InputWeightPrediction::P2PKH_COMPRESSED_MAX
+    } else if script.is_p2sh() && utxo.redeem_script.as_ref().is_some_and(|r| r.is_p2wpkh()) {
+        NESTED_P2WPKH_MAX
+    } else {
+        InputWeightPrediction::P2WPKH_MAX
+    }
+}
+
+/// Whether a UTXO's key contributes to silent payment derivation
+fn silent_payment_eligible(utxo: &Utxo) -> bool {
+    let script = &utxo.txout.script_pubkey;
+    script.is_p2tr()
+        || script.is_p2wpkh()
+        || script.is_p2pkh()
+        || (script.is_p2sh()
+            && utxo
+                .redeem_script
+                .as_ref()
+                .is_some_and(|redeem| redeem.is_p2wpkh()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::signer;
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Txid, WPubkeyHash};
+
+    fn script(seed: u8) -> ScriptBuf {
+        ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array([seed; 20]))
+    }
+
+    fn utxo(vout: u32, sats: u64) -> Utxo {
+        Utxo {
+            outpoint: OutPoint::new(Txid::all_zeros(), vout),
+            txout: TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey: script(vout as u8),
+            },
+            redeem_script: None,
+            witness_script: None,
+            confirmations: 6,
+            spendable: true,
+            from_wallet: true,
+        }
+    }
+
+    fn wallet_utxos() -> Vec<Utxo> {
+        vec![
+            utxo(0, 10_000),
+            utxo(1, 50_000),
+            utxo(2, 200_000),
+            utxo(3, 80_000),
+        ]
+    }
+
+    fn builder(amount: u64) -> TransactionBuilder {
+        TransactionBuilder::new(wallet_utxos())
+            .add_recipient(script(100), Amount::from_sat(amount))
+            .change_script(script(101))
+    }
+
+    fn selected(selection: &CoinSelection) -> Vec<OutPoint> {
+        selection.selected.iter().map(|u| u.outpoint).collect()
+    }
+
+    #[test]
+    fn test_must_spend_always_selected() {
+        let small = OutPoint::new(Txid::all_zeros(), 0);
+        let mid = OutPoint::new(Txid::all_zeros(), 1);
+
+        // Largest-first alone would pick the 200k UTXO only
+        let selection = builder(60_000)
+            .must_spend(&[small, mid])
+            .select_coins()
+            .unwrap();
+        let outpoints = selected(&selection);
+        assert!(outpoints.contains(&small));
+        assert!(outpoints.contains(&mid));
+        assert!(selection.total_input() >= 60_000 + selection.fee + selection.change);
+
+        let psbt = builder(60_000).must_spend(&[small]).build().unwrap();
+        assert!(psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .any(|input| input.previous_output == small));
+    }
+
+    #[test]
+    fn test_avoid_spend_never_selected() {
+        let largest = OutPoint::new(Txid::all_zeros(), 2);
+
+        let selection = builder(100_000)
+            .avoid_spend(&[largest])
+            .select_coins()
+            .unwrap();
+        assert!(!selected(&selection).contains(&largest));
+
+        // Without the avoided UTXO there is not enough left to pay
+        let result = builder(150_000).avoid_spend(&[largest]).select_coins();
+        assert!(matches!(result, Err(WalletError::InsufficientFunds(_))));
+    }
+
+    #[test]
+    fn test_manually_selected_inputs_must_cover_target() {
+        let small = OutPoint::new(Txid::all_zeros(), 0);
+
+        let result = builder(20_000)
+            .must_spend(&[small])
+            .manually_selected_only()
+            .select_coins();
+        assert!(matches!(result, Err(WalletError::InsufficientFunds(_))));
+
+        let selection = builder(5_000)
+            .must_spend(&[small])
+            .manually_selected_only()
+            .select_coins()
+            .unwrap();
+        assert_eq!(selected(&selection), vec![small]);
+    }
+
+    #[test]
+    fn test_conflicting_and_unknown_outpoints() {
+        let outpoint = OutPoint::new(Txid::all_zeros(), 1);
+        let result = builder(1_000)
+            .must_spend(&[outpoint])
+            .avoid_spend(&[outpoint])
+            .select_coins();
+        assert!(matches!(result, Err(WalletError::InvalidParameters(_))));
+
+        let unknown = OutPoint::new(Txid::all_zeros(), 99);
+        let result = builder(1_000).must_spend(&[unknown]).select_coins();
+        assert!(matches!(result, Err(WalletError::UtxoError(_))));
+    }
+
+    #[test]
+    fn test_simulated_vsize_matches_signed_transaction() {
+        let secp = Secp256k1::new();
+        let key = SecretKey::from_slice(&[9; 32]).unwrap();
+        let public_key = bitcoin::CompressedPublicKey(key.public_key(&secp));
+        let (internal_key, _) = key.x_only_public_key(&secp);
+
+        let mut segwit = utxo(0, 30_000);
+        segwit.txout.script_pubkey = ScriptBuf::new_p2wpkh(&public_key.wpubkey_hash());
+        let mut taproot = utxo(1, 40_000);
+        taproot.txout.script_pubkey = ScriptBuf::new_p2tr(&secp, internal_key, None);
+
+        let builder = TransactionBuilder::new(vec![segwit, taproot])
+            .add_recipient(script(100), Amount::from_sat(60_000))
+            .change_script(ScriptBuf::new_p2tr(&secp, internal_key, None))
+            .fee_rate(FeeRate::SatPerVb(5));
+        let simulation = builder.simulate().unwrap();
+        assert_eq!(simulation.inputs.len(), 2);
+        assert_eq!(simulation.total_input, 70_000);
+        assert_eq!(simulation.outputs, vec![60_000]);
+        assert_eq!(simulation.fee, simulation.vsize * 5);
+        assert_eq!(simulation.effective_fee_rate, 5.0);
+        assert_eq!(
+            simulation.total_input,
+            60_000 + simulation.change + simulation.fee
+        );
+
+        let mut psbt = builder.build().unwrap();
+        signer::sign_psbt(&secp, &mut psbt, &key, signer::SighashType::All).unwrap();
+        assert_eq!(signer::finalize_psbt(&mut psbt), 2);
+        let signed = psbt.extract_tx_unchecked_fee_rate();
+
+        // Estimates assume maximum-length ECDSA signatures
+        let actual = signed.vsize() as u64;
+        assert!(simulation.vsize >= actual && simulation.vsize - actual <= 1);
+        assert_eq!(signed.output[1].value.to_sat(), simulation.change);
+    }
+
+    #[test]
+    fn test_simulate_reports_shortfall() {
+        let result = builder(400_000).simulate();
+        assert!(matches!(result, Err(WalletError::InsufficientFunds(_))));
+    }
+
+    #[test]
+    fn test_dust_threshold_depends_on_script_type() {
+        let secp = Secp256k1::new();
+        let key = SecretKey::from_slice(&[9; 32]).unwrap();
+        let public_key = bitcoin::PublicKey::new(key.public_key(&secp));
+        let (internal_key, _) = key.x_only_public_key(&secp);
+
+        // Thresholds at the default dust relay fee of 3 sat/vB
+        let cases = [
+            (script(100), 294),
+            (ScriptBuf::new_p2tr(&secp, internal_key, None), 330),
+            (ScriptBuf::new_p2pkh(&public_key.pubkey_hash()), 546),
+        ];
+        for (recipient, threshold) in cases {
+            let pay = |amount: u64| {
+                TransactionBuilder::new(wallet_utxos())
+                    .add_recipient(recipient.clone(), Amount::from_sat(amount))
+                    .change_script(script(101))
+            };
+            assert_eq!(
+                pay(threshold - 1).build().unwrap_err().to_string(),
+                WalletError::DustOutput {
+                    index: 0,
+                    value: threshold - 1,
+                    threshold,
+                }
+                .to_string()
+            );
+            assert!(pay(threshold).build().is_ok());
+            assert!(pay(threshold + 1).build().is_ok());
+
+            // Warning instead of rejecting still creates the output
+            let psbt = pay(threshold - 1)
+                .dust_policy(DustPolicy::Warn)
+                .build()
+                .unwrap();
+            assert_eq!(psbt.unsigned_tx.output[0].value.to_sat(), threshold - 1);
+        }
+
+        // A higher dust relay fee raises the threshold
+        let result = builder(500)
+            .dust_relay_fee(bitcoin::FeeRate::from_sat_per_kwu(2_500))
+            .simulate();
+        assert!(matches!(
+            result,
+            Err(WalletError::DustOutput { threshold: 980, .. })
+        ));
+    }
+
+    #[test]
+    fn test_outputs_above_max_money_rejected() {
+        let result = builder(Amount::MAX_MONEY.to_sat())
+            .add_recipient(script(102), Amount::from_sat(1))
+            .select_coins();
+        assert!(matches!(result, Err(WalletError::InvalidParameters(_))));
+    }
+
+    #[test]
+    fn test_anti_fee_sniping_locktime_matches_tip() {
+        // An all-ones RNG never takes the randomization branch
+        let mut rng = rand::rngs::mock::StepRng::new(u64::MAX, 0);
+        let lock_time = anti_fee_sniping_locktime(850_000, &mut rng).unwrap();
+        assert_eq!(lock_time, LockTime::from_height(850_000).unwrap());
+    }
+
+    #[test]
+    fn test_default_locktime_from_tip_height() {
+        let tip = 850_000;
+        let psbt = builder(5_000).current_height(tip).build().unwrap();
+        let height = match psbt.unsigned_tx.lock_time {
+            LockTime::Blocks(height) => height.to_consensus_u32(),
+            LockTime::Seconds(_) => panic!("expected a block height locktime"),
+        };
+        assert!(height <= tip && height > tip - LOCKTIME_MAX_RANDOM_OFFSET);
+
+        // Without RBF the transaction is final, as before
+        let psbt = builder(5_000)
+            .current_height(tip)
+            .enable_rbf(false)
+            .build()
+            .unwrap();
+        assert_eq!(psbt.unsigned_tx.lock_time, LockTime::ZERO);
+    }
+
+    #[test]
+    fn test_explicit_locktime_overrides_default() {
+        let explicit = LockTime::from_height(123).unwrap();
+        let psbt = builder(5_000)
+            .current_height(850_000)
+            .lock_time(explicit)
+            .build()
+            .unwrap();
+        assert_eq!(psbt.unsigned_tx.lock_time, explicit);
+    }
+
+    #[test]
+    fn test_taproot_change_gets_bip371_fields() {
+        let secp = Secp256k1::new();
+        let (internal_key, _) = SecretKey::from_slice(&[7; 32])
+            .unwrap()
+            .x_only_public_key(&secp);
+        let origin = (Default::default(), "m/86'/0'/0'/1/0".parse().unwrap());
+        let psbt = TransactionBuilder::new(wallet_utxos())
+            .add_recipient(script(100), Amount::from_sat(5_000))
+            .change_script(ScriptBuf::new_p2tr(&secp, internal_key, None))
+            .taproot_derivations([TaprootDerivation::key_path(internal_key, origin.clone())])
+            .build()
+            .unwrap();
+
+        let change = psbt
+            .outputs
+            .iter()
+            .find(|output| output.tap_internal_key == Some(internal_key))
+            .unwrap();
+        assert_eq!(change.tap_key_origins[&internal_key], (Vec::new(), origin));
+    }
+
+    #[test]
+    fn test_recipient_address_must_match_network() {
+        let mainnet = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+        let recipient = Recipient::parse(mainnet, Network::Bitcoin).unwrap();
+        assert!(matches!(recipient, Recipient::Script(script) if script.is_p2wpkh()));
+        assert!(Recipient::parse(testnet, Network::Testnet).is_ok());
+        assert!(matches!(
+            Recipient::parse(testnet, Network::Bitcoin),
+            Err(WalletError::InvalidParameters(_))
+        ));
+        assert!(Recipient::parse(mainnet, Network::Signet).is_err());
+    }
+
+    #[test]
+    fn test_silent_payment_recipient_resolved_from_selected_inputs() {
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let key = |seed: u8| SecretKey::from_slice(&[seed; 32]).unwrap();
+        let keyed_utxo = |vout: u32, sats: u64| {
+            let public_key = key(vout as u8 + 1).public_key(&secp);
+            let mut utxo = utxo(vout, sats);
+            utxo.txout.script_pubkey =
+                ScriptBuf::new_p2wpkh(&bitcoin::CompressedPublicKey(public_key).wpubkey_hash());
+            utxo
+        };
+        let utxos = vec![keyed_utxo(0, 30_000), keyed_utxo(1, 40_000)];
+        let keys = utxos
+            .iter()
+            .map(|u| (u.outpoint, key(u.outpoint.vout as u8 + 1)));
+
+        let address = SilentPaymentAddress::new(
+            key(0x51).public_key(&secp),
+            key(0x52).public_key(&secp),
+            bitcoin::Network::Bitcoin,
+        );
+        let recipient = Recipient::parse(&address.to_string(), bitcoin::Network::Bitcoin).unwrap();
+        assert_eq!(recipient, Recipient::SilentPayment(address));
+        assert!(Recipient::parse(&address.to_string(), bitcoin::Network::Testnet).is_err());
+
+        let builder = TransactionBuilder::new(utxos.clone())
+            .add_recipient(recipient, Amount::from_sat(50_000))
+            .change_script(script(101));
+        assert!(matches!(
+            builder.build(),
+            Err(WalletError::TransactionError(_))
+        ));
+
+        let psbt = builder.input_keys(keys).build().unwrap();
+        let tx = &psbt.unsigned_tx;
+        assert_eq!(tx.input.len(), 2);
+
+        let inputs: Vec<SilentPaymentInput> = tx
+            .input
+            .iter()
+            .map(|input| SilentPaymentInput {
+                outpoint: input.previous_output,
+                secret_key: key(input.previous_output.vout as u8 + 1),
+                is_taproot: false,
+            })
+            .collect();
+        let outpoints: Vec<OutPoint> = tx.input.iter().map(|i| i.previous_output).collect();
+        let expected = silent_payments::create_outputs(&[address], &inputs, &outpoints).unwrap();
+
+        assert!(tx.output[0].script_pubkey.is_p2tr());
+        assert_eq!(tx.output[0].script_pubkey, expected[0]);
+        assert_eq!(tx.output[0].value, Amount::from_sat(50_000));
+    }
+}
@@ -0,0 +1,530 @@
+// External signing devices (HWI)
+//
+// Hardware wallets keep their keys on the device, so the wallet hands them a
+// PSBT and merges back whatever they signed. `HwiSigner` drives devices
+// through the `hwi` command line tool (bitcoin-core/HWI), which speaks the
+// USB protocols of Ledger, Trezor, Coldcard, BitBox02 and Jade and answers in
+// JSON. Every command runs under a timeout, since a device waiting for a
+// button press never answers on its own, and a user pressing "reject" is
+// reported as `ExternalSignerError::Declined` rather than a device failure.
+
+use super::AddressType;
+use crate::AnyaError;
+use async_trait::async_trait;
+use bitcoin::bip32::{DerivationPath, Fingerprint, Xpub};
+use bitcoin::psbt::Psbt as PSBT;
+use bitcoin::{Address, Network};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How long a device may take to answer, including user confirmation
+pub const DEFAULT_DEVICE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// HWI error code for an action the user rejected on the device
+const HWI_ACTION_CANCELED: i64 = -14;
+
+/// External signer errors
+#[derive(Debug, thiserror::Error)]
+pub enum ExternalSignerError {
+    #[error("No signing device connected")]
+    NoDevice,
+    #[error("Device did not respond within {0:?}")]
+    Timeout(Duration),
+    #[error("Action declined on the device")]
+    Declined,
+    #[error("Device error {code}: {message}")]
+    Device { code: i64, message: String },
+    #[error("Unexpected device response: {0}")]
+    Protocol(String),
+    #[error("Cannot run signer: {0}")]
+    Io(String),
+}
+
+impl From<ExternalSignerError> for AnyaError {
+    fn from(err: ExternalSignerError) -> Self {
+        match err {
+            ExternalSignerError::Timeout(_) => AnyaError::Timeout(err.to_string()),
+            _ => AnyaError::Bitcoin(err.to_string()),
+        }
+    }
+}
+
+/// Signing device found by [`ExternalSigner::enumerate`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SignerDevice {
+    /// Device family, e.g. `ledger` or `trezor`
+    #[serde(rename = "type")]
+    pub device_type: String,
+    pub model: String,
+    /// Transport path of the device
+    pub path: String,
+    /// Master key fingerprint; unknown while the device is locked
+    pub fingerprint: Option<Fingerprint>,
+    #[serde(default, rename = "needs_pin_sent")]
+    pub needs_pin: bool,
+    #[serde(default, rename = "needs_passphrase_sent")]
+    pub needs_passphrase: bool,
+}
+
+/// Device that holds keys and signs PSBTs without revealing them
+#[async_trait]
+pub trait ExternalSigner: Send + Sync {
+    /// List the connected devices
+    async fn enumerate(&self) -> Result<Vec<SignerDevice>, ExternalSignerError>;
+
+    /// Extended public key at `path`
+    async fn get_xpub(&self, path: &DerivationPath) -> Result<Xpub, ExternalSignerError>;
+
+    /// Show the address at `path` on the device screen and return it
+    ///
+    /// The user compares the two to detect a compromised host.
+    async fn display_address(
+        &self,
+        path: &DerivationPath,
+        address_type: AddressType,
+    ) -> Result<Address, ExternalSignerError>;
+
+    /// Return `psbt` with the signatures the device added
+    async fn sign_psbt(&self, psbt: &PSBT) -> Result<PSBT, ExternalSignerError>;
+}
+
+/// Have `signer` sign `psbt` and merge its signatures in
+///
+/// Fails if the device changed the transaction. Returns the number of
+/// inputs that gained a signature.
+pub async fn sign_external(
+    signer: &dyn ExternalSigner,
+    psbt: &mut PSBT,
+) -> Result<usize, ExternalSignerError> {
+    let signed = signer.sign_psbt(psbt).await?;
+    if signed.unsigned_tx != psbt.unsigned_tx {
+        return Err(ExternalSignerError::Protocol(
+            "device returned a different transaction".to_string(),
+        ));
+    }
+
+    let before: Vec<usize> = psbt.inputs.iter().map(signature_count).collect();
+    psbt.combine(signed)
+        .map_err(|e| ExternalSignerError::Protocol(e.to_string()))?;
+    Ok(psbt
+        .inputs
+        .iter()
+        .zip(before)
+        .filter(|(input, before)| signature_count(input) > *before)
+        .count())
+}
+
+fn signature_count(input: &bitcoin::psbt::Input) -> usize {
+    input.partial_sigs.len()
+        + input.tap_script_sigs.len()
+        + usize::from(input.tap_key_sig.is_some())
+        + usize::from(input.final_script_sig.is_some() || input.final_script_witness.is_some())
+}
+
+/// Runs an `hwi` command and returns its standard output
+#[async_trait]
+pub trait HwiTransport: Send + Sync {
+    async fn call(&self, args: &[String]) -> Result<String, ExternalSignerError>;
+}
+
+/// The `hwi` executable
+#[derive(Debug, Clone)]
+pub struct HwiCommand {
+    binary: PathBuf,
+}
+
+impl HwiCommand {
+    pub fn new(binary: impl Into<PathBuf>) -> Self {
+        Self {
+            binary: binary.into(),
+        }
+    }
+}
+
+impl Default for HwiCommand {
+    fn default() -> Self {
+        Self::new("hwi")
+    }
+}
+
+#[async_trait]
+impl HwiTransport for HwiCommand {
+    async fn call(&self, args: &[String]) -> Result<String, ExternalSignerError> {
+        // Killed on drop, so a timed out command does not keep the device busy
+        let output = tokio::process::Command::new(&self.binary)
+            .args(args)
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|e| ExternalSignerError::Io(format!("{}: {e}", self.binary.display())))?;
+        // HWI reports device errors as JSON on stdout with a non-zero status
+        if output.stdout.is_empty() && !output.status.success() {
+            return Err(ExternalSignerError::Io(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        String::from_utf8(output.stdout).map_err(|e| ExternalSignerError::Protocol(e.to_string()))
+    }
+}
+
+/// [`ExternalSigner`] for hardware wallets supported by HWI
+pub struct HwiSigner<T = HwiCommand> {
+    transport: T,
+    network: Network,
+    fingerprint: Option<Fingerprint>,
+    timeout: Duration,
+}
+
+impl HwiSigner<HwiCommand> {
+    /// Use the `hwi` executable on the `PATH`
+    pub fn new(network: Network) -> Self {
+        Self::with_transport(HwiCommand::default(), network)
+    }
+}
+
+impl<T: HwiTransport> HwiSigner<T> {
+    pub fn with_transport(transport: T, network: Network) -> Self {
+        Self {
+            transport,
+            network,
+            fingerprint: None,
+            timeout: DEFAULT_DEVICE_TIMEOUT,
+        }
+    }
+
+    /// Talk to the device with this master fingerprint
+    ///
+    /// Without one, HWI picks the first device it finds.
+    pub fn with_fingerprint(mut self, fingerprint: Fingerprint) -> Self {
+        self.fingerprint = Some(fingerprint);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run `command`, returning its JSON result or the error HWI reported
+    async fn run(&self, command: &[&str]) -> Result<serde_json::Value, ExternalSignerError> {
+        let chain = match self.network {
+            Network::Bitcoin => "main",
+            Network::Signet => "signet",
+            Network::Regtest => "regtest",
+            _ => "test",
+        };
+        let mut args = vec!["--chain".to_string(), chain.to_string()];
+        if let Some(fingerprint) = self.fingerprint {
+            args.push("--fingerprint".to_string());
+            args.push(fingerprint.to_string());
+        }
+        args.extend(command.iter().map(|arg| arg.to_string()));
+
+        let output = tokio::time::timeout(self.timeout, self.transport.call(&args))
+            .await
+            .map_err(|_| ExternalSignerError::Timeout(self.timeout))??;
+        let value: serde_json::Value = serde_json::from_str(&output)
+            .map_err(|e| ExternalSignerError::Protocol(format!("{e}: {output}")))?;
+        match value.get("error") {
+            Some(error) => Err(device_error(
+                value.get("code").and_then(|code| code.as_i64()),
+                error,
+            )),
+            None => Ok(value),
+        }
+    }
+
+    async fn field(&self, command: &[&str], field: &str) -> Result<String, ExternalSignerError> {
+        let value = self.run(command).await?;
+        value
+            .get(field)
+            .and_then(|field| field.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| ExternalSignerError::Protocol(format!("missing {field:?} in {value}")))
+    }
+}
+
+fn device_error(code: Option<i64>, error: &serde_json::Value) -> ExternalSignerError {
+    let message = error
+        .as_str()
+        .map_or_else(|| error.to_string(), str::to_string);
+    match code {
+        Some(HWI_ACTION_CANCELED) => ExternalSignerError::Declined,
+        Some(code) => ExternalSignerError::Device { code, message },
+        None if message.contains("No device") => ExternalSignerError::NoDevice,
+        None => ExternalSignerError::Device { code: 0, message },
+    }
+}
+
+#[async_trait]
+impl<T: HwiTransport> ExternalSigner for HwiSigner<T> {
+    async fn enumerate(&self) -> Result<Vec<SignerDevice>, ExternalSignerError> {
+        let value = self.run(&["enumerate"]).await?;
+        let devices: Vec<serde_json::Value> = serde_json::from_value(value)
+            .map_err(|e| ExternalSignerError::Protocol(e.to_string()))?;
+        // Devices HWI could not open are listed with an error instead
+        devices
+            .into_iter()
+            .filter(|device| device.get("error").is_none())
+            .map(|device| {
+                serde_json::from_value(device)
+                    .map_err(|e| ExternalSignerError::Protocol(e.to_string()))
+            })
+            .collect()
+    }
+
+    async fn get_xpub(&self, path: &DerivationPath) -> Result<Xpub, ExternalSignerError> {
+        let xpub = self
+            .field(&["getxpub", &format!("m/{path}")], "xpub")
+            .await?;
+        Xpub::from_str(&xpub).map_err(|e| ExternalSignerError::Protocol(e.to_string()))
+    }
+
+    async fn display_address(
+        &self,
+        path: &DerivationPath,
+        address_type: AddressType,
+    ) -> Result<Address, ExternalSignerError> {
+        let addr_type = match address_type {
+            AddressType::Legacy => "legacy",
+            AddressType::SegWit => "wit",
+            AddressType::NestedSegWit => "sh_wit",
+            AddressType::Taproot => "tap",
+        };
+        let path = format!("m/{path}");
+        let address = self
+            .field(
+                &["displayaddress", "--path", &path, "--addr-type", addr_type],
+                "address",
+            )
+            .await?;
+        Address::from_str(&address)
+            .and_then(|address| address.require_network(self.network))
+            .map_err(|e| ExternalSignerError::Protocol(e.to_string()))
+    }
+
+    async fn sign_psbt(&self, psbt: &PSBT) -> Result<PSBT, ExternalSignerError> {
+        let signed = self.field(&["signtx", &psbt.to_string()], "psbt").await?;
+        PSBT::from_str(&signed).map_err(|e| ExternalSignerError::Protocol(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::signer::{self, SighashType};
+    use bitcoin::absolute::LockTime;
+    use bitcoin::bip32::Xpriv;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::Secp256k1;
+    use bitcoin::transaction::Version;
+    use bitcoin::{
+        Amount, CompressedPublicKey, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
+        Witness,
+    };
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Device answering like `hwi` would, signing with a test key
+    struct MockDevice {
+        xpriv: Xpriv,
+        path: DerivationPath,
+        /// Answer for every command instead of acting on it
+        canned: Option<serde_json::Value>,
+        delay: Duration,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl MockDevice {
+        fn new() -> Self {
+            Self {
+                xpriv: Xpriv::new_master(Network::Regtest, &[9; 32]).unwrap(),
+                path: DerivationPath::from_str("m/84'/1'/0'/0/0").unwrap(),
+                canned: None,
+                delay: Duration::ZERO,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn fingerprint(&self) -> Fingerprint {
+            self.xpriv.fingerprint(&Secp256k1::new())
+        }
+
+        fn address(&self) -> Address {
+            let secp = Secp256k1::new();
+            let key = self.xpriv.derive_priv(&secp, &self.path).unwrap();
+            let public_key = CompressedPublicKey(key.private_key.public_key(&secp));
+            Address::p2wpkh(&public_key, Network::Regtest)
+        }
+    }
+
+    #[async_trait]
+    impl HwiTransport for MockDevice {
+        async fn call(&self, args: &[String]) -> Result<String, ExternalSignerError> {
+            self.calls.lock().unwrap().push(args.to_vec());
+            tokio::time::sleep(self.delay).await;
+            if let Some(canned) = &self.canned {
+                return Ok(canned.to_string());
+            }
+
+            let secp = Secp256k1::new();
+            let command = args.iter().position(|arg| {
+                ["enumerate", "getxpub", "displayaddress", "signtx"].contains(&arg.as_str())
+            });
+            let response = match command.map(|index| (args[index].as_str(), &args[index + 1..])) {
+                Some(("enumerate", _)) => json!([
+                    {
+                        "type": "trezor", "model": "trezor_t", "path": "webusb:001:4",
+                        "fingerprint": self.fingerprint().to_string(),
+                        "needs_pin_sent": false, "needs_passphrase_sent": false,
+                    },
+                    {
+                        "type": "ledger", "model": "ledger_nano_x", "path": "hid:002",
+                        "error": "Could not open client", "code": -13,
+                    },
+                ]),
+                Some(("getxpub", [path])) => {
+                    let path = DerivationPath::from_str(path).unwrap();
+                    let xpriv = self.xpriv.derive_priv(&secp, &path).unwrap();
+                    json!({ "xpub": Xpub::from_priv(&secp, &xpriv).to_string() })
+                }
+                Some(("displayaddress", _)) => json!({ "address": self.address().to_string() }),
+                Some(("signtx", [psbt])) => {
+                    let mut psbt = PSBT::from_str(psbt).unwrap();
+                    let key = self.xpriv.derive_priv(&secp, &self.path).unwrap();
+                    let count =
+                        signer::sign_psbt(&secp, &mut psbt, &key.private_key, SighashType::All)
+                            .unwrap();
+                    json!({ "psbt": psbt.to_string(), "signed": count > 0 })
+                }
+                _ => json!({ "error": "Unknown command", "code": -7 }),
+            };
+            Ok(response.to_string())
+        }
+    }
+
+    fn psbt_spending(address: &Address) -> PSBT {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(90_000),
+                script_pubkey: ScriptBuf::new_op_return([]),
+            }],
+        };
+        let mut psbt = PSBT::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: Amount::from_sat(100_000),
+            script_pubkey: address.script_pubkey(),
+        });
+        psbt
+    }
+
+    #[tokio::test]
+    async fn test_hwi_signer_talks_to_device() {
+        let device = MockDevice::new();
+        let fingerprint = device.fingerprint();
+        let address = device.address();
+        let signer =
+            HwiSigner::with_transport(device, Network::Regtest).with_fingerprint(fingerprint);
+
+        let devices = signer.enumerate().await.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].fingerprint, Some(fingerprint));
+        assert_eq!(devices[0].device_type, "trezor");
+
+        let account = DerivationPath::from_str("m/84'/1'/0'").unwrap();
+        let xpub = signer.get_xpub(&account).await.unwrap();
+        assert_eq!(xpub.parent_fingerprint, {
+            let secp = Secp256k1::new();
+            let parent = DerivationPath::from_str("m/84'/1'").unwrap();
+            let xpriv = signer.transport.xpriv.derive_priv(&secp, &parent).unwrap();
+            xpriv.fingerprint(&secp)
+        });
+
+        let path = DerivationPath::from_str("m/84'/1'/0'/0/0").unwrap();
+        let shown = signer
+            .display_address(&path, AddressType::SegWit)
+            .await
+            .unwrap();
+        assert_eq!(shown, address);
+
+        let mut psbt = psbt_spending(&address);
+        assert_eq!(sign_external(&signer, &mut psbt).await.unwrap(), 1);
+        assert_eq!(psbt.inputs[0].partial_sigs.len(), 1);
+        // Nothing left for the device to sign
+        assert_eq!(sign_external(&signer, &mut psbt).await.unwrap(), 0);
+
+        let calls = signer.transport.calls.lock().unwrap();
+        assert_eq!(
+            calls[1],
+            [
+                "--chain",
+                "regtest",
+                "--fingerprint",
+                &fingerprint.to_string(),
+                "getxpub",
+                "m/84'/1'/0'"
+            ]
+        );
+        assert_eq!(
+            calls[2][4..],
+            [
+                "displayaddress",
+                "--path",
+                "m/84'/1'/0'/0/0",
+                "--addr-type",
+                "wit"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hwi_signer_timeout_and_declined() {
+        let mut device = MockDevice::new();
+        device.delay = Duration::from_millis(200);
+        let signer = HwiSigner::with_transport(device, Network::Regtest)
+            .with_timeout(Duration::from_millis(20));
+        let mut psbt = psbt_spending(&signer.transport.address());
+        assert!(matches!(
+            sign_external(&signer, &mut psbt).await,
+            Err(ExternalSignerError::Timeout(_))
+        ));
+        assert!(psbt.inputs[0].partial_sigs.is_empty());
+
+        let mut device = MockDevice::new();
+        device.canned = Some(json!({ "error": "Sign transaction canceled by user", "code": -14 }));
+        let signer = HwiSigner::with_transport(device, Network::Regtest);
+        assert!(matches!(
+            sign_external(&signer, &mut psbt).await,
+            Err(ExternalSignerError::Declined)
+        ));
+
+        let mut device = MockDevice::new();
+        device.canned = Some(json!({ "error": "Device busy", "code": -15 }));
+        let signer = HwiSigner::with_transport(device, Network::Regtest);
+        assert!(matches!(
+            signer.enumerate().await,
+            Err(ExternalSignerError::Device { code: -15, .. })
+        ));
+
+        // A device that answers with another transaction is not trusted
+        let mut device = MockDevice::new();
+        let mut other = psbt_spending(&device.address());
+        other.unsigned_tx.output[0].value = Amount::from_sat(1);
+        device.canned = Some(json!({ "psbt": other.to_string(), "signed": true }));
+        let signer = HwiSigner::with_transport(device, Network::Regtest);
+        assert!(matches!(
+            sign_external(&signer, &mut psbt).await,
+            Err(ExternalSignerError::Protocol(_))
+        ));
+    }
+}
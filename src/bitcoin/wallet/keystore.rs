@@ -0,0 +1,345 @@
+// [AIR-3][AIS-3][BPC-3][AIT-3] Encrypted wallet seed storage
+//
+// The seed is encrypted with ChaCha20-Poly1305 under a key derived from the
+// passphrase with Argon2id. The random salt and nonce and the KDF cost are
+// stored next to the ciphertext, and the cost is authenticated with it, so a
+// wrong passphrase or a tampered file fails decryption instead of yielding a
+// different seed.
+
+use super::{bip32, BitcoinWallet, WalletConfig, WalletIndexes, WalletMetadata, WalletStorage};
+use crate::bitcoin::error::BitcoinError;
+use crate::security::rng::secure_rng;
+use crate::{AnyaError, AnyaResult};
+use argon2::{Algorithm, Argon2, Params, Version};
+use bitcoin::secp256k1::Secp256k1;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// Format version of [`EncryptedSeed`]
+const KEYSTORE_VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Wallet keystore errors
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    #[error("Key derivation failed: {0}")]
+    Kdf(String),
+    #[error("Wrong passphrase or corrupted keystore")]
+    Decryption,
+    #[error("Invalid keystore: {0}")]
+    Format(String),
+}
+
+impl From<KeystoreError> for AnyaError {
+    fn from(err: KeystoreError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+/// Argon2id cost parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory size in KiB
+    pub memory_cost: u32,
+    /// Number of passes
+    pub iterations: u32,
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_cost: 65536, // 64 MiB
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+/// [AIS-3] Wallet seed encrypted under a passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedSeed {
+    pub version: u8,
+    pub kdf: KdfParams,
+    pub salt: [u8; SALT_LEN],
+    pub nonce: [u8; NONCE_LEN],
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedSeed {
+    /// Encrypt `seed` with a fresh salt and nonce
+    pub fn encrypt(seed: &[u8], passphrase: &str, kdf: KdfParams) -> Result<Self, KeystoreError> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        let mut rng = secure_rng();
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+
+        let key = derive_key(passphrase, &salt, &kdf)?;
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key[..]))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: seed,
+                    aad: &associated_data(KEYSTORE_VERSION, &kdf),
+                },
+            )
+            .map_err(|_| KeystoreError::Format("Seed encryption failed".to_string()))?;
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            kdf,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Decrypt the seed, failing with [`KeystoreError::Decryption`] on a wrong passphrase
+    pub fn decrypt(&self, passphrase: &str) -> Result<Zeroizing<Vec<u8>>, KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::Format(format!(
+                "Unsupported version {}",
+                self.version
+            )));
+        }
+        let key = derive_key(passphrase, &self.salt, &self.kdf)?;
+        ChaCha20Poly1305::new(Key::from_slice(&key[..]))
+            .decrypt(
+                Nonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &associated_data(self.version, &self.kdf),
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| KeystoreError::Decryption)
+    }
+
+    /// Write the keystore to `path`, replacing any existing file
+    pub fn save(&self, path: impl AsRef<Path>) -> AnyaResult<()> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?).map_err(|e| io_error(&tmp, e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))
+                .map_err(|e| io_error(&tmp, e))?;
+        }
+        std::fs::rename(&tmp, path).map_err(|e| io_error(path, e))
+    }
+
+    /// Read a keystore written by [`EncryptedSeed::save`]
+    pub fn load(path: impl AsRef<Path>) -> AnyaResult<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| io_error(path, e))?;
+        serde_json::from_slice(&data)
+            .map_err(|e| KeystoreError::Format(format!("{}: {e}", path.display())).into())
+    }
+}
+
+/// Header fields bound to the ciphertext so they cannot be altered undetected
+fn associated_data(version: u8, kdf: &KdfParams) -> Vec<u8> {
+    format!(
+        "anya-keystore:{version}:{}:{}:{}",
+        kdf.memory_cost, kdf.iterations, kdf.parallelism
+    )
+    .into_bytes()
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> AnyaError {
+    AnyaError::System(format!("Wallet keystore {}: {err}", path.display()))
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    kdf: &KdfParams,
+) -> Result<Zeroizing<[u8; 32]>, KeystoreError> {
+    let params = Params::new(kdf.memory_cost, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| KeystoreError::Kdf(e.to_string()))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key[..])
+        .map_err(|e| KeystoreError::Kdf(e.to_string()))?;
+    Ok(key)
+}
+
+impl BitcoinWallet {
+    /// [AIS-3] Encrypt the wallet seed under `passphrase` and write it to `path`
+    ///
+    /// Without a loaded seed, the seed is derived from the configured
+    /// mnemonic; watch-only wallets have nothing to save.
+    pub fn save_encrypted(&self, path: impl AsRef<Path>, passphrase: &str) -> AnyaResult<()> {
+        let seed = match (&self.seed, &self.config.seed_phrase) {
+            (Some(seed), _) => seed.clone(),
+            (None, Some(phrase)) => Zeroizing::new(bip32::seed_from_mnemonic(
+                phrase,
+                self.config.password.as_deref().unwrap_or(""),
+            )?),
+            (None, None) => {
+                return Err(BitcoinError::Wallet("Wallet has no seed".to_string()).into())
+            }
+        };
+
+        EncryptedSeed::encrypt(&seed[..], passphrase, KdfParams::default())?.save(path)
+    }
+
+    /// [AIS-3] Open the wallet described by `config` with a seed saved by
+    /// [`BitcoinWallet::save_encrypted`]
+    ///
+    /// A wrong passphrase fails with an error. The wallet has no addresses or
+    /// UTXOs; rescan to find its funds.
+    pub fn load_encrypted(
+        config: WalletConfig,
+        path: impl AsRef<Path>,
+        passphrase: &str,
+    ) -> AnyaResult<BitcoinWallet> {
+        let decrypted = EncryptedSeed::load(path)?.decrypt(passphrase)?;
+        let seed =
+            Zeroizing::new(<[u8; 64]>::try_from(&decrypted[..]).map_err(|_| {
+                KeystoreError::Format(format!("Seed is {} bytes", decrypted.len()))
+            })?);
+        let master = bip32::derive_master_key(&seed[..], config.network)?;
+        let secp = Secp256k1::new();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let storage = WalletStorage {
+            metadata: WalletMetadata {
+                created_at: now,
+                updated_at: now,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                network: config.network,
+                master_fingerprint: Some(master.xpriv.fingerprint(&secp).to_bytes()),
+                labels: HashMap::new(),
+            },
+            utxos: HashMap::new(),
+            transactions: HashMap::new(),
+            addresses: HashMap::new(),
+            indexes: WalletIndexes {
+                receive_index: 0,
+                change_index: 0,
+                last_block: None,
+                last_sync: None,
+            },
+            labels: Default::default(),
+        };
+        Ok(BitcoinWallet {
+            config,
+            storage: Arc::new(Mutex::new(storage)),
+            secp,
+            seed: Some(seed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::{CoinSelectionStrategy, FeeStrategy, WalletType};
+    use bitcoin::Network;
+    use std::path::PathBuf;
+
+    /// Cheap parameters so tests run quickly in debug builds
+    const TEST_KDF: KdfParams = KdfParams {
+        memory_cost: 256,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn config() -> WalletConfig {
+        WalletConfig {
+            wallet_type: WalletType::Standard,
+            network: Network::Regtest,
+            name: "keystore".to_string(),
+            seed_phrase: None,
+            password: None,
+            receive_descriptor: String::new(),
+            change_descriptor: String::new(),
+            xpub: None,
+            data_dir: PathBuf::new(),
+            use_rpc: false,
+            coin_selection: CoinSelectionStrategy::LargestFirst,
+            gap_limit: 20,
+            min_confirmations: 1,
+            fee_strategy: FeeStrategy::Medium,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let seed = [7u8; 64];
+        let encrypted = EncryptedSeed::encrypt(&seed, "correct horse", TEST_KDF).unwrap();
+        assert_ne!(&encrypted.ciphertext[..64], &seed[..]);
+        assert_eq!(&encrypted.decrypt("correct horse").unwrap()[..], &seed[..]);
+
+        // Salt and nonce are fresh for every encryption
+        let again = EncryptedSeed::encrypt(&seed, "correct horse", TEST_KDF).unwrap();
+        assert_ne!(encrypted.salt, again.salt);
+        assert_ne!(encrypted.ciphertext, again.ciphertext);
+    }
+
+    #[test]
+    fn test_wrong_passphrase_and_tampering_rejected() {
+        let encrypted = EncryptedSeed::encrypt(&[7u8; 64], "correct horse", TEST_KDF).unwrap();
+        assert!(matches!(
+            encrypted.decrypt("battery staple"),
+            Err(KeystoreError::Decryption)
+        ));
+
+        let mut weakened = encrypted.clone();
+        weakened.kdf.iterations = 2;
+        assert!(matches!(
+            weakened.decrypt("correct horse"),
+            Err(KeystoreError::Decryption)
+        ));
+
+        let mut flipped = encrypted;
+        flipped.ciphertext[0] ^= 1;
+        assert!(matches!(
+            flipped.decrypt("correct horse"),
+            Err(KeystoreError::Decryption)
+        ));
+    }
+
+    #[test]
+    fn test_wallet_save_and_load_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.keystore");
+        let seed = [7u8; 64];
+        EncryptedSeed::encrypt(&seed, "correct horse", TEST_KDF)
+            .unwrap()
+            .save(&path)
+            .unwrap();
+
+        assert!(BitcoinWallet::load_encrypted(config(), &path, "battery staple").is_err());
+        let wallet = BitcoinWallet::load_encrypted(config(), &path, "correct horse").unwrap();
+        assert_eq!(wallet.seed.as_deref(), Some(&seed));
+        let master = bip32::derive_master_key(&seed, Network::Regtest).unwrap();
+        assert_eq!(
+            wallet.backup_contents().master_fingerprint,
+            Some(master.xpriv.fingerprint(&wallet.secp).to_bytes())
+        );
+
+        // Re-saving under a new passphrase keeps the seed
+        let resaved = dir.path().join("resaved.keystore");
+        wallet.save_encrypted(&resaved, "battery staple").unwrap();
+        assert!(BitcoinWallet::load_encrypted(config(), &resaved, "correct horse").is_err());
+        let restored = BitcoinWallet::load_encrypted(config(), &resaved, "battery staple").unwrap();
+        assert_eq!(restored.seed.as_deref(), Some(&seed));
+    }
+}
@@ -0,0 +1,421 @@
+// Wallet labels in the BIP-329 format
+//
+// Labels attach a human-readable note to a transaction, address, public key,
+// input, output or xpub of the wallet. They are stored with the wallet,
+// carried by backups, and exported and imported as BIP-329 JSON Lines: one
+// `{"type", "ref", "label", "origin", "spendable"}` record per line, which
+// other wallets read and write. Import validates every line and skips the
+// malformed ones instead of failing the whole file.
+
+use super::{BitcoinWallet, WalletError};
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::bip32::Xpub;
+use bitcoin::{Address, OutPoint, PublicKey, Txid};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// [AIR-3][BPC-3] What a label is attached to
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LabelRef {
+    Tx(Txid),
+    /// Address string as exported
+    Addr(String),
+    /// Hex-encoded public key
+    Pubkey(String),
+    /// Output spent by a wallet transaction
+    Input(OutPoint),
+    Output(OutPoint),
+    Xpub(String),
+}
+
+impl LabelRef {
+    /// BIP-329 `type` of the reference
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Tx(_) => "tx",
+            Self::Addr(_) => "addr",
+            Self::Pubkey(_) => "pubkey",
+            Self::Input(_) => "input",
+            Self::Output(_) => "output",
+            Self::Xpub(_) => "xpub",
+        }
+    }
+
+    /// Parse a BIP-329 `type` and `ref` pair
+    pub fn parse(kind: &str, reference: &str) -> Result<Self, String> {
+        let invalid = |e: &dyn fmt::Display| format!("Invalid {kind} reference '{reference}': {e}");
+        Ok(match kind {
+            "tx" => Self::Tx(Txid::from_str(reference).map_err(|e| invalid(&e))?),
+            "addr" => {
+                Address::<NetworkUnchecked>::from_str(reference).map_err(|e| invalid(&e))?;
+                Self::Addr(reference.to_string())
+            }
+            "pubkey" => {
+                PublicKey::from_str(reference).map_err(|e| invalid(&e))?;
+                Self::Pubkey(reference.to_string())
+            }
+            "input" => Self::Input(OutPoint::from_str(reference).map_err(|e| invalid(&e))?),
+            "output" => Self::Output(OutPoint::from_str(reference).map_err(|e| invalid(&e))?),
+            "xpub" => {
+                Xpub::from_str(reference).map_err(|e| invalid(&e))?;
+                Self::Xpub(reference.to_string())
+            }
+            _ => return Err(format!("Unknown label type '{kind}'")),
+        })
+    }
+}
+
+impl fmt::Display for LabelRef {
+    /// The BIP-329 `ref` string
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tx(txid) => write!(f, "{txid}"),
+            Self::Input(outpoint) | Self::Output(outpoint) => write!(f, "{outpoint}"),
+            Self::Addr(s) | Self::Pubkey(s) | Self::Xpub(s) => f.write_str(s),
+        }
+    }
+}
+
+/// BIP-329 record as it appears on a line of the export
+#[derive(Serialize, Deserialize)]
+struct Bip329Record {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(rename = "ref")]
+    reference: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    origin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spendable: Option<bool>,
+}
+
+/// [AIR-3][BPC-3] A BIP-329 label
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Bip329Record", into = "Bip329Record")]
+pub struct Label {
+    pub label_ref: LabelRef,
+    pub label: Option<String>,
+    /// Key origin descriptor of the wallet the reference belongs to
+    pub origin: Option<String>,
+    /// Whether an output may be spent; only valid on outputs
+    pub spendable: Option<bool>,
+}
+
+impl TryFrom<Bip329Record> for Label {
+    type Error = String;
+
+    fn try_from(record: Bip329Record) -> Result<Self, String> {
+        let label_ref = LabelRef::parse(&record.kind, &record.reference)?;
+        if record.spendable.is_some() && !matches!(label_ref, LabelRef::Output(_)) {
+            return Err(format!(
+                "'spendable' is only valid on outputs, not {}",
+                record.kind
+            ));
+        }
+        if record.label.is_none() && record.spendable.is_none() {
+            return Err(format!(
+                "Label record for {} has no label",
+                record.reference
+            ));
+        }
+        Ok(Self {
+            label_ref,
+            label: record.label,
+            origin: record.origin,
+            spendable: record.spendable,
+        })
+    }
+}
+
+impl From<Label> for Bip329Record {
+    fn from(label: Label) -> Self {
+        Self {
+            kind: label.label_ref.kind().to_string(),
+            reference: label.label_ref.to_string(),
+            label: label.label,
+            origin: label.origin,
+            spendable: label.spendable,
+        }
+    }
+}
+
+/// Labels of a wallet, one per reference, serialized as a list of records
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<Label>", into = "Vec<Label>")]
+pub(crate) struct LabelStore(BTreeMap<LabelRef, Label>);
+
+impl LabelStore {
+    pub(crate) fn insert(&mut self, label: Label) {
+        self.0.insert(label.label_ref.clone(), label);
+    }
+
+    pub(crate) fn get(&self, label_ref: &LabelRef) -> Option<&Label> {
+        self.0.get(label_ref)
+    }
+
+    pub(crate) fn remove(&mut self, label_ref: &LabelRef) {
+        self.0.remove(label_ref);
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Label> {
+        self.0.values()
+    }
+}
+
+impl From<Vec<Label>> for LabelStore {
+    fn from(labels: Vec<Label>) -> Self {
+        Self(
+            labels
+                .into_iter()
+                .map(|label| (label.label_ref.clone(), label))
+                .collect(),
+        )
+    }
+}
+
+impl From<LabelStore> for Vec<Label> {
+    fn from(store: LabelStore) -> Self {
+        store.0.into_values().collect()
+    }
+}
+
+/// [AIR-3][BPC-3] Outcome of [`BitcoinWallet::import_labels`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LabelImport {
+    pub imported: usize,
+    /// Malformed lines that were skipped
+    pub skipped: usize,
+}
+
+impl BitcoinWallet {
+    /// Attach `label` to `label_ref`, replacing any previous label
+    ///
+    /// An empty label removes it.
+    pub fn set_label(&self, label_ref: LabelRef, label: &str) -> Result<(), WalletError> {
+        let label_ref = self.check_label_ref(label_ref)?;
+        let mut storage = self.storage.lock().unwrap_or_else(|e| e.into_inner());
+        if label.is_empty() {
+            storage.labels.remove(&label_ref);
+            return Ok(());
+        }
+        let previous = storage.labels.get(&label_ref).cloned();
+        storage.labels.insert(Label {
+            label_ref,
+            label: Some(label.to_string()),
+            origin: previous.as_ref().and_then(|p| p.origin.clone()),
+            spendable: previous.and_then(|p| p.spendable),
+        });
+        Ok(())
+    }
+
+    pub fn get_label(&self, label_ref: &LabelRef) -> Option<String> {
+        let storage = self.storage.lock().unwrap_or_else(|e| e.into_inner());
+        storage.labels.get(label_ref)?.label.clone()
+    }
+
+    /// Every label as BIP-329 JSON Lines
+    pub fn export_labels(&self) -> Result<String, WalletError> {
+        let storage = self.storage.lock().unwrap_or_else(|e| e.into_inner());
+        let mut export = String::new();
+        for label in storage.labels.iter() {
+            let line = serde_json::to_string(label)
+                .map_err(|e| WalletError::SerializationError(e.to_string()))?;
+            export.push_str(&line);
+            export.push('\n');
+        }
+        Ok(export)
+    }
+
+    /// Import BIP-329 JSON Lines, replacing labels with the same reference
+    ///
+    /// Blank lines are ignored; malformed lines, and addresses of another
+    /// network, are skipped with a warning and counted.
+    pub fn import_labels(&self, jsonl: &str) -> LabelImport {
+        let mut result = LabelImport::default();
+        let mut labels = Vec::new();
+        for (number, line) in jsonl.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let label = serde_json::from_str::<Label>(line)
+                .map_err(|e| e.to_string())
+                .and_then(|label| {
+                    let label_ref = self
+                        .check_label_ref(label.label_ref)
+                        .map_err(|e| e.to_string())?;
+                    Ok(Label { label_ref, ..label })
+                });
+            match label {
+                Ok(label) => labels.push(label),
+                Err(e) => {
+                    log::warn!("Skipping BIP-329 label on line {}: {e}", number + 1);
+                    result.skipped += 1;
+                }
+            }
+        }
+
+        let mut storage = self.storage.lock().unwrap_or_else(|e| e.into_inner());
+        result.imported = labels.len();
+        for label in labels {
+            storage.labels.insert(label);
+        }
+        result
+    }
+
+    /// Validate `label_ref`, and that an address is on the wallet's network
+    fn check_label_ref(&self, label_ref: LabelRef) -> Result<LabelRef, WalletError> {
+        let label_ref = LabelRef::parse(label_ref.kind(), &label_ref.to_string())
+            .map_err(WalletError::InvalidParameters)?;
+        if let LabelRef::Addr(address) = &label_ref {
+            Address::<NetworkUnchecked>::from_str(address)
+                .map_err(|e| WalletError::InvalidParameters(e.to_string()))?
+                .require_network(self.config.network)
+                .map_err(|e| WalletError::InvalidParameters(e.to_string()))?;
+        }
+        Ok(label_ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::{
+        CoinSelectionStrategy, FeeStrategy, WalletConfig, WalletIndexes, WalletMetadata,
+        WalletStorage, WalletType,
+    };
+    use bitcoin::secp256k1::Secp256k1;
+    use bitcoin::Network;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    /// Example export from BIP-329
+    const BIP329_EXAMPLE: &str = include_str!("../../../tests/data/bip329_labels.jsonl");
+
+    fn wallet() -> BitcoinWallet {
+        BitcoinWallet {
+            config: WalletConfig {
+                wallet_type: WalletType::Standard,
+                network: Network::Bitcoin,
+                name: "labels".to_string(),
+                seed_phrase: None,
+                password: None,
+                receive_descriptor: String::new(),
+                change_descriptor: String::new(),
+                xpub: None,
+                data_dir: PathBuf::new(),
+                use_rpc: false,
+                coin_selection: CoinSelectionStrategy::LargestFirst,
+                gap_limit: 20,
+                min_confirmations: 1,
+                fee_strategy: FeeStrategy::Medium,
+            },
+            storage: Arc::new(Mutex::new(WalletStorage {
+                metadata: WalletMetadata {
+                    created_at: 0,
+                    updated_at: 0,
+                    version: "1".to_string(),
+                    network: Network::Bitcoin,
+                    master_fingerprint: None,
+                    labels: HashMap::new(),
+                },
+                utxos: HashMap::new(),
+                transactions: HashMap::new(),
+                addresses: HashMap::new(),
+                indexes: WalletIndexes {
+                    receive_index: 0,
+                    change_index: 0,
+                    last_block: None,
+                    last_sync: None,
+                },
+                labels: LabelStore::default(),
+            })),
+            secp: Secp256k1::new(),
+            seed: None,
+        }
+    }
+
+    fn records(jsonl: &str) -> Vec<serde_json::Value> {
+        let mut records: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        records.sort_by_key(|record| record.to_string());
+        records
+    }
+
+    #[test]
+    fn test_bip329_example_round_trip() {
+        let wallet = wallet();
+        let result = wallet.import_labels(BIP329_EXAMPLE);
+        assert_eq!(
+            result,
+            LabelImport {
+                imported: 7,
+                skipped: 0
+            }
+        );
+
+        let tx = LabelRef::parse(
+            "tx",
+            "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd",
+        )
+        .unwrap();
+        assert_eq!(wallet.get_label(&tx).as_deref(), Some("Transaction"));
+
+        let export = wallet.export_labels().unwrap();
+        assert_eq!(records(&export), records(BIP329_EXAMPLE));
+
+        let restored = self::wallet();
+        assert_eq!(restored.import_labels(&export).imported, 7);
+        assert_eq!(restored.export_labels().unwrap(), export);
+
+        // Relabeling keeps the origin, and an empty label removes the entry
+        wallet.set_label(tx.clone(), "Rent").unwrap();
+        assert!(wallet
+            .export_labels()
+            .unwrap()
+            .contains(r#""label":"Rent","origin":"wpkh([d34db33f/84'/0'/0'])""#));
+        wallet.set_label(tx.clone(), "").unwrap();
+        assert_eq!(wallet.get_label(&tx), None);
+    }
+
+    #[test]
+    fn test_malformed_lines_skipped() {
+        let wallet = wallet();
+        let txid = "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd";
+        let jsonl = [
+            format!(r#"{{"type":"tx","ref":"{txid}","label":"Valid"}}"#),
+            String::new(),
+            format!(r#"{{"type":"block","ref":"{txid}","label":"Unknown type"}}"#),
+            r#"{"type":"tx","ref":"not-a-txid","label":"Bad ref"}"#.to_string(),
+            format!(r#"{{"type":"tx","ref":"{txid}","label":"Tx","spendable":true}}"#),
+            format!(r#"{{"type":"output","ref":"{txid}:0"}}"#),
+            format!(r#"{{"type":"tx","ref":"{txid}","label":7}}"#),
+            r#"{"type":"addr","ref":"tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx","label":"Testnet"}"#
+                .to_string(),
+            "not json".to_string(),
+        ]
+        .join("\n");
+
+        let result = wallet.import_labels(&jsonl);
+        assert_eq!(
+            result,
+            LabelImport {
+                imported: 1,
+                skipped: 7
+            }
+        );
+        assert_eq!(wallet.export_labels().unwrap().lines().count(), 1);
+
+        let testnet = LabelRef::Addr("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string());
+        assert!(wallet.set_label(testnet, "Testnet").is_err());
+        assert!(wallet
+            .set_label(LabelRef::Pubkey("02ab".to_string()), "Short key")
+            .is_err());
+    }
+}
@@ -6,7 +6,7 @@
 // privacy, and protocol compliance ratings.
 
 use crate::bitcoin::error::BitcoinError;
-use crate::bitcoin::interface::BitcoinInterface;
+use crate::bitcoin::chain_source::ChainSource;
 use crate::{AnyaError, AnyaResult};
 use async_trait::async_trait;
 use bitcoin::absolute::LockTime;
@@ -23,12 +23,27 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
+use zeroize::Zeroizing;
 
+pub mod backup;
 pub mod bip32;
+pub mod builder;
+pub mod external_signer;
+pub mod keystore;
+pub mod labels;
+pub mod payjoin;
+pub mod psbt;
+pub mod rescan;
+pub mod reserves;
+pub mod scan;
+pub mod signer;
+pub mod sweep;
+pub mod timelock;
 pub mod transactions;
+pub mod vault;
 pub mod advanced_features;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WalletType {
     Standard,         // Basic Bitcoin wallet
     Taproot,          // Bitcoin with Taproot support
@@ -133,16 +148,17 @@ pub struct Asset {
 #[allow(dead_code)]
 pub struct Wallet {
     config: WalletConfig,
-    seed: Mutex<Option<[u8; 64]>>,
+    seed: Mutex<Option<Zeroizing<[u8; 64]>>>,
     secp: Secp256k1<bitcoin::secp256k1::All>,
     addresses: Mutex<HashMap<AddressType, Vec<Address>>>,
     assets: Mutex<HashMap<String, Asset>>,
     transactions: Mutex<Vec<Transaction>>,
-    bitcoin_client: Option<Arc<dyn BitcoinInterface>>,
+    utxos: Mutex<HashMap<OutPoint, Utxo>>,
+    chain_source: Option<Arc<dyn ChainSource>>,
 }
 
 impl Wallet {
-    pub fn new(config: WalletConfig, bitcoin_client: Option<Arc<dyn BitcoinInterface>>) -> Self {
+    pub fn new(config: WalletConfig, chain_source: Option<Arc<dyn ChainSource>>) -> Self {
         Self {
             config,
             seed: Mutex::new(None),
@@ -150,7 +166,8 @@ impl Wallet {
             addresses: Mutex::new(HashMap::new()),
             assets: Mutex::new(HashMap::new()),
             transactions: Mutex::new(Vec::new()),
-            bitcoin_client,
+            utxos: Mutex::new(HashMap::new()),
+            chain_source,
         }
     }
 
@@ -162,11 +179,11 @@ impl Wallet {
             bip32::generate_seed(password.unwrap_or(""))?
         };
 
-        let mut seed_guard = self
+        // Release the seed lock before deriving addresses, which takes it again
+        *self
             .seed
             .lock()
-            .map_err(|e| format!("Mutex lock error: {e}"))?;
-        *seed_guard = Some(seed);
+            .map_err(|e| format!("Mutex lock error: {e}"))? = Some(Zeroizing::new(seed));
 
         // Generate initial addresses
         self.init_addresses()?;
@@ -230,6 +247,148 @@ impl Wallet {
 
         Ok(())
     }
+
+    /// [AIR-3][BPC-3] Replace the UTXO set with the outputs the chain source reports
+    ///
+    /// Every derived address is queried. Returns the resulting balance in satoshis.
+    pub async fn refresh_utxos(&self) -> AnyaResult<u64> {
+        let chain_source = self.chain_source()?;
+        let scripts: Vec<ScriptBuf> = self
+            .addresses
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?
+            .values()
+            .flatten()
+            .map(|address| address.script_pubkey())
+            .collect();
+
+        let tip_height = chain_source.tip_height().await?;
+        let mut utxos = HashMap::new();
+        for script in scripts {
+            for utxo in chain_source.get_utxos(&script).await? {
+                utxos.insert(
+                    utxo.outpoint,
+                    Utxo {
+                        outpoint: utxo.outpoint,
+                        txout: utxo.txout,
+                        redeem_script: None,
+                        witness_script: None,
+                        confirmations: utxo
+                            .height
+                            .map_or(0, |height| tip_height.saturating_sub(height) + 1),
+                        spendable: true,
+                        from_wallet: true,
+                    },
+                );
+            }
+        }
+
+        *self
+            .utxos
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))? = utxos;
+        self.get_balance()
+    }
+
+    /// [AIR-3][BPC-3] Broadcast `tx` through the chain source and record it
+    pub async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        let txid = self.chain_source()?.broadcast(tx).await?;
+        self.transactions
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?
+            .push(tx.clone());
+        Ok(txid)
+    }
+
+    fn chain_source(&self) -> AnyaResult<Arc<dyn ChainSource>> {
+        self.chain_source
+            .clone()
+            .ok_or_else(|| BitcoinError::Wallet("Wallet has no chain source".to_string()).into())
+    }
+
+    /// Sign the PSBT inputs spendable by the key at `path`
+    ///
+    /// Returns the number of inputs signed; see [`signer::sign_psbt`].
+    pub fn sign_psbt(
+        &self,
+        psbt: &mut PSBT,
+        path: &str,
+        sighash_type: signer::SighashType,
+    ) -> AnyaResult<usize> {
+        let secret_key = self.derive_key(path)?;
+        signer::sign_psbt(&self.secp, psbt, &secret_key, sighash_type)
+            .map_err(|e| AnyaError::Bitcoin(e.to_string()))
+    }
+
+    /// Unspent outputs found by the last [`Wallet::refresh_utxos`]
+    pub fn list_utxos(&self) -> AnyaResult<Vec<Utxo>> {
+        Ok(self
+            .utxos
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    /// Sign every PSBT input that spends one of the wallet's derived addresses
+    ///
+    /// Returns the number of inputs signed; inputs paying elsewhere are left alone.
+    pub fn sign_owned_inputs(
+        &self,
+        psbt: &mut PSBT,
+        sighash_type: signer::SighashType,
+    ) -> AnyaResult<usize> {
+        let scripts = self.wallet_scripts()?;
+        let mut paths = Vec::new();
+        for index in 0..psbt.inputs.len() {
+            let Some(txout) = signer::spent_output(psbt, index) else {
+                continue;
+            };
+            let path = match scripts.get(&txout.script_pubkey) {
+                Some((AddressType::Legacy, index)) => format!("m/44'/0'/0'/0/{index}"),
+                Some((AddressType::SegWit, index)) => format!("m/84'/0'/0'/0/{index}"),
+                Some((AddressType::NestedSegWit, index)) => format!("m/49'/0'/0'/0/{index}"),
+                Some((AddressType::Taproot, index)) => format!("m/86'/0'/0'/0/{index}"),
+                None => continue,
+            };
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+
+        let mut signed = 0;
+        for path in paths {
+            signed += self.sign_psbt(psbt, &path, sighash_type)?;
+        }
+        Ok(signed)
+    }
+
+    /// Estimate inputs, vsize and fee of paying `recipients` from the wallet's UTXOs
+    ///
+    /// `fee_rate` is in sat/vB. Change is sized as a native SegWit output, and
+    /// no address is derived. Nothing is built or signed; see
+    /// [`TransactionBuilder::simulate`].
+    pub fn simulate_transaction(
+        &self,
+        recipients: &[(Address, u64)],
+        fee_rate: f64,
+        strategy: CoinSelectionStrategy,
+    ) -> AnyaResult<TxSimulation> {
+        if !fee_rate.is_finite() || fee_rate <= 0.0 {
+            return Err(AnyaError::InvalidInput(format!("invalid fee rate {fee_rate} sat/vB")));
+        }
+        let change = self.get_address(0, AddressType::SegWit)?;
+
+        let mut builder = TransactionBuilder::new(self.list_utxos()?)
+            .change_script(change.script_pubkey())
+            .fee_rate(FeeRate::SatPerKb((fee_rate * 1000.0).ceil() as u64))
+            .coin_selection(strategy);
+        for (address, amount) in recipients {
+            builder = builder.add_recipient(address.script_pubkey(), Amount::from_sat(*amount));
+        }
+        builder.simulate().map_err(|e| AnyaError::Bitcoin(e.to_string()))
+    }
 }
 
 impl KeyManager for Wallet {
@@ -443,16 +602,22 @@ impl TransactionManager for Wallet {
         Ok(None)
     }
 
-    fn get_transactions(&self, _limit: usize, _offset: usize) -> AnyaResult<Vec<Transaction>> {
-        // Simplified implementation
-        Ok(vec![])
+    fn get_transactions(&self, limit: usize, offset: usize) -> AnyaResult<Vec<Transaction>> {
+        let transactions = self
+            .transactions
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?;
+        Ok(transactions.iter().skip(offset).take(limit).cloned().collect())
     }
 }
 
 impl BalanceManager for Wallet {
     fn get_balance(&self) -> AnyaResult<u64> {
-        // Simplified implementation
-        Ok(0)
+        let utxos = self
+            .utxos
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?;
+        Ok(utxos.values().map(|utxo| utxo.txout.value.to_sat()).sum())
     }
 
     fn get_unconfirmed_balance(&self) -> AnyaResult<u64> {
@@ -693,6 +858,14 @@ pub enum WalletError {
     #[error("Insufficient funds: {0}")]
     InsufficientFunds(String),
 
+    /// Output below the dust threshold of its script type
+    #[error("Output {index} of {value} sats is below the dust threshold of {threshold} sats")]
+    DustOutput {
+        index: usize,
+        value: u64,
+        threshold: u64,
+    },
+
     /// IO error
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
@@ -704,6 +877,10 @@ pub enum WalletError {
     /// UTXO management error
     #[error("UTXO management error: {0}")]
     UtxoError(String),
+
+    /// Timelock not satisfied
+    #[error("Timelock error: {0}")]
+    TimelockError(String),
 }
 
 /// UTXO (Unspent Transaction Output) representation
@@ -946,6 +1123,9 @@ pub struct BitcoinWallet {
     #[allow(dead_code)]
     // Required for future cryptographic operations (see docs/research/PROTOCOL_UPGRADES.md)
     secp: Secp256k1<bitcoin::secp256k1::All>,
+
+    /// HD seed, once saved to or loaded from an encrypted keystore
+    seed: Option<Zeroizing<[u8; 64]>>,
 }
 
 /// Wallet storage structure
@@ -965,6 +1145,10 @@ struct WalletStorage {
 
     /// Current indexes
     indexes: WalletIndexes,
+
+    /// BIP-329 labels
+    #[serde(default)]
+    labels: labels::LabelStore,
 }
 
 /// Wallet metadata
@@ -990,7 +1174,7 @@ struct WalletMetadata {
 }
 
 /// Wallet address information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AddressInfo {
     /// The address string
     pub address: String,
@@ -1096,6 +1280,16 @@ pub enum CoinSelectionStrategy {
     BranchAndBound,
 }
 
+pub use backup::{WalletBackup, BACKUP_FORMAT, BACKUP_VERSION};
+pub use labels::{Label, LabelImport, LabelRef};
+pub use builder::{CoinSelection, DustPolicy, Recipient, TransactionBuilder, TxSimulation};
+pub use external_signer::{ExternalSigner, ExternalSignerError, HwiSigner};
+pub use rescan::{BlockSource, RescanProgress, RescanSummary};
+pub use reserves::{verify_reserves, ReservesError, ReservesProof};
+pub use scan::{ChainScan, ScanResult};
+pub use timelock::{build_cltv_script, build_csv_script, ChainTip, Timelock, TimelockedOutput};
+pub use vault::Vault;
+
 // Re-export advanced wallet features
 pub use advanced_features::{
     AdvancedWalletManager, HardwareWalletInterface, HardwareWalletType, HardwareWalletInfo,
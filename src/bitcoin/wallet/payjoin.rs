@@ -0,0 +1,927 @@
+// Payjoin (BIP-78)
+//
+// The sender pays with a signed, broadcastable original transaction and posts
+// its PSBT to the receiver's BIP-21 `pj` endpoint. The receiver adds one of
+// its own coins of the same script type, raises its output by that amount
+// less the fee for the extra input, and replies with its input signed and the
+// sender's inputs stripped. Before signing again, the sender checks that the
+// proposal spends all of its inputs unchanged, pays no script it did not
+// already pay, and takes no more fee from it than allowed. Whenever the
+// exchange fails, the original transaction can be broadcast instead.
+//
+// Output substitution is not supported: senders always request
+// `disableoutputsubstitution` and the receiver keeps the payee script.
+
+use super::signer::spent_output;
+use super::{TransactionBuilder, Utxo, WalletError};
+use crate::bitcoin::bip21::BitcoinUri;
+use crate::bitcoin::mempool::policy::dust_threshold;
+use crate::bitcoin::weight::fee_for;
+use crate::AnyaError;
+use async_trait::async_trait;
+use bitcoin::psbt::{Input, Output, Psbt as PSBT};
+use bitcoin::{Amount, FeeRate, OutPoint, Script, ScriptBuf, TxIn, Weight, Witness};
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+
+/// Protocol version sent in the `v` parameter
+pub const PAYJOIN_VERSION: u32 = 1;
+
+/// Payjoin errors
+#[derive(Debug, thiserror::Error)]
+pub enum PayjoinError {
+    #[error("Unsupported payjoin version {0}")]
+    VersionUnsupported(u32),
+    #[error("Original PSBT rejected: {0}")]
+    OriginalPsbtRejected(String),
+    #[error("Receiver has no input to contribute")]
+    Unavailable,
+    #[error("Invalid payjoin proposal: {0}")]
+    InvalidProposal(String),
+    #[error("Payjoin request failed: {0}")]
+    Request(String),
+}
+
+impl PayjoinError {
+    /// BIP-78 `errorCode` a receiver replies with, for receiver-side errors
+    pub fn error_code(&self) -> Option<&'static str> {
+        match self {
+            Self::VersionUnsupported(_) => Some("version-unsupported"),
+            Self::OriginalPsbtRejected(_) => Some("original-psbt-rejected"),
+            Self::Unavailable => Some("unavailable"),
+            Self::InvalidProposal(_) | Self::Request(_) => None,
+        }
+    }
+}
+
+impl From<PayjoinError> for AnyaError {
+    fn from(err: PayjoinError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+/// Sender parameters, passed to the receiver in the endpoint's query string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayjoinParams {
+    /// Output the receiver may reduce to pay for its input, usually the sender's change
+    pub additional_fee_output_index: Option<usize>,
+    /// Most the receiver may take from that output
+    pub max_additional_fee_contribution: Amount,
+    /// Lowest fee rate the proposal may pay
+    pub min_fee_rate: FeeRate,
+}
+
+impl Default for PayjoinParams {
+    fn default() -> Self {
+        Self {
+            additional_fee_output_index: None,
+            max_additional_fee_contribution: Amount::ZERO,
+            min_fee_rate: FeeRate::ZERO,
+        }
+    }
+}
+
+impl PayjoinParams {
+    /// Query string for the request, without the leading `?`
+    pub fn to_query(&self) -> String {
+        let mut query = format!("v={PAYJOIN_VERSION}&disableoutputsubstitution=true");
+        if let Some(index) = self.additional_fee_output_index {
+            query.push_str(&format!(
+                "&additionalfeeoutputindex={index}&maxadditionalfeecontribution={}",
+                self.max_additional_fee_contribution.to_sat()
+            ));
+        }
+        if self.min_fee_rate > FeeRate::ZERO {
+            let sat_per_vb = self.min_fee_rate.to_sat_per_kwu() as f64 / 250.0;
+            query.push_str(&format!("&minfeerate={sat_per_vb}"));
+        }
+        query
+    }
+
+    /// Parse the query string of a request; unknown parameters are ignored
+    pub fn from_query(query: &str) -> Result<Self, PayjoinError> {
+        let reject =
+            |param: &str| PayjoinError::OriginalPsbtRejected(format!("invalid parameter {param}"));
+        let mut params = Self::default();
+        let mut max_contribution = None;
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            match key {
+                "v" => {
+                    let version = value.parse().map_err(|_| reject(param))?;
+                    if version != PAYJOIN_VERSION {
+                        return Err(PayjoinError::VersionUnsupported(version));
+                    }
+                }
+                "additionalfeeoutputindex" => {
+                    params.additional_fee_output_index =
+                        Some(value.parse().map_err(|_| reject(param))?);
+                }
+                "maxadditionalfeecontribution" => {
+                    max_contribution =
+                        Some(Amount::from_sat(value.parse().map_err(|_| reject(param))?));
+                }
+                "minfeerate" => {
+                    let sat_per_vb: f64 = value.parse().map_err(|_| reject(param))?;
+                    if !sat_per_vb.is_finite() || sat_per_vb < 0.0 {
+                        return Err(reject(param));
+                    }
+                    params.min_fee_rate =
+                        FeeRate::from_sat_per_kwu((sat_per_vb * 250.0).ceil() as u64);
+                }
+                _ => {}
+            }
+        }
+        // The fee output is only usable together with a contribution limit
+        match max_contribution {
+            Some(max) => params.max_additional_fee_contribution = max,
+            None => params.additional_fee_output_index = None,
+        }
+        Ok(params)
+    }
+}
+
+/// Script types payjoin inputs are matched on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum InputType {
+    P2pkh,
+    /// P2SH, assumed to wrap P2WPKH
+    P2shP2wpkh,
+    P2wpkh,
+    P2tr,
+}
+
+impl InputType {
+    fn of(script: &Script) -> Option<Self> {
+        if script.is_p2pkh() {
+            Some(Self::P2pkh)
+        } else if script.is_p2sh() {
+            Some(Self::P2shP2wpkh)
+        } else if script.is_p2wpkh() {
+            Some(Self::P2wpkh)
+        } else if script.is_p2tr() {
+            Some(Self::P2tr)
+        } else {
+            None
+        }
+    }
+
+    /// Weight of a signed input of this type
+    fn weight(self) -> Weight {
+        Weight::from_wu(match self {
+            Self::P2pkh => 592,
+            Self::P2shP2wpkh => 364,
+            Self::P2wpkh => 272,
+            Self::P2tr => 230,
+        })
+    }
+}
+
+/// What the sender and receiver both check about an original PSBT
+struct Original {
+    input_type: InputType,
+    fee: Amount,
+    /// Weight of the signed transaction
+    weight: Weight,
+    payee_index: usize,
+}
+
+impl Original {
+    fn check(original: &PSBT, payee_script: &Script) -> Result<Self, String> {
+        let tx = &original.unsigned_tx;
+        if tx.input.is_empty() {
+            return Err("the original spends nothing".to_string());
+        }
+        if original
+            .inputs
+            .iter()
+            .any(|input| input.final_script_sig.is_none() && input.final_script_witness.is_none())
+        {
+            return Err("every input of the original must be finalized".to_string());
+        }
+
+        let mut input_types = HashSet::new();
+        for index in 0..tx.input.len() {
+            let txout = spent_output(original, index)
+                .ok_or_else(|| format!("input {index} is missing its spent output"))?;
+            input_types.insert(InputType::of(&txout.script_pubkey));
+        }
+        let input_type = match input_types.into_iter().collect::<Vec<_>>()[..] {
+            [Some(input_type)] => input_type,
+            [None] => return Err("unsupported input script type".to_string()),
+            _ => return Err("inputs of mixed script types".to_string()),
+        };
+        if tx
+            .input
+            .iter()
+            .any(|txin| txin.sequence != tx.input[0].sequence)
+        {
+            return Err("inputs with different sequence numbers".to_string());
+        }
+
+        let payees: Vec<usize> = tx
+            .output
+            .iter()
+            .enumerate()
+            .filter(|(_, txout)| txout.script_pubkey == *payee_script)
+            .map(|(index, _)| index)
+            .collect();
+        let [payee_index] = payees[..] else {
+            return Err(format!(
+                "expected one output paying the receiver, found {}",
+                payees.len()
+            ));
+        };
+
+        let fee = original.fee().map_err(|e| e.to_string())?;
+        let weight = original.clone().extract_tx_unchecked_fee_rate().weight();
+        Ok(Self {
+            input_type,
+            fee,
+            weight,
+            payee_index,
+        })
+    }
+
+    fn fee_rate(&self) -> FeeRate {
+        FeeRate::from_sat_per_kwu(self.fee.to_sat() * 1000 / self.weight.to_wu().max(1))
+    }
+}
+
+/// Receiver side: turn the sender's `original` PSBT into a payjoin proposal
+///
+/// `payee_script` is the receiver's output in the original and
+/// `available_utxos` its spendable coins. The smallest confirmed coin of the
+/// sender's input type is added at a random position, and the payee output
+/// grows by its value less the fee the input costs at the original fee rate.
+/// Up to `max_additional_fee_contribution` of that fee is taken from the
+/// output the sender designated. The sender's inputs are stripped of
+/// signatures and UTXO data.
+///
+/// The added input is left unsigned: sign it and [`finalize_psbt`] it before
+/// replying. Keep the original transaction and broadcast it if the sender
+/// does not complete the payjoin, and do not offer the same coin to
+/// different senders, who could otherwise probe the wallet's coins.
+///
+/// [`finalize_psbt`]: super::signer::finalize_psbt
+pub fn receive(
+    original: &PSBT,
+    available_utxos: &[Utxo],
+    payee_script: &Script,
+    params: &PayjoinParams,
+) -> Result<PSBT, PayjoinError> {
+    let checked =
+        Original::check(original, payee_script).map_err(PayjoinError::OriginalPsbtRejected)?;
+    let tx = &original.unsigned_tx;
+
+    // A sender spending our own coins is probing which of them we hold
+    let ours: HashSet<OutPoint> = available_utxos.iter().map(|utxo| utxo.outpoint).collect();
+    if let Some(txin) = tx
+        .input
+        .iter()
+        .find(|txin| ours.contains(&txin.previous_output))
+    {
+        return Err(PayjoinError::OriginalPsbtRejected(format!(
+            "input {} belongs to the receiver",
+            txin.previous_output
+        )));
+    }
+    let fee_rate = checked.fee_rate();
+    if fee_rate < params.min_fee_rate {
+        return Err(PayjoinError::OriginalPsbtRejected(format!(
+            "fee rate {fee_rate} is below the requested minimum {}",
+            params.min_fee_rate
+        )));
+    }
+
+    let contribution = available_utxos
+        .iter()
+        .filter(|utxo| utxo.spendable && utxo.confirmations > 0)
+        .filter(|utxo| InputType::of(&utxo.txout.script_pubkey) == Some(checked.input_type))
+        .min_by_key(|utxo| utxo.txout.value)
+        .ok_or(PayjoinError::Unavailable)?;
+
+    let input_fee = fee_for(fee_rate, checked.input_type.weight());
+    let sender_share = match params.additional_fee_output_index {
+        Some(index) if index != checked.payee_index => tx
+            .output
+            .get(index)
+            .map(|txout| {
+                let dust = dust_threshold(&txout.script_pubkey);
+                txout.value.checked_sub(dust).unwrap_or(Amount::ZERO)
+            })
+            .ok_or_else(|| {
+                PayjoinError::OriginalPsbtRejected(format!("no output at index {index}"))
+            })?
+            .min(input_fee)
+            .min(params.max_additional_fee_contribution),
+        _ => Amount::ZERO,
+    };
+    let payee_value = (tx.output[checked.payee_index].value + contribution.txout.value)
+        .checked_sub(input_fee - sender_share)
+        .ok_or(PayjoinError::Unavailable)?;
+
+    let mut proposal = original.clone();
+    proposal
+        .inputs
+        .iter_mut()
+        .for_each(|input| *input = Input::default());
+    proposal
+        .outputs
+        .iter_mut()
+        .for_each(|output| *output = Output::default());
+    proposal.unsigned_tx.output[checked.payee_index].value = payee_value;
+    if let Some(index) = params.additional_fee_output_index {
+        proposal.unsigned_tx.output[index].value -= sender_share;
+    }
+
+    let position = rand::thread_rng().gen_range(0..=tx.input.len());
+    proposal.unsigned_tx.input.insert(
+        position,
+        TxIn {
+            previous_output: contribution.outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: tx.input[0].sequence,
+            witness: Witness::new(),
+        },
+    );
+    proposal.inputs.insert(
+        position,
+        Input {
+            witness_utxo: Some(contribution.txout.clone()),
+            redeem_script: contribution.redeem_script.clone(),
+            witness_script: contribution.witness_script.clone(),
+            ..Default::default()
+        },
+    );
+    Ok(proposal)
+}
+
+/// Delivers payjoin requests to the receiver's endpoint
+#[async_trait]
+pub trait PayjoinTransport: Send + Sync {
+    /// POST `body` to `url`, returning the response body
+    async fn post(&self, url: &str, body: String) -> Result<String, PayjoinError>;
+}
+
+/// [`PayjoinTransport`] over HTTPS
+#[derive(Debug, Clone, Default)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PayjoinTransport for HttpTransport {
+    async fn post(&self, url: &str, body: String) -> Result<String, PayjoinError> {
+        let response = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "text/plain")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| PayjoinError::Request(format!("{url}: {e}")))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| PayjoinError::Request(format!("{url}: {e}")))?;
+        if !status.is_success() {
+            return Err(PayjoinError::Request(format!(
+                "{url} returned {status}: {text}"
+            )));
+        }
+        Ok(text)
+    }
+}
+
+/// Sender side of a payjoin to a BIP-21 `pj` endpoint
+#[derive(Debug, Clone)]
+pub struct PayjoinSender {
+    original: PSBT,
+    endpoint: String,
+    input_type: InputType,
+    original_fee: Amount,
+    original_weight: Weight,
+    payee_index: usize,
+    params: PayjoinParams,
+}
+
+impl PayjoinSender {
+    /// Unsigned original PSBT paying the amount `uri` requests
+    pub fn original_psbt(
+        builder: TransactionBuilder,
+        uri: &BitcoinUri,
+    ) -> Result<PSBT, WalletError> {
+        let amount = uri.amount.ok_or_else(|| {
+            WalletError::InvalidParameters("payjoin URI has no amount".to_string())
+        })?;
+        builder
+            .add_recipient(uri.address.script_pubkey(), amount)
+            .build()
+    }
+
+    /// Prepare to send the signed and finalized `original` to `uri`'s endpoint
+    ///
+    /// When the original has exactly one output besides the payment, the
+    /// receiver may take the fee for its input from it, up to the cost of one
+    /// input like the sender's at the original fee rate. The proposal must
+    /// keep the original fee rate.
+    pub fn new(original: PSBT, uri: &BitcoinUri) -> Result<Self, PayjoinError> {
+        let endpoint = uri
+            .payjoin
+            .clone()
+            .ok_or_else(|| PayjoinError::Request("URI has no payjoin endpoint".to_string()))?;
+        let host = endpoint
+            .strip_prefix("http://")
+            .and_then(|rest| rest.split(['/', ':', '?']).next());
+        if !endpoint.starts_with("https://") && !host.is_some_and(|host| host.ends_with(".onion")) {
+            return Err(PayjoinError::Request(format!(
+                "endpoint {endpoint} is neither https nor an onion service"
+            )));
+        }
+
+        let checked = Original::check(&original, &uri.address.script_pubkey()).map_err(|e| {
+            PayjoinError::Request(format!("original PSBT is not ready to send: {e}"))
+        })?;
+        let fee_rate = checked.fee_rate();
+        let others: Vec<usize> = (0..original.unsigned_tx.output.len())
+            .filter(|index| *index != checked.payee_index)
+            .collect();
+        let params = PayjoinParams {
+            additional_fee_output_index: match others[..] {
+                [index] => Some(index),
+                _ => None,
+            },
+            max_additional_fee_contribution: fee_for(fee_rate, checked.input_type.weight()),
+            min_fee_rate: fee_rate,
+        };
+
+        Ok(Self {
+            original,
+            endpoint,
+            input_type: checked.input_type,
+            original_fee: checked.fee,
+            original_weight: checked.weight,
+            payee_index: checked.payee_index,
+            params,
+        })
+    }
+
+    pub fn params(&self) -> &PayjoinParams {
+        &self.params
+    }
+
+    /// URL and base64 PSBT body of the request to post
+    pub fn request(&self) -> (String, String) {
+        let separator = if self.endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let url = format!("{}{separator}{}", self.endpoint, self.params.to_query());
+        (url, self.original.to_string())
+    }
+
+    /// Post the original through `transport` and check the proposal
+    pub async fn send(&self, transport: &dyn PayjoinTransport) -> Result<PSBT, PayjoinError> {
+        let (url, body) = self.request();
+        let response = transport.post(&url, body).await?;
+        self.process_proposal(&response)
+    }
+
+    /// Check the receiver's base64 `proposal`, returning it ready to sign
+    ///
+    /// Our inputs get their UTXO data back from the original and need to be
+    /// signed again, since the transaction changed.
+    pub fn process_proposal(&self, proposal: &str) -> Result<PSBT, PayjoinError> {
+        let invalid = PayjoinError::InvalidProposal;
+        let mut proposal: PSBT = proposal
+            .trim()
+            .parse()
+            .map_err(|e| invalid(format!("not a base64 PSBT: {e}")))?;
+        let original_tx = &self.original.unsigned_tx;
+        let tx = &proposal.unsigned_tx;
+        if tx.version != original_tx.version || tx.lock_time != original_tx.lock_time {
+            return Err(invalid("version or lock time changed".to_string()));
+        }
+
+        let our_inputs: HashMap<OutPoint, usize> = original_tx
+            .input
+            .iter()
+            .enumerate()
+            .map(|(index, txin)| (txin.previous_output, index))
+            .collect();
+        let mut restored = Vec::new();
+        let mut receiver_inputs = 0;
+        let mut receiver_value = Amount::ZERO;
+        let mut receiver_weight = Weight::ZERO;
+        for (index, (txin, input)) in tx.input.iter().zip(&proposal.inputs).enumerate() {
+            let finalized =
+                input.final_script_sig.is_some() || input.final_script_witness.is_some();
+            if let Some(&original_index) = our_inputs.get(&txin.previous_output) {
+                if restored
+                    .iter()
+                    .any(|(_, restored)| *restored == original_index)
+                {
+                    return Err(invalid(format!("spends {} twice", txin.previous_output)));
+                }
+                if txin.sequence != original_tx.input[original_index].sequence {
+                    return Err(invalid(format!(
+                        "changed the sequence of our input {}",
+                        txin.previous_output
+                    )));
+                }
+                if finalized || !input.partial_sigs.is_empty() || input.tap_key_sig.is_some() {
+                    return Err(invalid(format!(
+                        "our input {} carries signatures",
+                        txin.previous_output
+                    )));
+                }
+                restored.push((index, original_index));
+                continue;
+            }
+
+            let txout = spent_output(&proposal, index).ok_or_else(|| {
+                invalid(format!(
+                    "receiver input {index} is missing its spent output"
+                ))
+            })?;
+            if !finalized {
+                return Err(invalid(format!("receiver input {index} is not finalized")));
+            }
+            if InputType::of(&txout.script_pubkey) != Some(self.input_type) {
+                return Err(invalid(format!(
+                    "receiver input {index} has a different script type than ours"
+                )));
+            }
+            if txin.sequence != original_tx.input[0].sequence {
+                return Err(invalid(format!(
+                    "receiver input {index} has a different sequence than ours"
+                )));
+            }
+            let signed = TxIn {
+                script_sig: input.final_script_sig.clone().unwrap_or_default(),
+                witness: input.final_script_witness.clone().unwrap_or_default(),
+                ..txin.clone()
+            };
+            receiver_weight += if signed.witness.is_empty() {
+                signed.legacy_weight()
+            } else {
+                signed.segwit_weight()
+            };
+            receiver_inputs += 1;
+            receiver_value += txout.value;
+        }
+        if restored.len() != our_inputs.len() {
+            return Err(invalid("dropped one of our inputs".to_string()));
+        }
+
+        // Match every output to a distinct original output with the same script
+        let mut matched = vec![None; original_tx.output.len()];
+        let mut additional_fee = Amount::ZERO;
+        for (index, txout) in tx.output.iter().enumerate() {
+            let original_index = (0..original_tx.output.len())
+                .find(|i| {
+                    matched[*i].is_none()
+                        && original_tx.output[*i].script_pubkey == txout.script_pubkey
+                })
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "output {index} pays a script the original does not"
+                    ))
+                })?;
+            matched[original_index] = Some(index);
+
+            let original_value = original_tx.output[original_index].value;
+            if original_index == self.payee_index {
+                if txout.value < original_value {
+                    return Err(invalid("reduced the payment".to_string()));
+                }
+            } else if Some(original_index) == self.params.additional_fee_output_index
+                && txout.value <= original_value
+            {
+                additional_fee = original_value - txout.value;
+            } else if txout.value != original_value {
+                return Err(invalid(format!("changed our output {original_index}")));
+            }
+        }
+        if matched.contains(&None) {
+            return Err(invalid("dropped one of our outputs".to_string()));
+        }
+
+        // We only pay for the receiver's inputs, at no more than our own fee rate
+        let original_rate = FeeRate::from_sat_per_kwu(
+            self.original_fee.to_sat() * 1000 / self.original_weight.to_wu().max(1),
+        );
+        if additional_fee > self.params.max_additional_fee_contribution {
+            return Err(invalid(format!(
+                "takes {additional_fee} in fees from us, more than the allowed {}",
+                self.params.max_additional_fee_contribution
+            )));
+        }
+        let receiver_input_weight = self.input_type.weight() * receiver_inputs;
+        if additional_fee > fee_for(original_rate, receiver_input_weight) {
+            return Err(invalid(format!(
+                "takes {additional_fee} in fees from us, more than its inputs cost"
+            )));
+        }
+
+        let original_input_value = self.original_fee
+            + original_tx
+                .output
+                .iter()
+                .map(|txout| txout.value)
+                .sum::<Amount>();
+        let fee = (original_input_value + receiver_value)
+            .checked_sub(tx.output.iter().map(|txout| txout.value).sum())
+            .ok_or_else(|| invalid("outputs exceed inputs".to_string()))?;
+        let weight = self.original_weight + receiver_weight;
+        let fee_rate = FeeRate::from_sat_per_kwu(fee.to_sat() * 1000 / weight.to_wu().max(1));
+        if fee_rate < self.params.min_fee_rate {
+            return Err(invalid(format!(
+                "fee rate {fee_rate} is below the minimum {}",
+                self.params.min_fee_rate
+            )));
+        }
+
+        for (index, original_index) in restored {
+            let mut input = self.original.inputs[original_index].clone();
+            input.final_script_sig = None;
+            input.final_script_witness = None;
+            proposal.inputs[index] = input;
+        }
+        for (original_index, index) in matched.into_iter().enumerate() {
+            if let Some(index) = index {
+                proposal.outputs[index] = self.original.outputs[original_index].clone();
+            }
+        }
+        Ok(proposal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::signer::{finalize_psbt, sign_psbt, SighashType};
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+    use bitcoin::{Address, CompressedPublicKey, Network, TxOut, Txid};
+
+    type Tamper = Box<dyn Fn(&mut PSBT) + Send + Sync>;
+
+    fn key(seed: u8) -> SecretKey {
+        SecretKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn p2wpkh(secret_key: &SecretKey) -> ScriptBuf {
+        let secp = Secp256k1::new();
+        ScriptBuf::new_p2wpkh(&CompressedPublicKey(secret_key.public_key(&secp)).wpubkey_hash())
+    }
+
+    fn p2tr(secret_key: &SecretKey) -> ScriptBuf {
+        let secp = Secp256k1::new();
+        let (internal_key, _) = secret_key.x_only_public_key(&secp);
+        ScriptBuf::new_p2tr(&secp, internal_key, None)
+    }
+
+    fn utxo(n: u8, script_pubkey: ScriptBuf, sats: u64) -> Utxo {
+        Utxo {
+            outpoint: OutPoint::new(Txid::from_byte_array([n; 32]), 0),
+            txout: TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey,
+            },
+            redeem_script: None,
+            witness_script: None,
+            confirmations: 6,
+            spendable: true,
+            from_wallet: true,
+        }
+    }
+
+    fn sign(psbt: &mut PSBT, secret_key: &SecretKey) {
+        sign_psbt(&Secp256k1::new(), psbt, secret_key, SighashType::All).unwrap();
+        finalize_psbt(psbt);
+    }
+
+    /// Receiver paid by key 2, holding coins of both script types
+    struct MockReceiver {
+        utxos: Vec<Utxo>,
+        tamper: Tamper,
+    }
+
+    impl MockReceiver {
+        fn new(tamper: Tamper) -> Self {
+            Self {
+                utxos: vec![
+                    utxo(20, p2wpkh(&key(2)), 30_000),
+                    utxo(21, p2tr(&key(2)), 5_000),
+                ],
+                tamper,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl PayjoinTransport for MockReceiver {
+        async fn post(&self, url: &str, body: String) -> Result<String, PayjoinError> {
+            let (endpoint, query) = url.split_once('?').unwrap();
+            assert_eq!(endpoint, "https://example.com/pj");
+            let params = PayjoinParams::from_query(query)?;
+            let original: PSBT = body.parse().unwrap();
+            let mut proposal = receive(&original, &self.utxos, &p2wpkh(&key(2)), &params)?;
+            sign(&mut proposal, &key(2));
+            (self.tamper)(&mut proposal);
+            Ok(proposal.to_string())
+        }
+    }
+
+    /// URI for 40_000 sats to key 2 and the sender's signed original paying it
+    fn original() -> (BitcoinUri, PSBT) {
+        let payee = Address::from_script(&p2wpkh(&key(2)), Network::Regtest).unwrap();
+        let uri = BitcoinUri::parse(
+            &format!("bitcoin:{payee}?amount=0.0004&pj=https://example.com/pj"),
+            Network::Regtest,
+        )
+        .unwrap();
+        let builder = TransactionBuilder::new(vec![utxo(1, p2wpkh(&key(1)), 100_000)])
+            .change_script(p2wpkh(&key(3)))
+            .fee_rate(super::super::FeeRate::SatPerVb(2));
+        let mut original = PayjoinSender::original_psbt(builder, &uri).unwrap();
+        sign(&mut original, &key(1));
+        (uri, original)
+    }
+
+    async fn send(tamper: Tamper) -> Result<PSBT, PayjoinError> {
+        let (uri, original) = original();
+        let sender = PayjoinSender::new(original, &uri).unwrap();
+        sender.send(&MockReceiver::new(tamper)).await
+    }
+
+    #[tokio::test]
+    async fn test_payjoin_round_trip() {
+        let (uri, original) = original();
+        let original_fee = original.fee().unwrap();
+        let original_tx = original.clone().extract_tx().unwrap();
+        let sender = PayjoinSender::new(original, &uri).unwrap();
+        assert_eq!(sender.params().additional_fee_output_index, Some(1));
+
+        let mut proposal = sender
+            .send(&MockReceiver::new(Box::new(|_| {})))
+            .await
+            .unwrap();
+        sign(&mut proposal, &key(1));
+        let payjoin = proposal.clone().extract_tx().unwrap();
+
+        // The receiver added its P2WPKH coin, not the taproot one
+        assert_eq!(payjoin.input.len(), 2);
+        assert!(payjoin
+            .input
+            .iter()
+            .any(|txin| txin.previous_output.txid == Txid::from_byte_array([20; 32])));
+        assert!(payjoin.input.iter().all(|txin| txin.witness.len() == 2));
+
+        // Its output grew by its coin, less whatever part of the input fee it paid
+        let payment = payjoin.output[0].value;
+        let change = payjoin.output[1].value;
+        let sender_share = original_tx.output[1].value - change;
+        let receiver_share = original_tx.output[0].value + Amount::from_sat(30_000) - payment;
+        assert!(
+            sender_share > Amount::ZERO
+                && sender_share <= sender.params().max_additional_fee_contribution
+        );
+        assert_eq!(
+            proposal.fee().unwrap(),
+            original_fee + sender_share + receiver_share
+        );
+        assert!(payjoin.weight() > original_tx.weight());
+    }
+
+    #[tokio::test]
+    async fn test_sender_rejects_malicious_proposals() {
+        let tampered: Vec<(&str, Tamper)> = vec![
+            (
+                "pays a script",
+                Box::new(|psbt: &mut PSBT| {
+                    psbt.unsigned_tx.output.push(TxOut {
+                        value: Amount::from_sat(1_000),
+                        script_pubkey: p2wpkh(&key(9)),
+                    });
+                    psbt.outputs.push(Output::default());
+                }),
+            ),
+            (
+                "takes",
+                Box::new(|psbt: &mut PSBT| {
+                    psbt.unsigned_tx.output[1].value -= Amount::from_sat(5_000)
+                }),
+            ),
+            (
+                "changed our output",
+                Box::new(|psbt: &mut PSBT| {
+                    psbt.unsigned_tx.output[1].value += Amount::from_sat(1_000)
+                }),
+            ),
+            (
+                "reduced the payment",
+                Box::new(|psbt: &mut PSBT| {
+                    psbt.unsigned_tx.output[0].value = Amount::from_sat(39_000)
+                }),
+            ),
+            (
+                "sequence of our input",
+                Box::new(|psbt: &mut PSBT| {
+                    let ours = psbt
+                        .unsigned_tx
+                        .input
+                        .iter_mut()
+                        .find(|txin| txin.previous_output.txid == Txid::from_byte_array([1; 32]))
+                        .unwrap();
+                    ours.sequence = bitcoin::Sequence::ZERO;
+                }),
+            ),
+            (
+                "dropped one of our inputs",
+                Box::new(|psbt: &mut PSBT| {
+                    let ours = psbt
+                        .unsigned_tx
+                        .input
+                        .iter()
+                        .position(|txin| {
+                            txin.previous_output.txid == Txid::from_byte_array([1; 32])
+                        })
+                        .unwrap();
+                    psbt.unsigned_tx.input.remove(ours);
+                    psbt.inputs.remove(ours);
+                }),
+            ),
+            (
+                "not finalized",
+                Box::new(|psbt: &mut PSBT| {
+                    for input in &mut psbt.inputs {
+                        input.final_script_witness = None;
+                    }
+                }),
+            ),
+        ];
+        for (expected, tamper) in tampered {
+            match send(tamper).await {
+                Err(PayjoinError::InvalidProposal(reason)) => {
+                    assert!(reason.contains(expected), "{reason:?} is not {expected:?}")
+                }
+                other => panic!("{expected}: accepted {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_receiver_rejects_probing_and_unsigned_originals() {
+        let (_, original) = original();
+        let payee = p2wpkh(&key(2));
+        let params = PayjoinParams::default();
+
+        // Spending one of the receiver's own coins reveals nothing
+        let own = utxo(1, p2wpkh(&key(1)), 100_000);
+        let err = receive(&original, &[own], &payee, &params).unwrap_err();
+        assert_eq!(err.error_code(), Some("original-psbt-rejected"));
+
+        let mut unsigned = original.clone();
+        unsigned.inputs[0].final_script_witness = None;
+        let utxos = [utxo(20, payee.clone(), 30_000)];
+        assert!(matches!(
+            receive(&unsigned, &utxos, &payee, &params),
+            Err(PayjoinError::OriginalPsbtRejected(_))
+        ));
+
+        // Only coins of the sender's script type can be contributed
+        let taproot = [utxo(21, p2tr(&key(2)), 30_000)];
+        let err = receive(&original, &taproot, &payee, &params).unwrap_err();
+        assert_eq!(err.error_code(), Some("unavailable"));
+    }
+
+    #[test]
+    fn test_params_round_trip_through_query() {
+        let params = PayjoinParams {
+            additional_fee_output_index: Some(1),
+            max_additional_fee_contribution: Amount::from_sat(136),
+            min_fee_rate: FeeRate::from_sat_per_kwu(500),
+        };
+        let query = params.to_query();
+        assert_eq!(
+            query,
+            "v=1&disableoutputsubstitution=true&additionalfeeoutputindex=1&maxadditionalfeecontribution=136&minfeerate=2"
+        );
+        assert_eq!(PayjoinParams::from_query(&query).unwrap(), params);
+        assert!(matches!(
+            PayjoinParams::from_query("v=2"),
+            Err(PayjoinError::VersionUnsupported(2))
+        ));
+    }
+}
@@ -0,0 +1,630 @@
+// PSBT metadata for external signers (BIP-174, BIP-371)
+//
+// Hardware signers only sign and display inputs and outputs whose keys they
+// can recognise. This fills the BIP-32 key origins of the ECDSA keys the
+// wallet controls and the BIP-371 fields for its P2TR inputs and outputs:
+// the internal key, the merkle root and script tree, control blocks for each
+// leaf, and the key origins of the internal key and of any keys used in the
+// leaves. `validate_signable` lists whatever a signer would still be missing.
+
+use super::WalletError;
+use bitcoin::bip32::{DerivationPath, KeySource, Xpriv};
+use bitcoin::psbt::Psbt as PSBT;
+use bitcoin::script::Instruction;
+use bitcoin::secp256k1::{PublicKey, Secp256k1, Signing, Verification, XOnlyPublicKey};
+use bitcoin::taproot::{TapLeafHash, TapTree, TaprootSpendInfo};
+use bitcoin::{CompressedPublicKey, Script, ScriptBuf};
+
+/// An ECDSA key the wallet can sign with, and its origin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyDerivation {
+    pub public_key: PublicKey,
+    pub origin: KeySource,
+}
+
+impl KeyDerivation {
+    /// Key at `path` below `master`
+    pub fn bip32<C: Signing>(
+        secp: &Secp256k1<C>,
+        master: &Xpriv,
+        path: &DerivationPath,
+    ) -> Result<Self, WalletError> {
+        let derived = master
+            .derive_priv(secp, path)
+            .map_err(|e| WalletError::DescriptorError(format!("Failed to derive {path}: {e}")))?;
+        Ok(Self {
+            public_key: derived.private_key.public_key(secp),
+            origin: (master.fingerprint(secp), path.clone()),
+        })
+    }
+
+    /// Whether `script` pays to this key directly or as nested segwit
+    fn pays_to(&self, script: &Script) -> bool {
+        let key = CompressedPublicKey(self.public_key);
+        let p2wpkh = ScriptBuf::new_p2wpkh(&key.wpubkey_hash());
+        *script == ScriptBuf::new_p2pkh(&bitcoin::PublicKey::new(self.public_key).pubkey_hash())
+            || *script == p2wpkh
+            || *script == ScriptBuf::new_p2sh(&p2wpkh.script_hash())
+    }
+}
+
+/// A taproot output the wallet can sign for, with its key origins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaprootDerivation {
+    pub internal_key: XOnlyPublicKey,
+    pub origin: KeySource,
+    /// Script tree committed to by the output key, if any
+    pub tree: Option<TapTree>,
+    /// Keys used in leaf scripts, with their origins
+    pub leaf_keys: Vec<(XOnlyPublicKey, KeySource)>,
+}
+
+impl TaprootDerivation {
+    /// Key-path only output for `internal_key`
+    pub fn key_path(internal_key: XOnlyPublicKey, origin: KeySource) -> Self {
+        Self {
+            internal_key,
+            origin,
+            tree: None,
+            leaf_keys: Vec::new(),
+        }
+    }
+
+    /// BIP-86 output for the key at `path` below `master`
+    pub fn bip86<C: Signing>(
+        secp: &Secp256k1<C>,
+        master: &Xpriv,
+        path: &DerivationPath,
+    ) -> Result<Self, WalletError> {
+        let derived = master
+            .derive_priv(secp, path)
+            .map_err(|e| WalletError::DescriptorError(format!("Failed to derive {path}: {e}")))?;
+        let (internal_key, _) = derived.private_key.x_only_public_key(secp);
+        Ok(Self::key_path(
+            internal_key,
+            (master.fingerprint(secp), path.clone()),
+        ))
+    }
+
+    /// Commit to a script tree
+    pub fn with_tree(mut self, tree: TapTree) -> Self {
+        self.tree = Some(tree);
+        self
+    }
+
+    /// Record the origin of a key used in the leaf scripts
+    pub fn with_leaf_key(mut self, key: XOnlyPublicKey, origin: KeySource) -> Self {
+        self.leaf_keys.push((key, origin));
+        self
+    }
+
+    fn spend_info<C: Verification>(&self, secp: &Secp256k1<C>) -> TaprootSpendInfo {
+        match &self.tree {
+            Some(tree) => {
+                TaprootSpendInfo::from_node_info(secp, self.internal_key, tree.node_info().clone())
+            }
+            None => TaprootSpendInfo::new_key_spend(secp, self.internal_key, None),
+        }
+    }
+
+    /// Hashes of the leaves whose script uses `key`
+    fn leaf_hashes(&self, key: &XOnlyPublicKey) -> Vec<TapLeafHash> {
+        let Some(tree) = &self.tree else {
+            return Vec::new();
+        };
+        tree.script_leaves()
+            .filter(|leaf| uses_key(leaf.script(), key))
+            .map(|leaf| TapLeafHash::from_script(leaf.script(), leaf.version()))
+            .collect()
+    }
+}
+
+/// Fill BIP-371 fields for the P2TR inputs and outputs paying to `derivations`
+///
+/// Inputs are matched through their `witness_utxo` or `non_witness_utxo`,
+/// outputs through the unsigned transaction. Returns the number of inputs
+/// and outputs updated.
+pub fn add_taproot_derivations<C: Verification>(
+    secp: &Secp256k1<C>,
+    psbt: &mut PSBT,
+    derivations: &[TaprootDerivation],
+) -> Result<usize, WalletError> {
+    let mut updated = 0;
+
+    for derivation in derivations {
+        let spend_info = derivation.spend_info(secp);
+        let script_pubkey = ScriptBuf::new_p2tr_tweaked(spend_info.output_key());
+
+        for index in 0..psbt.inputs.len() {
+            let spends_ours = super::signer::spent_output(psbt, index)
+                .is_some_and(|txout| txout.script_pubkey == script_pubkey);
+            if !spends_ours {
+                continue;
+            }
+
+            let input = &mut psbt.inputs[index];
+            input.tap_internal_key = Some(derivation.internal_key);
+            input.tap_merkle_root = spend_info.merkle_root();
+            for leaf in spend_info.script_map().keys() {
+                let control_block = spend_info.control_block(leaf).ok_or_else(|| {
+                    WalletError::PsbtError(format!("No control block for leaf {}", leaf.0))
+                })?;
+                input.tap_scripts.insert(control_block, leaf.clone());
+            }
+            input.tap_key_origins.insert(
+                derivation.internal_key,
+                (Vec::new(), derivation.origin.clone()),
+            );
+            for (key, origin) in &derivation.leaf_keys {
+                input
+                    .tap_key_origins
+                    .insert(*key, (derivation.leaf_hashes(key), origin.clone()));
+            }
+            updated += 1;
+        }
+
+        for index in 0..psbt.outputs.len() {
+            if psbt.unsigned_tx.output[index].script_pubkey != script_pubkey {
+                continue;
+            }
+
+            let output = &mut psbt.outputs[index];
+            output.tap_internal_key = Some(derivation.internal_key);
+            output.tap_tree = derivation.tree.clone();
+            output.tap_key_origins.insert(
+                derivation.internal_key,
+                (Vec::new(), derivation.origin.clone()),
+            );
+            for (key, origin) in &derivation.leaf_keys {
+                output
+                    .tap_key_origins
+                    .insert(*key, (derivation.leaf_hashes(key), origin.clone()));
+            }
+            updated += 1;
+        }
+    }
+
+    Ok(updated)
+}
+
+/// Fill `bip32_derivation` for the inputs and outputs using `derivations`
+///
+/// An input or output uses a key if its script pays to the key, or if the
+/// key appears in its redeem or witness script. Returns the number of inputs
+/// and outputs updated.
+pub fn add_key_derivations(psbt: &mut PSBT, derivations: &[KeyDerivation]) -> usize {
+    let mut updated = 0;
+
+    for index in 0..psbt.inputs.len() {
+        let spent = super::signer::spent_output(psbt, index).map(|txout| txout.script_pubkey);
+        let input = &mut psbt.inputs[index];
+        let scripts = [input.redeem_script.as_ref(), input.witness_script.as_ref()];
+        let mut found = false;
+        for derivation in derivations {
+            let key = derivation.public_key.serialize();
+            let uses = spent
+                .as_ref()
+                .is_some_and(|script| derivation.pays_to(script))
+                || scripts.iter().flatten().any(|script| pushes(script, &key));
+            if uses {
+                input
+                    .bip32_derivation
+                    .insert(derivation.public_key, derivation.origin.clone());
+                found = true;
+            }
+        }
+        updated += usize::from(found);
+    }
+
+    for index in 0..psbt.outputs.len() {
+        let script = &psbt.unsigned_tx.output[index].script_pubkey;
+        let output = &mut psbt.outputs[index];
+        let mut found = false;
+        for derivation in derivations {
+            let key = derivation.public_key.serialize();
+            let uses = derivation.pays_to(script)
+                || [
+                    output.redeem_script.as_ref(),
+                    output.witness_script.as_ref(),
+                ]
+                .iter()
+                .flatten()
+                .any(|script| pushes(script, &key));
+            if uses {
+                output
+                    .bip32_derivation
+                    .insert(derivation.public_key, derivation.origin.clone());
+                found = true;
+            }
+        }
+        updated += usize::from(found);
+    }
+
+    updated
+}
+
+/// Check that an external signer has everything it needs to sign `psbt`
+///
+/// Every input that is not final needs the output it spends: the full
+/// previous transaction for legacy inputs, at least the `witness_utxo` for
+/// segwit ones. P2SH and P2WSH inputs need their redeem and witness scripts,
+/// and every input needs the BIP-32 origin of a key that can sign it. Returns
+/// one message per missing or inconsistent field.
+pub fn validate_signable(psbt: &PSBT) -> Result<(), Vec<String>> {
+    let mut problems = Vec::new();
+
+    if psbt.inputs.len() != psbt.unsigned_tx.input.len() {
+        problems.push(format!(
+            "{} PSBT inputs for {} transaction inputs",
+            psbt.inputs.len(),
+            psbt.unsigned_tx.input.len()
+        ));
+        return Err(problems);
+    }
+
+    for (index, (input, txin)) in psbt.inputs.iter().zip(&psbt.unsigned_tx.input).enumerate() {
+        if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+            continue;
+        }
+        let mut missing = |problem: &str| problems.push(format!("input {index}: {problem}"));
+
+        if let Some(tx) = &input.non_witness_utxo {
+            if tx.compute_txid() != txin.previous_output.txid {
+                missing("non_witness_utxo is not the transaction being spent");
+            } else if tx.output.len() <= txin.previous_output.vout as usize {
+                missing("non_witness_utxo has no output at the spent index");
+            }
+        }
+        let Some(spent) = super::signer::spent_output(psbt, index) else {
+            missing("missing witness_utxo or non_witness_utxo");
+            continue;
+        };
+        let script = &spent.script_pubkey;
+
+        // Without the redeem script nothing else about a P2SH input can be checked
+        let program = if script.is_p2sh() {
+            match &input.redeem_script {
+                Some(redeem) if ScriptBuf::new_p2sh(&redeem.script_hash()) == *script => {
+                    redeem.clone()
+                }
+                Some(_) => {
+                    missing("redeem_script does not match the spent output");
+                    continue;
+                }
+                None => {
+                    missing("missing redeem_script for P2SH input");
+                    continue;
+                }
+            }
+        } else {
+            script.clone()
+        };
+        if program.is_p2wsh() {
+            match &input.witness_script {
+                Some(witness) if ScriptBuf::new_p2wsh(&witness.wscript_hash()) == program => {}
+                Some(_) => missing("witness_script does not match the spent output"),
+                None => missing("missing witness_script for P2WSH input"),
+            }
+        }
+        if !program.is_witness_program() && input.non_witness_utxo.is_none() {
+            missing("missing non_witness_utxo for legacy input");
+        }
+
+        if program.is_p2tr() {
+            if input.tap_internal_key.is_none() {
+                missing("missing tap_internal_key");
+            }
+            if input.tap_key_origins.is_empty() {
+                missing("missing tap_key_origins");
+            }
+        } else if input.bip32_derivation.is_empty() {
+            missing("missing bip32_derivation");
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
+
+/// Whether `script` pushes `key`
+fn uses_key(script: &bitcoin::Script, key: &XOnlyPublicKey) -> bool {
+    pushes(script, &key.serialize())
+}
+
+/// Whether `script` pushes exactly `data`
+fn pushes(script: &bitcoin::Script, data: &[u8]) -> bool {
+    script.instructions().any(|instruction| {
+        matches!(instruction, Ok(Instruction::PushBytes(bytes)) if bytes.as_bytes() == data)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::signer::{finalize_psbt, sign_psbt, SighashType};
+    use crate::bitcoin::wallet::{TransactionBuilder, Utxo};
+    use bitcoin::absolute::LockTime;
+    use bitcoin::bip32::Fingerprint;
+    use bitcoin::hashes::Hash;
+    use bitcoin::key::TapTweak;
+    use bitcoin::opcodes::all::OP_CHECKSIG;
+    use bitcoin::secp256k1::{Message, SecretKey};
+    use bitcoin::sighash::{Prevouts, SighashCache};
+    use bitcoin::taproot::{LeafVersion, TaprootBuilder};
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, Network, OutPoint, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+    use std::str::FromStr;
+
+    fn key(seed: u8) -> SecretKey {
+        SecretKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn checksig(key: XOnlyPublicKey) -> ScriptBuf {
+        bitcoin::script::Builder::new()
+            .push_x_only_key(&key)
+            .push_opcode(OP_CHECKSIG)
+            .into_script()
+    }
+
+    /// Key-path output for `internal` with a two-leaf tree using `leaf_key`
+    fn tree_derivation(internal: XOnlyPublicKey, leaf_key: XOnlyPublicKey) -> TaprootDerivation {
+        let tree = TaprootBuilder::new()
+            .add_leaf(1, checksig(leaf_key))
+            .unwrap()
+            .add_leaf(1, ScriptBuf::new_op_return([1u8]))
+            .unwrap();
+        let origin = |path: &str| (Fingerprint::from([1, 2, 3, 4]), path.parse().unwrap());
+        TaprootDerivation::key_path(internal, origin("m/86'/1'/0'/1/0"))
+            .with_tree(TapTree::try_from(tree).unwrap())
+            .with_leaf_key(leaf_key, origin("m/86'/1'/0'/0/7"))
+    }
+
+    fn psbt(spent: &[ScriptBuf], outputs: &[ScriptBuf]) -> PSBT {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: (0..spent.len())
+                .map(|vout| TxIn {
+                    previous_output: OutPoint::new(Txid::all_zeros(), vout as u32),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: outputs
+                .iter()
+                .map(|script| TxOut {
+                    value: Amount::from_sat(20_000),
+                    script_pubkey: script.clone(),
+                })
+                .collect(),
+        };
+        let mut psbt = PSBT::from_unsigned_tx(tx).unwrap();
+        for (input, script) in psbt.inputs.iter_mut().zip(spent) {
+            input.witness_utxo = Some(TxOut {
+                value: Amount::from_sat(50_000),
+                script_pubkey: script.clone(),
+            });
+        }
+        psbt
+    }
+
+    fn p2tr(derivation: &TaprootDerivation) -> ScriptBuf {
+        let secp = Secp256k1::new();
+        ScriptBuf::new_p2tr_tweaked(derivation.spend_info(&secp).output_key())
+    }
+
+    #[test]
+    fn test_taproot_fields_round_trip() {
+        let secp = Secp256k1::new();
+        let master = Xpriv::new_master(Network::Regtest, &[7; 32]).unwrap();
+        let path = DerivationPath::from_str("m/86'/1'/0'/0/3").unwrap();
+        let bip86 = TaprootDerivation::bip86(&secp, &master, &path).unwrap();
+        let change_key = key(2).x_only_public_key(&secp).0;
+        let leaf_key = key(3).x_only_public_key(&secp).0;
+        let with_tree = tree_derivation(change_key, leaf_key);
+
+        let unrelated = p2tr(&TaprootDerivation::key_path(
+            key(9).x_only_public_key(&secp).0,
+            (Fingerprint::default(), DerivationPath::master()),
+        ));
+        let mut psbt = psbt(
+            &[p2tr(&bip86), p2tr(&with_tree)],
+            &[unrelated, p2tr(&with_tree)],
+        );
+        let updated =
+            add_taproot_derivations(&secp, &mut psbt, &[bip86.clone(), with_tree.clone()]).unwrap();
+        assert_eq!(updated, 3);
+
+        let input = &psbt.inputs[0];
+        assert_eq!(input.tap_internal_key, Some(bip86.internal_key));
+        assert_eq!(input.tap_merkle_root, None);
+        assert_eq!(
+            input.tap_key_origins[&bip86.internal_key],
+            (Vec::new(), (master.fingerprint(&secp), path))
+        );
+
+        let input = &psbt.inputs[1];
+        assert_eq!(input.tap_internal_key, Some(change_key));
+        assert!(input.tap_merkle_root.is_some());
+        assert_eq!(input.tap_scripts.len(), 2);
+        let leaf_hash = TapLeafHash::from_script(&checksig(leaf_key), LeafVersion::TapScript);
+        assert_eq!(input.tap_key_origins[&leaf_key].0, vec![leaf_hash]);
+
+        assert!(psbt.outputs[0].tap_internal_key.is_none());
+        assert_eq!(psbt.outputs[1].tap_tree, with_tree.tree);
+        assert_eq!(psbt.outputs[1].tap_key_origins.len(), 2);
+
+        let decoded = PSBT::deserialize(&psbt.serialize()).unwrap();
+        assert_eq!(decoded, psbt);
+    }
+
+    #[test]
+    fn test_signs_key_path_of_output_with_tree() {
+        let secp = Secp256k1::new();
+        let signer = key(2);
+        let derivation = tree_derivation(
+            signer.x_only_public_key(&secp).0,
+            key(3).x_only_public_key(&secp).0,
+        );
+        let mut psbt = psbt(&[p2tr(&derivation)], &[p2tr(&derivation)]);
+        add_taproot_derivations(&secp, &mut psbt, std::slice::from_ref(&derivation)).unwrap();
+
+        assert_eq!(
+            sign_psbt(&secp, &mut psbt, &signer, SighashType::All).unwrap(),
+            1
+        );
+        let signature = psbt.inputs[0].tap_key_sig.unwrap();
+        let prevouts = [psbt.inputs[0].witness_utxo.clone().unwrap()];
+        let sighash = SighashCache::new(&psbt.unsigned_tx)
+            .taproot_key_spend_signature_hash(0, &Prevouts::All(&prevouts), signature.sighash_type)
+            .unwrap();
+        let (output_key, _) = derivation
+            .internal_key
+            .tap_tweak(&secp, psbt.inputs[0].tap_merkle_root);
+        secp.verify_schnorr(
+            &signature.signature,
+            &Message::from_digest(sighash.to_byte_array()),
+            &output_key.to_x_only_public_key(),
+        )
+        .unwrap();
+    }
+
+    fn utxo(outpoint: OutPoint, script_pubkey: ScriptBuf, sats: u64) -> Utxo {
+        Utxo {
+            outpoint,
+            txout: TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey,
+            },
+            redeem_script: None,
+            witness_script: None,
+            confirmations: 6,
+            spendable: true,
+            from_wallet: true,
+        }
+    }
+
+    #[test]
+    fn test_signable_psbt_signs_with_rust_bitcoin() {
+        let secp = Secp256k1::new();
+        let master = Xpriv::new_master(Network::Regtest, &[7; 32]).unwrap();
+        let derive = |path: &str| {
+            KeyDerivation::bip32(&secp, &master, &DerivationPath::from_str(path).unwrap()).unwrap()
+        };
+        let segwit = derive("m/84'/1'/0'/0/0");
+        let legacy = derive("m/44'/1'/0'/0/0");
+        let change = derive("m/84'/1'/0'/1/0");
+        let taproot =
+            TaprootDerivation::bip86(&secp, &master, &"m/86'/1'/0'/0/0".parse().unwrap()).unwrap();
+
+        let p2wpkh = |key: &KeyDerivation| {
+            ScriptBuf::new_p2wpkh(&CompressedPublicKey(key.public_key).wpubkey_hash())
+        };
+        let p2pkh = ScriptBuf::new_p2pkh(&bitcoin::PublicKey::new(legacy.public_key).pubkey_hash());
+        let previous = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: Vec::new(),
+            output: vec![TxOut {
+                value: Amount::from_sat(40_000),
+                script_pubkey: p2pkh.clone(),
+            }],
+        };
+        let utxos = vec![
+            utxo(OutPoint::new(previous.compute_txid(), 0), p2pkh, 40_000),
+            utxo(OutPoint::new(Txid::all_zeros(), 1), p2wpkh(&segwit), 50_000),
+            utxo(OutPoint::new(Txid::all_zeros(), 2), p2tr(&taproot), 60_000),
+        ];
+        let outpoints: Vec<OutPoint> = utxos.iter().map(|utxo| utxo.outpoint).collect();
+
+        let unsigned = TransactionBuilder::new(utxos)
+            .add_recipient(
+                p2wpkh(&derive("m/84'/1'/1'/0/0")),
+                Amount::from_sat(100_000),
+            )
+            .change_script(p2wpkh(&change))
+            .must_spend(&outpoints)
+            .previous_transactions([previous])
+            .key_derivations([segwit.clone(), legacy.clone(), change.clone()])
+            .taproot_derivations([taproot])
+            .build()
+            .unwrap();
+        assert_eq!(validate_signable(&unsigned), Ok(()));
+
+        let legacy_input = unsigned
+            .inputs
+            .iter()
+            .find(|input| input.bip32_derivation.contains_key(&legacy.public_key))
+            .unwrap();
+        assert!(legacy_input.non_witness_utxo.is_some());
+        assert!(legacy_input.witness_utxo.is_none());
+        let change_output = unsigned
+            .outputs
+            .iter()
+            .find(|output| !output.bip32_derivation.is_empty())
+            .unwrap();
+        assert_eq!(
+            change_output.bip32_derivation[&change.public_key],
+            change.origin
+        );
+
+        // An external signer holding only the master key finds every key it needs
+        let mut psbt = unsigned;
+        let signed = psbt.sign(&master, &secp).unwrap();
+        assert_eq!(signed.len(), 3);
+        assert_eq!(finalize_psbt(&mut psbt), 3);
+        assert!(psbt.extract_tx().is_ok());
+    }
+
+    #[test]
+    fn test_validate_signable_lists_missing_fields() {
+        let secp = Secp256k1::new();
+        let segwit = KeyDerivation::bip32(
+            &secp,
+            &Xpriv::new_master(Network::Regtest, &[7; 32]).unwrap(),
+            &"m/84'/1'/0'/0/0".parse().unwrap(),
+        )
+        .unwrap();
+        let p2wpkh = ScriptBuf::new_p2wpkh(&CompressedPublicKey(segwit.public_key).wpubkey_hash());
+        let p2sh = ScriptBuf::new_p2sh(&p2wpkh.script_hash());
+        let p2pkh = ScriptBuf::new_p2pkh(&bitcoin::PublicKey::new(segwit.public_key).pubkey_hash());
+
+        let mut psbt = psbt(
+            &[
+                p2wpkh.clone(),
+                p2sh,
+                p2pkh,
+                p2tr(&tree_derivation(
+                    key(2).x_only_public_key(&secp).0,
+                    key(3).x_only_public_key(&secp).0,
+                )),
+            ],
+            &[],
+        );
+        psbt.inputs.push(Default::default());
+        psbt.unsigned_tx
+            .input
+            .push(psbt.unsigned_tx.input[0].clone());
+        add_key_derivations(&mut psbt, std::slice::from_ref(&segwit));
+
+        assert_eq!(
+            validate_signable(&psbt),
+            Err(vec![
+                "input 1: missing redeem_script for P2SH input".to_string(),
+                "input 2: missing non_witness_utxo for legacy input".to_string(),
+                "input 3: missing tap_internal_key".to_string(),
+                "input 3: missing tap_key_origins".to_string(),
+                "input 4: missing witness_utxo or non_witness_utxo".to_string(),
+            ])
+        );
+
+        psbt.inputs[1].redeem_script = Some(p2wpkh);
+        psbt.inputs.truncate(2);
+        psbt.unsigned_tx.input.truncate(2);
+        assert_eq!(validate_signable(&psbt), Ok(()));
+    }
+}
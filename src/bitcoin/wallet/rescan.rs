@@ -0,0 +1,370 @@
+// Wallet Rescan Module
+// [AIR-3][AIS-3][BPC-3]
+//
+// Walks the chain from a given height and rebuilds the wallet's transaction
+// history and UTXO set from block contents. The derived address range is
+// extended whenever an address within `gap_limit` of its end is found in use,
+// so restored and imported wallets recover funds beyond the initial lookahead.
+
+use super::{AddressManager, AddressType, Utxo, Wallet};
+use crate::bitcoin::error::BitcoinError;
+use crate::{AnyaError, AnyaResult};
+use bitcoin::{Amount, Block, OutPoint, ScriptBuf, Txid};
+use bitcoincore_rpc::RpcApi;
+use std::collections::{HashMap, HashSet};
+
+/// Source of blocks on the active chain
+pub trait BlockSource {
+    /// Height of the current chain tip
+    fn tip_height(&self) -> AnyaResult<u32>;
+
+    /// Block at `height`
+    fn block_at(&self, height: u32) -> AnyaResult<Block>;
+}
+
+impl BlockSource for bitcoincore_rpc::Client {
+    fn tip_height(&self) -> AnyaResult<u32> {
+        let height = self
+            .get_block_count()
+            .map_err(|e| AnyaError::Bitcoin(format!("Failed to get block count: {e}")))?;
+        Ok(height as u32)
+    }
+
+    fn block_at(&self, height: u32) -> AnyaResult<Block> {
+        let hash = self.get_block_hash(height as u64).map_err(|e| {
+            AnyaError::Bitcoin(format!("Failed to get block hash at {height}: {e}"))
+        })?;
+        self.get_block(&hash)
+            .map_err(|e| AnyaError::Bitcoin(format!("Failed to get block {hash}: {e}")))
+    }
+}
+
+/// Progress reported after each scanned block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RescanProgress {
+    pub height: u32,
+    pub tip_height: u32,
+    /// Wallet transactions found so far
+    pub transactions_found: usize,
+}
+
+/// Outcome of a completed rescan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RescanSummary {
+    pub from_height: u32,
+    pub tip_height: u32,
+    pub blocks_scanned: u32,
+    /// Transactions paying to or spending from the wallet
+    pub transactions_found: usize,
+    /// Addresses derived beyond the existing range
+    pub addresses_derived: usize,
+    pub utxo_count: usize,
+    pub balance: Amount,
+}
+
+impl Wallet {
+    /// Rescan blocks from `from_height` to the tip for wallet transactions
+    ///
+    /// Updates the wallet's transactions, UTXOs and balance, and calls
+    /// `progress` after each block. Fails if `from_height` is above the tip.
+    pub fn rescan(
+        &self,
+        source: &impl BlockSource,
+        from_height: u32,
+        mut progress: impl FnMut(RescanProgress),
+    ) -> AnyaResult<RescanSummary> {
+        let tip_height = source.tip_height()?;
+        if from_height > tip_height {
+            return Err(BitcoinError::Wallet(format!(
+                "Rescan height {from_height} is above the chain tip at {tip_height}"
+            ))
+            .into());
+        }
+
+        let gap_limit = self.config.gap_limit.max(1);
+        let mut found = HashSet::new();
+        let mut addresses_derived = 0;
+
+        for height in from_height..=tip_height {
+            let block = source.block_at(height)?;
+
+            // Scanning is idempotent, so repeat the block until the lookahead
+            // stops growing; later transactions may pay newly derived addresses
+            loop {
+                let scripts = self.wallet_scripts()?;
+                let used = self.scan_block(&block, height, tip_height, &scripts, &mut found)?;
+                let derived = self.extend_lookahead(&used, gap_limit)?;
+                if derived == 0 {
+                    break;
+                }
+                addresses_derived += derived;
+            }
+
+            progress(RescanProgress {
+                height,
+                tip_height,
+                transactions_found: found.len(),
+            });
+        }
+
+        let utxos = self
+            .utxos
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?;
+        Ok(RescanSummary {
+            from_height,
+            tip_height,
+            blocks_scanned: tip_height - from_height + 1,
+            transactions_found: found.len(),
+            addresses_derived,
+            utxo_count: utxos.len(),
+            balance: utxos.values().map(|utxo| utxo.txout.value).sum(),
+        })
+    }
+
+    /// Scripts of all derived addresses with their type and index
+    pub(super) fn wallet_scripts(&self) -> AnyaResult<HashMap<ScriptBuf, (AddressType, u32)>> {
+        let addresses = self
+            .addresses
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?;
+
+        let mut scripts = HashMap::new();
+        for (address_type, type_addresses) in addresses.iter() {
+            for (index, address) in type_addresses.iter().enumerate() {
+                scripts.insert(address.script_pubkey(), (*address_type, index as u32));
+            }
+        }
+        Ok(scripts)
+    }
+
+    /// Apply a block's wallet transactions, returning the highest used index per type
+    fn scan_block(
+        &self,
+        block: &Block,
+        height: u32,
+        tip_height: u32,
+        scripts: &HashMap<ScriptBuf, (AddressType, u32)>,
+        found: &mut HashSet<Txid>,
+    ) -> AnyaResult<HashMap<AddressType, u32>> {
+        let mut utxos = self
+            .utxos
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?;
+        let mut transactions = self
+            .transactions
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?;
+
+        let mut used: HashMap<AddressType, u32> = HashMap::new();
+        for tx in &block.txdata {
+            let txid = tx.compute_txid();
+            let mut relevant = false;
+
+            for input in &tx.input {
+                relevant |= utxos.remove(&input.previous_output).is_some();
+            }
+            for (vout, output) in tx.output.iter().enumerate() {
+                let Some((address_type, index)) = scripts.get(&output.script_pubkey) else {
+                    continue;
+                };
+                relevant = true;
+                let highest = used.entry(*address_type).or_insert(*index);
+                *highest = (*highest).max(*index);
+
+                let outpoint = OutPoint::new(txid, vout as u32);
+                utxos.insert(
+                    outpoint,
+                    Utxo {
+                        outpoint,
+                        txout: output.clone(),
+                        redeem_script: None,
+                        witness_script: None,
+                        confirmations: tip_height - height + 1,
+                        spendable: true,
+                        from_wallet: true,
+                    },
+                );
+            }
+
+            if relevant
+                && found.insert(txid)
+                && !transactions
+                    .iter()
+                    .any(|known| known.compute_txid() == txid)
+            {
+                transactions.push(tx.clone());
+            }
+        }
+        Ok(used)
+    }
+
+    /// Derive addresses so `gap_limit` unused ones follow each used index
+    fn extend_lookahead(
+        &self,
+        used: &HashMap<AddressType, u32>,
+        gap_limit: u32,
+    ) -> AnyaResult<usize> {
+        let mut derived = 0;
+        for (address_type, highest) in used {
+            let wanted = (*highest + 1 + gap_limit) as usize;
+            let mut have = self
+                .addresses
+                .lock()
+                .map_err(|e| format!("Mutex lock error: {e}"))?
+                .get(address_type)
+                .map_or(0, Vec::len);
+            while have < wanted {
+                self.get_new_address(*address_type)?;
+                have += 1;
+                derived += 1;
+            }
+        }
+        Ok(derived)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::{
+        BalanceManager, CoinSelectionStrategy, FeeStrategy, TransactionManager, WalletConfig,
+        WalletType,
+    };
+    use bitcoin::absolute::LockTime;
+    use bitcoin::block::{Header, Version as BlockVersion};
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction::Version;
+    use bitcoin::{
+        BlockHash, CompactTarget, Network, Sequence, Transaction, TxIn, TxMerkleNode, TxOut,
+        Witness,
+    };
+    use std::path::PathBuf;
+
+    struct Chain(Vec<Block>);
+
+    impl BlockSource for Chain {
+        fn tip_height(&self) -> AnyaResult<u32> {
+            Ok(self.0.len() as u32 - 1)
+        }
+
+        fn block_at(&self, height: u32) -> AnyaResult<Block> {
+            Ok(self.0[height as usize].clone())
+        }
+    }
+
+    fn wallet() -> Wallet {
+        let config = WalletConfig {
+            wallet_type: WalletType::Standard,
+            network: Network::Regtest,
+            name: "rescan".to_string(),
+            seed_phrase: None,
+            password: None,
+            receive_descriptor: String::new(),
+            change_descriptor: String::new(),
+            xpub: None,
+            data_dir: PathBuf::new(),
+            use_rpc: false,
+            coin_selection: CoinSelectionStrategy::LargestFirst,
+            gap_limit: 20,
+            min_confirmations: 1,
+            fee_strategy: FeeStrategy::Medium,
+        };
+        let wallet = Wallet::new(config, None);
+        wallet.initialize(Some("rescan test seed"), None).unwrap();
+        wallet
+    }
+
+    fn block(txdata: Vec<Transaction>) -> Block {
+        Block {
+            header: Header {
+                version: BlockVersion::ONE,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: 0,
+                bits: CompactTarget::from_consensus(0x207fffff),
+                nonce: 0,
+            },
+            txdata,
+        }
+    }
+
+    fn tx(spends: OutPoint, pays: ScriptBuf, sats: u64) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: spends,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey: pays,
+            }],
+        }
+    }
+
+    fn external(n: u8) -> OutPoint {
+        OutPoint::new(Txid::from_byte_array([n; 32]), 0)
+    }
+
+    fn script(wallet: &Wallet, index: u32) -> ScriptBuf {
+        wallet
+            .get_address(index, AddressType::SegWit)
+            .unwrap()
+            .script_pubkey()
+    }
+
+    #[test]
+    fn test_rescan_follows_gap_limit_and_spends() {
+        let wallet = wallet();
+        let first = tx(external(1), script(&wallet, 5), 10_000);
+        // Beyond the initial 20 addresses, but within the gap after index 5
+        let second = tx(external(2), script(&wallet, 22), 20_000);
+        let third = tx(external(3), script(&wallet, 40), 30_000);
+        let spend = tx(
+            OutPoint::new(first.compute_txid(), 0),
+            ScriptBuf::new_op_return([]),
+            9_000,
+        );
+        // More than gap_limit past the last used address
+        let unreachable = tx(external(4), script(&wallet, 70), 40_000);
+        let chain = Chain(vec![
+            block(vec![]),
+            block(vec![first]),
+            block(vec![second, third]),
+            block(vec![spend, unreachable]),
+        ]);
+
+        let mut heights = Vec::new();
+        let summary = wallet
+            .rescan(&chain, 0, |progress| heights.push(progress.height))
+            .unwrap();
+        assert_eq!(heights, vec![0, 1, 2, 3]);
+        assert_eq!(summary.blocks_scanned, 4);
+        assert_eq!(summary.transactions_found, 4);
+        assert_eq!(summary.utxo_count, 2);
+        assert_eq!(summary.balance, Amount::from_sat(50_000));
+        assert_eq!(summary.addresses_derived, 41);
+        assert_eq!(wallet.get_balance().unwrap(), 50_000);
+
+        // Rescanning again changes nothing
+        let again = wallet.rescan(&chain, 0, |_| {}).unwrap();
+        assert_eq!(again.balance, summary.balance);
+        assert_eq!(again.addresses_derived, 0);
+        assert_eq!(wallet.get_transactions(10, 0).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_rescan_above_tip_fails() {
+        let wallet = wallet();
+        let chain = Chain(vec![block(vec![]), block(vec![])]);
+
+        assert!(wallet.rescan(&chain, 2, |_| {}).is_err());
+        let summary = wallet.rescan(&chain, 1, |_| {}).unwrap();
+        assert_eq!(summary.blocks_scanned, 1);
+        assert_eq!(summary.balance, Amount::ZERO);
+    }
+}
@@ -0,0 +1,536 @@
+// Proof of reserves (BIP-127)
+//
+// A proof is a finalized PSBT that could spend the wallet's UTXOs but can
+// never be mined. Its first input spends a fake outpoint whose txid is
+// derived from the challenge message, so the transaction is invalid. Every
+// other input spends one of the UTXOs being proven and is signed with
+// SIGHASH_ALL, which makes each signature commit to the challenge. A single
+// output pays the total to a script nobody can spend.
+//
+// The verifier checks the signatures against a UTXO set it trusts, typically
+// its own node's, and learns the amount that was proven. Nested segwit
+// outputs cannot be proven, since the signer does not sign them.
+
+use super::signer::{self, spent_output, SighashType};
+use super::{AddressType, Wallet};
+use crate::{AnyaError, AnyaResult};
+use bitcoin::absolute::LockTime;
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::ecdsa;
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::opcodes::OP_TRUE;
+use bitcoin::psbt::Psbt as PSBT;
+use bitcoin::secp256k1::{Message, Secp256k1, Verification};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Amount, OutPoint, PublicKey, ScriptBuf, ScriptHash, Sequence, Transaction, TxIn, TxOut, Txid,
+    Witness, XOnlyPublicKey,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Prefix hashed with the challenge message into the fake input's txid
+const CHALLENGE_PREFIX: &[u8] = b"Proof-of-Reserves: ";
+
+/// Proof of reserves errors
+#[derive(Debug, thiserror::Error)]
+pub enum ReservesError {
+    #[error("Wallet has no UTXOs to prove")]
+    NoUtxos,
+    #[error("Proof does not commit to the challenge message")]
+    ChallengeMismatch,
+    #[error("Proof must have exactly one output, found {0}")]
+    WrongNumberOfOutputs(usize),
+    #[error("Proof output does not pay the unspendable script")]
+    SpendableOutput,
+    #[error("UTXO {0} is spent by more than one input")]
+    DuplicateInput(OutPoint),
+    #[error("UTXO {0} is not in the verifier's UTXO set")]
+    UnknownInput(OutPoint),
+    #[error("UTXO {0} has an unsupported script")]
+    UnsupportedScript(OutPoint),
+    #[error("Input {index} has an invalid signature: {reason}")]
+    InvalidSignature { index: usize, reason: String },
+    #[error("Proof output of {output} does not match the {inputs} spent")]
+    ValueMismatch { inputs: Amount, output: Amount },
+    #[error("Invalid proof: {0}")]
+    Malformed(String),
+}
+
+impl From<ReservesError> for AnyaError {
+    fn from(err: ReservesError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+pub type ReservesResult<T> = Result<T, ReservesError>;
+
+/// Signed proof of control over a set of UTXOs, encoded as a base64 PSBT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReservesProof {
+    psbt: PSBT,
+}
+
+impl ReservesProof {
+    pub fn psbt(&self) -> &PSBT {
+        &self.psbt
+    }
+
+    /// UTXOs the proof claims, excluding the challenge input
+    pub fn outpoints(&self) -> impl Iterator<Item = OutPoint> + '_ {
+        self.psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .skip(1)
+            .map(|txin| txin.previous_output)
+    }
+}
+
+impl From<PSBT> for ReservesProof {
+    fn from(psbt: PSBT) -> Self {
+        Self { psbt }
+    }
+}
+
+impl fmt::Display for ReservesProof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.psbt.fmt(f)
+    }
+}
+
+impl FromStr for ReservesProof {
+    type Err = ReservesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PSBT::from_str(s)
+            .map(Self::from)
+            .map_err(|e| ReservesError::Malformed(format!("not a base64 PSBT: {e}")))
+    }
+}
+
+/// Fake input committing to `challenge`
+pub fn challenge_txin(challenge: &str) -> TxIn {
+    let preimage = [CHALLENGE_PREFIX, challenge.as_bytes()].concat();
+    TxIn {
+        previous_output: OutPoint::new(Txid::from_raw_hash(sha256d::Hash::hash(&preimage)), 0),
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::MAX,
+        witness: Witness::new(),
+    }
+}
+
+/// Output the challenge input pretends to spend
+fn challenge_txout() -> TxOut {
+    TxOut {
+        value: Amount::ZERO,
+        script_pubkey: ScriptBuf::builder().push_opcode(OP_TRUE).into_script(),
+    }
+}
+
+/// P2SH of an all-zero hash, which has no known preimage
+fn unspendable_script() -> ScriptBuf {
+    ScriptBuf::new_p2sh(&ScriptHash::all_zeros())
+}
+
+impl Wallet {
+    /// [AIS-3][BPC-3] Prove control of every UTXO in the wallet
+    ///
+    /// Each UTXO is signed by its address key over a transaction committing
+    /// to `challenge`. Fails if a UTXO pays a nested segwit address or a
+    /// script the wallet did not derive.
+    pub fn prove_reserves(&self, challenge: &str) -> AnyaResult<ReservesProof> {
+        let scripts = self.wallet_scripts()?;
+        let utxos = {
+            let utxos = self
+                .utxos
+                .lock()
+                .map_err(|e| format!("Mutex lock error: {e}"))?;
+            let mut utxos: Vec<_> = utxos.values().cloned().collect();
+            utxos.sort_by_key(|utxo| utxo.outpoint);
+            utxos
+        };
+        if utxos.is_empty() {
+            return Err(ReservesError::NoUtxos.into());
+        }
+
+        let mut paths = Vec::new();
+        for utxo in &utxos {
+            let path = match scripts.get(&utxo.txout.script_pubkey) {
+                Some((AddressType::Legacy, index)) => format!("m/44'/0'/0'/0/{index}"),
+                Some((AddressType::SegWit, index)) => format!("m/84'/0'/0'/0/{index}"),
+                Some((AddressType::Taproot, index)) => format!("m/86'/0'/0'/0/{index}"),
+                Some((AddressType::NestedSegWit, _)) | None => {
+                    return Err(ReservesError::UnsupportedScript(utxo.outpoint).into())
+                }
+            };
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+
+        let total = utxos.iter().map(|utxo| utxo.txout.value).sum();
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: std::iter::once(challenge_txin(challenge))
+                .chain(utxos.iter().map(|utxo| TxIn {
+                    previous_output: utxo.outpoint,
+                    ..Default::default()
+                }))
+                .collect(),
+            output: vec![TxOut {
+                value: total,
+                script_pubkey: unspendable_script(),
+            }],
+        };
+        let mut psbt = PSBT::from_unsigned_tx(tx).map_err(|e| AnyaError::Bitcoin(e.to_string()))?;
+        psbt.inputs[0].witness_utxo = Some(challenge_txout());
+        for (input, utxo) in psbt.inputs[1..].iter_mut().zip(&utxos) {
+            input.witness_utxo = Some(utxo.txout.clone());
+        }
+
+        for path in &paths {
+            self.sign_psbt(&mut psbt, path, SighashType::All)?;
+        }
+        signer::finalize_psbt(&mut psbt);
+        psbt.inputs[0].final_script_sig = Some(ScriptBuf::new());
+        if let Some(index) = psbt.inputs[1..].iter().position(|input| {
+            input.final_script_sig.is_none() && input.final_script_witness.is_none()
+        }) {
+            return Err(ReservesError::InvalidSignature {
+                index: index + 1,
+                reason: "input was not signed".to_string(),
+            }
+            .into());
+        }
+
+        Ok(ReservesProof { psbt })
+    }
+}
+
+/// Check `proof` against `challenge` and the verifier's `utxos`
+///
+/// Every input but the challenge must spend one of `utxos`, once, with a
+/// valid SIGHASH_ALL signature. Only P2PKH, P2WPKH and P2TR key-path
+/// spends are accepted. Returns the proven amount in satoshis.
+pub fn verify_reserves(
+    proof: &ReservesProof,
+    utxos: &[(OutPoint, TxOut)],
+    challenge: &str,
+) -> ReservesResult<u64> {
+    let psbt = &proof.psbt;
+    let tx = &psbt.unsigned_tx;
+    let Some((first, inputs)) = tx.input.split_first() else {
+        return Err(ReservesError::ChallengeMismatch);
+    };
+    if first.previous_output != challenge_txin(challenge).previous_output {
+        return Err(ReservesError::ChallengeMismatch);
+    }
+    if inputs.is_empty() {
+        return Err(ReservesError::Malformed(
+            "proof spends no UTXOs".to_string(),
+        ));
+    }
+    let [output] = tx.output.as_slice() else {
+        return Err(ReservesError::WrongNumberOfOutputs(tx.output.len()));
+    };
+    if output.script_pubkey != unspendable_script() {
+        return Err(ReservesError::SpendableOutput);
+    }
+
+    let known: HashMap<OutPoint, &TxOut> = utxos.iter().map(|(op, txout)| (*op, txout)).collect();
+    let mut seen = HashSet::new();
+    let mut prevouts = vec![challenge_txout()];
+    for txin in inputs {
+        let outpoint = txin.previous_output;
+        if !seen.insert(outpoint) {
+            return Err(ReservesError::DuplicateInput(outpoint));
+        }
+        let txout = known
+            .get(&outpoint)
+            .ok_or(ReservesError::UnknownInput(outpoint))?;
+        prevouts.push((*txout).clone());
+    }
+
+    let total = prevouts
+        .iter()
+        .try_fold(Amount::ZERO, |sum, txout| sum.checked_add(txout.value))
+        .ok_or_else(|| ReservesError::Malformed("input amounts overflow".to_string()))?;
+    if output.value != total {
+        return Err(ReservesError::ValueMismatch {
+            inputs: total,
+            output: output.value,
+        });
+    }
+
+    let secp = Secp256k1::verification_only();
+    let mut cache = SighashCache::new(tx);
+    for index in 1..tx.input.len() {
+        if spent_output(psbt, index).is_some_and(|txout| txout != prevouts[index]) {
+            return Err(ReservesError::InvalidSignature {
+                index,
+                reason: "spent output differs from the verifier's UTXO".to_string(),
+            });
+        }
+        verify_input(&secp, &mut cache, psbt, index, &prevouts)
+            .map_err(|reason| ReservesError::InvalidSignature { index, reason })?;
+    }
+
+    Ok(total.to_sat())
+}
+
+/// Verify the final signature of input `index` against its spent output
+fn verify_input<C: Verification>(
+    secp: &Secp256k1<C>,
+    cache: &mut SighashCache<&Transaction>,
+    psbt: &PSBT,
+    index: usize,
+    prevouts: &[TxOut],
+) -> Result<(), String> {
+    let input = &psbt.inputs[index];
+    let script = &prevouts[index].script_pubkey;
+    let empty = Witness::new();
+    let witness = input.final_script_witness.as_ref().unwrap_or(&empty);
+    let script_sig = input.final_script_sig.clone().unwrap_or_default();
+
+    if script.is_p2tr() {
+        let [signature] = witness
+            .to_vec()
+            .try_into()
+            .map_err(|_| "expected a key-path witness")?;
+        let signature =
+            bitcoin::taproot::Signature::from_slice(&signature).map_err(|e| e.to_string())?;
+        if !matches!(
+            signature.sighash_type,
+            TapSighashType::Default | TapSighashType::All
+        ) {
+            return Err(format!(
+                "sighash {} does not commit to the challenge",
+                signature.sighash_type
+            ));
+        }
+        let output_key =
+            XOnlyPublicKey::from_slice(&script.as_bytes()[2..]).map_err(|e| e.to_string())?;
+        let sighash = cache
+            .taproot_key_spend_signature_hash(
+                index,
+                &Prevouts::All(prevouts),
+                signature.sighash_type,
+            )
+            .map_err(|e| e.to_string())?;
+        let message = Message::from_digest(sighash.to_byte_array());
+        return secp
+            .verify_schnorr(&signature.signature, &message, &output_key)
+            .map_err(|e| e.to_string());
+    }
+
+    let (signature, key) = if script.is_p2wpkh() {
+        let [signature, key] = witness
+            .to_vec()
+            .try_into()
+            .map_err(|_| "expected a P2WPKH witness")?;
+        (signature, key)
+    } else if script.is_p2pkh() {
+        let pushes = script_sig
+            .instructions()
+            .map(|instruction| match instruction {
+                Ok(Instruction::PushBytes(bytes)) => Ok(bytes.as_bytes().to_vec()),
+                _ => Err("expected a P2PKH script sig".to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let [signature, key] = pushes
+            .try_into()
+            .map_err(|_| "expected a P2PKH script sig")?;
+        (signature, key)
+    } else {
+        return Err("unsupported script".to_string());
+    };
+
+    let signature = ecdsa::Signature::from_slice(&signature).map_err(|e| e.to_string())?;
+    if signature.sighash_type != EcdsaSighashType::All {
+        return Err(format!(
+            "sighash {} does not commit to the challenge",
+            signature.sighash_type
+        ));
+    }
+    let key = PublicKey::from_slice(&key).map_err(|e| e.to_string())?;
+    let digest = if script.is_p2wpkh() {
+        let hash = key.wpubkey_hash().map_err(|e| e.to_string())?;
+        if *script != ScriptBuf::new_p2wpkh(&hash) {
+            return Err("key does not match the spent script".to_string());
+        }
+        cache
+            .p2wpkh_signature_hash(index, script, prevouts[index].value, signature.sighash_type)
+            .map_err(|e| e.to_string())?
+            .to_byte_array()
+    } else {
+        if *script != ScriptBuf::new_p2pkh(&key.pubkey_hash()) {
+            return Err("key does not match the spent script".to_string());
+        }
+        cache
+            .legacy_signature_hash(index, script, signature.sighash_type.to_u32())
+            .map_err(|e| e.to_string())?
+            .to_byte_array()
+    };
+    secp.verify_ecdsa(
+        &Message::from_digest(digest),
+        &signature.signature,
+        &key.inner,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::{
+        AddressManager, CoinSelectionStrategy, FeeStrategy, Utxo, WalletConfig, WalletType,
+    };
+    use bitcoin::Network;
+    use std::path::PathBuf;
+
+    fn wallet() -> Wallet {
+        let config = WalletConfig {
+            wallet_type: WalletType::Standard,
+            network: Network::Regtest,
+            name: "reserves".to_string(),
+            seed_phrase: None,
+            password: None,
+            receive_descriptor: String::new(),
+            change_descriptor: String::new(),
+            xpub: None,
+            data_dir: PathBuf::new(),
+            use_rpc: false,
+            coin_selection: CoinSelectionStrategy::LargestFirst,
+            gap_limit: 20,
+            min_confirmations: 1,
+            fee_strategy: FeeStrategy::Medium,
+        };
+        let wallet = Wallet::new(config, None);
+        wallet.initialize(Some("reserves test seed"), None).unwrap();
+        wallet
+    }
+
+    /// Give `wallet` one UTXO per address, returning the verifier's view of them
+    fn fund(wallet: &Wallet, addresses: &[(AddressType, u32, u64)]) -> Vec<(OutPoint, TxOut)> {
+        let mut funded = Vec::new();
+        let mut utxos = wallet.utxos.lock().unwrap();
+        for (n, (address_type, index, sats)) in addresses.iter().enumerate() {
+            let outpoint = OutPoint::new(Txid::from_byte_array([n as u8 + 1; 32]), *index);
+            let txout = TxOut {
+                value: Amount::from_sat(*sats),
+                script_pubkey: wallet
+                    .get_address(*index, *address_type)
+                    .unwrap()
+                    .script_pubkey(),
+            };
+            utxos.insert(
+                outpoint,
+                Utxo {
+                    outpoint,
+                    txout: txout.clone(),
+                    redeem_script: None,
+                    witness_script: None,
+                    confirmations: 6,
+                    spendable: true,
+                    from_wallet: true,
+                },
+            );
+            funded.push((outpoint, txout));
+        }
+        funded
+    }
+
+    #[test]
+    fn test_prove_and_verify_multi_utxo_reserves() {
+        let wallet = wallet();
+        let utxos = fund(
+            &wallet,
+            &[
+                (AddressType::SegWit, 0, 50_000),
+                (AddressType::SegWit, 3, 20_000),
+                (AddressType::Legacy, 1, 15_000),
+                (AddressType::Taproot, 2, 40_000),
+            ],
+        );
+
+        let proof = wallet.prove_reserves("audit 2026-10-15").unwrap();
+        assert_eq!(proof.outpoints().count(), 4);
+        assert_eq!(
+            verify_reserves(&proof, &utxos, "audit 2026-10-15").unwrap(),
+            125_000
+        );
+
+        // The proof survives its text encoding and can never be mined
+        let decoded: ReservesProof = proof.to_string().parse().unwrap();
+        assert_eq!(
+            verify_reserves(&decoded, &utxos, "audit 2026-10-15").unwrap(),
+            125_000
+        );
+        assert_eq!(
+            decoded.psbt().unsigned_tx.input[0].previous_output,
+            challenge_txin("audit 2026-10-15").previous_output
+        );
+
+        assert!(matches!(
+            verify_reserves(&proof, &utxos, "audit 2026-10-16"),
+            Err(ReservesError::ChallengeMismatch)
+        ));
+        assert!(matches!(
+            verify_reserves(&proof, &utxos[1..], "audit 2026-10-15"),
+            Err(ReservesError::UnknownInput(outpoint)) if outpoint == utxos[0].0
+        ));
+    }
+
+    #[test]
+    fn test_verify_rejects_reused_utxo_and_tampering() {
+        let wallet = wallet();
+        let utxos = fund(
+            &wallet,
+            &[
+                (AddressType::SegWit, 0, 30_000),
+                (AddressType::Taproot, 0, 10_000),
+            ],
+        );
+        let proof = wallet.prove_reserves("challenge").unwrap();
+
+        let mut reused = proof.psbt().clone();
+        let txin = reused.unsigned_tx.input[1].clone();
+        let input = reused.inputs[1].clone();
+        reused.unsigned_tx.input.push(txin);
+        reused.inputs.push(input);
+        reused.unsigned_tx.output[0].value += utxos[0].1.value;
+        assert!(matches!(
+            verify_reserves(&reused.into(), &utxos, "challenge"),
+            Err(ReservesError::DuplicateInput(outpoint)) if outpoint == utxos[0].0
+        ));
+
+        let mut inflated = proof.psbt().clone();
+        inflated.unsigned_tx.output[0].value += Amount::from_sat(1);
+        assert!(matches!(
+            verify_reserves(&inflated.into(), &utxos, "challenge"),
+            Err(ReservesError::ValueMismatch { .. })
+        ));
+
+        // Moving value between the verifier's UTXOs breaks the signatures
+        let mut shifted = utxos.clone();
+        shifted[0].1.value -= Amount::from_sat(1_000);
+        shifted[1].1.value += Amount::from_sat(1_000);
+        assert!(matches!(
+            verify_reserves(&proof, &shifted, "challenge"),
+            Err(ReservesError::InvalidSignature { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_prove_rejects_nested_segwit() {
+        let wallet = wallet();
+        assert!(wallet.prove_reserves("challenge").is_err());
+
+        fund(&wallet, &[(AddressType::NestedSegWit, 0, 10_000)]);
+        assert!(wallet.prove_reserves("challenge").is_err());
+    }
+}
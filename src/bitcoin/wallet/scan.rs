@@ -0,0 +1,302 @@
+// Address gap-limit scanning
+//
+// Derives addresses from the receive and change descriptors in index order and
+// asks a chain source whether each has been used. A chain is scanned until
+// `gap_limit` consecutive unused addresses follow the last used one, so funds
+// beyond the initial lookahead of a restored wallet are found. The receive and
+// change chains keep separate gap counters: change addresses are used in bursts
+// that have nothing to do with how many receive addresses were handed out.
+
+use super::{AddressInfo, BitcoinWallet, WalletError};
+use crate::bitcoin::chain_source::ChainSource;
+use bitcoin::{Address, Network};
+use miniscript::descriptor::{Descriptor, DescriptorPublicKey};
+
+/// Used addresses found on one derivation chain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainScan {
+    /// Highest used index, `None` if no address of the chain was used
+    pub last_used_index: Option<u32>,
+    /// Used addresses with their index, in index order
+    pub used: Vec<(u32, Address)>,
+    /// Addresses derived and queried
+    pub addresses_scanned: u32,
+}
+
+impl ChainScan {
+    /// Index of the first address after the last used one
+    pub fn next_index(&self) -> u32 {
+        self.last_used_index.map_or(0, |index| index + 1)
+    }
+}
+
+/// Outcome of [`BitcoinWallet::scan_addresses`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanResult {
+    pub receive: ChainScan,
+    pub change: ChainScan,
+}
+
+impl BitcoinWallet {
+    /// Find the used receive and change addresses up to `gap_limit`
+    ///
+    /// Each chain is scanned from index 0 until `gap_limit` consecutive
+    /// addresses are unused. Used addresses are recorded in the wallet and
+    /// its next receive and change indexes are moved past the last used ones.
+    pub async fn scan_addresses(
+        &self,
+        chain_source: &dyn ChainSource,
+        gap_limit: u32,
+    ) -> Result<ScanResult, WalletError> {
+        if gap_limit == 0 {
+            return Err(WalletError::InvalidParameters(
+                "gap limit must be at least 1".to_string(),
+            ));
+        }
+        let receive = self.parse_descriptor(&self.config.receive_descriptor)?;
+        let change = self.parse_descriptor(&self.config.change_descriptor)?;
+
+        let network = self.config.network;
+        let result = ScanResult {
+            receive: scan_chain(&receive, network, chain_source, gap_limit).await?,
+            change: scan_chain(&change, network, chain_source, gap_limit).await?,
+        };
+
+        let mut storage = self.storage.lock().unwrap_or_else(|e| e.into_inner());
+        for (scan, is_change) in [(&result.receive, false), (&result.change, true)] {
+            for (index, address) in &scan.used {
+                storage
+                    .addresses
+                    .entry(address.to_string())
+                    .or_insert_with(|| AddressInfo {
+                        address: address.to_string(),
+                        path: None,
+                        script: address.script_pubkey(),
+                        is_change,
+                        index: *index,
+                        labels: Vec::new(),
+                        last_used: None,
+                    });
+            }
+        }
+        let indexes = &mut storage.indexes;
+        indexes.receive_index = indexes.receive_index.max(result.receive.next_index());
+        indexes.change_index = indexes.change_index.max(result.change.next_index());
+
+        Ok(result)
+    }
+
+    fn parse_descriptor(
+        &self,
+        descriptor: &str,
+    ) -> Result<Descriptor<DescriptorPublicKey>, WalletError> {
+        let (descriptor, _) = Descriptor::parse_descriptor(&self.secp, descriptor)
+            .map_err(|e| WalletError::DescriptorError(format!("{descriptor:?}: {e}")))?;
+        if !descriptor.has_wildcard() {
+            return Err(WalletError::DescriptorError(format!(
+                "{descriptor} derives a single address and cannot be scanned"
+            )));
+        }
+        Ok(descriptor)
+    }
+}
+
+/// Scan one descriptor chain until `gap_limit` unused addresses in a row
+async fn scan_chain(
+    descriptor: &Descriptor<DescriptorPublicKey>,
+    network: Network,
+    chain_source: &dyn ChainSource,
+    gap_limit: u32,
+) -> Result<ChainScan, WalletError> {
+    let mut scan = ChainScan::default();
+    let mut unused = 0;
+    while unused < gap_limit {
+        let index = scan.addresses_scanned;
+        let address = descriptor
+            .at_derivation_index(index)
+            .map_err(|e| WalletError::DescriptorError(format!("index {index}: {e}")))?
+            .address(network)
+            .map_err(|e| WalletError::DescriptorError(format!("index {index}: {e}")))?;
+        let used = chain_source
+            .is_script_used(&address.script_pubkey())
+            .await
+            .map_err(|e| WalletError::SyncError(e.to_string()))?;
+        scan.addresses_scanned += 1;
+
+        if used {
+            scan.last_used_index = Some(index);
+            scan.used.push((index, address));
+            unused = 0;
+        } else {
+            unused += 1;
+        }
+    }
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        CoinSelectionStrategy, FeeStrategy, WalletConfig, WalletIndexes, WalletMetadata,
+        WalletStorage, WalletType,
+    };
+    use super::*;
+    use crate::bitcoin::chain_source::{ChainUtxo, MockChainSource};
+    use bitcoin::bip32::{Xpriv, Xpub};
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::Secp256k1;
+    use bitcoin::{Amount, OutPoint, ScriptBuf, TxOut, Txid};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+
+    fn wallet(receive_descriptor: String, change_descriptor: String) -> BitcoinWallet {
+        BitcoinWallet {
+            config: WalletConfig {
+                wallet_type: WalletType::Standard,
+                network: Network::Regtest,
+                name: "scan".to_string(),
+                seed_phrase: None,
+                password: None,
+                receive_descriptor,
+                change_descriptor,
+                xpub: None,
+                data_dir: PathBuf::new(),
+                use_rpc: false,
+                coin_selection: CoinSelectionStrategy::LargestFirst,
+                gap_limit: 20,
+                min_confirmations: 1,
+                fee_strategy: FeeStrategy::Medium,
+            },
+            storage: Arc::new(Mutex::new(WalletStorage {
+                metadata: WalletMetadata {
+                    created_at: 0,
+                    updated_at: 0,
+                    version: "1".to_string(),
+                    network: Network::Regtest,
+                    master_fingerprint: None,
+                    labels: HashMap::new(),
+                },
+                utxos: HashMap::new(),
+                transactions: HashMap::new(),
+                addresses: HashMap::new(),
+                indexes: WalletIndexes {
+                    receive_index: 0,
+                    change_index: 0,
+                    last_block: None,
+                    last_sync: None,
+                },
+                labels: Default::default(),
+            })),
+            secp: Secp256k1::new(),
+            seed: None,
+        }
+    }
+
+    /// Wallet with `wpkh` receive and change chains under one test key
+    fn descriptor_wallet() -> BitcoinWallet {
+        let secp = Secp256k1::new();
+        let xpriv = Xpriv::new_master(Network::Regtest, &[7; 32]).unwrap();
+        let xpub = Xpub::from_priv(&secp, &xpriv);
+        wallet(format!("wpkh({xpub}/0/*)"), format!("wpkh({xpub}/1/*)"))
+    }
+
+    fn script(wallet: &BitcoinWallet, change: bool, index: u32) -> ScriptBuf {
+        let descriptor = if change {
+            &wallet.config.change_descriptor
+        } else {
+            &wallet.config.receive_descriptor
+        };
+        Descriptor::<DescriptorPublicKey>::from_str(descriptor)
+            .unwrap()
+            .at_derivation_index(index)
+            .unwrap()
+            .script_pubkey()
+    }
+
+    fn fund(chain: &MockChainSource, script_pubkey: ScriptBuf) {
+        chain.add_utxo(ChainUtxo {
+            outpoint: OutPoint::new(Txid::all_zeros(), 0),
+            txout: TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey,
+            },
+            height: Some(1),
+        });
+    }
+
+    #[tokio::test]
+    async fn test_scan_extends_past_used_addresses_per_chain() {
+        let wallet = descriptor_wallet();
+        let chain = MockChainSource::new();
+        fund(&chain, script(&wallet, false, 0));
+        // Spent by now, but still marks the address as used
+        chain.add_spent_history(script(&wallet, false, 4));
+        // Within the gap after index 4, so found
+        fund(&chain, script(&wallet, false, 9));
+        // Five unused addresses after index 9 end the scan first
+        fund(&chain, script(&wallet, false, 15));
+        // The change chain has its own gap: index 3 is found even though
+        // the receive chain's last used index is far beyond
+        fund(&chain, script(&wallet, true, 3));
+
+        let result = wallet.scan_addresses(&chain, 5).await.unwrap();
+        let indexes: Vec<u32> = result
+            .receive
+            .used
+            .iter()
+            .map(|(index, _)| *index)
+            .collect();
+        assert_eq!(indexes, vec![0, 4, 9]);
+        assert_eq!(result.receive.last_used_index, Some(9));
+        assert_eq!(result.receive.addresses_scanned, 15);
+        assert_eq!(result.change.last_used_index, Some(3));
+        assert_eq!(result.change.addresses_scanned, 9);
+
+        {
+            let storage = wallet.storage.lock().unwrap();
+            assert_eq!(storage.indexes.receive_index, 10);
+            assert_eq!(storage.indexes.change_index, 4);
+            assert_eq!(storage.addresses.len(), 4);
+            assert_eq!(
+                storage
+                    .addresses
+                    .values()
+                    .filter(|info| info.is_change)
+                    .count(),
+                1
+            );
+        }
+
+        // A larger gap limit reaches index 15, and the scan is deterministic
+        let wider = wallet.scan_addresses(&chain, 10).await.unwrap();
+        assert_eq!(wider.receive.last_used_index, Some(15));
+        assert_eq!(wider.receive.used[..3], result.receive.used[..]);
+        assert_eq!(
+            wider.change,
+            wallet.scan_addresses(&chain, 10).await.unwrap().change
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_of_unused_wallet_and_invalid_input() {
+        let unused = descriptor_wallet();
+        let chain = MockChainSource::new();
+        let result = unused.scan_addresses(&chain, 20).await.unwrap();
+        assert_eq!(result.receive.last_used_index, None);
+        assert_eq!(result.receive.next_index(), 0);
+        assert_eq!(result.receive.addresses_scanned, 20);
+        assert_eq!(result.change.addresses_scanned, 20);
+
+        assert!(matches!(
+            unused.scan_addresses(&chain, 0).await,
+            Err(WalletError::InvalidParameters(_))
+        ));
+        let unscannable = wallet(unused.config.receive_descriptor.clone(), String::new());
+        assert!(matches!(
+            unscannable.scan_addresses(&chain, 20).await,
+            Err(WalletError::DescriptorError(_))
+        ));
+    }
+}
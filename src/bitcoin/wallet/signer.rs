@@ -0,0 +1,553 @@
+// PSBT signing with explicit sighash selection
+//
+// Signs the inputs of a PSBT that a single key controls: P2PKH and P2WPKH
+// inputs with ECDSA, P2TR inputs with a key-path Schnorr signature. P2TR
+// outputs committing to a script tree are recognised through the input's
+// BIP-371 `tap_merkle_root`; without one, BIP-86 outputs are assumed.
+// The sighash type is chosen by the caller and applies to every input signed
+// in the call. Signatures are only written once every input has been checked,
+// so a rejected call leaves the PSBT untouched. `finalize_psbt` turns those
+// signatures into the final script sig or witness of each input.
+
+use super::WalletError;
+use bitcoin::hashes::Hash;
+use bitcoin::key::{Keypair, TapTweak};
+use bitcoin::psbt::Psbt as PSBT;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey, Signing, Verification};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::{CompressedPublicKey, PublicKey, ScriptBuf, TxOut, Witness};
+
+/// Which parts of the transaction a signature commits to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SighashType {
+    /// All inputs and all outputs
+    #[default]
+    All,
+    /// All inputs, no outputs
+    None,
+    /// All inputs and the output at the same index as the signed input
+    Single,
+    /// Only the signed input and all outputs
+    AllPlusAnyoneCanPay,
+    /// Only the signed input, no outputs
+    NonePlusAnyoneCanPay,
+    /// Only the signed input and the output at the same index
+    SinglePlusAnyoneCanPay,
+}
+
+impl SighashType {
+    /// Every supported sighash type
+    pub const ALL_TYPES: [SighashType; 6] = [
+        SighashType::All,
+        SighashType::None,
+        SighashType::Single,
+        SighashType::AllPlusAnyoneCanPay,
+        SighashType::NonePlusAnyoneCanPay,
+        SighashType::SinglePlusAnyoneCanPay,
+    ];
+
+    /// Whether the signature commits to the output at the input's index
+    pub fn is_single(self) -> bool {
+        matches!(self, Self::Single | Self::SinglePlusAnyoneCanPay)
+    }
+
+    /// Whether the signature commits to the signed input only
+    pub fn is_anyone_can_pay(self) -> bool {
+        matches!(
+            self,
+            Self::AllPlusAnyoneCanPay | Self::NonePlusAnyoneCanPay | Self::SinglePlusAnyoneCanPay
+        )
+    }
+
+    /// Flag for legacy and segwit v0 signatures
+    pub fn to_ecdsa(self) -> EcdsaSighashType {
+        match self {
+            Self::All => EcdsaSighashType::All,
+            Self::None => EcdsaSighashType::None,
+            Self::Single => EcdsaSighashType::Single,
+            Self::AllPlusAnyoneCanPay => EcdsaSighashType::AllPlusAnyoneCanPay,
+            Self::NonePlusAnyoneCanPay => EcdsaSighashType::NonePlusAnyoneCanPay,
+            Self::SinglePlusAnyoneCanPay => EcdsaSighashType::SinglePlusAnyoneCanPay,
+        }
+    }
+
+    /// Flag for taproot signatures
+    ///
+    /// `All` maps to an explicit `SIGHASH_ALL` byte rather than
+    /// `SIGHASH_DEFAULT`; both commit to the same data.
+    pub fn to_taproot(self) -> TapSighashType {
+        match self {
+            Self::All => TapSighashType::All,
+            Self::None => TapSighashType::None,
+            Self::Single => TapSighashType::Single,
+            Self::AllPlusAnyoneCanPay => TapSighashType::AllPlusAnyoneCanPay,
+            Self::NonePlusAnyoneCanPay => TapSighashType::NonePlusAnyoneCanPay,
+            Self::SinglePlusAnyoneCanPay => TapSighashType::SinglePlusAnyoneCanPay,
+        }
+    }
+}
+
+/// Signature produced for one input, applied once all inputs are checked
+enum InputSignature {
+    Ecdsa(PublicKey, bitcoin::ecdsa::Signature),
+    Taproot(bitcoin::taproot::Signature),
+}
+
+/// Sign every input of `psbt` spendable by `secret_key`
+///
+/// Inputs need a `witness_utxo` or `non_witness_utxo`; inputs paying to other
+/// keys are left alone. If an input already requests a sighash type, it must
+/// match `sighash_type`. With `SIGHASH_SINGLE`, every signed input needs an
+/// output at the same index. Returns the number of inputs signed.
+pub fn sign_psbt<C: Signing + Verification>(
+    secp: &Secp256k1<C>,
+    psbt: &mut PSBT,
+    secret_key: &SecretKey,
+    sighash_type: SighashType,
+) -> Result<usize, WalletError> {
+    let public_key = PublicKey::new(secret_key.public_key(secp));
+    let compressed = CompressedPublicKey(public_key.inner);
+    let keypair = Keypair::from_secret_key(secp, secret_key);
+    let (internal_key, _) = keypair.x_only_public_key();
+
+    let p2pkh = ScriptBuf::new_p2pkh(&public_key.pubkey_hash());
+    let p2wpkh = ScriptBuf::new_p2wpkh(&compressed.wpubkey_hash());
+
+    let spent = (0..psbt.inputs.len())
+        .map(|index| spent_output(psbt, index))
+        .collect::<Vec<_>>();
+    let output_count = psbt.unsigned_tx.output.len();
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+    let mut signatures = Vec::new();
+
+    for (index, txout) in spent.iter().enumerate() {
+        let Some(txout) = txout else { continue };
+        let script = &txout.script_pubkey;
+        let merkle_root = psbt.inputs[index].tap_merkle_root;
+        let p2tr = ScriptBuf::new_p2tr(secp, internal_key, merkle_root);
+        if *script != p2pkh && *script != p2wpkh && *script != p2tr {
+            continue;
+        }
+
+        if sighash_type.is_single() && index >= output_count {
+            return Err(WalletError::SigningError(format!(
+                "SIGHASH_SINGLE on input {index} has no corresponding output"
+            )));
+        }
+        if let Some(requested) = psbt.inputs[index].sighash_type {
+            let matches = if *script == p2tr {
+                requested.taproot_hash_ty().ok() == Some(sighash_type.to_taproot())
+            } else {
+                requested.ecdsa_hash_ty().ok() == Some(sighash_type.to_ecdsa())
+            };
+            if !matches {
+                return Err(WalletError::SigningError(format!(
+                    "Input {index} requests sighash {requested}, not {sighash_type:?}"
+                )));
+            }
+        }
+
+        let signature = if *script == p2tr {
+            let hash_ty = sighash_type.to_taproot();
+            let sighash = if sighash_type.is_anyone_can_pay() {
+                cache.taproot_key_spend_signature_hash(
+                    index,
+                    &Prevouts::One(index, txout),
+                    hash_ty,
+                )
+            } else {
+                let prevouts = spent
+                    .iter()
+                    .enumerate()
+                    .map(|(i, txout)| {
+                        txout.as_ref().ok_or_else(|| {
+                            WalletError::SigningError(format!(
+                                "Input {i} is missing its spent output, required to sign taproot input {index}"
+                            ))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                cache.taproot_key_spend_signature_hash(index, &Prevouts::All(&prevouts), hash_ty)
+            }
+            .map_err(|e| WalletError::SigningError(e.to_string()))?;
+            let message = Message::from_digest(sighash.to_byte_array());
+            let tweaked = keypair.tap_tweak(secp, merkle_root);
+            InputSignature::Taproot(bitcoin::taproot::Signature {
+                signature: secp.sign_schnorr(&message, &tweaked.to_keypair()),
+                sighash_type: hash_ty,
+            })
+        } else {
+            let hash_ty = sighash_type.to_ecdsa();
+            let digest = if *script == p2wpkh {
+                cache
+                    .p2wpkh_signature_hash(index, script, txout.value, hash_ty)
+                    .map_err(|e| WalletError::SigningError(e.to_string()))?
+                    .to_byte_array()
+            } else {
+                cache
+                    .legacy_signature_hash(index, script, hash_ty.to_u32())
+                    .map_err(|e| WalletError::SigningError(e.to_string()))?
+                    .to_byte_array()
+            };
+            let message = Message::from_digest(digest);
+            InputSignature::Ecdsa(
+                public_key,
+                bitcoin::ecdsa::Signature {
+                    signature: secp.sign_ecdsa(&message, secret_key),
+                    sighash_type: hash_ty,
+                },
+            )
+        };
+        signatures.push((index, signature));
+    }
+
+    let signed = signatures.len();
+    for (index, signature) in signatures {
+        let input = &mut psbt.inputs[index];
+        match signature {
+            InputSignature::Ecdsa(key, signature) => {
+                input.partial_sigs.insert(key, signature);
+            }
+            InputSignature::Taproot(signature) => {
+                input.tap_key_sig = Some(signature);
+                input.tap_internal_key = Some(internal_key);
+            }
+        }
+    }
+    Ok(signed)
+}
+
+/// Move the signatures made by [`sign_psbt`] into final scripts
+///
+/// Finalizes P2PKH and P2WPKH inputs signed by the key their script pays to,
+/// and P2TR inputs with a key-path signature, then clears their signing data
+/// as BIP-174 requires. Inputs that are already final or not signed yet are
+/// left alone. Returns the number of inputs finalized.
+pub fn finalize_psbt(psbt: &mut PSBT) -> usize {
+    let mut finalized = 0;
+    for index in 0..psbt.inputs.len() {
+        let Some(txout) = spent_output(psbt, index) else {
+            continue;
+        };
+        let script = &txout.script_pubkey;
+        let input = &mut psbt.inputs[index];
+        if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+            continue;
+        }
+
+        if script.is_p2tr() {
+            let Some(signature) = input.tap_key_sig else {
+                continue;
+            };
+            input.final_script_witness = Some(Witness::p2tr_key_spend(&signature));
+        } else {
+            let signer = input.partial_sigs.iter().find(|(key, _)| {
+                *script == ScriptBuf::new_p2pkh(&key.pubkey_hash())
+                    || key
+                        .wpubkey_hash()
+                        .is_ok_and(|hash| *script == ScriptBuf::new_p2wpkh(&hash))
+            });
+            let Some((key, signature)) = signer else {
+                continue;
+            };
+            if script.is_p2wpkh() {
+                input.final_script_witness = Some(Witness::p2wpkh(signature, &key.inner));
+            } else {
+                input.final_script_sig = Some(
+                    ScriptBuf::builder()
+                        .push_slice(signature.serialize())
+                        .push_key(key)
+                        .into_script(),
+                );
+            }
+        }
+
+        input.partial_sigs.clear();
+        input.sighash_type = None;
+        input.redeem_script = None;
+        input.witness_script = None;
+        input.bip32_derivation.clear();
+        input.tap_key_sig = None;
+        input.tap_script_sigs.clear();
+        input.tap_scripts.clear();
+        input.tap_key_origins.clear();
+        input.tap_internal_key = None;
+        input.tap_merkle_root = None;
+        finalized += 1;
+    }
+    finalized
+}
+
+/// Output spent by input `index`, from its witness or full previous transaction
+pub(super) fn spent_output(psbt: &PSBT, index: usize) -> Option<TxOut> {
+    let input = &psbt.inputs[index];
+    if let Some(txout) = &input.witness_utxo {
+        return Some(txout.clone());
+    }
+    let vout = psbt.unsigned_tx.input[index].previous_output.vout as usize;
+    input
+        .non_witness_utxo
+        .as_ref()
+        .and_then(|tx| tx.output.get(vout))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::key::TweakedPublicKey;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, OutPoint, Sequence, Transaction, TxIn, Txid, Witness, XOnlyPublicKey};
+
+    fn key(seed: u8) -> SecretKey {
+        SecretKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn p2pkh(secret_key: &SecretKey) -> ScriptBuf {
+        let secp = Secp256k1::new();
+        ScriptBuf::new_p2pkh(&PublicKey::new(secret_key.public_key(&secp)).pubkey_hash())
+    }
+
+    fn p2wpkh(secret_key: &SecretKey) -> ScriptBuf {
+        let secp = Secp256k1::new();
+        ScriptBuf::new_p2wpkh(&CompressedPublicKey(secret_key.public_key(&secp)).wpubkey_hash())
+    }
+
+    fn p2tr(secret_key: &SecretKey) -> ScriptBuf {
+        let secp = Secp256k1::new();
+        let (internal_key, _) = secret_key.x_only_public_key(&secp);
+        ScriptBuf::new_p2tr(&secp, internal_key, None)
+    }
+
+    /// PSBT spending one input per script, with `outputs` outputs
+    fn psbt(scripts: &[ScriptBuf], outputs: usize) -> PSBT {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: (0..scripts.len())
+                .map(|vout| TxIn {
+                    previous_output: OutPoint::new(Txid::all_zeros(), vout as u32),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: (0..outputs)
+                .map(|i| TxOut {
+                    value: Amount::from_sat(10_000 + i as u64),
+                    script_pubkey: p2wpkh(&key(200)),
+                })
+                .collect(),
+        };
+        let mut psbt = PSBT::from_unsigned_tx(tx).unwrap();
+        for (input, script) in psbt.inputs.iter_mut().zip(scripts) {
+            input.witness_utxo = Some(TxOut {
+                value: Amount::from_sat(50_000),
+                script_pubkey: script.clone(),
+            });
+        }
+        psbt
+    }
+
+    fn prevouts(psbt: &PSBT) -> Vec<TxOut> {
+        psbt.inputs
+            .iter()
+            .map(|input| input.witness_utxo.clone().unwrap())
+            .collect()
+    }
+
+    fn verify_ecdsa(psbt: &PSBT, index: usize, secret_key: &SecretKey, legacy: bool) {
+        let secp = Secp256k1::new();
+        let public_key = PublicKey::new(secret_key.public_key(&secp));
+        let signature = psbt.inputs[index].partial_sigs[&public_key];
+        let txout = psbt.inputs[index].witness_utxo.as_ref().unwrap();
+        let mut cache = SighashCache::new(&psbt.unsigned_tx);
+        let digest = if legacy {
+            cache
+                .legacy_signature_hash(index, &txout.script_pubkey, signature.sighash_type.to_u32())
+                .unwrap()
+                .to_byte_array()
+        } else {
+            cache
+                .p2wpkh_signature_hash(
+                    index,
+                    &txout.script_pubkey,
+                    txout.value,
+                    signature.sighash_type,
+                )
+                .unwrap()
+                .to_byte_array()
+        };
+        secp.verify_ecdsa(
+            &Message::from_digest(digest),
+            &signature.signature,
+            &public_key.inner,
+        )
+        .unwrap();
+    }
+
+    fn verify_taproot(psbt: &PSBT, index: usize, secret_key: &SecretKey) {
+        let secp = Secp256k1::new();
+        let signature = psbt.inputs[index].tap_key_sig.unwrap();
+        let txouts = prevouts(psbt);
+        let anyone_can_pay = matches!(
+            signature.sighash_type,
+            TapSighashType::AllPlusAnyoneCanPay
+                | TapSighashType::NonePlusAnyoneCanPay
+                | TapSighashType::SinglePlusAnyoneCanPay
+        );
+        let prevouts = if anyone_can_pay {
+            Prevouts::One(index, txouts[index].clone())
+        } else {
+            Prevouts::All(&txouts)
+        };
+        let sighash = SighashCache::new(&psbt.unsigned_tx)
+            .taproot_key_spend_signature_hash(index, &prevouts, signature.sighash_type)
+            .unwrap();
+        let output_key = XOnlyPublicKey::from_slice(&txouts[index].script_pubkey.as_bytes()[2..])
+            .map(TweakedPublicKey::dangerous_assume_tweaked)
+            .unwrap();
+        assert_eq!(
+            output_key,
+            secret_key
+                .x_only_public_key(&secp)
+                .0
+                .tap_tweak(&secp, None)
+                .0
+        );
+        secp.verify_schnorr(
+            &signature.signature,
+            &Message::from_digest(sighash.to_byte_array()),
+            &output_key.to_x_only_public_key(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_signs_every_sighash_type() {
+        let secp = Secp256k1::new();
+        let signer = key(1);
+        let scripts = [p2pkh(&signer), p2wpkh(&signer), p2tr(&signer)];
+
+        for sighash_type in SighashType::ALL_TYPES {
+            let mut psbt = psbt(&scripts, 3);
+            assert_eq!(
+                sign_psbt(&secp, &mut psbt, &signer, sighash_type).unwrap(),
+                3
+            );
+
+            verify_ecdsa(&psbt, 0, &signer, true);
+            verify_ecdsa(&psbt, 1, &signer, false);
+            verify_taproot(&psbt, 2, &signer);
+
+            let public_key = PublicKey::new(signer.public_key(&secp));
+            let ecdsa = psbt.inputs[1].partial_sigs[&public_key];
+            assert_eq!(ecdsa.sighash_type, sighash_type.to_ecdsa());
+            let taproot = psbt.inputs[2].tap_key_sig.unwrap();
+            assert_eq!(taproot.sighash_type, sighash_type.to_taproot());
+        }
+    }
+
+    #[test]
+    fn test_anyone_can_pay_survives_added_inputs() {
+        let secp = Secp256k1::new();
+        let signer = key(1);
+        let mut psbt = psbt(&[p2wpkh(&signer), p2tr(&signer)], 2);
+        sign_psbt(&secp, &mut psbt, &signer, SighashType::AllPlusAnyoneCanPay).unwrap();
+
+        // Another party adds an input; our signatures must stay valid
+        psbt.unsigned_tx.input.push(TxIn {
+            previous_output: OutPoint::new(Txid::all_zeros(), 9),
+            ..TxIn::default()
+        });
+        psbt.inputs.push(bitcoin::psbt::Input {
+            witness_utxo: Some(TxOut {
+                value: Amount::from_sat(1_000),
+                script_pubkey: p2wpkh(&key(2)),
+            }),
+            ..Default::default()
+        });
+
+        verify_ecdsa(&psbt, 0, &signer, false);
+        verify_taproot(&psbt, 1, &signer);
+    }
+
+    #[test]
+    fn test_single_requires_matching_output() {
+        let secp = Secp256k1::new();
+        let signer = key(1);
+        let scripts = [p2wpkh(&signer), p2tr(&signer)];
+
+        for sighash_type in [SighashType::Single, SighashType::SinglePlusAnyoneCanPay] {
+            let mut psbt = psbt(&scripts, 1);
+            let err = sign_psbt(&secp, &mut psbt, &signer, sighash_type).unwrap_err();
+            assert!(matches!(err, WalletError::SigningError(ref msg) if msg.contains("input 1")));
+            // Nothing is written when any input is rejected
+            assert!(psbt.inputs[0].partial_sigs.is_empty());
+            assert!(psbt.inputs[1].tap_key_sig.is_none());
+        }
+
+        let mut psbt = psbt(&scripts, 1);
+        assert_eq!(
+            sign_psbt(&secp, &mut psbt, &signer, SighashType::All).unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_skips_foreign_inputs_and_checks_requested_sighash() {
+        let secp = Secp256k1::new();
+        let signer = key(1);
+        let mut psbt = psbt(&[p2wpkh(&key(2)), p2wpkh(&signer)], 2);
+        assert_eq!(
+            sign_psbt(&secp, &mut psbt, &signer, SighashType::None).unwrap(),
+            1
+        );
+        assert!(psbt.inputs[0].partial_sigs.is_empty());
+
+        let mut psbt = psbt_with_request(&signer, EcdsaSighashType::Single);
+        assert!(sign_psbt(&secp, &mut psbt, &signer, SighashType::All).is_err());
+        assert_eq!(
+            sign_psbt(&secp, &mut psbt, &signer, SighashType::Single).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_finalizes_signed_inputs() {
+        let secp = Secp256k1::new();
+        let signer = key(1);
+        let mut psbt = psbt(&[p2pkh(&signer), p2wpkh(&signer), p2tr(&signer), p2wpkh(&key(2))], 1);
+        sign_psbt(&secp, &mut psbt, &signer, SighashType::All).unwrap();
+        let ecdsa = psbt.inputs[1].partial_sigs[&PublicKey::new(signer.public_key(&secp))];
+        let schnorr = psbt.inputs[2].tap_key_sig.unwrap();
+
+        // The input signed by another key stays open
+        assert_eq!(finalize_psbt(&mut psbt), 3);
+        assert_eq!(finalize_psbt(&mut psbt), 0);
+        assert!(psbt.inputs[3].final_script_witness.is_none());
+
+        let public_key = signer.public_key(&secp);
+        let script_sig = psbt.inputs[0].final_script_sig.as_ref().unwrap();
+        assert!(script_sig.is_push_only());
+        assert_eq!(
+            psbt.inputs[1].final_script_witness,
+            Some(Witness::p2wpkh(&ecdsa, &public_key))
+        );
+        assert_eq!(psbt.inputs[2].final_script_witness.as_ref().unwrap().len(), 1);
+        assert_eq!(
+            psbt.inputs[2].final_script_witness.as_ref().unwrap().nth(0),
+            Some(schnorr.to_vec().as_slice())
+        );
+        assert!(psbt.inputs[..3]
+            .iter()
+            .all(|input| input.partial_sigs.is_empty() && input.tap_key_sig.is_none()));
+    }
+
+    fn psbt_with_request(signer: &SecretKey, requested: EcdsaSighashType) -> PSBT {
+        let mut psbt = psbt(&[p2wpkh(signer)], 1);
+        psbt.inputs[0].sighash_type = Some(requested.into());
+        psbt
+    }
+}
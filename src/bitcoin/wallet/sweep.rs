@@ -0,0 +1,281 @@
+// Sweep all wallet funds to one address
+//
+// Spends every spendable UTXO to a single output with no change, e.g. when
+// moving to a new wallet. The fee is computed from the vsize of the signed
+// transaction, found by filling each input with a worst-case satisfaction for
+// its script type: a 72-byte ECDSA signature and a compressed key for P2PKH,
+// P2WPKH and P2SH-P2WPKH inputs, a 64-byte Schnorr signature for taproot
+// key-path inputs. Other script types are rejected since their satisfaction
+// size is not known.
+
+use super::transactions::TransactionAnalyzer;
+use super::{BitcoinWallet, Utxo, WalletError};
+use crate::bitcoin::weight::{COMPRESSED_KEY_SIZE, ECDSA_SIGNATURE_SIZE, SCHNORR_SIGNATURE_SIZE};
+use bitcoin::absolute::LockTime;
+use bitcoin::psbt::Psbt as PSBT;
+use bitcoin::script::PushBytes;
+use bitcoin::transaction::Version;
+use bitcoin::{Address, Amount, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+
+impl BitcoinWallet {
+    /// Unsigned PSBT sending every spendable UTXO to `destination`
+    ///
+    /// `fee_rate` is in sat/vB. Fails if the swept funds do not cover the
+    /// fee plus a non-dust output.
+    pub fn sweep_all(&self, destination: &Address, fee_rate: f64) -> Result<PSBT, WalletError> {
+        let utxos = {
+            let storage = self.storage.lock().unwrap_or_else(|e| e.into_inner());
+            storage
+                .utxos
+                .values()
+                .filter(|utxo| utxo.spendable)
+                .cloned()
+                .collect()
+        };
+        sweep_utxos(utxos, destination, fee_rate)
+    }
+}
+
+/// Unsigned PSBT spending all of `utxos` to `destination` at `fee_rate` sat/vB
+pub fn sweep_utxos(
+    mut utxos: Vec<Utxo>,
+    destination: &Address,
+    fee_rate: f64,
+) -> Result<PSBT, WalletError> {
+    if !fee_rate.is_finite() || fee_rate < 0.0 {
+        return Err(WalletError::InvalidParameters(format!(
+            "invalid fee rate {fee_rate} sat/vB"
+        )));
+    }
+    if utxos.is_empty() {
+        return Err(WalletError::InsufficientFunds(
+            "no spendable UTXOs to sweep".to_string(),
+        ));
+    }
+    utxos.sort_by_key(|utxo| utxo.outpoint);
+
+    let script_pubkey = destination.script_pubkey();
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: utxos
+            .iter()
+            .map(|utxo| TxIn {
+                previous_output: utxo.outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            })
+            .collect(),
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: script_pubkey.clone(),
+        }],
+    };
+
+    let total: u64 = utxos.iter().map(|utxo| utxo.txout.value.to_sat()).sum();
+    let fee = TransactionAnalyzer::calculate_fee(signed_vsize(&tx, &utxos)?, fee_rate);
+    let value = total.saturating_sub(fee);
+    let dust = script_pubkey.minimal_non_dust().to_sat();
+    if value < dust {
+        return Err(WalletError::InsufficientFunds(format!(
+            "sweeping {total} sats leaves {value} sats after a {fee} sat fee, below the {dust} sat dust limit"
+        )));
+    }
+    tx.output[0].value = Amount::from_sat(value);
+
+    let mut psbt = PSBT::from_unsigned_tx(tx).map_err(|e| WalletError::PsbtError(e.to_string()))?;
+    for (input, utxo) in psbt.inputs.iter_mut().zip(&utxos) {
+        input.witness_utxo = Some(utxo.txout.clone());
+        input.redeem_script = utxo.redeem_script.clone();
+        input.witness_script = utxo.witness_script.clone();
+    }
+    Ok(psbt)
+}
+
+/// Vsize of `tx` once every input spending `utxos` is signed
+fn signed_vsize(tx: &Transaction, utxos: &[Utxo]) -> Result<usize, WalletError> {
+    let ecdsa = [
+        [0u8; ECDSA_SIGNATURE_SIZE].as_slice(),
+        &[0u8; COMPRESSED_KEY_SIZE],
+    ];
+    let mut signed = tx.clone();
+    for (input, utxo) in signed.input.iter_mut().zip(utxos) {
+        let script = &utxo.txout.script_pubkey;
+        let nested = utxo
+            .redeem_script
+            .as_ref()
+            .filter(|redeem| redeem.is_p2wpkh());
+        if script.is_p2tr() {
+            input.witness = Witness::from_slice(&[[0u8; SCHNORR_SIGNATURE_SIZE]]);
+        } else if script.is_p2wpkh() {
+            input.witness = Witness::from_slice(&ecdsa);
+        } else if script.is_p2pkh() {
+            input.script_sig = ScriptBuf::builder()
+                .push_slice([0u8; ECDSA_SIGNATURE_SIZE])
+                .push_slice([0u8; COMPRESSED_KEY_SIZE])
+                .into_script();
+        } else if let (true, Some(redeem)) = (script.is_p2sh(), nested) {
+            let redeem = <&PushBytes>::try_from(redeem.as_bytes())
+                .map_err(|e| WalletError::TransactionError(e.to_string()))?;
+            input.script_sig = ScriptBuf::builder().push_slice(redeem).into_script();
+            input.witness = Witness::from_slice(&ecdsa);
+        } else {
+            return Err(WalletError::UtxoError(format!(
+                "cannot estimate the size of spending {}",
+                utxo.outpoint
+            )));
+        }
+    }
+    Ok(signed.vsize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        CoinSelectionStrategy, FeeStrategy, WalletConfig, WalletIndexes, WalletMetadata,
+        WalletStorage, WalletType,
+    };
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+    use bitcoin::{CompressedPublicKey, Network, OutPoint, PublicKey, Txid};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    fn key(seed: u8) -> CompressedPublicKey {
+        let secret_key = SecretKey::from_slice(&[seed; 32]).unwrap();
+        CompressedPublicKey(secret_key.public_key(&Secp256k1::new()))
+    }
+
+    fn utxo(vout: u32, sats: u64, script_pubkey: ScriptBuf) -> Utxo {
+        Utxo {
+            outpoint: OutPoint::new(Txid::all_zeros(), vout),
+            txout: TxOut {
+                value: Amount::from_sat(sats),
+                script_pubkey,
+            },
+            redeem_script: None,
+            witness_script: None,
+            confirmations: 6,
+            spendable: true,
+            from_wallet: true,
+        }
+    }
+
+    fn wallet(utxos: Vec<Utxo>) -> BitcoinWallet {
+        BitcoinWallet {
+            config: WalletConfig {
+                wallet_type: WalletType::Standard,
+                network: Network::Regtest,
+                name: "sweep".to_string(),
+                seed_phrase: None,
+                password: None,
+                receive_descriptor: String::new(),
+                change_descriptor: String::new(),
+                xpub: None,
+                data_dir: PathBuf::new(),
+                use_rpc: false,
+                coin_selection: CoinSelectionStrategy::LargestFirst,
+                gap_limit: 20,
+                min_confirmations: 1,
+                fee_strategy: FeeStrategy::Medium,
+            },
+            storage: Arc::new(Mutex::new(WalletStorage {
+                metadata: WalletMetadata {
+                    created_at: 0,
+                    updated_at: 0,
+                    version: "1".to_string(),
+                    network: Network::Regtest,
+                    master_fingerprint: None,
+                    labels: HashMap::new(),
+                },
+                utxos: utxos
+                    .into_iter()
+                    .map(|utxo| (utxo.outpoint, utxo))
+                    .collect(),
+                transactions: HashMap::new(),
+                addresses: HashMap::new(),
+                indexes: WalletIndexes {
+                    receive_index: 0,
+                    change_index: 0,
+                    last_block: None,
+                    last_sync: None,
+                },
+                labels: Default::default(),
+            })),
+            secp: Secp256k1::new(),
+            seed: None,
+        }
+    }
+
+    fn destination() -> Address {
+        Address::p2wpkh(&key(9), Network::Regtest)
+    }
+
+    #[test]
+    fn test_sweep_mixed_input_types() {
+        let secp = Secp256k1::new();
+        let mut nested = utxo(
+            2,
+            30_000,
+            ScriptBuf::new_p2sh(&ScriptBuf::new_p2wpkh(&key(3).wpubkey_hash()).script_hash()),
+        );
+        nested.redeem_script = Some(ScriptBuf::new_p2wpkh(&key(3).wpubkey_hash()));
+        let mut locked = utxo(4, 1_000_000, ScriptBuf::new_p2wpkh(&key(5).wpubkey_hash()));
+        locked.spendable = false;
+        let wallet = wallet(vec![
+            utxo(3, 40_000, ScriptBuf::new_p2tr(&secp, key(4).0.into(), None)),
+            utxo(
+                0,
+                10_000,
+                ScriptBuf::new_p2pkh(&PublicKey::from(key(1)).pubkey_hash()),
+            ),
+            utxo(1, 20_000, ScriptBuf::new_p2wpkh(&key(2).wpubkey_hash())),
+            nested,
+            locked,
+        ]);
+
+        let psbt = wallet.sweep_all(&destination(), 2.5).unwrap();
+        let tx = &psbt.unsigned_tx;
+        let vouts: Vec<u32> = tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output.vout)
+            .collect();
+        assert_eq!(vouts, vec![0, 1, 2, 3]);
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(tx.output[0].script_pubkey, destination().script_pubkey());
+
+        // 335 non-witness bytes and 285 witness bytes: 1625 WU, 407 vB
+        let fee = (407.0f64 * 2.5).ceil() as u64;
+        assert_eq!(tx.output[0].value.to_sat(), 100_000 - fee);
+        assert!(psbt.inputs.iter().all(|input| input.witness_utxo.is_some()));
+        assert!(psbt.inputs[2].redeem_script.is_some());
+    }
+
+    #[test]
+    fn test_sweep_rejects_uneconomic_and_unknown_inputs() {
+        let p2wpkh = ScriptBuf::new_p2wpkh(&key(1).wpubkey_hash());
+        assert!(matches!(
+            wallet(vec![utxo(0, 1_000, p2wpkh.clone())]).sweep_all(&destination(), 10.0),
+            Err(WalletError::InsufficientFunds(_))
+        ));
+        assert!(matches!(
+            wallet(Vec::new()).sweep_all(&destination(), 1.0),
+            Err(WalletError::InsufficientFunds(_))
+        ));
+        assert!(matches!(
+            wallet(vec![utxo(0, 10_000, p2wpkh.clone())]).sweep_all(&destination(), f64::NAN),
+            Err(WalletError::InvalidParameters(_))
+        ));
+
+        let p2wsh = ScriptBuf::new_p2wsh(&p2wpkh.wscript_hash());
+        assert!(matches!(
+            wallet(vec![utxo(0, 10_000, p2wpkh), utxo(1, 10_000, p2wsh)])
+                .sweep_all(&destination(), 1.0),
+            Err(WalletError::UtxoError(_))
+        ));
+    }
+}
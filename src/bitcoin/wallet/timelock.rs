@@ -0,0 +1,493 @@
+// Timelocked scripts (BIP-65 / BIP-112)
+//
+// `build_cltv_script` and `build_csv_script` lock coins to a key until an
+// absolute height or time, or until the output has aged by a number of blocks
+// or 512-second intervals. The scripts are paid to as P2WSH. Spending one
+// needs the transaction's `lock_time` (CLTV) or the input's `sequence` (CSV)
+// set to at least the script's value, which `build_timelock_spend` does once
+// the timelock has matured at the current chain tip. Spends that are not yet
+// final are refused with `WalletError::TimelockError` rather than built and
+// rejected by the network.
+
+use super::{FeeRate, WalletError};
+use crate::bitcoin::mempool::policy::dust_threshold;
+use bitcoin::absolute::{self, LockTime};
+use bitcoin::hashes::Hash;
+use bitcoin::opcodes::all::{OP_CHECKSIG, OP_CLTV, OP_CSV, OP_DROP};
+use bitcoin::psbt::Psbt as PSBT;
+use bitcoin::script::Instruction;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey, Signing};
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    relative, Amount, OutPoint, PublicKey, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut,
+    Witness,
+};
+
+/// Length of a DER-encoded ECDSA signature with its sighash byte, at most
+const MAX_SIGNATURE_LEN: u64 = 73;
+
+/// Condition a timelocked script enforces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timelock {
+    /// OP_CHECKLOCKTIMEVERIFY: spendable from a block height or time
+    Absolute(absolute::LockTime),
+    /// OP_CHECKSEQUENCEVERIFY: spendable once the output has aged
+    Relative(relative::LockTime),
+}
+
+/// Chain position a spend is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainTip {
+    pub height: u32,
+    /// Median time past of the block at `height` (BIP-113)
+    pub median_time_past: u32,
+}
+
+/// `<locktime> OP_CHECKLOCKTIMEVERIFY OP_DROP <pubkey> OP_CHECKSIG`
+pub fn build_cltv_script(pubkey: &PublicKey, locktime: absolute::LockTime) -> ScriptBuf {
+    ScriptBuf::builder()
+        .push_lock_time(locktime)
+        .push_opcode(OP_CLTV)
+        .push_opcode(OP_DROP)
+        .push_key(pubkey)
+        .push_opcode(OP_CHECKSIG)
+        .into_script()
+}
+
+/// `<blocks or time> OP_CHECKSEQUENCEVERIFY OP_DROP <pubkey> OP_CHECKSIG`
+pub fn build_csv_script(pubkey: &PublicKey, blocks_or_time: relative::LockTime) -> ScriptBuf {
+    ScriptBuf::builder()
+        .push_sequence(blocks_or_time.to_sequence())
+        .push_opcode(OP_CSV)
+        .push_opcode(OP_DROP)
+        .push_key(pubkey)
+        .push_opcode(OP_CHECKSIG)
+        .into_script()
+}
+
+/// Timelock and key of a script built by [`build_cltv_script`] or [`build_csv_script`]
+pub fn parse_timelock_script(script: &Script) -> Option<(Timelock, PublicKey)> {
+    let instructions = script
+        .instructions_minimal()
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    let [value, check, drop, key, checksig] = instructions.as_slice() else {
+        return None;
+    };
+    if *drop != Instruction::Op(OP_DROP) || *checksig != Instruction::Op(OP_CHECKSIG) {
+        return None;
+    }
+    let value = u32::try_from(value.script_num()?).ok()?;
+    let timelock = match check.opcode()? {
+        OP_CLTV => Timelock::Absolute(LockTime::from_consensus(value)),
+        OP_CSV => Timelock::Relative(Sequence(value).to_relative_lock_time()?),
+        _ => return None,
+    };
+    let key = PublicKey::from_slice(key.push_bytes()?.as_bytes()).ok()?;
+    Some((timelock, key))
+}
+
+/// P2WSH output script paying to `witness_script`
+pub fn timelock_script_pubkey(witness_script: &Script) -> ScriptBuf {
+    ScriptBuf::new_p2wsh(&witness_script.wscript_hash())
+}
+
+/// Output locked by a timelocked witness script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelockedOutput {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    pub witness_script: ScriptBuf,
+    /// Height of the block confirming the output, with the median time past
+    /// of the block before it (BIP-68); needed for relative timelocks
+    pub confirmed: Option<ChainTip>,
+}
+
+impl TimelockedOutput {
+    /// Timelock enforced by the witness script
+    pub fn timelock(&self) -> Result<Timelock, WalletError> {
+        if self.txout.script_pubkey != timelock_script_pubkey(&self.witness_script) {
+            return Err(WalletError::InvalidParameters(format!(
+                "output {} does not pay to its witness script",
+                self.outpoint
+            )));
+        }
+        parse_timelock_script(&self.witness_script)
+            .map(|(timelock, _)| timelock)
+            .ok_or_else(|| {
+                WalletError::InvalidParameters(format!(
+                    "witness script of {} is not a CLTV or CSV script",
+                    self.outpoint
+                ))
+            })
+    }
+
+    /// Check a spend could be mined in the block after `tip`
+    pub fn check_maturity(&self, tip: &ChainTip) -> Result<(), WalletError> {
+        let next_height = tip.height + 1;
+        match self.timelock()? {
+            Timelock::Absolute(LockTime::Blocks(height)) => {
+                if height.to_consensus_u32() >= next_height {
+                    return Err(WalletError::TimelockError(format!(
+                        "CLTV height {height} not reached at tip {}, {} more blocks needed",
+                        tip.height,
+                        height.to_consensus_u32() - tip.height
+                    )));
+                }
+            }
+            Timelock::Absolute(LockTime::Seconds(time)) => {
+                if time.to_consensus_u32() >= tip.median_time_past {
+                    return Err(WalletError::TimelockError(format!(
+                        "CLTV time {time} not reached, median time past is {}",
+                        tip.median_time_past
+                    )));
+                }
+            }
+            Timelock::Relative(lock) => {
+                let confirmed = self.confirmed.ok_or_else(|| {
+                    WalletError::TimelockError(format!(
+                        "CSV lock {lock} needs {} to be confirmed",
+                        self.outpoint
+                    ))
+                })?;
+                match lock {
+                    relative::LockTime::Blocks(blocks) => {
+                        let confirmations = next_height.saturating_sub(confirmed.height);
+                        let required = u32::from(blocks.value());
+                        if confirmations < required {
+                            return Err(WalletError::TimelockError(format!(
+                                "CSV lock of {required} blocks not reached, {} more blocks needed",
+                                required - confirmations
+                            )));
+                        }
+                    }
+                    relative::LockTime::Time(interval) => {
+                        let elapsed = tip
+                            .median_time_past
+                            .saturating_sub(confirmed.median_time_past);
+                        let required = u32::from(interval.value()) * 512;
+                        if elapsed < required {
+                            return Err(WalletError::TimelockError(format!(
+                                "CSV lock of {required} seconds not reached, {} seconds remaining",
+                                required - elapsed
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build a PSBT sending a matured timelocked output to `destination`
+///
+/// The transaction's `lock_time` or the input's `sequence` is set to satisfy
+/// the script, and the fee covers a signature of maximum length. Fails with
+/// [`WalletError::TimelockError`] if the spend could not be mined in the block
+/// after `tip`.
+pub fn build_timelock_spend(
+    output: &TimelockedOutput,
+    destination: ScriptBuf,
+    fee_rate: FeeRate,
+    tip: &ChainTip,
+) -> Result<PSBT, WalletError> {
+    output.check_maturity(tip)?;
+    let (lock_time, sequence) = match output.timelock()? {
+        Timelock::Absolute(lock_time) => (lock_time, Sequence::ENABLE_RBF_NO_LOCKTIME),
+        Timelock::Relative(lock) => (LockTime::ZERO, lock.to_sequence()),
+    };
+
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time,
+        input: vec![TxIn {
+            previous_output: output.outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: destination,
+        }],
+    };
+    let witness_weight = 1 + 1 + MAX_SIGNATURE_LEN + 1 + output.witness_script.len() as u64;
+    let vsize = (tx.weight().to_wu() + 2 + witness_weight + 3) / 4;
+    let fee = vsize * fee_rate.to_sat_per_vb();
+    let value = output.txout.value.to_sat().saturating_sub(fee);
+    if value < dust_threshold(&tx.output[0].script_pubkey).to_sat() {
+        return Err(WalletError::InsufficientFunds(format!(
+            "{} cannot pay a fee of {fee} sats",
+            output.txout.value
+        )));
+    }
+    tx.output[0].value = Amount::from_sat(value);
+
+    let mut psbt = PSBT::from_unsigned_tx(tx).map_err(|e| WalletError::PsbtError(e.to_string()))?;
+    psbt.inputs[0].witness_utxo = Some(output.txout.clone());
+    psbt.inputs[0].witness_script = Some(output.witness_script.clone());
+    Ok(psbt)
+}
+
+/// Sign and finalize every timelocked input of `psbt` locked to `secret_key`
+///
+/// Inputs need a `witness_utxo` and their `witness_script`. The transaction
+/// must already satisfy each script's timelock, as [`build_timelock_spend`]
+/// ensures, since a signature over it could never be mined otherwise.
+/// Returns the number of inputs signed.
+pub fn sign_timelock_spend<C: Signing>(
+    secp: &Secp256k1<C>,
+    psbt: &mut PSBT,
+    secret_key: &SecretKey,
+) -> Result<usize, WalletError> {
+    let public_key = PublicKey::new(secret_key.public_key(secp));
+    let mut witnesses = Vec::new();
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        let (Some(txout), Some(witness_script)) = (&input.witness_utxo, &input.witness_script)
+        else {
+            continue;
+        };
+        let Some((timelock, key)) = parse_timelock_script(witness_script) else {
+            continue;
+        };
+        if key != public_key || txout.script_pubkey != timelock_script_pubkey(witness_script) {
+            continue;
+        }
+        check_satisfies(&psbt.unsigned_tx, index, timelock)?;
+
+        let sighash = cache
+            .p2wsh_signature_hash(index, witness_script, txout.value, EcdsaSighashType::All)
+            .map_err(|e| WalletError::SigningError(e.to_string()))?;
+        let signature = bitcoin::ecdsa::Signature {
+            signature: secp.sign_ecdsa(&Message::from_digest(sighash.to_byte_array()), secret_key),
+            sighash_type: EcdsaSighashType::All,
+        };
+        let mut witness = Witness::new();
+        witness.push(signature.serialize());
+        witness.push(witness_script.as_bytes());
+        witnesses.push((index, witness));
+    }
+
+    let signed = witnesses.len();
+    for (index, witness) in witnesses {
+        let input = &mut psbt.inputs[index];
+        input.final_script_witness = Some(witness);
+        input.partial_sigs.clear();
+        input.witness_script = None;
+    }
+    Ok(signed)
+}
+
+/// Check the fields CLTV and CSV compare against allow input `index` to pass
+pub(super) fn check_satisfies(
+    tx: &Transaction,
+    index: usize,
+    timelock: Timelock,
+) -> Result<(), WalletError> {
+    let sequence = tx.input[index].sequence;
+    match timelock {
+        Timelock::Absolute(required) => {
+            if sequence == Sequence::MAX {
+                return Err(WalletError::TimelockError(format!(
+                    "input {index} has a final sequence, which disables CLTV"
+                )));
+            }
+            if !required.is_implied_by(tx.lock_time) {
+                return Err(WalletError::TimelockError(format!(
+                    "transaction lock time {} does not satisfy CLTV {required}",
+                    tx.lock_time
+                )));
+            }
+        }
+        Timelock::Relative(required) => {
+            if tx.version < Version::TWO {
+                return Err(WalletError::TimelockError(
+                    "CSV needs a version 2 transaction".to_string(),
+                ));
+            }
+            let satisfied = sequence
+                .to_relative_lock_time()
+                .is_some_and(|lock| required.is_implied_by(lock));
+            if !satisfied {
+                return Err(WalletError::TimelockError(format!(
+                    "input {index} sequence {sequence} does not satisfy CSV {required}"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Txid;
+
+    fn key() -> SecretKey {
+        SecretKey::from_slice(&[7; 32]).unwrap()
+    }
+
+    fn pubkey() -> PublicKey {
+        PublicKey::new(key().public_key(&Secp256k1::new()))
+    }
+
+    fn output(witness_script: ScriptBuf, confirmed: Option<ChainTip>) -> TimelockedOutput {
+        TimelockedOutput {
+            outpoint: OutPoint::new(Txid::all_zeros(), 0),
+            txout: TxOut {
+                value: Amount::from_sat(100_000),
+                script_pubkey: timelock_script_pubkey(&witness_script),
+            },
+            witness_script,
+            confirmed,
+        }
+    }
+
+    fn tip(height: u32, median_time_past: u32) -> ChainTip {
+        ChainTip {
+            height,
+            median_time_past,
+        }
+    }
+
+    #[test]
+    fn test_scripts_round_trip() {
+        let height = LockTime::from_height(800_000).unwrap();
+        let time = LockTime::from_time(1_700_000_000).unwrap();
+        let blocks = relative::LockTime::from_height(144);
+        let interval = relative::LockTime::from_512_second_intervals(10);
+
+        for timelock in [
+            Timelock::Absolute(height),
+            Timelock::Absolute(time),
+            Timelock::Relative(blocks),
+            Timelock::Relative(interval),
+        ] {
+            let script = match timelock {
+                Timelock::Absolute(lock) => build_cltv_script(&pubkey(), lock),
+                Timelock::Relative(lock) => build_csv_script(&pubkey(), lock),
+            };
+            assert_eq!(parse_timelock_script(&script), Some((timelock, pubkey())));
+        }
+
+        let p2wpkh = ScriptBuf::new_p2wpkh(&pubkey().wpubkey_hash().unwrap());
+        assert_eq!(parse_timelock_script(&p2wpkh), None);
+    }
+
+    #[test]
+    fn test_absolute_maturity() {
+        let by_height = output(
+            build_cltv_script(&pubkey(), LockTime::from_height(150).unwrap()),
+            None,
+        );
+        let err = by_height.check_maturity(&tip(140, 0)).unwrap_err();
+        assert!(
+            matches!(err, WalletError::TimelockError(ref msg) if msg.contains("10 more blocks"))
+        );
+        // A transaction locked to 150 can be mined in block 151
+        assert!(by_height.check_maturity(&tip(149, 0)).is_err());
+        assert!(by_height.check_maturity(&tip(150, 0)).is_ok());
+
+        let by_time = output(
+            build_cltv_script(&pubkey(), LockTime::from_time(1_700_000_000).unwrap()),
+            None,
+        );
+        assert!(by_time
+            .check_maturity(&tip(900_000, 1_700_000_000))
+            .is_err());
+        assert!(by_time.check_maturity(&tip(900_000, 1_700_000_001)).is_ok());
+    }
+
+    #[test]
+    fn test_relative_maturity() {
+        let script = build_csv_script(&pubkey(), relative::LockTime::from_height(10));
+        assert!(matches!(
+            output(script.clone(), None).check_maturity(&tip(200, 0)),
+            Err(WalletError::TimelockError(_))
+        ));
+
+        // Confirmed in block 100, so the spend may be in block 110
+        let confirmed = output(script, Some(tip(100, 0)));
+        assert!(confirmed.check_maturity(&tip(108, 0)).is_err());
+        assert!(confirmed.check_maturity(&tip(109, 0)).is_ok());
+
+        let script = build_csv_script(&pubkey(), relative::LockTime::from_512_second_intervals(2));
+        let confirmed = output(script, Some(tip(100, 1_000_000)));
+        assert!(confirmed.check_maturity(&tip(105, 1_001_023)).is_err());
+        assert!(confirmed.check_maturity(&tip(105, 1_001_024)).is_ok());
+    }
+
+    #[test]
+    fn test_spends_set_lock_fields_and_sign() {
+        let secp = Secp256k1::new();
+        let destination = ScriptBuf::new_p2wpkh(&pubkey().wpubkey_hash().unwrap());
+
+        let lock = LockTime::from_height(150).unwrap();
+        let cltv = output(build_cltv_script(&pubkey(), lock), None);
+        let err = build_timelock_spend(
+            &cltv,
+            destination.clone(),
+            FeeRate::SatPerVb(2),
+            &tip(100, 0),
+        );
+        assert!(matches!(err, Err(WalletError::TimelockError(_))));
+        let mut psbt = build_timelock_spend(
+            &cltv,
+            destination.clone(),
+            FeeRate::SatPerVb(2),
+            &tip(150, 0),
+        )
+        .unwrap();
+        assert_eq!(psbt.unsigned_tx.lock_time, lock);
+        assert!(psbt.unsigned_tx.input[0]
+            .sequence
+            .enables_absolute_lock_time());
+        assert_eq!(sign_timelock_spend(&secp, &mut psbt, &key()).unwrap(), 1);
+        let tx = psbt.extract_tx().unwrap();
+        assert_eq!(tx.input[0].witness.len(), 2);
+        assert_eq!(
+            tx.input[0].witness.last(),
+            Some(cltv.witness_script.as_bytes())
+        );
+        let fee = 100_000 - tx.output[0].value.to_sat();
+        assert!(fee >= tx.vsize() as u64 * 2);
+
+        let blocks = relative::LockTime::from_height(10);
+        let csv = output(build_csv_script(&pubkey(), blocks), Some(tip(100, 0)));
+        let mut psbt =
+            build_timelock_spend(&csv, destination, FeeRate::SatPerVb(2), &tip(109, 0)).unwrap();
+        assert_eq!(psbt.unsigned_tx.input[0].sequence, blocks.to_sequence());
+
+        // A signature over a transaction that cannot satisfy CSV is refused
+        psbt.unsigned_tx.input[0].sequence = Sequence::from_height(9);
+        assert!(matches!(
+            sign_timelock_spend(&secp, &mut psbt, &key()),
+            Err(WalletError::TimelockError(_))
+        ));
+        let other = SecretKey::from_slice(&[8; 32]).unwrap();
+        assert_eq!(sign_timelock_spend(&secp, &mut psbt, &other).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_dust_depends_on_destination() {
+        let p2wpkh = ScriptBuf::new_p2wpkh(&pubkey().wpubkey_hash().unwrap());
+        let p2pkh = ScriptBuf::new_p2pkh(&pubkey().pubkey_hash());
+        let blocks = relative::LockTime::from_height(10);
+        let mut csv = output(build_csv_script(&pubkey(), blocks), Some(tip(100, 0)));
+        let psbt =
+            build_timelock_spend(&csv, p2wpkh.clone(), FeeRate::SatPerVb(2), &tip(110, 0)).unwrap();
+        let fee = 100_000 - psbt.unsigned_tx.output[0].value.to_sat();
+
+        // 400 sats are above the P2WPKH dust threshold but below the P2PKH one
+        csv.txout.value = Amount::from_sat(fee + 400);
+        build_timelock_spend(&csv, p2wpkh, FeeRate::SatPerVb(2), &tip(110, 0)).unwrap();
+        assert!(matches!(
+            build_timelock_spend(&csv, p2pkh, FeeRate::SatPerVb(2), &tip(110, 0)),
+            Err(WalletError::InsufficientFunds(_))
+        ));
+    }
+}
@@ -0,0 +1,497 @@
+// Taproot vault with a hot and a delayed recovery path
+//
+// Deposits pay to a taproot output with no usable key path and two script
+// leaves over the same N keys:
+//
+//   hot:      <k1> CHECKSIGVERIFY ... <kN-1> CHECKSIGVERIFY <kN> CHECKSIG
+//   recovery: <delay> CHECKSEQUENCEVERIFY DROP
+//             <k1> CHECKSIG <k2> CHECKSIGADD ... <kN> CHECKSIGADD <M> NUMEQUAL
+//
+// The hot path needs every key and can be spent at once; the recovery path
+// needs M of the keys and only once the deposit has aged by the CSV delay.
+// `spend_hot` and `spend_recovery` pick the leaf for every vault input of a
+// PSBT, `sign` adds script-path signatures for it and `finalize` assembles
+// the witnesses.
+
+use super::timelock::{check_satisfies, Timelock};
+use super::WalletError;
+use bitcoin::hashes::Hash;
+use bitcoin::key::Keypair;
+use bitcoin::opcodes::all::{
+    OP_CHECKSIG, OP_CHECKSIGADD, OP_CHECKSIGVERIFY, OP_CSV, OP_DROP, OP_NUMEQUAL,
+};
+use bitcoin::psbt::Psbt as PSBT;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey, Signing};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{LeafVersion, TapLeafHash, TaprootBuilder, TaprootSpendInfo};
+use bitcoin::{relative, Address, Network, ScriptBuf, Witness, XOnlyPublicKey};
+use std::collections::{BTreeMap, HashSet};
+
+/// BIP-341 NUMS point: vault outputs have no usable key path
+const NUMS_INTERNAL_KEY: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// Leaf a vault input is spent through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VaultPath {
+    Hot,
+    Recovery,
+}
+
+/// N-of-N hot path and M-of-N recovery path after a relative delay
+#[derive(Debug, Clone)]
+pub struct Vault {
+    keys: Vec<XOnlyPublicKey>,
+    threshold: usize,
+    delay: relative::LockTime,
+    network: Network,
+    hot_script: ScriptBuf,
+    recovery_script: ScriptBuf,
+    spend_info: TaprootSpendInfo,
+}
+
+impl Vault {
+    /// Vault over `keys`, recoverable by `threshold` of them after `delay`
+    pub fn new(
+        keys: Vec<XOnlyPublicKey>,
+        threshold: usize,
+        delay: relative::LockTime,
+        network: Network,
+    ) -> Result<Self, WalletError> {
+        if keys.is_empty() || threshold == 0 || threshold > keys.len() {
+            return Err(WalletError::InvalidParameters(format!(
+                "recovery threshold {threshold} is not between 1 and {} keys",
+                keys.len()
+            )));
+        }
+        if keys.iter().collect::<HashSet<_>>().len() != keys.len() {
+            return Err(WalletError::InvalidParameters(
+                "vault keys must be distinct".to_string(),
+            ));
+        }
+        let zero_delay = match delay {
+            relative::LockTime::Blocks(blocks) => blocks.value() == 0,
+            relative::LockTime::Time(interval) => interval.value() == 0,
+        };
+        if zero_delay {
+            return Err(WalletError::InvalidParameters(
+                "recovery delay must not be zero".to_string(),
+            ));
+        }
+
+        let (last, rest) = keys.split_last().expect("keys are not empty");
+        let mut hot = ScriptBuf::builder();
+        for key in rest {
+            hot = hot.push_x_only_key(key).push_opcode(OP_CHECKSIGVERIFY);
+        }
+        let hot_script = hot
+            .push_x_only_key(last)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+
+        let mut recovery = ScriptBuf::builder()
+            .push_sequence(delay.to_sequence())
+            .push_opcode(OP_CSV)
+            .push_opcode(OP_DROP)
+            .push_x_only_key(&keys[0])
+            .push_opcode(OP_CHECKSIG);
+        for key in &keys[1..] {
+            recovery = recovery.push_x_only_key(key).push_opcode(OP_CHECKSIGADD);
+        }
+        let recovery_script = recovery
+            .push_int(threshold as i64)
+            .push_opcode(OP_NUMEQUAL)
+            .into_script();
+
+        let secp = Secp256k1::verification_only();
+        let internal_key = XOnlyPublicKey::from_slice(&NUMS_INTERNAL_KEY)
+            .map_err(|e| WalletError::InvalidParameters(format!("invalid NUMS key: {e}")))?;
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, hot_script.clone())
+            .and_then(|builder| builder.add_leaf(1, recovery_script.clone()))
+            .map_err(|e| WalletError::DescriptorError(format!("failed to build vault tree: {e}")))?
+            .finalize(&secp, internal_key)
+            .map_err(|_| {
+                WalletError::DescriptorError("failed to finalize vault tree".to_string())
+            })?;
+
+        Ok(Self {
+            keys,
+            threshold,
+            delay,
+            network,
+            hot_script,
+            recovery_script,
+            spend_info,
+        })
+    }
+
+    pub fn keys(&self) -> &[XOnlyPublicKey] {
+        &self.keys
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Relative delay before the recovery path can be used
+    pub fn delay(&self) -> relative::LockTime {
+        self.delay
+    }
+
+    /// Taproot address deposits are paid to
+    pub fn deposit_address(&self) -> Address {
+        Address::p2tr_tweaked(self.spend_info.output_key(), self.network)
+    }
+
+    pub fn script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_p2tr_tweaked(self.spend_info.output_key())
+    }
+
+    /// Spend every vault input of `psbt` through the N-of-N hot path
+    pub fn spend_hot(&self, psbt: &mut PSBT) -> Result<usize, WalletError> {
+        self.prepare(psbt, VaultPath::Hot)
+    }
+
+    /// Spend every vault input of `psbt` through the delayed recovery path
+    ///
+    /// Each vault input's `sequence`, and the transaction version, must
+    /// satisfy the recovery delay, otherwise the spend could never be mined.
+    pub fn spend_recovery(&self, psbt: &mut PSBT) -> Result<usize, WalletError> {
+        for index in self.vault_inputs(psbt)? {
+            check_satisfies(&psbt.unsigned_tx, index, Timelock::Relative(self.delay))?;
+        }
+        self.prepare(psbt, VaultPath::Recovery)
+    }
+
+    /// Add script-path signatures by `secret_key` to the prepared vault inputs
+    ///
+    /// Needs the spent output of every input, vault or not. Returns the number
+    /// of inputs signed.
+    pub fn sign<C: Signing>(
+        &self,
+        secp: &Secp256k1<C>,
+        psbt: &mut PSBT,
+        secret_key: &SecretKey,
+    ) -> Result<usize, WalletError> {
+        let keypair = Keypair::from_secret_key(secp, secret_key);
+        let (key, _) = keypair.x_only_public_key();
+        if !self.keys.contains(&key) {
+            return Err(WalletError::SigningError(format!(
+                "{key} is not a vault key"
+            )));
+        }
+        let prevouts = psbt
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                input.witness_utxo.clone().ok_or_else(|| {
+                    WalletError::SigningError(format!("input {index} is missing its spent output"))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut signatures = Vec::new();
+        let mut cache = SighashCache::new(&psbt.unsigned_tx);
+        for index in self.vault_inputs(psbt)? {
+            let path = self.prepared_path(psbt, index)?;
+            let leaf_hash = TapLeafHash::from_script(self.script(path), LeafVersion::TapScript);
+            let sighash = cache
+                .taproot_script_spend_signature_hash(
+                    index,
+                    &Prevouts::All(&prevouts),
+                    leaf_hash,
+                    TapSighashType::Default,
+                )
+                .map_err(|e| WalletError::SigningError(e.to_string()))?;
+            let signature = bitcoin::taproot::Signature {
+                signature: secp
+                    .sign_schnorr(&Message::from_digest(sighash.to_byte_array()), &keypair),
+                sighash_type: TapSighashType::Default,
+            };
+            signatures.push((index, leaf_hash, signature));
+        }
+
+        let signed = signatures.len();
+        for (index, leaf_hash, signature) in signatures {
+            psbt.inputs[index]
+                .tap_script_sigs
+                .insert((key, leaf_hash), signature);
+        }
+        Ok(signed)
+    }
+
+    /// Build the witness of every prepared vault input from its signatures
+    ///
+    /// The hot path needs a signature from every key, the recovery path from
+    /// at least the threshold; surplus recovery signatures are left out.
+    /// Returns the number of inputs finalized.
+    pub fn finalize(&self, psbt: &mut PSBT) -> Result<usize, WalletError> {
+        let mut witnesses = Vec::new();
+        for index in self.vault_inputs(psbt)? {
+            let path = self.prepared_path(psbt, index)?;
+            let script = self.script(path);
+            let leaf_hash = TapLeafHash::from_script(script, LeafVersion::TapScript);
+            let signatures = &psbt.inputs[index].tap_script_sigs;
+            let required = match path {
+                VaultPath::Hot => self.keys.len(),
+                VaultPath::Recovery => self.threshold,
+            };
+
+            // Keys without a signature, or beyond the threshold, get an empty push
+            let mut stack = Vec::with_capacity(self.keys.len());
+            let mut present = 0;
+            for key in &self.keys {
+                match signatures.get(&(*key, leaf_hash)) {
+                    Some(signature) if present < required => {
+                        stack.push(signature.to_vec());
+                        present += 1;
+                    }
+                    _ => stack.push(Vec::new()),
+                }
+            }
+            if present < required {
+                return Err(WalletError::SigningError(format!(
+                    "input {index} has {present} of {required} vault signatures"
+                )));
+            }
+
+            // The first key's signature is consumed first, so it goes on top
+            let mut witness = Witness::new();
+            for item in stack.iter().rev() {
+                witness.push(item);
+            }
+            witness.push(script.as_bytes());
+            witness.push(self.control_block(path)?.serialize());
+            witnesses.push((index, witness));
+        }
+
+        let finalized = witnesses.len();
+        for (index, witness) in witnesses {
+            let input = &mut psbt.inputs[index];
+            input.final_script_witness = Some(witness);
+            input.tap_script_sigs.clear();
+            input.tap_scripts.clear();
+            input.tap_internal_key = None;
+            input.tap_merkle_root = None;
+        }
+        Ok(finalized)
+    }
+
+    /// Set the leaf, control block and internal key of every vault input
+    fn prepare(&self, psbt: &mut PSBT, path: VaultPath) -> Result<usize, WalletError> {
+        let control_block = self.control_block(path)?;
+        let inputs = self.vault_inputs(psbt)?;
+        for &index in &inputs {
+            let input = &mut psbt.inputs[index];
+            input.tap_scripts = BTreeMap::from([(
+                control_block.clone(),
+                (self.script(path).clone(), LeafVersion::TapScript),
+            )]);
+            input.tap_internal_key = Some(self.spend_info.internal_key());
+            input.tap_merkle_root = self.spend_info.merkle_root();
+            input.tap_script_sigs.clear();
+        }
+        Ok(inputs.len())
+    }
+
+    /// Indices of the inputs spending a vault output, at least one
+    fn vault_inputs(&self, psbt: &PSBT) -> Result<Vec<usize>, WalletError> {
+        let script_pubkey = self.script_pubkey();
+        let inputs: Vec<usize> = psbt
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, input)| {
+                input
+                    .witness_utxo
+                    .as_ref()
+                    .is_some_and(|txout| txout.script_pubkey == script_pubkey)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if inputs.is_empty() {
+            return Err(WalletError::PsbtError(
+                "no input spends a vault output with its witness_utxo set".to_string(),
+            ));
+        }
+        Ok(inputs)
+    }
+
+    /// Leaf chosen for input `index` by `spend_hot` or `spend_recovery`
+    fn prepared_path(&self, psbt: &PSBT, index: usize) -> Result<VaultPath, WalletError> {
+        let mut leaves = psbt.inputs[index].tap_scripts.values();
+        match (leaves.next(), leaves.next()) {
+            (Some((script, _)), None) if *script == self.hot_script => Ok(VaultPath::Hot),
+            (Some((script, _)), None) if *script == self.recovery_script => Ok(VaultPath::Recovery),
+            _ => Err(WalletError::PsbtError(format!(
+                "vault input {index} has no spending path, call spend_hot or spend_recovery first"
+            ))),
+        }
+    }
+
+    fn script(&self, path: VaultPath) -> &ScriptBuf {
+        match path {
+            VaultPath::Hot => &self.hot_script,
+            VaultPath::Recovery => &self.recovery_script,
+        }
+    }
+
+    fn control_block(
+        &self,
+        path: VaultPath,
+    ) -> Result<bitcoin::taproot::ControlBlock, WalletError> {
+        self.spend_info
+            .control_block(&(self.script(path).clone(), LeafVersion::TapScript))
+            .ok_or_else(|| WalletError::DescriptorError(format!("missing {path:?} control block")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, OutPoint, Sequence, Transaction, TxIn, TxOut, Txid};
+
+    fn key(seed: u8) -> SecretKey {
+        SecretKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn vault() -> Vault {
+        let secp = Secp256k1::new();
+        let keys = (1..=3)
+            .map(|seed| key(seed).x_only_public_key(&secp).0)
+            .collect();
+        Vault::new(
+            keys,
+            2,
+            relative::LockTime::from_height(144),
+            Network::Regtest,
+        )
+        .unwrap()
+    }
+
+    fn psbt(vault: &Vault, sequence: Sequence) -> PSBT {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), 0),
+                script_sig: ScriptBuf::new(),
+                sequence,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(99_000),
+                script_pubkey: ScriptBuf::new_op_return([]),
+            }],
+        };
+        let mut psbt = PSBT::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: Amount::from_sat(100_000),
+            script_pubkey: vault.script_pubkey(),
+        });
+        psbt
+    }
+
+    /// Check the witness against the output key and the signatures against the leaf
+    fn verify(vault: &Vault, psbt: &PSBT, path: VaultPath, signers: &[u8]) {
+        let secp = Secp256k1::new();
+        let witness = psbt.inputs[0].final_script_witness.as_ref().unwrap();
+        let items: Vec<&[u8]> = witness.iter().collect();
+        let script = ScriptBuf::from_bytes(items[3].to_vec());
+        assert_eq!(&script, vault.script(path));
+        let control_block = bitcoin::taproot::ControlBlock::decode(items[4]).unwrap();
+        assert!(control_block.verify_taproot_commitment(
+            &secp,
+            vault.spend_info.output_key().to_x_only_public_key(),
+            &script
+        ));
+
+        let prevout = psbt.inputs[0].witness_utxo.clone().unwrap();
+        let leaf_hash = TapLeafHash::from_script(&script, LeafVersion::TapScript);
+        let sighash = SighashCache::new(&psbt.unsigned_tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&[prevout]),
+                leaf_hash,
+                TapSighashType::Default,
+            )
+            .unwrap();
+        let message = Message::from_digest(sighash.to_byte_array());
+        // Signatures are listed for the last key first
+        for (item, seed) in items[..3].iter().rev().zip(1..=3u8) {
+            if signers.contains(&seed) {
+                let signature = bitcoin::secp256k1::schnorr::Signature::from_slice(item).unwrap();
+                let pubkey = key(seed).x_only_public_key(&secp).0;
+                secp.verify_schnorr(&signature, &message, &pubkey).unwrap();
+            } else {
+                assert!(item.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_hot_path_needs_every_key() {
+        let secp = Secp256k1::new();
+        let vault = vault();
+        assert_eq!(
+            vault.deposit_address().script_pubkey(),
+            vault.script_pubkey()
+        );
+        let mut psbt = psbt(&vault, Sequence::ENABLE_RBF_NO_LOCKTIME);
+        assert!(vault.sign(&secp, &mut psbt, &key(1)).is_err());
+
+        assert_eq!(vault.spend_hot(&mut psbt).unwrap(), 1);
+        for seed in 1..=2 {
+            assert_eq!(vault.sign(&secp, &mut psbt, &key(seed)).unwrap(), 1);
+        }
+        assert!(matches!(
+            vault.finalize(&mut psbt),
+            Err(WalletError::SigningError(_))
+        ));
+        vault.sign(&secp, &mut psbt, &key(3)).unwrap();
+        assert!(vault.sign(&secp, &mut psbt, &key(4)).is_err());
+        assert_eq!(vault.finalize(&mut psbt).unwrap(), 1);
+        verify(&vault, &psbt, VaultPath::Hot, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_recovery_path_needs_threshold_and_delay() {
+        let secp = Secp256k1::new();
+        let vault = vault();
+
+        let mut early = psbt(&vault, Sequence::from_height(143));
+        assert!(matches!(
+            vault.spend_recovery(&mut early),
+            Err(WalletError::TimelockError(_))
+        ));
+        let mut no_csv = psbt(&vault, Sequence::ENABLE_RBF_NO_LOCKTIME);
+        assert!(vault.spend_recovery(&mut no_csv).is_err());
+
+        let mut psbt = psbt(&vault, Sequence::from_height(144));
+        vault.spend_recovery(&mut psbt).unwrap();
+        vault.sign(&secp, &mut psbt, &key(3)).unwrap();
+        assert!(vault.finalize(&mut psbt).is_err());
+        vault.sign(&secp, &mut psbt, &key(1)).unwrap();
+        assert_eq!(vault.finalize(&mut psbt).unwrap(), 1);
+        verify(&vault, &psbt, VaultPath::Recovery, &[1, 3]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_vaults() {
+        let secp = Secp256k1::new();
+        let keys: Vec<_> = (1..=3)
+            .map(|seed| key(seed).x_only_public_key(&secp).0)
+            .collect();
+        let delay = relative::LockTime::from_height(10);
+        assert!(Vault::new(keys.clone(), 0, delay, Network::Regtest).is_err());
+        assert!(Vault::new(keys.clone(), 4, delay, Network::Regtest).is_err());
+        assert!(Vault::new(vec![keys[0], keys[0]], 1, delay, Network::Regtest).is_err());
+        assert!(Vault::new(keys, 2, relative::LockTime::ZERO, Network::Regtest).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+// Witness element sizes used to estimate the weight of transactions
+// before they are signed, and the fee such a weight pays
+
+use bitcoin::{Amount, FeeRate, Weight};
+
+/// Size of a DER-encoded ECDSA signature with its sighash byte, at most
+pub const ECDSA_SIGNATURE_SIZE: usize = 72;
+
+/// Size of a Schnorr signature with the default sighash type
+pub const SCHNORR_SIGNATURE_SIZE: usize = 64;
+
+/// Size of a compressed public key
+pub const COMPRESSED_KEY_SIZE: usize = 33;
+
+/// Fee for `weight` at `fee_rate`, rounded up
+pub fn fee_for(fee_rate: FeeRate, weight: Weight) -> Amount {
+    Amount::from_sat((fee_rate.to_sat_per_kwu() * weight.to_wu() + 999) / 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_rounds_up() {
+        let rate = FeeRate::from_sat_per_kwu(250);
+        assert_eq!(fee_for(rate, Weight::from_wu(400)), Amount::from_sat(100));
+        assert_eq!(fee_for(rate, Weight::from_wu(401)), Amount::from_sat(101));
+        assert_eq!(fee_for(FeeRate::ZERO, Weight::from_wu(401)), Amount::ZERO);
+    }
+}
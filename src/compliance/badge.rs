@@ -0,0 +1,362 @@
+//! Signed compliance badges
+//!
+//! A badge is an SVG image that embeds the hash of the compliance report it
+//! was generated from, signed with a BIP-340 Schnorr key. The signature is
+//! either carried in the SVG metadata or written to a detached `.sig` file
+//! next to the badge. Verification checks the signature against an expected
+//! public key and that the embedded report hash matches the current report.
+
+use super::{generate_report, BuildComplianceReport};
+use bitcoin::hashes::{sha256, Hash};
+use secp256k1::{schnorr, Keypair, Message, Secp256k1, SecretKey, XOnlyPublicKey};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the hex-encoded badge signing key
+pub const BADGE_SIGNING_KEY_ENV: &str = "ANYA_BADGE_SIGNING_KEY";
+
+/// Environment variable holding the hex-encoded x-only badge public key
+pub const BADGE_PUBLIC_KEY_ENV: &str = "ANYA_BADGE_PUBLIC_KEY";
+
+/// Directory badges are written to by [`generate_badge`]
+const BADGE_DIR: &str = "reports";
+
+/// Placeholder the embedded signature is hashed with
+const EMPTY_SIGNATURE: &str = "signature=\"\"";
+
+/// Key used to sign badges
+///
+/// Implemented by [`LocalBadgeSigner`]; key stores such as an HSM provider
+/// can implement it to keep the badge key off the host.
+pub trait BadgeSigner {
+    /// Public key badges are verified against
+    fn public_key(&self) -> XOnlyPublicKey;
+
+    /// BIP-340 signature over a 32-byte digest
+    fn sign_digest(&self, digest: [u8; 32]) -> Result<schnorr::Signature, Box<dyn Error>>;
+}
+
+/// Badge signer backed by an in-memory secret key
+pub struct LocalBadgeSigner {
+    secp: Secp256k1<secp256k1::All>,
+    keypair: Keypair,
+}
+
+impl LocalBadgeSigner {
+    /// Create a signer from a secret key
+    pub fn new(secret_key: &SecretKey) -> Self {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, secret_key);
+        Self { secp, keypair }
+    }
+
+    /// Create a signer from the hex key in `ANYA_BADGE_SIGNING_KEY`
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let hex_key = std::env::var(BADGE_SIGNING_KEY_ENV)
+            .map_err(|_| format!("{BADGE_SIGNING_KEY_ENV} is not set"))?;
+        let secret_key = SecretKey::from_slice(&hex::decode(hex_key.trim())?)?;
+        Ok(Self::new(&secret_key))
+    }
+}
+
+impl BadgeSigner for LocalBadgeSigner {
+    fn public_key(&self) -> XOnlyPublicKey {
+        self.keypair.x_only_public_key().0
+    }
+
+    fn sign_digest(&self, digest: [u8; 32]) -> Result<schnorr::Signature, Box<dyn Error>> {
+        Ok(self
+            .secp
+            .sign_schnorr_no_aux_rand(&Message::from_digest(digest), &self.keypair))
+    }
+}
+
+/// Where the badge signature is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureLocation {
+    /// In the SVG metadata
+    Embedded,
+    /// In a `.sig` file next to the badge
+    Detached,
+}
+
+impl std::str::FromStr for SignatureLocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(SignatureLocation::Embedded),
+            "svg-detached" => Ok(SignatureLocation::Detached),
+            other => Err(format!(
+                "Unsupported badge format '{other}', expected 'svg' or 'svg-detached'"
+            )),
+        }
+    }
+}
+
+/// Generated badge
+#[derive(Debug, Clone)]
+pub struct SignedBadge {
+    /// SVG document
+    pub svg: String,
+    /// Hex signature for a detached `.sig` file, if requested
+    pub detached_signature: Option<String>,
+}
+
+/// Hash committing to the checked items and score of a report
+///
+/// The timestamp is excluded so that an unchanged build yields the same hash.
+pub fn report_hash(report: &BuildComplianceReport) -> [u8; 32] {
+    let mut data = Vec::new();
+    for item in &report.items {
+        data.extend_from_slice(item.name.as_bytes());
+        data.push(0);
+        data.extend_from_slice(&item.weight.to_be_bytes());
+        data.push(u8::from(item.passed));
+    }
+    data.extend_from_slice(&report.overall_score.to_be_bytes());
+    sha256::Hash::hash(&data).to_byte_array()
+}
+
+/// Render and sign a badge for a report
+pub fn create_badge(
+    report: &BuildComplianceReport,
+    signer: &dyn BadgeSigner,
+    location: SignatureLocation,
+) -> Result<SignedBadge, Box<dyn Error>> {
+    let unsigned = render_svg(report, &signer.public_key());
+    let signature = hex::encode(signer.sign_digest(digest(&unsigned))?.serialize());
+
+    Ok(match location {
+        SignatureLocation::Embedded => SignedBadge {
+            svg: unsigned.replacen(EMPTY_SIGNATURE, &format!("signature=\"{signature}\""), 1),
+            detached_signature: None,
+        },
+        SignatureLocation::Detached => SignedBadge {
+            svg: unsigned,
+            detached_signature: Some(signature),
+        },
+    })
+}
+
+/// Check a badge against the expected key and the current report
+///
+/// Returns `false` for any tampering: a bad or missing signature, a
+/// different signing key, or a report hash that no longer matches.
+pub fn check_badge(
+    svg: &str,
+    detached_signature: Option<&str>,
+    expected_key: &XOnlyPublicKey,
+    current_report: &BuildComplianceReport,
+) -> bool {
+    let (signed_svg, signature_hex) = match detached_signature {
+        Some(signature) => (svg.to_string(), signature.trim().to_string()),
+        None => match attribute(svg, "signature") {
+            Some(signature) => (
+                svg.replacen(&format!("signature=\"{signature}\""), EMPTY_SIGNATURE, 1),
+                signature,
+            ),
+            None => return false,
+        },
+    };
+
+    let signature = match hex::decode(&signature_hex)
+        .ok()
+        .and_then(|bytes| schnorr::Signature::from_slice(&bytes).ok())
+    {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    let signature_valid = Secp256k1::verification_only()
+        .verify_schnorr(
+            &signature,
+            &Message::from_digest(digest(&signed_svg)),
+            expected_key,
+        )
+        .is_ok();
+
+    signature_valid
+        && attribute(&signed_svg, "report-hash") == Some(hex::encode(report_hash(current_report)))
+}
+
+/// Generate a signed badge for the current build
+///
+/// Signs with the key in `ANYA_BADGE_SIGNING_KEY`, writes the badge to
+/// `reports/compliance-badge.svg` (plus a `.sig` file for `svg-detached`)
+/// and returns the badge path.
+pub fn generate_badge(format: &str) -> Result<String, Box<dyn Error>> {
+    let location: SignatureLocation = format.parse()?;
+    let signer = LocalBadgeSigner::from_env()?;
+    let badge = create_badge(&generate_report()?, &signer, location)?;
+
+    std::fs::create_dir_all(BADGE_DIR)?;
+    let path = Path::new(BADGE_DIR).join("compliance-badge.svg");
+    std::fs::write(&path, &badge.svg)?;
+    if let Some(signature) = &badge.detached_signature {
+        std::fs::write(signature_path(&path), signature)?;
+    }
+
+    Ok(path.display().to_string())
+}
+
+/// Verify a badge file against the current build
+///
+/// The expected key is read from `ANYA_BADGE_PUBLIC_KEY`, falling back to the
+/// key derived from `ANYA_BADGE_SIGNING_KEY`. A `.sig` file next to the badge
+/// takes precedence over an embedded signature.
+pub fn verify_badge(path: &str) -> Result<bool, Box<dyn Error>> {
+    let expected_key = match std::env::var(BADGE_PUBLIC_KEY_ENV) {
+        Ok(hex_key) => XOnlyPublicKey::from_slice(&hex::decode(hex_key.trim())?)?,
+        Err(_) => LocalBadgeSigner::from_env()?.public_key(),
+    };
+
+    let svg = std::fs::read_to_string(path)?;
+    let sig_path = signature_path(Path::new(path));
+    let detached = if sig_path.exists() {
+        Some(std::fs::read_to_string(sig_path)?)
+    } else {
+        None
+    };
+
+    Ok(check_badge(
+        &svg,
+        detached.as_deref(),
+        &expected_key,
+        &generate_report()?,
+    ))
+}
+
+fn signature_path(badge: &Path) -> PathBuf {
+    let mut path = badge.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+fn digest(svg: &str) -> [u8; 32] {
+    sha256::Hash::hash(svg.as_bytes()).to_byte_array()
+}
+
+/// Value of the first `name="..."` attribute in the document
+fn attribute(svg: &str, name: &str) -> Option<String> {
+    let start = svg.find(&format!(" {name}=\""))? + name.len() + 3;
+    let end = svg[start..].find('"')? + start;
+    Some(svg[start..end].to_string())
+}
+
+fn render_svg(report: &BuildComplianceReport, public_key: &XOnlyPublicKey) -> String {
+    let percent = (report.overall_score * 100.0).round() as u32;
+    let color = if percent >= 95 {
+        "#4c1"
+    } else if percent >= 75 {
+        "#dfb317"
+    } else {
+        "#e05d44"
+    };
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="150" height="20">
+  <metadata>
+    <anya-badge report-hash="{hash}" score="{score}" timestamp="{timestamp}" public-key="{public_key}" {empty_signature}/>
+  </metadata>
+  <rect width="90" height="20" fill="#555"/>
+  <rect x="90" width="60" height="20" fill="{color}"/>
+  <g fill="#fff" font-family="Verdana,sans-serif" font-size="11">
+    <text x="6" y="14">compliance</text>
+    <text x="98" y="14">{percent}%</text>
+  </g>
+</svg>
+"##,
+        hash = hex::encode(report_hash(report)),
+        score = report.overall_score,
+        timestamp = report.timestamp,
+        empty_signature = EMPTY_SIGNATURE,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compliance::ComplianceItem;
+
+    fn report(passed: bool) -> BuildComplianceReport {
+        BuildComplianceReport {
+            items: vec![ComplianceItem {
+                name: "BIP-341".to_string(),
+                weight: 1.0,
+                passed,
+                reason: String::new(),
+            }],
+            overall_score: if passed { 1.0 } else { 0.0 },
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    fn signer(seed: u8) -> LocalBadgeSigner {
+        LocalBadgeSigner::new(&SecretKey::from_slice(&[seed; 32]).unwrap())
+    }
+
+    #[test]
+    fn test_embedded_badge_round_trip() {
+        let signer = signer(1);
+        let badge = create_badge(&report(true), &signer, SignatureLocation::Embedded).unwrap();
+        assert!(badge.detached_signature.is_none());
+        assert!(check_badge(
+            &badge.svg,
+            None,
+            &signer.public_key(),
+            &report(true)
+        ));
+    }
+
+    #[test]
+    fn test_detached_badge_round_trip() {
+        let signer = signer(1);
+        let badge = create_badge(&report(true), &signer, SignatureLocation::Detached).unwrap();
+        let signature = badge.detached_signature.as_deref().unwrap();
+        assert!(check_badge(
+            &badge.svg,
+            Some(signature),
+            &signer.public_key(),
+            &report(true)
+        ));
+        // The unsigned SVG on its own does not verify
+        assert!(!check_badge(
+            &badge.svg,
+            None,
+            &signer.public_key(),
+            &report(true)
+        ));
+    }
+
+    #[test]
+    fn test_tampered_badge_is_invalid() {
+        let signer = signer(1);
+        let badge = create_badge(&report(false), &signer, SignatureLocation::Embedded).unwrap();
+        let tampered = badge.svg.replace(">0%<", ">100%<");
+        assert_ne!(tampered, badge.svg);
+        assert!(!check_badge(
+            &tampered,
+            None,
+            &signer.public_key(),
+            &report(false)
+        ));
+    }
+
+    #[test]
+    fn test_badge_rejects_wrong_key_and_stale_report() {
+        let badge = create_badge(&report(true), &signer(1), SignatureLocation::Embedded).unwrap();
+        assert!(!check_badge(
+            &badge.svg,
+            None,
+            &signer(2).public_key(),
+            &report(true)
+        ));
+        assert!(!check_badge(
+            &badge.svg,
+            None,
+            &signer(1).public_key(),
+            &report(false)
+        ));
+    }
+}
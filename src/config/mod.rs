@@ -1,6 +1,219 @@
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::result::Result;
+use std::str::FromStr;
+
+use crate::core::DeploymentMode;
+use crate::{AnyaConfig, AnyaError, AnyaResult};
+
+pub mod reload;
+
+pub use reload::LiveConfig;
+
+/// Values accepted for `node_config.network`
+pub const SUPPORTED_NETWORKS: &[&str] = &["mainnet", "testnet", "testnet4", "signet", "regtest"];
+
+/// Node settings shared by the binaries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeConfig {
+    /// Bitcoin network, one of [`SUPPORTED_NETWORKS`]
+    pub network: String,
+    /// Directory for node data
+    pub data_dir: PathBuf,
+    /// Minimum number of peers to keep connected
+    pub min_peers: u32,
+    /// Maximum number of peers to connect to
+    pub max_peers: u32,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            network: "testnet".to_string(),
+            data_dir: PathBuf::from("./data"),
+            min_peers: 3,
+            max_peers: 50,
+        }
+    }
+}
+
+/// Settings a running node picks up on reload, see [`reload`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    /// `tracing` filter directive, e.g. `info` or `anya_core=debug,warn`
+    pub log_level: String,
+    /// Lowest fee rate accepted for transactions, in sat/vB
+    pub min_fee_rate: f64,
+    /// Highest fee rate the wallet will pay, in sat/vB
+    pub max_fee_rate: f64,
+    /// Sustained API requests per second allowed per client
+    pub rate_limit_per_second: u32,
+    /// API requests a client may make at once before being limited
+    pub rate_limit_burst: u32,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            log_level: "info".to_string(),
+            min_fee_rate: 1.0,
+            max_fee_rate: 500.0,
+            rate_limit_per_second: 50,
+            rate_limit_burst: 100,
+        }
+    }
+}
+
+impl AnyaConfig {
+    /// Load a TOML config file, apply `ANYA_*` environment overrides and validate
+    ///
+    /// Sections and fields missing from the file keep their defaults.
+    pub fn from_file(path: impl AsRef<Path>) -> AnyaResult<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| {
+            AnyaError::System(format!("Failed to read config {}: {e}", path.display()))
+        })?;
+        let mut config = Self::from_toml_str(&content)
+            .map_err(|e| AnyaError::InvalidInput(format!("{}: {e}", path.display())))?;
+        config.apply_env_overrides()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse a TOML document without applying overrides or validating it
+    pub fn from_toml_str(content: &str) -> AnyaResult<Self> {
+        toml::from_str(content).map_err(|e| AnyaError::InvalidInput(e.to_string()))
+    }
+
+    /// Serialize to a TOML document
+    pub fn to_toml_string(&self) -> AnyaResult<String> {
+        toml::to_string(self).map_err(|e| AnyaError::System(format!("TOML error: {e}")))
+    }
+
+    /// Apply overrides from the process environment
+    pub fn apply_env_overrides(&mut self) -> AnyaResult<()> {
+        self.apply_overrides(|var| std::env::var(var).ok())
+    }
+
+    /// Apply overrides looked up by environment variable name
+    ///
+    /// Recognised variables: `ANYA_BITCOIN_NETWORK`, `ANYA_DATA_DIR`,
+    /// `ANYA_MIN_PEERS`, `ANYA_MAX_PEERS`, `ANYA_ML_ENABLED`,
+    /// `ANYA_WEB5_ENABLED` and `ANYA_DAO_ENABLED`.
+    pub fn apply_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) -> AnyaResult<()> {
+        if let Some(network) = lookup("ANYA_BITCOIN_NETWORK") {
+            self.node_config.network = network;
+        }
+        if let Some(data_dir) = lookup("ANYA_DATA_DIR") {
+            self.node_config.data_dir = PathBuf::from(data_dir);
+        }
+        if let Some(value) = lookup("ANYA_MIN_PEERS") {
+            self.node_config.min_peers = parse_override("ANYA_MIN_PEERS", &value)?;
+        }
+        if let Some(value) = lookup("ANYA_MAX_PEERS") {
+            self.node_config.max_peers = parse_override("ANYA_MAX_PEERS", &value)?;
+        }
+        if let Some(value) = lookup("ANYA_ML_ENABLED") {
+            self.ml_config.enabled = parse_override("ANYA_ML_ENABLED", &value)?;
+        }
+        if let Some(value) = lookup("ANYA_WEB5_ENABLED") {
+            self.web5_config.enabled = parse_override("ANYA_WEB5_ENABLED", &value)?;
+        }
+        if let Some(value) = lookup("ANYA_DAO_ENABLED") {
+            self.dao_config.enabled = parse_override("ANYA_DAO_ENABLED", &value)?;
+        }
+        Ok(())
+    }
+
+    /// Check cross-field invariants, naming the offending field on failure
+    pub fn validate(&self) -> AnyaResult<()> {
+        let node = &self.node_config;
+        if !SUPPORTED_NETWORKS.contains(&node.network.as_str()) {
+            return Err(invalid_field(
+                "node_config.network",
+                format!(
+                    "'{}' is not one of {}",
+                    node.network,
+                    SUPPORTED_NETWORKS.join(", ")
+                ),
+            ));
+        }
+        if node.data_dir.as_os_str().is_empty() {
+            return Err(invalid_field("node_config.data_dir", "must not be empty"));
+        }
+        if node.max_peers == 0 {
+            return Err(invalid_field("node_config.max_peers", "must be at least 1"));
+        }
+        if node.min_peers > node.max_peers {
+            return Err(invalid_field(
+                "node_config.min_peers",
+                format!(
+                    "{} exceeds node_config.max_peers ({})",
+                    node.min_peers, node.max_peers
+                ),
+            ));
+        }
+        let runtime = &self.runtime;
+        if let Err(e) = crate::logging::parse_filter(&runtime.log_level) {
+            return Err(invalid_field("runtime.log_level", e));
+        }
+        if runtime.min_fee_rate.is_nan() || runtime.min_fee_rate <= 0.0 {
+            return Err(invalid_field("runtime.min_fee_rate", "must be positive"));
+        }
+        if runtime.max_fee_rate.is_nan() || runtime.max_fee_rate < runtime.min_fee_rate {
+            return Err(invalid_field(
+                "runtime.max_fee_rate",
+                format!(
+                    "{} is below runtime.min_fee_rate ({})",
+                    runtime.max_fee_rate, runtime.min_fee_rate
+                ),
+            ));
+        }
+        if runtime.rate_limit_per_second == 0 {
+            return Err(invalid_field(
+                "runtime.rate_limit_per_second",
+                "must be at least 1",
+            ));
+        }
+        if runtime.rate_limit_burst < runtime.rate_limit_per_second {
+            return Err(invalid_field(
+                "runtime.rate_limit_burst",
+                format!(
+                    "{} is below runtime.rate_limit_per_second ({})",
+                    runtime.rate_limit_burst, runtime.rate_limit_per_second
+                ),
+            ));
+        }
+        if let DeploymentMode::Canary { traffic_percent } = self.deployment {
+            if traffic_percent > 100 {
+                return Err(invalid_field(
+                    "deployment.traffic_percent",
+                    format!("{traffic_percent} exceeds 100"),
+                ));
+            }
+        }
+        if self.web5_config.enabled && self.web5_config.did_method.is_empty() {
+            return Err(invalid_field(
+                "web5_config.did_method",
+                "must not be empty while Web5 is enabled",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn parse_override<T: FromStr>(var: &str, value: &str) -> AnyaResult<T> {
+    value
+        .parse()
+        .map_err(|_| AnyaError::InvalidInput(format!("{var}: invalid value '{value}'")))
+}
+
+fn invalid_field(field: &str, problem: impl std::fmt::Display) -> AnyaError {
+    AnyaError::InvalidInput(format!("Invalid config field {field}: {problem}"))
+}
 
 // Create our own BitcoinConfig since the import is not available
 #[derive(Debug, Clone, Default)]
@@ -73,3 +286,100 @@ impl ConfigManager {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn assert_invalid_field(config: &AnyaConfig, field: &str) {
+        match config.validate() {
+            Err(AnyaError::InvalidInput(message)) => {
+                assert!(message.contains(field), "{message}")
+            }
+            other => panic!("expected {field} to be rejected, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_default_config_round_trips_through_toml() {
+        let defaults = AnyaConfig::default();
+        let serialized = defaults.to_toml_string().unwrap();
+        let parsed = AnyaConfig::from_toml_str(&serialized).unwrap();
+
+        assert_eq!(parsed.to_toml_string().unwrap(), serialized);
+        assert_eq!(parsed.node_config.network, defaults.node_config.network);
+        assert_eq!(parsed.node_config.max_peers, defaults.node_config.max_peers);
+        assert_eq!(parsed.dao_config.time_lock_blocks, 144);
+        assert!(parsed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_names_bad_field() {
+        let mut config = AnyaConfig::default();
+        config.node_config.min_peers = 10;
+        config.node_config.max_peers = 5;
+        assert_invalid_field(&config, "node_config.min_peers");
+
+        let mut config = AnyaConfig::default();
+        config.node_config.network = "moonnet".to_string();
+        assert_invalid_field(&config, "node_config.network");
+
+        let mut config = AnyaConfig::default();
+        config.node_config.data_dir = PathBuf::new();
+        assert_invalid_field(&config, "node_config.data_dir");
+
+        let config = AnyaConfig {
+            deployment: DeploymentMode::Canary {
+                traffic_percent: 150,
+            },
+            ..AnyaConfig::default()
+        };
+        assert_invalid_field(&config, "deployment.traffic_percent");
+
+        let mut config = AnyaConfig::default();
+        config.runtime.log_level = "anya_core=loud".to_string();
+        assert_invalid_field(&config, "runtime.log_level");
+
+        let mut config = AnyaConfig::default();
+        config.runtime.max_fee_rate = 0.5;
+        assert_invalid_field(&config, "runtime.max_fee_rate");
+    }
+
+    #[test]
+    fn test_overrides_apply_and_reject_bad_values() {
+        let vars: HashMap<&str, &str> = [
+            ("ANYA_BITCOIN_NETWORK", "regtest"),
+            ("ANYA_MAX_PEERS", "8"),
+            ("ANYA_ML_ENABLED", "false"),
+        ]
+        .into();
+        let mut config = AnyaConfig::default();
+        config
+            .apply_overrides(|var| vars.get(var).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.node_config.network, "regtest");
+        assert_eq!(config.node_config.max_peers, 8);
+        assert!(!config.ml_config.enabled);
+
+        let err = config
+            .apply_overrides(|var| (var == "ANYA_MIN_PEERS").then(|| "many".to_string()))
+            .unwrap_err();
+        assert!(err.to_string().contains("ANYA_MIN_PEERS"));
+    }
+
+    #[test]
+    fn test_from_file_fills_missing_fields_with_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "[node_config]\nmin_peers = 1\n").unwrap();
+
+        let config = AnyaConfig::from_file(&path).unwrap();
+        assert_eq!(config.node_config.min_peers, 1);
+        assert_eq!(config.dao_config.voting_period_blocks, 1008);
+
+        fs::write(&path, "[node_config]\nmin_peers = \"one\"\n").unwrap();
+        let err = AnyaConfig::from_file(&path).unwrap_err();
+        assert!(err.to_string().contains("min_peers"), "{err}");
+    }
+}
@@ -0,0 +1,250 @@
+//! Live configuration reload
+//!
+//! [`LiveConfig`] holds the running node's [`AnyaConfig`]. A reload parses
+//! and validates the new configuration, compares it field by field with the
+//! current one and swaps it in only if every changed field is in
+//! [`RELOADABLE_FIELDS`]. Anything else, such as the network, data
+//! directory, fee policy, peer or rate limits, is rejected with the list of
+//! fields that need a restart, and an invalid configuration is rejected
+//! outright; either way the current configuration stays in place.
+//! Components watch for new values through [`LiveConfig::subscribe`].
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use crate::{AnyaConfig, AnyaError, AnyaResult};
+
+/// Fields, or whole sections, that can change without a restart
+///
+/// Only fields a running component applies belong here. Fee, peer and rate
+/// limits are read once at startup, so reloading them would change nothing.
+pub const RELOADABLE_FIELDS: &[&str] = &["runtime.log_level"];
+
+fn is_reloadable(field: &str) -> bool {
+    RELOADABLE_FIELDS.iter().any(|reloadable| {
+        field == *reloadable
+            || field
+                .strip_prefix(reloadable)
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// The running node's configuration
+#[derive(Debug)]
+pub struct LiveConfig {
+    config: watch::Sender<Arc<AnyaConfig>>,
+}
+
+impl LiveConfig {
+    pub fn new(config: AnyaConfig) -> Self {
+        Self {
+            config: watch::Sender::new(Arc::new(config)),
+        }
+    }
+
+    pub fn current(&self) -> Arc<AnyaConfig> {
+        self.config.borrow().clone()
+    }
+
+    /// Receive every configuration swapped in by a reload
+    pub fn subscribe(&self) -> watch::Receiver<Arc<AnyaConfig>> {
+        self.config.subscribe()
+    }
+
+    /// Reload from a config file, as loaded by [`AnyaConfig::from_file`]
+    pub fn reload_from_file(&self, path: impl AsRef<Path>) -> AnyaResult<Vec<String>> {
+        self.reload(AnyaConfig::from_file(path)?)
+    }
+
+    /// Swap in `config`, returning the changed fields
+    ///
+    /// The log filter is applied before subscribers are notified.
+    pub fn reload(&self, config: AnyaConfig) -> AnyaResult<Vec<String>> {
+        config.validate()?;
+        let mut outcome = Ok(Vec::new());
+        self.config.send_if_modified(|current| {
+            outcome = check_reload(current, &config);
+            match &outcome {
+                Ok(changed) if !changed.is_empty() => {
+                    *current = Arc::new(config);
+                    true
+                }
+                _ => false,
+            }
+        });
+        if let Ok(changed) = &outcome {
+            if !changed.is_empty() {
+                log::info!("Reloaded configuration: {}", changed.join(", "));
+            }
+        }
+        outcome
+    }
+}
+
+/// Fields changed by `new`, if they can all be reloaded
+fn check_reload(current: &AnyaConfig, new: &AnyaConfig) -> AnyaResult<Vec<String>> {
+    let changed = changed_fields(current, new)?;
+    let restart: Vec<&str> = changed
+        .iter()
+        .map(String::as_str)
+        .filter(|field| !is_reloadable(field))
+        .collect();
+    if !restart.is_empty() {
+        return Err(AnyaError::InvalidInput(format!(
+            "Configuration not reloaded; a restart is required to change {}",
+            restart.join(", ")
+        )));
+    }
+    if current.runtime.log_level != new.runtime.log_level {
+        crate::logging::set_log_level(&new.runtime.log_level)?;
+    }
+    Ok(changed)
+}
+
+/// Dotted paths of the fields that differ between `old` and `new`
+fn changed_fields(old: &AnyaConfig, new: &AnyaConfig) -> AnyaResult<Vec<String>> {
+    let flatten = |config: &AnyaConfig| -> AnyaResult<BTreeMap<String, toml::Value>> {
+        let value = toml::Value::try_from(config)
+            .map_err(|e| AnyaError::System(format!("TOML error: {e}")))?;
+        let mut fields = BTreeMap::new();
+        flatten_into(String::new(), value, &mut fields);
+        Ok(fields)
+    };
+    let (old, new) = (flatten(old)?, flatten(new)?);
+
+    let mut changed: Vec<String> = old
+        .iter()
+        .filter(|(field, value)| new.get(*field) != Some(value))
+        .map(|(field, _)| field.clone())
+        .collect();
+    changed.extend(
+        new.keys()
+            .filter(|field| !old.contains_key(*field))
+            .cloned(),
+    );
+    changed.sort();
+    Ok(changed)
+}
+
+fn flatten_into(prefix: String, value: toml::Value, fields: &mut BTreeMap<String, toml::Value>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let field = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten_into(field, value, fields);
+            }
+        }
+        value => {
+            fields.insert(prefix, value);
+        }
+    }
+}
+
+/// Reload the configuration from `path` whenever the process receives SIGHUP
+#[cfg(unix)]
+pub fn reload_on_sighup(
+    live: Arc<LiveConfig>,
+    path: std::path::PathBuf,
+) -> AnyaResult<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup())
+        .map_err(|e| AnyaError::System(format!("Failed to listen for SIGHUP: {e}")))?;
+    Ok(tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            log::info!("SIGHUP received, reloading {}", path.display());
+            if let Err(e) = live.reload_from_file(&path) {
+                log::error!("{e}");
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_reload_applies_valid_config() {
+        let live = LiveConfig::new(AnyaConfig::default());
+        let mut updates = live.subscribe();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anya.toml");
+        fs::write(&path, "[runtime]\nlog_level = \"debug\"\n").unwrap();
+
+        let changed = live.reload_from_file(&path).unwrap();
+        assert_eq!(changed, ["runtime.log_level"]);
+        assert!(updates.has_changed().unwrap());
+        let current = updates.borrow_and_update().clone();
+        assert_eq!(current.runtime.log_level, "debug");
+
+        // Reloading the same file changes nothing and notifies no one
+        assert!(live.reload_from_file(&path).unwrap().is_empty());
+        assert!(!updates.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_reloaded_value_reaches_subscriber() {
+        let live = LiveConfig::new(AnyaConfig::default());
+        let mut updates = live.subscribe();
+        let consumer = tokio::spawn(async move {
+            updates.changed().await.unwrap();
+            let log_level = updates.borrow_and_update().runtime.log_level.clone();
+            log_level
+        });
+
+        let mut config = AnyaConfig::default();
+        config.runtime.log_level = "anya_core=trace,warn".to_string();
+        live.reload(config).unwrap();
+        assert_eq!(consumer.await.unwrap(), "anya_core=trace,warn");
+    }
+
+    #[test]
+    fn test_reload_rejects_invalid_and_restart_only_changes() {
+        let live = LiveConfig::new(AnyaConfig::default());
+
+        let mut invalid = AnyaConfig::default();
+        invalid.node_config.min_peers = 100;
+        invalid.runtime.rate_limit_per_second = 10;
+        assert!(live.reload(invalid).is_err());
+
+        let mut restart = AnyaConfig::default();
+        restart.node_config.network = "regtest".to_string();
+        restart.node_config.data_dir = PathBuf::from("/var/lib/anya");
+        restart.node_config.max_peers = 10;
+        restart.runtime.log_level = "debug".to_string();
+        let err = live.reload(restart).unwrap_err().to_string();
+        assert!(
+            err.contains("node_config.data_dir, node_config.max_peers, node_config.network"),
+            "{err}"
+        );
+        assert!(!err.contains("log_level"), "{err}");
+
+        // Nothing running applies fee or rate limits after startup
+        let mut limits = AnyaConfig::default();
+        limits.runtime.min_fee_rate = 2.0;
+        limits.runtime.rate_limit_burst = 200;
+        let err = live.reload(limits).unwrap_err().to_string();
+        assert!(
+            err.contains("runtime.min_fee_rate, runtime.rate_limit_burst"),
+            "{err}"
+        );
+
+        // Neither reload touched the running configuration
+        let current = live.current();
+        assert_eq!(current.node_config.network, "testnet");
+        assert_eq!(current.node_config.max_peers, 50);
+        assert_eq!(current.runtime.log_level, "info");
+        assert_eq!(current.runtime.min_fee_rate, 1.0);
+        assert_eq!(current.runtime.rate_limit_per_second, 50);
+    }
+}
@@ -0,0 +1,91 @@
+// [AIR-3][AIS-3][RES-3] Injectable wall clock
+//
+// Time-dependent components read the time through `Clock` instead of calling
+// `SystemTime::now()` directly, so tests can drive deadlines and expiries with
+// a `MockClock` instead of sleeping.
+
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// [AIR-3][RES-3] Source of the current wall-clock time
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now(&self) -> SystemTime;
+
+    /// Seconds since the Unix epoch, or 0 if the clock is set before it
+    fn unix_timestamp(&self) -> u64 {
+        self.now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.now().into()
+    }
+}
+
+/// The operating system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that only moves when told to
+///
+/// Clones share the same time, so a test can keep one handle and advance the
+/// clock a component was given.
+#[derive(Debug, Clone)]
+pub struct MockClock(Arc<Mutex<SystemTime>>);
+
+impl MockClock {
+    pub fn new(start: SystemTime) -> Self {
+        Self(Arc::new(Mutex::new(start)))
+    }
+
+    /// Clock starting `secs` seconds after the Unix epoch
+    pub fn at_unix(secs: u64) -> Self {
+        Self::new(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    pub fn set(&self, time: SystemTime) {
+        *self.time() = time;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.time() += by;
+    }
+
+    fn time(&self) -> std::sync::MutexGuard<'_, SystemTime> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.time()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_is_shared_and_clamps_before_epoch() {
+        let clock = MockClock::at_unix(1_700_000_000);
+        let handle: Arc<dyn Clock> = Arc::new(clock.clone());
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(handle.unix_timestamp(), 1_700_000_060);
+        assert_eq!(handle.now_utc().timestamp(), 1_700_000_060);
+
+        clock.set(UNIX_EPOCH - Duration::from_secs(1));
+        assert_eq!(handle.unix_timestamp(), 0);
+
+        assert!(SystemClock.unix_timestamp() > 1_700_000_000);
+    }
+}
@@ -0,0 +1,288 @@
+//! Staged deployment of a node
+//!
+//! A node started in [`DeploymentMode::Canary`] handles only
+//! `traffic_percent` of incoming requests and sends the rest to a fallback,
+//! normally the stable deployment it is replacing. A
+//! [`DeploymentMode::BlueGreen`] node is the idle stack and sends everything
+//! to the fallback until promoted. Requests, fallbacks and errors are counted
+//! under the deployment's label so a supervisor can compare the canary
+//! against the stable fleet and call [`DeploymentController::promote`] or
+//! [`DeploymentController::rollback`].
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use crate::{AnyaError, AnyaResult};
+
+/// How much traffic the node takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum DeploymentMode {
+    /// Handle this percentage of requests, 0 to 100
+    Canary { traffic_percent: u8 },
+    /// Handle no requests until promoted
+    BlueGreen,
+    /// Handle every request
+    #[default]
+    Full,
+}
+
+impl DeploymentMode {
+    /// Label attached to the deployment's metrics
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeploymentMode::Canary { .. } => "canary",
+            DeploymentMode::BlueGreen => "blue_green",
+            DeploymentMode::Full => "stable",
+        }
+    }
+}
+
+/// Where a deployment stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeploymentPhase {
+    /// Running in its configured mode
+    Staged,
+    /// Promoted to take all traffic
+    Promoted,
+    /// Rolled back; all traffic goes to the fallback
+    RolledBack,
+}
+
+/// Where a request is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Primary,
+    Fallback,
+}
+
+/// Request counts of a deployment
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeploymentMetrics {
+    /// The configured mode's [`DeploymentMode::label`]
+    pub label: &'static str,
+    pub phase: DeploymentPhase,
+    /// Requests handled by this node
+    pub handled: u64,
+    /// Requests sent to the fallback
+    pub fallback: u64,
+    /// Handled requests that failed
+    pub errors: u64,
+}
+
+impl DeploymentMetrics {
+    /// Share of handled requests that failed
+    pub fn error_rate(&self) -> f64 {
+        if self.handled == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.handled as f64
+        }
+    }
+
+    /// Metrics keyed by label, in the shape of [`crate::SystemStatus::metrics`]
+    pub fn to_status_metrics(&self) -> HashMap<String, HashMap<String, f64>> {
+        let values = HashMap::from([
+            ("handled".to_string(), self.handled as f64),
+            ("fallback".to_string(), self.fallback as f64),
+            ("errors".to_string(), self.errors as f64),
+            ("error_rate".to_string(), self.error_rate()),
+        ]);
+        HashMap::from([(self.label.to_string(), values)])
+    }
+}
+
+impl fmt::Display for DeploymentPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            DeploymentPhase::Staged => "staged",
+            DeploymentPhase::Promoted => "promoted",
+            DeploymentPhase::RolledBack => "rolled back",
+        };
+        f.write_str(phase)
+    }
+}
+
+/// Routes requests according to a [`DeploymentMode`] and tracks their outcome
+#[derive(Debug)]
+pub struct DeploymentController {
+    mode: DeploymentMode,
+    phase: RwLock<DeploymentPhase>,
+    requests: AtomicU64,
+    handled: AtomicU64,
+    fallback: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Default for DeploymentController {
+    fn default() -> Self {
+        Self::new(DeploymentMode::Full)
+    }
+}
+
+impl DeploymentController {
+    pub fn new(mode: DeploymentMode) -> Self {
+        Self {
+            mode,
+            phase: RwLock::new(DeploymentPhase::Staged),
+            requests: AtomicU64::new(0),
+            handled: AtomicU64::new(0),
+            fallback: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    pub fn mode(&self) -> DeploymentMode {
+        self.mode
+    }
+
+    pub fn phase(&self) -> DeploymentPhase {
+        *self.phase.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Percentage of requests currently handled by this node
+    pub fn traffic_percent(&self) -> u8 {
+        match (self.phase(), self.mode) {
+            (DeploymentPhase::Promoted, _) | (_, DeploymentMode::Full) => 100,
+            (DeploymentPhase::RolledBack, _) | (_, DeploymentMode::BlueGreen) => 0,
+            (DeploymentPhase::Staged, DeploymentMode::Canary { traffic_percent }) => {
+                traffic_percent.min(100)
+            }
+        }
+    }
+
+    /// Pick the route for the next request
+    ///
+    /// Sampling is evenly spread rather than random: of every 100
+    /// consecutive requests exactly `traffic_percent` are handled.
+    pub fn route(&self) -> Route {
+        let percent = u64::from(self.traffic_percent());
+        let n = self.requests.fetch_add(1, Ordering::Relaxed) % 100;
+        if (n + 1) * percent / 100 > n * percent / 100 {
+            self.handled.fetch_add(1, Ordering::Relaxed);
+            Route::Primary
+        } else {
+            self.fallback.fetch_add(1, Ordering::Relaxed);
+            Route::Fallback
+        }
+    }
+
+    /// Serve a request with `primary` or `fallback` as [`Self::route`] decides
+    ///
+    /// Failures of `primary` are counted towards the error rate; the request
+    /// is not retried on the fallback.
+    pub fn dispatch<T, E>(
+        &self,
+        primary: impl FnOnce() -> Result<T, E>,
+        fallback: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        match self.route() {
+            Route::Primary => primary().map_err(|e| {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                e
+            }),
+            Route::Fallback => fallback(),
+        }
+    }
+
+    pub fn metrics(&self) -> DeploymentMetrics {
+        DeploymentMetrics {
+            label: self.mode.label(),
+            phase: self.phase(),
+            handled: self.handled.load(Ordering::Relaxed),
+            fallback: self.fallback.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Take all traffic
+    pub fn promote(&self) -> AnyaResult<()> {
+        self.transition(DeploymentPhase::Promoted)
+    }
+
+    /// Stop taking traffic, sending every request to the fallback
+    pub fn rollback(&self) -> AnyaResult<()> {
+        self.transition(DeploymentPhase::RolledBack)
+    }
+
+    /// Only a staged canary or blue-green deployment can change phase
+    fn transition(&self, to: DeploymentPhase) -> AnyaResult<()> {
+        let mut phase = self.phase.write().unwrap_or_else(|e| e.into_inner());
+        if self.mode == DeploymentMode::Full {
+            return Err(AnyaError::InvalidInput(format!(
+                "A full deployment cannot be {to}"
+            )));
+        }
+        if *phase != DeploymentPhase::Staged {
+            return Err(AnyaError::InvalidInput(format!(
+                "Deployment is already {}",
+                *phase
+            )));
+        }
+        log::info!("{} deployment {to}", self.mode.label());
+        *phase = to;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canary_handles_configured_fraction() {
+        let controller = DeploymentController::new(DeploymentMode::Canary {
+            traffic_percent: 10,
+        });
+
+        let mut handled = 0;
+        for i in 0..1000 {
+            let result: Result<&str, &str> = controller.dispatch(
+                || {
+                    if i < 100 {
+                        Err("failed")
+                    } else {
+                        Ok("canary")
+                    }
+                },
+                || Ok("stable"),
+            );
+            if result != Ok("stable") {
+                handled += 1;
+            }
+        }
+
+        assert_eq!(handled, 100);
+        let metrics = controller.metrics();
+        assert_eq!(metrics.label, "canary");
+        assert_eq!((metrics.handled, metrics.fallback), (100, 900));
+        // Of the first 100 requests, the 10 handled ones failed
+        assert_eq!(metrics.errors, 10);
+        assert_eq!(metrics.to_status_metrics()["canary"]["error_rate"], 0.1);
+    }
+
+    #[test]
+    fn test_promote_and_rollback() {
+        let canary = DeploymentController::new(DeploymentMode::Canary {
+            traffic_percent: 25,
+        });
+        canary.promote().unwrap();
+        assert_eq!(canary.phase(), DeploymentPhase::Promoted);
+        assert!((0..10).all(|_| canary.route() == Route::Primary));
+        assert!(canary.rollback().is_err());
+
+        let blue_green = DeploymentController::new(DeploymentMode::BlueGreen);
+        assert_eq!(blue_green.route(), Route::Fallback);
+        blue_green.rollback().unwrap();
+        assert_eq!(blue_green.traffic_percent(), 0);
+        assert!(blue_green.promote().is_err());
+
+        let full = DeploymentController::default();
+        assert!(full.promote().is_err());
+        assert!(full.rollback().is_err());
+        assert_eq!(full.route(), Route::Primary);
+    }
+}
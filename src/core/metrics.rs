@@ -1,5 +1,6 @@
 // Core metrics implementation using Prometheus
 use std::collections::HashMap;
+use std::time::Duration;
 // [AIR-3][AIS-3][BPC-3][RES-3] Removed unused import: crate::AnyaResult
 
 /// Simple prometheus metrics implementation
@@ -77,3 +78,147 @@ impl PrometheusMetrics {
         result
     }
 }
+
+/// Rolling transactions-per-second rate of confirmed transactions
+///
+/// Each window keeps an exponentially weighted rate in the style of the Unix
+/// load average: a block adds its transactions, and the rate decays with a
+/// time constant of the window length. A steady stream of blocks converges on
+/// the true rate, and when blocks stop arriving the rate decays toward zero
+/// instead of repeating the last value.
+#[derive(Debug, Clone)]
+pub struct TpsTracker {
+    windows: Vec<TpsWindow>,
+    /// Unix time of the last update, in seconds
+    last_update: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct TpsWindow {
+    length: Duration,
+    rate: f64,
+}
+
+impl Default for TpsTracker {
+    /// Windows of 1, 5 and 15 minutes
+    fn default() -> Self {
+        Self::new(&[
+            Duration::from_secs(60),
+            Duration::from_secs(300),
+            Duration::from_secs(900),
+        ])
+    }
+}
+
+impl TpsTracker {
+    /// Track a rate for each of `windows`; the first is the headline rate
+    pub fn new(windows: &[Duration]) -> Self {
+        Self {
+            windows: windows
+                .iter()
+                .map(|&length| TpsWindow { length, rate: 0.0 })
+                .collect(),
+            last_update: None,
+        }
+    }
+
+    /// Record a connected block confirming `tx_count` transactions at
+    /// `timestamp`, in Unix seconds
+    pub fn record_block(&mut self, tx_count: u64, timestamp: u64) {
+        self.decay_to(timestamp);
+        for window in &mut self.windows {
+            let seconds = window.length.as_secs_f64().max(1.0);
+            window.rate += tx_count as f64 / seconds;
+        }
+    }
+
+    /// Rates per window as of `now`, in Unix seconds
+    pub fn rates(&self, now: u64) -> Vec<(Duration, f64)> {
+        let elapsed = self.elapsed(now);
+        self.windows
+            .iter()
+            .map(|window| (window.length, window.decayed(elapsed)))
+            .collect()
+    }
+
+    /// Rate over the first window as of `now`
+    pub fn tps(&self, now: u64) -> f64 {
+        let elapsed = self.elapsed(now);
+        self.windows
+            .first()
+            .map_or(0.0, |window| window.decayed(elapsed))
+    }
+
+    /// Set `anya_core_tps` to the headline rate and `anya_core_tps_<window>`,
+    /// e.g. `anya_core_tps_5m`, to the rate of each window
+    pub fn publish(&self, metrics: &mut PrometheusMetrics, now: u64) {
+        metrics.set_gauge("anya_core_tps", self.tps(now));
+        for (length, rate) in self.rates(now) {
+            let secs = length.as_secs();
+            let suffix = if secs % 60 == 0 {
+                format!("{}m", secs / 60)
+            } else {
+                format!("{secs}s")
+            };
+            metrics.set_gauge(&format!("anya_core_tps_{suffix}"), rate);
+        }
+    }
+
+    fn elapsed(&self, now: u64) -> f64 {
+        // A clock or block time going backwards counts as no time passing
+        self.last_update
+            .map_or(0.0, |last| now.saturating_sub(last) as f64)
+    }
+
+    fn decay_to(&mut self, now: u64) {
+        let elapsed = self.elapsed(now);
+        for window in &mut self.windows {
+            window.rate = window.decayed(elapsed);
+        }
+        self.last_update = Some(self.last_update.map_or(now, |last| last.max(now)));
+    }
+}
+
+impl TpsWindow {
+    fn decayed(&self, elapsed: f64) -> f64 {
+        let seconds = self.length.as_secs_f64().max(1.0);
+        self.rate * (-elapsed / seconds).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tps_tracks_block_burst_and_decays() {
+        let mut tracker = TpsTracker::default();
+        let start = 1_700_000_000;
+
+        // Fifteen minutes of blocks every ten seconds with 70 transactions each
+        for i in 0..=90 {
+            tracker.record_block(70, start + i * 10);
+        }
+        let now = start + 900;
+        let one_minute = tracker.tps(now);
+        assert!((one_minute - 7.0).abs() < 0.7, "{one_minute}");
+        let rates = tracker.rates(now);
+        assert_eq!(rates[0].0, Duration::from_secs(60));
+        // Longer windows are still catching up with the burst
+        assert!(rates[1].1 < one_minute && rates[2].1 < rates[1].1);
+
+        let mut metrics = PrometheusMetrics::new();
+        tracker.publish(&mut metrics, now);
+        assert_eq!(metrics.get_gauge("anya_core_tps"), Some(one_minute));
+        assert_eq!(metrics.get_gauge("anya_core_tps_15m"), Some(rates[2].1));
+
+        // No blocks for ten minutes: the 1m rate is close to zero, the 15m
+        // rate still remembers the burst
+        let later = now + 600;
+        assert!(tracker.tps(later) < 0.01);
+        let rates = tracker.rates(later);
+        assert!(rates[2].1 > 1.0, "{}", rates[2].1);
+        tracker.publish(&mut metrics, later);
+        assert!(metrics.get_gauge("anya_core_tps").unwrap() < 0.01);
+    }
+}
@@ -1,12 +1,16 @@
 use std::sync::Arc;
 
 // Modules
+pub mod clock;
+pub mod deployment;
 pub mod metrics;
 pub mod performance_optimization;
 pub mod reliability;
 
 // Re-exports
-pub use metrics::PrometheusMetrics;
+pub use clock::{Clock, MockClock, SystemClock};
+pub use deployment::{DeploymentController, DeploymentMetrics, DeploymentMode, DeploymentPhase};
+pub use metrics::{PrometheusMetrics, TpsTracker};
 pub use performance_optimization::{OptimizationStatus, PerformanceOptimizer, ResourceType};
 pub use reliability::{
     execute_with_monitoring, execute_with_recovery, AiVerification, ProgressTracker, Watchdog,
@@ -150,6 +154,7 @@ impl AnyaCore {
             min_confirmations: 6,
             default_fee_rate: 1,
             wallet_path: Some("/tmp/bitcoin-wallet".to_string()),
+            chain_source: None,
         };
 
         let bitcoin_adapter = crate::bitcoin::BitcoinAdapter::new(bitcoin_config).await?;
@@ -3,12 +3,15 @@
 //! This module provides decentralized autonomous organization functionality,
 //! including governance, voting, and proposal management.
 
+use crate::core::{Clock, SystemClock};
 use crate::AnyaError;
 use chrono::Utc;
 use rand::random;
+use serde::{Deserialize, Serialize};
 use serde_json::json as serde_json;
 use std::collections::HashMap;
 use std::error::Error;
+use std::sync::Arc;
 
 // DAO Module for Anya Core
 // Implements governance and voting mechanisms
@@ -71,7 +74,8 @@ pub struct RiskMetrics {
 }
 
 /// Configuration options for DAO functionality
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DAOConfig {
     /// Whether DAO functionality is enabled
     pub enabled: bool,
@@ -97,10 +101,14 @@ impl Default for DAOConfig {
     }
 }
 
+/// Expected seconds between blocks, used to turn block counts into deadlines
+const TARGET_BLOCK_SECS: i64 = 600;
+
 /// Core DAO implementation
 pub struct DAOManager {
     config: DAOConfig,
     proposals: HashMap<String, Proposal>,
+    clock: Arc<dyn Clock>,
 }
 
 impl DAOManager {
@@ -110,15 +118,34 @@ impl DAOManager {
             return Ok(Self {
                 config,
                 proposals: HashMap::new(),
+                clock: Arc::new(SystemClock),
             });
         }
 
         Ok(Self {
             config,
             proposals: HashMap::new(),
+            clock: Arc::new(SystemClock),
         })
     }
 
+    /// Read the time for proposal timestamps and voting windows from `clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn voting_period(&self) -> chrono::Duration {
+        chrono::Duration::seconds(i64::from(self.config.voting_period_blocks) * TARGET_BLOCK_SECS)
+    }
+
+    /// End of the voting window of `proposal`
+    ///
+    /// The window lasts `voting_period_blocks` at one block every ten minutes.
+    pub fn voting_ends_at(&self, proposal: &Proposal) -> chrono::DateTime<Utc> {
+        proposal.created_at + self.voting_period()
+    }
+
     /// Create a new proposal
     pub fn create_proposal(
         &mut self,
@@ -134,6 +161,7 @@ impl DAOManager {
         }
 
         let proposal_id = format!("proposal:{:x}", random::<u64>());
+        let now = self.clock.now_utc();
 
         let proposal = Proposal {
             id: proposal_id.clone(),
@@ -146,8 +174,8 @@ impl DAOManager {
             votes_against: 0,
             votes: 0,
             status: ProposalStatus::Active,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            created_at: now,
+            updated_at: now,
             execution_time: None,
         };
 
@@ -163,6 +191,8 @@ impl DAOManager {
         vote_for: bool,
         amount: u64,
     ) -> Result<(), Box<dyn Error>> {
+        let now = self.clock.now_utc();
+        let voting_period = self.voting_period();
         let proposal = self.proposals.get_mut(proposal_id).ok_or_else(|| {
             Box::new(AnyaError::DAO(format!("Proposal not found: {proposal_id}")))
         })?;
@@ -174,13 +204,19 @@ impl DAOManager {
             ))));
         }
 
+        if now >= proposal.created_at + voting_period {
+            return Err(Box::new(AnyaError::DAO(format!(
+                "Voting period for {proposal_id} has ended"
+            ))));
+        }
+
         if vote_for {
             proposal.votes_for += amount;
         } else {
             proposal.votes_against += amount;
         }
 
-        proposal.updated_at = Utc::now();
+        proposal.updated_at = now;
 
         Ok(())
     }
@@ -197,13 +233,45 @@ impl DAOManager {
         self.proposals.values().cloned().collect()
     }
 
+    /// Close the voting window of every active proposal whose window has ended
+    ///
+    /// A proposal with more votes for than against passes, any other is
+    /// rejected. Returns the IDs of the closed proposals.
+    pub fn close_expired_proposals(&mut self) -> Vec<String> {
+        let now = self.clock.now_utc();
+        let voting_period = self.voting_period();
+        let mut closed = Vec::new();
+        for proposal in self.proposals.values_mut() {
+            if proposal.status != ProposalStatus::Active
+                || now < proposal.created_at + voting_period
+            {
+                continue;
+            }
+            proposal.status = if proposal.votes_for > proposal.votes_against {
+                ProposalStatus::Passed
+            } else {
+                ProposalStatus::Rejected
+            };
+            proposal.updated_at = now;
+            closed.push(proposal.id.clone());
+        }
+        closed
+    }
+
     /// Execute a proposal
+    ///
+    /// Active proposals can be executed as soon as they have a majority,
+    /// closed ones only if they passed.
     pub fn execute_proposal(&mut self, proposal_id: &str) -> Result<(), Box<dyn Error>> {
+        let now = self.clock.now_utc();
         let proposal = self.proposals.get_mut(proposal_id).ok_or_else(|| {
             Box::new(AnyaError::DAO(format!("Proposal not found: {proposal_id}")))
         })?;
 
-        if proposal.status != ProposalStatus::Active {
+        if !matches!(
+            proposal.status,
+            ProposalStatus::Active | ProposalStatus::Passed
+        ) {
             return Err(Box::new(AnyaError::DAO(format!(
                 "Proposal is not active: {:?}",
                 proposal.status
@@ -219,8 +287,8 @@ impl DAOManager {
 
         // In a real implementation, this would execute the proposal
         proposal.status = ProposalStatus::Executed;
-        proposal.execution_time = Some(Utc::now());
-        proposal.updated_at = Utc::now();
+        proposal.execution_time = Some(now);
+        proposal.updated_at = now;
 
         Ok(())
     }
@@ -59,10 +59,57 @@ fn test_proposal_execution() -> Result<(), String> {
 #[cfg(test)]
 mod dao_tests {
     use super::*;
+    use crate::core::MockClock;
+    use crate::dao::{DAOConfig, DAOManager, ProposalStatus};
+    use std::sync::Arc;
+    use std::time::Duration;
 
     #[test]
     fn test_run_all() {
         // Test that run_all completes without panicking
         let _ = run_all();
     }
+
+    #[test]
+    fn test_proposals_expire_when_mock_clock_passes_voting_window() {
+        let clock = MockClock::at_unix(1_700_000_000);
+        let config = DAOConfig {
+            proposal_threshold: 1,
+            voting_period_blocks: 6,
+            ..DAOConfig::default()
+        };
+        let mut dao = DAOManager::new(config)
+            .unwrap()
+            .with_clock(Arc::new(clock.clone()));
+        let passing = dao.create_proposal("Fund", "Pay the auditor", 10).unwrap();
+        let failing = dao.create_proposal("Spend", "Buy a yacht", 10).unwrap();
+        assert_eq!(passing.created_at.timestamp(), 1_700_000_000);
+        assert_eq!(dao.voting_ends_at(&passing).timestamp(), 1_700_003_600);
+
+        dao.vote(&passing.id, true, 5).unwrap();
+        dao.vote(&failing.id, false, 5).unwrap();
+        clock.advance(Duration::from_secs(3_599));
+        assert!(dao.close_expired_proposals().is_empty());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(dao.vote(&passing.id, true, 5).is_err());
+        let mut closed = dao.close_expired_proposals();
+        closed.sort();
+        let mut expected = vec![passing.id.clone(), failing.id.clone()];
+        expected.sort();
+        assert_eq!(closed, expected);
+        assert_eq!(
+            dao.get_proposal(&passing.id).unwrap().status,
+            ProposalStatus::Passed
+        );
+        assert_eq!(
+            dao.get_proposal(&failing.id).unwrap().status,
+            ProposalStatus::Rejected
+        );
+
+        dao.execute_proposal(&passing.id).unwrap();
+        assert!(dao.execute_proposal(&failing.id).is_err());
+        let executed = dao.get_proposal(&passing.id).unwrap();
+        assert_eq!(executed.execution_time.unwrap().timestamp(), 1_700_003_600);
+    }
 }
@@ -1,16 +1,17 @@
 use std::error::Error;
-use std::fs;
 use std::path::Path;
 use std::process::Command;
 use log::{info, warn, error};
 
+use anya_core::install::rollback::ChangeSet;
+
 use crate::config;
 
-pub fn install_core(config_path: &str) -> Result<(), String> {
+pub fn install_core(config_path: &str, changes: &mut ChangeSet) -> Result<(), String> {
     info!("Installing Anya-Core components...");
     
     // Create necessary directories
-    create_directories()?;
+    create_directories(changes)?;
     
     // Install core binaries
     install_core_binaries()?;
@@ -22,7 +23,7 @@ pub fn install_core(config_path: &str) -> Result<(), String> {
     Ok(())
 }
 
-pub fn install_bitcoin(config_path: &str) -> Result<(), String> {
+pub fn install_bitcoin(config_path: &str, changes: &mut ChangeSet) -> Result<(), String> {
     info!("Installing Bitcoin components...");
     
     // Load configuration
@@ -33,12 +34,12 @@ pub fn install_bitcoin(config_path: &str) -> Result<(), String> {
     };
     
     // Create Bitcoin directories
-    create_bitcoin_directories()?;
+    create_bitcoin_directories(changes)?;
     
     // Check if Bitcoin Core is already installed
     if !is_bitcoin_core_installed() {
         // Install Bitcoin Core
-        install_bitcoin_core(&bitcoin_config.network)?;
+        install_bitcoin_core(&bitcoin_config.network, changes)?;
     } else {
         info!("Bitcoin Core is already installed");
     }
@@ -50,7 +51,7 @@ pub fn install_bitcoin(config_path: &str) -> Result<(), String> {
     Ok(())
 }
 
-pub fn install_dao(config_path: &str) -> Result<(), String> {
+pub fn install_dao(config_path: &str, changes: &mut ChangeSet) -> Result<(), String> {
     info!("Installing DAO components...");
     
     // Load configuration
@@ -61,7 +62,7 @@ pub fn install_dao(config_path: &str) -> Result<(), String> {
     };
     
     // Create DAO directories
-    create_dao_directories()?;
+    create_dao_directories(changes)?;
     
     // Install Clarity contracts
     install_clarity_contracts(dao_config)?;
@@ -73,7 +74,7 @@ pub fn install_dao(config_path: &str) -> Result<(), String> {
     Ok(())
 }
 
-pub fn install_web5(config_path: &str) -> Result<(), String> {
+pub fn install_web5(config_path: &str, changes: &mut ChangeSet) -> Result<(), String> {
     info!("Installing Web5 components...");
     
     // Load configuration
@@ -84,7 +85,7 @@ pub fn install_web5(config_path: &str) -> Result<(), String> {
     };
     
     // Create Web5 directories
-    create_web5_directories()?;
+    create_web5_directories(changes)?;
     
     // Install Web5 DWN
     install_web5_dwn(web5_config)?;
@@ -96,7 +97,7 @@ pub fn install_web5(config_path: &str) -> Result<(), String> {
     Ok(())
 }
 
-pub fn install_ml(config_path: &str) -> Result<(), String> {
+pub fn install_ml(config_path: &str, changes: &mut ChangeSet) -> Result<(), String> {
     info!("Installing ML components...");
     
     // Load configuration
@@ -107,7 +108,7 @@ pub fn install_ml(config_path: &str) -> Result<(), String> {
     };
     
     // Create ML directories
-    create_ml_directories()?;
+    create_ml_directories(changes)?;
     
     // Install ML models
     install_ml_models(ml_config)?;
@@ -120,24 +121,24 @@ pub fn install_ml(config_path: &str) -> Result<(), String> {
 }
 
 // Helper functions
-fn create_directories() -> Result<(), String> {
+fn create_directories(changes: &mut ChangeSet) -> Result<(), String> {
     for dir in &["data", "config", "logs", "bin"] {
         if !Path::new(dir).exists() {
-            match fs::create_dir_all(dir) {
+            match changes.create_dir_all(Path::new(dir)) {
                 Ok(_) => info!("Created directory: {}", dir),
-                Err(e) => return Err(format!("Failed to create directory {}: {}", dir, e)),
+                Err(e) => return Err(format!("Failed to create directory {}: {:#}", dir, e)),
             }
         }
     }
     Ok(())
 }
 
-fn create_bitcoin_directories() -> Result<(), String> {
+fn create_bitcoin_directories(changes: &mut ChangeSet) -> Result<(), String> {
     for dir in &["data/bitcoin", "config/bitcoin"] {
         if !Path::new(dir).exists() {
-            match fs::create_dir_all(dir) {
+            match changes.create_dir_all(Path::new(dir)) {
                 Ok(_) => info!("Created directory: {}", dir),
-                Err(e) => return Err(format!("Failed to create directory {}: {}", dir, e)),
+                Err(e) => return Err(format!("Failed to create directory {}: {:#}", dir, e)),
             }
         }
     }
@@ -153,7 +154,7 @@ fn is_bitcoin_core_installed() -> bool {
         .is_ok()
 }
 
-fn install_bitcoin_core(network: &str) -> Result<(), String> {
+fn install_bitcoin_core(network: &str, changes: &mut ChangeSet) -> Result<(), String> {
     info!("Installing Bitcoin Core...");
     
     // Download Bitcoin Core (platform-specific logic)
@@ -164,7 +165,13 @@ fn install_bitcoin_core(network: &str) -> Result<(), String> {
             .status();
             
         match status {
-            Ok(exit_status) if exit_status.success() => Ok(()),
+            Ok(exit_status) if exit_status.success() => {
+                changes.record_command(
+                    "apt-get install bitcoind",
+                    &["apt-get", "remove", "-y", "bitcoind"],
+                );
+                Ok(())
+            }
             Ok(_) => Err("Failed to install Bitcoin Core".to_string()),
             Err(e) => Err(format!("Failed to execute apt-get: {}", e)),
         }
@@ -193,7 +200,7 @@ fn configure_bitcoin_core(config: &config::BitcoinConfig) -> Result<(), String>
     Ok(())
 }
 
-fn create_dao_directories() -> Result<(), String> {
+fn create_dao_directories(changes: &mut ChangeSet) -> Result<(), String> {
     // Implementation for creating DAO directories
     Ok(())
 }
@@ -208,7 +215,7 @@ fn configure_dao(config: &config::DaoConfig) -> Result<(), String> {
     Ok(())
 }
 
-fn create_web5_directories() -> Result<(), String> {
+fn create_web5_directories(changes: &mut ChangeSet) -> Result<(), String> {
     // Implementation for creating Web5 directories
     Ok(())
 }
@@ -223,7 +230,7 @@ fn configure_web5(config: &config::Web5Config) -> Result<(), String> {
     Ok(())
 }
 
-fn create_ml_directories() -> Result<(), String> {
+fn create_ml_directories(changes: &mut ChangeSet) -> Result<(), String> {
     // Implementation for creating ML directories
     Ok(())
 }
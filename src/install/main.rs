@@ -5,6 +5,12 @@ mod config;
 mod telemetry;
 mod validation;
 use crate::dashboard::{Dashboard, DashboardConfig, OperationType};
+use anya_core::install::requirements::{
+    validate_requirements, CheckStatus, Requirements, SysinfoAnalyzer,
+};
+use anya_core::install::rollback::{
+    ChangeSet, Component, FnComponent, InstallOptions, PlannedAction, RollbackManager,
+};
 use clap::{App, Arg};
 use log::{error, info, warn};
 use std::path::Path;
@@ -55,6 +61,16 @@ fn main() {
                 .long("verify")
                 .help("Run BIP compliance verification"),
         )
+        .arg(
+            Arg::with_name("force")
+                .long("force")
+                .help("Reinstall components that are already installed"),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Show what would be installed without changing anything"),
+        )
         .get_matches();
 
     // Load configuration
@@ -78,6 +94,27 @@ fn install_core(config_path: &str, matches: clap::ArgMatches) -> Result<(), Stri
     // 1. Verify system requirements
     dashboard.set_operation("Checking system requirements...", OperationType::Info);
     validation::check_system_requirements()?;
+    let report = validate_requirements(
+        &SysinfoAnalyzer::new(),
+        &Requirements::default(),
+        Path::new("."),
+    );
+    for check in &report.checks {
+        let operation = match check.status {
+            CheckStatus::Pass => OperationType::Success,
+            CheckStatus::Warning | CheckStatus::Fail => OperationType::Warning,
+        };
+        dashboard.set_operation(
+            &format!(
+                "{}: {} (required {})",
+                check.name, check.detected, check.required
+            ),
+            operation,
+        );
+    }
+    if !report.passed() {
+        return Err(format!("System requirements not met:\n{}", report));
+    }
     dashboard.set_operation("System requirements verified", OperationType::Success);
 
     // 2. Update configuration to use the public RPC endpoint
@@ -106,7 +143,9 @@ fn install_core(config_path: &str, matches: clap::ArgMatches) -> Result<(), Stri
             );
         }
     }
-    config::save_config(&config, config_path)?;
+    if !matches.is_present("dry-run") {
+        config::save_config(&config, config_path)?;
+    }
 
     dashboard.set_operation("Configuration updated", OperationType::Success);
 
@@ -123,34 +162,15 @@ fn install_core(config_path: &str, matches: clap::ArgMatches) -> Result<(), Stri
 
     dashboard.set_progress(completed_steps, total_steps);
 
-    // 4. Install selected components
+    // 4. Install selected components, rolling back on failure
+    let mut installers = Vec::with_capacity(components.len());
     for component in &components {
-        dashboard.set_operation(
-            &format!("Installing {} component...", component),
-            OperationType::Info,
-        );
-
-        match *component {
-            "core" => {
-                components::install_core(config_path)?;
-                dashboard.set_operation("Core component installed", OperationType::Success);
-            }
-            "bitcoin" => {
-                components::install_bitcoin(config_path)?;
-                dashboard.set_operation("Bitcoin component installed", OperationType::Success);
-            }
-            "dao" => {
-                components::install_dao(config_path)?;
-                dashboard.set_operation("DAO component installed", OperationType::Success);
-            }
-            "web5" => {
-                components::install_web5(config_path)?;
-                dashboard.set_operation("Web5 component installed", OperationType::Success);
-            }
-            "ml" => {
-                components::install_ml(config_path)?;
-                dashboard.set_operation("ML component installed", OperationType::Success);
-            }
+        let install: fn(&str, &mut ChangeSet) -> Result<(), String> = match *component {
+            "core" => components::install_core,
+            "bitcoin" => components::install_bitcoin,
+            "dao" => components::install_dao,
+            "web5" => components::install_web5,
+            "ml" => components::install_ml,
             _ => {
                 dashboard.set_operation(
                     &format!("Unknown component: {}", component),
@@ -158,11 +178,44 @@ fn install_core(config_path: &str, matches: clap::ArgMatches) -> Result<(), Stri
                 );
                 return Err(format!("Unknown component: {}", component));
             }
-        }
+        };
+        installers.push(FnComponent::new(
+            component,
+            move |changes: &mut ChangeSet| {
+                install(config_path, changes).map_err(anyhow::Error::msg)
+            },
+        ));
+    }
+    let installers: Vec<&dyn Component> = installers.iter().map(|c| c as &dyn Component).collect();
 
-        completed_steps += 1;
-        dashboard.set_progress(completed_steps, total_steps);
+    let options = InstallOptions {
+        force: matches.is_present("force"),
+        dry_run: matches.is_present("dry-run"),
+    };
+    let mut manager = RollbackManager::new(Path::new(".")).map_err(|e| format!("{:#}", e))?;
+    let report = manager.install(&installers, options).map_err(|e| {
+        dashboard.set_operation(&format!("{:#}", e), OperationType::Warning);
+        format!("{:#}", e)
+    })?;
+    if options.dry_run {
+        for (component, action) in &report.plan {
+            let action = match action {
+                PlannedAction::Install => "install",
+                PlannedAction::Reinstall => "reinstall",
+                PlannedAction::Skip => "skip (already installed)",
+            };
+            info!("Plan: {} -> {}", component, action);
+        }
+        dashboard.set_operation("Dry run complete, nothing changed", OperationType::Success);
+        dashboard.stop();
+        return Ok(());
     }
+    dashboard.set_operation(
+        &format!("Installed: {}", report.installed.join(", ")),
+        OperationType::Success,
+    );
+    completed_steps += components.len();
+    dashboard.set_progress(completed_steps, total_steps);
 
     // 5. Verify installation
     if matches.is_present("verify") {
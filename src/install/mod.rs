@@ -2,6 +2,15 @@ use anyhow::Result;
 use std::cmp::Ordering;
 use std::path::{Path, PathBuf};
 
+pub mod requirements;
+pub mod rollback;
+
+pub use requirements::{
+    validate_requirements, CheckStatus, Requirements, RequirementsReport, SysinfoAnalyzer,
+    SystemAnalyzer,
+};
+pub use rollback::{Component, InstallOptions, InstallReport, PlannedAction, RollbackManager};
+
 /// Installation source configuration
 #[derive(Debug, Clone)]
 pub enum InstallationSource {
@@ -44,6 +53,15 @@ impl AnyaInstaller {
         Ok(())
     }
 
+    /// Check this host against `requirements` for an install into `target_dir`
+    pub fn check_requirements(
+        &self,
+        requirements: &Requirements,
+        target_dir: &Path,
+    ) -> RequirementsReport {
+        validate_requirements(&SysinfoAnalyzer::new(), requirements, target_dir)
+    }
+
     fn validate_source(&self) -> Result<()> {
         match &self.installation_source {
             InstallationSource::LocalBuild => {
@@ -0,0 +1,293 @@
+//! System requirements validation
+//!
+//! [`validate_requirements`] runs every check and returns a
+//! [`RequirementsReport`] whether or not they pass, so callers can show each
+//! check with the detected value, the required value and, for anything short
+//! of a pass, a hint on how to fix it. The host is inspected through the
+//! [`SystemAnalyzer`] trait; [`SysinfoAnalyzer`] is the real implementation.
+
+use std::fmt;
+use std::io;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::Path;
+
+const GB: u64 = 1_000_000_000;
+
+/// Whether the installer could bind a port
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortStatus {
+    Available,
+    /// Another process is listening on the port
+    InUse,
+    /// The system refused the bind, e.g. a privileged port or a
+    /// firewall or mandatory access control policy
+    Blocked(String),
+    Unknown(String),
+}
+
+/// Source of the host facts the requirements are checked against
+pub trait SystemAnalyzer {
+    fn cpu_cores(&self) -> usize;
+
+    /// Total physical memory in bytes
+    fn total_memory(&self) -> u64;
+
+    /// Free space in bytes on the filesystem holding `path`, if it can be found
+    fn available_disk_space(&self, path: &Path) -> Option<u64>;
+
+    fn port_status(&self, port: u16) -> PortStatus;
+}
+
+/// [`SystemAnalyzer`] reading the host through `sysinfo` and test binds
+#[derive(Debug, Default)]
+pub struct SysinfoAnalyzer;
+
+impl SysinfoAnalyzer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl SystemAnalyzer for SysinfoAnalyzer {
+    fn cpu_cores(&self) -> usize {
+        std::thread::available_parallelism()
+            .map(|cores| cores.get())
+            .unwrap_or(1)
+    }
+
+    fn total_memory(&self) -> u64 {
+        let mut system = sysinfo::System::new();
+        system.refresh_memory();
+        system.total_memory()
+    }
+
+    fn available_disk_space(&self, path: &Path) -> Option<u64> {
+        // The target may not exist yet; measure the nearest existing parent
+        let path = path.ancestors().find_map(|dir| dir.canonicalize().ok())?;
+        let disks = sysinfo::Disks::new_with_refreshed_list();
+        disks
+            .list()
+            .iter()
+            .filter(|disk| path.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len())
+            .map(|disk| disk.available_space())
+    }
+
+    fn port_status(&self, port: u16) -> PortStatus {
+        match TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)) {
+            Ok(_) => PortStatus::Available,
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => PortStatus::InUse,
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                PortStatus::Blocked(e.to_string())
+            }
+            Err(e) => PortStatus::Unknown(e.to_string()),
+        }
+    }
+}
+
+/// Minimum host resources for an installation
+#[derive(Debug, Clone)]
+pub struct Requirements {
+    pub min_cpu_cores: usize,
+    /// Bytes
+    pub min_memory: u64,
+    /// Bytes free in the install directory
+    pub min_disk_space: u64,
+    /// Ports the installed services listen on
+    pub ports: Vec<u16>,
+}
+
+impl Default for Requirements {
+    fn default() -> Self {
+        Self {
+            min_cpu_cores: 2,
+            min_memory: 4 * GB,
+            min_disk_space: 100 * GB,
+            ports: vec![8332, 8333],
+        }
+    }
+}
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Could not be verified; the installation may still work
+    Warning,
+    Fail,
+}
+
+/// Result of checking one requirement
+#[derive(Debug, Clone)]
+pub struct RequirementCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detected: String,
+    pub required: String,
+    /// How to fix a warning or failure
+    pub remediation: Option<String>,
+}
+
+impl RequirementCheck {
+    fn new(name: &str, detected: String, required: String) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Pass,
+            detected,
+            required,
+            remediation: None,
+        }
+    }
+
+    fn with_status(mut self, status: CheckStatus, remediation: String) -> Self {
+        self.status = status;
+        self.remediation = Some(remediation);
+        self
+    }
+}
+
+/// Results of all requirement checks, in the order they ran
+#[derive(Debug, Clone, Default)]
+pub struct RequirementsReport {
+    pub checks: Vec<RequirementCheck>,
+}
+
+impl RequirementsReport {
+    /// Whether no check failed; warnings do not block installation
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &RequirementCheck> {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+    }
+
+    pub fn check(&self, name: &str) -> Option<&RequirementCheck> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+}
+
+impl fmt::Display for RequirementsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = match check.status {
+                CheckStatus::Pass => "PASS",
+                CheckStatus::Warning => "WARN",
+                CheckStatus::Fail => "FAIL",
+            };
+            write!(
+                f,
+                "[{status}] {}: detected {}, required {}",
+                check.name, check.detected, check.required
+            )?;
+            if let Some(remediation) = &check.remediation {
+                write!(f, " ({remediation})")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn format_gb(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / GB as f64)
+}
+
+/// Check the host against `requirements` for an install into `install_dir`
+pub fn validate_requirements(
+    analyzer: &dyn SystemAnalyzer,
+    requirements: &Requirements,
+    install_dir: &Path,
+) -> RequirementsReport {
+    let mut checks = Vec::new();
+
+    let cores = analyzer.cpu_cores();
+    let check = RequirementCheck::new(
+        "cpu",
+        format!("{cores} cores"),
+        format!("{} cores", requirements.min_cpu_cores),
+    );
+    checks.push(if cores < requirements.min_cpu_cores {
+        check.with_status(
+            CheckStatus::Fail,
+            "Run on a machine or VM with more CPU cores".to_string(),
+        )
+    } else {
+        check
+    });
+
+    let memory = analyzer.total_memory();
+    let check = RequirementCheck::new(
+        "memory",
+        format_gb(memory),
+        format_gb(requirements.min_memory),
+    );
+    checks.push(if memory < requirements.min_memory {
+        check.with_status(
+            CheckStatus::Fail,
+            format!(
+                "Add at least {} of RAM",
+                format_gb(requirements.min_memory - memory)
+            ),
+        )
+    } else {
+        check
+    });
+
+    let required = format!(
+        "{} free in {}",
+        format_gb(requirements.min_disk_space),
+        install_dir.display()
+    );
+    checks.push(match analyzer.available_disk_space(install_dir) {
+        Some(free) if free < requirements.min_disk_space => {
+            RequirementCheck::new("disk", format_gb(free), required).with_status(
+                CheckStatus::Fail,
+                format!(
+                    "Free {} or choose an install directory on a larger volume",
+                    format_gb(requirements.min_disk_space - free)
+                ),
+            )
+        }
+        Some(free) => RequirementCheck::new("disk", format_gb(free), required),
+        None => RequirementCheck::new("disk", "unknown".to_string(), required).with_status(
+            CheckStatus::Warning,
+            "Could not find the volume holding the install directory; check free space manually"
+                .to_string(),
+        ),
+    });
+
+    for &port in &requirements.ports {
+        let name = format!("port {port}");
+        let required = "available".to_string();
+        let check = match analyzer.port_status(port) {
+            PortStatus::Available => RequirementCheck::new(&name, "available".into(), required),
+            PortStatus::InUse => RequirementCheck::new(&name, "in use".into(), required)
+                .with_status(
+                    CheckStatus::Fail,
+                    format!(
+                        "Stop the process listening on {port} (find it with `ss -ltnp 'sport = :{port}'`) or configure another port"
+                    ),
+                ),
+            PortStatus::Blocked(reason) => {
+                RequirementCheck::new(&name, format!("blocked: {reason}"), required).with_status(
+                    CheckStatus::Fail,
+                    format!(
+                        "Allow binding {port} in the firewall or security policy, or configure another port"
+                    ),
+                )
+            }
+            PortStatus::Unknown(reason) => {
+                RequirementCheck::new(&name, format!("unknown: {reason}"), required).with_status(
+                    CheckStatus::Warning,
+                    format!("Check that {port} can be bound before starting services"),
+                )
+            }
+        };
+        checks.push(check);
+    }
+
+    RequirementsReport { checks }
+}
@@ -1,23 +1,357 @@
-use std::error::Error;
+//! Component install manifest with rollback
+//!
+//! Each component records the changes it makes through a [`ChangeSet`], and
+//! the [`RollbackManager`] persists them per component in a JSON manifest.
+//! Components already in the manifest are skipped on later runs unless
+//! forced, and if a component fails, its own changes and those of every
+//! component completed earlier in the run are reverted in LIFO order.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Manifest file name inside the install directory
+pub const MANIFEST_FILE: &str = ".anya-install-manifest.json";
+
+/// A reversible change made by a component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Change {
+    /// Directory that did not exist before
+    CreatedDir(PathBuf),
+    /// File that did not exist before
+    CreatedFile(PathBuf),
+    /// File overwritten, with its previous contents saved at `backup`
+    ReplacedFile { path: PathBuf, backup: PathBuf },
+    /// Change made by an external command, undone by running `undo`
+    Command {
+        description: String,
+        undo: Vec<String>,
+    },
+}
+
+impl Change {
+    fn revert(&self) -> Result<()> {
+        match self {
+            Change::CreatedDir(path) => ignore_missing(fs::remove_dir_all(path))
+                .with_context(|| format!("Failed to remove {}", path.display())),
+            Change::CreatedFile(path) => ignore_missing(fs::remove_file(path))
+                .with_context(|| format!("Failed to remove {}", path.display())),
+            Change::ReplacedFile { path, backup } => fs::rename(backup, path)
+                .with_context(|| format!("Failed to restore {}", path.display())),
+            Change::Command { description, undo } => {
+                let (program, args) = undo
+                    .split_first()
+                    .with_context(|| format!("No undo command for {description}"))?;
+                let status = Command::new(program)
+                    .args(args)
+                    .status()
+                    .with_context(|| format!("Failed to run {program}"))?;
+                if !status.success() {
+                    anyhow::bail!("Undoing {description} failed: {status}");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn ignore_missing(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Changes made by one component install, in the order they were made
+#[derive(Debug)]
+pub struct ChangeSet {
+    backup_dir: PathBuf,
+    changes: Vec<Change>,
+}
+
+impl ChangeSet {
+    fn new(backup_dir: PathBuf) -> Self {
+        Self {
+            backup_dir,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Create `path` and any missing parents, recording each one created
+    pub fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        let missing: Vec<&Path> = path.ancestors().take_while(|dir| !dir.exists()).collect();
+        for dir in missing.into_iter().rev() {
+            fs::create_dir(dir)
+                .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+            self.changes.push(Change::CreatedDir(dir.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    /// Write `contents` to `path`, backing up the file it replaces
+    pub fn write_file(&mut self, path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            self.create_dir_all(parent)?;
+        }
+        if path.exists() {
+            fs::create_dir_all(&self.backup_dir)?;
+            let backup = self.backup_dir.join(format!(
+                "{}-{}",
+                self.changes.len(),
+                uuid::Uuid::new_v4().simple()
+            ));
+            fs::copy(path, &backup)
+                .with_context(|| format!("Failed to back up {}", path.display()))?;
+            self.changes.push(Change::ReplacedFile {
+                path: path.to_path_buf(),
+                backup,
+            });
+        } else {
+            self.changes.push(Change::CreatedFile(path.to_path_buf()));
+        }
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Record a change made outside the installer and the command undoing it
+    pub fn record_command(&mut self, description: &str, undo: &[&str]) {
+        self.changes.push(Change::Command {
+            description: description.to_string(),
+            undo: undo.iter().map(|arg| arg.to_string()).collect(),
+        });
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+}
+
+/// Reverts `changes` newest first, attempting every change before failing
+fn revert_all(changes: &[Change]) -> Result<()> {
+    let mut errors = Vec::new();
+    for change in changes.iter().rev() {
+        if let Err(e) = change.revert() {
+            log::error!("Rollback step failed: {e:#}");
+            errors.push(format!("{e:#}"));
+        }
+    }
+    if !errors.is_empty() {
+        anyhow::bail!("Rollback incomplete: {}", errors.join("; "));
+    }
+    Ok(())
+}
+
+/// A component the installer can install
+pub trait Component {
+    fn name(&self) -> &str;
+
+    /// Install the component, making every change through `changes`
+    fn install(&self, changes: &mut ChangeSet) -> Result<()>;
+}
+
+/// A [`Component`] installed by a closure
+pub struct FnComponent<F> {
+    name: String,
+    install: F,
+}
+
+impl<F: Fn(&mut ChangeSet) -> Result<()>> FnComponent<F> {
+    pub fn new(name: &str, install: F) -> Self {
+        Self {
+            name: name.to_string(),
+            install,
+        }
+    }
+}
+
+impl<F: Fn(&mut ChangeSet) -> Result<()>> Component for FnComponent<F> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn install(&self, changes: &mut ChangeSet) -> Result<()> {
+        (self.install)(changes)
+    }
+}
+
+/// How a run installs components
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InstallOptions {
+    /// Reinstall components the manifest lists as installed
+    pub force: bool,
+    /// Report the plan without changing anything
+    pub dry_run: bool,
+}
+
+/// What a run does with a component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlannedAction {
+    Install,
+    Reinstall,
+    Skip,
+}
+
+/// Outcome of [`RollbackManager::install`]
+#[derive(Debug, Default)]
+pub struct InstallReport {
+    pub plan: Vec<(String, PlannedAction)>,
+    /// Components installed by this run; empty for a dry run
+    pub installed: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    components: BTreeMap<String, ComponentRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ComponentRecord {
+    installed_at: chrono::DateTime<chrono::Utc>,
+    changes: Vec<Change>,
+}
+
+/// Installs components, recording their changes in the install manifest
 pub struct RollbackManager {
-    manifest: Vec<PathBuf>,
+    install_dir: PathBuf,
+    manifest: Manifest,
 }
 
 impl RollbackManager {
-    pub fn new(install_dir: &Path) -> Self {
-        let mut manifest = vec![install_dir.to_path_buf()];
-        manifest.push(install_dir.join("conf/bitcoin.conf"));
-        Self { manifest }
-    }
-
-    pub fn execute_rollback(&self) -> Result<()> {
-        for path in self.manifest.iter().rev() {
-            if path.is_dir() {
-                fs::remove_dir_all(path)?;
-            } else {
-                fs::remove_file(path)?;
+    /// Open the manifest in `install_dir`, starting an empty one if there is none
+    pub fn new(install_dir: &Path) -> Result<Self> {
+        let path = install_dir.join(MANIFEST_FILE);
+        let manifest = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("Corrupt install manifest {}", path.display()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Manifest::default(),
+            Err(e) => return Err(e).context("Failed to read install manifest"),
+        };
+        Ok(Self {
+            install_dir: install_dir.to_path_buf(),
+            manifest,
+        })
+    }
+
+    pub fn is_installed(&self, component: &str) -> bool {
+        self.manifest.components.contains_key(component)
+    }
+
+    /// Changes recorded for an installed component, oldest first
+    pub fn changes(&self, component: &str) -> Option<&[Change]> {
+        self.manifest
+            .components
+            .get(component)
+            .map(|record| record.changes.as_slice())
+    }
+
+    pub fn plan(&self, components: &[&dyn Component], force: bool) -> Vec<(String, PlannedAction)> {
+        components
+            .iter()
+            .map(|component| {
+                let action = match (self.is_installed(component.name()), force) {
+                    (false, _) => PlannedAction::Install,
+                    (true, true) => PlannedAction::Reinstall,
+                    (true, false) => PlannedAction::Skip,
+                };
+                (component.name().to_string(), action)
+            })
+            .collect()
+    }
+
+    /// Install `components` in order
+    ///
+    /// The manifest is saved after every component. If one fails, the run is
+    /// rolled back: the failed component's changes and then those of the
+    /// components completed before it are reverted, newest first. A forced
+    /// reinstall keeps the changes of the earlier install, so rolling it back
+    /// only reverts what the reinstall changed.
+    pub fn install(
+        &mut self,
+        components: &[&dyn Component],
+        options: InstallOptions,
+    ) -> Result<InstallReport> {
+        let plan = self.plan(components, options.force);
+        let mut report = InstallReport {
+            plan: plan.clone(),
+            installed: Vec::new(),
+        };
+        if options.dry_run {
+            for (name, action) in &plan {
+                log::info!("Dry run: {name}: {action:?}");
             }
+            return Ok(report);
         }
-        Ok(())
+
+        // Changes made by this run, per completed component
+        let mut completed: Vec<(String, PlannedAction, Vec<Change>)> = Vec::new();
+        for (component, (name, action)) in components.iter().zip(plan) {
+            if action == PlannedAction::Skip {
+                log::info!("{name} is already installed, skipping");
+                continue;
+            }
+
+            let mut changes = ChangeSet::new(self.install_dir.join(".anya-backups").join(&name));
+            let result = component
+                .install(&mut changes)
+                .and_then(|()| self.record(&name, changes.changes.clone()));
+            if let Err(e) = result {
+                log::error!("Installing {name} failed, rolling back: {e:#}");
+                let rollback = revert_all(&changes.changes).and_then(|()| self.rollback(completed));
+                return match rollback {
+                    Ok(()) => Err(e.context(format!("Installing {name} failed; rolled back"))),
+                    Err(rollback_err) => Err(e.context(format!(
+                        "Installing {name} failed and rollback did not complete: {rollback_err:#}"
+                    ))),
+                };
+            }
+            completed.push((name.clone(), action, changes.changes));
+            report.installed.push(name);
+        }
+        Ok(report)
     }
-} 
+
+    fn record(&mut self, name: &str, changes: Vec<Change>) -> Result<()> {
+        let record = self
+            .manifest
+            .components
+            .entry(name.to_string())
+            .or_insert_with(|| ComponentRecord {
+                installed_at: chrono::Utc::now(),
+                changes: Vec::new(),
+            });
+        record.installed_at = chrono::Utc::now();
+        record.changes.extend(changes);
+        self.save()
+    }
+
+    /// Revert the changes of `completed` components in LIFO order
+    fn rollback(&mut self, completed: Vec<(String, PlannedAction, Vec<Change>)>) -> Result<()> {
+        let mut result = Ok(());
+        for (name, action, changes) in completed.into_iter().rev() {
+            if let Err(e) = revert_all(&changes) {
+                result = result.and(Err(e));
+                continue;
+            }
+            if action == PlannedAction::Install {
+                self.manifest.components.remove(&name);
+            } else if let Some(record) = self.manifest.components.get_mut(&name) {
+                let kept = record.changes.len() - changes.len();
+                record.changes.truncate(kept);
+            }
+            log::info!("Rolled back {name}");
+        }
+        self.save()?;
+        result
+    }
+
+    fn save(&self) -> Result<()> {
+        fs::create_dir_all(&self.install_dir)?;
+        let path = self.install_dir.join(MANIFEST_FILE);
+        let json = serde_json::to_string_pretty(&self.manifest)?;
+        fs::write(&path, json)
+            .with_context(|| format!("Failed to write install manifest {}", path.display()))
+    }
+}
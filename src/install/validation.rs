@@ -13,11 +13,8 @@ pub fn check_system_requirements() -> Result<(), String> {
     // Check for Docker
     check_docker_version()?;
     
-    // Check for disk space
-    check_disk_space()?;
-    
-    // Check for memory
-    check_memory()?;
+    // Hardware, disk space and ports are checked by
+    // `anya_core::install::requirements::validate_requirements`
     
     info!("System requirements checked successfully");
     Ok(())
@@ -107,82 +104,6 @@ fn check_docker_version() -> Result<(), String> {
     }
 }
 
-fn check_disk_space() -> Result<(), String> {
-    // Implementation for checking disk space
-    // This is platform-specific, so a simplified version:
-    
-    info!("Checking disk space requirements...");
-    // Minimum requirement: 10GB
-    
-    // On Unix-like systems, use 'df' command
-    #[cfg(unix)]
-    {
-        let output = Command::new("df")
-            .args(&["-h", "."])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    return Err("Failed to execute df command".to_string());
-                }
-                
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                info!("Disk space check: {}", output_str.trim());
-                // In a real implementation, we would parse the output to get available space
-                
-                Ok(())
-            },
-            Err(e) => Err(format!("Failed to check disk space: {}", e)),
-        }
-    }
-    
-    // On Windows, simplify for this example
-    #[cfg(windows)]
-    {
-        info!("Disk space check: Windows implementation");
-        Ok(())
-    }
-}
-
-fn check_memory() -> Result<(), String> {
-    // Implementation for checking memory
-    // This is platform-specific, so a simplified version:
-    
-    info!("Checking memory requirements...");
-    // Minimum requirement: 4GB
-    
-    // On Unix-like systems, use 'free' command
-    #[cfg(unix)]
-    {
-        let output = Command::new("free")
-            .args(&["-h"])
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    return Err("Failed to execute free command".to_string());
-                }
-                
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                info!("Memory check: {}", output_str.trim());
-                // In a real implementation, we would parse the output to get available memory
-                
-                Ok(())
-            },
-            Err(e) => Err(format!("Failed to check memory: {}", e)),
-        }
-    }
-    
-    // On Windows, simplify for this example
-    #[cfg(windows)]
-    {
-        info!("Memory check: Windows implementation");
-        Ok(())
-    }
-}
-
 fn verify_core_components() -> Result<(), String> {
     // Implementation for verifying core components
     
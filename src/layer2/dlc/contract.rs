@@ -0,0 +1,600 @@
+//! DLC contract construction and settlement
+//!
+//! [`DlcContract::build`] turns an oracle announcement and a payout per
+//! outcome into a funding transaction and one Contract Execution Transaction
+//! (CET) per outcome. The funding output is a taproot 2-of-2 between the
+//! offering and accepting party, spendable only through its script path.
+//! Each party adaptor-signs every CET under the oracle's attestation point
+//! for that CET's outcome, so a CET can only be completed once the oracle
+//! attests to its outcome.
+
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::Hash;
+use bitcoin::opcodes::all::{OP_CHECKSIG, OP_CHECKSIGVERIFY};
+use bitcoin::psbt::Psbt;
+use bitcoin::script::Builder;
+use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, SecretKey, Signing, Verification};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{ControlBlock, LeafVersion, TapLeafHash, TaprootBuilder};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness, XOnlyPublicKey,
+};
+use std::collections::HashSet;
+
+use super::oracle::{OracleAnnouncement, OracleAttestation};
+use crate::bitcoin::mempool::policy::dust_threshold;
+use crate::layer2::{Layer2Error, Layer2ErrorReason};
+use crate::security::crypto::adaptor::{self, AdaptorSignature};
+
+/// Version, locktime and input/output counts
+const TX_OVERHEAD_VBYTES: u64 = 11;
+
+/// Funding inputs are assumed to be P2WPKH
+const FUNDING_INPUT_VBYTES: u64 = 68;
+
+/// Upper bound for a P2WPKH or P2TR output
+const OUTPUT_VBYTES: u64 = 43;
+
+/// CET spending the 2-of-2 script path into two outputs
+const CET_VBYTES: u64 = 200;
+
+/// BIP-341 NUMS point: the funding output has no usable key path
+const NUMS_INTERNAL_KEY: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// Side of the contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Party {
+    Offer,
+    Accept,
+}
+
+/// One party's contribution to the funding transaction
+#[derive(Debug, Clone)]
+pub struct PartyFunding {
+    /// Key for the party's half of the 2-of-2 funding output
+    pub fund_pubkey: XOnlyPublicKey,
+    /// Coins the party spends, with the outputs they hold
+    pub utxos: Vec<(OutPoint, TxOut)>,
+    /// Amount the party puts at stake
+    pub collateral: u64,
+    /// Where the party's CET payouts go
+    pub payout_script: ScriptBuf,
+    /// Where leftover funding coins go
+    pub change_script: ScriptBuf,
+}
+
+/// Both parties' contributions and the fee rate they agreed on
+#[derive(Debug, Clone)]
+pub struct FundingInputs {
+    pub offer: PartyFunding,
+    pub accept: PartyFunding,
+    /// Fee rate in sat/vB, for the funding transaction and the CETs
+    pub fee_rate: u64,
+}
+
+/// Contract Execution Transaction for one outcome
+#[derive(Debug, Clone)]
+pub struct Cet {
+    pub outcome: String,
+    pub tx: Transaction,
+    /// Oracle attestation point for `outcome`
+    pub adaptor_point: PublicKey,
+    pub offer_payout: u64,
+    pub accept_payout: u64,
+}
+
+/// Funding and execution transactions of a DLC
+#[derive(Debug, Clone)]
+pub struct DlcContract {
+    pub announcement: OracleAnnouncement,
+    pub funding_tx: Transaction,
+    /// CETs in announcement outcome order
+    pub cets: Vec<Cet>,
+    offer_pubkey: XOnlyPublicKey,
+    accept_pubkey: XOnlyPublicKey,
+    funding_inputs: Vec<TxOut>,
+    funding_output: TxOut,
+    leaf_script: ScriptBuf,
+    control_block: ControlBlock,
+    offer_signatures: Vec<AdaptorSignature>,
+    accept_signatures: Vec<AdaptorSignature>,
+}
+
+impl DlcContract {
+    /// Build the funding transaction and a CET for each announced outcome
+    ///
+    /// `outcome_payouts` maps every announced outcome to its
+    /// `(offer, accept)` payout; each pair must add up to the total
+    /// collateral. CETs still need both parties' adaptor signatures, see
+    /// [`sign_cets`](Self::sign_cets) and
+    /// [`add_cet_signatures`](Self::add_cet_signatures).
+    pub fn build(
+        oracle_announcement: &OracleAnnouncement,
+        outcome_payouts: &[(String, (u64, u64))],
+        funding: FundingInputs,
+    ) -> Result<DlcContract, Layer2Error> {
+        let secp = Secp256k1::verification_only();
+        let collateral = funding
+            .offer
+            .collateral
+            .checked_add(funding.accept.collateral)
+            .ok_or_else(|| {
+                Layer2Error::Validation(
+                    Layer2ErrorReason::InvalidInput,
+                    "Collateral overflows".to_string(),
+                )
+            })?;
+        validate_payouts(oracle_announcement, outcome_payouts, collateral)?;
+
+        // Funding output: <offer> CHECKSIGVERIFY <accept> CHECKSIG behind a NUMS key
+        let leaf_script = Builder::new()
+            .push_x_only_key(&funding.offer.fund_pubkey)
+            .push_opcode(OP_CHECKSIGVERIFY)
+            .push_x_only_key(&funding.accept.fund_pubkey)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let internal_key = XOnlyPublicKey::from_slice(&NUMS_INTERNAL_KEY)
+            .map_err(|e| Layer2Error::Internal(format!("Invalid NUMS key: {e}")))?;
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(0, leaf_script.clone())
+            .map_err(|e| Layer2Error::Internal(format!("Failed to build funding script: {e}")))?
+            .finalize(&secp, internal_key)
+            .map_err(|_| Layer2Error::Internal("Failed to finalize funding script".to_string()))?;
+        let control_block = spend_info
+            .control_block(&(leaf_script.clone(), LeafVersion::TapScript))
+            .ok_or_else(|| Layer2Error::Internal("Missing funding control block".to_string()))?;
+
+        let cet_fee = CET_VBYTES * funding.fee_rate;
+        let funding_output = TxOut {
+            value: Amount::from_sat(collateral + cet_fee),
+            script_pubkey: ScriptBuf::new_p2tr_tweaked(spend_info.output_key()),
+        };
+
+        // Shared costs are split evenly, the offering party covers the odd satoshi
+        let shared_fee = (TX_OVERHEAD_VBYTES + OUTPUT_VBYTES) * funding.fee_rate + cet_fee;
+        let offer_change = change(
+            "offer",
+            &funding.offer,
+            shared_fee - shared_fee / 2,
+            funding.fee_rate,
+        )?;
+        let accept_change = change("accept", &funding.accept, shared_fee / 2, funding.fee_rate)?;
+
+        let mut output = vec![funding_output.clone()];
+        for (party, amount) in [
+            (&funding.offer, offer_change),
+            (&funding.accept, accept_change),
+        ] {
+            if amount >= dust_threshold(&party.change_script).to_sat() {
+                output.push(TxOut {
+                    value: Amount::from_sat(amount),
+                    script_pubkey: party.change_script.clone(),
+                });
+            }
+        }
+        let utxos = funding.offer.utxos.iter().chain(&funding.accept.utxos);
+        let funding_tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: utxos
+                .clone()
+                .map(|(outpoint, _)| TxIn {
+                    previous_output: *outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output,
+        };
+        let funding_outpoint = OutPoint::new(funding_tx.compute_txid(), 0);
+
+        let mut cets = Vec::with_capacity(oracle_announcement.outcomes.len());
+        for outcome in &oracle_announcement.outcomes {
+            let (offer_payout, accept_payout) = outcome_payouts
+                .iter()
+                .find(|(o, _)| o == outcome)
+                .map(|(_, payout)| *payout)
+                .expect("payouts validated for every outcome");
+            let output = [
+                (offer_payout, &funding.offer.payout_script),
+                (accept_payout, &funding.accept.payout_script),
+            ]
+            .into_iter()
+            .filter(|(amount, script)| *amount >= dust_threshold(script).to_sat())
+            .map(|(amount, script)| TxOut {
+                value: Amount::from_sat(amount),
+                script_pubkey: script.clone(),
+            })
+            .collect();
+
+            cets.push(Cet {
+                outcome: outcome.clone(),
+                tx: Transaction {
+                    version: Version::TWO,
+                    lock_time: LockTime::ZERO,
+                    input: vec![TxIn {
+                        previous_output: funding_outpoint,
+                        script_sig: ScriptBuf::new(),
+                        sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                        witness: Witness::new(),
+                    }],
+                    output,
+                },
+                adaptor_point: oracle_announcement.attestation_point(outcome)?,
+                offer_payout,
+                accept_payout,
+            });
+        }
+
+        Ok(DlcContract {
+            announcement: oracle_announcement.clone(),
+            funding_tx,
+            cets,
+            offer_pubkey: funding.offer.fund_pubkey,
+            accept_pubkey: funding.accept.fund_pubkey,
+            funding_inputs: utxos.map(|(_, txout)| txout.clone()).collect(),
+            funding_output,
+            leaf_script,
+            control_block,
+            offer_signatures: Vec::new(),
+            accept_signatures: Vec::new(),
+        })
+    }
+
+    /// Unsigned funding transaction as a PSBT, for each party to sign its inputs
+    pub fn funding_psbt(&self) -> Result<Psbt, Layer2Error> {
+        let mut psbt = Psbt::from_unsigned_tx(self.funding_tx.clone()).map_err(|e| {
+            Layer2Error::Transaction(
+                Layer2ErrorReason::InvalidInput,
+                format!("Invalid funding transaction: {e}"),
+            )
+        })?;
+        for (input, txout) in psbt.inputs.iter_mut().zip(&self.funding_inputs) {
+            input.witness_utxo = Some(txout.clone());
+        }
+        Ok(psbt)
+    }
+
+    /// Adaptor-sign every CET with `party`'s funding key
+    pub fn sign_cets<C: Signing + Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        party: Party,
+        secret_key: &SecretKey,
+    ) -> Result<Vec<AdaptorSignature>, Layer2Error> {
+        if secret_key.x_only_public_key(secp).0 != self.fund_pubkey(party) {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::Unauthorized,
+                format!("Key does not match the {party:?} funding key"),
+            ));
+        }
+        self.cets
+            .iter()
+            .map(|cet| {
+                adaptor::adaptor_sign(secret_key, &self.cet_sighash(cet)?, &cet.adaptor_point)
+                    .map_err(|e| Layer2Error::Protocol(format!("Failed to sign CET: {e}")))
+            })
+            .collect()
+    }
+
+    /// Verify and store `party`'s adaptor signatures, one per CET
+    pub fn add_cet_signatures(
+        &mut self,
+        party: Party,
+        signatures: Vec<AdaptorSignature>,
+    ) -> Result<(), Layer2Error> {
+        if signatures.len() != self.cets.len() {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                format!(
+                    "Expected {} CET signatures, got {}",
+                    self.cets.len(),
+                    signatures.len()
+                ),
+            ));
+        }
+        let pubkey = self.fund_pubkey(party);
+        for (cet, signature) in self.cets.iter().zip(&signatures) {
+            let sighash = self.cet_sighash(cet)?;
+            if !adaptor::adaptor_verify(&pubkey, &sighash, &cet.adaptor_point, signature) {
+                return Err(Layer2Error::Validation(
+                    Layer2ErrorReason::Unauthorized,
+                    format!("{party:?} signature for outcome '{}' rejected", cet.outcome),
+                ));
+            }
+        }
+        match party {
+            Party::Offer => self.offer_signatures = signatures,
+            Party::Accept => self.accept_signatures = signatures,
+        }
+        Ok(())
+    }
+
+    /// Complete the CET for the attested outcome
+    ///
+    /// Decrypts both parties' adaptor signatures with the attestation and
+    /// returns the CET with its witness, ready to broadcast.
+    pub fn decrypt_cet<C: Signing + Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        attestation: &OracleAttestation,
+    ) -> Result<Transaction, Layer2Error> {
+        attestation.verify(secp, &self.announcement)?;
+        let index = self
+            .cets
+            .iter()
+            .position(|cet| cet.outcome == attestation.outcome)
+            .ok_or_else(|| {
+                Layer2Error::Validation(
+                    Layer2ErrorReason::InvalidInput,
+                    format!("No CET for outcome '{}'", attestation.outcome),
+                )
+            })?;
+        let (Some(offer), Some(accept)) = (
+            self.offer_signatures.get(index),
+            self.accept_signatures.get(index),
+        ) else {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                "CETs are missing adaptor signatures".to_string(),
+            ));
+        };
+
+        let cet = &self.cets[index];
+        let secret = attestation.secret()?;
+        let msg = Message::from_digest(self.cet_sighash(cet)?);
+        let decrypt = |signature| {
+            adaptor::decrypt(signature, &secret).map_err(|e| {
+                Layer2Error::Validation(
+                    Layer2ErrorReason::InvalidInput,
+                    format!("Failed to decrypt CET signature: {e}"),
+                )
+            })
+        };
+        let offer = decrypt(offer)?;
+        let accept = decrypt(accept)?;
+        for (signature, pubkey) in [(&offer, &self.offer_pubkey), (&accept, &self.accept_pubkey)] {
+            secp.verify_schnorr(signature, &msg, pubkey).map_err(|e| {
+                Layer2Error::Validation(
+                    Layer2ErrorReason::InvalidInput,
+                    format!("Decrypted CET signature is invalid: {e}"),
+                )
+            })?;
+        }
+
+        // Witness stack: the offer signature is consumed first, so it goes on top
+        let mut tx = cet.tx.clone();
+        let mut witness = Witness::new();
+        witness.push(accept.serialize());
+        witness.push(offer.serialize());
+        witness.push(self.leaf_script.as_bytes());
+        witness.push(self.control_block.serialize());
+        tx.input[0].witness = witness;
+        Ok(tx)
+    }
+
+    fn fund_pubkey(&self, party: Party) -> XOnlyPublicKey {
+        match party {
+            Party::Offer => self.offer_pubkey,
+            Party::Accept => self.accept_pubkey,
+        }
+    }
+
+    /// Script-path sighash of a CET spending the funding output
+    fn cet_sighash(&self, cet: &Cet) -> Result<[u8; 32], Layer2Error> {
+        let leaf_hash = TapLeafHash::from_script(&self.leaf_script, LeafVersion::TapScript);
+        SighashCache::new(&cet.tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&[&self.funding_output]),
+                leaf_hash,
+                TapSighashType::Default,
+            )
+            .map(|sighash| sighash.to_byte_array())
+            .map_err(|e| {
+                Layer2Error::Transaction(
+                    Layer2ErrorReason::InvalidInput,
+                    format!("Failed to compute CET sighash: {e}"),
+                )
+            })
+    }
+}
+
+/// Every announced outcome needs exactly one payout spending the whole collateral
+fn validate_payouts(
+    announcement: &OracleAnnouncement,
+    outcome_payouts: &[(String, (u64, u64))],
+    collateral: u64,
+) -> Result<(), Layer2Error> {
+    if announcement.outcomes.is_empty() {
+        return Err(Layer2Error::Validation(
+            Layer2ErrorReason::InvalidInput,
+            format!("Event {} announces no outcomes", announcement.event_id),
+        ));
+    }
+    let mut seen = HashSet::new();
+    for (outcome, (offer, accept)) in outcome_payouts {
+        if !announcement.outcomes.contains(outcome) {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                format!("Payout for unannounced outcome '{outcome}'"),
+            ));
+        }
+        if !seen.insert(outcome) {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                format!("Duplicate payout for outcome '{outcome}'"),
+            ));
+        }
+        if offer.checked_add(*accept) != Some(collateral) {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                format!(
+                    "Payout for outcome '{outcome}' does not match total collateral {collateral}"
+                ),
+            ));
+        }
+    }
+    if let Some(missing) = announcement.outcomes.iter().find(|o| !seen.contains(o)) {
+        return Err(Layer2Error::Validation(
+            Layer2ErrorReason::InvalidInput,
+            format!("Missing payout for outcome '{missing}'"),
+        ));
+    }
+    Ok(())
+}
+
+/// Change left to `party` after its collateral and fees
+fn change(
+    name: &str,
+    party: &PartyFunding,
+    shared_fee: u64,
+    fee_rate: u64,
+) -> Result<u64, Layer2Error> {
+    let available: u64 = party
+        .utxos
+        .iter()
+        .map(|(_, txout)| txout.value.to_sat())
+        .sum();
+    let own_fee =
+        (party.utxos.len() as u64 * FUNDING_INPUT_VBYTES + OUTPUT_VBYTES) * fee_rate + shared_fee;
+    let required = party.collateral + own_fee;
+    available.checked_sub(required).ok_or_else(|| {
+        Layer2Error::Validation(
+            Layer2ErrorReason::InsufficientFunds,
+            format!(
+                "The {name} party's funding is {} sat short",
+                required - available
+            ),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer2::dlc::oracle::LocalOracle;
+    use bitcoin::{CompressedPublicKey, Txid};
+
+    const COLLATERAL: u64 = 50_000;
+
+    fn key(seed: u8) -> SecretKey {
+        SecretKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn party(seed: u8) -> PartyFunding {
+        let secp = Secp256k1::new();
+        let script =
+            ScriptBuf::new_p2wpkh(&CompressedPublicKey(key(seed).public_key(&secp)).wpubkey_hash());
+        PartyFunding {
+            fund_pubkey: key(seed).x_only_public_key(&secp).0,
+            utxos: vec![(
+                OutPoint::new(Txid::all_zeros(), seed as u32),
+                TxOut {
+                    value: Amount::from_sat(100_000),
+                    script_pubkey: script.clone(),
+                },
+            )],
+            collateral: COLLATERAL,
+            payout_script: script.clone(),
+            change_script: script,
+        }
+    }
+
+    fn funding() -> FundingInputs {
+        FundingInputs {
+            offer: party(1),
+            accept: party(2),
+            fee_rate: 2,
+        }
+    }
+
+    fn payouts() -> Vec<(String, (u64, u64))> {
+        vec![
+            ("heads".to_string(), (2 * COLLATERAL, 0)),
+            ("tails".to_string(), (0, 2 * COLLATERAL)),
+        ]
+    }
+
+    #[test]
+    fn test_two_outcome_bet_settles_with_attestation() {
+        let secp = Secp256k1::new();
+        let oracle = LocalOracle::new(key(9));
+        let announcement = oracle.announce(&secp, "coin-flip", &["heads", "tails"]);
+
+        let mut contract = DlcContract::build(&announcement, &payouts(), funding()).unwrap();
+        assert_eq!(contract.cets.len(), 2);
+        assert_eq!(
+            contract.funding_tx.output[0].value.to_sat(),
+            2 * COLLATERAL + CET_VBYTES * 2
+        );
+        // Each CET pays the whole pot to one side
+        assert_eq!(contract.cets[0].tx.output.len(), 1);
+        assert_eq!(contract.cets[0].offer_payout, 2 * COLLATERAL);
+
+        let offer = contract.sign_cets(&secp, Party::Offer, &key(1)).unwrap();
+        let accept = contract.sign_cets(&secp, Party::Accept, &key(2)).unwrap();
+        // Signatures from the wrong party are rejected
+        assert!(contract
+            .add_cet_signatures(Party::Accept, offer.clone())
+            .is_err());
+        assert!(contract.sign_cets(&secp, Party::Offer, &key(2)).is_err());
+        contract.add_cet_signatures(Party::Offer, offer).unwrap();
+        contract.add_cet_signatures(Party::Accept, accept).unwrap();
+
+        let attestation = oracle.attest(&announcement, "tails").unwrap();
+        let cet = contract.decrypt_cet(&secp, &attestation).unwrap();
+        assert_eq!(cet.compute_txid(), contract.cets[1].tx.compute_txid());
+        assert_eq!(cet.input[0].witness.len(), 4);
+
+        // An attestation for another event cannot unlock this contract
+        let other = oracle.announce(&secp, "other-flip", &["heads", "tails"]);
+        let foreign = oracle.attest(&other, "heads").unwrap();
+        assert!(contract.decrypt_cet(&secp, &foreign).is_err());
+    }
+
+    #[test]
+    fn test_cet_keeps_payouts_above_script_dust() {
+        let secp = Secp256k1::new();
+        let announcement =
+            LocalOracle::new(key(9)).announce(&secp, "coin-flip", &["heads", "tails"]);
+
+        // 400 sats are above the P2WPKH dust threshold of 294
+        let mut payouts = payouts();
+        payouts[0].1 = (2 * COLLATERAL - 400, 400);
+        let contract = DlcContract::build(&announcement, &payouts, funding()).unwrap();
+        assert_eq!(contract.cets[0].tx.output.len(), 2);
+        assert_eq!(contract.cets[0].tx.output[1].value.to_sat(), 400);
+    }
+
+    #[test]
+    fn test_build_rejects_invalid_payouts() {
+        let secp = Secp256k1::new();
+        let announcement =
+            LocalOracle::new(key(9)).announce(&secp, "coin-flip", &["heads", "tails"]);
+
+        let mut missing = payouts();
+        missing.pop();
+        let mut unbalanced = payouts();
+        unbalanced[0].1 .0 -= 1;
+        let mut unknown = payouts();
+        unknown.push(("edge".to_string(), (COLLATERAL, COLLATERAL)));
+        for bad in [missing, unbalanced, unknown] {
+            assert!(DlcContract::build(&announcement, &bad, funding()).is_err());
+        }
+
+        let mut short = funding();
+        short.accept.collateral = 99_900;
+        let payouts = vec![
+            ("heads".to_string(), (COLLATERAL + 99_900, 0)),
+            ("tails".to_string(), (0, COLLATERAL + 99_900)),
+        ];
+        let err = DlcContract::build(&announcement, &payouts, short).unwrap_err();
+        assert!(matches!(err, Layer2Error::Validation(_, msg) if msg.contains("accept")));
+    }
+}
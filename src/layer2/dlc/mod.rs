@@ -4,6 +4,16 @@
 //! This module provides a comprehensive DLC protocol implementation following
 //! the Layer2 async architecture patterns and official Bitcoin standards.
 
+#[cfg(feature = "bitcoin")]
+pub mod contract;
+#[cfg(feature = "bitcoin")]
+pub mod oracle;
+
+#[cfg(feature = "bitcoin")]
+pub use contract::{Cet, FundingInputs, Party, PartyFunding};
+#[cfg(feature = "bitcoin")]
+pub use oracle::{LocalOracle, OracleAnnouncement, OracleAttestation};
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -13,7 +23,7 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::layer2::{
-    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Protocol, Proof,
+    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Operation, Layer2Protocol, Proof,
     ProtocolCapabilities, ProtocolHealth, ProtocolState, TransactionResult, TransactionStatus,
     TransferResult, ValidationResult, VerificationResult,
 };
@@ -234,10 +244,15 @@ impl Layer2Protocol for DlcProtocol {
 
     async fn get_capabilities(&self) -> Result<ProtocolCapabilities, Layer2Error> {
         Ok(ProtocolCapabilities {
-            supports_assets: false,
+            asset_kinds: [].into(),
             supports_smart_contracts: true,
             supports_privacy: false,
-            max_transaction_size: 1000000,
+            max_sizes: [
+                (Layer2Operation::Transaction, 1_000_000),
+                (Layer2Operation::ContractCall, 1_000_000),
+            ]
+            .into(),
+            proof_types: ["dlc".to_string()].into(),
             fee_estimation: true,
         })
     }
@@ -0,0 +1,183 @@
+//! DLC oracle announcements and attestations
+//!
+//! An oracle announces an event with a one-time nonce `R` and its possible
+//! outcomes. It later attests to the actual outcome with a BIP-340 signature
+//! using that nonce, whose scalar unlocks the matching CET adaptor signatures.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{
+    schnorr, Message, PublicKey, Secp256k1, SecretKey, Signing, Verification, XOnlyPublicKey,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::layer2::{Layer2Error, Layer2ErrorReason};
+use crate::security::crypto::adaptor::{sign_with_nonce, signature_point, AdaptorError};
+
+/// Oracle's commitment to attest one event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleAnnouncement {
+    pub event_id: String,
+    pub oracle_pubkey: XOnlyPublicKey,
+    /// One-time nonce the attestation will be signed with
+    pub nonce: XOnlyPublicKey,
+    pub outcomes: Vec<String>,
+}
+
+impl OracleAnnouncement {
+    /// Message the oracle signs for `outcome`
+    pub fn outcome_message(outcome: &str) -> [u8; 32] {
+        sha256::Hash::hash(outcome.as_bytes()).to_byte_array()
+    }
+
+    /// Point revealed by an attestation to `outcome`
+    pub fn attestation_point(&self, outcome: &str) -> Result<PublicKey, Layer2Error> {
+        if !self.outcomes.iter().any(|o| o == outcome) {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                format!(
+                    "Outcome '{outcome}' not announced for event {}",
+                    self.event_id
+                ),
+            ));
+        }
+        signature_point(
+            &self.oracle_pubkey,
+            &self.nonce,
+            &Self::outcome_message(outcome),
+        )
+        .map_err(adaptor_error)
+    }
+}
+
+/// Oracle's signature on the outcome of an announced event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleAttestation {
+    pub event_id: String,
+    pub outcome: String,
+    pub signature: schnorr::Signature,
+}
+
+impl OracleAttestation {
+    /// Check the attestation was made for `announcement` with its nonce
+    pub fn verify<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        announcement: &OracleAnnouncement,
+    ) -> Result<(), Layer2Error> {
+        if self.event_id != announcement.event_id {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                format!(
+                    "Attestation is for event {}, not {}",
+                    self.event_id, announcement.event_id
+                ),
+            ));
+        }
+        if !announcement.outcomes.contains(&self.outcome) {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                format!("Attested outcome '{}' was not announced", self.outcome),
+            ));
+        }
+        if self.signature.as_ref()[..32] != announcement.nonce.serialize() {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                "Attestation does not use the announced nonce".to_string(),
+            ));
+        }
+        let msg = Message::from_digest(OracleAnnouncement::outcome_message(&self.outcome));
+        secp.verify_schnorr(&self.signature, &msg, &announcement.oracle_pubkey)
+            .map_err(|e| {
+                Layer2Error::Validation(
+                    Layer2ErrorReason::InvalidInput,
+                    format!("Invalid attestation signature: {e}"),
+                )
+            })
+    }
+
+    /// Secret scalar of the attestation, the discrete log of its attestation point
+    pub fn secret(&self) -> Result<SecretKey, Layer2Error> {
+        SecretKey::from_slice(&self.signature.as_ref()[32..]).map_err(|e| {
+            Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                format!("Invalid attestation scalar: {e}"),
+            )
+        })
+    }
+}
+
+/// Oracle holding its own key, for regtest and tests
+///
+/// Nonces are derived from the key and event id, so each event must be
+/// attested at most once.
+pub struct LocalOracle {
+    secret_key: SecretKey,
+}
+
+impl LocalOracle {
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self { secret_key }
+    }
+
+    /// Announce `event_id` with the given possible outcomes
+    pub fn announce<C: Signing>(
+        &self,
+        secp: &Secp256k1<C>,
+        event_id: &str,
+        outcomes: &[&str],
+    ) -> OracleAnnouncement {
+        OracleAnnouncement {
+            event_id: event_id.to_string(),
+            oracle_pubkey: self.secret_key.x_only_public_key(secp).0,
+            nonce: self.nonce_secret(event_id).x_only_public_key(secp).0,
+            outcomes: outcomes.iter().map(|o| o.to_string()).collect(),
+        }
+    }
+
+    /// Attest that `outcome` happened
+    pub fn attest(
+        &self,
+        announcement: &OracleAnnouncement,
+        outcome: &str,
+    ) -> Result<OracleAttestation, Layer2Error> {
+        if !announcement.outcomes.iter().any(|o| o == outcome) {
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
+                format!(
+                    "Outcome '{outcome}' not announced for event {}",
+                    announcement.event_id
+                ),
+            ));
+        }
+        let signature = sign_with_nonce(
+            &self.secret_key,
+            &self.nonce_secret(&announcement.event_id),
+            &OracleAnnouncement::outcome_message(outcome),
+        )
+        .map_err(adaptor_error)?;
+        Ok(OracleAttestation {
+            event_id: announcement.event_id.clone(),
+            outcome: outcome.to_string(),
+            signature,
+        })
+    }
+
+    fn nonce_secret(&self, event_id: &str) -> SecretKey {
+        let mut counter = 0u32;
+        loop {
+            let mut engine = sha256::Hash::engine();
+            engine.input(b"anya/dlc/oracle-nonce");
+            engine.input(&self.secret_key.secret_bytes());
+            engine.input(event_id.as_bytes());
+            engine.input(&counter.to_be_bytes());
+            counter += 1;
+            if let Ok(nonce) = SecretKey::from_slice(&sha256::Hash::from_engine(engine)[..]) {
+                return nonce;
+            }
+        }
+    }
+}
+
+fn adaptor_error(e: AdaptorError) -> Layer2Error {
+    Layer2Error::Protocol(e.to_string())
+}
@@ -14,9 +14,9 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::layer2::{
-    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Protocol, Proof,
-    ProtocolCapabilities, ProtocolHealth, ProtocolState, TransactionResult, TransactionStatus,
-    TransferResult, ValidationResult, VerificationResult,
+    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2ErrorReason, Layer2Operation,
+    Layer2Protocol, Proof, ProtocolCapabilities, ProtocolHealth, ProtocolState, TransactionResult,
+    TransactionStatus, TransferResult, ValidationResult, VerificationResult,
 };
 
 /// Lightning Network configuration
@@ -156,7 +156,10 @@ impl LightningProtocol {
     ) -> Result<LightningInvoice, Layer2Error> {
         let connected = *self.connected.read().await;
         if !connected {
-            return Err(Layer2Error::Connection("Node not connected".to_string()));
+            return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
+                "Node not connected".to_string(),
+            ));
         }
 
         let timestamp = SystemTime::now()
@@ -188,7 +191,10 @@ impl LightningProtocol {
     ) -> Result<LightningPayment, Layer2Error> {
         let connected = *self.connected.read().await;
         if !connected {
-            return Err(Layer2Error::Connection("Node not connected".to_string()));
+            return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
+                "Node not connected".to_string(),
+            ));
         }
 
         let timestamp = SystemTime::now()
@@ -234,7 +240,10 @@ impl LightningProtocol {
     pub async fn get_channel_balance(&self) -> Result<(u64, u64), Layer2Error> {
         let connected = *self.connected.read().await;
         if !connected {
-            return Err(Layer2Error::Connection("Node not connected".to_string()));
+            return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
+                "Node not connected".to_string(),
+            ));
         }
 
         let channels = self.channels.read().await;
@@ -248,7 +257,10 @@ impl LightningProtocol {
     pub async fn list_channels(&self) -> Result<Vec<ChannelInfo>, Layer2Error> {
         let connected = *self.connected.read().await;
         if !connected {
-            return Err(Layer2Error::Connection("Node not connected".to_string()));
+            return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
+                "Node not connected".to_string(),
+            ));
         }
 
         let channels = self.channels.read().await;
@@ -263,11 +275,15 @@ impl LightningProtocol {
     ) -> Result<String, Layer2Error> {
         let connected = *self.connected.read().await;
         if !connected {
-            return Err(Layer2Error::Connection("Node not connected".to_string()));
+            return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
+                "Node not connected".to_string(),
+            ));
         }
 
         if amount < self.config.min_channel_size || amount > self.config.max_channel_size {
             return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
                 "Channel size outside allowed limits".to_string(),
             ));
         }
@@ -316,6 +332,7 @@ impl LightningProtocol {
             Ok((default_value, estimated_fee))
         } else {
             Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
                 "Invalid payment request format".to_string(),
             ))
         }
@@ -364,7 +381,10 @@ impl LightningProtocol {
     pub async fn close_channel(&self, channel_id: String) -> Result<String, Layer2Error> {
         let connected = *self.connected.read().await;
         if !connected {
-            return Err(Layer2Error::Connection("Node not connected".to_string()));
+            return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
+                "Node not connected".to_string(),
+            ));
         }
 
         let mut channels = self.channels.write().await;
@@ -508,7 +528,10 @@ impl Layer2Protocol for LightningProtocol {
     async fn submit_transaction(&self, tx_data: &[u8]) -> Result<String, Layer2Error> {
         let connected = *self.connected.read().await;
         if !connected {
-            return Err(Layer2Error::Connection("Node not connected".to_string()));
+            return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
+                "Node not connected".to_string(),
+            ));
         }
 
         // Parse transaction data as payment request
@@ -537,6 +560,7 @@ impl Layer2Protocol for LightningProtocol {
                 })
             } else {
                 Err(Layer2Error::Transaction(
+                    Layer2ErrorReason::NotFound,
                     "Transaction not found".to_string(),
                 ))
             }
@@ -581,6 +605,7 @@ impl Layer2Protocol for LightningProtocol {
 
         if !transactions.contains_key(transaction_id) {
             return Err(Layer2Error::Transaction(
+                Layer2ErrorReason::NotFound,
                 "Transaction not found".to_string(),
             ));
         }
@@ -598,10 +623,13 @@ impl Layer2Protocol for LightningProtocol {
 
     async fn get_capabilities(&self) -> Result<ProtocolCapabilities, Layer2Error> {
         Ok(ProtocolCapabilities {
-            supports_assets: false, // Lightning primarily handles BTC
+            // Lightning primarily handles BTC
+            asset_kinds: [].into(),
             supports_smart_contracts: false,
-            supports_privacy: true,          // Onion routing provides privacy
-            max_transaction_size: 4_000_000, // ~4.3M sats max HTLC
+            supports_privacy: true, // Onion routing provides privacy
+            // ~4.3M sats max HTLC
+            max_sizes: [(Layer2Operation::Transaction, 4_000_000)].into(),
+            proof_types: ["lightning_payment_proof".to_string()].into(),
             fee_estimation: true,
         })
     }
@@ -668,6 +696,7 @@ impl LightningNetwork {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_lightning_initialization() {
@@ -732,4 +761,31 @@ mod tests {
         let close_result = lightning.close_channel(channel_id).await.unwrap();
         assert!(close_result.contains("closed"));
     }
+
+    #[tokio::test]
+    async fn test_connection_errors_are_retryable_and_validation_errors_are_not() {
+        let lightning = LightningProtocol::default();
+        let peer_pubkey = format!("03{}", "b".repeat(64));
+
+        let err = lightning.list_channels().await.unwrap_err();
+        assert_eq!(err.reason(), Some(Layer2ErrorReason::NotConnected));
+        assert!(err.is_retryable());
+
+        lightning.connect().await.unwrap();
+        let err = lightning
+            .open_channel(peer_pubkey, 1_000)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Layer2Error::Validation(Layer2ErrorReason::InvalidInput, _)
+        ));
+        assert!(!err.is_retryable());
+
+        let throttled = Layer2Error::Throttled {
+            retry_after: Duration::from_secs(30),
+        };
+        assert!(throttled.is_retryable());
+        assert_eq!(throttled.retry_after(), Some(Duration::from_secs(30)));
+    }
 }
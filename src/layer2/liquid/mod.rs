@@ -12,9 +12,9 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::layer2::{
-    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Protocol, Proof,
-    ProtocolCapabilities, ProtocolHealth, ProtocolState, TransactionResult, TransactionStatus,
-    TransferResult, ValidationResult, VerificationResult,
+    AssetKind, AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2ErrorReason,
+    Layer2Operation, Layer2Protocol, Proof, ProtocolCapabilities, ProtocolHealth, ProtocolState,
+    TransactionResult, TransactionStatus, TransferResult, ValidationResult, VerificationResult,
 };
 
 /// Liquid asset type enumeration
@@ -221,6 +221,7 @@ impl LiquidProtocol {
         let connected = *self.connected.read().await;
         if !connected {
             return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
                 "Liquid node not connected".to_string(),
             ));
         }
@@ -282,6 +283,7 @@ impl LiquidProtocol {
         let connected = *self.connected.read().await;
         if !connected {
             return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
                 "Liquid node not connected".to_string(),
             ));
         }
@@ -289,7 +291,10 @@ impl LiquidProtocol {
         // Validate asset exists
         let assets = self.assets.read().await;
         if !assets.contains_key(&asset_id) {
-            return Err(Layer2Error::Validation("Asset not found".to_string()));
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::NotFound,
+                "Asset not found".to_string(),
+            ));
         }
         drop(assets);
 
@@ -297,7 +302,10 @@ impl LiquidProtocol {
         let wallet_balance = self.wallet_balance.read().await;
         let current_balance = wallet_balance.get(&asset_id).copied().unwrap_or(0);
         if current_balance < amount {
-            return Err(Layer2Error::Validation("Insufficient balance".to_string()));
+            return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InsufficientFunds,
+                "Insufficient balance".to_string(),
+            ));
         }
         drop(wallet_balance);
 
@@ -342,6 +350,7 @@ impl LiquidProtocol {
         let connected = *self.connected.read().await;
         if !connected {
             return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
                 "Liquid node not connected".to_string(),
             ));
         }
@@ -380,6 +389,7 @@ impl LiquidProtocol {
         let connected = *self.connected.read().await;
         if !connected {
             return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
                 "Liquid node not connected".to_string(),
             ));
         }
@@ -391,6 +401,7 @@ impl LiquidProtocol {
         let current_balance = wallet_balance.get(&lbtc_asset_id).copied().unwrap_or(0);
         if current_balance < amount {
             return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InsufficientFunds,
                 "Insufficient L-BTC balance".to_string(),
             ));
         }
@@ -429,10 +440,9 @@ impl LiquidProtocol {
     /// Get asset information
     pub async fn get_asset(&self, asset_id: &str) -> Result<LiquidAsset, Layer2Error> {
         let assets = self.assets.read().await;
-        assets
-            .get(asset_id)
-            .cloned()
-            .ok_or_else(|| Layer2Error::Validation("Asset not found".to_string()))
+        assets.get(asset_id).cloned().ok_or_else(|| {
+            Layer2Error::Validation(Layer2ErrorReason::NotFound, "Asset not found".to_string())
+        })
     }
 
     /// List all assets
@@ -575,6 +585,7 @@ impl Layer2Protocol for LiquidProtocol {
         let connected = *self.connected.read().await;
         if !connected {
             return Err(Layer2Error::Connection(
+                Layer2ErrorReason::NotConnected,
                 "Liquid node not connected".to_string(),
             ));
         }
@@ -615,6 +626,7 @@ impl Layer2Protocol for LiquidProtocol {
             Ok(tx.status.clone())
         } else {
             Err(Layer2Error::Transaction(
+                Layer2ErrorReason::NotFound,
                 "Transaction not found".to_string(),
             ))
         }
@@ -699,6 +711,7 @@ impl Layer2Protocol for LiquidProtocol {
 
         if !transactions.contains_key(transaction_id) {
             return Err(Layer2Error::Transaction(
+                Layer2ErrorReason::NotFound,
                 "Transaction not found".to_string(),
             ));
         }
@@ -716,10 +729,18 @@ impl Layer2Protocol for LiquidProtocol {
 
     async fn get_capabilities(&self) -> Result<ProtocolCapabilities, Layer2Error> {
         Ok(ProtocolCapabilities {
-            supports_assets: true,           // Liquid is primarily for assets
+            // Liquid is primarily for assets
+            asset_kinds: [AssetKind::Fungible].into(),
             supports_smart_contracts: false, // Liquid doesn't support complex smart contracts
             supports_privacy: true,          // Confidential transactions
-            max_transaction_size: 400_000,   // Liquid transaction size limit
+            // Liquid transaction size limit
+            max_sizes: [
+                (Layer2Operation::Transaction, 400_000),
+                (Layer2Operation::AssetIssuance, 400_000),
+                (Layer2Operation::AssetTransfer, 400_000),
+            ]
+            .into(),
+            proof_types: ["liquid_transaction_proof".to_string()].into(),
             fee_estimation: true,
         })
     }
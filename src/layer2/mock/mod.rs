@@ -1,7 +1,7 @@
 use crate::layer2::{
-    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Protocol, Proof,
-    ProtocolCapabilities, ProtocolHealth, ProtocolState, TransactionResult, TransactionStatus,
-    TransferResult, ValidationResult, VerificationResult,
+    AssetKind, AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Operation,
+    Layer2Protocol, Proof, ProtocolCapabilities, ProtocolHealth, ProtocolState, TransactionResult,
+    TransactionStatus, TransferResult, ValidationResult, VerificationResult,
 };
 use async_trait::async_trait;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -10,6 +10,7 @@ use uuid::Uuid;
 /// Simple mock implementation for testing
 pub struct MockLayer2Protocol {
     pub connected: bool,
+    capabilities: Option<ProtocolCapabilities>,
 }
 
 impl Default for MockLayer2Protocol {
@@ -20,7 +21,16 @@ impl Default for MockLayer2Protocol {
 
 impl MockLayer2Protocol {
     pub fn new() -> Self {
-        Self { connected: false }
+        Self {
+            connected: false,
+            capabilities: None,
+        }
+    }
+
+    /// Report `capabilities` instead of the default full set
+    pub fn with_capabilities(mut self, capabilities: ProtocolCapabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
     }
 }
 
@@ -147,11 +157,27 @@ impl Layer2Protocol for MockLayer2Protocol {
     }
 
     async fn get_capabilities(&self) -> Result<ProtocolCapabilities, Layer2Error> {
+        if let Some(capabilities) = &self.capabilities {
+            return Ok(capabilities.clone());
+        }
+
         Ok(ProtocolCapabilities {
-            supports_assets: true,
+            asset_kinds: [
+                AssetKind::Fungible,
+                AssetKind::NonFungible,
+                AssetKind::Identity,
+            ]
+            .into(),
             supports_smart_contracts: true,
             supports_privacy: true,
-            max_transaction_size: 1000000,
+            max_sizes: [
+                (Layer2Operation::Transaction, 1_000_000),
+                (Layer2Operation::AssetIssuance, 1_000_000),
+                (Layer2Operation::AssetTransfer, 1_000_000),
+                (Layer2Operation::ContractCall, 1_000_000),
+            ]
+            .into(),
+            proof_types: ["mock".to_string()].into(),
             fee_estimation: true,
         })
     }
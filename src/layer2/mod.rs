@@ -5,7 +5,8 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
 // Module declarations
@@ -20,6 +21,7 @@ pub mod production; // New production implementation
                     // Dev-only simulation helpers (feature gated)
 #[cfg(feature = "dev-sim")]
 pub mod dev_sim;
+pub mod registry;
 pub mod rgb;
 pub mod rsk;
 pub mod stacks;
@@ -34,6 +36,7 @@ pub use lightning::LightningProtocol;
 pub use liquid::LiquidProtocol;
 pub use manager::Layer2Manager;
 pub use production::{ProductionLayer2Protocol, RealLayer2Protocol}; // Use production implementation
+pub use registry::{Layer2Registry, SharedLayer2Protocol};
 pub use rgb::RgbProtocol;
 pub use rsk::RskProtocol;
 pub use stacks::StacksProtocol;
@@ -44,17 +47,41 @@ pub use taproot_assets::TaprootAssetsProtocol;
 #[cfg(test)]
 pub use mock::MockLayer2Protocol;
 
+/// Why a Layer2 connection, validation or transaction failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Layer2ErrorReason {
+    /// The protocol has not been connected yet
+    NotConnected,
+    /// The backend or its peers could not be reached
+    Unreachable,
+    /// The request is malformed or breaks a protocol rule
+    InvalidInput,
+    /// The asset, transaction or invoice does not exist
+    NotFound,
+    /// The sender cannot cover the amount
+    InsufficientFunds,
+    /// The caller lacks the key or authority for the operation
+    Unauthorized,
+    /// The target is in a state that forbids the operation, e.g. frozen
+    InvalidState,
+    /// A capacity limit such as a pool or schema limit was hit
+    LimitReached,
+}
+
 /// Error types for Layer2 operations
 #[derive(Debug, Clone, Error)]
 pub enum Layer2Error {
-    #[error("Connection error: {0}")]
-    Connection(String),
+    #[error("Connection error: {1}")]
+    Connection(Layer2ErrorReason, String),
+
+    #[error("Validation error: {1}")]
+    Validation(Layer2ErrorReason, String),
 
-    #[error("Validation error: {0}")]
-    Validation(String),
+    #[error("Transaction error: {1}")]
+    Transaction(Layer2ErrorReason, String),
 
-    #[error("Transaction error: {0}")]
-    Transaction(String),
+    #[error("Throttled by backend, retry after {retry_after:?}")]
+    Throttled { retry_after: Duration },
 
     #[error("Protocol error: {0}")]
     Protocol(String),
@@ -69,6 +96,43 @@ pub enum Layer2Error {
     Internal(String),
 }
 
+impl Layer2Error {
+    /// Structured reason, for the variants that carry one
+    pub fn reason(&self) -> Option<Layer2ErrorReason> {
+        match self {
+            Layer2Error::Connection(reason, _)
+            | Layer2Error::Validation(reason, _)
+            | Layer2Error::Transaction(reason, _) => Some(*reason),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request may succeed if retried later
+    ///
+    /// Connection, network and throttling failures are transient. A
+    /// transaction is only retried when it hit a capacity limit such as a
+    /// full pool; every other failure will repeat until the request changes.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Layer2Error::Connection(..) | Layer2Error::Network(_) => true,
+            Layer2Error::Throttled { .. } => true,
+            Layer2Error::Transaction(reason, _) => *reason == Layer2ErrorReason::LimitReached,
+            Layer2Error::Validation(..)
+            | Layer2Error::Protocol(_)
+            | Layer2Error::Configuration(_)
+            | Layer2Error::Internal(_) => false,
+        }
+    }
+
+    /// Delay requested by a throttling backend
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Layer2Error::Throttled { retry_after } => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
 /// Layer2 protocol type enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Layer2ProtocolType {
@@ -132,7 +196,7 @@ pub struct TransferResult {
 }
 
 /// Proof data structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Proof {
     pub proof_type: String,
     pub data: Vec<u8>,
@@ -183,16 +247,64 @@ pub struct ProtocolHealth {
     pub uptime_seconds: u64,
 }
 
+/// Kinds of assets a protocol can issue and transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum AssetKind {
+    Fungible,
+    NonFungible,
+    Identity,
+}
+
+/// Operations a protocol may limit in size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Layer2Operation {
+    Transaction,
+    AssetIssuance,
+    AssetTransfer,
+    ContractCall,
+}
+
 /// Protocol capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProtocolCapabilities {
-    pub supports_assets: bool,
+    /// Asset kinds supported by `issue_asset` and `transfer_asset`
+    pub asset_kinds: BTreeSet<AssetKind>,
     pub supports_smart_contracts: bool,
     pub supports_privacy: bool,
-    pub max_transaction_size: u32,
+    /// Maximum payload size in bytes per operation
+    pub max_sizes: BTreeMap<Layer2Operation, u32>,
+    /// `Proof::proof_type` values the protocol generates and verifies
+    pub proof_types: BTreeSet<String>,
     pub fee_estimation: bool,
 }
 
+impl ProtocolCapabilities {
+    /// Whether any asset kind is supported
+    pub fn supports_assets(&self) -> bool {
+        !self.asset_kinds.is_empty()
+    }
+
+    /// Whether the given asset kind is supported
+    pub fn supports_asset_kind(&self, kind: AssetKind) -> bool {
+        self.asset_kinds.contains(&kind)
+    }
+
+    /// Size limit for an operation, `None` if the operation is unsupported
+    pub fn max_size(&self, operation: Layer2Operation) -> Option<u32> {
+        self.max_sizes.get(&operation).copied()
+    }
+
+    /// Size limit for plain transactions
+    pub fn max_transaction_size(&self) -> u32 {
+        self.max_size(Layer2Operation::Transaction).unwrap_or(0)
+    }
+
+    /// Whether proofs of the given type are supported
+    pub fn supports_proof_type(&self, proof_type: &str) -> bool {
+        self.proof_types.contains(proof_type)
+    }
+}
+
 /// Fee estimation result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FeeEstimate {
@@ -4,9 +4,9 @@
 //! [AIR-3][AIS-3][BPC-3][RES-3]
 
 use crate::layer2::{
-    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Protocol, Proof,
-    ProtocolCapabilities, ProtocolHealth, ProtocolState, TransactionResult, TransactionStatus,
-    TransferResult, ValidationResult, VerificationResult,
+    AssetKind, AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2ErrorReason,
+    Layer2Operation, Layer2Protocol, Proof, ProtocolCapabilities, ProtocolHealth, ProtocolState,
+    TransactionResult, TransactionStatus, TransferResult, ValidationResult, VerificationResult,
 };
 use async_trait::async_trait;
 // Bring RPC trait into scope for Bitcoin Core RPC calls
@@ -289,8 +289,9 @@ impl ProductionLayer2Protocol {
                 }
                 #[cfg(not(feature = "dev-sim"))]
                 {
-                    // Without dev-sim, refuse simulation and require fallback
-                    return Err(Layer2Error::Connection(
+                    // Without dev-sim, refuse simulation and require fallback. No
+                    // retry can succeed until the configuration changes.
+                    return Err(Layer2Error::Configuration(
                         "Simulation disabled (enable 'dev-sim' or set enable_self_node_fallback)"
                             .to_string(),
                     ));
@@ -336,10 +337,13 @@ impl ProductionLayer2Protocol {
                 );
                 self.activate_self_node().await?;
             } else {
-                return Err(Layer2Error::Connection(format!(
-                    "Insufficient peers connected: {} < {}",
-                    peer_count, self.config.min_peers
-                )));
+                return Err(Layer2Error::Connection(
+                    Layer2ErrorReason::Unreachable,
+                    format!(
+                        "Insufficient peers connected: {} < {}",
+                        peer_count, self.config.min_peers
+                    ),
+                ));
             }
         }
 
@@ -468,6 +472,7 @@ impl ProductionLayer2Protocol {
         let peers = self.peers.read().await;
         if peers.is_empty() {
             return Err(Layer2Error::Connection(
+                Layer2ErrorReason::Unreachable,
                 "No peers available for sync".to_string(),
             ));
         }
@@ -782,6 +787,7 @@ impl Layer2Protocol for ProductionLayer2Protocol {
     async fn submit_transaction(&self, tx_data: &[u8]) -> Result<String, Layer2Error> {
         if tx_data.is_empty() {
             return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
                 "Transaction data cannot be empty".to_string(),
             ));
         }
@@ -810,6 +816,7 @@ impl Layer2Protocol for ProductionLayer2Protocol {
             let mut tx_pool = self.tx_pool.write().await;
             if tx_pool.len() >= self.config.tx_pool_size {
                 return Err(Layer2Error::Transaction(
+                    Layer2ErrorReason::LimitReached,
                     "Transaction pool is full".to_string(),
                 ));
             }
@@ -860,6 +867,7 @@ impl Layer2Protocol for ProductionLayer2Protocol {
                 }
             }
             None => Err(Layer2Error::Transaction(
+                Layer2ErrorReason::NotFound,
                 "Transaction not found".to_string(),
             )),
         }
@@ -892,12 +900,14 @@ impl Layer2Protocol for ProductionLayer2Protocol {
         // Validate asset parameters
         if params.name.is_empty() {
             return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
                 "Asset name cannot be empty".to_string(),
             ));
         }
 
         if params.total_supply == 0 {
             return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
                 "Total supply must be greater than zero".to_string(),
             ));
         }
@@ -949,12 +959,14 @@ impl Layer2Protocol for ProductionLayer2Protocol {
         // Validate transfer parameters
         if transfer.amount == 0 {
             return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
                 "Transfer amount must be greater than zero".to_string(),
             ));
         }
 
         if transfer.from == transfer.to {
             return Err(Layer2Error::Validation(
+                Layer2ErrorReason::InvalidInput,
                 "Cannot transfer to same address".to_string(),
             ));
         }
@@ -1029,6 +1041,7 @@ impl Layer2Protocol for ProductionLayer2Protocol {
 
         if !tx_pool.contains_key(transaction_id) {
             return Err(Layer2Error::Transaction(
+                Layer2ErrorReason::NotFound,
                 "Transaction not found".to_string(),
             ));
         }
@@ -1050,14 +1063,17 @@ impl Layer2Protocol for ProductionLayer2Protocol {
     }
 
     async fn get_capabilities(&self) -> Result<ProtocolCapabilities, Layer2Error> {
+        let max_transaction_size = match self.config.protocol_type.as_str() {
+            "lightning" => 4_294_967, // ~4MB for Lightning
+            _ => 1_000_000,           // 1MB default
+        };
+
         Ok(ProtocolCapabilities {
-            supports_assets: true,
+            asset_kinds: [AssetKind::Fungible].into(),
             supports_smart_contracts: matches!(self.config.protocol_type.as_str(), "dlc" | "rgb"),
             supports_privacy: matches!(self.config.protocol_type.as_str(), "rgb" | "lightning"),
-            max_transaction_size: match self.config.protocol_type.as_str() {
-                "lightning" => 4_294_967, // ~4MB for Lightning
-                _ => 1_000_000,           // 1MB default
-            },
+            max_sizes: [(Layer2Operation::Transaction, max_transaction_size)].into(),
+            proof_types: [format!("{}_inclusion_proof", self.config.protocol_type)].into(),
             fee_estimation: true,
         })
     }
@@ -1201,3 +1217,22 @@ pub use ProductionLayer2Protocol as RealLayer2Protocol;
 pub type MockLayer2Protocol = ProductionLayer2Protocol;
 
 // Dev-only simulation helpers live in crate::layer2::dev_sim
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "dev-sim"))]
+    #[tokio::test]
+    async fn test_disabled_simulation_is_not_retryable() {
+        let protocol = ProductionLayer2Protocol::new(NetworkConfig {
+            enable_real_networking: false,
+            enable_self_node_fallback: false,
+            ..NetworkConfig::default()
+        });
+
+        let err = protocol.connect().await.unwrap_err();
+        assert!(matches!(err, Layer2Error::Configuration(_)), "{err}");
+        assert!(!err.is_retryable());
+    }
+}
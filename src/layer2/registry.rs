@@ -0,0 +1,136 @@
+//! Layer2 protocol registry
+//!
+//! Holds registered protocols behind shared handles so async handlers can
+//! use them concurrently.
+//!
+//! # Locking contract
+//!
+//! Each protocol sits in its own [`tokio::sync::RwLock`]:
+//!
+//! - every `&self` method of [`Layer2Protocol`] (`get_state`, `health_check`,
+//!   `submit_transaction`, ...) is called under a **read** lock, so any number
+//!   of callers may use the same protocol at once;
+//! - `sync_state`, the only `&mut self` method, needs the **write** lock and
+//!   runs exclusively, waiting for in-flight reads to finish.
+//!
+//! Locks on different protocols are independent. Do not hold a protocol
+//! guard while awaiting a write lock on the same protocol; use
+//! [`Layer2Registry::sync`] instead of locking manually where possible.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::layer2::{Layer2Error, Layer2Protocol};
+
+/// Shared handle to a registered protocol
+pub type SharedLayer2Protocol = Arc<RwLock<dyn Layer2Protocol>>;
+
+/// Registry of Layer2 protocols addressed by name
+#[derive(Default)]
+pub struct Layer2Registry {
+    protocols: RwLock<HashMap<String, SharedLayer2Protocol>>,
+}
+
+impl Layer2Registry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a protocol, returning the handle it replaced, if any
+    pub async fn register<P>(
+        &self,
+        name: impl Into<String>,
+        protocol: P,
+    ) -> Option<SharedLayer2Protocol>
+    where
+        P: Layer2Protocol + 'static,
+    {
+        let handle: SharedLayer2Protocol = Arc::new(RwLock::new(protocol));
+        self.protocols.write().await.insert(name.into(), handle)
+    }
+
+    /// Remove a protocol from the registry
+    ///
+    /// Callers still holding its handle can keep using it.
+    pub async fn unregister(&self, name: &str) -> Option<SharedLayer2Protocol> {
+        self.protocols.write().await.remove(name)
+    }
+
+    /// Handle to a registered protocol
+    pub async fn get(&self, name: &str) -> Option<SharedLayer2Protocol> {
+        self.protocols.read().await.get(name).cloned()
+    }
+
+    /// Sync a protocol's state under its write lock
+    pub async fn sync(&self, name: &str) -> Result<(), Layer2Error> {
+        let protocol = self
+            .get(name)
+            .await
+            .ok_or_else(|| Layer2Error::Protocol(format!("Protocol {name} not registered")))?;
+        let mut protocol = protocol.write().await;
+        protocol.sync_state().await
+    }
+
+    /// Names of all registered protocols
+    pub async fn list_protocols(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.protocols.read().await.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer2::{LightningProtocol, RgbProtocol};
+
+    #[tokio::test]
+    async fn test_concurrent_get_state() {
+        let registry = Arc::new(Layer2Registry::new());
+        registry.register("rgb", RgbProtocol::default()).await;
+        registry
+            .register("lightning", LightningProtocol::default())
+            .await;
+        assert_eq!(registry.list_protocols().await, vec!["lightning", "rgb"]);
+
+        let tasks: Vec<_> = ["rgb", "lightning", "rgb", "lightning"]
+            .into_iter()
+            .map(|name| {
+                let registry = Arc::clone(&registry);
+                tokio::spawn(async move {
+                    registry
+                        .get(name)
+                        .await
+                        .unwrap()
+                        .read()
+                        .await
+                        .get_state()
+                        .await
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            assert!(task.await.unwrap().is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_waits_for_readers() {
+        let registry = Layer2Registry::new();
+        registry.register("rgb", RgbProtocol::default()).await;
+
+        let protocol = registry.get("rgb").await.unwrap();
+        let reader = protocol.read().await;
+        // The write lock is unavailable while a read guard is held
+        assert!(protocol.try_write().is_err());
+        drop(reader);
+
+        registry.sync("rgb").await.unwrap();
+        assert!(registry.sync("missing").await.is_err());
+        assert!(registry.unregister("rgb").await.is_some());
+        assert!(registry.get("rgb").await.is_none());
+    }
+}
@@ -11,9 +11,9 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::layer2::{
-    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Protocol, Proof,
-    ProtocolCapabilities, ProtocolHealth, ProtocolState, TransactionResult, TransactionStatus,
-    TransferResult, ValidationResult, VerificationResult,
+    AssetKind, AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2ErrorReason,
+    Layer2Operation, Layer2Protocol, Proof, ProtocolCapabilities, ProtocolHealth, ProtocolState,
+    TransactionResult, TransactionStatus, TransferResult, ValidationResult, VerificationResult,
 };
 
 // Import and re-export the protocol trait
@@ -137,6 +137,7 @@ impl Layer2Protocol for StacksProtocol {
             Ok(tx.status.clone())
         } else {
             Err(Layer2Error::Transaction(
+                Layer2ErrorReason::NotFound,
                 "Transaction not found".to_string(),
             ))
         }
@@ -209,10 +210,17 @@ impl Layer2Protocol for StacksProtocol {
 
     async fn get_capabilities(&self) -> Result<ProtocolCapabilities, Layer2Error> {
         Ok(ProtocolCapabilities {
-            supports_assets: true,
+            asset_kinds: [AssetKind::Fungible, AssetKind::NonFungible].into(),
             supports_smart_contracts: true,
             supports_privacy: false,
-            max_transaction_size: 100_000,
+            max_sizes: [
+                (Layer2Operation::Transaction, 100_000),
+                (Layer2Operation::AssetIssuance, 100_000),
+                (Layer2Operation::AssetTransfer, 100_000),
+                (Layer2Operation::ContractCall, 100_000),
+            ]
+            .into(),
+            proof_types: ["stacks_proof".to_string()].into(),
             fee_estimation: true,
         })
     }
@@ -13,7 +13,7 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::layer2::{
-    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Protocol, Proof,
+    AssetParams, AssetTransfer, FeeEstimate, Layer2Error, Layer2Operation, Layer2Protocol, Proof,
     ProtocolCapabilities, ProtocolHealth, ProtocolState, TransactionResult, TransactionStatus,
     TransferResult, ValidationResult, VerificationResult,
 };
@@ -294,10 +294,15 @@ impl Layer2Protocol for StateChannelsProtocol {
 
     async fn get_capabilities(&self) -> Result<ProtocolCapabilities, Layer2Error> {
         Ok(ProtocolCapabilities {
-            supports_assets: false,
+            asset_kinds: [].into(),
             supports_smart_contracts: true,
             supports_privacy: true,
-            max_transaction_size: 1000000,
+            max_sizes: [
+                (Layer2Operation::Transaction, 1_000_000),
+                (Layer2Operation::ContractCall, 1_000_000),
+            ]
+            .into(),
+            proof_types: ["state_channel".to_string()].into(),
             fee_estimation: true,
         })
     }
@@ -40,6 +40,8 @@
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
 
 pub mod api;
 pub mod bip;
@@ -55,6 +57,7 @@ pub mod handlers;
 pub mod infrastructure;
 pub mod install;
 pub mod layer2;
+pub mod logging;
 pub mod ml;
 #[cfg(any(feature = "ffi", feature = "mobile"))]
 pub mod mobile;
@@ -73,6 +76,10 @@ pub mod web5; // Export storage (persistent, decentralized, router)
 pub mod analytics;
 pub mod security_ml;
 
+// UniFFI scaffolding for the Kotlin and Swift bindings in `mobile::bindings`
+#[cfg(feature = "mobile")]
+uniffi::setup_scaffolding!();
+
 // Hardware optimization module
 pub mod hardware_optimization {
     use std::collections::HashMap;
@@ -249,6 +256,7 @@ pub use crate::bitcoin::adapters::BitcoinAdapter;
 #[cfg(feature = "rust-bitcoin")]
 pub use crate::bitcoin::interface::BitcoinInterface;
 pub use crate::dao::DaoLevel;
+pub use crate::logging::init_logging;
 pub use crate::types::compliance::*;
 
 #[cfg(feature = "hsm")]
@@ -272,6 +280,7 @@ pub enum AnyaError {
     PerformanceError(String),
     Analytics(String),
     Security(String),
+    Cancelled(String),
 }
 
 impl fmt::Display for AnyaError {
@@ -290,6 +299,7 @@ impl fmt::Display for AnyaError {
             AnyaError::PerformanceError(msg) => write!(f, "Performance error: {msg}"),
             AnyaError::Analytics(msg) => write!(f, "Analytics error: {msg}"),
             AnyaError::Security(msg) => write!(f, "Security error: {msg}"),
+            AnyaError::Cancelled(msg) => write!(f, "Cancelled: {msg}"),
         }
     }
 }
@@ -323,8 +333,11 @@ impl From<serde_json::Error> for AnyaError {
 
 pub type AnyaResult<T> = Result<T, AnyaError>;
 
-#[derive(Debug, Clone, Default)]
+/// Top-level configuration, loaded with [`AnyaConfig::from_file`]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct AnyaConfig {
+    pub node_config: config::NodeConfig,
     pub ml_config: ml::MLConfig,
     pub web5_config: web5::Web5Config,
     #[cfg(feature = "hsm")]
@@ -332,29 +345,77 @@ pub struct AnyaConfig {
     #[cfg(not(feature = "hsm"))]
     pub bitcoin_config: crate::security::hsm_shim::HsmConfig,
     pub dao_config: dao::DAOConfig,
+    pub deployment: crate::core::DeploymentMode,
+    pub runtime: config::RuntimeConfig,
+    /// Fail [`AnyaCore::new`] when any enabled subsystem fails to start
+    ///
+    /// When false, the failure is reported as a degraded component in
+    /// [`SystemStatus`] and the remaining subsystems keep running.
+    pub strict_init: bool,
 }
 
+impl Default for AnyaConfig {
+    fn default() -> Self {
+        Self {
+            node_config: Default::default(),
+            ml_config: Default::default(),
+            web5_config: Default::default(),
+            bitcoin_config: Default::default(),
+            dao_config: Default::default(),
+            deployment: Default::default(),
+            runtime: Default::default(),
+            strict_init: true,
+        }
+    }
+}
+
+/// Entry point tying the ML, Web5 and DAO subsystems together
+///
+/// Cloning is cheap: the subsystems are shared behind `Arc`, so an `AnyaCore`
+/// can live in web framework state and serve concurrent request handlers.
+#[derive(Clone)]
 pub struct AnyaCore {
-    pub ml_system: Option<ml::MLSystem>,
-    pub web5_manager: Option<web5::Web5Manager>,
-    pub dao_manager: Option<dao::DAOManager>,
+    pub ml_system: Option<Arc<ml::MLSystem>>,
+    pub web5_manager: Option<Arc<web5::Web5Manager>>,
+    pub dao_manager: Option<Arc<dao::DAOManager>>,
+    pub deployment: Arc<crate::core::DeploymentController>,
+    /// Subsystems that were enabled but failed to start, with the reason
+    init_errors: Arc<HashMap<&'static str, String>>,
 }
 
 impl AnyaCore {
     pub fn new(config: AnyaConfig) -> AnyaResult<Self> {
+        let deployment = crate::core::DeploymentController::new(config.deployment);
+        let mut init_errors = HashMap::new();
+        let mut degrade = |component: &'static str, err: AnyaError| {
+            if config.strict_init {
+                return Err(err);
+            }
+            log::warn!("{component} subsystem failed to initialize: {err}");
+            init_errors.insert(component, err.to_string());
+            Ok(())
+        };
+
         let ml_system = if config.ml_config.enabled {
-            // For now, use a blocking approach - this needs to be refactored later
-            let rt = tokio::runtime::Runtime::new()
-                .map_err(|e| AnyaError::ML(format!("Failed to create runtime: {e}")))?;
-            Some(rt.block_on(ml::MLSystem::new(config.ml_config))?)
+            let ml_config = config.ml_config;
+            match block_on(|| ml::MLSystem::new(ml_config)).and_then(|ml| ml) {
+                Ok(ml) => Some(Arc::new(ml)),
+                Err(e) => {
+                    degrade("ml", e)?;
+                    None
+                }
+            }
         } else {
             None
         };
 
         let web5_manager = if config.web5_config.enabled {
             match web5::Web5Manager::new(config.web5_config) {
-                Ok(manager) => Some(manager),
-                Err(e) => return Err(AnyaError::Web5(e.to_string())),
+                Ok(manager) => Some(Arc::new(manager)),
+                Err(e) => {
+                    degrade("web5", AnyaError::Web5(e.to_string()))?;
+                    None
+                }
             }
         } else {
             None
@@ -362,11 +423,13 @@ impl AnyaCore {
 
         let dao_manager = if config.dao_config.enabled {
             match dao::DAOManager::new(config.dao_config) {
-                Ok(manager) => Some(manager),
+                Ok(manager) => Some(Arc::new(manager)),
                 Err(e) => {
-                    return Err(AnyaError::Custom(format!(
-                        "Failed to initialize DAO manager: {e}"
-                    )))
+                    degrade(
+                        "dao",
+                        AnyaError::Custom(format!("Failed to initialize DAO manager: {e}")),
+                    )?;
+                    None
                 }
             }
         } else {
@@ -377,6 +440,8 @@ impl AnyaCore {
             ml_system,
             web5_manager,
             dao_manager,
+            deployment: Arc::new(deployment),
+            init_errors: Arc::new(init_errors),
         })
     }
 
@@ -384,11 +449,50 @@ impl AnyaCore {
         Self::new(AnyaConfig::default())
     }
 
+    /// Serve a request with `primary`, or with `fallback` if the deployment
+    /// mode routes it away from this node
+    pub fn dispatch<T, E>(
+        &self,
+        primary: impl FnOnce() -> Result<T, E>,
+        fallback: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        self.deployment.dispatch(primary, fallback)
+    }
+
+    /// Promote a canary or blue-green deployment to take all traffic
+    pub fn promote(&self) -> AnyaResult<()> {
+        self.deployment.promote()
+    }
+
+    /// Roll back a canary or blue-green deployment, sending all traffic to the fallback
+    pub fn rollback(&self) -> AnyaResult<()> {
+        self.deployment.rollback()
+    }
+
+    fn component_status(&self, name: &'static str, operational: bool) -> ComponentStatus {
+        ComponentStatus {
+            name: name.to_string(),
+            operational,
+            health_score: if operational { 1.0 } else { 0.0 },
+            reason: self.init_errors.get(name).cloned(),
+        }
+    }
+
+    /// Whether at least one subsystem is up
     pub fn is_operational(&self) -> bool {
         self.ml_system.is_some() || self.web5_manager.is_some() || self.dao_manager.is_some()
     }
 
+    /// Status of every subsystem, for callers outside async code
+    ///
+    /// Safe to call from inside a Tokio runtime as well, but async callers
+    /// should prefer [`AnyaCore::get_status_async`], which does not block.
     pub fn get_status(&self) -> AnyaResult<SystemStatus> {
+        block_on(|| self.get_status_async())?
+    }
+
+    /// Status of every subsystem
+    pub async fn get_status_async(&self) -> AnyaResult<SystemStatus> {
         let mut status = SystemStatus {
             ml_enabled: self.ml_system.is_some(),
             web5_enabled: self.web5_manager.is_some(),
@@ -399,39 +503,54 @@ impl AnyaCore {
         };
 
         if let Some(ml_system) = &self.ml_system {
-            // Use blocking approach for async method
-            let rt = tokio::runtime::Runtime::new()
-                .map_err(|e| AnyaError::ML(format!("Failed to create runtime: {e}")))?;
-            let health_metrics = rt.block_on(ml_system.get_model_health_metrics());
+            let health_metrics = ml_system.get_model_health_metrics().await;
             status.metrics.insert("ml".to_string(), health_metrics);
         }
-
-        status.component_status.push(ComponentStatus {
-            name: "ml".to_string(),
-            operational: self.ml_system.is_some(),
-            health_score: if self.ml_system.is_some() { 1.0 } else { 0.0 },
-        });
-
-        status.component_status.push(ComponentStatus {
-            name: "web5".to_string(),
-            operational: self.web5_manager.is_some(),
-            health_score: if self.web5_manager.is_some() {
-                1.0
-            } else {
-                0.0
-            },
-        });
-
-        status.component_status.push(ComponentStatus {
-            name: "dao".to_string(),
-            operational: self.dao_manager.is_some(),
-            health_score: if self.dao_manager.is_some() { 1.0 } else { 0.0 },
-        });
+        status.metrics.insert(
+            "deployment".to_string(),
+            self.deployment.metrics().to_status_metrics(),
+        );
+
+        status
+            .component_status
+            .push(self.component_status("ml", self.ml_system.is_some()));
+        status
+            .component_status
+            .push(self.component_status("web5", self.web5_manager.is_some()));
+        status
+            .component_status
+            .push(self.component_status("dao", self.dao_manager.is_some()));
 
         Ok(status)
     }
 }
 
+/// Run the future `make` returns to completion from synchronous code
+///
+/// Blocking a Tokio worker on a nested runtime panics, so when called from
+/// inside a runtime the future runs on a scoped thread with its own runtime.
+fn block_on<F: Future>(make: impl FnOnce() -> F + Send) -> AnyaResult<F::Output>
+where
+    F::Output: Send,
+{
+    let run = move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| AnyaError::System(format!("Failed to create runtime: {e}")))?;
+        Ok(rt.block_on(make()))
+    };
+    if tokio::runtime::Handle::try_current().is_err() {
+        return run();
+    }
+    std::thread::scope(|scope| {
+        scope
+            .spawn(run)
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct SystemStatus {
     pub ml_enabled: bool,
@@ -447,12 +566,54 @@ pub struct ComponentStatus {
     pub name: String,
     pub operational: bool,
     pub health_score: f64,
+    /// Why the component is not operational, if it failed to initialize
+    pub reason: Option<String>,
 }
 
 pub fn version() -> &'static str {
     env!("CARGO_PKG_VERSION")
 }
 
+/// Build provenance of the running binary
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BuildInfo {
+    /// Crate version
+    pub version: &'static str,
+    /// Git commit the binary was built from, suffixed `-dirty` for local changes
+    pub git_commit: &'static str,
+    /// Build time (RFC 3339), taken from `SOURCE_DATE_EPOCH` when set
+    pub build_time: String,
+    /// Compiler used for the build
+    pub rustc_version: &'static str,
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (commit {}, built {}, {})",
+            self.version, self.git_commit, self.build_time, self.rustc_version
+        )
+    }
+}
+
+/// Version, commit, build time and compiler captured by the build script
+pub fn build_info() -> BuildInfo {
+    let build_time = env!("ANYA_BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| env!("ANYA_BUILD_TIMESTAMP").to_string());
+
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: env!("ANYA_GIT_COMMIT"),
+        build_time,
+        rustc_version: env!("ANYA_RUSTC_VERSION"),
+    }
+}
+
 #[cfg(feature = "bitcoin")]
 pub mod integration {
     pub fn bitcoin_enabled() -> bool {
@@ -478,11 +639,99 @@ mod tests {
         assert!(config.dao_config.enabled);
     }
 
+    #[test]
+    fn test_canary_deployment_dispatch() {
+        let mut config = AnyaConfig::default();
+        config.ml_config.enabled = false;
+        config.web5_config.enabled = false;
+        config.dao_config.enabled = false;
+        config.deployment = crate::core::DeploymentMode::Canary {
+            traffic_percent: 20,
+        };
+        let anya = AnyaCore::new(config).unwrap();
+
+        let handled = (0..50)
+            .filter(|_| anya.dispatch(|| Ok::<_, ()>(true), || Ok(false)).unwrap())
+            .count();
+        assert_eq!(handled, 10);
+
+        let status = anya.get_status().unwrap();
+        assert_eq!(status.metrics["deployment"]["canary"]["fallback"], 40.0);
+
+        anya.promote().unwrap();
+        assert!(anya.dispatch(|| Ok::<_, ()>(true), || Ok(false)).unwrap());
+        assert!(anya.rollback().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_status_from_concurrent_tasks() {
+        let mut config = AnyaConfig::default();
+        config.ml_config.enabled = false;
+        let anya = AnyaCore::new(config).unwrap();
+
+        let tasks: Vec<_> = (0..16)
+            .map(|i| {
+                let anya = anya.clone();
+                tokio::spawn(async move {
+                    assert!(anya.is_operational());
+                    let status = if i % 2 == 0 {
+                        anya.get_status_async().await.unwrap()
+                    } else {
+                        anya.get_status().unwrap()
+                    };
+                    assert!(status.web5_enabled && status.dao_enabled);
+                    status.component_status.len()
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), 3);
+        }
+
+        // Nested runtimes are avoided when blocking from inside one
+        assert_eq!(block_on(|| async { 42 }).unwrap(), 42);
+    }
+
+    #[test]
+    fn test_failed_subsystem_degrades_without_strict_init() {
+        let mut config = AnyaConfig::default();
+        config.ml_config.enabled = false;
+        config.web5_config.did_method = String::new();
+        assert!(matches!(
+            AnyaCore::new(config.clone()),
+            Err(AnyaError::Web5(_))
+        ));
+
+        config.strict_init = false;
+        let anya = AnyaCore::new(config).unwrap();
+        assert!(anya.web5_manager.is_none());
+        assert!(anya.is_operational());
+
+        let status = anya.get_status().unwrap();
+        let web5 = &status.component_status[1];
+        assert_eq!(web5.name, "web5");
+        assert!(!web5.operational);
+        assert!(web5.reason.as_deref().unwrap().contains("DID method"));
+        let dao = &status.component_status[2];
+        assert!(dao.operational && dao.reason.is_none());
+        // Disabled subsystems are down but not degraded
+        assert!(status.component_status[0].reason.is_none());
+    }
+
     #[test]
     fn test_error_display() {
         let err = AnyaError::ML("test error".to_string());
         assert_eq!(err.to_string(), "ML error: test error");
     }
+
+    #[test]
+    fn test_build_info_populated() {
+        let info = build_info();
+        assert_eq!(info.version, version());
+        assert!(!info.git_commit.is_empty());
+        assert!(!info.build_time.is_empty());
+        assert!(!info.rustc_version.is_empty());
+    }
 }
 
 pub fn init() {
@@ -0,0 +1,183 @@
+//! Process-wide logging setup
+//!
+//! Every binary initializes `tracing` through [`init_logging`] so that log
+//! output looks the same regardless of the entry point. The output format is
+//! selected with the `ANYA_LOG_FORMAT` environment variable (`pretty` or
+//! `json`) and the filter with `RUST_LOG`. The filter can be replaced later
+//! with [`set_log_level`].
+
+use crate::{AnyaError, AnyaResult};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tracing::Level;
+use tracing_subscriber::{reload, EnvFilter};
+
+/// Environment variable selecting the log output format
+pub const LOG_FORMAT_ENV: &str = "ANYA_LOG_FORMAT";
+
+type ReloadFilter = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>;
+
+/// Replaces the filter of the subscriber installed by [`init_logging_with`]
+static RELOAD_FILTER: OnceLock<ReloadFilter> = OnceLock::new();
+
+/// Log output format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable, one line per event
+    #[default]
+    Pretty,
+    /// Structured JSON, one object per line, for log aggregation
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = AnyaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "pretty" | "text" | "human" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            other => Err(AnyaError::InvalidInput(format!(
+                "Unknown log format '{other}', expected 'pretty' or 'json'"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Pretty => write!(f, "pretty"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Logging configuration
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    /// Output format
+    pub format: LogFormat,
+    /// Level used when `RUST_LOG` is not set
+    pub default_level: Level,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            default_level: Level::INFO,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Build a configuration from `ANYA_LOG_FORMAT`
+    ///
+    /// Unset or unrecognised values fall back to the pretty format.
+    pub fn from_env() -> Self {
+        let format = std::env::var(LOG_FORMAT_ENV)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default();
+        Self {
+            format,
+            ..Self::default()
+        }
+    }
+
+    /// Override the level used when `RUST_LOG` is not set
+    pub fn with_default_level(mut self, level: Level) -> Self {
+        self.default_level = level;
+        self
+    }
+}
+
+/// Initialize logging from the environment
+pub fn init_logging() -> AnyaResult<()> {
+    init_logging_with(&LoggingConfig::from_env())
+}
+
+/// Initialize logging with an explicit configuration
+///
+/// JSON events carry the timestamp, level, module target, event fields and
+/// the fields of the current span and its parents. Fails if a global
+/// subscriber has already been installed.
+pub fn init_logging_with(config: &LoggingConfig) -> AnyaResult<()> {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(config.default_level.as_str()));
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(true);
+
+    let result = match config.format {
+        LogFormat::Pretty => {
+            let builder = builder.with_filter_reloading();
+            let handle = builder.reload_handle();
+            builder.try_init().map(|()| store_reload_handle(handle))
+        }
+        LogFormat::Json => {
+            let builder = builder
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .with_filter_reloading();
+            let handle = builder.reload_handle();
+            builder.try_init().map(|()| store_reload_handle(handle))
+        }
+    };
+
+    result.map_err(|e| AnyaError::System(format!("Failed to initialize logging: {e}")))
+}
+
+fn store_reload_handle<S: 'static>(handle: reload::Handle<EnvFilter, S>) {
+    let _ = RELOAD_FILTER.set(Box::new(move |filter| handle.reload(filter)));
+}
+
+/// Parse a `RUST_LOG` style filter directive
+pub fn parse_filter(directive: &str) -> AnyaResult<EnvFilter> {
+    EnvFilter::try_new(directive)
+        .map_err(|e| AnyaError::InvalidInput(format!("Invalid log filter '{directive}': {e}")))
+}
+
+/// Replace the log filter of the running process, overriding `RUST_LOG`
+///
+/// Does nothing if logging was not initialized by [`init_logging_with`].
+pub fn set_log_level(directive: &str) -> AnyaResult<()> {
+    let filter = parse_filter(directive)?;
+    match RELOAD_FILTER.get() {
+        Some(reload) => reload(filter)
+            .map_err(|e| AnyaError::System(format!("Failed to change log filter: {e}"))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_format_parsing() {
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!(" JSON ".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!("pretty".parse::<LogFormat>().unwrap(), LogFormat::Pretty);
+        assert_eq!("text".parse::<LogFormat>().unwrap(), LogFormat::Pretty);
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_logging_config_default_level() {
+        let config = LoggingConfig::default().with_default_level(Level::DEBUG);
+        assert_eq!(config.format, LogFormat::Pretty);
+        assert_eq!(config.default_level, Level::DEBUG);
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert!(parse_filter("info").is_ok());
+        assert!(parse_filter("anya_core=debug,warn").is_ok());
+        assert!(parse_filter("anya_core=loud").is_err());
+    }
+}
@@ -1,18 +1,14 @@
+use anya_core::logging::{init_logging_with, LoggingConfig};
 use anya_core::{AnyaConfig, AnyaCore, AnyaError, AnyaResult};
 use tokio::net::TcpListener;
 use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
 
 #[tokio::main]
 async fn main() -> AnyaResult<()> {
     // Initialize logging
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::DEBUG)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber)
-        .map_err(|e| AnyaError::System(e.to_string()))?;
+    init_logging_with(&LoggingConfig::from_env().with_default_level(Level::DEBUG))?;
 
-    info!("Starting Anya Core system...");
+    info!("Starting Anya Core {}", anya_core::build_info());
 
     // Initialize Anya with default configuration
     let config = AnyaConfig::default();
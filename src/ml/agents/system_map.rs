@@ -10,7 +10,7 @@ use dashmap::{DashMap, DashSet};
 use once_cell::sync::Lazy;
 use rayon::iter::{ParallelBridge, ParallelIterator};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{
     atomic::{AtomicU32, AtomicU64, Ordering},
     Arc, RwLock,
@@ -37,6 +37,8 @@ pub struct SystemIndex {
     pub version: AtomicU32,
     /// Rust-specific metrics
     pub rust_metrics: DashMap<String, RustCodeMetrics>,
+    /// Components each registered component depends on
+    pub component_dependencies: DashMap<String, Vec<String>>,
 }
 
 impl Default for SystemIndex {
@@ -49,6 +51,7 @@ impl Default for SystemIndex {
             last_updated: AtomicU64::new(0),
             version: AtomicU32::new(0),
             rust_metrics: DashMap::new(),
+            component_dependencies: DashMap::new(),
         }
     }
 }
@@ -327,6 +330,99 @@ impl SystemIndexManager {
         Ok(())
     }
 
+    /// Register a component and the components it depends on
+    ///
+    /// Fails without changing the index if the dependencies would form a cycle.
+    pub async fn register_component_with_dependencies(
+        &self,
+        component_id: String,
+        path: String,
+        depends_on: Vec<String>,
+    ) -> Result<(), AgentError> {
+        {
+            let index = self.index.write().map_err(|_| {
+                AgentError::InternalError(
+                    "Failed to acquire write lock on system index".to_string(),
+                )
+            })?;
+
+            for dependency in &depends_on {
+                let mut seen = HashSet::new();
+                if let Some(path) = dependency_path(
+                    &index.component_dependencies,
+                    dependency,
+                    &component_id,
+                    &mut seen,
+                ) {
+                    return Err(AgentError::ProcessingError(format!(
+                        "Dependency cycle: {component_id} -> {}",
+                        path.join(" -> ")
+                    )));
+                }
+            }
+            index
+                .component_dependencies
+                .insert(component_id.clone(), depends_on);
+        }
+
+        self.register_component(component_id, path).await
+    }
+
+    /// Components in startup order, each after all of its dependencies
+    pub async fn startup_order(&self) -> Result<Vec<String>, AgentError> {
+        let index = self.index.read().map_err(|_| {
+            AgentError::InternalError("Failed to acquire read lock on system index".to_string())
+        })?;
+        Ok(topological_order(&index.component_dependencies))
+    }
+
+    /// Components in shutdown order, each before all of its dependencies
+    pub async fn shutdown_order(&self) -> Result<Vec<String>, AgentError> {
+        let mut order = self.startup_order().await?;
+        order.reverse();
+        Ok(order)
+    }
+
+    /// Effective status of each component given its dependencies
+    ///
+    /// An active component is reported as degraded when any of its
+    /// dependencies, directly or transitively, is not active. Components
+    /// without a reported state are `Unknown`.
+    pub async fn health_with_dependencies(
+        &self,
+        states: &HashMap<String, ComponentState>,
+    ) -> Result<HashMap<String, ComponentStatus>, AgentError> {
+        let index = self.index.read().map_err(|_| {
+            AgentError::InternalError("Failed to acquire read lock on system index".to_string())
+        })?;
+
+        let mut health: HashMap<String, ComponentStatus> = HashMap::new();
+        for component_id in topological_order(&index.component_dependencies) {
+            let own = states
+                .get(&component_id)
+                .map(|state| state.status.clone())
+                .unwrap_or_default();
+            let dependencies_healthy = index
+                .component_dependencies
+                .get(&component_id)
+                .map(|dependencies| {
+                    dependencies
+                        .iter()
+                        .all(|dependency| health.get(dependency) == Some(&ComponentStatus::Active))
+                })
+                .unwrap_or(true);
+
+            let status = if own == ComponentStatus::Active && !dependencies_healthy {
+                ComponentStatus::Degraded
+            } else {
+                own
+            };
+            health.insert(component_id, status);
+        }
+
+        Ok(health)
+    }
+
     /// Register a model in the index
     pub async fn register_model(&self, model_id: String, path: String) -> Result<(), AgentError> {
         let index = self.index.read().map_err(|_| {
@@ -688,6 +784,66 @@ impl MapProvider for SystemMapManager {
     }
 }
 
+/// Dependency chain from `from` to `to`, inclusive of both
+fn dependency_path(
+    dependencies: &DashMap<String, Vec<String>>,
+    from: &str,
+    to: &str,
+    seen: &mut HashSet<String>,
+) -> Option<Vec<String>> {
+    if from == to {
+        return Some(vec![to.to_string()]);
+    }
+    if !seen.insert(from.to_string()) {
+        return None;
+    }
+    let next = dependencies
+        .get(from)
+        .map(|entry| entry.value().clone())
+        .unwrap_or_default();
+    next.iter().find_map(|dependency| {
+        dependency_path(dependencies, dependency, to, seen).map(|mut path| {
+            path.insert(0, from.to_string());
+            path
+        })
+    })
+}
+
+/// Components and their dependencies, dependencies first
+fn topological_order(dependencies: &DashMap<String, Vec<String>>) -> Vec<String> {
+    fn visit(
+        dependencies: &DashMap<String, Vec<String>>,
+        component_id: &str,
+        visited: &mut HashSet<String>,
+        order: &mut Vec<String>,
+    ) {
+        if !visited.insert(component_id.to_string()) {
+            return;
+        }
+        let next = dependencies
+            .get(component_id)
+            .map(|entry| entry.value().clone())
+            .unwrap_or_default();
+        for dependency in next {
+            visit(dependencies, &dependency, visited, order);
+        }
+        order.push(component_id.to_string());
+    }
+
+    let mut component_ids: Vec<String> = dependencies
+        .iter()
+        .map(|entry| entry.key().clone())
+        .collect();
+    component_ids.sort();
+
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+    for component_id in component_ids {
+        visit(dependencies, &component_id, &mut visited, &mut order);
+    }
+    order
+}
+
 // Stub implementations for missing analysis functions
 fn calculate_cyclomatic_complexity(_syntax: &syn::File) -> f32 {
     1.0 // Default complexity
@@ -713,6 +869,80 @@ fn calculate_protocol_adherence(_content: &str) -> f32 {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn state(id: &str, status: ComponentStatus) -> (String, ComponentState) {
+        (
+            id.to_string(),
+            ComponentState {
+                id: id.to_string(),
+                status,
+                health: 1.0,
+                last_updated: 0,
+                properties: HashMap::new(),
+            },
+        )
+    }
+
+    async fn wallet_chain() -> SystemIndexManager {
+        let manager = SystemIndexManager::new();
+        for (id, depends_on) in [
+            ("p2p", vec![]),
+            ("network", vec!["p2p"]),
+            ("wallet", vec!["network"]),
+            ("dao", vec![]),
+        ] {
+            manager
+                .register_component_with_dependencies(
+                    id.to_string(),
+                    format!("src/{id}"),
+                    depends_on.into_iter().map(String::from).collect(),
+                )
+                .await
+                .unwrap();
+        }
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_leaf_failure_degrades_dependents() {
+        let manager = wallet_chain().await;
+        let states = HashMap::from([
+            state("p2p", ComponentStatus::Offline),
+            state("network", ComponentStatus::Active),
+            state("wallet", ComponentStatus::Active),
+            state("dao", ComponentStatus::Active),
+        ]);
+
+        let health = manager.health_with_dependencies(&states).await.unwrap();
+        assert_eq!(health["p2p"], ComponentStatus::Offline);
+        assert_eq!(health["network"], ComponentStatus::Degraded);
+        assert_eq!(health["wallet"], ComponentStatus::Degraded);
+        assert_eq!(health["dao"], ComponentStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn test_dependency_order_and_cycles() {
+        let manager = wallet_chain().await;
+        let startup = manager.startup_order().await.unwrap();
+        let position = |id: &str| startup.iter().position(|c| c == id).unwrap();
+        assert!(position("p2p") < position("network"));
+        assert!(position("network") < position("wallet"));
+
+        let shutdown = manager.shutdown_order().await.unwrap();
+        assert_eq!(shutdown.first().map(String::as_str), Some("wallet"));
+
+        let result = manager
+            .register_component_with_dependencies(
+                "p2p".to_string(),
+                "src/p2p".to_string(),
+                vec!["wallet".to_string()],
+            )
+            .await;
+        assert!(matches!(result, Err(AgentError::ProcessingError(_))));
+        // The rejected edge was not recorded
+        assert_eq!(manager.startup_order().await.unwrap(), startup);
+    }
 
     #[tokio::test]
     async fn test_system_index_operations() {
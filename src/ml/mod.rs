@@ -52,8 +52,17 @@ pub use planning::{Goal, Plan, Planner, PlanningEngine, Reasoner};
 pub mod orchestration;
 pub use orchestration::{WorkflowBuilder, WorkflowDefinition, WorkflowEngine};
 
+// Transaction anomaly screening
+pub mod screening;
+pub use screening::{AnomalyModel, AnomalyScore, TransactionScreener, TxFeatures};
+
+// Secure aggregation of federated learning updates
+pub mod secure_aggregation;
+pub use secure_aggregation::{SecureAggregationClient, SecureAggregationConfig, SecureAggregator};
+
 /// Configuration options for ML functionality
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MLConfig {
     /// Whether ML functionality is enabled
     pub enabled: bool,
@@ -84,6 +93,7 @@ pub struct MLSystem {
     config: MLConfig,
     service: MLService,
     models: HashMap<String, Arc<Mutex<dyn MLModel>>>,
+    screener: Option<TransactionScreener>,
 }
 
 impl std::fmt::Debug for MLSystem {
@@ -92,6 +102,7 @@ impl std::fmt::Debug for MLSystem {
             .field("config", &self.config)
             .field("service", &"<MLService>")
             .field("models", &format!("{} models", self.models.len()))
+            .field("screener", &self.screener)
             .finish()
     }
 }
@@ -128,6 +139,7 @@ impl MLSystem {
                 config,
                 service,
                 models: HashMap::new(),
+                screener: None,
             });
         }
 
@@ -163,6 +175,7 @@ impl MLSystem {
             config,
             service: ml_service,
             models: HashMap::new(),
+            screener: None,
         })
     }
 
@@ -183,6 +196,30 @@ impl MLSystem {
         self.models.get(name).cloned()
     }
 
+    /// Use `screener` for [`MLSystem::score_transaction`]
+    pub fn set_transaction_screener(&mut self, screener: TransactionScreener) {
+        self.screener = Some(screener);
+    }
+
+    /// Screen transactions with the anomaly model saved at `path`
+    ///
+    /// Scores above `threshold` are flagged.
+    pub fn load_anomaly_model(&mut self, path: impl AsRef<Path>, threshold: f64) -> AnyaResult<()> {
+        let screener = TransactionScreener::from_file(path)?.with_threshold(threshold)?;
+        self.set_transaction_screener(screener);
+        Ok(())
+    }
+
+    /// Anomaly score of a transaction, flagged if above the screener threshold
+    ///
+    /// Fails if no anomaly model has been loaded.
+    pub fn score_transaction(&self, features: TxFeatures) -> AnyaResult<AnomalyScore> {
+        self.screener
+            .as_ref()
+            .ok_or_else(|| AnyaError::ML("No transaction anomaly model loaded".to_string()))?
+            .screen(&features)
+    }
+
     /// Get health metrics for the ML system
     pub async fn get_health_metrics(&self) -> HashMap<String, f64> {
         let mut metrics = HashMap::new();
@@ -0,0 +1,413 @@
+//! Transaction anomaly screening
+//!
+//! Scores transactions with a logistic regression over features of their
+//! shape: fee rate, input and output counts, address reuse and how output
+//! value is distributed. Peel chains (one input, a small payment and a change
+//! output carrying almost all of the value) and dusting (many outputs below
+//! the dust limit sent to previously used addresses) stand out on these
+//! features. Model weights are trained offline with [`AnomalyModel::train`]
+//! and stored as JSON.
+
+use crate::{AnyaError, AnyaResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Number of model inputs derived from [`TxFeatures`]
+pub const FEATURE_COUNT: usize = 6;
+
+/// Default score above which a transaction is flagged
+pub const DEFAULT_ANOMALY_THRESHOLD: f64 = 0.8;
+
+/// Shape of a transaction as seen by the anomaly model
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TxFeatures {
+    /// Fee rate in sat/vB
+    pub fee_rate: f64,
+    /// Number of inputs
+    pub input_count: u32,
+    /// Number of outputs
+    pub output_count: u32,
+    /// Outputs paying to an address that already received funds
+    pub reused_outputs: u32,
+    /// Outputs below the dust limit of their script type
+    pub dust_outputs: u32,
+    /// Fraction of the total output value held by the largest output
+    pub largest_output_share: f64,
+}
+
+impl TxFeatures {
+    /// Model inputs before standardisation
+    ///
+    /// Counts and the fee rate are log-scaled so a few very large
+    /// transactions do not dominate training.
+    fn to_vector(self) -> AnyaResult<[f64; FEATURE_COUNT]> {
+        if !self.fee_rate.is_finite() || self.fee_rate < 0.0 {
+            return Err(AnyaError::InvalidInput(format!(
+                "invalid fee rate {} sat/vB",
+                self.fee_rate
+            )));
+        }
+        if self.input_count == 0 || self.output_count == 0 {
+            return Err(AnyaError::InvalidInput(
+                "a transaction needs at least one input and one output".to_string(),
+            ));
+        }
+        if self.reused_outputs > self.output_count || self.dust_outputs > self.output_count {
+            return Err(AnyaError::InvalidInput(format!(
+                "{} reused and {} dust outputs out of {}",
+                self.reused_outputs, self.dust_outputs, self.output_count
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.largest_output_share) {
+            return Err(AnyaError::InvalidInput(format!(
+                "largest output share {} outside 0..=1",
+                self.largest_output_share
+            )));
+        }
+
+        let outputs = f64::from(self.output_count);
+        Ok([
+            self.fee_rate.ln_1p(),
+            f64::from(self.input_count).ln_1p(),
+            outputs.ln_1p(),
+            f64::from(self.reused_outputs) / outputs,
+            f64::from(self.dust_outputs) / outputs,
+            self.largest_output_share,
+        ])
+    }
+}
+
+/// Logistic regression over standardised [`TxFeatures`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnomalyModel {
+    /// Weight per feature
+    pub weights: Vec<f64>,
+    pub bias: f64,
+    /// Per-feature mean of the training set
+    pub means: Vec<f64>,
+    /// Per-feature standard deviation of the training set
+    pub scales: Vec<f64>,
+}
+
+impl AnomalyModel {
+    /// Fit a model to labelled samples, `true` marking an anomaly
+    ///
+    /// Runs `epochs` rounds of full-batch gradient descent on the log loss.
+    pub fn train(
+        samples: &[(TxFeatures, bool)],
+        epochs: usize,
+        learning_rate: f64,
+    ) -> AnyaResult<Self> {
+        if !samples.iter().any(|(_, anomalous)| *anomalous)
+            || samples.iter().all(|(_, anomalous)| *anomalous)
+        {
+            return Err(AnyaError::ML(
+                "training needs both normal and anomalous samples".to_string(),
+            ));
+        }
+        let inputs = samples
+            .iter()
+            .map(|(features, _)| features.to_vector())
+            .collect::<AnyaResult<Vec<_>>>()?;
+
+        let count = inputs.len() as f64;
+        let mut means = vec![0.0; FEATURE_COUNT];
+        let mut scales = vec![0.0; FEATURE_COUNT];
+        for i in 0..FEATURE_COUNT {
+            means[i] = inputs.iter().map(|x| x[i]).sum::<f64>() / count;
+            let variance = inputs
+                .iter()
+                .map(|x| (x[i] - means[i]).powi(2))
+                .sum::<f64>()
+                / count;
+            // A constant feature carries no signal; keep it from dividing by zero
+            scales[i] = if variance > f64::EPSILON {
+                variance.sqrt()
+            } else {
+                1.0
+            };
+        }
+
+        let mut model = Self {
+            weights: vec![0.0; FEATURE_COUNT],
+            bias: 0.0,
+            means,
+            scales,
+        };
+        let standardised: Vec<[f64; FEATURE_COUNT]> =
+            inputs.iter().map(|x| model.standardise(x)).collect();
+        for _ in 0..epochs {
+            let mut weight_gradient = [0.0; FEATURE_COUNT];
+            let mut bias_gradient = 0.0;
+            for (x, (_, anomalous)) in standardised.iter().zip(samples) {
+                let error = model.predict(x) - if *anomalous { 1.0 } else { 0.0 };
+                for (gradient, value) in weight_gradient.iter_mut().zip(x) {
+                    *gradient += error * value;
+                }
+                bias_gradient += error;
+            }
+            for (weight, gradient) in model.weights.iter_mut().zip(weight_gradient) {
+                *weight -= learning_rate * gradient / count;
+            }
+            model.bias -= learning_rate * bias_gradient / count;
+        }
+        Ok(model)
+    }
+
+    /// Load weights saved with [`AnomalyModel::save`]
+    pub fn load(path: impl AsRef<Path>) -> AnyaResult<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| AnyaError::ML(format!("Failed to read {}: {e}", path.display())))?;
+        let model: Self = serde_json::from_str(&json)
+            .map_err(|e| AnyaError::ML(format!("Invalid model in {}: {e}", path.display())))?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// Write the weights to `path` as JSON
+    pub fn save(&self, path: impl AsRef<Path>) -> AnyaResult<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AnyaError::ML(format!("Failed to serialize model: {e}")))?;
+        std::fs::write(path, json)
+            .map_err(|e| AnyaError::ML(format!("Failed to write {}: {e}", path.display())))
+    }
+
+    /// Probability in `0.0..=1.0` that the transaction is anomalous
+    pub fn score(&self, features: &TxFeatures) -> AnyaResult<f64> {
+        let input = features.to_vector()?;
+        Ok(self.predict(&self.standardise(&input)))
+    }
+
+    fn validate(&self) -> AnyaResult<()> {
+        if self.weights.len() != FEATURE_COUNT
+            || self.means.len() != FEATURE_COUNT
+            || self.scales.len() != FEATURE_COUNT
+        {
+            return Err(AnyaError::ML(format!(
+                "model must have {FEATURE_COUNT} weights, means and scales"
+            )));
+        }
+        let mut parameters = self.weights.iter().chain(&self.means).chain(&self.scales);
+        if !self.bias.is_finite() || !parameters.all(|value| value.is_finite()) {
+            return Err(AnyaError::ML("model parameters must be finite".to_string()));
+        }
+        if self.scales.iter().any(|scale| *scale <= 0.0) {
+            return Err(AnyaError::ML("model scales must be positive".to_string()));
+        }
+        Ok(())
+    }
+
+    fn standardise(&self, input: &[f64; FEATURE_COUNT]) -> [f64; FEATURE_COUNT] {
+        let mut standardised = [0.0; FEATURE_COUNT];
+        for i in 0..FEATURE_COUNT {
+            standardised[i] = (input[i] - self.means[i]) / self.scales[i];
+        }
+        standardised
+    }
+
+    fn predict(&self, input: &[f64; FEATURE_COUNT]) -> f64 {
+        let logit = self.bias
+            + self
+                .weights
+                .iter()
+                .zip(input)
+                .map(|(weight, value)| weight * value)
+                .sum::<f64>();
+        1.0 / (1.0 + (-logit).exp())
+    }
+}
+
+/// Result of screening one transaction
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnomalyScore {
+    /// Anomaly probability, 0.0 (normal) to 1.0 (anomalous)
+    pub score: f64,
+    /// Threshold the score was compared against
+    pub threshold: f64,
+    /// Whether the score is above the threshold
+    pub flagged: bool,
+}
+
+impl AnomalyScore {
+    /// Fail with [`AnyaError::LowConfidence`] if the transaction was flagged
+    pub fn require_normal(self) -> AnyaResult<Self> {
+        if self.flagged {
+            return Err(AnyaError::LowConfidence(format!(
+                "transaction anomaly score {:.3} above threshold {:.3}",
+                self.score, self.threshold
+            )));
+        }
+        Ok(self)
+    }
+}
+
+/// Anomaly model with the threshold used to flag transactions
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionScreener {
+    model: AnomalyModel,
+    threshold: f64,
+}
+
+impl TransactionScreener {
+    /// Screener flagging scores above [`DEFAULT_ANOMALY_THRESHOLD`]
+    pub fn new(model: AnomalyModel) -> Self {
+        Self {
+            model,
+            threshold: DEFAULT_ANOMALY_THRESHOLD,
+        }
+    }
+
+    /// Screener with the model saved at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> AnyaResult<Self> {
+        Ok(Self::new(AnomalyModel::load(path)?))
+    }
+
+    /// Flag scores above `threshold` instead of the default
+    pub fn with_threshold(mut self, threshold: f64) -> AnyaResult<Self> {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(AnyaError::InvalidInput(format!(
+                "anomaly threshold {threshold} outside 0..=1"
+            )));
+        }
+        self.threshold = threshold;
+        Ok(self)
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    pub fn model(&self) -> &AnomalyModel {
+        &self.model
+    }
+
+    /// Score `features` and flag them if above the threshold
+    pub fn screen(&self, features: &TxFeatures) -> AnyaResult<AnomalyScore> {
+        let score = self.model.score(features)?;
+        Ok(AnomalyScore {
+            score,
+            threshold: self.threshold,
+            flagged: score > self.threshold,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(
+        fee_rate: f64,
+        input_count: u32,
+        output_count: u32,
+        reused_outputs: u32,
+        dust_outputs: u32,
+        largest_output_share: f64,
+    ) -> TxFeatures {
+        TxFeatures {
+            fee_rate,
+            input_count,
+            output_count,
+            reused_outputs,
+            dust_outputs,
+            largest_output_share,
+        }
+    }
+
+    /// Payments with change, consolidations and batched payouts, labelled
+    /// normal, next to peel chain hops and dusting runs labelled anomalous
+    fn training_set() -> Vec<(TxFeatures, bool)> {
+        let mut samples = Vec::new();
+        for i in 0..20u32 {
+            let step = f64::from(i);
+            samples.push((
+                features(5.0 + step, 1 + i % 3, 2, 0, 0, 0.55 + step * 0.01),
+                false,
+            ));
+            samples.push((features(3.0 + step * 0.5, 5 + i, 1, 0, 0, 1.0), false));
+            samples.push((features(10.0 + step, 2, 6 + i % 5, i % 2, 0, 0.3), false));
+
+            samples.push((
+                features(1.0 + step * 0.1, 1, 2, 0, 0, 0.97 + step * 0.001),
+                true,
+            ));
+            samples.push((
+                features(1.0, 1, 40 + i * 5, 30 + i * 4, 35 + i * 4, 0.02),
+                true,
+            ));
+        }
+        samples
+    }
+
+    fn screener() -> TransactionScreener {
+        TransactionScreener::new(AnomalyModel::train(&training_set(), 2_000, 0.5).unwrap())
+    }
+
+    #[test]
+    fn test_separates_normal_from_anomalous_transactions() {
+        let screener = screener();
+        let normal = [
+            features(12.0, 2, 2, 0, 0, 0.7),
+            features(6.0, 9, 1, 0, 0, 1.0),
+            features(18.0, 2, 8, 1, 0, 0.3),
+        ];
+        let anomalous = [
+            // Peel chain hop: most of the value moves on to the next hop
+            features(1.5, 1, 2, 0, 0, 0.985),
+            // Dusting: many sub-dust outputs to addresses already in use
+            features(1.0, 1, 120, 100, 110, 0.01),
+        ];
+        for tx in &normal {
+            let score = screener.screen(tx).unwrap();
+            assert!(score.score < 0.2, "{tx:?} scored {}", score.score);
+            assert!(!score.flagged);
+            assert!(score.require_normal().is_ok());
+        }
+        for tx in &anomalous {
+            let score = screener.screen(tx).unwrap();
+            assert!(score.score > 0.8, "{tx:?} scored {}", score.score);
+            assert!(score.flagged);
+            assert!(matches!(
+                score.require_normal(),
+                Err(AnyaError::LowConfidence(_))
+            ));
+        }
+
+        // Nothing scores above a threshold of 1.0
+        let lenient = screener.clone().with_threshold(1.0).unwrap();
+        assert!(!lenient.screen(&anomalous[0]).unwrap().flagged);
+        assert!(screener.clone().with_threshold(1.5).is_err());
+        assert!(matches!(
+            screener.screen(&features(f64::NAN, 1, 2, 0, 0, 0.5)),
+            Err(AnyaError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            screener.screen(&features(1.0, 1, 2, 3, 0, 0.5)),
+            Err(AnyaError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_model_round_trips_through_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anomaly.json");
+        let screener = screener();
+        screener.model().save(&path).unwrap();
+
+        // JSON may round the last bit of a parameter, so compare scores
+        let loaded = TransactionScreener::from_file(&path).unwrap();
+        let tx = features(1.5, 1, 2, 0, 0, 0.985);
+        let (before, after) = (screener.screen(&tx).unwrap(), loaded.screen(&tx).unwrap());
+        assert!((before.score - after.score).abs() < 1e-9);
+        assert_eq!(before.flagged, after.flagged);
+
+        let mut truncated = screener.model().clone();
+        truncated.weights.pop();
+        truncated.save(&path).unwrap();
+        assert!(matches!(AnomalyModel::load(&path), Err(AnyaError::ML(_))));
+        assert!(AnomalyModel::load(dir.path().join("missing.json")).is_err());
+        assert!(AnomalyModel::train(&training_set()[..1], 10, 0.5).is_err());
+    }
+}
@@ -0,0 +1,736 @@
+//! Secure aggregation of federated learning updates
+//!
+//! Pairwise-masking protocol of Bonawitz et al., "Practical Secure Aggregation
+//! for Privacy-Preserving Machine Learning" (CCS 2017), so the server learns the
+//! sum of the client updates but none of the updates themselves.
+//!
+//! 1. Each client advertises a secp256k1 mask key.
+//! 2. Once the roster is fixed, each client splits its mask secret key and a
+//!    random self-mask seed into `threshold`-of-n Shamir shares, one per peer.
+//!    Shares are sent to peers over an authenticated, encrypted channel; the
+//!    server must not see them.
+//! 3. Each client sends its update, fixed-point encoded in `Z_2^64`, plus its
+//!    self mask, plus a mask per peer derived from their ECDH secret. For each
+//!    pair the lower id adds the mask and the higher id subtracts it, so
+//!    pairwise masks cancel in the sum.
+//! 4. The server announces which clients submitted. Each of those reveals its
+//!    shares of the other survivors' self-mask seeds and of the dropped
+//!    clients' mask keys, never both for the same client. With `threshold`
+//!    reveals the server removes the self masks of the survivors and the
+//!    uncancelled pairwise masks of the dropped clients.
+//!
+//! Client ids are the Shamir x-coordinates, so there are at most 255 clients.
+
+use crate::{AnyaError, AnyaResult};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use secp256k1::ecdh::SharedSecret;
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Client identifier, 1 to 255
+pub type ClientId = u8;
+
+/// Largest sum of encoded values, in magnitude, that decodes correctly
+const MAX_ENCODED_SUM: f64 = i64::MAX as f64;
+
+/// Parameters of one aggregation round
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecureAggregationConfig {
+    /// Reveals needed to unmask, and the minimum number of survivors
+    pub threshold: usize,
+    /// Length of every update vector
+    pub vector_len: usize,
+    /// Fractional bits of the fixed-point encoding
+    pub precision_bits: u32,
+    /// Largest magnitude accepted for an update value
+    pub max_abs_value: f64,
+}
+
+impl Default for SecureAggregationConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 2,
+            vector_len: 1,
+            precision_bits: 24,
+            max_abs_value: 1.0e6,
+        }
+    }
+}
+
+impl SecureAggregationConfig {
+    fn validate(&self) -> AnyaResult<()> {
+        if self.threshold < 2 {
+            return Err(AnyaError::InvalidInput(
+                "secure aggregation needs a threshold of at least 2".to_string(),
+            ));
+        }
+        if self.vector_len == 0 {
+            return Err(AnyaError::InvalidInput(
+                "update vectors cannot be empty".to_string(),
+            ));
+        }
+        let max_sum = f64::from(u8::MAX) * self.max_abs_value * self.scale();
+        if !self.max_abs_value.is_finite()
+            || self.max_abs_value <= 0.0
+            || max_sum >= MAX_ENCODED_SUM
+        {
+            return Err(AnyaError::InvalidInput(format!(
+                "a sum of 255 values up to {} with {} fractional bits overflows",
+                self.max_abs_value, self.precision_bits
+            )));
+        }
+        Ok(())
+    }
+
+    fn scale(&self) -> f64 {
+        2f64.powi(self.precision_bits as i32)
+    }
+
+    fn encode(&self, update: &[f64]) -> AnyaResult<Vec<u64>> {
+        if update.len() != self.vector_len {
+            return Err(AnyaError::InvalidInput(format!(
+                "update has {} values, expected {}",
+                update.len(),
+                self.vector_len
+            )));
+        }
+        update
+            .iter()
+            .map(|value| {
+                if !value.is_finite() || value.abs() > self.max_abs_value {
+                    return Err(AnyaError::InvalidInput(format!(
+                        "update value {value} outside ±{}",
+                        self.max_abs_value
+                    )));
+                }
+                Ok((value * self.scale()).round() as i64 as u64)
+            })
+            .collect()
+    }
+
+    fn decode(&self, sum: &[u64]) -> Vec<f64> {
+        sum.iter()
+            .map(|value| *value as i64 as f64 / self.scale())
+            .collect()
+    }
+}
+
+/// Mask key advertised by a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientKey {
+    pub id: ClientId,
+    pub public_key: PublicKey,
+}
+
+/// Shares of one client's secrets held by another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareBundle {
+    pub from: ClientId,
+    pub to: ClientId,
+    pub mask_key_share: [u8; 32],
+    pub self_seed_share: [u8; 32],
+}
+
+/// Masked update sent to the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaskedUpdate {
+    pub from: ClientId,
+    pub values: Vec<u64>,
+}
+
+/// Shares a surviving client reveals so the server can unmask the sum
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnmaskShares {
+    pub from: ClientId,
+    /// Shares of the self-mask seeds of surviving clients
+    pub self_seed_shares: BTreeMap<ClientId, [u8; 32]>,
+    /// Shares of the mask keys of dropped clients
+    pub mask_key_shares: BTreeMap<ClientId, [u8; 32]>,
+}
+
+/// One participant of a secure aggregation round
+pub struct SecureAggregationClient {
+    id: ClientId,
+    config: SecureAggregationConfig,
+    mask_key: SecretKey,
+    self_seed: [u8; 32],
+    roster: BTreeMap<ClientId, PublicKey>,
+    received: BTreeMap<ClientId, ShareBundle>,
+}
+
+impl SecureAggregationClient {
+    /// Client with a fresh mask key and self-mask seed
+    pub fn new(id: ClientId, config: SecureAggregationConfig) -> AnyaResult<Self> {
+        config.validate()?;
+        if id == 0 {
+            return Err(AnyaError::InvalidInput(
+                "client id 0 is reserved".to_string(),
+            ));
+        }
+        let mut rng = rand::thread_rng();
+        let mut self_seed = [0u8; 32];
+        rng.fill_bytes(&mut self_seed);
+        Ok(Self {
+            id,
+            config,
+            mask_key: SecretKey::new(&mut rng),
+            self_seed,
+            roster: BTreeMap::new(),
+            received: BTreeMap::new(),
+        })
+    }
+
+    pub fn id(&self) -> ClientId {
+        self.id
+    }
+
+    /// Key to register with the server
+    pub fn advertise(&self) -> ClientKey {
+        ClientKey {
+            id: self.id,
+            public_key: self.mask_key.public_key(&Secp256k1::new()),
+        }
+    }
+
+    /// Split this client's secrets into one share per peer in `roster`
+    pub fn share_keys(&mut self, roster: &[ClientKey]) -> AnyaResult<Vec<ShareBundle>> {
+        let roster: BTreeMap<ClientId, PublicKey> =
+            roster.iter().map(|key| (key.id, key.public_key)).collect();
+        if roster.get(&self.id) != Some(&self.advertise().public_key) {
+            return Err(AnyaError::ML(format!(
+                "client {} is missing from the roster",
+                self.id
+            )));
+        }
+        if roster.len() < self.config.threshold {
+            return Err(AnyaError::ML(format!(
+                "roster of {} clients is below the threshold of {}",
+                roster.len(),
+                self.config.threshold
+            )));
+        }
+
+        let ids: Vec<ClientId> = roster.keys().copied().collect();
+        let mask_key_shares =
+            shamir::split(&self.mask_key.secret_bytes(), &ids, self.config.threshold);
+        let self_seed_shares = shamir::split(&self.self_seed, &ids, self.config.threshold);
+        self.roster = roster;
+        Ok(ids
+            .iter()
+            .zip(mask_key_shares.into_iter().zip(self_seed_shares))
+            .map(|(to, (mask_key_share, self_seed_share))| ShareBundle {
+                from: self.id,
+                to: *to,
+                mask_key_share,
+                self_seed_share,
+            })
+            .collect())
+    }
+
+    /// Store a share sent by a peer
+    pub fn receive_share(&mut self, bundle: ShareBundle) -> AnyaResult<()> {
+        if bundle.to != self.id || !self.roster.contains_key(&bundle.from) {
+            return Err(AnyaError::ML(format!(
+                "unexpected share from client {} to client {}",
+                bundle.from, bundle.to
+            )));
+        }
+        self.received.insert(bundle.from, bundle);
+        Ok(())
+    }
+
+    /// Mask `update` for the server
+    pub fn masked_update(&self, update: &[f64]) -> AnyaResult<MaskedUpdate> {
+        if self.roster.is_empty() {
+            return Err(AnyaError::ML(
+                "keys must be shared before submitting an update".to_string(),
+            ));
+        }
+        let mut values = self.config.encode(update)?;
+        apply_mask(&mut values, &self.self_seed, true);
+        for (peer, public_key) in &self.roster {
+            if *peer != self.id {
+                let seed = pairwise_seed(&self.mask_key, public_key);
+                apply_mask(&mut values, &seed, self.id < *peer);
+            }
+        }
+        Ok(MaskedUpdate {
+            from: self.id,
+            values,
+        })
+    }
+
+    /// Shares needed to unmask the sum of the updates from `survivors`
+    ///
+    /// Reveals self-seed shares for survivors and mask-key shares for
+    /// dropped clients, so the server never learns both secrets of one client.
+    pub fn reveal(&self, survivors: &BTreeSet<ClientId>) -> AnyaResult<UnmaskShares> {
+        if !survivors.contains(&self.id) {
+            return Err(AnyaError::ML(format!(
+                "client {} did not survive the round",
+                self.id
+            )));
+        }
+        if survivors.len() < self.config.threshold
+            || survivors.iter().any(|id| !self.roster.contains_key(id))
+        {
+            return Err(AnyaError::Security(format!(
+                "refusing to unmask with survivors {survivors:?}"
+            )));
+        }
+
+        let mut shares = UnmaskShares {
+            from: self.id,
+            self_seed_shares: BTreeMap::new(),
+            mask_key_shares: BTreeMap::new(),
+        };
+        for peer in self.roster.keys() {
+            let bundle = self
+                .received
+                .get(peer)
+                .ok_or_else(|| AnyaError::ML(format!("no share received from client {peer}")))?;
+            if survivors.contains(peer) {
+                shares
+                    .self_seed_shares
+                    .insert(*peer, bundle.self_seed_share);
+            } else {
+                shares.mask_key_shares.insert(*peer, bundle.mask_key_share);
+            }
+        }
+        Ok(shares)
+    }
+}
+
+/// Server side of a secure aggregation round
+///
+/// Only ever holds masked updates, so it learns the sum of the surviving
+/// clients' updates and nothing about any single one.
+pub struct SecureAggregator {
+    config: SecureAggregationConfig,
+    keys: BTreeMap<ClientId, PublicKey>,
+    roster_closed: bool,
+    masked: BTreeMap<ClientId, Vec<u64>>,
+    survivors: Option<BTreeSet<ClientId>>,
+    reveals: BTreeMap<ClientId, UnmaskShares>,
+}
+
+impl SecureAggregator {
+    pub fn new(config: SecureAggregationConfig) -> AnyaResult<Self> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            keys: BTreeMap::new(),
+            roster_closed: false,
+            masked: BTreeMap::new(),
+            survivors: None,
+            reveals: BTreeMap::new(),
+        })
+    }
+
+    /// Add a client to the round
+    pub fn register(&mut self, key: ClientKey) -> AnyaResult<()> {
+        if self.roster_closed {
+            return Err(AnyaError::ML("the roster is closed".to_string()));
+        }
+        if key.id == 0 || self.keys.insert(key.id, key.public_key).is_some() {
+            return Err(AnyaError::InvalidInput(format!(
+                "client id {} is invalid or already registered",
+                key.id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Close registration and return the keys to send to every client
+    pub fn roster(&mut self) -> AnyaResult<Vec<ClientKey>> {
+        if self.keys.len() < self.config.threshold {
+            return Err(AnyaError::ML(format!(
+                "{} clients registered, {} needed",
+                self.keys.len(),
+                self.config.threshold
+            )));
+        }
+        self.roster_closed = true;
+        Ok(self
+            .keys
+            .iter()
+            .map(|(id, public_key)| ClientKey {
+                id: *id,
+                public_key: *public_key,
+            })
+            .collect())
+    }
+
+    /// Accept a masked update until the survivors are fixed
+    pub fn submit(&mut self, update: MaskedUpdate) -> AnyaResult<()> {
+        if !self.roster_closed || self.survivors.is_some() {
+            return Err(AnyaError::ML("updates are not being accepted".to_string()));
+        }
+        if !self.keys.contains_key(&update.from) || update.values.len() != self.config.vector_len {
+            return Err(AnyaError::InvalidInput(format!(
+                "invalid update from client {}",
+                update.from
+            )));
+        }
+        self.masked.insert(update.from, update.values);
+        Ok(())
+    }
+
+    /// Stop accepting updates and return the clients whose updates are summed
+    ///
+    /// Clients that have not submitted by now are treated as dropped; an
+    /// update arriving later is rejected, since their masks may be removed.
+    pub fn survivors(&mut self) -> AnyaResult<BTreeSet<ClientId>> {
+        if let Some(survivors) = &self.survivors {
+            return Ok(survivors.clone());
+        }
+        if self.masked.len() < self.config.threshold {
+            return Err(AnyaError::ML(format!(
+                "{} updates received, {} needed",
+                self.masked.len(),
+                self.config.threshold
+            )));
+        }
+        let survivors: BTreeSet<ClientId> = self.masked.keys().copied().collect();
+        self.survivors = Some(survivors.clone());
+        Ok(survivors)
+    }
+
+    /// Accept a survivor's unmasking shares
+    pub fn add_unmask_shares(&mut self, shares: UnmaskShares) -> AnyaResult<()> {
+        let survivors = self
+            .survivors
+            .as_ref()
+            .ok_or_else(|| AnyaError::ML("survivors have not been fixed".to_string()))?;
+        let dropped = self.keys.len() - survivors.len();
+        if !survivors.contains(&shares.from)
+            || !shares.self_seed_shares.keys().eq(survivors.iter())
+            || shares.mask_key_shares.len() != dropped
+            || shares
+                .mask_key_shares
+                .keys()
+                .any(|id| survivors.contains(id))
+        {
+            return Err(AnyaError::InvalidInput(format!(
+                "invalid unmasking shares from client {}",
+                shares.from
+            )));
+        }
+        self.reveals.insert(shares.from, shares);
+        Ok(())
+    }
+
+    /// Sum of the surviving clients' updates
+    pub fn aggregate(&self) -> AnyaResult<Vec<f64>> {
+        let survivors = self
+            .survivors
+            .as_ref()
+            .ok_or_else(|| AnyaError::ML("survivors have not been fixed".to_string()))?;
+        if self.reveals.len() < self.config.threshold {
+            return Err(AnyaError::ML(format!(
+                "{} unmasking shares received, {} needed",
+                self.reveals.len(),
+                self.config.threshold
+            )));
+        }
+        let reveals: Vec<&UnmaskShares> =
+            self.reveals.values().take(self.config.threshold).collect();
+        let xs: Vec<ClientId> = reveals.iter().map(|shares| shares.from).collect();
+
+        let mut sum = vec![0u64; self.config.vector_len];
+        for values in self.masked.values() {
+            for (total, value) in sum.iter_mut().zip(values) {
+                *total = total.wrapping_add(*value);
+            }
+        }
+
+        for id in survivors {
+            let shares: Vec<[u8; 32]> = reveals
+                .iter()
+                .map(|shares| shares.self_seed_shares[id])
+                .collect();
+            let self_seed = shamir::combine(&xs, &shares);
+            apply_mask(&mut sum, &self_seed, false);
+        }
+
+        for (dropped, public_key) in &self.keys {
+            if survivors.contains(dropped) {
+                continue;
+            }
+            let shares: Vec<[u8; 32]> = reveals
+                .iter()
+                .map(|shares| shares.mask_key_shares[dropped])
+                .collect();
+            let mask_key = SecretKey::from_slice(&shamir::combine(&xs, &shares))
+                .map_err(|e| AnyaError::Security(format!("bad mask key shares: {e}")))?;
+            if mask_key.public_key(&Secp256k1::new()) != *public_key {
+                return Err(AnyaError::Security(format!(
+                    "shares do not reconstruct the mask key of client {dropped}"
+                )));
+            }
+            // Undo each survivor's mask with the dropped client: a survivor
+            // with a lower id added it, one with a higher id subtracted it
+            for survivor in survivors {
+                let seed = pairwise_seed(&mask_key, &self.keys[survivor]);
+                apply_mask(&mut sum, &seed, survivor > dropped);
+            }
+        }
+
+        Ok(self.config.decode(&sum))
+    }
+}
+
+/// Seed both ends of a pair derive from their ECDH secret
+fn pairwise_seed(secret_key: &SecretKey, public_key: &PublicKey) -> [u8; 32] {
+    let shared = SharedSecret::new(public_key, secret_key);
+    let mut hasher = Sha256::new();
+    hasher.update(b"anya/secagg/pairwise");
+    hasher.update(shared.secret_bytes());
+    hasher.finalize().into()
+}
+
+/// Add (or subtract) the mask expanded from `seed` to `values`
+fn apply_mask(values: &mut [u64], seed: &[u8; 32], add: bool) {
+    let mut rng = ChaCha20Rng::from_seed(*seed);
+    for value in values {
+        let mask = rng.next_u64();
+        *value = if add {
+            value.wrapping_add(mask)
+        } else {
+            value.wrapping_sub(mask)
+        };
+    }
+}
+
+/// Byte-wise Shamir secret sharing over GF(2^8)
+mod shamir {
+    use super::ClientId;
+    use rand::RngCore;
+
+    /// Product in GF(2^8) with the AES polynomial x^8 + x^4 + x^3 + x + 1
+    fn mul(mut a: u8, mut b: u8) -> u8 {
+        let mut product = 0;
+        while b != 0 {
+            if b & 1 != 0 {
+                product ^= a;
+            }
+            let carry = a & 0x80 != 0;
+            a <<= 1;
+            if carry {
+                a ^= 0x1b;
+            }
+            b >>= 1;
+        }
+        product
+    }
+
+    /// Inverse of a non-zero element, `a^254`
+    fn inv(a: u8) -> u8 {
+        let mut result = 1;
+        for _ in 0..254 {
+            result = mul(result, a);
+        }
+        result
+    }
+
+    /// Shares of `secret` at x = each of `ids`, any `threshold` recovering it
+    pub(super) fn split(secret: &[u8; 32], ids: &[ClientId], threshold: usize) -> Vec<[u8; 32]> {
+        let mut rng = rand::thread_rng();
+        let mut coefficients = vec![[0u8; 32]; threshold - 1];
+        for coefficient in &mut coefficients {
+            rng.fill_bytes(coefficient);
+        }
+        ids.iter()
+            .map(|x| {
+                let mut share = [0u8; 32];
+                for (i, byte) in share.iter_mut().enumerate() {
+                    // Horner's rule, highest coefficient first
+                    *byte = coefficients
+                        .iter()
+                        .rev()
+                        .fold(0, |acc, coefficient| mul(acc, *x) ^ coefficient[i]);
+                    *byte = mul(*byte, *x) ^ secret[i];
+                }
+                share
+            })
+            .collect()
+    }
+
+    /// Secret from shares at distinct non-zero x-coordinates `xs`
+    pub(super) fn combine(xs: &[ClientId], shares: &[[u8; 32]]) -> [u8; 32] {
+        let mut secret = [0u8; 32];
+        for (j, (xj, share)) in xs.iter().zip(shares).enumerate() {
+            // Lagrange basis polynomial for xj evaluated at 0
+            let mut basis = 1;
+            for (m, xm) in xs.iter().enumerate() {
+                if m != j {
+                    basis = mul(basis, mul(*xm, inv(xm ^ xj)));
+                }
+            }
+            for (byte, value) in secret.iter_mut().zip(share) {
+                *byte ^= mul(basis, *value);
+            }
+        }
+        secret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SecureAggregationConfig {
+        SecureAggregationConfig {
+            threshold: 3,
+            vector_len: 3,
+            ..Default::default()
+        }
+    }
+
+    /// Run the key setup for `ids`, returning the server and the clients
+    fn setup(ids: &[ClientId]) -> (SecureAggregator, Vec<SecureAggregationClient>) {
+        let mut server = SecureAggregator::new(config()).unwrap();
+        let mut clients: Vec<SecureAggregationClient> = ids
+            .iter()
+            .map(|id| SecureAggregationClient::new(*id, config()).unwrap())
+            .collect();
+        for client in &clients {
+            server.register(client.advertise()).unwrap();
+        }
+        let roster = server.roster().unwrap();
+        let mut bundles = Vec::new();
+        for client in &mut clients {
+            bundles.extend(client.share_keys(&roster).unwrap());
+        }
+        for bundle in bundles {
+            let to = clients.iter_mut().find(|c| c.id() == bundle.to).unwrap();
+            to.receive_share(bundle).unwrap();
+        }
+        (server, clients)
+    }
+
+    fn update(id: ClientId) -> Vec<f64> {
+        let id = f64::from(id);
+        vec![id * 0.5, -id, 1.25 + id * 0.001]
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_shamir_threshold_subsets_recover_secret() {
+        let secret = [0xa5; 32];
+        let ids = [3, 7, 200, 255];
+        let shares = shamir::split(&secret, &ids, 3);
+        assert_eq!(shamir::combine(&ids[..3], &shares[..3]), secret);
+        assert_eq!(
+            shamir::combine(
+                &[ids[1], ids[3], ids[2]],
+                &[shares[1], shares[3], shares[2]]
+            ),
+            secret
+        );
+        assert_ne!(shamir::combine(&ids[..2], &shares[..2]), secret);
+    }
+
+    #[test]
+    fn test_masked_updates_hide_values_but_sum_exactly() {
+        let (mut server, clients) = setup(&[1, 2, 3, 4]);
+        for client in &clients {
+            let masked = client.masked_update(&update(client.id())).unwrap();
+            let plain = config().encode(&update(client.id())).unwrap();
+            assert_ne!(masked.values, plain);
+            server.submit(masked).unwrap();
+        }
+        let survivors = server.survivors().unwrap();
+        assert_eq!(survivors.len(), 4);
+        for client in &clients {
+            let shares = client.reveal(&survivors).unwrap();
+            assert!(shares.mask_key_shares.is_empty());
+            server.add_unmask_shares(shares).unwrap();
+        }
+
+        let expected: Vec<f64> = (0..3)
+            .map(|i| (1..=4).map(|id| update(id)[i]).sum())
+            .collect();
+        assert_close(&server.aggregate().unwrap(), &expected);
+    }
+
+    #[test]
+    fn test_dropped_participant_masks_are_removed() {
+        let (mut server, clients) = setup(&[1, 2, 3, 4, 5]);
+        // Client 3 drops out after sharing its keys but before submitting
+        for client in clients.iter().filter(|c| c.id() != 3) {
+            server
+                .submit(client.masked_update(&update(client.id())).unwrap())
+                .unwrap();
+        }
+        let survivors = server.survivors().unwrap();
+        assert!(!survivors.contains(&3));
+
+        // Client 3 is too late once the survivors are fixed
+        let late = clients[2].masked_update(&update(3)).unwrap();
+        assert!(server.submit(late).is_err());
+        assert!(clients[2].reveal(&survivors).is_err());
+
+        // Without the threshold of reveals the sum stays masked
+        server
+            .add_unmask_shares(clients[0].reveal(&survivors).unwrap())
+            .unwrap();
+        assert!(server.aggregate().is_err());
+
+        // Only three of the four survivors need to respond
+        for client in &clients[3..] {
+            let shares = client.reveal(&survivors).unwrap();
+            assert_eq!(shares.mask_key_shares.keys().collect::<Vec<_>>(), vec![&3]);
+            assert!(!shares.self_seed_shares.contains_key(&3));
+            server.add_unmask_shares(shares).unwrap();
+        }
+
+        let expected: Vec<f64> = (0..3)
+            .map(|i| [1, 2, 4, 5].iter().map(|id| update(*id)[i]).sum())
+            .collect();
+        assert_close(&server.aggregate().unwrap(), &expected);
+    }
+
+    #[test]
+    fn test_protocol_rejects_unsafe_requests() {
+        let (mut server, clients) = setup(&[1, 2, 3]);
+        assert!(clients[0].masked_update(&[1.0]).is_err());
+        assert!(clients[0]
+            .masked_update(&[f64::INFINITY, 0.0, 0.0])
+            .is_err());
+        assert!(server
+            .register(
+                SecureAggregationClient::new(9, config())
+                    .unwrap()
+                    .advertise()
+            )
+            .is_err());
+
+        // Fewer survivors than the threshold would expose the sum of too few
+        let two: BTreeSet<ClientId> = [1, 2].into_iter().collect();
+        assert!(matches!(
+            clients[0].reveal(&two),
+            Err(AnyaError::Security(_))
+        ));
+        server
+            .submit(clients[0].masked_update(&update(1)).unwrap())
+            .unwrap();
+        assert!(server.survivors().is_err());
+
+        assert!(SecureAggregationClient::new(0, config()).is_err());
+        assert!(SecureAggregator::new(SecureAggregationConfig {
+            precision_bits: 50,
+            ..config()
+        })
+        .is_err());
+    }
+}
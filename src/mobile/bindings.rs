@@ -0,0 +1,491 @@
+// UniFFI bindings for Android and iOS wallets
+//
+// Exposes wallet creation, balance, addresses and PSBT building and signing to
+// Kotlin and Swift. The bindings are generated from the compiled library:
+//
+//   cargo build --release --features mobile
+//   cargo run --features uniffi-cli --bin uniffi-bindgen -- generate \
+//       --library target/release/libanya_core.so --language kotlin --out-dir bindings/kotlin
+//
+// (`--language swift` for iOS.) Mnemonics and passphrases cross the boundary
+// into Rust only: no exported function returns key material, and errors carry
+// the message of the underlying error, never the inputs that caused it.
+
+use super::signing::{SigningGate, SigningOutput, SigningRequest, SIGNING_REQUEST_TIMEOUT};
+use crate::bitcoin::chain_source::{ChainSource, EsploraSource};
+use crate::bitcoin::wallet::signer::{self, SighashType};
+use crate::bitcoin::wallet::{
+    AddressManager, AddressType, BalanceManager, CoinSelectionStrategy, FeeRate, FeeStrategy,
+    TransactionBuilder, Wallet, WalletConfig, WalletType,
+};
+use crate::AnyaError;
+use bitcoin::psbt::Psbt as PSBT;
+use bitcoin::{Address, Amount, Network};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use zeroize::Zeroizing;
+
+/// Error returned across the FFI boundary
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum AnyaFfiError {
+    #[error("Invalid input: {message}")]
+    InvalidInput { message: String },
+    #[error("Bitcoin error: {message}")]
+    Bitcoin { message: String },
+    #[error("Network error: {message}")]
+    Network { message: String },
+    #[error("Not found: {message}")]
+    NotFound { message: String },
+    #[error("Security error: {message}")]
+    Security { message: String },
+    #[error("Authentication failed: {message}")]
+    AuthenticationFailed { message: String },
+    #[error("Expired: {message}")]
+    Expired { message: String },
+    #[error("Internal error: {message}")]
+    Internal { message: String },
+}
+
+impl From<AnyaError> for AnyaFfiError {
+    fn from(err: AnyaError) -> Self {
+        match err {
+            AnyaError::InvalidInput(message) => AnyaFfiError::InvalidInput { message },
+            AnyaError::Bitcoin(message) => AnyaFfiError::Bitcoin { message },
+            AnyaError::Timeout(message) => AnyaFfiError::Network { message },
+            AnyaError::NotFound(message) => AnyaFfiError::NotFound { message },
+            AnyaError::Security(message) => AnyaFfiError::Security { message },
+            other => AnyaFfiError::Internal {
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
+/// Bitcoin network a wallet runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum WalletNetwork {
+    Bitcoin,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl From<WalletNetwork> for Network {
+    fn from(network: WalletNetwork) -> Self {
+        match network {
+            WalletNetwork::Bitcoin => Network::Bitcoin,
+            WalletNetwork::Testnet => Network::Testnet,
+            WalletNetwork::Signet => Network::Signet,
+            WalletNetwork::Regtest => Network::Regtest,
+        }
+    }
+}
+
+/// Single-seed wallet handed out to mobile apps
+///
+/// Receives on native SegWit addresses. Async wallet calls run to completion on
+/// a runtime owned by the wallet, so every exported method is blocking and
+/// should be called off the UI thread.
+#[derive(uniffi::Object)]
+pub struct AnyaWallet {
+    wallet: Wallet,
+    network: Network,
+    runtime: tokio::runtime::Runtime,
+    gate: SigningGate,
+}
+
+/// Restore a wallet from its seed phrase
+///
+/// Without `esplora_url` the wallet cannot sync and only derives addresses and
+/// signs PSBTs.
+#[uniffi::export]
+pub fn create_wallet(
+    mnemonic: String,
+    passphrase: Option<String>,
+    network: WalletNetwork,
+    esplora_url: Option<String>,
+) -> Result<Arc<AnyaWallet>, AnyaFfiError> {
+    let chain_source =
+        esplora_url.map(|url| Arc::new(EsploraSource::new(&url)) as Arc<dyn ChainSource>);
+    AnyaWallet::restore(
+        Zeroizing::new(mnemonic),
+        passphrase.map(Zeroizing::new),
+        network.into(),
+        chain_source,
+    )
+    .map(Arc::new)
+}
+
+impl AnyaWallet {
+    fn restore(
+        mnemonic: Zeroizing<String>,
+        passphrase: Option<Zeroizing<String>>,
+        network: Network,
+        chain_source: Option<Arc<dyn ChainSource>>,
+    ) -> Result<Self, AnyaFfiError> {
+        let config = WalletConfig {
+            wallet_type: WalletType::Standard,
+            network,
+            name: "mobile".to_string(),
+            seed_phrase: None,
+            password: None,
+            receive_descriptor: String::new(),
+            change_descriptor: String::new(),
+            xpub: None,
+            data_dir: PathBuf::new(),
+            use_rpc: false,
+            coin_selection: CoinSelectionStrategy::LargestFirst,
+            gap_limit: 20,
+            min_confirmations: 1,
+            fee_strategy: FeeStrategy::Medium,
+        };
+        let wallet = Wallet::new(config, chain_source);
+        wallet.initialize(Some(&mnemonic), passphrase.as_ref().map(|p| p.as_str()))?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| AnyaFfiError::Internal {
+                message: e.to_string(),
+            })?;
+        Ok(Self {
+            wallet,
+            network,
+            runtime,
+            gate: SigningGate::default(),
+        })
+    }
+
+    fn parse_psbt(psbt: &str) -> Result<PSBT, AnyaFfiError> {
+        PSBT::from_str(psbt).map_err(|e| AnyaFfiError::InvalidInput {
+            message: format!("invalid PSBT: {e}"),
+        })
+    }
+
+    fn sign(&self, mut psbt: PSBT) -> Result<String, AnyaFfiError> {
+        if self.wallet.sign_owned_inputs(&mut psbt, SighashType::All)? == 0 {
+            return Err(AnyaFfiError::NotFound {
+                message: "no PSBT input spends a wallet address".to_string(),
+            });
+        }
+        signer::finalize_psbt(&mut psbt);
+        Ok(psbt.to_string())
+    }
+
+    fn prepare_signing_request_at(
+        &self,
+        psbt: String,
+        now: Instant,
+    ) -> Result<SigningRequest, AnyaFfiError> {
+        let psbt = Self::parse_psbt(&psbt)?;
+        let fee_sats = psbt
+            .fee()
+            .map_err(|e| AnyaFfiError::InvalidInput {
+                message: format!("cannot compute the PSBT fee: {e}"),
+            })?
+            .to_sat();
+
+        let mut outputs = Vec::new();
+        for txout in &psbt.unsigned_tx.output {
+            let (address, is_change) =
+                match Address::from_script(&txout.script_pubkey, self.network) {
+                    Ok(address) => {
+                        let address = address.to_string();
+                        let is_change = self.wallet.is_address_mine(&address)?;
+                        (address, is_change)
+                    }
+                    Err(_) => (format!("script {}", txout.script_pubkey), false),
+                };
+            outputs.push(SigningOutput {
+                address,
+                amount_sats: txout.value.to_sat(),
+                is_change,
+            });
+        }
+        let mut summary: Vec<String> = outputs
+            .iter()
+            .filter(|output| !output.is_change)
+            .map(|output| format!("Send {} sats to {}", output.amount_sats, output.address))
+            .collect();
+        summary.push(format!("Fee: {fee_sats} sats"));
+
+        let (id, challenge) = self.gate.open(psbt, now)?;
+        Ok(SigningRequest {
+            id,
+            outputs,
+            fee_sats,
+            summary: summary.join("\n"),
+            challenge: challenge.to_vec(),
+            expires_in_secs: SIGNING_REQUEST_TIMEOUT.as_secs(),
+        })
+    }
+
+    fn confirm_and_sign_at(
+        &self,
+        request_id: &str,
+        auth_token: &[u8],
+        now: Instant,
+    ) -> Result<String, AnyaFfiError> {
+        let psbt = self.gate.confirm(request_id, auth_token, now)?;
+        self.sign(psbt)
+    }
+}
+
+#[uniffi::export]
+impl AnyaWallet {
+    /// Fetch the wallet's UTXOs from the chain source; returns the balance in satoshis
+    pub fn sync(&self) -> Result<u64, AnyaFfiError> {
+        Ok(self.runtime.block_on(self.wallet.refresh_utxos())?)
+    }
+
+    /// Balance in satoshis as of the last sync
+    pub fn get_balance(&self) -> Result<u64, AnyaFfiError> {
+        Ok(self.wallet.get_balance()?)
+    }
+
+    /// Derive the next unused receive address
+    pub fn new_address(&self) -> Result<String, AnyaFfiError> {
+        Ok(self
+            .wallet
+            .get_new_address(AddressType::SegWit)?
+            .to_string())
+    }
+
+    /// Build an unsigned PSBT paying `amount_sats` to `recipient`
+    ///
+    /// Spends UTXOs found by the last sync and sends change to a fresh
+    /// address. Returns the PSBT in base64.
+    pub fn build_psbt(
+        &self,
+        recipient: String,
+        amount_sats: u64,
+        fee_rate_sat_vb: u64,
+    ) -> Result<String, AnyaFfiError> {
+        let recipient = Address::from_str(&recipient)
+            .and_then(|address| address.require_network(self.network))
+            .map_err(|e| AnyaFfiError::InvalidInput {
+                message: format!("invalid recipient address: {e}"),
+            })?;
+        let change = self.wallet.get_new_address(AddressType::SegWit)?;
+
+        let psbt = TransactionBuilder::new(self.wallet.list_utxos()?)
+            .add_recipient(recipient.script_pubkey(), Amount::from_sat(amount_sats))
+            .change_script(change.script_pubkey())
+            .fee_rate(FeeRate::SatPerVb(fee_rate_sat_vb))
+            .build()
+            .map_err(|e| AnyaFfiError::Bitcoin {
+                message: e.to_string(),
+            })?;
+        Ok(psbt.to_string())
+    }
+
+    /// Sign and finalize the inputs of a base64 PSBT that spend this wallet's coins
+    ///
+    /// Fails if no input belongs to the wallet, or once an authenticator is
+    /// registered: signing then goes through [`AnyaWallet::prepare_signing_request`].
+    pub fn sign_psbt(&self, psbt: String) -> Result<String, AnyaFfiError> {
+        if self.gate.is_enabled()? {
+            return Err(AnyaFfiError::AuthenticationFailed {
+                message: "signing requires a confirmed signing request".to_string(),
+            });
+        }
+        self.sign(Self::parse_psbt(&psbt)?)
+    }
+
+    /// Require user confirmation for every signature from now on
+    ///
+    /// `public_key` is the uncompressed SEC1 encoding of a P-256 key the
+    /// platform only unlocks after a biometric prompt. It can be set once.
+    pub fn register_authenticator(&self, public_key: Vec<u8>) -> Result<(), AnyaFfiError> {
+        self.gate.register(public_key)
+    }
+
+    /// Summarize a base64 PSBT for the user and hold it for confirmation
+    pub fn prepare_signing_request(&self, psbt: String) -> Result<SigningRequest, AnyaFfiError> {
+        self.prepare_signing_request_at(psbt, Instant::now())
+    }
+
+    /// Sign the PSBT of a prepared request, returning it in base64
+    ///
+    /// `auth_token` is the authenticator's DER-encoded ECDSA signature over
+    /// the request challenge.
+    pub fn confirm_and_sign(
+        &self,
+        request_id: String,
+        auth_token: Vec<u8>,
+    ) -> Result<String, AnyaFfiError> {
+        self.confirm_and_sign_at(&request_id, &auth_token, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::chain_source::{ChainUtxo, MockChainSource};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{OutPoint, TxOut, Txid};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+    use std::time::Duration;
+
+    const MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    /// Regtest wallet holding a single 100,000 sat UTXO
+    fn funded_wallet() -> AnyaWallet {
+        let chain = Arc::new(MockChainSource::new());
+        let wallet = AnyaWallet::restore(
+            Zeroizing::new(MNEMONIC.to_string()),
+            None,
+            Network::Regtest,
+            Some(chain.clone()),
+        )
+        .unwrap();
+
+        let address = wallet.new_address().unwrap();
+        assert!(address.starts_with("bcrt1q"));
+        let funded = Address::from_str(&address).unwrap().assume_checked();
+        chain.set_tip_height(100);
+        chain.add_utxo(ChainUtxo {
+            outpoint: OutPoint::new(Txid::from_byte_array([1; 32]), 0),
+            txout: TxOut {
+                value: Amount::from_sat(100_000),
+                script_pubkey: funded.script_pubkey(),
+            },
+            height: Some(90),
+        });
+        assert_eq!(wallet.sync().unwrap(), 100_000);
+        wallet
+    }
+
+    fn payee() -> Arc<AnyaWallet> {
+        create_wallet(
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong".to_string(),
+            None,
+            WalletNetwork::Regtest,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_wallet_round_trip_through_exported_api() {
+        let wallet = funded_wallet();
+        assert_eq!(wallet.get_balance().unwrap(), 100_000);
+
+        let payee = payee();
+        let unsigned = wallet
+            .build_psbt(payee.new_address().unwrap(), 40_000, 2)
+            .unwrap();
+        assert!(matches!(
+            payee.sign_psbt(unsigned.clone()),
+            Err(AnyaFfiError::NotFound { .. })
+        ));
+        let signed = PSBT::from_str(&wallet.sign_psbt(unsigned).unwrap()).unwrap();
+        let tx = signed.extract_tx().unwrap();
+        assert_eq!(tx.input.len(), 1);
+        assert!(!tx.input[0].witness.is_empty());
+    }
+
+    #[test]
+    fn test_errors_translate_without_secrets() {
+        let wallet =
+            create_wallet(MNEMONIC.to_string(), None, WalletNetwork::Regtest, None).unwrap();
+        let err = wallet.sync().unwrap_err();
+        assert!(matches!(err, AnyaFfiError::Bitcoin { .. }));
+        assert!(!format!("{err} {err:?}").contains("abandon"));
+        assert!(matches!(
+            wallet.build_psbt("tb1qnotanaddress".to_string(), 1_000, 1),
+            Err(AnyaFfiError::InvalidInput { .. })
+        ));
+        assert!(matches!(
+            wallet.sign_psbt("cHNidP8=".to_string()),
+            Err(AnyaFfiError::InvalidInput { .. })
+        ));
+    }
+
+    #[test]
+    fn test_signing_requires_confirmed_request() {
+        let rng = SystemRandom::new();
+        let authenticator = key_pair(&rng);
+        let impostor = key_pair(&rng);
+        let wallet = funded_wallet();
+        wallet
+            .register_authenticator(authenticator.public_key().as_ref().to_vec())
+            .unwrap();
+        assert!(matches!(
+            wallet.register_authenticator(impostor.public_key().as_ref().to_vec()),
+            Err(AnyaFfiError::Security { .. })
+        ));
+
+        let payee = payee().new_address().unwrap();
+        let unsigned = wallet.build_psbt(payee.clone(), 40_000, 2).unwrap();
+        assert!(matches!(
+            wallet.sign_psbt(unsigned.clone()),
+            Err(AnyaFfiError::AuthenticationFailed { .. })
+        ));
+
+        let now = Instant::now();
+        let request = wallet
+            .prepare_signing_request_at(unsigned.clone(), now)
+            .unwrap();
+        let sent: Vec<_> = request.outputs.iter().filter(|o| !o.is_change).collect();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            (sent[0].address.as_str(), sent[0].amount_sats),
+            (payee.as_str(), 40_000)
+        );
+        let change: u64 = request
+            .outputs
+            .iter()
+            .filter(|o| o.is_change)
+            .map(|o| o.amount_sats)
+            .sum();
+        assert_eq!(40_000 + change + request.fee_sats, 100_000);
+        assert!(request.summary.contains(&payee));
+
+        // A token from another key, or over another challenge, is refused
+        // and leaves the request open
+        let forged = impostor.sign(&rng, &request.challenge).unwrap();
+        assert!(matches!(
+            wallet.confirm_and_sign_at(&request.id, forged.as_ref(), now),
+            Err(AnyaFfiError::AuthenticationFailed { .. })
+        ));
+        let other_challenge = authenticator.sign(&rng, &[0; 32]).unwrap();
+        assert!(matches!(
+            wallet.confirm_and_sign_at(&request.id, other_challenge.as_ref(), now),
+            Err(AnyaFfiError::AuthenticationFailed { .. })
+        ));
+
+        let token = authenticator.sign(&rng, &request.challenge).unwrap();
+        let signed = wallet
+            .confirm_and_sign_at(&request.id, token.as_ref(), now + Duration::from_secs(60))
+            .unwrap();
+        assert!(PSBT::from_str(&signed).unwrap().extract_tx().is_ok());
+        // Each request signs once
+        assert!(matches!(
+            wallet.confirm_and_sign_at(&request.id, token.as_ref(), now),
+            Err(AnyaFfiError::NotFound { .. })
+        ));
+
+        // After the timeout even a valid token is refused, and the request is gone
+        let request = wallet.prepare_signing_request_at(unsigned, now).unwrap();
+        let token = authenticator.sign(&rng, &request.challenge).unwrap();
+        let late = now + SIGNING_REQUEST_TIMEOUT;
+        assert!(matches!(
+            wallet.confirm_and_sign_at(&request.id, token.as_ref(), late),
+            Err(AnyaFfiError::Expired { .. })
+        ));
+        assert!(matches!(
+            wallet.confirm_and_sign_at(&request.id, token.as_ref(), now),
+            Err(AnyaFfiError::NotFound { .. })
+        ));
+    }
+
+    fn key_pair(rng: &SystemRandom) -> EcdsaKeyPair {
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, rng).unwrap();
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), rng).unwrap()
+    }
+}
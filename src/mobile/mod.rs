@@ -1,8 +1,12 @@
 // Mobile module for anya-core
 // Contains FFI bindings and SDK implementations for mobile platforms
 
+#[cfg(feature = "mobile")]
+pub mod bindings;
 #[cfg(feature = "ffi")]
 pub mod ffi;
 pub mod sdk;
+#[cfg(feature = "mobile")]
+pub mod signing;
 
 pub use self::sdk::MobileSDK;
@@ -0,0 +1,158 @@
+// Biometric-gated signing requests
+//
+// Once an authenticator is registered, the wallet key is only used after the
+// user confirms a request on the device. The host registers the public half of
+// a P-256 key that the platform keeps behind its biometric prompt (a Secure
+// Enclave key on iOS, an Android Keystore key requiring user authentication).
+// Each signing request carries a random challenge; the host shows the request
+// summary, has the user unlock the key and returns its ECDSA signature over
+// the challenge as the auth token. Requests expire after
+// `SIGNING_REQUEST_TIMEOUT`.
+
+use super::bindings::AnyaFfiError;
+use bitcoin::psbt::Psbt as PSBT;
+use rand::RngCore;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a signing request can be confirmed after it was prepared
+pub const SIGNING_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Output of a transaction awaiting confirmation
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct SigningOutput {
+    /// Address paid, or the script if it has no address form
+    pub address: String,
+    pub amount_sats: u64,
+    /// Whether the output pays back to this wallet
+    pub is_change: bool,
+}
+
+/// Transaction summary shown to the user before signing
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct SigningRequest {
+    pub id: String,
+    pub outputs: Vec<SigningOutput>,
+    pub fee_sats: u64,
+    /// One line per recipient followed by the fee, for display
+    pub summary: String,
+    /// Bytes the authenticator key signs to produce the auth token
+    pub challenge: Vec<u8>,
+    pub expires_in_secs: u64,
+}
+
+struct PendingRequest {
+    psbt: PSBT,
+    challenge: [u8; 32],
+    expires_at: Instant,
+}
+
+/// Authenticator key and the signing requests awaiting confirmation
+#[derive(Default)]
+pub(super) struct SigningGate {
+    authenticator: Mutex<Option<Vec<u8>>>,
+    pending: Mutex<HashMap<String, PendingRequest>>,
+}
+
+impl SigningGate {
+    /// Register the SEC1-encoded P-256 authenticator public key
+    ///
+    /// The key can only be set once, so an unlocked app cannot swap it for a
+    /// key that signs without the biometric prompt.
+    pub(super) fn register(&self, public_key: Vec<u8>) -> Result<(), AnyaFfiError> {
+        if public_key.len() != 65 || public_key[0] != 0x04 {
+            return Err(AnyaFfiError::InvalidInput {
+                message: "authenticator key must be an uncompressed P-256 point".to_string(),
+            });
+        }
+        let mut authenticator = self.authenticator.lock().map_err(lock_error)?;
+        if authenticator.is_some() {
+            return Err(AnyaFfiError::Security {
+                message: "an authenticator is already registered".to_string(),
+            });
+        }
+        *authenticator = Some(public_key);
+        Ok(())
+    }
+
+    /// Whether signing requires a confirmed request
+    pub(super) fn is_enabled(&self) -> Result<bool, AnyaFfiError> {
+        Ok(self.authenticator.lock().map_err(lock_error)?.is_some())
+    }
+
+    /// Hold `psbt` until confirmed, returning the request id and challenge
+    pub(super) fn open(
+        &self,
+        psbt: PSBT,
+        now: Instant,
+    ) -> Result<(String, [u8; 32]), AnyaFfiError> {
+        let mut id = [0u8; 16];
+        let mut challenge = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut id);
+        rand::thread_rng().fill_bytes(&mut challenge);
+        let id = hex::encode(id);
+
+        let mut pending = self.pending.lock().map_err(lock_error)?;
+        pending.retain(|_, request| request.expires_at > now);
+        pending.insert(
+            id.clone(),
+            PendingRequest {
+                psbt,
+                challenge,
+                expires_at: now + SIGNING_REQUEST_TIMEOUT,
+            },
+        );
+        Ok((id, challenge))
+    }
+
+    /// Release the PSBT of request `id` if `auth_token` signs its challenge
+    ///
+    /// A wrong token leaves the request pending so the user can retry until
+    /// it expires; an expired request is dropped.
+    pub(super) fn confirm(
+        &self,
+        id: &str,
+        auth_token: &[u8],
+        now: Instant,
+    ) -> Result<PSBT, AnyaFfiError> {
+        let authenticator = self
+            .authenticator
+            .lock()
+            .map_err(lock_error)?
+            .clone()
+            .ok_or_else(|| AnyaFfiError::Security {
+                message: "no authenticator is registered".to_string(),
+            })?;
+
+        let mut pending = self.pending.lock().map_err(lock_error)?;
+        let request = pending.get(id).ok_or_else(|| AnyaFfiError::NotFound {
+            message: format!("no signing request {id}"),
+        })?;
+        if request.expires_at <= now {
+            pending.remove(id);
+            return Err(AnyaFfiError::Expired {
+                message: format!("signing request {id} expired"),
+            });
+        }
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, &authenticator)
+            .verify(&request.challenge, auth_token)
+            .map_err(|_| AnyaFfiError::AuthenticationFailed {
+                message: format!("auth token does not confirm signing request {id}"),
+            })?;
+
+        pending
+            .remove(id)
+            .map(|request| request.psbt)
+            .ok_or_else(|| AnyaFfiError::NotFound {
+                message: format!("no signing request {id}"),
+            })
+    }
+}
+
+fn lock_error<T>(e: std::sync::PoisonError<T>) -> AnyaFfiError {
+    AnyaFfiError::Internal {
+        message: format!("Mutex lock error: {e}"),
+    }
+}
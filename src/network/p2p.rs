@@ -20,6 +20,10 @@ const MIN_PEER_CONNECTIONS: usize = 8;
 const DDOS_CONNECTION_RATE_LIMIT: u32 = 30;
 /// NAT traversal timeout
 const NAT_TRAVERSAL_TIMEOUT: Duration = Duration::from_secs(30);
+/// Connection slots reserved for outbound peers by default
+const DEFAULT_OUTBOUND_SLOTS: usize = 10;
+/// Default limit of simultaneous connections from one IP address
+const DEFAULT_MAX_CONNECTIONS_PER_IP: usize = 4;
 
 /// Peer connection state
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -38,6 +42,47 @@ pub enum PeerState {
     Banned,
 }
 
+/// Which side opened a peer connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionDirection {
+    /// The peer connected to us
+    Inbound,
+    /// We connected to the peer
+    Outbound,
+}
+
+/// Connection slot limits
+///
+/// Outbound peers are chosen by us and are the defence against being
+/// surrounded by an attacker, so `outbound_slots` of the `max_connections`
+/// slots are kept for them and inbound connections never consume those.
+#[derive(Debug, Clone)]
+pub struct ConnectionLimits {
+    /// Total connection slots, inbound and outbound
+    pub max_connections: usize,
+    /// Slots reserved for outbound connections
+    pub outbound_slots: usize,
+    /// Simultaneous connections allowed from one IP address
+    pub max_per_ip: usize,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: MAX_PEER_CONNECTIONS,
+            outbound_slots: DEFAULT_OUTBOUND_SLOTS,
+            max_per_ip: DEFAULT_MAX_CONNECTIONS_PER_IP,
+        }
+    }
+}
+
+impl ConnectionLimits {
+    /// Slots left for inbound connections
+    pub fn inbound_slots(&self) -> usize {
+        self.max_connections.saturating_sub(self.outbound_slots)
+    }
+}
+
 /// Peer capabilities and services
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerCapabilities {
@@ -60,6 +105,8 @@ pub struct PeerCapabilities {
 pub struct PeerInfo {
     /// Peer's socket address
     pub addr: SocketAddr,
+    /// Which side opened the connection
+    pub direction: ConnectionDirection,
     /// Current connection state
     pub state: PeerState,
     /// When connection was established
@@ -84,6 +131,8 @@ pub struct PeerInfo {
     pub failed_attempts: u32,
     /// Whether this peer is behind NAT
     pub behind_nat: bool,
+    /// Valid blocks and transactions first relayed to us by this peer
+    pub valid_relays: u64,
 }
 
 /// DDoS protection metrics
@@ -134,6 +183,10 @@ pub struct P2PNetworkManager {
     nat_traversal_enabled: bool,
     /// DNS seed servers for peer discovery
     dns_seeds: Vec<String>,
+    /// Connection slot limits
+    limits: ConnectionLimits,
+    /// Inbound peers evicted to make room for new inbound connections
+    evicted_inbound: Arc<RwLock<u64>>,
 }
 
 impl Default for PeerCapabilities {
@@ -192,9 +245,22 @@ impl P2PNetworkManager {
             external_ip: Arc::new(RwLock::new(None)),
             nat_traversal_enabled,
             dns_seeds,
+            limits: ConnectionLimits::default(),
+            evicted_inbound: Arc::new(RwLock::new(0)),
         }
     }
 
+    /// Use `limits` instead of the default connection slot limits
+    pub fn with_connection_limits(mut self, limits: ConnectionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Connection slot limits in use
+    pub fn connection_limits(&self) -> &ConnectionLimits {
+        &self.limits
+    }
+
     /// Discover peers using DNS seeds
     pub async fn discover_peers(&self) -> AnyaResult<Vec<SocketAddr>> {
         let mut discovered_peers = Vec::new();
@@ -344,16 +410,55 @@ impl P2PNetworkManager {
     }
 
     /// Add a new peer connection
-    pub fn add_peer(&self, addr: SocketAddr, capabilities: PeerCapabilities) -> AnyaResult<()> {
+    ///
+    /// Outbound connections fail once the outbound slots are used up. An
+    /// inbound connection arriving while the inbound slots are full evicts the
+    /// least useful inbound peer, see [`Self::eviction_candidate`].
+    pub fn add_peer(
+        &self,
+        addr: SocketAddr,
+        capabilities: PeerCapabilities,
+        direction: ConnectionDirection,
+    ) -> AnyaResult<()> {
         let mut peers = self.peers.write().unwrap();
-        
-        // Check if we're at max capacity
-        if peers.len() >= MAX_PEER_CONNECTIONS {
-            return Err(AnyaError::System("Maximum peer connections reached".to_string()));
+
+        let from_ip = peers
+            .values()
+            .filter(|p| p.addr != addr && p.addr.ip() == addr.ip())
+            .count();
+        if from_ip >= self.limits.max_per_ip {
+            return Err(AnyaError::System(format!(
+                "Connection limit of {} reached for IP {}",
+                self.limits.max_per_ip,
+                addr.ip()
+            )));
+        }
+
+        let in_direction = peers
+            .values()
+            .filter(|p| p.addr != addr && p.direction == direction)
+            .count();
+        match direction {
+            ConnectionDirection::Outbound => {
+                if in_direction >= self.limits.outbound_slots {
+                    return Err(AnyaError::System("Outbound connection slots full".to_string()));
+                }
+            }
+            ConnectionDirection::Inbound => {
+                if in_direction >= self.limits.inbound_slots() {
+                    let evicted = Self::eviction_candidate(&peers).ok_or_else(|| {
+                        AnyaError::System("Inbound connection slots full".to_string())
+                    })?;
+                    peers.remove(&evicted);
+                    *self.evicted_inbound.write().unwrap() += 1;
+                    info!("Evicted inbound peer {} to make room for {}", evicted, addr);
+                }
+            }
         }
 
         let peer_info = PeerInfo {
             addr,
+            direction,
             state: PeerState::Connecting,
             connected_at: None,
             last_activity: Instant::now(),
@@ -366,6 +471,7 @@ impl P2PNetworkManager {
             score: 0,
             failed_attempts: 0,
             behind_nat: false,
+            valid_relays: 0,
         };
 
         peers.insert(addr, peer_info);
@@ -374,6 +480,32 @@ impl P2PNetworkManager {
         Ok(())
     }
 
+    /// Least useful inbound peer, the one evicted when inbound slots are full
+    ///
+    /// Peers that relayed the most valid data are kept, then those with the
+    /// best score; among equals the most recently active peer goes, so
+    /// long-standing peers are not displaced by a burst of new connections.
+    fn eviction_candidate(peers: &HashMap<SocketAddr, PeerInfo>) -> Option<SocketAddr> {
+        peers
+            .values()
+            .filter(|p| p.direction == ConnectionDirection::Inbound)
+            .min_by_key(|p| (p.valid_relays, p.score, std::cmp::Reverse(p.last_activity)))
+            .map(|p| p.addr)
+    }
+
+    /// Record that `addr` relayed a block or transaction that passed validation
+    pub fn record_valid_relay(&self, addr: &SocketAddr) -> AnyaResult<()> {
+        let mut peers = self.peers.write().unwrap();
+
+        if let Some(peer) = peers.get_mut(addr) {
+            peer.valid_relays += 1;
+            peer.last_activity = Instant::now();
+            Ok(())
+        } else {
+            Err(AnyaError::NotFound(format!("Peer not found: {}", addr)))
+        }
+    }
+
     /// Update peer state
     pub fn update_peer_state(&self, addr: &SocketAddr, state: PeerState) -> AnyaResult<()> {
         let mut peers = self.peers.write().unwrap();
@@ -492,7 +624,7 @@ impl P2PNetworkManager {
     /// Calculate overall connectivity score
     fn calculate_connectivity_score(&self, topology: &NetworkTopology) -> f64 {
         let region_diversity = topology.peers_by_region.len() as f64 / 10.0; // Max 10 regions
-        let node_count_score = (topology.total_nodes as f64 / MAX_PEER_CONNECTIONS as f64).min(1.0);
+        let node_count_score = (topology.total_nodes as f64 / self.limits.max_connections as f64).min(1.0);
         let clustering_score = topology.clustering_coefficient;
         
         // Weighted average
@@ -516,6 +648,20 @@ impl P2PNetworkManager {
         stats.insert("banned_peers".to_string(),
                      peers.values().filter(|p| p.state == PeerState::Banned).count() as f64);
 
+        // Connection slot usage
+        let inbound = peers
+            .values()
+            .filter(|p| p.direction == ConnectionDirection::Inbound)
+            .count();
+        stats.insert("inbound_peers".to_string(), inbound as f64);
+        stats.insert("outbound_peers".to_string(), (peers.len() - inbound) as f64);
+        stats.insert("inbound_slots".to_string(), self.limits.inbound_slots() as f64);
+        stats.insert("outbound_slots".to_string(), self.limits.outbound_slots as f64);
+        stats.insert(
+            "evicted_inbound_peers".to_string(),
+            *self.evicted_inbound.read().unwrap() as f64,
+        );
+
         // DDoS protection statistics
         stats.insert("banned_ips".to_string(), ddos_metrics.banned_ips.len() as f64);
         stats.insert("total_blocked_connections".to_string(), ddos_metrics.total_blocked as f64);
@@ -640,7 +786,9 @@ mod tests {
         let capabilities = PeerCapabilities::default();
 
         // Add peer
-        assert!(manager.add_peer(addr, capabilities).is_ok());
+        assert!(manager
+            .add_peer(addr, capabilities, ConnectionDirection::Outbound)
+            .is_ok());
         assert_eq!(manager.get_connected_peer_count(), 0); // Still connecting
 
         // Update to connected state
@@ -652,6 +800,69 @@ mod tests {
         assert_eq!(manager.get_connected_peer_count(), 0);
     }
 
+    #[test]
+    fn test_full_inbound_slots_leave_outbound_slots_free() {
+        let manager = P2PNetworkManager::new(false).with_connection_limits(ConnectionLimits {
+            max_connections: 6,
+            outbound_slots: 2,
+            max_per_ip: 2,
+        });
+        let inbound: Vec<_> = (1..=4)
+            .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 1, i)), 8333))
+            .collect();
+        for addr in &inbound {
+            manager
+                .add_peer(*addr, PeerCapabilities::default(), ConnectionDirection::Inbound)
+                .unwrap();
+        }
+        manager.record_valid_relay(&inbound[0]).unwrap();
+        manager.record_valid_relay(&inbound[2]).unwrap();
+        manager.record_valid_relay(&inbound[3]).unwrap();
+
+        // Another inbound peer displaces the one that relayed nothing
+        let newcomer = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 1, 5)), 8333);
+        manager
+            .add_peer(newcomer, PeerCapabilities::default(), ConnectionDirection::Inbound)
+            .unwrap();
+        let peers: HashSet<SocketAddr> = manager
+            .export_peer_info()
+            .into_iter()
+            .map(|(addr, _, _)| addr)
+            .collect();
+        assert!(!peers.contains(&inbound[1]));
+        assert!(peers.contains(&newcomer));
+        assert_eq!(peers.len(), 4);
+
+        // The reserved outbound slots are still available, and only those
+        for i in 1..=2 {
+            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 0, 0, i)), 8333);
+            manager
+                .add_peer(addr, PeerCapabilities::default(), ConnectionDirection::Outbound)
+                .unwrap();
+        }
+        let extra = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 0, 0, 3)), 8333);
+        assert!(manager
+            .add_peer(extra, PeerCapabilities::default(), ConnectionDirection::Outbound)
+            .is_err());
+
+        // A third connection from one IP is refused
+        let same_ip = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 0, 0, 1)), 18333);
+        manager
+            .add_peer(same_ip, PeerCapabilities::default(), ConnectionDirection::Inbound)
+            .unwrap();
+        let third = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 0, 0, 1)), 28333);
+        assert!(manager
+            .add_peer(third, PeerCapabilities::default(), ConnectionDirection::Inbound)
+            .is_err());
+
+        let stats = manager.get_network_stats();
+        assert_eq!(stats["inbound_peers"], 4.0);
+        assert_eq!(stats["outbound_peers"], 2.0);
+        assert_eq!(stats["inbound_slots"], 4.0);
+        assert_eq!(stats["outbound_slots"], 2.0);
+        assert_eq!(stats["evicted_inbound_peers"], 2.0);
+    }
+
     #[test]
     fn test_network_topology_analysis() {
         let manager = P2PNetworkManager::new(false);
@@ -664,7 +875,9 @@ mod tests {
         ];
 
         for addr in peers {
-            manager.add_peer(addr, PeerCapabilities::default()).unwrap();
+            manager
+                .add_peer(addr, PeerCapabilities::default(), ConnectionDirection::Outbound)
+                .unwrap();
             manager.update_peer_state(&addr, PeerState::Connected).unwrap();
         }
 
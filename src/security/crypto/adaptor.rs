@@ -0,0 +1,292 @@
+// Adaptor Signature Module
+// [AIR-3][AIS-3][BPC-3][AIT-3][RES-3]
+//
+// BIP-340 Schnorr adaptor signatures over secp256k1. An adaptor signature is
+// a signature encrypted under an encryption point `T`: it can be verified
+// against `T` without knowing its discrete log, becomes a valid signature
+// once the secret `t` with `t·G = T` is added, and reveals `t` to anyone
+// holding both the adaptor and the completed signature. DLCs and PTLCs are
+// built on these three properties.
+use crate::bitcoin::bip341::tagged_hash;
+use secp256k1::{schnorr, Parity, PublicKey, Scalar, SecretKey, XOnlyPublicKey, SECP256K1};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// Adaptor signature error type
+#[derive(Debug, Error)]
+pub enum AdaptorError {
+    #[error("Invalid adaptor signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Cryptographic error: {0}")]
+    Crypto(#[from] secp256k1::Error),
+}
+
+/// BIP-340 signature encrypted under an encryption point
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptorSignature {
+    /// Signer's nonce point, before the encryption point is added
+    pub nonce: PublicKey,
+    /// Encrypted signature scalar
+    pub s: [u8; 32],
+}
+
+/// Sign `msg` with `secret_key`, encrypted under `encryption_point`
+pub fn adaptor_sign(
+    secret_key: &SecretKey,
+    msg: &[u8; 32],
+    encryption_point: &PublicKey,
+) -> Result<AdaptorSignature, AdaptorError> {
+    let secret = even_secret(secret_key);
+    let (pubkey, _) = secret.x_only_public_key(SECP256K1);
+
+    // Deterministic nonce, retried until the final nonce R' + T has even y
+    for counter in 0u32.. {
+        let digest: [u8; 32] = Sha256::new()
+            .chain_update(b"anya/adaptor-nonce")
+            .chain_update(secret.secret_bytes())
+            .chain_update(msg)
+            .chain_update(encryption_point.serialize())
+            .chain_update(counter.to_be_bytes())
+            .finalize()
+            .into();
+        let Ok(nonce_secret) = SecretKey::from_slice(&digest) else {
+            continue;
+        };
+        let nonce = nonce_secret.public_key(SECP256K1);
+        let Ok(final_nonce) = nonce.combine(encryption_point) else {
+            continue;
+        };
+        let (final_nonce, parity) = final_nonce.x_only_public_key();
+        if parity == Parity::Odd {
+            continue;
+        }
+
+        let e = challenge(&final_nonce, &pubkey, msg)?;
+        let s = secret
+            .mul_tweak(&e)?
+            .add_tweak(&Scalar::from(nonce_secret))?;
+        return Ok(AdaptorSignature {
+            nonce,
+            s: s.secret_bytes(),
+        });
+    }
+    unreachable!("nonce counter exhausted")
+}
+
+/// Check `adaptor_sig` encrypts a valid signature by `pubkey` on `msg`
+pub fn adaptor_verify(
+    pubkey: &XOnlyPublicKey,
+    msg: &[u8; 32],
+    encryption_point: &PublicKey,
+    adaptor_sig: &AdaptorSignature,
+) -> bool {
+    let verify = || -> Result<bool, AdaptorError> {
+        let final_nonce = final_nonce(adaptor_sig, encryption_point)?;
+        let e = challenge(&final_nonce, pubkey, msg)?;
+        let s = SecretKey::from_slice(&adaptor_sig.s)?;
+
+        // s'·G == R' + e·P
+        let expected = pubkey
+            .public_key(Parity::Even)
+            .mul_tweak(SECP256K1, &e)?
+            .combine(&adaptor_sig.nonce)?;
+        Ok(s.public_key(SECP256K1) == expected)
+    };
+    verify().unwrap_or(false)
+}
+
+/// Complete `adaptor_sig` with the secret of its encryption point
+pub fn decrypt(
+    adaptor_sig: &AdaptorSignature,
+    secret: &SecretKey,
+) -> Result<schnorr::Signature, AdaptorError> {
+    let final_nonce = final_nonce(adaptor_sig, &secret.public_key(SECP256K1))?;
+    let s = SecretKey::from_slice(&adaptor_sig.s)?.add_tweak(&Scalar::from(*secret))?;
+    signature(&final_nonce, &s)
+}
+
+/// Extract the encryption secret from `adaptor_sig` and its completed `signature`
+pub fn recover(
+    adaptor_sig: &AdaptorSignature,
+    signature: &schnorr::Signature,
+) -> Result<SecretKey, AdaptorError> {
+    let encrypted = SecretKey::from_slice(&adaptor_sig.s)?;
+    let secret = SecretKey::from_slice(&signature.as_ref()[32..])?
+        .add_tweak(&Scalar::from(encrypted.negate()))?;
+
+    // The recovered secret must account for the signature's nonce
+    let final_nonce = final_nonce(adaptor_sig, &secret.public_key(SECP256K1))?;
+    if final_nonce.serialize()[..] != signature.as_ref()[..32] {
+        return Err(AdaptorError::InvalidSignature(
+            "Signature does not complete this adaptor signature".to_string(),
+        ));
+    }
+    Ok(secret)
+}
+
+/// BIP-340 signature on `msg` with an explicit nonce
+///
+/// The nonce must never be reused with the same key for another message.
+pub fn sign_with_nonce(
+    secret_key: &SecretKey,
+    nonce_secret: &SecretKey,
+    msg: &[u8; 32],
+) -> Result<schnorr::Signature, AdaptorError> {
+    let secret = even_secret(secret_key);
+    let nonce_secret = even_secret(nonce_secret);
+    let (pubkey, _) = secret.x_only_public_key(SECP256K1);
+    let (nonce, _) = nonce_secret.x_only_public_key(SECP256K1);
+
+    let e = challenge(&nonce, &pubkey, msg)?;
+    let s = secret
+        .mul_tweak(&e)?
+        .add_tweak(&Scalar::from(nonce_secret))?;
+    signature(&nonce, &s)
+}
+
+/// Point `R + e·P` that a BIP-340 signature by `pubkey` with `nonce` on `msg` reveals
+///
+/// Used as the encryption point for a signature that has not been made yet,
+/// e.g. an oracle's attestation to one outcome.
+pub fn signature_point(
+    pubkey: &XOnlyPublicKey,
+    nonce: &XOnlyPublicKey,
+    msg: &[u8; 32],
+) -> Result<PublicKey, AdaptorError> {
+    let e = challenge(nonce, pubkey, msg)?;
+    Ok(pubkey
+        .public_key(Parity::Even)
+        .mul_tweak(SECP256K1, &e)?
+        .combine(&nonce.public_key(Parity::Even))?)
+}
+
+/// BIP-340 challenge `e = H(R.x || P.x || m)`
+fn challenge(
+    nonce: &XOnlyPublicKey,
+    pubkey: &XOnlyPublicKey,
+    msg: &[u8; 32],
+) -> Result<Scalar, AdaptorError> {
+    let data = [&nonce.serialize()[..], &pubkey.serialize(), msg].concat();
+    Scalar::from_be_bytes(tagged_hash(b"BIP0340/challenge", &data))
+        .map_err(|_| AdaptorError::InvalidSignature("Challenge out of range".to_string()))
+}
+
+/// Secret key whose public key has even y, as BIP-340 requires
+fn even_secret(secret_key: &SecretKey) -> SecretKey {
+    match secret_key.x_only_public_key(SECP256K1).1 {
+        Parity::Even => *secret_key,
+        Parity::Odd => secret_key.negate(),
+    }
+}
+
+/// Nonce of the completed signature, `R' + T`
+fn final_nonce(
+    adaptor_sig: &AdaptorSignature,
+    encryption_point: &PublicKey,
+) -> Result<XOnlyPublicKey, AdaptorError> {
+    let (final_nonce, parity) = adaptor_sig
+        .nonce
+        .combine(encryption_point)?
+        .x_only_public_key();
+    if parity == Parity::Odd {
+        return Err(AdaptorError::InvalidSignature(
+            "Nonce has odd y".to_string(),
+        ));
+    }
+    Ok(final_nonce)
+}
+
+fn signature(nonce: &XOnlyPublicKey, s: &SecretKey) -> Result<schnorr::Signature, AdaptorError> {
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(&nonce.serialize());
+    bytes[32..].copy_from_slice(&s.secret_bytes());
+    Ok(schnorr::Signature::from_slice(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::Message;
+
+    fn key(seed: u8) -> SecretKey {
+        SecretKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_recover_cycle() {
+        let signer = key(7);
+        let (pubkey, _) = signer.x_only_public_key(SECP256K1);
+        let secret = key(9);
+        let encryption_point = secret.public_key(SECP256K1);
+        let msg = [3; 32];
+
+        let adaptor_sig = adaptor_sign(&signer, &msg, &encryption_point).unwrap();
+        assert!(adaptor_verify(
+            &pubkey,
+            &msg,
+            &encryption_point,
+            &adaptor_sig
+        ));
+
+        let signature = decrypt(&adaptor_sig, &secret).unwrap();
+        SECP256K1
+            .verify_schnorr(&signature, &Message::from_digest(msg), &pubkey)
+            .unwrap();
+
+        let recovered = recover(&adaptor_sig, &signature).unwrap();
+        assert_eq!(recovered.public_key(SECP256K1), encryption_point);
+
+        // A signature made without the adaptor reveals nothing
+        let unrelated = SECP256K1
+            .sign_schnorr_no_aux_rand(&Message::from_digest(msg), &signer.keypair(SECP256K1));
+        assert!(recover(&adaptor_sig, &unrelated).is_err());
+    }
+
+    #[test]
+    fn test_rejects_forged_adaptor() {
+        let signer = key(7);
+        let (pubkey, _) = signer.x_only_public_key(SECP256K1);
+        let encryption_point = key(9).public_key(SECP256K1);
+        let msg = [3; 32];
+        let adaptor_sig = adaptor_sign(&signer, &msg, &encryption_point).unwrap();
+
+        let mut forged = adaptor_sig;
+        forged.s[31] ^= 1;
+        assert!(!adaptor_verify(&pubkey, &msg, &encryption_point, &forged));
+
+        // Valid adaptor, but for another key, message or encryption point
+        let (other_key, _) = key(8).x_only_public_key(SECP256K1);
+        let other_point = key(8).public_key(SECP256K1);
+        assert!(!adaptor_verify(
+            &other_key,
+            &msg,
+            &encryption_point,
+            &adaptor_sig
+        ));
+        assert!(!adaptor_verify(
+            &pubkey,
+            &[4; 32],
+            &encryption_point,
+            &adaptor_sig
+        ));
+        assert!(!adaptor_verify(&pubkey, &msg, &other_point, &adaptor_sig));
+    }
+
+    #[test]
+    fn test_signature_point_matches_signature() {
+        let signer = key(5);
+        let nonce = key(6);
+        let msg = [1; 32];
+        let (pubkey, _) = signer.x_only_public_key(SECP256K1);
+        let (nonce_point, _) = nonce.x_only_public_key(SECP256K1);
+
+        let signature = sign_with_nonce(&signer, &nonce, &msg).unwrap();
+        SECP256K1
+            .verify_schnorr(&signature, &Message::from_digest(msg), &pubkey)
+            .unwrap();
+        let point = signature_point(&pubkey, &nonce_point, &msg).unwrap();
+        let scalar = SecretKey::from_slice(&signature.as_ref()[32..]).unwrap();
+        assert_eq!(scalar.public_key(SECP256K1), point);
+    }
+}
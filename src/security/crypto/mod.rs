@@ -26,6 +26,9 @@ pub mod signature;
 // Module for key derivation functions (PBKDF2, Argon2, scrypt)
 pub mod kdf;
 
+// Module for Schnorr adaptor signatures (DLCs, PTLCs)
+pub mod adaptor;
+
 /// Helper function to generate a secure cryptographic key of specified length
 pub fn generate_key(length_bytes: usize) -> Vec<u8> {
     random_bytes(length_bytes)
@@ -40,3 +43,33 @@ pub fn generate_iv(length_bytes: usize) -> Vec<u8> {
 pub fn generate_nonce(length_bytes: usize) -> Vec<u8> {
     random_bytes(length_bytes)
 }
+
+/// Build a secp256k1 message from input of any length
+///
+/// The input is hashed with SHA-256, so empty, short and long inputs all
+/// yield a valid 32-byte digest instead of failing a length check.
+pub fn message_from_hashed(data: &[u8]) -> secp256k1::Message {
+    use sha2::{Digest, Sha256};
+    secp256k1::Message::from_digest(Sha256::digest(data).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_from_hashed_accepts_any_length() {
+        let empty = message_from_hashed(b"");
+        let short = message_from_hashed(b"abc");
+        let long = message_from_hashed(&[0x55; 4096]);
+
+        // SHA-256 of the empty string
+        assert_eq!(
+            hex::encode(empty.as_ref()),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(short, empty);
+        assert_ne!(long, short);
+        assert_eq!(message_from_hashed(b"abc"), short);
+    }
+}
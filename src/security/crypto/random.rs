@@ -1,8 +1,9 @@
 use rand::distributions::Standard;
-use rand::rngs::OsRng;
 use rand::{distributions::Distribution, seq::SliceRandom, Rng, RngCore};
 use std::fmt;
 
+use crate::security::rng::secure_rng;
+
 /// Error type for random number generation
 #[derive(Debug)]
 pub enum RandomError {
@@ -26,7 +27,7 @@ type Result<T> = std::result::Result<T, RandomError>;
 
 /// Generate random bytes
 pub fn random_bytes(size: usize) -> Vec<u8> {
-    let mut rng = OsRng;
+    let mut rng = secure_rng();
     let mut bytes = vec![0u8; size];
     rng.fill_bytes(&mut bytes);
     bytes
@@ -34,25 +35,25 @@ pub fn random_bytes(size: usize) -> Vec<u8> {
 
 /// Generate a random u64
 pub fn random_u64() -> u64 {
-    let mut rng = OsRng;
+    let mut rng = secure_rng();
     rng.gen()
 }
 
 /// Generate a random u32
 pub fn random_u32() -> u32 {
-    let mut rng = OsRng;
+    let mut rng = secure_rng();
     rng.gen()
 }
 
 /// Generate a random usize
 pub fn random_usize() -> usize {
-    let mut rng = OsRng;
+    let mut rng = secure_rng();
     rng.gen()
 }
 
 /// Generate a random f64
 pub fn random_f64() -> f64 {
-    let mut rng = OsRng;
+    let mut rng = secure_rng();
     rng.gen()
 }
 
@@ -62,7 +63,7 @@ where
     T: PartialOrd + Copy,
     Standard: Distribution<T>,
 {
-    let mut rng = OsRng;
+    let mut rng = secure_rng();
     loop {
         let val: T = rng.gen();
         if val >= min && val <= max {
@@ -73,19 +74,19 @@ where
 
 /// Generate a random boolean
 pub fn random_bool() -> bool {
-    let mut rng = OsRng;
+    let mut rng = secure_rng();
     rng.gen()
 }
 
 /// Shuffle a slice in place
 pub fn shuffle<T>(slice: &mut [T]) {
-    let mut rng = OsRng;
+    let mut rng = secure_rng();
     slice.shuffle(&mut rng);
 }
 
-/// Reseed the random number generator (no-op for OsRng)
+/// Reseed the random number generator (no-op, each call is freshly seeded)
 pub fn reseed() {
-    // OsRng doesn't need reseeding as it uses the OS entropy source
+    // Every helper draws from a new generator seeded from the OS entropy source
 }
 
 #[cfg(test)]
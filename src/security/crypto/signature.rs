@@ -2,6 +2,8 @@
 //!
 //! This module provides cryptographic signature functionality for Bitcoin security.
 
+#[cfg(feature = "bitcoin")]
+use super::message_from_hashed;
 use std::error::Error;
 
 /// Supported signature algorithms
@@ -31,8 +33,7 @@ pub fn sign(
     private_key: &[u8],
     algorithm: SignatureAlgorithm,
 ) -> Result<Signature, Box<dyn Error>> {
-    use secp256k1::hashes::{sha256, Hash};
-    use secp256k1::{Keypair, Message, Secp256k1, SecretKey};
+    use secp256k1::{Keypair, Secp256k1, SecretKey};
 
     log::debug!(
         "Signing message of length {} with key length {}",
@@ -47,9 +48,7 @@ pub fn sign(
                 .map_err(|e| format!("Invalid private key: {e}"))?;
 
             // Hash the message using SHA256
-            let msg_hash = sha256::Hash::hash(message);
-            let msg = Message::from_digest_slice(&msg_hash[..])
-                .map_err(|e| format!("Invalid message hash: {e}"))?;
+            let msg = message_from_hashed(message);
 
             // Sign with ECDSA
             let signature = secp.sign_ecdsa(&msg, &secret_key);
@@ -67,9 +66,7 @@ pub fn sign(
             let key_pair = Keypair::from_secret_key(&secp, &secret_key);
 
             // Hash the message using SHA256
-            let msg_hash = sha256::Hash::hash(message);
-            let msg = Message::from_digest_slice(&msg_hash[..])
-                .map_err(|e| format!("Invalid message hash: {e}"))?;
+            let msg = message_from_hashed(message);
 
             // Sign with Schnorr
             let signature = secp.sign_schnorr(&msg, &key_pair);
@@ -103,8 +100,7 @@ pub fn verify(
     signature: &Signature,
     public_key: &[u8],
 ) -> Result<bool, Box<dyn Error>> {
-    use secp256k1::hashes::{sha256, Hash};
-    use secp256k1::{ecdsa, Message, PublicKey, Secp256k1, XOnlyPublicKey};
+    use secp256k1::{ecdsa, PublicKey, Secp256k1, XOnlyPublicKey};
 
     match signature.algorithm {
         SignatureAlgorithm::EcdsaSecp256k1 => {
@@ -117,9 +113,7 @@ pub fn verify(
                 .map_err(|e| format!("Invalid signature: {e}"))?;
 
             // Hash the message using SHA256
-            let msg_hash = sha256::Hash::hash(message);
-            let msg = Message::from_digest_slice(&msg_hash[..])
-                .map_err(|e| format!("Invalid message hash: {e}"))?;
+            let msg = message_from_hashed(message);
 
             Ok(secp.verify_ecdsa(&msg, &signature, &public_key).is_ok())
         }
@@ -141,9 +135,7 @@ pub fn verify(
                 .map_err(|e| format!("Invalid Schnorr signature: {e}"))?;
 
             // Hash the message using SHA256
-            let msg_hash = sha256::Hash::hash(message);
-            let msg = Message::from_digest_slice(&msg_hash[..])
-                .map_err(|e| format!("Invalid message hash: {e}"))?;
+            let msg = message_from_hashed(message);
 
             Ok(secp.verify_schnorr(&signature, &msg, &xonly_pk).is_ok())
         }
@@ -211,3 +203,27 @@ pub fn schnorr_verify_secp256k1(
     };
     verify(message, &sig, public_key)
 }
+
+#[cfg(all(test, feature = "bitcoin"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify_any_message_length() {
+        let private_key = [7u8; 32];
+        let public_key = secp256k1::SecretKey::from_slice(&private_key)
+            .unwrap()
+            .public_key(&secp256k1::Secp256k1::new())
+            .serialize();
+
+        for message in [&b""[..], &b"short"[..], &[0xab; 1024][..]] {
+            for algorithm in [
+                SignatureAlgorithm::EcdsaSecp256k1,
+                SignatureAlgorithm::SchnorrSecp256k1,
+            ] {
+                let signature = sign(message, &private_key, algorithm).unwrap();
+                assert!(verify(message, &signature, &public_key).unwrap());
+            }
+        }
+    }
+}
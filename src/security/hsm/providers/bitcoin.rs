@@ -15,11 +15,11 @@ use uuid::Uuid;
 
 use bitcoin::{
     bip32::{DerivationPath, Xpriv as ExtendedPrivKey, Xpub as ExtendedPubKey},
-    hashes::{sha256, Hash},
-    secp256k1::{ecdsa::Signature, Message, PublicKey as SepcPublicKey, Secp256k1, SecretKey},
+    secp256k1::{ecdsa::Signature, PublicKey as SepcPublicKey, Secp256k1, SecretKey},
     Network,
 };
 
+use crate::security::crypto::message_from_hashed;
 use crate::security::hsm::config::BitcoinConfig;
 use crate::security::hsm::error::HsmError;
 use crate::security::hsm::provider::{
@@ -187,11 +187,7 @@ impl BitcoinHsmProvider {
 
         // Create message to sign
         let message = match algorithm {
-            SigningAlgorithm::EcdsaSha256 => {
-                let hash = sha256::Hash::hash(data);
-                Message::from_digest_slice(hash.as_ref())
-                    .map_err(|e| HsmError::ProviderError(format!("Invalid message: {e}")))?
-            }
+            SigningAlgorithm::EcdsaSha256 => message_from_hashed(data),
             _ => {
                 return Err(HsmError::InvalidParameters(format!(
                     "Unsupported algorithm: {algorithm:?}"
@@ -230,11 +226,7 @@ impl BitcoinHsmProvider {
 
         // Create message to verify
         let message = match algorithm {
-            SigningAlgorithm::EcdsaSha256 => {
-                let hash = sha256::Hash::hash(data);
-                Message::from_digest_slice(hash.as_ref())
-                    .map_err(|e| HsmError::ProviderError(format!("Invalid message: {e}")))?
-            }
+            SigningAlgorithm::EcdsaSha256 => message_from_hashed(data),
             _ => {
                 return Err(HsmError::InvalidParameters(format!(
                     "Unsupported algorithm: {algorithm:?}"
@@ -0,0 +1,177 @@
+//! External Signing Device HSM Provider
+//!
+//! Signs PSBTs on a hardware wallet through an [`ExternalSigner`], such as
+//! [`HwiSigner`](crate::bitcoin::wallet::external_signer::HwiSigner), instead
+//! of with keys held by the software HSM. Keys never leave the device: key IDs
+//! are BIP32 derivation paths, public keys come from the device's xpubs, and
+//! the only signing operation is [`HsmProvider::sign_psbt`].
+
+use async_trait::async_trait;
+use bitcoin::bip32::DerivationPath;
+use bitcoin::psbt::Psbt;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::bitcoin::wallet::external_signer::{sign_external, ExternalSigner, ExternalSignerError};
+use crate::security::hsm::audit::AuditLogger;
+use crate::security::hsm::error::{AuditEventResult, AuditEventSeverity, AuditEventType, HsmError};
+use crate::security::hsm::provider::{
+    HsmProvider, HsmProviderStatus, HsmRequest, HsmResponse, KeyGenParams, KeyInfo, KeyPair,
+    SigningAlgorithm,
+};
+
+impl From<ExternalSignerError> for HsmError {
+    fn from(err: ExternalSignerError) -> Self {
+        match err {
+            ExternalSignerError::NoDevice => HsmError::DeviceDisconnected(err.to_string()),
+            ExternalSignerError::Timeout(_) => HsmError::TimeoutError(err.to_string()),
+            ExternalSignerError::Declined => HsmError::TransactionRejected,
+            ExternalSignerError::Device { .. } => HsmError::HardwareFailure(err.to_string()),
+            ExternalSignerError::Protocol(_) | ExternalSignerError::Io(_) => {
+                HsmError::DeviceCommunicationError(err.to_string())
+            }
+        }
+    }
+}
+
+/// HSM provider backed by an external signing device
+pub struct ExternalSignerHsmProvider {
+    signer: Arc<dyn ExternalSigner>,
+    audit_logger: Arc<AuditLogger>,
+}
+
+impl fmt::Debug for ExternalSignerHsmProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalSignerHsmProvider")
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExternalSignerHsmProvider {
+    /// Create a provider signing with `signer`
+    pub fn new(signer: Arc<dyn ExternalSigner>, audit_logger: Arc<AuditLogger>) -> Self {
+        Self {
+            signer,
+            audit_logger,
+        }
+    }
+
+    fn unsupported(operation: &str) -> HsmError {
+        HsmError::UnsupportedOperation(format!(
+            "{operation} is not supported by external signing devices"
+        ))
+    }
+}
+
+#[async_trait]
+impl HsmProvider for ExternalSignerHsmProvider {
+    async fn initialize(&self) -> Result<(), HsmError> {
+        let devices = self.signer.enumerate().await?;
+        if devices.is_empty() {
+            return Err(ExternalSignerError::NoDevice.into());
+        }
+        self.audit_logger
+            .log(
+                AuditEventType::Initialization,
+                AuditEventResult::Success,
+                AuditEventSeverity::Info,
+                &format!(
+                    "External signer initialized with {} device(s)",
+                    devices.len()
+                ),
+            )
+            .await
+    }
+
+    async fn generate_key(&self, _params: KeyGenParams) -> Result<(KeyPair, KeyInfo), HsmError> {
+        // Devices derive keys from their own seed
+        Err(Self::unsupported("Key generation"))
+    }
+
+    async fn sign(
+        &self,
+        _key_id: &str,
+        _algorithm: SigningAlgorithm,
+        _data: &[u8],
+    ) -> Result<Vec<u8>, HsmError> {
+        // Devices only sign transactions they can show to the user
+        Err(Self::unsupported("Signing raw data"))
+    }
+
+    async fn sign_psbt(&self, psbt: &mut Psbt) -> Result<(), HsmError> {
+        let signed = match sign_external(&*self.signer, psbt).await {
+            Ok(signed) => signed,
+            Err(e) => {
+                let result = match e {
+                    ExternalSignerError::Declined => AuditEventResult::Rejected,
+                    ExternalSignerError::Timeout(_) => AuditEventResult::Timeout,
+                    _ => AuditEventResult::Failure,
+                };
+                self.audit_logger
+                    .log(
+                        AuditEventType::Sign,
+                        result,
+                        AuditEventSeverity::Warning,
+                        &format!("External signer did not sign PSBT: {e}"),
+                    )
+                    .await?;
+                return Err(e.into());
+            }
+        };
+        if signed == 0 {
+            return Err(HsmError::SigningError(
+                "External signer has no key for any PSBT input".to_string(),
+            ));
+        }
+        self.audit_logger
+            .log(
+                AuditEventType::Sign,
+                AuditEventResult::Success,
+                AuditEventSeverity::Info,
+                &format!("External signer signed {signed} PSBT input(s)"),
+            )
+            .await
+    }
+
+    /// Public key at the derivation path `key_id`
+    async fn export_public_key(&self, key_id: &str) -> Result<Vec<u8>, HsmError> {
+        let path = DerivationPath::from_str(key_id)
+            .map_err(|e| HsmError::InvalidParameters(format!("{key_id}: {e}")))?;
+        let xpub = self.signer.get_xpub(&path).await?;
+        Ok(xpub.public_key.serialize().to_vec())
+    }
+
+    async fn list_keys(&self) -> Result<Vec<KeyInfo>, HsmError> {
+        // Any derivation path is a key; there is nothing to enumerate
+        Ok(vec![])
+    }
+
+    async fn delete_key(&self, _key_id: &str) -> Result<(), HsmError> {
+        Err(Self::unsupported("Key deletion"))
+    }
+
+    async fn get_status(&self) -> Result<HsmProviderStatus, HsmError> {
+        Ok(match self.signer.enumerate().await {
+            Ok(devices) if devices.is_empty() => HsmProviderStatus::Unavailable,
+            Ok(devices) if devices.iter().any(|d| d.needs_pin || d.needs_passphrase) => {
+                HsmProviderStatus::NeedsAuthentication
+            }
+            Ok(_) => HsmProviderStatus::Ready,
+            Err(e) => HsmProviderStatus::Error(e.to_string()),
+        })
+    }
+
+    async fn close(&self) -> Result<(), HsmError> {
+        Ok(())
+    }
+
+    async fn execute_operation(&self, request: HsmRequest) -> Result<HsmResponse, HsmError> {
+        Err(Self::unsupported(&format!("{:?}", request.operation)))
+    }
+
+    async fn perform_health_check(&self) -> Result<bool, HsmError> {
+        // The default check generates and signs with a key, which devices refuse
+        Ok(self.get_status().await? == HsmProviderStatus::Ready)
+    }
+}
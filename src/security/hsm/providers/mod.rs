@@ -3,6 +3,9 @@
 // Each provider implements the HsmProvider trait with specific security features
 
 pub mod bitcoin;
+// Hardware wallets driven through HWI; signs with wallet PSBT support
+#[cfg(all(feature = "hsm-external", feature = "bitcoin"))]
+pub mod external;
 pub mod hardware;
 pub mod ledger;
 pub mod pkcs11;
@@ -14,6 +17,8 @@ pub mod tpm;
 
 // Re-export provider structs for use by other modules
 pub use self::bitcoin::BitcoinHsmProvider;
+#[cfg(all(feature = "hsm-external", feature = "bitcoin"))]
+pub use self::external::ExternalSignerHsmProvider;
 pub use self::hardware::HardwareHsmProvider;
 #[cfg(feature = "dev-sim")]
 pub use self::simulator::SimulatorHsmProvider;
@@ -8,13 +8,13 @@ use bitcoin::{psbt::Psbt, Address, CompressedPublicKey, Network};
 use chrono::Utc;
 use rand::prelude::*;
 use rand::rngs::OsRng;
-use sha2::{Digest, Sha256};
 use std::str::FromStr;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::time::sleep;
 use uuid::Uuid;
 
+use crate::security::crypto::message_from_hashed;
 use crate::security::hsm::config::SimulatorConfig;
 use crate::security::hsm::error::HsmError;
 use crate::security::hsm::provider::{
@@ -340,14 +340,8 @@ impl HsmProvider for SimulatorHsmProvider {
                 let secret_key = SecretKey::from_slice(private_key_data)
                     .map_err(|e| HsmError::SigningError(format!("Invalid key data: {e}")))?;
 
-                // Create message hash
-                let mut hasher = Sha256::new();
-                hasher.update(data);
-                let message_hash = hasher.finalize();
-
-                // Sign the hash
-                let message = bitcoin::secp256k1::Message::from_digest_slice(&message_hash)
-                    .map_err(|e| HsmError::SigningError(format!("Invalid message hash: {e}")))?;
+                // Sign the SHA-256 hash of the data
+                let message = message_from_hashed(data);
 
                 let signature = self.secp.sign_ecdsa(&message, &secret_key);
                 Ok(signature.serialize_der().to_vec())
@@ -387,11 +381,6 @@ impl HsmProvider for SimulatorHsmProvider {
                     KeyType::Ec {
                         curve: crate::security::hsm::provider::EcCurve::Secp256k1,
                     } => {
-                        // Create message hash
-                        let mut hasher = Sha256::new();
-                        hasher.update(data);
-                        let message_hash = hasher.finalize();
-
                         // Get the serialized public key from key storage or regenerate
                         let pubkey_bytes = self.export_public_key(key_id).await?;
                         let public_key = PublicKey::from_slice(&pubkey_bytes).map_err(|e| {
@@ -407,10 +396,7 @@ impl HsmProvider for SimulatorHsmProvider {
                             })?;
 
                         // Verify
-                        let message = bitcoin::secp256k1::Message::from_digest_slice(&message_hash)
-                            .map_err(|e| {
-                                HsmError::VerificationError(format!("Invalid message hash: {e}"))
-                            })?;
+                        let message = message_from_hashed(data);
 
                         match self.secp.verify_ecdsa(&message, &sig, &public_key) {
                             Ok(_) => Ok(true),
@@ -40,7 +40,7 @@ use zeroize::Zeroize;
 // [AIR-3][AIS-3][BPC-3][RES-3] Import Bitcoin types for software HSM implementation
 // This follows official Bitcoin Improvement Proposals (BIPs) standards for secure HSM implementation
 use bitcoin::{
-    secp256k1::{Message, PublicKey, Secp256k1, SecretKey},
+    secp256k1::{PublicKey, Secp256k1, SecretKey},
     Network,
 };
 
@@ -57,12 +57,13 @@ use crate::security::hsm::{
 };
 
 // Import from parent modules
+use crate::security::crypto::message_from_hashed;
 use crate::security::hsm::audit::AuditLogger;
 use crate::security::hsm::config::SoftHsmConfig;
 use crate::security::hsm::error::{AuditEventResult, AuditEventSeverity, AuditEventType};
 use chrono::{DateTime, Utc};
 use sha2::Digest;
-use sha2::{Sha256, Sha384};
+use sha2::Sha384;
 
 use tokio::sync::Mutex;
 
@@ -435,14 +436,8 @@ impl SoftwareHsmProvider {
                 let secret_key = SecretKey::from_slice(secret_bytes)
                     .map_err(|e| HsmError::SigningError(format!("Invalid key data: {e}")))?;
 
-                // Create message hash
-                let mut hasher = Sha256::new();
-                hasher.update(data);
-                let message_hash = hasher.finalize();
-
-                // Sign the hash
-                let message = bitcoin::secp256k1::Message::from_digest_slice(&message_hash)
-                    .map_err(|e| HsmError::SigningError(format!("Invalid message hash: {e}")))?;
+                // Sign the SHA-256 hash of the data
+                let message = message_from_hashed(data);
 
                 let signature = self.secp.sign_ecdsa(&message, &secret_key);
 
@@ -496,11 +491,6 @@ impl SoftwareHsmProvider {
                     KeyType::Ec {
                         curve: EcCurve::Secp256k1,
                     } => {
-                        // Create message hash
-                        let mut hasher = Sha256::new();
-                        hasher.update(data);
-                        let message_hash = hasher.finalize();
-
                         // Get the serialized public key from key storage or regenerate
                         let pubkey_bytes = self.export_public_key(key_id).await?;
                         let public_key = PublicKey::from_slice(&pubkey_bytes).map_err(|e| {
@@ -516,9 +506,7 @@ impl SoftwareHsmProvider {
                             })?;
 
                         // Verify the signature
-                        let message = Message::from_digest_slice(&message_hash).map_err(|e| {
-                            HsmError::VerificationError(format!("Invalid message hash: {e}"))
-                        })?;
+                        let message = message_from_hashed(data);
 
                         match self.secp.verify_ecdsa(&message, &sig, &public_key) {
                             Ok(()) => Ok(true),
@@ -673,14 +661,8 @@ impl HsmProvider for SoftwareHsmProvider {
                 let secret_key = SecretKey::from_slice(&key_data)
                     .map_err(|e| HsmError::InvalidKeyData(format!("Invalid secret key: {e}")))?;
 
-                // Create message hash
-                let mut hasher = Sha256::new();
-                hasher.update(data);
-                let message_hash = hasher.finalize();
-
                 // Create message for signing
-                let message = Message::from_digest_slice(&message_hash)
-                    .map_err(|e| HsmError::SigningError(format!("Invalid message: {e}")))?;
+                let message = message_from_hashed(data);
 
                 // Sign the message (compact 64-byte signature)
                 let signature = self.secp.sign_ecdsa(&message, &secret_key);
@@ -713,11 +695,6 @@ impl HsmProvider for SoftwareHsmProvider {
                     KeyType::Ec {
                         curve: EcCurve::Secp256k1,
                     } => {
-                        // Create message hash
-                        let mut hasher = Sha256::new();
-                        hasher.update(data);
-                        let message_hash = hasher.finalize();
-
                         // Get the serialized public key from key storage or regenerate
                         let pubkey_bytes = self.export_public_key(key_id).await?;
                         let public_key = PublicKey::from_slice(&pubkey_bytes).map_err(|e| {
@@ -731,9 +708,7 @@ impl HsmProvider for SoftwareHsmProvider {
                             })?;
 
                         // Create message for verification
-                        let message = Message::from_digest_slice(&message_hash).map_err(|e| {
-                            HsmError::VerificationError(format!("Invalid message: {e}"))
-                        })?;
+                        let message = message_from_hashed(data);
 
                         // Verify the signature
                         Ok(self.secp.verify_ecdsa(&message, &sig, &public_key).is_ok())
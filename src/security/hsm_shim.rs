@@ -7,6 +7,7 @@
 //! [AIR-3][AIS-3][BPC-3][RES-3] Enhanced security provider implementations
 //! with proper trait implementations and validation.
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -28,7 +29,7 @@ pub struct HsmStubError {
 }
 
 /// [AIR-3][AIS-3][BPC-3][SEC-2] Security classification for HSM errors
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum SecurityLevel {
     /// Informational security message
     #[default]
@@ -339,7 +340,8 @@ impl LedgerHsmProvider {
 
 /// Enhanced HSM Configuration
 /// [AIR-3][AIS-3][BPC-3][SEC-2] Improved with security configuration options
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct HsmConfig {
     /// Provider type (software, hardware, etc.)
     pub provider_type: String,
@@ -12,6 +12,9 @@ pub mod constant_time;
 // Cryptographic operations module
 pub mod crypto;
 
+// Self-tested CSPRNG for keys, seeds and nonces
+pub mod rng;
+
 // Software-based HSM implementation (real cryptographic operations)
 pub mod software_hsm;
 
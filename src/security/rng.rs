@@ -0,0 +1,122 @@
+// Secure Random Number Generation
+// [AIR-3][AIS-3][BPC-3][AIT-3]
+//
+// Single entry point for randomness used in keys, seeds and nonces. Every
+// generator is a ChaCha20 CSPRNG freshly seeded from the operating system.
+// The OS source is checked once per process with the FIPS 140-2 monobit test
+// and a repeated-output check; if it looks broken, the failure is logged and
+// no generator is handed out.
+use once_cell::sync::Lazy;
+use rand::rngs::OsRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use thiserror::Error;
+
+/// Cryptographically secure generator returned by [`secure_rng`]
+pub type SecureRng = ChaCha20Rng;
+
+/// Bits sampled by the monobit test, as in FIPS 140-2
+const MONOBIT_SAMPLE_BITS: usize = 20_000;
+
+/// Range of set bits a healthy source stays strictly within
+const MONOBIT_BOUNDS: (u32, u32) = (9_725, 10_275);
+
+/// RNG self-test error type
+#[derive(Debug, Clone, Error)]
+pub enum RngError {
+    #[error("Entropy source unavailable: {0}")]
+    Entropy(String),
+
+    #[error("RNG self-test failed: {0}")]
+    SelfTest(String),
+}
+
+/// Result of the startup self-test, run on first use
+static SELF_TEST: Lazy<Result<(), RngError>> = Lazy::new(self_test);
+
+/// Generator for key, seed and nonce material
+///
+/// # Panics
+///
+/// Panics if the OS entropy source failed the startup self-test or cannot be
+/// read, since no key material may be produced from it.
+pub fn secure_rng() -> SecureRng {
+    if let Err(e) = &*SELF_TEST {
+        log::error!("Refusing to generate randomness: {e}");
+        panic!("{e}");
+    }
+    ChaCha20Rng::from_rng(OsRng).unwrap_or_else(|e| {
+        log::error!("Failed to seed secure RNG: {e}");
+        panic!("Failed to seed secure RNG: {e}")
+    })
+}
+
+/// Check the OS entropy source with the monobit and repeated-output tests
+pub fn self_test() -> Result<(), RngError> {
+    let mut sample = [0u8; MONOBIT_SAMPLE_BITS / 8];
+    OsRng
+        .try_fill_bytes(&mut sample)
+        .map_err(|e| RngError::Entropy(e.to_string()))?;
+    monobit(&sample)?;
+
+    // Consecutive blocks from a working source never repeat
+    let mut first = [0u8; 32];
+    let mut second = [0u8; 32];
+    for block in [&mut first, &mut second] {
+        OsRng
+            .try_fill_bytes(block)
+            .map_err(|e| RngError::Entropy(e.to_string()))?;
+    }
+    if first == second {
+        return Err(RngError::SelfTest(
+            "Entropy source repeated its output".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// FIPS 140-2 monobit test over a 20,000-bit sample
+fn monobit(sample: &[u8]) -> Result<(), RngError> {
+    let ones: u32 = sample.iter().map(|byte| byte.count_ones()).sum();
+    let (low, high) = MONOBIT_BOUNDS;
+    if ones <= low || ones >= high {
+        return Err(RngError::SelfTest(format!(
+            "Monobit test counted {ones} set bits in {} (expected {low}..{high})",
+            sample.len() * 8
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        self_test().unwrap();
+        assert!(SELF_TEST.is_ok());
+    }
+
+    #[test]
+    fn test_successive_draws_differ() {
+        let mut rng = secure_rng();
+        let mut first = [0u8; 32];
+        let mut second = [0u8; 32];
+        rng.fill_bytes(&mut first);
+        rng.fill_bytes(&mut second);
+        assert_ne!(first, second);
+
+        // Independently seeded generators diverge too
+        let mut other = [0u8; 32];
+        secure_rng().fill_bytes(&mut other);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_monobit_rejects_biased_sample() {
+        assert!(monobit(&[0u8; MONOBIT_SAMPLE_BITS / 8]).is_err());
+        assert!(monobit(&[0xff; MONOBIT_SAMPLE_BITS / 8]).is_err());
+        assert!(monobit(&[0x0f; MONOBIT_SAMPLE_BITS / 8]).is_ok());
+    }
+}
@@ -12,13 +12,15 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use super::rng::secure_rng;
+
 // Cryptographic dependencies
 use aes_gcm::aead::{Aead, KeyInit};
 use aes_gcm::{Aes256Gcm, Key, Nonce};
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use hmac::Hmac;
 use pbkdf2;
-use rand::{rngs::OsRng, RngCore};
+use rand::RngCore;
 // REMOVED: RSA dependency due to RUSTSEC-2023-0071 timing attack vulnerability
 // use rsa::{pkcs1::{EncodeRsaPrivateKey, EncodeRsaPublicKey}, RsaPrivateKey, RsaPublicKey,};
 use ring::signature::{self, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
@@ -294,7 +296,7 @@ impl SoftwareHSM {
         } else {
             // Generate new master key
             let mut key = [0u8; 32];
-            OsRng.fill_bytes(&mut key);
+            secure_rng().fill_bytes(&mut key);
 
             // Encrypt and save master key
             let password = "default_hsm_password"; // In production, get from secure source
@@ -521,8 +523,7 @@ impl SoftwareHSM {
 
         info!("Generating Ed25519 keypair: {key_id}");
 
-        let mut rng = OsRng;
-        let signing_key = SigningKey::generate(&mut rng);
+        let signing_key = SigningKey::generate(&mut secure_rng());
         let verifying_key = signing_key.verifying_key();
 
         // Encrypt seed if configured
@@ -590,7 +591,7 @@ impl SoftwareHSM {
         info!("Generating AES-256 symmetric key: {key_id}");
 
         let mut key = [0u8; 32];
-        OsRng.fill_bytes(&mut key);
+        secure_rng().fill_bytes(&mut key);
 
         // Encrypt key if configured
         let encrypted_key = if self.config.encrypt_keys_at_rest {
@@ -664,7 +665,7 @@ impl SoftwareHSM {
 
         // Generate random nonce
         let mut nonce_bytes = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce_bytes);
+        secure_rng().fill_bytes(&mut nonce_bytes);
         let nonce = Nonce::from_slice(&nonce_bytes);
 
         // Create cipher
@@ -990,7 +991,7 @@ impl SoftwareHSM {
         let cipher = Aes256Gcm::new(key);
 
         let mut nonce_bytes = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce_bytes);
+        secure_rng().fill_bytes(&mut nonce_bytes);
         let nonce = Nonce::from_slice(&nonce_bytes);
 
         let mut ciphertext = cipher
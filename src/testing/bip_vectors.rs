@@ -0,0 +1,648 @@
+//! BIP-341 test vector runner
+//!
+//! Runs the BIP-341 wallet test vectors (`bip-0341/wallet-test-vectors.json`)
+//! against the crate's taproot code and reports which vectors pass:
+//!
+//! - `scriptPubKey` vectors check leaf hashes, the merkle root and the tweak
+//!   computed by [`crate::bitcoin::bip341`], then the output key, script,
+//!   address and control blocks of the spend info the wallet builds.
+//! - `keyPathSpending` vectors check the tweak, the tweaked private key, the
+//!   signature hash and the resulting key-path witness of each input.
+//!
+//! [`run_bip341_vectors`] uses the vectors bundled with the crate; the upstream
+//! file can be run as is with [`run_bip341_vectors_from`].
+
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::hashes::Hash;
+use bitcoin::key::{Keypair, TapTweak};
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{self, TapNodeHash, TaprootBuilder};
+use bitcoin::{Address, Amount, Network, ScriptBuf, Transaction, TxOut};
+use serde::Deserialize;
+use std::fmt;
+
+use crate::bitcoin::bip341::{Bip341Taproot, LeafVersion, TaprootBranch, TaprootLeaf};
+use crate::compliance::ComplianceStatus;
+use crate::{AnyaError, AnyaResult};
+
+/// The BIP-341 wallet test vectors bundled with the crate
+///
+/// Only the `scriptPubKey` section is bundled so far. Until the upstream
+/// `keyPathSpending` section is added, runs over this file report
+/// [`ComplianceStatus::Partial`].
+pub const BIP341_WALLET_VECTORS: &str = include_str!("../../tests/data/bip341_wallet_vectors.json");
+
+/// Outcome of one test vector
+#[derive(Debug, Clone)]
+pub struct VectorResult {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Outcome of a test vector run
+#[derive(Debug, Clone, Default)]
+pub struct VectorReport {
+    pub results: Vec<VectorResult>,
+}
+
+impl VectorReport {
+    /// Whether every vector passed
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed)
+    }
+
+    /// Names of the vectors that failed
+    pub fn failures(&self) -> Vec<&str> {
+        self.results
+            .iter()
+            .filter(|result| !result.passed)
+            .map(|result| result.name.as_str())
+            .collect()
+    }
+
+    /// Share of vectors passed, from 0 to 1
+    pub fn score(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        let passed = self.results.iter().filter(|result| result.passed).count();
+        passed as f64 / self.results.len() as f64
+    }
+
+    /// Compliance a run supports
+    ///
+    /// `Full` needs both the `scriptPubKey` and the `keyPathSpending` vectors
+    /// to be present and pass; a run missing either covers only part of
+    /// BIP-341 and is `Partial`, as is one with some failures.
+    pub fn compliance_status(&self) -> ComplianceStatus {
+        let covers = |section: &str| {
+            self.results
+                .iter()
+                .any(|result| result.name.starts_with(section))
+        };
+        if !self.results.iter().any(|result| result.passed) {
+            ComplianceStatus::Missing
+        } else if self.passed() && covers("scriptPubKey[") && covers("keyPathSpending[") {
+            ComplianceStatus::Full
+        } else {
+            ComplianceStatus::Partial
+        }
+    }
+}
+
+impl fmt::Display for VectorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            let mark = if result.passed { "✅" } else { "❌" };
+            writeln!(f, "{mark} {}: {}", result.name, result.detail)?;
+        }
+        let passed = self.results.len() - self.failures().len();
+        writeln!(f, "{passed}/{} vectors passed", self.results.len())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WalletVectors {
+    #[serde(default)]
+    script_pub_key: Vec<ScriptPubKeyVector>,
+    #[serde(default)]
+    key_path_spending: Vec<KeyPathSpendingVector>,
+}
+
+#[derive(Deserialize)]
+struct ScriptPubKeyVector {
+    given: ScriptPubKeyGiven,
+    intermediary: ScriptPubKeyIntermediary,
+    expected: ScriptPubKeyExpected,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScriptPubKeyGiven {
+    internal_pubkey: String,
+    script_tree: Option<ScriptTree>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScriptTree {
+    Leaf(TreeLeaf),
+    Branch(Vec<ScriptTree>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TreeLeaf {
+    id: usize,
+    script: String,
+    leaf_version: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScriptPubKeyIntermediary {
+    #[serde(default)]
+    leaf_hashes: Vec<String>,
+    merkle_root: Option<String>,
+    tweak: String,
+    tweaked_pubkey: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScriptPubKeyExpected {
+    script_pub_key: String,
+    bip_address: String,
+    #[serde(default)]
+    script_path_control_blocks: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyPathSpendingVector {
+    given: KeyPathGiven,
+    input_spending: Vec<InputSpendingVector>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyPathGiven {
+    raw_unsigned_tx: String,
+    utxos_spent: Vec<SpentOutput>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpentOutput {
+    script_pub_key: String,
+    amount_sats: u64,
+}
+
+#[derive(Deserialize)]
+struct InputSpendingVector {
+    given: InputGiven,
+    intermediary: InputIntermediary,
+    expected: InputExpected,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputGiven {
+    txin_index: usize,
+    internal_privkey: String,
+    merkle_root: Option<String>,
+    hash_type: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputIntermediary {
+    internal_pubkey: String,
+    tweak: String,
+    tweaked_privkey: String,
+    sig_hash: String,
+}
+
+#[derive(Deserialize)]
+struct InputExpected {
+    witness: Vec<String>,
+}
+
+/// Mismatches found while checking one vector
+#[derive(Default)]
+struct Checks {
+    run: usize,
+    failed: Vec<String>,
+}
+
+impl Checks {
+    fn check(&mut self, name: &str, actual: impl fmt::Display, expected: &str) {
+        self.run += 1;
+        let actual = actual.to_string();
+        if actual != expected {
+            self.failed
+                .push(format!("{name} is {actual}, expected {expected}"));
+        }
+    }
+
+    fn into_result(self, name: String) -> VectorResult {
+        let passed = self.failed.is_empty();
+        let detail = if passed {
+            format!("{} checks passed", self.run)
+        } else {
+            self.failed.join("; ")
+        };
+        VectorResult {
+            name,
+            passed,
+            detail,
+        }
+    }
+}
+
+/// Run the bundled BIP-341 wallet test vectors
+pub fn run_bip341_vectors() -> VectorReport {
+    run_bip341_vectors_from(BIP341_WALLET_VECTORS).expect("bundled BIP-341 vectors are well formed")
+}
+
+/// Run BIP-341 wallet test vectors in the upstream JSON format
+pub fn run_bip341_vectors_from(json: &str) -> AnyaResult<VectorReport> {
+    let vectors: WalletVectors = serde_json::from_str(json)
+        .map_err(|e| AnyaError::InvalidInput(format!("Malformed BIP-341 vectors: {e}")))?;
+
+    let mut report = VectorReport::default();
+    for (i, vector) in vectors.script_pub_key.iter().enumerate() {
+        let name = format!("scriptPubKey[{i}]");
+        report.results.push(match run_script_pub_key(vector) {
+            Ok(checks) => checks.into_result(name),
+            Err(e) => malformed(name, e),
+        });
+    }
+    for (i, vector) in vectors.key_path_spending.iter().enumerate() {
+        match prepare_key_path(vector) {
+            Ok((tx, utxos)) => {
+                for input in &vector.input_spending {
+                    let name = format!("keyPathSpending[{i}] input {}", input.given.txin_index);
+                    report
+                        .results
+                        .push(match run_key_path_input(&tx, &utxos, input) {
+                            Ok(checks) => checks.into_result(name),
+                            Err(e) => malformed(name, e),
+                        });
+                }
+            }
+            Err(e) => report
+                .results
+                .push(malformed(format!("keyPathSpending[{i}]"), e)),
+        }
+    }
+    Ok(report)
+}
+
+fn malformed(name: String, reason: String) -> VectorResult {
+    VectorResult {
+        name,
+        passed: false,
+        detail: format!("malformed vector: {reason}"),
+    }
+}
+
+fn run_script_pub_key(vector: &ScriptPubKeyVector) -> Result<Checks, String> {
+    let secp = Secp256k1::verification_only();
+    let internal_key: XOnlyPublicKey = vector
+        .given
+        .internal_pubkey
+        .parse()
+        .map_err(|e| format!("internal key: {e}"))?;
+    let mut checks = Checks::default();
+
+    let mut leaves = Vec::new();
+    if let Some(tree) = &vector.given.script_tree {
+        collect_leaves(tree, 0, &mut leaves)?;
+    }
+    leaves.sort_by_key(|(_, leaf)| leaf.id);
+    let mut scripts = Vec::with_capacity(leaves.len());
+    for (depth, leaf) in &leaves {
+        let script = hex::decode(&leaf.script).map_err(|e| format!("leaf {}: {e}", leaf.id))?;
+        let version = taproot::LeafVersion::from_consensus(leaf.leaf_version)
+            .map_err(|e| format!("leaf {}: {e}", leaf.id))?;
+        scripts.push((*depth, ScriptBuf::from_bytes(script), version));
+    }
+
+    // Hashes computed by the crate's BIP-341 implementation
+    for ((_, script, version), expected) in scripts.iter().zip(&vector.intermediary.leaf_hashes) {
+        let leaf = TaprootLeaf::new(LeafVersion::from(version.to_consensus()), script.to_bytes());
+        checks.check("leaf hash", hex::encode(leaf.compute_leaf_hash()), expected);
+    }
+    let merkle_root = match &vector.given.script_tree {
+        Some(tree) => Some(node_hash(tree)?),
+        None => None,
+    };
+    checks.check(
+        "merkle root",
+        merkle_root.map(hex::encode).unwrap_or_default(),
+        vector
+            .intermediary
+            .merkle_root
+            .as_deref()
+            .unwrap_or_default(),
+    );
+    let tweak = Bip341Taproot::with_internal_key(internal_key)
+        .compute_taproot_tweak(&internal_key, merkle_root);
+    checks.check("tweak", hex::encode(tweak), &vector.intermediary.tweak);
+
+    // Spend info as built for wallet outputs
+    let mut builder = TaprootBuilder::new();
+    for (depth, script, version) in &scripts {
+        builder = builder
+            .add_leaf_with_ver(*depth, script.clone(), *version)
+            .map_err(|e| format!("script tree: {e}"))?;
+    }
+    let spend_info = builder
+        .finalize(&secp, internal_key)
+        .map_err(|_| "script tree is incomplete".to_string())?;
+    let output_key = spend_info.output_key();
+    checks.check(
+        "tweaked key",
+        output_key.to_x_only_public_key(),
+        &vector.intermediary.tweaked_pubkey,
+    );
+    checks.check(
+        "scriptPubKey",
+        hex::encode(ScriptBuf::new_p2tr_tweaked(output_key).as_bytes()),
+        &vector.expected.script_pub_key,
+    );
+    checks.check(
+        "address",
+        Address::p2tr_tweaked(output_key, Network::Bitcoin),
+        &vector.expected.bip_address,
+    );
+    for ((_, script, version), expected) in scripts
+        .iter()
+        .zip(&vector.expected.script_path_control_blocks)
+    {
+        let control_block = spend_info
+            .control_block(&(script.clone(), *version))
+            .map(|control_block| hex::encode(control_block.serialize()))
+            .unwrap_or_default();
+        checks.check("control block", control_block, expected);
+    }
+    Ok(checks)
+}
+
+fn collect_leaves<'a>(
+    tree: &'a ScriptTree,
+    depth: u8,
+    leaves: &mut Vec<(u8, &'a TreeLeaf)>,
+) -> Result<(), String> {
+    match tree {
+        ScriptTree::Leaf(leaf) => leaves.push((depth, leaf)),
+        ScriptTree::Branch(children) if children.len() == 2 => {
+            for child in children {
+                collect_leaves(child, depth + 1, leaves)?;
+            }
+        }
+        ScriptTree::Branch(children) => {
+            return Err(format!("branch with {} children", children.len()))
+        }
+    }
+    Ok(())
+}
+
+fn node_hash(tree: &ScriptTree) -> Result<[u8; 32], String> {
+    match tree {
+        ScriptTree::Leaf(leaf) => {
+            let script = hex::decode(&leaf.script).map_err(|e| format!("leaf {}: {e}", leaf.id))?;
+            Ok(TaprootLeaf::new(LeafVersion::from(leaf.leaf_version), script).compute_leaf_hash())
+        }
+        ScriptTree::Branch(children) => match children.as_slice() {
+            [left, right] => {
+                Ok(TaprootBranch::new(node_hash(left)?, node_hash(right)?).compute_branch_hash())
+            }
+            _ => Err(format!("branch with {} children", children.len())),
+        },
+    }
+}
+
+fn prepare_key_path(vector: &KeyPathSpendingVector) -> Result<(Transaction, Vec<TxOut>), String> {
+    let tx: Transaction = deserialize_hex(&vector.given.raw_unsigned_tx)
+        .map_err(|e| format!("unsigned transaction: {e}"))?;
+    let utxos = vector
+        .given
+        .utxos_spent
+        .iter()
+        .map(|utxo| {
+            Ok(TxOut {
+                value: Amount::from_sat(utxo.amount_sats),
+                script_pubkey: ScriptBuf::from_hex(&utxo.script_pub_key)
+                    .map_err(|e| format!("spent output: {e}"))?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok((tx, utxos))
+}
+
+fn run_key_path_input(
+    tx: &Transaction,
+    utxos: &[TxOut],
+    input: &InputSpendingVector,
+) -> Result<Checks, String> {
+    let secp = Secp256k1::new();
+    let given = &input.given;
+    let secret_key: SecretKey = given
+        .internal_privkey
+        .parse()
+        .map_err(|e| format!("internal private key: {e}"))?;
+    let merkle_root = given
+        .merkle_root
+        .as_deref()
+        .map(|root| root.parse::<TapNodeHash>())
+        .transpose()
+        .map_err(|e| format!("merkle root: {e}"))?;
+    let hash_type = TapSighashType::from_consensus_u8(given.hash_type)
+        .map_err(|e| format!("hash type: {e}"))?;
+    let mut checks = Checks::default();
+
+    let keypair = Keypair::from_secret_key(&secp, &secret_key);
+    let (internal_key, _) = keypair.x_only_public_key();
+    checks.check(
+        "internal key",
+        internal_key,
+        &input.intermediary.internal_pubkey,
+    );
+    let tweak = Bip341Taproot::with_internal_key(internal_key)
+        .compute_taproot_tweak(&internal_key, merkle_root.map(|root| root.to_byte_array()));
+    checks.check("tweak", hex::encode(tweak), &input.intermediary.tweak);
+    let tweaked = keypair.tap_tweak(&secp, merkle_root).to_keypair();
+    checks.check(
+        "tweaked private key",
+        hex::encode(tweaked.secret_bytes()),
+        &input.intermediary.tweaked_privkey,
+    );
+
+    let sighash = SighashCache::new(tx)
+        .taproot_key_spend_signature_hash(given.txin_index, &Prevouts::All(utxos), hash_type)
+        .map_err(|e| format!("signature hash: {e}"))?;
+    checks.check("signature hash", sighash, &input.intermediary.sig_hash);
+
+    // Expected signatures are made without auxiliary randomness
+    let signature = taproot::Signature {
+        signature: secp.sign_schnorr_no_aux_rand(&Message::from(sighash), &tweaked),
+        sighash_type: hash_type,
+    };
+    checks.check(
+        "witness",
+        hex::encode(signature.to_vec()),
+        input
+            .expected
+            .witness
+            .first()
+            .map(String::as_str)
+            .unwrap_or_default(),
+    );
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::{OutPoint, Sequence, TxIn, Txid, Witness};
+    use serde_json::json;
+
+    #[test]
+    fn test_bundled_script_pub_key_vectors_pass() {
+        let report = run_bip341_vectors();
+        assert_eq!(report.results.len(), 7);
+        assert!(report.passed(), "{report}");
+        assert_eq!(report.score(), 1.0);
+        assert!(matches!(
+            report.compliance_status(),
+            ComplianceStatus::Partial
+        ));
+    }
+
+    #[test]
+    fn test_compliance_status_needs_both_sections() {
+        assert!(matches!(
+            VectorReport::default().compliance_status(),
+            ComplianceStatus::Missing
+        ));
+        let result = |name: &str, passed| VectorResult {
+            name: name.to_string(),
+            passed,
+            detail: String::new(),
+        };
+        let report = |results| VectorReport { results };
+
+        assert!(matches!(
+            report(vec![result("scriptPubKey[0]", true)]).compliance_status(),
+            ComplianceStatus::Partial
+        ));
+        assert!(matches!(
+            report(vec![
+                result("scriptPubKey[0]", true),
+                result("keyPathSpending[0] input 0", false),
+            ])
+            .compliance_status(),
+            ComplianceStatus::Partial
+        ));
+        assert!(matches!(
+            report(vec![
+                result("scriptPubKey[0]", true),
+                result("keyPathSpending[0] input 0", true),
+            ])
+            .compliance_status(),
+            ComplianceStatus::Full
+        ));
+    }
+
+    #[test]
+    fn test_mismatch_is_reported() {
+        let mut vectors: serde_json::Value = serde_json::from_str(BIP341_WALLET_VECTORS).unwrap();
+        vectors["scriptPubKey"][3]["expected"]["scriptPathControlBlocks"][1] = json!("c0");
+        vectors["scriptPubKey"][5]["given"]["internalPubkey"] = json!("00");
+
+        let report = run_bip341_vectors_from(&vectors.to_string()).unwrap();
+        assert_eq!(
+            report.failures(),
+            vec!["scriptPubKey[3]", "scriptPubKey[5]"]
+        );
+        assert!(report.results[3].detail.starts_with("control block is fa"));
+        assert!(report.results[5].detail.starts_with("malformed vector"));
+        assert!(report.to_string().ends_with("5/7 vectors passed\n"));
+        assert!(run_bip341_vectors_from(r#"{"scriptPubKey": {}}"#).is_err());
+    }
+
+    #[test]
+    fn test_key_path_spending_vector() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let keypair = Keypair::from_secret_key(&secp, &secret_key);
+        let (internal_key, _) = keypair.x_only_public_key();
+        let merkle_root = TapNodeHash::from_byte_array([0x22; 32]);
+        let utxos = vec![
+            TxOut {
+                value: Amount::from_sat(50_000),
+                script_pubkey: ScriptBuf::new_p2tr(&secp, internal_key, Some(merkle_root)),
+            },
+            TxOut {
+                value: Amount::from_sat(20_000),
+                script_pubkey: ScriptBuf::new_p2tr(&secp, internal_key, None),
+            },
+        ];
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: (0..2)
+                .map(|vout| TxIn {
+                    previous_output: OutPoint::new(Txid::all_zeros(), vout),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: Amount::from_sat(69_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+
+        let spend = |index: usize, root: Option<TapNodeHash>, hash_type: TapSighashType| {
+            let tweak = Bip341Taproot::with_internal_key(internal_key)
+                .compute_taproot_tweak(&internal_key, root.map(|root| root.to_byte_array()));
+            let tweaked = keypair.tap_tweak(&secp, root).to_keypair();
+            let sighash = SighashCache::new(&tx)
+                .taproot_key_spend_signature_hash(index, &Prevouts::All(&utxos), hash_type)
+                .unwrap();
+            let signature = taproot::Signature {
+                signature: secp.sign_schnorr_no_aux_rand(&Message::from(sighash), &tweaked),
+                sighash_type: hash_type,
+            };
+            json!({
+                "given": {
+                    "txinIndex": index,
+                    "internalPrivkey": hex::encode(secret_key.secret_bytes()),
+                    "merkleRoot": root.map(|root| root.to_string()),
+                    "hashType": hash_type as u8,
+                },
+                "intermediary": {
+                    "internalPubkey": internal_key.to_string(),
+                    "tweak": hex::encode(tweak),
+                    "tweakedPrivkey": hex::encode(tweaked.secret_bytes()),
+                    "sigHash": sighash.to_string(),
+                },
+                "expected": { "witness": [hex::encode(signature.to_vec())] },
+            })
+        };
+        let mut vectors = json!({
+            "keyPathSpending": [{
+                "given": {
+                    "rawUnsignedTx": bitcoin::consensus::encode::serialize_hex(&tx),
+                    "utxosSpent": utxos.iter().map(|utxo| json!({
+                        "scriptPubKey": utxo.script_pubkey.to_hex_string(),
+                        "amountSats": utxo.value.to_sat(),
+                    })).collect::<Vec<_>>(),
+                },
+                "inputSpending": [
+                    spend(0, Some(merkle_root), TapSighashType::Default),
+                    spend(1, None, TapSighashType::AllPlusAnyoneCanPay),
+                ],
+            }],
+        });
+
+        let report = run_bip341_vectors_from(&vectors.to_string()).unwrap();
+        assert!(report.passed(), "{report}");
+        assert_eq!(report.results[1].name, "keyPathSpending[0] input 1");
+
+        vectors["keyPathSpending"][0]["inputSpending"][0]["intermediary"]["sigHash"] =
+            json!("00".repeat(32));
+        let report = run_bip341_vectors_from(&vectors.to_string()).unwrap();
+        assert_eq!(report.failures(), vec!["keyPathSpending[0] input 0"]);
+        assert!(report.results[0].detail.starts_with("signature hash is"));
+    }
+}
@@ -0,0 +1,339 @@
+//! Signet faucet client for test workflows
+//!
+//! [`FaucetClient::request_coins`] asks a faucet to pay an address and returns
+//! the txid of the funding transaction. The address is checked against the
+//! configured network before anything is sent. Server errors and rate limiting
+//! are retried with exponential backoff; a `Retry-After` header, when present,
+//! replaces the computed delay.
+//!
+//! The faucet is expected to accept `POST {endpoint}` with a JSON body
+//! `{"address": "tb1...", "amount": 10000}`, the amount in satoshis and omitted
+//! for the faucet default, and to answer with `{"txid": "..."}` or the bare txid.
+
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, Network, Txid};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::bitcoin::broadcast::exponential_backoff;
+use crate::AnyaError;
+
+/// Faucet client errors
+#[derive(Debug, Error)]
+pub enum FaucetError {
+    #[error("invalid address {address}: {reason}")]
+    InvalidAddress { address: String, reason: String },
+    #[error("address {address} is not valid on {network}")]
+    WrongNetwork { address: String, network: Network },
+    #[error("faucet still rate limited after {attempts} attempts")]
+    RateLimited {
+        attempts: u32,
+        retry_after: Option<Duration>,
+    },
+    #[error("faucet rejected the request with {status}: {body}")]
+    Rejected { status: StatusCode, body: String },
+    #[error("faucet unavailable after {attempts} attempts: {reason}")]
+    Unavailable { attempts: u32, reason: String },
+    #[error("invalid faucet response: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<FaucetError> for AnyaError {
+    fn from(err: FaucetError) -> Self {
+        AnyaError::Bitcoin(err.to_string())
+    }
+}
+
+pub type FaucetResult<T> = Result<T, FaucetError>;
+
+/// Faucet endpoint and retry behaviour
+#[derive(Debug, Clone)]
+pub struct FaucetConfig {
+    /// URL the claim is posted to
+    pub endpoint: String,
+    /// Network requested addresses must belong to
+    pub network: Network,
+    /// Retries after the first attempt before giving up
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled after every failed attempt
+    pub initial_backoff: Duration,
+    /// Upper bound on the retry delay, also the longest `Retry-After` honoured
+    pub max_backoff: Duration,
+    /// Timeout of a single request
+    pub request_timeout: Duration,
+}
+
+impl FaucetConfig {
+    /// Signet faucet at `endpoint` with default retry behaviour
+    pub fn signet(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            network: Network::Signet,
+            max_retries: 5,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(60),
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Delay before the next attempt after `attempts` failed attempts
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        exponential_backoff(self.initial_backoff, self.max_backoff, attempts)
+    }
+}
+
+#[derive(Serialize)]
+struct FaucetRequest {
+    address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct FaucetResponse {
+    txid: Txid,
+}
+
+/// Why an attempt should be retried
+enum RetryReason {
+    RateLimited(Option<Duration>),
+    Unavailable(String),
+}
+
+/// Client requesting test coins from a faucet
+pub struct FaucetClient {
+    client: reqwest::Client,
+    config: FaucetConfig,
+}
+
+impl FaucetClient {
+    pub fn new(config: FaucetConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &FaucetConfig {
+        &self.config
+    }
+
+    /// Request `amount` satoshis, or the faucet default, to `address`
+    pub async fn request_coins(&self, address: &str, amount: Option<u64>) -> FaucetResult<Txid> {
+        let address = self.check_address(address)?;
+        let request = FaucetRequest {
+            address: address.to_string(),
+            amount,
+        };
+
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let reason = match self
+                .client
+                .post(&self.config.endpoint)
+                .timeout(self.config.request_timeout)
+                .json(&request)
+                .send()
+                .await
+            {
+                Ok(response) => match response.status() {
+                    status if status.is_success() => return parse_txid(response).await,
+                    StatusCode::TOO_MANY_REQUESTS => {
+                        RetryReason::RateLimited(retry_after(&response))
+                    }
+                    status if status.is_server_error() => {
+                        RetryReason::Unavailable(format!("faucet returned {status}"))
+                    }
+                    status => {
+                        let body = response.text().await.unwrap_or_default();
+                        return Err(FaucetError::Rejected { status, body });
+                    }
+                },
+                Err(e) => RetryReason::Unavailable(e.to_string()),
+            };
+
+            let delay = match &reason {
+                RetryReason::RateLimited(Some(delay)) => *delay,
+                _ => self.config.backoff(attempts),
+            };
+            if attempts > self.config.max_retries || delay > self.config.max_backoff {
+                return Err(match reason {
+                    RetryReason::RateLimited(retry_after) => FaucetError::RateLimited {
+                        attempts,
+                        retry_after,
+                    },
+                    RetryReason::Unavailable(reason) => {
+                        FaucetError::Unavailable { attempts, reason }
+                    }
+                });
+            }
+            log::debug!("Faucet attempt {attempts} failed, retrying in {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn check_address(&self, address: &str) -> FaucetResult<Address> {
+        let unchecked: Address<NetworkUnchecked> =
+            address.parse().map_err(|e| FaucetError::InvalidAddress {
+                address: address.to_string(),
+                reason: format!("{e}"),
+            })?;
+        unchecked
+            .require_network(self.config.network)
+            .map_err(|_| FaucetError::WrongNetwork {
+                address: address.to_string(),
+                network: self.config.network,
+            })
+    }
+}
+
+/// Delay requested by a `Retry-After` header given in seconds
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    seconds.trim().parse().ok().map(Duration::from_secs)
+}
+
+async fn parse_txid(response: Response) -> FaucetResult<Txid> {
+    let body = response
+        .text()
+        .await
+        .map_err(|e| FaucetError::InvalidResponse(e.to_string()))?;
+    if let Ok(FaucetResponse { txid }) = serde_json::from_str(&body) {
+        return Ok(txid);
+    }
+    body.trim()
+        .parse()
+        .map_err(|_| FaucetError::InvalidResponse(format!("no txid in {body:?}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const SIGNET_ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+    const MAINNET_ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+    fn client(server: &MockServer) -> FaucetClient {
+        FaucetClient::new(FaucetConfig {
+            initial_backoff: Duration::from_millis(1),
+            max_retries: 2,
+            ..FaucetConfig::signet(&format!("{}/api/claim", server.uri()))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_retries_after_rate_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/claim"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/claim"))
+            .and(body_json(
+                json!({ "address": SIGNET_ADDRESS, "amount": 10_000 }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "txid": TXID })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let txid = client(&server)
+            .request_coins(SIGNET_ADDRESS, Some(10_000))
+            .await
+            .unwrap();
+        assert_eq!(txid, TXID.parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_accepts_plain_txid_and_omits_default_amount() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_json(json!({ "address": SIGNET_ADDRESS })))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("{TXID}\n")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let txid = client(&server)
+            .request_coins(SIGNET_ADDRESS, None)
+            .await
+            .unwrap();
+        assert_eq!(txid, TXID.parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_address_of_other_network_without_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = client(&server);
+
+        assert!(matches!(
+            client.request_coins(MAINNET_ADDRESS, None).await,
+            Err(FaucetError::WrongNetwork { .. })
+        ));
+        assert!(matches!(
+            client.request_coins("not-an-address", None).await,
+            Err(FaucetError::InvalidAddress { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_retries_and_on_client_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(3)
+            .mount(&server)
+            .await;
+        assert!(matches!(
+            client(&server).request_coins(SIGNET_ADDRESS, None).await,
+            Err(FaucetError::Unavailable { attempts: 3, .. })
+        ));
+
+        // A rate limit longer than the maximum backoff is not waited out
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "3600"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        assert!(matches!(
+            client(&server).request_coins(SIGNET_ADDRESS, None).await,
+            Err(FaucetError::RateLimited {
+                attempts: 1,
+                retry_after: Some(_)
+            })
+        ));
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("amount too large"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let Err(FaucetError::Rejected { status, body }) = client(&server)
+            .request_coins(SIGNET_ADDRESS, Some(u64::MAX))
+            .await
+        else {
+            panic!("expected the request to be rejected");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "amount too large");
+    }
+}
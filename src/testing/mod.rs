@@ -3,7 +3,13 @@
 use std::error::Error;
 use std::sync::Arc;
 
+#[cfg(feature = "bitcoin")]
+pub mod bip_vectors;
+#[cfg(feature = "bitcoin")]
+pub mod faucet;
 pub mod performance;
+#[cfg(feature = "test-integration")]
+pub mod regtest;
 pub mod sectional_test_utils;
 
 // Re-export performance test runner for convenience
@@ -0,0 +1,211 @@
+//! Embedded regtest node for integration tests
+//!
+//! [`RegtestNode::start`] launches `bitcoind -regtest` in a temporary data
+//! directory with a funded-on-demand wallet. The node is stopped and its data
+//! directory removed when the handle is dropped. If no `bitcoind` binary is
+//! available, `start` returns `Ok(None)` so callers can skip instead of fail:
+//!
+//! ```no_run
+//! # use anya_core::testing::regtest::RegtestNode;
+//! let Some(node) = RegtestNode::start().unwrap() else {
+//!     eprintln!("[skip] bitcoind not found; skipping");
+//!     return;
+//! };
+//! node.mine_blocks(101).unwrap();
+//! ```
+
+use bitcoin::{Address, Amount, BlockHash, Txid};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+use crate::{AnyaError, AnyaResult};
+
+/// Environment variable overriding the `bitcoind` binary location
+pub const BITCOIND_EXE_ENV: &str = "BITCOIND_EXE";
+
+const RPC_USER: &str = "anya";
+const RPC_PASSWORD: &str = "regtest";
+const WALLET_NAME: &str = "anya-test";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A running `bitcoind -regtest` instance
+pub struct RegtestNode {
+    process: Child,
+    client: Client,
+    rpc_url: String,
+    // Held so the data directory lives as long as the node
+    _datadir: TempDir,
+}
+
+impl RegtestNode {
+    /// Start a node, or return `None` if no `bitcoind` binary is available
+    pub fn start() -> AnyaResult<Option<Self>> {
+        match bitcoind_exe() {
+            Some(exe) => Self::start_with(exe).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Start a node using a specific `bitcoind` binary
+    pub fn start_with(exe: PathBuf) -> AnyaResult<Self> {
+        let datadir = tempfile::tempdir()
+            .map_err(|e| AnyaError::System(format!("Failed to create regtest datadir: {e}")))?;
+        let rpc_port = free_port()?;
+        let p2p_port = free_port()?;
+
+        let mut process = Command::new(&exe)
+            .arg("-regtest")
+            .arg(format!("-datadir={}", datadir.path().display()))
+            .arg(format!("-rpcport={rpc_port}"))
+            .arg(format!("-port={p2p_port}"))
+            .arg(format!("-rpcuser={RPC_USER}"))
+            .arg(format!("-rpcpassword={RPC_PASSWORD}"))
+            .args([
+                "-server=1",
+                "-listen=0",
+                "-txindex=1",
+                "-fallbackfee=0.0001",
+            ])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| AnyaError::System(format!("Failed to spawn {}: {e}", exe.display())))?;
+
+        let rpc_url = format!("http://127.0.0.1:{rpc_port}");
+        let auth = Auth::UserPass(RPC_USER.to_string(), RPC_PASSWORD.to_string());
+        let client = match wait_for_rpc(&mut process, &rpc_url, auth) {
+            Ok(client) => client,
+            Err(e) => {
+                let _ = process.kill();
+                let _ = process.wait();
+                return Err(e);
+            }
+        };
+
+        let node = Self {
+            process,
+            client,
+            rpc_url,
+            _datadir: datadir,
+        };
+        node.client
+            .create_wallet(WALLET_NAME, None, None, None, None)
+            .map_err(rpc_error)?;
+        Ok(node)
+    }
+
+    /// RPC client connected to the node's wallet
+    pub fn rpc(&self) -> &Client {
+        &self.client
+    }
+
+    /// URL of the node's RPC interface
+    pub fn rpc_url(&self) -> &str {
+        &self.rpc_url
+    }
+
+    /// RPC credentials as `(user, password)`
+    pub fn rpc_auth(&self) -> (&'static str, &'static str) {
+        (RPC_USER, RPC_PASSWORD)
+    }
+
+    /// New address from the node's wallet
+    pub fn new_address(&self) -> AnyaResult<Address> {
+        let address = self.client.get_new_address(None, None).map_err(rpc_error)?;
+        Ok(address.assume_checked())
+    }
+
+    /// Mine `count` blocks paying to the node's wallet
+    pub fn mine_blocks(&self, count: u64) -> AnyaResult<Vec<BlockHash>> {
+        let address = self.new_address()?;
+        self.client
+            .generate_to_address(count, &address)
+            .map_err(rpc_error)
+    }
+
+    /// Send `amount` to `address` from the node's wallet and confirm it in one block
+    ///
+    /// The wallet needs mature coins; mine at least 101 blocks first.
+    pub fn fund_address(&self, address: &Address, amount: Amount) -> AnyaResult<Txid> {
+        let txid = self
+            .client
+            .send_to_address(address, amount, None, None, None, None, None, None)
+            .map_err(rpc_error)?;
+        self.mine_blocks(1)?;
+        Ok(txid)
+    }
+
+    /// Current block height
+    pub fn height(&self) -> AnyaResult<u64> {
+        self.client.get_block_count().map_err(rpc_error)
+    }
+}
+
+impl Drop for RegtestNode {
+    fn drop(&mut self) {
+        // Ask for a clean shutdown first so bitcoind releases the datadir
+        let _ = self.client.stop();
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.process.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// Locate `bitcoind` via [`BITCOIND_EXE_ENV`] or `PATH`
+pub fn bitcoind_exe() -> Option<PathBuf> {
+    if let Some(exe) = std::env::var_os(BITCOIND_EXE_ENV) {
+        let exe = PathBuf::from(exe);
+        return exe.is_file().then_some(exe);
+    }
+    let name = if cfg!(windows) {
+        "bitcoind.exe"
+    } else {
+        "bitcoind"
+    };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+fn free_port() -> AnyaResult<u16> {
+    TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| AnyaError::System(format!("Failed to reserve a local port: {e}")))
+}
+
+fn wait_for_rpc(process: &mut Child, rpc_url: &str, auth: Auth) -> AnyaResult<Client> {
+    let client = Client::new(rpc_url, auth).map_err(rpc_error)?;
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        if let Ok(Some(status)) = process.try_wait() {
+            return Err(AnyaError::System(format!(
+                "bitcoind exited during startup with {status}"
+            )));
+        }
+        match client.get_blockchain_info() {
+            Ok(_) => return Ok(client),
+            Err(e) if Instant::now() >= deadline => {
+                return Err(AnyaError::Timeout(format!(
+                    "bitcoind RPC not ready after {STARTUP_TIMEOUT:?}: {e}"
+                )))
+            }
+            Err(_) => std::thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
+
+fn rpc_error(e: bitcoincore_rpc::Error) -> AnyaError {
+    AnyaError::Bitcoin(format!("Regtest RPC error: {e}"))
+}
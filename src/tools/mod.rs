@@ -8,11 +8,13 @@ pub mod commit_tracker;
 pub mod doc_duplication_scanner;
 pub mod doc_duplication_scanner_cli;
 pub mod markdown;
+pub mod selftest;
 pub mod source_of_truth_registry;
 
 // Re-export commonly used tools
 pub use commit_tracker::{update_ai_labelling_file, CommitInfo};
 pub use markdown::{DocError, DocumentationValidator};
+pub use selftest::{run_selftest, SelfTestReport, SubsystemCheck};
 pub use source_of_truth_registry::{
     get_global_registry, initialize_global_registry, CanonicalDocument, CanonicalStatus,
     DuplicationCheckStatus, SourceOfTruthError, SourceOfTruthRegistry, WorkItem, WorkStatus,
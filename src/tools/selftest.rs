@@ -0,0 +1,291 @@
+//! Subsystem self-test [AIR-3][AIS-3][BPC-3][AIT-3]
+//!
+//! Initializes each enabled subsystem, performs one trivial operation
+//! against it and reports pass/fail per subsystem. Used by the
+//! `anya-core selftest` command to check a fresh deployment.
+
+use crate::core::metrics::PrometheusMetrics;
+use crate::layer2::rgb::{
+    AssetRights, AssetType, ContractManager, RgbConfig, RgbProtocol, SupplyPolicy,
+};
+use crate::layer2::Layer2Protocol;
+use crate::web5::identity::DIDManager;
+use crate::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A single subsystem check
+#[async_trait]
+pub trait SubsystemCheck: Send + Sync {
+    /// Subsystem name shown in the report
+    fn name(&self) -> &'static str;
+
+    /// Exercise the subsystem, returning a short description on success
+    async fn run(&self) -> AnyaResult<String>;
+}
+
+/// Outcome of one subsystem check
+#[derive(Debug, Clone)]
+pub struct SubsystemResult {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Outcome of a full self-test run
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+    pub results: Vec<SubsystemResult>,
+}
+
+impl SelfTestReport {
+    /// Whether every subsystem passed
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed)
+    }
+
+    /// Names of the subsystems that failed
+    pub fn failures(&self) -> Vec<&'static str> {
+        self.results
+            .iter()
+            .filter(|result| !result.passed)
+            .map(|result| result.name)
+            .collect()
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            let mark = if result.passed { "✅" } else { "❌" };
+            writeln!(f, "{mark} {}: {}", result.name, result.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every check in order, continuing past failures
+pub async fn run_selftest(checks: &[Box<dyn SubsystemCheck>]) -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+    for check in checks {
+        let (passed, detail) = match check.run().await {
+            Ok(detail) => (true, detail),
+            Err(e) => (false, e.to_string()),
+        };
+        report.results.push(SubsystemResult {
+            name: check.name(),
+            passed,
+            detail,
+        });
+    }
+    report
+}
+
+/// Checks for every subsystem enabled in this build
+pub fn default_checks() -> Vec<Box<dyn SubsystemCheck>> {
+    vec![
+        #[cfg(feature = "bitcoin")]
+        Box::new(BitcoinCheck),
+        Box::new(Web5Check),
+        Box::new(RgbCheck::default()),
+        Box::new(MetricsCheck),
+    ]
+}
+
+/// Derives a BIP-84 regtest address from a fixed seed
+#[cfg(feature = "bitcoin")]
+pub struct BitcoinCheck;
+
+#[cfg(feature = "bitcoin")]
+#[async_trait]
+impl SubsystemCheck for BitcoinCheck {
+    fn name(&self) -> &'static str {
+        "bitcoin"
+    }
+
+    async fn run(&self) -> AnyaResult<String> {
+        use bitcoin::bip32::{DerivationPath, Xpriv};
+        use bitcoin::secp256k1::Secp256k1;
+        use bitcoin::{Address, CompressedPublicKey, Network};
+        use std::str::FromStr;
+
+        let secp = Secp256k1::new();
+        let master = Xpriv::new_master(Network::Regtest, &[0x42; 32])
+            .map_err(|e| AnyaError::Bitcoin(e.to_string()))?;
+        let path = DerivationPath::from_str("m/84'/1'/0'/0/0")
+            .map_err(|e| AnyaError::Bitcoin(e.to_string()))?;
+        let child = master
+            .derive_priv(&secp, &path)
+            .map_err(|e| AnyaError::Bitcoin(e.to_string()))?;
+        let pubkey = CompressedPublicKey(child.private_key.public_key(&secp));
+        let address = Address::p2wpkh(&pubkey, Network::Regtest);
+
+        let parsed = Address::from_str(&address.to_string())
+            .map_err(|e| AnyaError::Bitcoin(e.to_string()))?
+            .require_network(Network::Regtest)
+            .map_err(|e| AnyaError::Bitcoin(e.to_string()))?;
+        if parsed != address {
+            return Err(AnyaError::Bitcoin(format!(
+                "Derived address {address} did not round-trip"
+            )));
+        }
+
+        Ok(format!("derived {address}"))
+    }
+}
+
+/// Creates a DID and resolves it again
+pub struct Web5Check;
+
+#[async_trait]
+impl SubsystemCheck for Web5Check {
+    fn name(&self) -> &'static str {
+        "web5"
+    }
+
+    async fn run(&self) -> AnyaResult<String> {
+        let manager = DIDManager::new("key");
+        let did = manager.create_did()?;
+        let document = manager
+            .resolve_did(&did.id)
+            .map_err(|e| AnyaError::Web5(e.to_string()))?;
+        if document.id != did.id {
+            return Err(AnyaError::Web5(format!(
+                "Resolved {} instead of {}",
+                document.id, did.id
+            )));
+        }
+
+        Ok(format!("resolved {}", did.id))
+    }
+}
+
+/// Issues a burnable asset on regtest and burns its whole supply
+#[derive(Default)]
+pub struct RgbCheck {
+    pub config: RgbConfig,
+}
+
+#[async_trait]
+impl SubsystemCheck for RgbCheck {
+    fn name(&self) -> &'static str {
+        "rgb"
+    }
+
+    async fn run(&self) -> AnyaResult<String> {
+        use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+        const SUPPLY: u64 = 1_000;
+        const ISSUER: &str = "selftest";
+
+        if self.config.network != "regtest" {
+            return Err(AnyaError::System(format!(
+                "RGB self-test must run on regtest, configured for '{}'",
+                self.config.network
+            )));
+        }
+
+        let issuer_key =
+            SecretKey::from_slice(&[0x42; 32]).map_err(|e| AnyaError::System(e.to_string()))?;
+        let rgb = RgbProtocol::new(self.config.clone());
+        let layer2_error = |e: crate::layer2::Layer2Error| AnyaError::System(e.to_string());
+        rgb.initialize().await.map_err(layer2_error)?;
+        rgb.connect().await.map_err(layer2_error)?;
+
+        let schema_id = rgb
+            .create_asset_schema(
+                AssetType::Fungible,
+                SupplyPolicy::Burnable,
+                8,
+                Vec::new(),
+                AssetRights {
+                    can_burn: true,
+                    can_replace: false,
+                    can_rename: false,
+                    can_issue_more: false,
+                },
+            )
+            .await
+            .map_err(layer2_error)?;
+        let asset_id = rgb
+            .issue_asset_internal(
+                schema_id,
+                "Self-test asset".to_string(),
+                Some("SELF".to_string()),
+                SUPPLY,
+                ISSUER.to_string(),
+                Some(issuer_key.public_key(&Secp256k1::new())),
+                HashMap::new(),
+            )
+            .await
+            .map_err(layer2_error)?;
+        let burn = ContractManager::new()
+            .burn_asset(&asset_id, &issuer_key, ISSUER, SUPPLY)
+            .map_err(|e| AnyaError::System(e.to_string()))?;
+        rgb.burn_asset(&burn).await.map_err(layer2_error)?;
+
+        let asset = rgb.get_asset(&asset_id).await.map_err(layer2_error)?;
+        if asset.circulating_supply != 0 {
+            return Err(AnyaError::System(format!(
+                "{} units of {asset_id} left after burn",
+                asset.circulating_supply
+            )));
+        }
+
+        Ok(format!("issued and burnt {asset_id}"))
+    }
+}
+
+/// Records a gauge and reads it back from the metrics export
+pub struct MetricsCheck;
+
+#[async_trait]
+impl SubsystemCheck for MetricsCheck {
+    fn name(&self) -> &'static str {
+        "metrics"
+    }
+
+    async fn run(&self) -> AnyaResult<String> {
+        let mut metrics = PrometheusMetrics::new();
+        metrics.set_gauge("selftest_probe", 1.0);
+
+        let scraped = metrics.get_metrics_json();
+        match scraped.get("gauge_selftest_probe") {
+            Some(gauge) if gauge["value"] == 1.0 => {
+                Ok(format!("scraped {} metrics", scraped.len()))
+            }
+            _ => Err(AnyaError::System(
+                "Probe gauge missing from metrics export".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_selftest_passes_on_default_configuration() {
+        let report = run_selftest(&default_checks()).await;
+        assert!(report.passed(), "{report}");
+        assert!(report.results.iter().any(|result| result.name == "rgb"));
+    }
+
+    #[tokio::test]
+    async fn test_selftest_flags_broken_subsystem() {
+        let broken = RgbCheck {
+            config: RgbConfig {
+                network: "bitcoin".to_string(),
+                ..RgbConfig::default()
+            },
+        };
+        let checks: Vec<Box<dyn SubsystemCheck>> = vec![Box::new(Web5Check), Box::new(broken)];
+
+        let report = run_selftest(&checks).await;
+        assert!(!report.passed());
+        assert_eq!(report.failures(), vec!["rgb"]);
+        assert!(report.results[0].passed);
+    }
+}
@@ -9,6 +9,14 @@ use walkdir::WalkDir;
 const REQUIRED_LABELS: [&str; 3] = ["AIS-3", "BPC-3", "DAO-4"];
 const MAX_LINE_LENGTH: usize = 100;
 
+/// Log an informational message
+///
+/// Kept for backward compatibility; new code should use `tracing` directly.
+#[deprecated(note = "use `tracing::info!` instead")]
+pub fn log(message: &str) {
+    tracing::info!("{message}");
+}
+
 #[derive(Debug, Error)]
 pub enum DocError {
     #[error("IO error: {0}")]
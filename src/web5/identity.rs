@@ -282,17 +282,7 @@ impl DIDManager {
             let secp = secp256k1::Secp256k1::signing_only();
 
             // Hash the data (using SHA256)
-            let hash = {
-                use sha2::{Digest, Sha256};
-                let mut hasher = Sha256::new();
-                hasher.update(data);
-                hasher.finalize()
-            };
-
-            // Create message from hash
-            let hash_array: [u8; 32] = hash.into();
-            let message = secp256k1::Message::from_digest_slice(&hash_array)
-                .map_err(|e| format!("Failed to create message: {e}"))?;
+            let message = crate::security::crypto::message_from_hashed(data);
 
             // Sign the message
             let signature = secp.sign_ecdsa(&message, &private_key);
@@ -374,7 +364,7 @@ fn generate_private_key() -> Vec<u8> {
     // Generate a 32-byte private key (simplified implementation)
     use rand::RngCore;
     let mut key = vec![0u8; 32];
-    rand::thread_rng().fill_bytes(&mut key);
+    crate::security::rng::secure_rng().fill_bytes(&mut key);
     key
 }
 
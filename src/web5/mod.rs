@@ -11,10 +11,12 @@ pub mod vc; // Verifiable Credentials
 pub use identity::{DIDDocument, DIDManager, IdentityManager, Web5Error, Web5Result, DID};
 pub use protocols::{ProtocolDefinition, ProtocolHandler, ProtocolManager};
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Web5 configuration with focused parameters
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Web5Config {
     /// Whether Web5 functionality is enabled
     pub enabled: bool,
@@ -51,6 +53,11 @@ pub struct Web5Manager {
 impl Web5Manager {
     /// Create a new Web5 manager with the specified configuration
     pub fn new(config: Web5Config) -> Web5Result<Self> {
+        if config.did_method.is_empty() {
+            return Err(Web5Error::Identity(
+                "DID method must not be empty".to_string(),
+            ));
+        }
         let did_manager = identity::DIDManager::new(&config.did_method);
         let protocol_manager = protocols::ProtocolManager::new();
 
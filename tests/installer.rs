@@ -71,3 +71,191 @@ async fn test_network_compliance_failure() {
     assert!(result.is_err(), \"Should fail on port validation\");
 }
 */
+
+mod rollback {
+    use anya_core::install::rollback::{
+        ChangeSet, Component, FnComponent, InstallOptions, PlannedAction, RollbackManager,
+    };
+    use std::fs;
+    use std::path::Path;
+
+    fn writes(name: &str, dir: &Path, contents: &str) -> impl Component {
+        let (dir, contents) = (dir.to_path_buf(), contents.to_string());
+        FnComponent::new(name, move |changes: &mut ChangeSet| {
+            changes.create_dir_all(&dir.join(&contents).join("data"))?;
+            changes.write_file(&dir.join("anya.conf"), &contents)
+        })
+    }
+
+    #[test]
+    fn test_failed_component_rolls_back_earlier_components() {
+        let dir = tempfile::tempdir().unwrap();
+        let conf = dir.path().join("anya.conf");
+        fs::write(&conf, "original").unwrap();
+
+        let core = writes("core", dir.path(), "core");
+        let bitcoin = writes("bitcoin", dir.path(), "bitcoin");
+        let dao = FnComponent::new("dao", |changes: &mut ChangeSet| {
+            changes.create_dir_all(&dir.path().join("dao"))?;
+            anyhow::bail!("contract deployment failed")
+        });
+
+        let mut manager = RollbackManager::new(dir.path()).unwrap();
+        let err = manager
+            .install(&[&core, &bitcoin, &dao], InstallOptions::default())
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("contract deployment failed"));
+
+        // Reverted newest first, so the original configuration is back
+        assert_eq!(fs::read_to_string(&conf).unwrap(), "original");
+        for created in ["core", "bitcoin", "dao"] {
+            assert!(!dir.path().join(created).exists(), "{created} left behind");
+        }
+        let manager = RollbackManager::new(dir.path()).unwrap();
+        assert!(!manager.is_installed("core"));
+        assert!(!manager.is_installed("bitcoin"));
+    }
+
+    #[test]
+    fn test_reinstall_is_skipped_unless_forced() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = writes("core", dir.path(), "core");
+        let bitcoin = writes("bitcoin", dir.path(), "bitcoin");
+
+        let mut manager = RollbackManager::new(dir.path()).unwrap();
+        let report = manager
+            .install(&[&core], InstallOptions::default())
+            .unwrap();
+        assert_eq!(report.installed, ["core"]);
+
+        // A dry run reports the plan and changes nothing
+        let mut manager = RollbackManager::new(dir.path()).unwrap();
+        let dry_run = InstallOptions {
+            dry_run: true,
+            ..InstallOptions::default()
+        };
+        let report = manager.install(&[&core, &bitcoin], dry_run).unwrap();
+        assert_eq!(
+            report.plan,
+            [
+                ("core".to_string(), PlannedAction::Skip),
+                ("bitcoin".to_string(), PlannedAction::Install)
+            ]
+        );
+        assert!(report.installed.is_empty());
+        assert!(!dir.path().join("bitcoin").exists());
+
+        let report = manager
+            .install(&[&core, &bitcoin], InstallOptions::default())
+            .unwrap();
+        assert_eq!(report.installed, ["bitcoin"]);
+
+        let forced = InstallOptions {
+            force: true,
+            ..InstallOptions::default()
+        };
+        let report = manager.install(&[&core], forced).unwrap();
+        assert_eq!(
+            report.plan,
+            [("core".to_string(), PlannedAction::Reinstall)]
+        );
+        assert_eq!(report.installed, ["core"]);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("anya.conf")).unwrap(),
+            "core"
+        );
+    }
+}
+
+mod requirements {
+    use anya_core::install::requirements::{
+        validate_requirements, CheckStatus, PortStatus, Requirements, SystemAnalyzer,
+    };
+    use std::path::Path;
+
+    const GB: u64 = 1_000_000_000;
+
+    struct MockAnalyzer {
+        cores: usize,
+        memory: u64,
+        disk: Option<u64>,
+    }
+
+    impl SystemAnalyzer for MockAnalyzer {
+        fn cpu_cores(&self) -> usize {
+            self.cores
+        }
+
+        fn total_memory(&self) -> u64 {
+            self.memory
+        }
+
+        fn available_disk_space(&self, _path: &Path) -> Option<u64> {
+            self.disk
+        }
+
+        fn port_status(&self, port: u16) -> PortStatus {
+            match port {
+                8332 => PortStatus::InUse,
+                8333 => PortStatus::Blocked("permission denied".to_string()),
+                _ => PortStatus::Available,
+            }
+        }
+    }
+
+    #[test]
+    fn test_report_lists_each_failed_requirement() {
+        let analyzer = MockAnalyzer {
+            cores: 1,
+            memory: 2 * GB,
+            disk: Some(40 * GB),
+        };
+        let report =
+            validate_requirements(&analyzer, &Requirements::default(), Path::new("/opt/anya"));
+
+        assert!(!report.passed());
+        let failed: Vec<&str> = report.failures().map(|c| c.name.as_str()).collect();
+        assert_eq!(failed, ["cpu", "memory", "disk", "port 8332", "port 8333"]);
+
+        let memory = report.check("memory").unwrap();
+        assert_eq!(memory.detected, "2.0 GB");
+        assert_eq!(memory.required, "4.0 GB");
+        assert_eq!(
+            memory.remediation.as_deref(),
+            Some("Add at least 2.0 GB of RAM")
+        );
+        assert_eq!(report.check("disk").unwrap().detected, "40.0 GB");
+        assert!(report
+            .check("port 8332")
+            .unwrap()
+            .detected
+            .contains("in use"));
+        assert!(report
+            .check("port 8333")
+            .unwrap()
+            .detected
+            .contains("blocked"));
+        assert!(report.to_string().contains("[FAIL] cpu: detected 1 cores"));
+    }
+
+    #[test]
+    fn test_report_is_returned_on_success() {
+        let analyzer = MockAnalyzer {
+            cores: 8,
+            memory: 16 * GB,
+            disk: None,
+        };
+        let requirements = Requirements {
+            ports: vec![9000],
+            ..Requirements::default()
+        };
+        let report = validate_requirements(&analyzer, &requirements, Path::new("/opt/anya"));
+
+        // An unknown disk size warns without blocking the install
+        assert!(report.passed());
+        assert_eq!(report.checks.len(), 4);
+        assert_eq!(report.check("disk").unwrap().status, CheckStatus::Warning);
+        assert_eq!(report.check("cpu").unwrap().status, CheckStatus::Pass);
+        assert!(report.check("port 9000").unwrap().remediation.is_none());
+    }
+}
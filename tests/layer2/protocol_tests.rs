@@ -398,10 +398,11 @@ mod tests {
         });
         protocol.expect_get_capabilities().returning(|| {
             Ok(anya_core::layer2::ProtocolCapabilities {
-                supports_assets: true,
+                asset_kinds: [anya_core::layer2::AssetKind::Fungible].into(),
                 supports_smart_contracts: false,
                 supports_privacy: false,
-                max_transaction_size: 1000,
+                max_sizes: [(anya_core::layer2::Layer2Operation::Transaction, 1000)].into(),
+                proof_types: ["mock".to_string()].into(),
                 fee_estimation: true,
             })
         });
@@ -0,0 +1,723 @@
+//! Integration tests against an embedded regtest bitcoind
+//!
+//! Run with `cargo test --features test-integration --test regtest_integration`.
+//! Tests skip when no `bitcoind` binary is found on PATH or via `BITCOIND_EXE`.
+#![cfg(feature = "test-integration")]
+
+use anya_core::bitcoin::chain_source::{BitcoindSource, ChainBackend, ChainSource, ChainUtxo};
+use anya_core::bitcoin::confirmations::{ChainEvent, ConfirmationOutcome, ConfirmationTracker};
+use anya_core::bitcoin::lightning_anchors::{
+    build_anchor_commitment, funding_script, obscure_factor, AnchorBumper, CommitmentKeys,
+    CommitmentParams,
+};
+use anya_core::bitcoin::lightning_store::{ChannelState, ChannelStore};
+use anya_core::bitcoin::timestamp::{verify_timestamp, TimestampAnchor};
+use anya_core::bitcoin::wallet::signer::{sign_psbt, SighashType};
+use anya_core::bitcoin::wallet::timelock::{build_timelock_spend, sign_timelock_spend};
+use anya_core::bitcoin::wallet::{
+    build_cltv_script, build_csv_script, AddressManager, AddressType, BalanceManager, ChainTip,
+    CoinSelectionStrategy, FeeRate, FeeStrategy, TimelockedOutput, Vault, Wallet, WalletConfig,
+    WalletError, WalletType,
+};
+use anya_core::layer2::dlc::contract::DlcContract;
+use anya_core::layer2::dlc::{FundingInputs, LocalOracle, Party, PartyFunding};
+use anya_core::testing::regtest::RegtestNode;
+use anya_core::{AnyaError, AnyaResult};
+use async_trait::async_trait;
+use bitcoin::absolute::LockTime;
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::psbt::Psbt;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    ecdsa, relative, Address, Amount, Block, BlockHash, CompressedPublicKey, Network, OutPoint,
+    PublicKey, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+use bitcoincore_rpc::RpcApi;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+fn start_node() -> Option<RegtestNode> {
+    let node = RegtestNode::start().expect("failed to start regtest node");
+    if node.is_none() {
+        eprintln!("[skip] bitcoind not found; skipping regtest integration test");
+    }
+    node
+}
+
+#[test]
+fn regtest_mines_blocks_on_demand() {
+    let Some(node) = start_node() else {
+        return;
+    };
+
+    assert_eq!(node.height().unwrap(), 0);
+    let hashes = node.mine_blocks(5).unwrap();
+    assert_eq!(hashes.len(), 5);
+    assert_eq!(node.height().unwrap(), 5);
+    assert_eq!(node.rpc().get_best_block_hash().unwrap(), hashes[4]);
+}
+
+#[test]
+fn regtest_funds_address_with_confirmation() {
+    let Some(node) = start_node() else {
+        return;
+    };
+
+    // Coinbase outputs mature after 100 blocks
+    node.mine_blocks(101).unwrap();
+    let address = node.new_address().unwrap();
+    let txid = node
+        .fund_address(&address, Amount::from_sat(100_000))
+        .unwrap();
+
+    let tx = node.rpc().get_transaction(&txid, None).unwrap();
+    assert_eq!(tx.info.confirmations, 1);
+    let received = node
+        .rpc()
+        .get_received_by_address(&address, Some(1))
+        .unwrap();
+    assert_eq!(received, Amount::from_sat(100_000));
+}
+
+#[test]
+fn regtest_wallet_rescan_recovers_funds() {
+    let Some(node) = start_node() else {
+        return;
+    };
+    node.mine_blocks(101).unwrap();
+
+    let wallet = Wallet::new(
+        WalletConfig {
+            wallet_type: WalletType::Standard,
+            network: Network::Regtest,
+            name: "restored".to_string(),
+            seed_phrase: None,
+            password: None,
+            receive_descriptor: String::new(),
+            change_descriptor: String::new(),
+            xpub: None,
+            data_dir: std::env::temp_dir(),
+            use_rpc: true,
+            coin_selection: CoinSelectionStrategy::LargestFirst,
+            gap_limit: 20,
+            min_confirmations: 1,
+            fee_strategy: FeeStrategy::Medium,
+        },
+        None,
+    );
+    wallet
+        .initialize(Some("regtest rescan seed"), None)
+        .unwrap();
+
+    // Funds sent to the wallet before it knew about them
+    for (index, sats) in [(15, 100_000), (30, 250_000)] {
+        let address = wallet.get_address(index, AddressType::SegWit).unwrap();
+        node.fund_address(&address, Amount::from_sat(sats)).unwrap();
+    }
+    assert_eq!(wallet.get_balance().unwrap(), 0);
+
+    let summary = wallet.rescan(node.rpc(), 100, |_| {}).unwrap();
+    assert_eq!(summary.tip_height, 103);
+    assert_eq!(summary.transactions_found, 2);
+    assert_eq!(wallet.get_balance().unwrap(), 350_000);
+}
+
+/// Fund a P2WPKH output of `key` and return it as a spendable UTXO
+fn fund_key(node: &RegtestNode, key: &SecretKey, sats: u64) -> (OutPoint, TxOut) {
+    let secp = Secp256k1::new();
+    let address = Address::p2wpkh(
+        &CompressedPublicKey(key.public_key(&secp)),
+        Network::Regtest,
+    );
+    let txid = node.fund_address(&address, Amount::from_sat(sats)).unwrap();
+    let tx = node.rpc().get_raw_transaction(&txid, None).unwrap();
+    let vout = tx
+        .output
+        .iter()
+        .position(|txout| txout.script_pubkey == address.script_pubkey())
+        .unwrap();
+    (OutPoint::new(txid, vout as u32), tx.output[vout].clone())
+}
+
+#[test]
+fn regtest_dlc_two_outcome_bet_settles() {
+    let Some(node) = start_node() else {
+        return;
+    };
+    node.mine_blocks(101).unwrap();
+
+    let secp = Secp256k1::new();
+    let offer_key = SecretKey::from_slice(&[1; 32]).unwrap();
+    let accept_key = SecretKey::from_slice(&[2; 32]).unwrap();
+    let party = |key: &SecretKey| {
+        let utxo = fund_key(&node, key, 200_000);
+        PartyFunding {
+            fund_pubkey: key.x_only_public_key(&secp).0,
+            payout_script: utxo.1.script_pubkey.clone(),
+            change_script: utxo.1.script_pubkey.clone(),
+            utxos: vec![utxo],
+            collateral: 100_000,
+        }
+    };
+    let funding = FundingInputs {
+        offer: party(&offer_key),
+        accept: party(&accept_key),
+        fee_rate: 2,
+    };
+
+    let oracle = LocalOracle::new(SecretKey::from_slice(&[9; 32]).unwrap());
+    let announcement = oracle.announce(&secp, "coin-flip", &["heads", "tails"]);
+    let payouts = vec![
+        ("heads".to_string(), (200_000, 0)),
+        ("tails".to_string(), (0, 200_000)),
+    ];
+    let mut contract = DlcContract::build(&announcement, &payouts, funding).unwrap();
+    for (party, key) in [(Party::Offer, &offer_key), (Party::Accept, &accept_key)] {
+        let signatures = contract.sign_cets(&secp, party, key).unwrap();
+        contract.add_cet_signatures(party, signatures).unwrap();
+    }
+
+    // Both parties sign their own funding inputs
+    let mut psbt = contract.funding_psbt().unwrap();
+    for key in [&offer_key, &accept_key] {
+        assert_eq!(
+            sign_psbt(&secp, &mut psbt, key, SighashType::All).unwrap(),
+            1
+        );
+    }
+    for input in &mut psbt.inputs {
+        let (pubkey, signature) = input.partial_sigs.pop_first().unwrap();
+        input.final_script_witness = Some(Witness::p2wpkh(&signature, &pubkey.inner));
+    }
+    let funding_tx = psbt.extract_tx().unwrap();
+    node.rpc().send_raw_transaction(&funding_tx).unwrap();
+    node.mine_blocks(1).unwrap();
+
+    let attestation = oracle.attest(&announcement, "tails").unwrap();
+    let cet = contract.decrypt_cet(&secp, &attestation).unwrap();
+    let cet_txid = node.rpc().send_raw_transaction(&cet).unwrap();
+    node.mine_blocks(1).unwrap();
+
+    let info = node
+        .rpc()
+        .get_raw_transaction_info(&cet_txid, None)
+        .unwrap();
+    assert_eq!(info.confirmations, Some(1));
+    assert_eq!(cet.output.len(), 1);
+    assert_eq!(cet.output[0].value, Amount::from_sat(200_000));
+}
+
+/// Height and median time past of the block at `height`
+fn chain_tip_at(node: &RegtestNode, height: u64) -> ChainTip {
+    let hash = node.rpc().get_block_hash(height).unwrap();
+    let header = node.rpc().get_block_header_info(&hash).unwrap();
+    ChainTip {
+        height: height as u32,
+        median_time_past: header.median_time.unwrap() as u32,
+    }
+}
+
+/// Fund the P2WSH output of `witness_script` and confirm it in one block
+fn fund_timelocked(node: &RegtestNode, witness_script: ScriptBuf, sats: u64) -> TimelockedOutput {
+    let address = Address::p2wsh(&witness_script, Network::Regtest);
+    let txid = node.fund_address(&address, Amount::from_sat(sats)).unwrap();
+    let tx = node.rpc().get_raw_transaction(&txid, None).unwrap();
+    let vout = tx
+        .output
+        .iter()
+        .position(|txout| txout.script_pubkey == address.script_pubkey())
+        .unwrap();
+    let height = node.height().unwrap();
+    TimelockedOutput {
+        outpoint: OutPoint::new(txid, vout as u32),
+        txout: tx.output[vout].clone(),
+        witness_script,
+        confirmed: Some(ChainTip {
+            height: height as u32,
+            median_time_past: chain_tip_at(node, height - 1).median_time_past,
+        }),
+    }
+}
+
+/// Spend `output` to the node's wallet, checking maturity against `tip`
+fn spend_timelocked(
+    node: &RegtestNode,
+    output: &TimelockedOutput,
+    key: &SecretKey,
+    tip: &ChainTip,
+) -> Result<Transaction, WalletError> {
+    let destination = node.new_address().unwrap().script_pubkey();
+    let mut psbt = build_timelock_spend(output, destination, FeeRate::SatPerVb(2), tip)?;
+    assert_eq!(sign_timelock_spend(&Secp256k1::new(), &mut psbt, key)?, 1);
+    Ok(psbt.extract_tx().unwrap())
+}
+
+#[test]
+fn regtest_cltv_spend_waits_for_lock_height() {
+    let Some(node) = start_node() else {
+        return;
+    };
+    node.mine_blocks(101).unwrap();
+
+    let key = SecretKey::from_slice(&[3; 32]).unwrap();
+    let pubkey = PublicKey::new(key.public_key(&Secp256k1::new()));
+    let lock_height = node.height().unwrap() as u32 + 10;
+    let script = build_cltv_script(&pubkey, LockTime::from_height(lock_height).unwrap());
+    let output = fund_timelocked(&node, script, 100_000);
+
+    let tip = chain_tip_at(&node, node.height().unwrap());
+    let early = spend_timelocked(&node, &output, &key, &tip);
+    assert!(matches!(early, Err(WalletError::TimelockError(_))));
+
+    // Built as if the lock height were reached, the node still refuses it
+    let mature = ChainTip {
+        height: lock_height,
+        ..tip
+    };
+    let tx = spend_timelocked(&node, &output, &key, &mature).unwrap();
+    assert!(node.rpc().send_raw_transaction(&tx).is_err());
+
+    node.mine_blocks(u64::from(lock_height) - node.height().unwrap())
+        .unwrap();
+    let tip = chain_tip_at(&node, node.height().unwrap());
+    let tx = spend_timelocked(&node, &output, &key, &tip).unwrap();
+    let txid = node.rpc().send_raw_transaction(&tx).unwrap();
+    node.mine_blocks(1).unwrap();
+    let info = node.rpc().get_raw_transaction_info(&txid, None).unwrap();
+    assert_eq!(info.confirmations, Some(1));
+}
+
+#[test]
+fn regtest_csv_spend_waits_for_confirmations() {
+    let Some(node) = start_node() else {
+        return;
+    };
+    node.mine_blocks(101).unwrap();
+
+    let key = SecretKey::from_slice(&[4; 32]).unwrap();
+    let pubkey = PublicKey::new(key.public_key(&Secp256k1::new()));
+    let script = build_csv_script(&pubkey, relative::LockTime::from_height(5));
+    let output = fund_timelocked(&node, script, 100_000);
+    let confirmed_height = node.height().unwrap();
+
+    let tip = chain_tip_at(&node, confirmed_height);
+    let early = spend_timelocked(&node, &output, &key, &tip);
+    assert!(matches!(early, Err(WalletError::TimelockError(_))));
+
+    // With only one confirmation the sequence lock is not final yet
+    let mature = ChainTip {
+        height: tip.height + 4,
+        ..tip
+    };
+    let tx = spend_timelocked(&node, &output, &key, &mature).unwrap();
+    assert!(node.rpc().send_raw_transaction(&tx).is_err());
+
+    // The spend can be mined in the block giving the output five confirmations
+    node.mine_blocks(4).unwrap();
+    let tip = chain_tip_at(&node, node.height().unwrap());
+    let tx = spend_timelocked(&node, &output, &key, &tip).unwrap();
+    let txid = node.rpc().send_raw_transaction(&tx).unwrap();
+    node.mine_blocks(1).unwrap();
+    let info = node.rpc().get_raw_transaction_info(&txid, None).unwrap();
+    assert_eq!(info.confirmations, Some(1));
+}
+
+/// PSBT spending `deposit` of `vault` to the node's wallet with `sequence`
+fn vault_spend_psbt(
+    node: &RegtestNode,
+    vault: &Vault,
+    deposit: &bitcoin::Txid,
+    sequence: Sequence,
+) -> Psbt {
+    let tx = node.rpc().get_raw_transaction(deposit, None).unwrap();
+    let vout = tx
+        .output
+        .iter()
+        .position(|txout| txout.script_pubkey == vault.script_pubkey())
+        .unwrap();
+    let unsigned = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(*deposit, vout as u32),
+            script_sig: ScriptBuf::new(),
+            sequence,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: tx.output[vout].value - Amount::from_sat(1_000),
+            script_pubkey: node.new_address().unwrap().script_pubkey(),
+        }],
+    };
+    let mut psbt = Psbt::from_unsigned_tx(unsigned).unwrap();
+    psbt.inputs[0].witness_utxo = Some(tx.output[vout].clone());
+    psbt
+}
+
+#[test]
+fn regtest_vault_hot_and_recovery_paths() {
+    let Some(node) = start_node() else {
+        return;
+    };
+    node.mine_blocks(101).unwrap();
+
+    let secp = Secp256k1::new();
+    let keys: Vec<SecretKey> = (5..8)
+        .map(|seed| SecretKey::from_slice(&[seed; 32]).unwrap())
+        .collect();
+    let vault = Vault::new(
+        keys.iter()
+            .map(|key| key.x_only_public_key(&secp).0)
+            .collect(),
+        2,
+        relative::LockTime::from_height(5),
+        Network::Regtest,
+    )
+    .unwrap();
+    let address = vault.deposit_address();
+    let hot_deposit = node
+        .fund_address(&address, Amount::from_sat(100_000))
+        .unwrap();
+    let cold_deposit = node
+        .fund_address(&address, Amount::from_sat(100_000))
+        .unwrap();
+
+    // Every key spends at once through the hot path
+    let mut psbt = vault_spend_psbt(
+        &node,
+        &vault,
+        &hot_deposit,
+        Sequence::ENABLE_RBF_NO_LOCKTIME,
+    );
+    vault.spend_hot(&mut psbt).unwrap();
+    for key in &keys {
+        vault.sign(&secp, &mut psbt, key).unwrap();
+    }
+    vault.finalize(&mut psbt).unwrap();
+    node.rpc()
+        .send_raw_transaction(&psbt.extract_tx().unwrap())
+        .unwrap();
+
+    // Two keys recover the other deposit, but only after the delay
+    let mut psbt = vault_spend_psbt(
+        &node,
+        &vault,
+        &cold_deposit,
+        Sequence::ENABLE_RBF_NO_LOCKTIME,
+    );
+    assert!(matches!(
+        vault.spend_recovery(&mut psbt),
+        Err(WalletError::TimelockError(_))
+    ));
+    let mut psbt = vault_spend_psbt(&node, &vault, &cold_deposit, Sequence::from_height(5));
+    vault.spend_recovery(&mut psbt).unwrap();
+    for key in &keys[1..] {
+        vault.sign(&secp, &mut psbt, key).unwrap();
+    }
+    vault.finalize(&mut psbt).unwrap();
+    let recovery = psbt.extract_tx().unwrap();
+    assert!(node.rpc().send_raw_transaction(&recovery).is_err());
+
+    node.mine_blocks(4).unwrap();
+    let txid = node.rpc().send_raw_transaction(&recovery).unwrap();
+    node.mine_blocks(1).unwrap();
+    let info = node.rpc().get_raw_transaction_info(&txid, None).unwrap();
+    assert_eq!(info.confirmations, Some(1));
+}
+
+/// Mine one block and report it to `tracker`
+fn connect_next_block(node: &RegtestNode, tracker: &ConfirmationTracker) -> BlockHash {
+    let hash = node.mine_blocks(1).unwrap()[0];
+    tracker.handle_event(&ChainEvent::BlockConnected {
+        block: node.rpc().get_block(&hash).unwrap(),
+        height: node.height().unwrap() as u32,
+    });
+    hash
+}
+
+#[tokio::test]
+async fn regtest_confirmation_tracker_reaches_target_and_sees_reorg() {
+    let Some(node) = start_node() else {
+        return;
+    };
+    node.mine_blocks(101).unwrap();
+    let tracker = ConfirmationTracker::new();
+    let send = |sats| {
+        let address = node.new_address().unwrap();
+        node.rpc()
+            .send_to_address(
+                &address,
+                Amount::from_sat(sats),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap()
+    };
+
+    let txid = send(50_000);
+    let notified = Arc::new(Mutex::new(None));
+    let recorded = notified.clone();
+    tracker.track_with_callback(txid, 3, move |outcome| {
+        *recorded.lock().unwrap() = Some(outcome);
+    });
+    let confirmed = tracker.track(txid, 3);
+
+    let block_hash = connect_next_block(&node, &tracker);
+    connect_next_block(&node, &tracker);
+    assert!(notified.lock().unwrap().is_none());
+    connect_next_block(&node, &tracker);
+    let expected = ConfirmationOutcome::Confirmed {
+        txid,
+        block_hash,
+        height: 102,
+        confirmations: 3,
+    };
+    assert_eq!(confirmed.await, expected);
+    assert_eq!(*notified.lock().unwrap(), Some(expected));
+
+    // Invalidating the confirming block un-confirms the transaction
+    let txid = send(20_000);
+    let reorged = tracker.track(txid, 6);
+    let hash = connect_next_block(&node, &tracker);
+    node.rpc().invalidate_block(&hash).unwrap();
+    tracker.handle_event(&ChainEvent::BlockDisconnected { hash, height: 105 });
+    assert_eq!(
+        reorged.await,
+        ConfirmationOutcome::Reorged {
+            txid,
+            block_hash: hash,
+            height: 105
+        }
+    );
+    assert_eq!(tracker.tracked(), 0);
+}
+
+/// Chain source over the regtest node reporting a fixed fee estimate
+///
+/// A fresh regtest node has no fee history to estimate from.
+struct FeeSpikeSource {
+    node: BitcoindSource,
+    fee_rate: bitcoin::FeeRate,
+}
+
+#[async_trait]
+impl ChainSource for FeeSpikeSource {
+    fn backend(&self) -> ChainBackend {
+        self.node.backend()
+    }
+
+    async fn tip_height(&self) -> AnyaResult<u32> {
+        self.node.tip_height().await
+    }
+
+    async fn get_block(&self, hash: &BlockHash) -> AnyaResult<Block> {
+        self.node.get_block(hash).await
+    }
+
+    async fn get_header(&self, hash: &BlockHash) -> AnyaResult<BlockHeader> {
+        self.node.get_header(hash).await
+    }
+
+    async fn get_tx(&self, txid: &Txid) -> AnyaResult<Transaction> {
+        self.node.get_tx(txid).await
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> AnyaResult<Txid> {
+        self.node.broadcast(tx).await
+    }
+
+    async fn get_utxos(&self, script: &Script) -> AnyaResult<Vec<ChainUtxo>> {
+        self.node.get_utxos(script).await
+    }
+
+    async fn estimate_fee(&self, _target_blocks: u16) -> AnyaResult<bitcoin::FeeRate> {
+        Ok(self.fee_rate)
+    }
+}
+
+#[tokio::test]
+async fn regtest_anchor_bump_confirms_stuck_commitment() {
+    let Some(node) = start_node() else {
+        return;
+    };
+    node.mine_blocks(101).unwrap();
+
+    let secp = Secp256k1::new();
+    let secret = |seed| SecretKey::from_slice(&[seed; 32]).unwrap();
+    let (local_funding, remote_funding, wallet_key) = (secret(11), secret(12), secret(13));
+    let funding_script = funding_script(
+        &local_funding.public_key(&secp),
+        &remote_funding.public_key(&secp),
+    );
+    let funding_address = Address::p2wsh(&funding_script, Network::Regtest);
+    let funding_txid = node
+        .fund_address(&funding_address, Amount::from_sat(100_000))
+        .unwrap();
+    let funding_tx = node.rpc().get_raw_transaction(&funding_txid, None).unwrap();
+    let funding_vout = funding_tx
+        .output
+        .iter()
+        .position(|txout| txout.script_pubkey == funding_address.script_pubkey())
+        .unwrap();
+
+    let store_dir = tempfile::tempdir().unwrap();
+    let (user, password) = node.rpc_auth();
+    let chain = Arc::new(FeeSpikeSource {
+        node: BitcoindSource::new(
+            node.rpc_url(),
+            Some((user.to_string(), password.to_string())),
+        )
+        .unwrap(),
+        fee_rate: bitcoin::FeeRate::from_sat_per_vb(25).unwrap(),
+    });
+    let bumper = AnchorBumper::new(
+        ChannelStore::open(store_dir.path()).unwrap(),
+        chain,
+        local_funding,
+        wallet_key,
+    )
+    .with_confirmation_target(2);
+    let wallet_address = Address::from_script(&bumper.wallet_script(), Network::Regtest).unwrap();
+    node.fund_address(&wallet_address, Amount::from_sat(50_000))
+        .unwrap();
+
+    // Commitment signed at 300 sat/kW, far below the 25 sat/vB now needed
+    let params = CommitmentParams {
+        funding_outpoint: OutPoint::new(funding_txid, funding_vout as u32),
+        commitment_number: 7,
+        obscure_factor: obscure_factor(
+            &secret(21).public_key(&secp),
+            &secret(22).public_key(&secp),
+        ),
+        to_self_delay: 144,
+        dust_limit: Amount::from_sat(546),
+        feerate_per_kw: 300,
+        local_is_funder: true,
+    };
+    let keys = CommitmentKeys {
+        local_funding_pubkey: local_funding.public_key(&secp),
+        remote_funding_pubkey: remote_funding.public_key(&secp),
+        revocation_pubkey: secret(23).public_key(&secp),
+        local_delayed_pubkey: secret(24).public_key(&secp),
+        remote_payment_pubkey: secret(25).public_key(&secp),
+    };
+    let mut commitment = build_anchor_commitment(&params, &keys, 70_000_000, 30_000_000).unwrap();
+    let sighash = SighashCache::new(&commitment)
+        .p2wsh_signature_hash(
+            0,
+            &funding_script,
+            Amount::from_sat(100_000),
+            EcdsaSighashType::All,
+        )
+        .unwrap();
+    let message = Message::from_digest(sighash.to_byte_array());
+    let mut signers = [local_funding, remote_funding];
+    signers.sort_by_key(|key| key.public_key(&secp).serialize());
+    let mut witness = Witness::new();
+    witness.push([]);
+    for key in &signers {
+        let signature = ecdsa::Signature {
+            signature: secp.sign_ecdsa(&message, key),
+            sighash_type: EcdsaSighashType::All,
+        };
+        witness.push(signature.to_vec());
+    }
+    witness.push(funding_script.as_bytes());
+    commitment.input[0].witness = witness;
+
+    let state = ChannelState {
+        channel_id: "regtest_anchor".to_string(),
+        funding_outpoint: params.funding_outpoint,
+        capacity_sat: 100_000,
+        remote_node_id: secret(26).public_key(&secp),
+        update_number: params.commitment_number,
+        local_balance_msat: 70_000_000,
+        remote_balance_msat: 30_000_000,
+        htlcs: Vec::new(),
+        commitment_tx: Some(commitment.clone()),
+        revoked_secrets: BTreeMap::new(),
+    };
+    let commitment_txid = ChannelStore::open(store_dir.path())
+        .unwrap()
+        .broadcast_commitment(&state, |tx| {
+            node.rpc()
+                .send_raw_transaction(tx)
+                .map_err(|e| AnyaError::Bitcoin(e.to_string()))
+        })
+        .unwrap();
+
+    let child = bumper
+        .bump_commitment("regtest_anchor")
+        .await
+        .unwrap()
+        .expect("stuck commitment should be bumped");
+    node.rpc().send_raw_transaction(&child).unwrap();
+
+    // The package now pays the target rate
+    let entry = node.rpc().get_mempool_entry(&commitment_txid).unwrap();
+    assert_eq!(entry.descendant_count, 2);
+    let package_weight = commitment.weight() + child.weight();
+    assert!(entry.fees.descendant.to_sat() * 4 >= 25 * package_weight.to_wu());
+    assert!(entry.fees.base.to_sat() * 4 < 25 * commitment.weight().to_wu());
+
+    node.mine_blocks(1).unwrap();
+    for txid in [commitment_txid, child.compute_txid()] {
+        let info = node.rpc().get_raw_transaction_info(&txid, None).unwrap();
+        assert_eq!(info.confirmations, Some(1));
+    }
+    assert!(bumper
+        .bump_commitment("regtest_anchor")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn regtest_timestamp_verifies_after_mining() {
+    let Some(node) = start_node() else {
+        return;
+    };
+    node.mine_blocks(101).unwrap();
+
+    let (user, password) = node.rpc_auth();
+    let chain = Arc::new(
+        BitcoindSource::new(
+            node.rpc_url(),
+            Some((user.to_string(), password.to_string())),
+        )
+        .unwrap(),
+    );
+    // Regtest has no fee estimates
+    let anchor = TimestampAnchor::new(chain.clone(), SecretKey::from_slice(&[31; 32]).unwrap())
+        .with_fee_rate(bitcoin::FeeRate::from_sat_per_vb(2).unwrap());
+    let funding = Address::from_script(&anchor.funding_script(), Network::Regtest).unwrap();
+    node.fund_address(&funding, Amount::from_sat(100_000))
+        .unwrap();
+
+    let data_hash = sha256::Hash::hash(b"anya regtest timestamp").to_byte_array();
+    let mut proof = anchor.anchor_hash(data_hash).await.unwrap();
+    assert_eq!(
+        verify_timestamp(chain.as_ref(), &proof, data_hash)
+            .await
+            .unwrap(),
+        None
+    );
+
+    let block_hash = node.mine_blocks(1).unwrap()[0];
+    let block = node.rpc().get_block(&block_hash).unwrap();
+    let height = node.height().unwrap() as u32;
+    assert!(proof.upgrade(&block, height));
+    assert_eq!(
+        verify_timestamp(chain.as_ref(), &proof, data_hash)
+            .await
+            .unwrap(),
+        Some(height)
+    );
+}
@@ -2,7 +2,8 @@
 
 #[cfg(any(feature = "bitcoin", feature = "complete"))]
 mod rgb_tests {
-    use anya_core::layer2::rgb::ContractManager;
+    use anya_core::layer2::rgb::{ContractManager, RgbError};
+    use secp256k1::{Secp256k1, SecretKey};
     use tempfile::tempdir;
 
     // Test constants
@@ -46,7 +47,7 @@ mod rgb_tests {
 
         // Create a new RGB asset for transfer testing
         println!("Creating RGB asset for transfer test...");
-        let _asset = contract_manager.create_asset(
+        let asset = contract_manager.create_asset(
             "tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh", // Example testnet address
             1000000,                                      // total supply
             8,                                            // precision
@@ -57,12 +58,18 @@ mod rgb_tests {
         // Get a new address for the transfer recipient
         let sender_address = SENDER_ADDRESS.to_string();
         let recipient_address = RECIPIENT_ADDRESS.to_string();
+        let secp = Secp256k1::new();
+        let sender_key = SecretKey::from_slice(&[1; 32])?;
+        let recipient_key = SecretKey::from_slice(&[2; 32])?;
 
         // Create a transfer using the contract manager
         println!("Creating RGB asset transfer...");
         let transfer = contract_manager.transfer_asset(
+            &asset.id,
+            &sender_key,
             &sender_address,
             &recipient_address,
+            &recipient_key.public_key(&secp),
             50_000, // Transfer 50,000 units
         )?;
 
@@ -75,6 +82,21 @@ mod rgb_tests {
         assert_eq!(transfer.from, sender_address);
         assert_eq!(transfer.to, recipient_address);
         assert_eq!(transfer.amount, 50_000);
+        transfer.verify_signature(&sender_key.public_key(&secp))?;
+
+        // A transfer signed with any other key does not verify as the sender's
+        let forged = contract_manager.transfer_asset(
+            &asset.id,
+            &recipient_key,
+            &sender_address,
+            &recipient_address,
+            &recipient_key.public_key(&secp),
+            50_000,
+        )?;
+        assert!(matches!(
+            forged.verify_signature(&sender_key.public_key(&secp)),
+            Err(RgbError::InvalidSignature(_))
+        ));
 
         Ok(())
     }